@@ -0,0 +1,38 @@
+-- Drops everything the baseline created, dependents first.
+
+DROP TABLE IF EXISTS agent_usage_patterns;
+DROP TABLE IF EXISTS agent_analytics_cache;
+DROP TABLE IF EXISTS agent_health_status;
+DROP TABLE IF EXISTS agent_recommendations;
+DROP TABLE IF EXISTS agent_activity_detailed;
+DROP TABLE IF EXISTS agent_performance_metrics;
+DROP TABLE IF EXISTS agent_template_relationships;
+DROP TABLE IF EXISTS agent_templates;
+DROP TABLE IF EXISTS agent_clone_relationships;
+DROP TABLE IF EXISTS agent_comprehensive_configs;
+DROP TABLE IF EXISTS agent_parameter_history;
+DROP TABLE IF EXISTS openclaw_config_snapshots;
+DROP TABLE IF EXISTS backup_records;
+DROP TABLE IF EXISTS performance_metrics;
+DROP TABLE IF EXISTS system_configuration;
+DROP TABLE IF EXISTS task_activity;
+DROP TABLE IF EXISTS activity_log;
+DROP TABLE IF EXISTS audit_legal_holds;
+DROP TABLE IF EXISTS audit_retention_policies;
+DROP TABLE IF EXISTS security_events;
+DROP TABLE IF EXISTS audit_log;
+DROP TABLE IF EXISTS two_factor_challenges;
+DROP TABLE IF EXISTS password_reset_tokens;
+DROP TABLE IF EXISTS password_history;
+DROP TABLE IF EXISTS two_factor_recovery_codes;
+DROP TABLE IF EXISTS user_roles;
+DROP TABLE IF EXISTS roles;
+DROP TABLE IF EXISTS permissions;
+DROP TABLE IF EXISTS sessions;
+DROP TABLE IF EXISTS users;
+DROP TABLE IF EXISTS recurring_tasks;
+DROP TABLE IF EXISTS deliverables;
+DROP TABLE IF EXISTS announcements;
+DROP TABLE IF EXISTS comments;
+DROP TABLE IF EXISTS tasks;
+DROP TABLE IF EXISTS agents;
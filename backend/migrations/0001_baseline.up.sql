@@ -0,0 +1,717 @@
+-- Baseline schema, consolidated from the table setup that used to run on every boot.
+-- Later schema changes go in new numbered migrations; never edit an applied one.
+
+-- Core tables
+CREATE TABLE IF NOT EXISTS agents (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL CHECK(length(name) >= 1 AND length(name) <= 255),
+    role TEXT NOT NULL CHECK(role IN ('LEAD', 'INT', 'SPC', 'ADMIN', 'AUDITOR', 'OBSERVER')),
+    description TEXT CHECK(description IS NULL OR length(description) <= 1000),
+    avatar TEXT,
+    status TEXT NOT NULL DEFAULT 'IDLE' CHECK(status IN ('WORKING', 'IDLE', 'STANDBY', 'OFFLINE', 'MAINTENANCE', 'SUSPENDED', 'ERROR')),
+    workspace TEXT,
+    agent_dir TEXT,
+    token TEXT CHECK(token IS NULL OR length(token) >= 32),
+    primary_model TEXT,
+    fallback_model TEXT,
+    current_model TEXT,
+    model_failure_count INTEGER DEFAULT 0 CHECK(model_failure_count >= 0 AND model_failure_count <= 100),
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    created_by TEXT,
+    last_active_at DATETIME,
+    version INTEGER DEFAULT 1 CHECK(version >= 1),
+    is_active BOOLEAN DEFAULT 1,
+    security_level TEXT DEFAULT 'INTERNAL' CHECK(security_level IN ('PUBLIC', 'INTERNAL', 'CONFIDENTIAL', 'RESTRICTED', 'SECRET')),
+    access_level TEXT DEFAULT 'READ_WRITE' CHECK(access_level IN ('READ_ONLY', 'READ_WRITE', 'ADMIN', 'SUPER_ADMIN')),
+    -- OpenClaw Advanced Configuration
+    image_model TEXT,
+    sandbox_mode TEXT CHECK(sandbox_mode IN ('OFF', 'ON', 'DOCKER')),
+    thinking_default TEXT CHECK(thinking_default IN ('OFF', 'MINIMAL', 'LOW', 'MEDIUM', 'HIGH', 'XHIGH')),
+    verbose_default TEXT CHECK(verbose_default IN ('OFF', 'ON', 'FULL')),
+    max_concurrent INTEGER CHECK(max_concurrent >= 1 AND max_concurrent <= 10),
+    timeout_seconds INTEGER CHECK(timeout_seconds >= 30 AND timeout_seconds <= 3600),
+    context_tokens INTEGER CHECK(context_tokens >= 1000 AND context_tokens <= 128000),
+    skills TEXT, -- JSON array
+    tools_config TEXT, -- JSON object
+    memory_search_config TEXT, -- JSON object
+    heartbeat_enabled BOOLEAN DEFAULT 0,
+    subagents_enabled BOOLEAN DEFAULT 0,
+    human_delay_enabled BOOLEAN DEFAULT 0,
+    block_streaming_enabled BOOLEAN DEFAULT 0,
+    context_pruning_enabled BOOLEAN DEFAULT 0,
+    openclaw_config_hash TEXT, -- For sync tracking
+    -- Enhanced fields
+    tags TEXT, -- JSON array
+    metadata TEXT, -- JSON object
+    performance_metrics TEXT, -- JSON object
+    health_score REAL CHECK(health_score >= 0.0 AND health_score <= 100.0),
+    last_health_check DATETIME,
+    -- Audit fields
+    created_ip TEXT,
+    modified_by TEXT,
+    modified_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    deleted_at DATETIME,
+    deleted_by TEXT,
+    is_deleted BOOLEAN DEFAULT 0,
+    email_verified BOOLEAN DEFAULT 0,
+    phone_verified BOOLEAN DEFAULT 0,
+    profile_picture TEXT,
+    timezone TEXT,
+    language TEXT,
+    last_login_ip TEXT
+);
+
+CREATE TABLE IF NOT EXISTS tasks (
+    id TEXT PRIMARY KEY,
+    title TEXT NOT NULL CHECK(length(title) >= 1 AND length(title) <= 500),
+    description TEXT CHECK(description IS NULL OR length(description) <= 5000),
+    status TEXT NOT NULL DEFAULT 'INBOX' CHECK(status IN ('INBOX', 'ASSIGNED', 'IN_PROGRESS', 'REVIEW', 'DONE', 'BLOCKED', 'CANCELLED', 'ARCHIVED')),
+    priority TEXT NOT NULL DEFAULT 'NORMAL' CHECK(priority IN ('LOW', 'NORMAL', 'HIGH', 'URGENT', 'CRITICAL')),
+    tags TEXT, -- JSON array
+    assignee_id TEXT,
+    reviewer TEXT,
+    reviewer_id TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    due_at DATETIME,
+    completed_at DATETIME,
+    created_by TEXT NOT NULL,
+    estimated_hours REAL CHECK(estimated_hours > 0),
+    actual_hours REAL CHECK(actual_hours >= 0),
+    complexity_score INTEGER CHECK(complexity_score >= 1 AND complexity_score <= 10),
+    risk_level TEXT CHECK(risk_level IN ('LOW', 'MEDIUM', 'HIGH', 'CRITICAL')),
+    classification TEXT NOT NULL DEFAULT 'INTERNAL' CHECK(classification IN ('PUBLIC', 'INTERNAL', 'CONFIDENTIAL', 'RESTRICTED', 'SECRET')),
+    dependencies TEXT, -- JSON array
+    deliverables TEXT, -- JSON array
+    metadata TEXT, -- JSON object
+    version INTEGER DEFAULT 1 CHECK(version >= 1),
+    is_template BOOLEAN DEFAULT 0,
+    template_usage_count INTEGER DEFAULT 0 CHECK(template_usage_count >= 0),
+    -- Audit fields
+    created_ip TEXT,
+    modified_by TEXT,
+    deleted_at DATETIME,
+    deleted_by TEXT,
+    is_deleted BOOLEAN DEFAULT 0,
+    modified_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    -- Constraints
+    FOREIGN KEY(assignee_id) REFERENCES agents(id) ON DELETE SET NULL,
+    FOREIGN KEY(reviewer_id) REFERENCES agents(id) ON DELETE SET NULL
+);
+
+CREATE TABLE IF NOT EXISTS comments (
+    id TEXT PRIMARY KEY,
+    task_id TEXT NOT NULL,
+    agent_id TEXT NOT NULL,
+    content TEXT NOT NULL CHECK(length(content) >= 1 AND length(content) <= 10000),
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    is_edited BOOLEAN DEFAULT 0,
+    parent_id TEXT, -- For threaded comments
+    mentions TEXT, -- JSON array
+    attachments TEXT, -- JSON array
+    reaction_count INTEGER DEFAULT 0 CHECK(reaction_count >= 0),
+    classification TEXT CHECK(classification IS NULL OR classification IN ('PUBLIC', 'INTERNAL', 'CONFIDENTIAL', 'RESTRICTED', 'SECRET')), -- NULL inherits the task's
+    is_deleted BOOLEAN DEFAULT 0,
+    deleted_at DATETIME,
+    deleted_by TEXT,
+    -- Constraints
+    FOREIGN KEY(task_id) REFERENCES tasks(id) ON DELETE CASCADE,
+    FOREIGN KEY(agent_id) REFERENCES agents(id) ON DELETE CASCADE,
+    FOREIGN KEY(parent_id) REFERENCES comments(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS announcements (
+    id TEXT PRIMARY KEY,
+    title TEXT,
+    message TEXT NOT NULL CHECK(length(message) >= 1),
+    priority TEXT NOT NULL DEFAULT 'NORMAL' CHECK(priority IN ('LOW', 'NORMAL', 'HIGH', 'URGENT', 'CRITICAL')),
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    created_by TEXT NOT NULL,
+    expires_at DATETIME,
+    target_audience TEXT, -- JSON array of roles/ids
+    is_active BOOLEAN DEFAULT 1,
+    view_count INTEGER DEFAULT 0 CHECK(view_count >= 0),
+    -- Constraints
+    FOREIGN KEY(created_by) REFERENCES agents(id)
+);
+
+CREATE TABLE IF NOT EXISTS deliverables (
+    id TEXT PRIMARY KEY,
+    task_id TEXT NOT NULL,
+    title TEXT NOT NULL CHECK(length(title) >= 1 AND length(title) <= 500),
+    description TEXT,
+    status TEXT NOT NULL DEFAULT 'PENDING' CHECK(status IN ('PENDING', 'IN_PROGRESS', 'COMPLETED', 'FAILED', 'CANCELLED')),
+    file_path TEXT,
+    file_size INTEGER CHECK(file_size >= 0),
+    file_hash TEXT,
+    mime_type TEXT,
+    version INTEGER DEFAULT 1 CHECK(version >= 1),
+    classification TEXT CHECK(classification IS NULL OR classification IN ('PUBLIC', 'INTERNAL', 'CONFIDENTIAL', 'RESTRICTED', 'SECRET')), -- NULL inherits the task's
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    completed_at DATETIME,
+    completed_by TEXT,
+    -- Constraints
+    FOREIGN KEY(task_id) REFERENCES tasks(id) ON DELETE CASCADE,
+    FOREIGN KEY(completed_by) REFERENCES agents(id) ON DELETE SET NULL
+);
+
+CREATE TABLE IF NOT EXISTS recurring_tasks (
+    id TEXT PRIMARY KEY,
+    title TEXT NOT NULL CHECK(length(title) >= 1 AND length(title) <= 500),
+    description TEXT CHECK(description IS NULL OR length(description) <= 5000),
+    assignee_id TEXT,
+    schedule_type TEXT NOT NULL CHECK(schedule_type IN ('DAILY', 'WEEKLY', 'MONTHLY', 'YEARLY', 'CUSTOM')),
+    schedule_value TEXT,
+    schedule_time TEXT NOT NULL,
+    schedule_timezone TEXT DEFAULT 'UTC',
+    last_run DATETIME,
+    next_run DATETIME NOT NULL,
+    is_active BOOLEAN DEFAULT 1,
+    max_runs INTEGER,
+    run_count INTEGER DEFAULT 0 CHECK(run_count >= 0),
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    created_by TEXT NOT NULL,
+    -- Constraints
+    FOREIGN KEY(assignee_id) REFERENCES agents(id) ON DELETE SET NULL,
+    FOREIGN KEY(created_by) REFERENCES agents(id)
+);
+
+-- Security tables
+CREATE TABLE IF NOT EXISTS users (
+    id TEXT PRIMARY KEY,
+    username TEXT NOT NULL UNIQUE CHECK(length(username) >= 3 AND length(username) <= 50),
+    email TEXT NOT NULL UNIQUE CHECK(email LIKE '%@%'),
+    password_hash TEXT NOT NULL CHECK(length(password_hash) >= 60),
+    role TEXT NOT NULL DEFAULT 'USER' CHECK(role IN ('SUPER_ADMIN', 'ADMIN', 'USER', 'READ_ONLY')),
+    is_active BOOLEAN DEFAULT 1,
+    last_login DATETIME,
+    failed_login_attempts INTEGER DEFAULT 0 CHECK(failed_login_attempts >= 0 AND failed_login_attempts <= 10),
+    locked_until DATETIME,
+    security_level TEXT DEFAULT 'INTERNAL' CHECK(security_level IN ('PUBLIC', 'INTERNAL', 'CONFIDENTIAL', 'RESTRICTED', 'SECRET')),
+    access_level TEXT DEFAULT 'READ_ONLY' CHECK(access_level IN ('READ_ONLY', 'READ_WRITE', 'ADMIN', 'SUPER_ADMIN')),
+    permissions TEXT, -- JSON array
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    last_password_change DATETIME DEFAULT CURRENT_TIMESTAMP,
+    two_factor_enabled BOOLEAN DEFAULT 0,
+    two_factor_secret TEXT, -- base32 TOTP secret, pending until two_factor_enabled
+    two_factor_last_step INTEGER, -- last accepted TOTP time step (replay protection)
+    email_verified BOOLEAN DEFAULT 0,
+    phone_verified BOOLEAN DEFAULT 0,
+    profile_picture TEXT,
+    timezone TEXT DEFAULT 'UTC',
+    language TEXT DEFAULT 'en',
+    created_by TEXT,
+    created_ip TEXT,
+    modified_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    modified_by TEXT
+);
+
+CREATE TABLE IF NOT EXISTS sessions (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    token TEXT NOT NULL UNIQUE CHECK(length(token) >= 128),
+    expires_at DATETIME NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    last_accessed DATETIME DEFAULT CURRENT_TIMESTAMP,
+    ip_address TEXT NOT NULL,
+    user_agent TEXT,
+    is_active BOOLEAN DEFAULT 1,
+    device_fingerprint TEXT,
+    login_method TEXT DEFAULT 'password',
+    two_factor_verified BOOLEAN DEFAULT 0,
+    requires_step_up BOOLEAN DEFAULT 0, -- set by the session anomaly policy
+    revoked_at DATETIME,
+    revoked_by TEXT,
+    -- Constraints
+    FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS permissions (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    description TEXT,
+    resource TEXT NOT NULL,
+    action TEXT NOT NULL,
+    conditions TEXT, -- JSON object
+    is_system BOOLEAN DEFAULT 0,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS roles (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    description TEXT,
+    permissions TEXT, -- JSON array of patterns such as 'tasks:*'
+    parent_role_id TEXT, -- inherits every permission of the parent role
+    is_system BOOLEAN DEFAULT 0,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    -- Constraints
+    FOREIGN KEY(parent_role_id) REFERENCES roles(id) ON DELETE SET NULL
+);
+
+CREATE TABLE IF NOT EXISTS user_roles (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    role_id TEXT NOT NULL,
+    assigned_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    assigned_by TEXT,
+    expires_at DATETIME,
+    -- Constraints
+    FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY(role_id) REFERENCES roles(id) ON DELETE CASCADE,
+    UNIQUE(user_id, role_id)
+);
+
+CREATE TABLE IF NOT EXISTS two_factor_recovery_codes (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    code_hash TEXT NOT NULL,
+    used_at DATETIME,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    -- Constraints
+    FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS password_history (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    password_hash TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    -- Constraints
+    FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS password_reset_tokens (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    expires_at DATETIME NOT NULL,
+    used_at DATETIME,
+    requested_ip TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    -- Constraints
+    FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS two_factor_challenges (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    ip_address TEXT,
+    user_agent TEXT,
+    attempts INTEGER DEFAULT 0,
+    expires_at DATETIME NOT NULL,
+    consumed_at DATETIME,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    -- Constraints
+    FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+-- Audit tables
+CREATE TABLE IF NOT EXISTS audit_log (
+    id TEXT PRIMARY KEY,
+    entity_type TEXT NOT NULL CHECK(entity_type IN ('agent', 'task', 'user', 'session', 'permission', 'role', 'system')),
+    entity_id TEXT NOT NULL,
+    action TEXT NOT NULL CHECK(action IN ('create', 'update', 'delete', 'access', 'login', 'logout', 'view', 'export', 'import')),
+    old_values TEXT, -- JSON object
+    new_values TEXT, -- JSON object
+    user_id TEXT,
+    user_role TEXT,
+    ip_address TEXT,
+    user_agent TEXT,
+    session_id TEXT,
+    timestamp DATETIME DEFAULT CURRENT_TIMESTAMP,
+    success BOOLEAN NOT NULL,
+    error_message TEXT,
+    risk_score INTEGER CHECK(risk_score >= 0 AND risk_score <= 100),
+    compliance_flags TEXT, -- JSON array
+    metadata TEXT -- JSON object
+);
+
+CREATE TABLE IF NOT EXISTS security_events (
+    id TEXT PRIMARY KEY,
+    event_type TEXT NOT NULL CHECK(event_type IN ('login_failure', 'login_success', 'session_created', 'session_revoked', 'password_changed', 'password_change_failed', 'password_reset_requested', 'password_reset', 'unauthorized_access', 'privilege_escalation', 'data_breach', 'suspicious_activity', 'malware_detected', 'two_factor_enrolled', 'two_factor_disabled', 'two_factor_challenge', 'two_factor_failure')),
+    severity TEXT NOT NULL CHECK(severity IN ('low', 'medium', 'high', 'critical')),
+    description TEXT NOT NULL,
+    source_ip TEXT,
+    target_resource TEXT,
+    user_id TEXT,
+    details TEXT, -- JSON object
+    resolved BOOLEAN DEFAULT 0,
+    resolved_at DATETIME,
+    resolved_by TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    -- Constraints
+    FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE SET NULL,
+    FOREIGN KEY(resolved_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE IF NOT EXISTS audit_retention_policies (
+    entity_type TEXT PRIMARY KEY,
+    retention_days INTEGER NOT NULL CHECK(retention_days > 0),
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_by TEXT
+);
+
+CREATE TABLE IF NOT EXISTS audit_legal_holds (
+    id TEXT PRIMARY KEY,
+    entity_type TEXT NOT NULL,
+    entity_id TEXT, -- NULL holds every record of the type
+    reason TEXT NOT NULL,
+    created_by TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    released_at DATETIME,
+    released_by TEXT
+);
+
+CREATE TABLE IF NOT EXISTS activity_log (
+    id TEXT PRIMARY KEY,
+    activity_type TEXT NOT NULL,
+    agent_id TEXT,
+    task_id TEXT,
+    description TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    -- Constraints
+    FOREIGN KEY(agent_id) REFERENCES agents(id) ON DELETE SET NULL,
+    FOREIGN KEY(task_id) REFERENCES tasks(id) ON DELETE SET NULL
+);
+
+CREATE TABLE IF NOT EXISTS task_activity (
+    id TEXT PRIMARY KEY,
+    task_id TEXT NOT NULL,
+    agent_id TEXT,
+    message TEXT NOT NULL,
+    timestamp DATETIME DEFAULT CURRENT_TIMESTAMP,
+    -- Constraints
+    FOREIGN KEY(task_id) REFERENCES tasks(id) ON DELETE CASCADE,
+    FOREIGN KEY(agent_id) REFERENCES agents(id) ON DELETE CASCADE
+);
+
+-- Monitoring tables
+CREATE TABLE IF NOT EXISTS system_configuration (
+    id TEXT PRIMARY KEY,
+    key TEXT NOT NULL UNIQUE,
+    value TEXT NOT NULL,
+    data_type TEXT NOT NULL CHECK(data_type IN ('string', 'number', 'boolean', 'json')),
+    description TEXT,
+    category TEXT NOT NULL,
+    is_sensitive BOOLEAN DEFAULT 0,
+    requires_restart BOOLEAN DEFAULT 0,
+    validation_rules TEXT, -- JSON object
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_by TEXT NOT NULL,
+    version INTEGER DEFAULT 1 CHECK(version >= 1),
+    -- Constraints
+    FOREIGN KEY(updated_by) REFERENCES users(id)
+);
+
+CREATE TABLE IF NOT EXISTS performance_metrics (
+    id TEXT PRIMARY KEY,
+    metric_name TEXT NOT NULL,
+    metric_type TEXT NOT NULL CHECK(metric_type IN ('counter', 'gauge', 'histogram', 'timer')),
+    value REAL NOT NULL,
+    avg_response_time REAL,
+    request_count INTEGER,
+    labels TEXT, -- JSON object
+    timestamp DATETIME DEFAULT CURRENT_TIMESTAMP,
+    source TEXT NOT NULL CHECK(source IN ('agent', 'system', 'api', 'database', 'network')),
+    entity_id TEXT,
+    entity_type TEXT,
+    unit TEXT,
+    threshold_warning REAL,
+    threshold_critical REAL,
+    -- Indexes for time-series queries
+    CHECK(timestamp IS NOT NULL)
+);
+
+CREATE TABLE IF NOT EXISTS backup_records (
+    id TEXT PRIMARY KEY,
+    backup_type TEXT NOT NULL CHECK(backup_type IN ('full', 'incremental', 'differential')),
+    location TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL CHECK(size_bytes > 0),
+    status TEXT NOT NULL DEFAULT 'in_progress' CHECK(status IN ('in_progress', 'completed', 'failed', 'cancelled')),
+    started_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    completed_at DATETIME,
+    error_message TEXT,
+    checksum TEXT,
+    retention_days INTEGER DEFAULT 30 CHECK(retention_days > 0),
+    created_by TEXT NOT NULL,
+    -- Constraints
+    FOREIGN KEY(created_by) REFERENCES users(id)
+);
+
+CREATE TABLE IF NOT EXISTS openclaw_config_snapshots (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    config_hash TEXT NOT NULL,
+    raw_config TEXT NOT NULL, -- Full OpenClaw config JSON
+    applied_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    is_active BOOLEAN DEFAULT 1,
+    backup_type TEXT DEFAULT 'manual',
+    created_by TEXT,
+    -- Constraints
+    FOREIGN KEY(agent_id) REFERENCES agents(id) ON DELETE CASCADE,
+    FOREIGN KEY(created_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE IF NOT EXISTS agent_parameter_history (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    parameter_name TEXT NOT NULL,
+    old_value TEXT,
+    new_value TEXT,
+    changed_by TEXT, -- 'sync' or 'user'
+    changed_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    change_reason TEXT,
+    -- Constraints
+    FOREIGN KEY(agent_id) REFERENCES agents(id) ON DELETE CASCADE
+);
+
+-- Agent management tables
+CREATE TABLE IF NOT EXISTS agent_comprehensive_configs (
+    agent_id TEXT PRIMARY KEY,
+    config_json TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(agent_id) REFERENCES agents(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS agent_clone_relationships (
+    id TEXT PRIMARY KEY,
+    source_agent_id TEXT NOT NULL,
+    cloned_agent_id TEXT NOT NULL,
+    cloned_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(source_agent_id) REFERENCES agents(id),
+    FOREIGN KEY(cloned_agent_id) REFERENCES agents(id)
+);
+
+CREATE TABLE IF NOT EXISTS agent_templates (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT,
+    category TEXT,
+    role TEXT NOT NULL,
+    configuration TEXT NOT NULL,
+    usage_count INTEGER DEFAULT 0,
+    rating REAL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    tags TEXT -- JSON array
+);
+
+CREATE TABLE IF NOT EXISTS agent_template_relationships (
+    id TEXT PRIMARY KEY,
+    template_id TEXT NOT NULL,
+    agent_id TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(template_id) REFERENCES agent_templates(id),
+    FOREIGN KEY(agent_id) REFERENCES agents(id)
+);
+
+CREATE TABLE IF NOT EXISTS agent_performance_metrics (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    metric_date DATE NOT NULL,
+    total_tasks_completed INTEGER DEFAULT 0,
+    average_task_duration_seconds REAL,
+    success_rate REAL,
+    error_rate REAL,
+    total_cost REAL,
+    total_tokens_used INTEGER,
+    total_api_calls INTEGER,
+    memory_usage_mb REAL,
+    cpu_usage_percent REAL,
+    user_satisfaction_score REAL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(agent_id) REFERENCES agents(id)
+);
+
+CREATE TABLE IF NOT EXISTS agent_activity_detailed (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    activity_type TEXT NOT NULL,
+    description TEXT,
+    task_id TEXT,
+    duration_seconds REAL,
+    success BOOLEAN,
+    error_message TEXT,
+    tokens_used INTEGER,
+    cost REAL,
+    metadata TEXT, -- JSON object
+    timestamp DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(agent_id) REFERENCES agents(id)
+);
+
+CREATE TABLE IF NOT EXISTS agent_recommendations (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    category TEXT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT,
+    priority TEXT,
+    estimated_impact TEXT,
+    implementation_difficulty TEXT,
+    auto_applicable BOOLEAN DEFAULT FALSE,
+    status TEXT DEFAULT 'pending', -- pending, applied, dismissed
+    steps TEXT, -- JSON array
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    applied_at DATETIME,
+    FOREIGN KEY(agent_id) REFERENCES agents(id)
+);
+
+CREATE TABLE IF NOT EXISTS agent_health_status (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    overall_health REAL,
+    performance_health REAL,
+    configuration_health REAL,
+    security_health REAL,
+    resource_health REAL,
+    health_trend TEXT,
+    last_check DATETIME DEFAULT CURRENT_TIMESTAMP,
+    issues TEXT, -- JSON array
+    FOREIGN KEY(agent_id) REFERENCES agents(id)
+);
+
+CREATE TABLE IF NOT EXISTS agent_analytics_cache (
+    agent_id TEXT,
+    period TEXT,
+    metrics_type TEXT,
+    analytics_data TEXT NOT NULL,
+    cached_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    expires_at DATETIME,
+    PRIMARY KEY (agent_id, period, metrics_type),
+    FOREIGN KEY(agent_id) REFERENCES agents(id)
+);
+
+CREATE TABLE IF NOT EXISTS agent_usage_patterns (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    pattern_type TEXT NOT NULL, -- daily, weekly, monthly
+    pattern_data TEXT NOT NULL, -- JSON array of usage data
+    calculated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    valid_until DATETIME,
+    FOREIGN KEY(agent_id) REFERENCES agents(id)
+);
+
+-- Indexes
+CREATE INDEX IF NOT EXISTS idx_agents_status ON agents(status, is_active);
+
+CREATE INDEX IF NOT EXISTS idx_agents_role ON agents(role, security_level);
+
+CREATE INDEX IF NOT EXISTS idx_agents_updated ON agents(updated_at);
+
+CREATE INDEX IF NOT EXISTS idx_tasks_status ON tasks(status, priority);
+
+CREATE INDEX IF NOT EXISTS idx_tasks_assignee ON tasks(assignee_id, due_at);
+
+CREATE INDEX IF NOT EXISTS idx_tasks_created ON tasks(created_at);
+
+CREATE INDEX IF NOT EXISTS idx_tasks_classification ON tasks(classification);
+
+CREATE INDEX IF NOT EXISTS idx_comments_task ON comments(task_id, created_at);
+
+CREATE INDEX IF NOT EXISTS idx_comments_agent ON comments(agent_id, created_at);
+
+CREATE INDEX IF NOT EXISTS idx_sessions_user ON sessions(user_id, is_active);
+
+CREATE INDEX IF NOT EXISTS idx_sessions_token ON sessions(token);
+
+CREATE INDEX IF NOT EXISTS idx_sessions_expires ON sessions(expires_at);
+
+CREATE INDEX IF NOT EXISTS idx_user_roles_user ON user_roles(user_id, expires_at);
+
+CREATE INDEX IF NOT EXISTS idx_audit_timestamp ON audit_log(timestamp);
+
+CREATE INDEX IF NOT EXISTS idx_audit_entity ON audit_log(entity_type, entity_id);
+
+CREATE INDEX IF NOT EXISTS idx_audit_user ON audit_log(user_id, timestamp);
+
+CREATE INDEX IF NOT EXISTS idx_security_events_created ON security_events(created_at);
+
+CREATE INDEX IF NOT EXISTS idx_security_events_severity ON security_events(severity);
+
+CREATE INDEX IF NOT EXISTS idx_audit_risk_score ON audit_log(risk_score);
+
+CREATE INDEX IF NOT EXISTS idx_recovery_codes_user ON two_factor_recovery_codes(user_id, used_at);
+
+CREATE INDEX IF NOT EXISTS idx_two_factor_challenges_expires ON two_factor_challenges(expires_at);
+
+CREATE INDEX IF NOT EXISTS idx_password_history_user ON password_history(user_id, created_at);
+
+CREATE INDEX IF NOT EXISTS idx_password_reset_tokens_user ON password_reset_tokens(user_id, used_at);
+
+CREATE INDEX IF NOT EXISTS idx_audit_legal_holds_active ON audit_legal_holds(entity_type, entity_id, released_at);
+
+CREATE INDEX IF NOT EXISTS idx_metrics_timestamp ON performance_metrics(timestamp);
+
+CREATE INDEX IF NOT EXISTS idx_metrics_name_source ON performance_metrics(metric_name, source);
+
+CREATE INDEX IF NOT EXISTS idx_backups_created ON backup_records(started_at);
+
+CREATE INDEX IF NOT EXISTS idx_config_snapshots_agent ON openclaw_config_snapshots(agent_id, is_active);
+
+CREATE INDEX IF NOT EXISTS idx_param_history_agent ON agent_parameter_history(agent_id, changed_at);
+
+CREATE INDEX IF NOT EXISTS idx_agent_performance_agent_date ON agent_performance_metrics(agent_id, metric_date);
+
+CREATE INDEX IF NOT EXISTS idx_agent_activity_agent_timestamp ON agent_activity_detailed(agent_id, timestamp);
+
+CREATE INDEX IF NOT EXISTS idx_agent_recommendations_agent_status ON agent_recommendations(agent_id, status);
+
+CREATE INDEX IF NOT EXISTS idx_agent_templates_category_role ON agent_templates(category, role);
+
+CREATE INDEX IF NOT EXISTS idx_analytics_cache_expires ON agent_analytics_cache(expires_at);
+
+-- Triggers
+CREATE TRIGGER IF NOT EXISTS agents_audit_insert
+AFTER INSERT ON agents
+BEGIN
+    INSERT INTO audit_log (
+        id, entity_type, entity_id, action, new_values, user_id, timestamp, success
+    ) VALUES (
+        lower(hex(randomblob(16))), 'agent', NEW.id, 'create',
+        json_object('name', NEW.name, 'role', NEW.role, 'status', NEW.status),
+        COALESCE(NEW.created_by, 'system'), CURRENT_TIMESTAMP, 1
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS agents_audit_update
+AFTER UPDATE ON agents
+BEGIN
+    INSERT INTO audit_log (
+        id, entity_type, entity_id, action, old_values, new_values, user_id, timestamp, success
+    ) VALUES (
+        lower(hex(randomblob(16))), 'agent', NEW.id, 'update',
+        json_object('name', OLD.name, 'role', OLD.role, 'status', OLD.status),
+        json_object('name', NEW.name, 'role', NEW.role, 'status', NEW.status),
+        COALESCE(NEW.modified_by, 'system'), CURRENT_TIMESTAMP, 1
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS tasks_audit_insert
+AFTER INSERT ON tasks
+BEGIN
+    INSERT INTO audit_log (
+        id, entity_type, entity_id, action, new_values, user_id, timestamp, success
+    ) VALUES (
+        lower(hex(randomblob(16))), 'task', NEW.id, 'create',
+        json_object('title', NEW.title, 'status', NEW.status, 'classification', NEW.classification),
+        NEW.created_by, CURRENT_TIMESTAMP, 1
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS agents_update_timestamp AFTER UPDATE ON agents BEGIN UPDATE agents SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id; END;
+
+CREATE TRIGGER IF NOT EXISTS tasks_update_timestamp AFTER UPDATE ON tasks BEGIN UPDATE tasks SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id; END;
+
+CREATE TRIGGER IF NOT EXISTS comments_update_timestamp AFTER UPDATE ON comments BEGIN UPDATE comments SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id; END;
+
+CREATE TRIGGER IF NOT EXISTS sessions_update_timestamp AFTER UPDATE ON sessions BEGIN UPDATE sessions SET last_accessed = CURRENT_TIMESTAMP WHERE id = NEW.id; END;
@@ -0,0 +1,30 @@
+CREATE TABLE backup_records_old (
+    id TEXT PRIMARY KEY,
+    backup_type TEXT NOT NULL CHECK(backup_type IN ('full', 'incremental', 'differential')),
+    location TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL CHECK(size_bytes > 0),
+    status TEXT NOT NULL DEFAULT 'in_progress' CHECK(status IN ('in_progress', 'completed', 'failed', 'cancelled')),
+    started_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    completed_at DATETIME,
+    error_message TEXT,
+    checksum TEXT,
+    retention_days INTEGER DEFAULT 30 CHECK(retention_days > 0),
+    created_by TEXT NOT NULL,
+    -- Constraints
+    FOREIGN KEY(created_by) REFERENCES users(id)
+);
+
+-- Rows without a size or a known creator cannot satisfy the old constraints
+INSERT INTO backup_records_old
+SELECT
+    id, backup_type, location, size_bytes,
+    CASE status WHEN 'expired' THEN 'cancelled' ELSE status END,
+    started_at, completed_at, error_message, checksum, retention_days, created_by
+FROM backup_records
+WHERE size_bytes IS NOT NULL
+  AND created_by IN (SELECT id FROM users);
+
+DROP TABLE backup_records;
+ALTER TABLE backup_records_old RENAME TO backup_records;
+
+CREATE INDEX IF NOT EXISTS idx_backups_created ON backup_records(started_at);
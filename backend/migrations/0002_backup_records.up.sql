@@ -0,0 +1,34 @@
+-- Backups are written by the scheduler as well as by users, and the size is
+-- only known once the file exists. SQLite cannot alter constraints, so the
+-- table is rebuilt.
+CREATE TABLE backup_records_new (
+    id TEXT PRIMARY KEY,
+    backup_type TEXT NOT NULL CHECK(backup_type IN ('full', 'incremental', 'differential')),
+    location TEXT NOT NULL,
+    size_bytes INTEGER CHECK(size_bytes IS NULL OR size_bytes > 0),
+    status TEXT NOT NULL DEFAULT 'in_progress' CHECK(status IN ('in_progress', 'completed', 'failed', 'cancelled', 'expired')),
+    started_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    completed_at DATETIME,
+    error_message TEXT,
+    checksum TEXT, -- SHA-256 of the file at `location`
+    compressed BOOLEAN NOT NULL DEFAULT 0,
+    verified_at DATETIME,
+    expired_at DATETIME,
+    retention_days INTEGER DEFAULT 30 CHECK(retention_days > 0),
+    created_by TEXT NOT NULL -- user id, or 'system' for scheduled backups
+);
+
+INSERT INTO backup_records_new (
+    id, backup_type, location, size_bytes, status, started_at, completed_at,
+    error_message, checksum, retention_days, created_by
+)
+SELECT
+    id, backup_type, location, size_bytes, status, started_at, completed_at,
+    error_message, checksum, retention_days, created_by
+FROM backup_records;
+
+DROP TABLE backup_records;
+ALTER TABLE backup_records_new RENAME TO backup_records;
+
+CREATE INDEX IF NOT EXISTS idx_backups_created ON backup_records(started_at);
+CREATE INDEX IF NOT EXISTS idx_backups_status ON backup_records(status, started_at);
@@ -0,0 +1,27 @@
+DROP TABLE IF EXISTS system_configuration_history;
+
+CREATE TABLE system_configuration_old (
+    id TEXT PRIMARY KEY,
+    key TEXT NOT NULL UNIQUE,
+    value TEXT NOT NULL,
+    data_type TEXT NOT NULL CHECK(data_type IN ('string', 'number', 'boolean', 'json')),
+    description TEXT,
+    category TEXT NOT NULL,
+    is_sensitive BOOLEAN DEFAULT 0,
+    requires_restart BOOLEAN DEFAULT 0,
+    validation_rules TEXT, -- JSON object
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_by TEXT NOT NULL,
+    version INTEGER DEFAULT 1 CHECK(version >= 1),
+    -- Constraints
+    FOREIGN KEY(updated_by) REFERENCES users(id)
+);
+
+-- Entries last changed by the server have no user to point at
+INSERT INTO system_configuration_old
+SELECT * FROM system_configuration
+WHERE updated_by IN (SELECT id FROM users);
+
+DROP TABLE system_configuration;
+ALTER TABLE system_configuration_old RENAME TO system_configuration;
@@ -0,0 +1,39 @@
+-- Defaults are seeded by the server itself, so updated_by may be 'system'
+-- rather than a user id. SQLite cannot drop the foreign key in place.
+CREATE TABLE system_configuration_new (
+    id TEXT PRIMARY KEY,
+    key TEXT NOT NULL UNIQUE,
+    value TEXT NOT NULL,
+    data_type TEXT NOT NULL CHECK(data_type IN ('string', 'number', 'boolean', 'json')),
+    description TEXT,
+    category TEXT NOT NULL,
+    is_sensitive BOOLEAN DEFAULT 0,
+    requires_restart BOOLEAN DEFAULT 0,
+    validation_rules TEXT, -- JSON object
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_by TEXT NOT NULL, -- user id, or 'system'
+    version INTEGER DEFAULT 1 CHECK(version >= 1)
+);
+
+INSERT INTO system_configuration_new SELECT * FROM system_configuration;
+
+DROP TABLE system_configuration;
+ALTER TABLE system_configuration_new RENAME TO system_configuration;
+
+CREATE INDEX IF NOT EXISTS idx_system_configuration_category ON system_configuration(category);
+
+-- One row per change; `version` is the version the change produced
+CREATE TABLE IF NOT EXISTS system_configuration_history (
+    id TEXT PRIMARY KEY,
+    config_key TEXT NOT NULL,
+    version INTEGER NOT NULL CHECK(version >= 1),
+    old_value TEXT,
+    new_value TEXT NOT NULL,
+    changed_by TEXT NOT NULL,
+    reason TEXT,
+    changed_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(config_key, version)
+);
+
+CREATE INDEX IF NOT EXISTS idx_system_configuration_history_key ON system_configuration_history(config_key, changed_at);
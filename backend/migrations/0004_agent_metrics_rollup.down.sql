@@ -0,0 +1,8 @@
+DROP INDEX IF EXISTS idx_agent_performance_agent_date;
+CREATE INDEX idx_agent_performance_agent_date ON agent_performance_metrics(agent_id, metric_date);
+
+ALTER TABLE agent_performance_metrics DROP COLUMN updated_at;
+ALTER TABLE agent_performance_metrics DROP COLUMN failed_outcomes;
+ALTER TABLE agent_performance_metrics DROP COLUMN successful_outcomes;
+
+DROP TABLE IF EXISTS task_reviews;
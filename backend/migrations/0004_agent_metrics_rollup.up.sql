@@ -0,0 +1,28 @@
+-- Review decisions, so success rates can count approved and rejected work
+CREATE TABLE IF NOT EXISTS task_reviews (
+    id TEXT PRIMARY KEY,
+    task_id TEXT NOT NULL,
+    agent_id TEXT, -- assignee at the time of the review
+    reviewer_id TEXT,
+    outcome TEXT NOT NULL CHECK(outcome IN ('approved', 'rejected')),
+    feedback TEXT,
+    reviewed_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    -- Constraints
+    FOREIGN KEY(task_id) REFERENCES tasks(id) ON DELETE CASCADE,
+    FOREIGN KEY(agent_id) REFERENCES agents(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_reviews_agent ON task_reviews(agent_id, reviewed_at);
+
+-- Outcome counts let rates be recombined exactly over any period
+ALTER TABLE agent_performance_metrics ADD COLUMN successful_outcomes INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE agent_performance_metrics ADD COLUMN failed_outcomes INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE agent_performance_metrics ADD COLUMN updated_at DATETIME;
+
+-- The rollup keeps one row per agent and day
+DELETE FROM agent_performance_metrics
+WHERE rowid NOT IN (
+    SELECT MAX(rowid) FROM agent_performance_metrics GROUP BY agent_id, metric_date
+);
+DROP INDEX IF EXISTS idx_agent_performance_agent_date;
+CREATE UNIQUE INDEX idx_agent_performance_agent_date ON agent_performance_metrics(agent_id, metric_date);
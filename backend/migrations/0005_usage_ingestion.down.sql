@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_agent_activity_task;
+DROP TABLE IF EXISTS usage_ingestion_offsets;
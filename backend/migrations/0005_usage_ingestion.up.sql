@@ -0,0 +1,11 @@
+-- How far each OpenClaw session transcript has been read
+CREATE TABLE IF NOT EXISTS usage_ingestion_offsets (
+    file_path TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    session_id TEXT NOT NULL,
+    task_id TEXT,
+    byte_offset INTEGER NOT NULL DEFAULT 0 CHECK(byte_offset >= 0),
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_activity_task ON agent_activity_detailed(task_id, timestamp);
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS agent_budget_alerts;
+DROP INDEX IF EXISTS idx_budget_overrides_window;
+DROP TABLE IF EXISTS agent_budget_overrides;
@@ -0,0 +1,29 @@
+-- One-off increases to an agent's spend cap for a single budget window
+CREATE TABLE IF NOT EXISTS agent_budget_overrides (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    scope TEXT NOT NULL CHECK(scope IN ('daily', 'weekly', 'monthly', 'task')),
+    window_key TEXT NOT NULL, -- Day, week, month or task the override applies to
+    amount REAL NOT NULL CHECK(amount > 0),
+    reason TEXT NOT NULL CHECK(length(reason) >= 1),
+    granted_by TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(agent_id) REFERENCES agents(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_budget_overrides_window ON agent_budget_overrides(agent_id, scope, window_key);
+
+-- Thresholds crossed per budget window, so each is only announced once
+CREATE TABLE IF NOT EXISTS agent_budget_alerts (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    scope TEXT NOT NULL CHECK(scope IN ('daily', 'weekly', 'monthly', 'task')),
+    window_key TEXT NOT NULL,
+    level TEXT NOT NULL CHECK(level IN ('warning', 'exceeded')),
+    spent REAL NOT NULL,
+    limit_amount REAL NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    resolved_at DATETIME,
+    FOREIGN KEY(agent_id) REFERENCES agents(id) ON DELETE CASCADE,
+    UNIQUE(agent_id, scope, window_key, level)
+);
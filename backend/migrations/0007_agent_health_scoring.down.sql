@@ -0,0 +1,20 @@
+DROP INDEX IF EXISTS idx_agent_health_agent_check;
+
+CREATE TABLE agent_health_status_old (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    overall_health REAL,
+    performance_health REAL,
+    configuration_health REAL,
+    security_health REAL,
+    resource_health REAL,
+    health_trend TEXT,
+    last_check DATETIME DEFAULT CURRENT_TIMESTAMP,
+    issues TEXT, -- JSON array
+    FOREIGN KEY(agent_id) REFERENCES agents(id)
+);
+
+INSERT INTO agent_health_status_old SELECT * FROM agent_health_status;
+
+DROP TABLE agent_health_status;
+ALTER TABLE agent_health_status_old RENAME TO agent_health_status;
@@ -0,0 +1,22 @@
+-- Scores are kept as history; deleting an agent removes its scores with it.
+-- SQLite cannot change the foreign key in place.
+CREATE TABLE agent_health_status_new (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    overall_health REAL,
+    performance_health REAL,
+    configuration_health REAL,
+    security_health REAL,
+    resource_health REAL,
+    health_trend TEXT,
+    last_check DATETIME DEFAULT CURRENT_TIMESTAMP,
+    issues TEXT, -- JSON array
+    FOREIGN KEY(agent_id) REFERENCES agents(id) ON DELETE CASCADE
+);
+
+INSERT INTO agent_health_status_new SELECT * FROM agent_health_status;
+
+DROP TABLE agent_health_status;
+ALTER TABLE agent_health_status_new RENAME TO agent_health_status;
+
+CREATE INDEX IF NOT EXISTS idx_agent_health_agent_check ON agent_health_status(agent_id, last_check);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_agent_model_events_model;
+DROP INDEX IF EXISTS idx_agent_model_events_agent;
+DROP TABLE IF EXISTS agent_model_events;
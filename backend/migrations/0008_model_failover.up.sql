@@ -0,0 +1,17 @@
+-- Model errors, successes and switches reported by agents, the gateway or the failover itself
+CREATE TABLE IF NOT EXISTS agent_model_events (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    model TEXT NOT NULL,
+    event_type TEXT NOT NULL CHECK(event_type IN ('error', 'success', 'switch')),
+    error_code TEXT,
+    message TEXT,
+    from_model TEXT, -- switches only
+    to_model TEXT, -- switches only
+    source TEXT NOT NULL CHECK(source IN ('agent', 'gateway', 'system')),
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(agent_id) REFERENCES agents(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_model_events_agent ON agent_model_events(agent_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_agent_model_events_model ON agent_model_events(model, event_type, created_at);
@@ -0,0 +1,7 @@
+DROP TABLE IF EXISTS team_context_access;
+DROP INDEX IF EXISTS idx_team_shared_context_history_key;
+DROP TABLE IF EXISTS team_shared_context_history;
+DROP TABLE IF EXISTS team_shared_context;
+DROP INDEX IF EXISTS idx_agent_team_members_agent;
+DROP TABLE IF EXISTS agent_team_members;
+DROP TABLE IF EXISTS agent_teams;
@@ -0,0 +1,63 @@
+-- Collaboration teams, their members and the key/value context they share
+CREATE TABLE IF NOT EXISTS agent_teams (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    team_type TEXT,
+    context_version INTEGER NOT NULL DEFAULT 0, -- bumped on every shared-context write
+    created_by TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS agent_team_members (
+    team_id TEXT NOT NULL,
+    agent_id TEXT NOT NULL,
+    role_type TEXT NOT NULL DEFAULT 'executor'
+        CHECK(role_type IN ('leader', 'specialist', 'coordinator', 'reviewer', 'executor', 'observer')),
+    responsibilities TEXT NOT NULL DEFAULT '[]', -- JSON array
+    permissions TEXT NOT NULL DEFAULT '[]', -- JSON array of read/write/delete, empty for the role default
+    reporting_to TEXT,
+    joined_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY(team_id, agent_id),
+    FOREIGN KEY(team_id) REFERENCES agent_teams(id) ON DELETE CASCADE,
+    FOREIGN KEY(agent_id) REFERENCES agents(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_team_members_agent ON agent_team_members(agent_id);
+
+-- Current value of each shared-context key
+CREATE TABLE IF NOT EXISTS team_shared_context (
+    team_id TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL, -- JSON
+    version INTEGER NOT NULL,
+    modified_by TEXT NOT NULL,
+    modified_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY(team_id, key),
+    FOREIGN KEY(team_id) REFERENCES agent_teams(id) ON DELETE CASCADE
+);
+
+-- Every value a key has held; deletions are recorded with a NULL value
+CREATE TABLE IF NOT EXISTS team_shared_context_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    team_id TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT,
+    version INTEGER NOT NULL,
+    modified_by TEXT NOT NULL,
+    modified_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(team_id) REFERENCES agent_teams(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_team_shared_context_history_key ON team_shared_context_history(team_id, key, version);
+
+-- Per-agent overrides of the role's context permissions
+CREATE TABLE IF NOT EXISTS team_context_access (
+    team_id TEXT NOT NULL,
+    agent_id TEXT NOT NULL,
+    permissions TEXT NOT NULL DEFAULT '[]', -- JSON array of read/write/delete
+    restrictions TEXT NOT NULL DEFAULT '[]', -- JSON array of keys, `prefix*` patterns allowed
+    expiry_time DATETIME,
+    PRIMARY KEY(team_id, agent_id),
+    FOREIGN KEY(team_id, agent_id) REFERENCES agent_team_members(team_id, agent_id) ON DELETE CASCADE
+);
@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS idx_task_delegation_assignments_agent;
+DROP TABLE IF EXISTS task_delegation_assignments;
+DROP INDEX IF EXISTS idx_task_delegations_team;
+DROP INDEX IF EXISTS idx_task_delegations_task;
+DROP TABLE IF EXISTS task_delegations;
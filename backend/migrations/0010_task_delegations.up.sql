@@ -0,0 +1,32 @@
+-- Tasks handed to a team and how the team's members were picked for them
+CREATE TABLE IF NOT EXISTS task_delegations (
+    id TEXT PRIMARY KEY,
+    task_id TEXT NOT NULL,
+    team_id TEXT NOT NULL,
+    mode TEXT NOT NULL CHECK(mode IN ('single', 'fanout')),
+    algorithm TEXT NOT NULL,
+    rationale TEXT NOT NULL,
+    created_by TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(task_id) REFERENCES tasks(id) ON DELETE CASCADE,
+    FOREIGN KEY(team_id) REFERENCES agent_teams(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_delegations_task ON task_delegations(task_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_task_delegations_team ON task_delegations(team_id, created_at);
+
+-- One row per assigned task: the delegated task itself, or each fan-out sub-task
+CREATE TABLE IF NOT EXISTS task_delegation_assignments (
+    delegation_id TEXT NOT NULL,
+    task_id TEXT NOT NULL,
+    agent_id TEXT NOT NULL,
+    score REAL NOT NULL,
+    routed BOOLEAN NOT NULL DEFAULT 0,
+    routing_error TEXT,
+    PRIMARY KEY(delegation_id, task_id),
+    FOREIGN KEY(delegation_id) REFERENCES task_delegations(id) ON DELETE CASCADE,
+    FOREIGN KEY(task_id) REFERENCES tasks(id) ON DELETE CASCADE,
+    FOREIGN KEY(agent_id) REFERENCES agents(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_delegation_assignments_agent ON task_delegation_assignments(agent_id);
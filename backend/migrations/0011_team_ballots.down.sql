@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS team_ballot_votes;
+DROP INDEX IF EXISTS idx_team_ballots_deadline;
+DROP INDEX IF EXISTS idx_team_ballots_team;
+DROP TABLE IF EXISTS team_ballots;
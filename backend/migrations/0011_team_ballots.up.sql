@@ -0,0 +1,37 @@
+-- Votes a team holds to settle a decision or a disputed review
+CREATE TABLE IF NOT EXISTS team_ballots (
+    id TEXT PRIMARY KEY,
+    team_id TEXT NOT NULL,
+    task_id TEXT,
+    kind TEXT NOT NULL DEFAULT 'decision' CHECK(kind IN ('decision', 'review')),
+    conflict_type TEXT NOT NULL DEFAULT 'decision_making',
+    question TEXT NOT NULL CHECK(length(question) >= 1),
+    options TEXT NOT NULL, -- JSON array
+    voting_type TEXT NOT NULL,
+    quorum REAL NOT NULL CHECK(quorum > 0 AND quorum <= 1),
+    tie_breaker TEXT NOT NULL,
+    deadline DATETIME NOT NULL,
+    status TEXT NOT NULL DEFAULT 'open' CHECK(status IN ('open', 'resolved', 'escalated')),
+    outcome TEXT,
+    resolution TEXT, -- how the outcome was reached, or why the ballot was escalated
+    resolved_by TEXT,
+    created_by TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    resolved_at DATETIME,
+    FOREIGN KEY(team_id) REFERENCES agent_teams(id) ON DELETE CASCADE,
+    FOREIGN KEY(task_id) REFERENCES tasks(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_team_ballots_team ON team_ballots(team_id, status);
+CREATE INDEX IF NOT EXISTS idx_team_ballots_deadline ON team_ballots(status, deadline);
+
+CREATE TABLE IF NOT EXISTS team_ballot_votes (
+    ballot_id TEXT NOT NULL,
+    agent_id TEXT NOT NULL,
+    choice TEXT NOT NULL,
+    comment TEXT,
+    cast_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY(ballot_id, agent_id),
+    FOREIGN KEY(ballot_id) REFERENCES team_ballots(id) ON DELETE CASCADE,
+    FOREIGN KEY(agent_id) REFERENCES agents(id) ON DELETE CASCADE
+);
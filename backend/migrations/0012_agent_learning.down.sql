@@ -0,0 +1,6 @@
+DROP INDEX IF EXISTS idx_agent_adaptations_agent;
+DROP TABLE IF EXISTS agent_adaptations;
+DROP INDEX IF EXISTS idx_agent_patterns_agent;
+DROP TABLE IF EXISTS agent_patterns;
+DROP INDEX IF EXISTS idx_agent_feedback_agent;
+DROP TABLE IF EXISTS agent_feedback;
@@ -0,0 +1,58 @@
+-- Feedback on agents, from people, reviews and agents themselves
+CREATE TABLE IF NOT EXISTS agent_feedback (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    feedback_type TEXT NOT NULL CHECK(feedback_type IN ('user_rating', 'task_completion', 'error_rate', 'response_time', 'cost_efficiency')),
+    rating REAL NOT NULL, -- as submitted: stars, 0-1, seconds or USD depending on the type
+    comment TEXT,
+    context TEXT,
+    task_id TEXT,
+    source TEXT NOT NULL CHECK(source IN ('user', 'review')),
+    submitted_by TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(agent_id) REFERENCES agents(id) ON DELETE CASCADE,
+    FOREIGN KEY(task_id) REFERENCES tasks(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_feedback_agent ON agent_feedback(agent_id, created_at);
+
+-- Trends the pattern recognizer found in an agent's feedback
+CREATE TABLE IF NOT EXISTS agent_patterns (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    pattern_type TEXT NOT NULL CHECK(pattern_type IN ('temporal', 'behavioral', 'performance', 'cost', 'error')),
+    trend_type TEXT NOT NULL CHECK(trend_type IN ('increasing', 'decreasing', 'stable', 'volatile', 'seasonal')),
+    slope REAL NOT NULL,
+    samples INTEGER NOT NULL,
+    confidence REAL NOT NULL CHECK(confidence >= 0.0 AND confidence <= 1.0),
+    description TEXT NOT NULL,
+    detected_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(agent_id) REFERENCES agents(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_patterns_agent ON agent_patterns(agent_id, detected_at);
+
+-- Proposed configuration changes and what became of them
+CREATE TABLE IF NOT EXISTS agent_adaptations (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    pattern_id TEXT,
+    action_type TEXT NOT NULL CHECK(action_type IN ('parameter_adjustment', 'model_switch', 'configuration_change', 'skill_update', 'resource_reallocation')),
+    parameters TEXT NOT NULL, -- JSON object
+    expected_impact TEXT NOT NULL, -- JSON object
+    rollback_plan TEXT NOT NULL, -- JSON object
+    evaluation_criteria TEXT NOT NULL, -- JSON array, targets filled in when applied
+    status TEXT NOT NULL DEFAULT 'proposed' CHECK(status IN ('proposed', 'rejected', 'applied', 'kept', 'rolled_back')),
+    previous_config TEXT, -- JSON, the OpenClaw config the adaptation replaced
+    outcome TEXT,
+    improvement REAL, -- weighted change in the evaluated metrics, positive when better
+    proposed_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    decided_by TEXT,
+    decided_at DATETIME,
+    applied_at DATETIME,
+    evaluated_at DATETIME,
+    FOREIGN KEY(agent_id) REFERENCES agents(id) ON DELETE CASCADE,
+    FOREIGN KEY(pattern_id) REFERENCES agent_patterns(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_adaptations_agent ON agent_adaptations(agent_id, status);
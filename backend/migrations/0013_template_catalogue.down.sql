@@ -0,0 +1,24 @@
+DROP INDEX IF EXISTS idx_agent_template_relationships_template;
+
+CREATE TABLE agent_template_relationships_old (
+    id TEXT PRIMARY KEY,
+    template_id TEXT NOT NULL,
+    agent_id TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(template_id) REFERENCES agent_templates(id),
+    FOREIGN KEY(agent_id) REFERENCES agents(id)
+);
+
+INSERT INTO agent_template_relationships_old (id, template_id, agent_id, created_at)
+SELECT id, template_id, agent_id, created_at FROM agent_template_relationships;
+
+DROP TABLE agent_template_relationships;
+ALTER TABLE agent_template_relationships_old RENAME TO agent_template_relationships;
+
+DROP TABLE IF EXISTS agent_template_ratings;
+DROP TABLE IF EXISTS agent_template_versions;
+
+ALTER TABLE agent_templates DROP COLUMN created_by;
+ALTER TABLE agent_templates DROP COLUMN source_agent_id;
+ALTER TABLE agent_templates DROP COLUMN rating_count;
+ALTER TABLE agent_templates DROP COLUMN version;
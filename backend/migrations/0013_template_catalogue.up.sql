@@ -0,0 +1,55 @@
+-- Templates are revised over time; agents remember which revision they were
+-- created from so they can be told when a newer one is published
+ALTER TABLE agent_templates ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE agent_templates ADD COLUMN rating_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE agent_templates ADD COLUMN source_agent_id TEXT; -- agent the current version was snapshotted from
+ALTER TABLE agent_templates ADD COLUMN created_by TEXT;
+
+CREATE TABLE IF NOT EXISTS agent_template_versions (
+    template_id TEXT NOT NULL,
+    version INTEGER NOT NULL CHECK(version >= 1),
+    configuration TEXT NOT NULL, -- JSON, as published in this version
+    changelog TEXT,
+    source_agent_id TEXT,
+    created_by TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY(template_id, version),
+    FOREIGN KEY(template_id) REFERENCES agent_templates(id) ON DELETE CASCADE
+);
+
+INSERT INTO agent_template_versions (template_id, version, configuration, created_at)
+SELECT id, 1, configuration, COALESCE(updated_at, CURRENT_TIMESTAMP) FROM agent_templates;
+
+-- One rating per user and template; agent_templates.rating holds the average
+CREATE TABLE IF NOT EXISTS agent_template_ratings (
+    template_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    rating INTEGER NOT NULL CHECK(rating BETWEEN 1 AND 5),
+    comment TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY(template_id, user_id),
+    FOREIGN KEY(template_id) REFERENCES agent_templates(id) ON DELETE CASCADE
+);
+
+-- Relationships need an explicit version, and must not block deleting an
+-- agent. SQLite cannot alter foreign keys, so the table is rebuilt.
+CREATE TABLE agent_template_relationships_new (
+    id TEXT PRIMARY KEY,
+    template_id TEXT NOT NULL,
+    agent_id TEXT NOT NULL UNIQUE,
+    template_version INTEGER NOT NULL DEFAULT 1, -- latest version the agent is known to be based on
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(template_id) REFERENCES agent_templates(id) ON DELETE CASCADE,
+    FOREIGN KEY(agent_id) REFERENCES agents(id) ON DELETE CASCADE
+);
+
+INSERT OR IGNORE INTO agent_template_relationships_new (id, template_id, agent_id, template_version, created_at)
+SELECT COALESCE(id, lower(hex(randomblob(16)))), template_id, agent_id, 1, created_at
+FROM agent_template_relationships
+ORDER BY created_at;
+
+DROP TABLE agent_template_relationships;
+ALTER TABLE agent_template_relationships_new RENAME TO agent_template_relationships;
+
+CREATE INDEX IF NOT EXISTS idx_agent_template_relationships_template ON agent_template_relationships(template_id, template_version);
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS agent_bulk_job_results;
+DROP INDEX IF EXISTS idx_agent_bulk_jobs_created;
+DROP TABLE IF EXISTS agent_bulk_jobs;
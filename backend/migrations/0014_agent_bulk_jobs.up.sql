@@ -0,0 +1,34 @@
+-- Bulk operations run in the background; the job and one result row per
+-- agent stay queryable after it finishes
+CREATE TABLE IF NOT EXISTS agent_bulk_jobs (
+    id TEXT PRIMARY KEY,
+    operation TEXT NOT NULL CHECK(operation IN ('enable', 'disable', 'reset', 'optimize', 'validate', 'apply_template')),
+    parameters TEXT, -- JSON object
+    all_or_nothing BOOLEAN NOT NULL DEFAULT 0,
+    concurrency INTEGER NOT NULL CHECK(concurrency >= 1),
+    status TEXT NOT NULL DEFAULT 'queued' CHECK(status IN ('queued', 'running', 'completed', 'cancelled', 'rolled_back', 'failed')),
+    total_agents INTEGER NOT NULL DEFAULT 0,
+    succeeded INTEGER NOT NULL DEFAULT 0,
+    failed INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    requested_by TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    started_at DATETIME,
+    finished_at DATETIME
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_bulk_jobs_created ON agent_bulk_jobs(created_at);
+
+CREATE TABLE IF NOT EXISTS agent_bulk_job_results (
+    job_id TEXT NOT NULL,
+    agent_id TEXT NOT NULL, -- not a foreign key: results outlive deleted agents
+    position INTEGER NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK(status IN ('pending', 'running', 'succeeded', 'failed', 'cancelled', 'rolled_back', 'rollback_failed')),
+    message TEXT,
+    previous_state TEXT, -- JSON, what rolling the agent back restores
+    started_at DATETIME,
+    finished_at DATETIME,
+    duration_ms INTEGER,
+    PRIMARY KEY(job_id, agent_id),
+    FOREIGN KEY(job_id) REFERENCES agent_bulk_jobs(id) ON DELETE CASCADE
+);
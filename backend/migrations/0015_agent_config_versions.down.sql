@@ -0,0 +1,8 @@
+DROP TRIGGER IF EXISTS config_snapshots_immutable;
+DROP INDEX IF EXISTS idx_config_snapshots_version;
+
+ALTER TABLE openclaw_config_snapshots DROP COLUMN rolled_back_from;
+ALTER TABLE openclaw_config_snapshots DROP COLUMN reason;
+ALTER TABLE openclaw_config_snapshots DROP COLUMN change_source;
+ALTER TABLE openclaw_config_snapshots DROP COLUMN config_kind;
+ALTER TABLE openclaw_config_snapshots DROP COLUMN version;
@@ -0,0 +1,38 @@
+-- Every change to an agent's configuration is kept as a numbered snapshot that
+-- can be compared with any other and rolled back to
+ALTER TABLE openclaw_config_snapshots ADD COLUMN version INTEGER;
+ALTER TABLE openclaw_config_snapshots ADD COLUMN config_kind TEXT NOT NULL DEFAULT 'openclaw'
+    CHECK(config_kind IN ('openclaw', 'comprehensive'));
+-- Snapshots taken before changes were attributed are 'unknown'
+ALTER TABLE openclaw_config_snapshots ADD COLUMN change_source TEXT NOT NULL DEFAULT 'unknown'
+    CHECK(change_source IN ('unknown', 'apply', 'parameters', 'comprehensive', 'sync', 'adaptation', 'rollback'));
+ALTER TABLE openclaw_config_snapshots ADD COLUMN reason TEXT;
+ALTER TABLE openclaw_config_snapshots ADD COLUMN rolled_back_from INTEGER; -- version a rollback restored
+
+UPDATE openclaw_config_snapshots
+SET version = (
+    SELECT COUNT(*) FROM openclaw_config_snapshots earlier
+    WHERE earlier.agent_id = openclaw_config_snapshots.agent_id
+      AND (earlier.applied_at < openclaw_config_snapshots.applied_at
+           OR (earlier.applied_at = openclaw_config_snapshots.applied_at
+               AND earlier.rowid <= openclaw_config_snapshots.rowid))
+);
+
+-- Only the latest snapshot of each kind describes the agent as it is now
+UPDATE openclaw_config_snapshots
+SET is_active = version = (
+    SELECT MAX(latest.version) FROM openclaw_config_snapshots latest
+    WHERE latest.agent_id = openclaw_config_snapshots.agent_id
+      AND latest.config_kind = openclaw_config_snapshots.config_kind
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_config_snapshots_version ON openclaw_config_snapshots(agent_id, version);
+
+-- Only is_active moves once a snapshot is taken. created_by is left out so
+-- deleting a user can still clear it.
+CREATE TRIGGER IF NOT EXISTS config_snapshots_immutable
+BEFORE UPDATE OF agent_id, version, config_kind, config_hash, raw_config, applied_at,
+    change_source, reason, rolled_back_from ON openclaw_config_snapshots
+BEGIN
+    SELECT RAISE(ABORT, 'configuration snapshots are immutable');
+END;
@@ -0,0 +1,5 @@
+UPDATE roles
+SET permissions = (SELECT json_group_array(value) FROM json_each(roles.permissions) WHERE value != 'audit:*')
+WHERE id = 'role_super_admin' AND json_valid(permissions);
+
+DELETE FROM permissions WHERE id = 'perm_audit_admin';
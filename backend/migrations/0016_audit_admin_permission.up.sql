@@ -0,0 +1,10 @@
+-- Retention policies and legal holds need audit:admin. Fresh databases get it
+-- from the seeded roles; existing SUPER_ADMIN roles are granted it here.
+UPDATE roles
+SET permissions = json_insert(permissions, '$[#]', 'audit:*'),
+    updated_at = CURRENT_TIMESTAMP
+WHERE id = 'role_super_admin'
+  AND json_valid(permissions)
+  AND NOT EXISTS (
+      SELECT 1 FROM json_each(roles.permissions) WHERE value IN ('audit:*', '*:*', '*')
+  );
@@ -0,0 +1,16 @@
+-- Changes from bulk jobs and failovers fall back to 'unknown'
+DROP TRIGGER IF EXISTS config_snapshots_immutable;
+
+ALTER TABLE openclaw_config_snapshots ADD COLUMN change_source_old TEXT NOT NULL DEFAULT 'unknown'
+    CHECK(change_source_old IN ('unknown', 'apply', 'parameters', 'comprehensive', 'sync', 'adaptation', 'rollback'));
+UPDATE openclaw_config_snapshots
+SET change_source_old = CASE WHEN change_source IN ('bulk_job', 'failover') THEN 'unknown' ELSE change_source END;
+ALTER TABLE openclaw_config_snapshots DROP COLUMN change_source;
+ALTER TABLE openclaw_config_snapshots RENAME COLUMN change_source_old TO change_source;
+
+CREATE TRIGGER IF NOT EXISTS config_snapshots_immutable
+BEFORE UPDATE OF agent_id, version, config_kind, config_hash, raw_config, applied_at,
+    change_source, reason, rolled_back_from ON openclaw_config_snapshots
+BEGIN
+    SELECT RAISE(ABORT, 'configuration snapshots are immutable');
+END;
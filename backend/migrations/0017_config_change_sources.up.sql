@@ -0,0 +1,17 @@
+-- Bulk jobs and model failovers record the configuration changes they make.
+-- SQLite cannot alter a CHECK constraint, so change_source is copied into a
+-- column with the wider constraint and the old column dropped.
+DROP TRIGGER IF EXISTS config_snapshots_immutable;
+
+ALTER TABLE openclaw_config_snapshots ADD COLUMN change_source_new TEXT NOT NULL DEFAULT 'unknown'
+    CHECK(change_source_new IN ('unknown', 'apply', 'parameters', 'comprehensive', 'sync', 'adaptation', 'rollback', 'bulk_job', 'failover'));
+UPDATE openclaw_config_snapshots SET change_source_new = change_source;
+ALTER TABLE openclaw_config_snapshots DROP COLUMN change_source;
+ALTER TABLE openclaw_config_snapshots RENAME COLUMN change_source_new TO change_source;
+
+CREATE TRIGGER IF NOT EXISTS config_snapshots_immutable
+BEFORE UPDATE OF agent_id, version, config_kind, config_hash, raw_config, applied_at,
+    change_source, reason, rolled_back_from ON openclaw_config_snapshots
+BEGIN
+    SELECT RAISE(ABORT, 'configuration snapshots are immutable');
+END;
@@ -0,0 +1,27 @@
+-- Account creation and update events have no place in the narrower constraint
+CREATE TABLE security_events_new (
+    id TEXT PRIMARY KEY,
+    event_type TEXT NOT NULL CHECK(event_type IN ('login_failure', 'login_success', 'session_created', 'session_revoked', 'password_changed', 'password_change_failed', 'password_reset_requested', 'password_reset', 'unauthorized_access', 'privilege_escalation', 'data_breach', 'suspicious_activity', 'malware_detected', 'two_factor_enrolled', 'two_factor_disabled', 'two_factor_challenge', 'two_factor_failure')),
+    severity TEXT NOT NULL CHECK(severity IN ('low', 'medium', 'high', 'critical')),
+    description TEXT NOT NULL,
+    source_ip TEXT,
+    target_resource TEXT,
+    user_id TEXT,
+    details TEXT, -- JSON object
+    resolved BOOLEAN DEFAULT 0,
+    resolved_at DATETIME,
+    resolved_by TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    -- Constraints
+    FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE SET NULL,
+    FOREIGN KEY(resolved_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+INSERT INTO security_events_new (id, event_type, severity, description, source_ip, target_resource, user_id, details, resolved, resolved_at, resolved_by, created_at)
+SELECT id, event_type, severity, description, source_ip, target_resource, user_id, details, resolved, resolved_at, resolved_by, created_at FROM security_events WHERE event_type NOT IN ('user_created', 'user_updated');
+
+DROP TABLE security_events;
+ALTER TABLE security_events_new RENAME TO security_events;
+
+CREATE INDEX IF NOT EXISTS idx_security_events_created ON security_events(created_at);
+CREATE INDEX IF NOT EXISTS idx_security_events_severity ON security_events(severity);
@@ -0,0 +1,25 @@
+-- Announcements by the server have no agent to reference
+CREATE TABLE announcements_new (
+    id TEXT PRIMARY KEY,
+    title TEXT,
+    message TEXT NOT NULL CHECK(length(message) >= 1),
+    priority TEXT NOT NULL DEFAULT 'NORMAL' CHECK(priority IN ('LOW', 'NORMAL', 'HIGH', 'URGENT', 'CRITICAL')),
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    created_by TEXT NOT NULL,
+    expires_at DATETIME,
+    target_audience TEXT, -- JSON array of roles/ids
+    is_active BOOLEAN DEFAULT 1,
+    view_count INTEGER DEFAULT 0 CHECK(view_count >= 0),
+    -- Constraints
+    FOREIGN KEY(created_by) REFERENCES agents(id)
+);
+
+INSERT INTO announcements_new (id, title, message, priority, created_at, created_by, expires_at, target_audience, is_active, view_count)
+SELECT id, title, message, priority, created_at, created_by, expires_at, target_audience, is_active, view_count FROM announcements
+WHERE created_by IN (SELECT id FROM agents);
+
+DROP TABLE announcements;
+ALTER TABLE announcements_new RENAME TO announcements;
+
+DROP TRIGGER IF EXISTS agents_clear_suspension;
+ALTER TABLE agents DROP COLUMN suspended_by;
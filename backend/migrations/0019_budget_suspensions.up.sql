@@ -0,0 +1,40 @@
+-- Budget enforcement only resumes agents it suspended itself, so the reason
+-- for a suspension is kept with the agent and cleared once it is lifted.
+ALTER TABLE agents ADD COLUMN suspended_by TEXT CHECK(suspended_by IS NULL OR suspended_by IN ('budget'));
+
+-- Suspensions from before this migration are attributed to the budget when an
+-- exceeded daily, weekly or monthly alert is still open for the agent
+UPDATE agents SET suspended_by = 'budget'
+WHERE status = 'SUSPENDED' AND id IN (
+    SELECT agent_id FROM agent_budget_alerts
+    WHERE resolved_at IS NULL AND scope != 'task' AND level = 'exceeded'
+);
+
+CREATE TRIGGER IF NOT EXISTS agents_clear_suspension
+AFTER UPDATE OF status ON agents
+WHEN NEW.status != 'SUSPENDED' AND NEW.suspended_by IS NOT NULL
+BEGIN
+    UPDATE agents SET suspended_by = NULL WHERE id = NEW.id;
+END;
+
+-- Budget alerts are announced by the server rather than an agent.
+-- SQLite cannot drop a foreign key, so the table is rebuilt without the
+-- reference to agents and created_by may be 'system'.
+CREATE TABLE announcements_new (
+    id TEXT PRIMARY KEY,
+    title TEXT,
+    message TEXT NOT NULL CHECK(length(message) >= 1),
+    priority TEXT NOT NULL DEFAULT 'NORMAL' CHECK(priority IN ('LOW', 'NORMAL', 'HIGH', 'URGENT', 'CRITICAL')),
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    created_by TEXT NOT NULL, -- agent id, or 'system'
+    expires_at DATETIME,
+    target_audience TEXT, -- JSON array of roles/ids
+    is_active BOOLEAN DEFAULT 1,
+    view_count INTEGER DEFAULT 0 CHECK(view_count >= 0)
+);
+
+INSERT INTO announcements_new (id, title, message, priority, created_at, created_by, expires_at, target_audience, is_active, view_count)
+SELECT id, title, message, priority, created_at, created_by, expires_at, target_audience, is_active, view_count FROM announcements;
+
+DROP TABLE announcements;
+ALTER TABLE announcements_new RENAME TO announcements;
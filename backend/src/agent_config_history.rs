@@ -0,0 +1,646 @@
+use crate::db::SqlitePool;
+use crate::models::OpenClawAgentConfig;
+use crate::agent_management::AgentConfigRequest;
+use crate::openclaw_optimization::HierarchicalCache;
+use crate::ConnectionManager;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, SqliteConnection};
+use std::collections::BTreeSet;
+use tracing::{info, warn};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+    response::IntoResponse,
+    http::{HeaderMap, StatusCode},
+};
+use crate::AppState;
+
+#[derive(Debug)]
+pub enum HistoryError {
+    AgentNotFound(String),
+    VersionNotFound(String, i64),
+    Invalid(String),
+    Conflict(String),
+    Internal(String),
+}
+
+impl std::fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            HistoryError::AgentNotFound(id) => write!(f, "Agent '{}' has no configuration history", id),
+            HistoryError::VersionNotFound(id, version) => write!(f, "Agent '{}' has no configuration version {}", id, version),
+            HistoryError::Invalid(msg) | HistoryError::Conflict(msg) => write!(f, "{}", msg),
+            HistoryError::Internal(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<sqlx::Error> for HistoryError {
+    fn from(error: sqlx::Error) -> Self {
+        HistoryError::Internal(format!("Database error: {}", error))
+    }
+}
+
+impl From<serde_json::Error> for HistoryError {
+    fn from(error: serde_json::Error) -> Self {
+        HistoryError::Internal(error.to_string())
+    }
+}
+
+impl From<HistoryError> for (StatusCode, String) {
+    fn from(error: HistoryError) -> Self {
+        let status = match &error {
+            HistoryError::AgentNotFound(_) | HistoryError::VersionNotFound(..) => StatusCode::NOT_FOUND,
+            HistoryError::Invalid(_) => StatusCode::BAD_REQUEST,
+            HistoryError::Conflict(_) => StatusCode::CONFLICT,
+            HistoryError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, error.to_string())
+    }
+}
+
+/// Which configuration a snapshot holds. Both kinds share one version sequence per agent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigKind {
+    /// The agent's entry in openclaw.json, as applied to the agents table
+    OpenClaw,
+    /// The configuration saved through the comprehensive agent endpoints
+    Comprehensive,
+}
+
+impl ConfigKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConfigKind::OpenClaw => "openclaw",
+            ConfigKind::Comprehensive => "comprehensive",
+        }
+    }
+
+    fn parse(kind: &str) -> Result<Self, HistoryError> {
+        match kind {
+            "openclaw" => Ok(ConfigKind::OpenClaw),
+            "comprehensive" => Ok(ConfigKind::Comprehensive),
+            other => Err(HistoryError::Internal(format!("Unknown configuration kind '{}'", other))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeSource {
+    Apply,
+    Parameters,
+    Comprehensive,
+    Sync,
+    Adaptation,
+    Rollback,
+    BulkJob,
+    /// openclaw.json rewritten to name a fallback model, or the primary again
+    Failover,
+}
+
+impl ChangeSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeSource::Apply => "apply",
+            ChangeSource::Parameters => "parameters",
+            ChangeSource::Comprehensive => "comprehensive",
+            ChangeSource::Sync => "sync",
+            ChangeSource::Adaptation => "adaptation",
+            ChangeSource::Rollback => "rollback",
+            ChangeSource::BulkJob => "bulk_job",
+            ChangeSource::Failover => "failover",
+        }
+    }
+}
+
+/// Who changed a configuration and why, stored with the snapshot the change produces
+#[derive(Debug, Clone)]
+pub struct ConfigChange {
+    pub source: ChangeSource,
+    /// User id; None for changes the server makes on its own
+    pub author: Option<String>,
+    pub reason: Option<String>,
+    /// Version a rollback restored
+    pub rolled_back_from: Option<i64>,
+}
+
+impl ConfigChange {
+    pub fn new(source: ChangeSource, author: Option<&str>, reason: Option<String>) -> Self {
+        Self {
+            source,
+            author: author.map(str::to_string),
+            reason: reason.filter(|r| !r.trim().is_empty()),
+            rolled_back_from: None,
+        }
+    }
+}
+
+/// `?reason=` accepted by every endpoint that changes an agent's configuration
+#[derive(Debug, Default, Deserialize)]
+pub struct ChangeReasonQuery {
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, FromRow)]
+struct SnapshotRow {
+    id: String,
+    agent_id: String,
+    version: i64,
+    config_kind: String,
+    config_hash: String,
+    raw_config: String,
+    applied_at: Option<DateTime<Utc>>,
+    is_active: bool,
+    created_by: Option<String>,
+    change_source: String,
+    reason: Option<String>,
+    rolled_back_from: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigSnapshot {
+    pub id: String,
+    pub agent_id: String,
+    pub version: i64,
+    pub kind: ConfigKind,
+    pub config_hash: String,
+    pub applied_at: Option<DateTime<Utc>>,
+    /// Whether this is the agent's current configuration of its kind
+    pub is_active: bool,
+    pub author: Option<String>,
+    pub source: String,
+    pub reason: Option<String>,
+    pub rolled_back_from: Option<i64>,
+    /// Left out of listings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<Value>,
+}
+
+impl SnapshotRow {
+    fn into_snapshot(self, with_config: bool) -> Result<ConfigSnapshot, HistoryError> {
+        let config = if with_config { Some(serde_json::from_str(&self.raw_config)?) } else { None };
+        Ok(ConfigSnapshot {
+            id: self.id,
+            agent_id: self.agent_id,
+            version: self.version,
+            kind: ConfigKind::parse(&self.config_kind)?,
+            config_hash: self.config_hash,
+            applied_at: self.applied_at,
+            is_active: self.is_active,
+            author: self.created_by,
+            source: self.change_source,
+            reason: self.reason,
+            rolled_back_from: self.rolled_back_from,
+            config,
+        })
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct SnapshotQuery {
+    pub kind: Option<ConfigKind>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiffQuery {
+    pub from: i64,
+    /// Defaults to the active snapshot of the same kind
+    pub to: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// One leaf that differs between two configurations
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldChange {
+    /// JSON Pointer into the configuration, e.g. `/model/fallbacks/0`
+    pub path: String,
+    pub change: FieldChangeKind,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfigDiff {
+    pub agent_id: String,
+    pub kind: ConfigKind,
+    pub from: i64,
+    pub to: i64,
+    pub changes: Vec<FieldChange>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RollbackRequest {
+    pub reason: Option<String>,
+    /// Also write an OpenClaw snapshot back to the agent's entry in openclaw.json
+    #[serde(default)]
+    pub write_openclaw_json: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RollbackResult {
+    pub restored_version: i64,
+    pub snapshot: ConfigSnapshot,
+    pub openclaw_json_written: bool,
+}
+
+fn escape_pointer_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// Field-level differences between two JSON documents. Objects are compared key by
+/// key and arrays index by index; a null is treated as an absent field.
+pub fn diff_json(old: &Value, new: &Value) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    diff_at(String::new(), Some(old), Some(new), &mut changes);
+    changes
+}
+
+fn diff_at(path: String, old: Option<&Value>, new: Option<&Value>, changes: &mut Vec<FieldChange>) {
+    let old = old.filter(|v| !v.is_null());
+    let new = new.filter(|v| !v.is_null());
+    match (old, new) {
+        (None, None) => {}
+        (None, Some(new)) => changes.push(FieldChange { path, change: FieldChangeKind::Added, old: None, new: Some(new.clone()) }),
+        (Some(old), None) => changes.push(FieldChange { path, change: FieldChangeKind::Removed, old: Some(old.clone()), new: None }),
+        (Some(Value::Object(old)), Some(Value::Object(new))) => {
+            let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+            for key in keys {
+                diff_at(format!("{}/{}", path, escape_pointer_token(key)), old.get(key), new.get(key), changes);
+            }
+        }
+        (Some(Value::Array(old)), Some(Value::Array(new))) => {
+            for index in 0..old.len().max(new.len()) {
+                diff_at(format!("{}/{}", path, index), old.get(index), new.get(index), changes);
+            }
+        }
+        (Some(old), Some(new)) if old != new => {
+            changes.push(FieldChange { path, change: FieldChangeKind::Changed, old: Some(old.clone()), new: Some(new.clone()) })
+        }
+        _ => {}
+    }
+}
+
+pub struct ConfigHistoryService;
+
+impl ConfigHistoryService {
+    /// Records `config_json` as the agent's newest snapshot of `kind` and returns its
+    /// version. Nothing is recorded when it matches the active snapshot. A removed
+    /// configuration is recorded as `null`.
+    pub async fn record(
+        &self,
+        conn: &mut SqliteConnection,
+        agent_id: &str,
+        kind: ConfigKind,
+        config_json: &str,
+        change: &ConfigChange,
+    ) -> Result<Option<i64>, HistoryError> {
+        let config_hash = format!("{:x}", Sha256::digest(config_json.as_bytes()));
+        let active_hash: Option<String> = sqlx::query_scalar(
+            "SELECT config_hash FROM openclaw_config_snapshots
+             WHERE agent_id = ?1 AND config_kind = ?2 AND is_active = 1
+             ORDER BY version DESC LIMIT 1"
+        )
+        .bind(agent_id)
+        .bind(kind.as_str())
+        .fetch_optional(&mut *conn)
+        .await?;
+        if active_hash.as_deref() == Some(config_hash.as_str()) {
+            return Ok(None);
+        }
+
+        sqlx::query("UPDATE openclaw_config_snapshots SET is_active = 0 WHERE agent_id = ?1 AND config_kind = ?2 AND is_active = 1")
+            .bind(agent_id)
+            .bind(kind.as_str())
+            .execute(&mut *conn)
+            .await?;
+
+        // The version is taken in the insert itself so concurrent writers cannot share one
+        let version: i64 = sqlx::query_scalar(
+            "INSERT INTO openclaw_config_snapshots (
+                id, agent_id, version, config_kind, config_hash, raw_config, is_active,
+                created_by, change_source, reason, rolled_back_from
+             )
+             SELECT ?1, ?2, COALESCE(MAX(version), 0) + 1, ?3, ?4, ?5, 1, ?6, ?7, ?8, ?9
+             FROM openclaw_config_snapshots WHERE agent_id = ?2
+             RETURNING version"
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(agent_id)
+        .bind(kind.as_str())
+        .bind(&config_hash)
+        .bind(config_json)
+        .bind(&change.author)
+        .bind(change.source.as_str())
+        .bind(&change.reason)
+        .bind(change.rolled_back_from)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        info!("Recorded {} configuration version {} for agent {} ({})", kind.as_str(), version, agent_id, change.source.as_str());
+        Ok(Some(version))
+    }
+
+    pub async fn list(&self, pool: &SqlitePool, agent_id: &str, kind: Option<ConfigKind>) -> Result<Vec<ConfigSnapshot>, HistoryError> {
+        sqlx::query_as::<sqlx::Sqlite, SnapshotRow>(
+            "SELECT * FROM openclaw_config_snapshots
+             WHERE agent_id = ?1 AND (?2 IS NULL OR config_kind = ?2)
+             ORDER BY version DESC"
+        )
+        .bind(agent_id)
+        .bind(kind.map(|k| k.as_str()))
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|row| row.into_snapshot(false))
+        .collect()
+    }
+
+    pub async fn snapshot(&self, pool: &SqlitePool, agent_id: &str, version: i64) -> Result<ConfigSnapshot, HistoryError> {
+        sqlx::query_as::<sqlx::Sqlite, SnapshotRow>(
+            "SELECT * FROM openclaw_config_snapshots WHERE agent_id = ?1 AND version = ?2"
+        )
+        .bind(agent_id)
+        .bind(version)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| HistoryError::VersionNotFound(agent_id.to_string(), version))?
+        .into_snapshot(true)
+    }
+
+    /// The agent's current configuration of `kind`, if it has ever been recorded
+    pub async fn active(&self, pool: &SqlitePool, agent_id: &str, kind: ConfigKind) -> Result<Option<ConfigSnapshot>, HistoryError> {
+        sqlx::query_as::<sqlx::Sqlite, SnapshotRow>(
+            "SELECT * FROM openclaw_config_snapshots
+             WHERE agent_id = ?1 AND config_kind = ?2 AND is_active = 1
+             ORDER BY version DESC LIMIT 1"
+        )
+        .bind(agent_id)
+        .bind(kind.as_str())
+        .fetch_optional(pool)
+        .await?
+        .map(|row| row.into_snapshot(true))
+        .transpose()
+    }
+
+    /// The OpenClaw configuration parameter updates start from: the last one applied,
+    /// or the agent's entry in openclaw.json when none has been
+    pub async fn current_openclaw_config(&self, pool: &SqlitePool, agent_id: &str) -> Result<OpenClawAgentConfig, HistoryError> {
+        if let Some(config) = self.active(pool, agent_id, ConfigKind::OpenClaw).await?.and_then(|s| s.config) {
+            return Ok(serde_json::from_value(config)?);
+        }
+        crate::openclaw_integration::read_and_parse_openclaw_config()
+            .await
+            .map_err(|e| HistoryError::Conflict(format!("Cannot read the OpenClaw configuration: {}", e)))?
+            .into_iter()
+            .find(|config| config.id == agent_id)
+            .ok_or_else(|| HistoryError::AgentNotFound(agent_id.to_string()))
+    }
+
+    pub async fn diff(&self, pool: &SqlitePool, agent_id: &str, from: i64, to: Option<i64>) -> Result<ConfigDiff, HistoryError> {
+        let from = self.snapshot(pool, agent_id, from).await?;
+        let to = match to {
+            Some(version) => self.snapshot(pool, agent_id, version).await?,
+            None => self
+                .active(pool, agent_id, from.kind)
+                .await?
+                .ok_or_else(|| HistoryError::AgentNotFound(agent_id.to_string()))?,
+        };
+        if from.kind != to.kind {
+            return Err(HistoryError::Invalid(format!(
+                "Version {} holds a {} configuration and version {} a {} one",
+                from.version, from.kind.as_str(), to.version, to.kind.as_str()
+            )));
+        }
+
+        Ok(ConfigDiff {
+            agent_id: agent_id.to_string(),
+            kind: from.kind,
+            from: from.version,
+            to: to.version,
+            changes: diff_json(
+                from.config.as_ref().unwrap_or(&Value::Null),
+                to.config.as_ref().unwrap_or(&Value::Null),
+            ),
+        })
+    }
+
+    /// Re-applies an old snapshot through the same path as a regular change, which
+    /// records it again as the agent's newest version
+    pub async fn rollback(
+        &self,
+        pool: &SqlitePool,
+        cache: &HierarchicalCache,
+        manager: &ConnectionManager,
+        agent_id: &str,
+        version: i64,
+        request: RollbackRequest,
+        user_id: &str,
+    ) -> Result<RollbackResult, HistoryError> {
+        let target = self.snapshot(pool, agent_id, version).await?;
+        let kind = target.kind;
+        if target.config.as_ref().is_none_or(Value::is_null) {
+            return Err(HistoryError::Invalid(format!("Version {} removed the configuration; there is nothing to restore", version)));
+        }
+        if let Some(active) = self.active(pool, agent_id, kind).await?
+            && active.config_hash == target.config_hash {
+                return Err(HistoryError::Conflict(format!(
+                    "Version {} already matches the agent's current configuration (version {})",
+                    version, active.version
+                )));
+            }
+        if request.write_openclaw_json && kind != ConfigKind::OpenClaw {
+            return Err(HistoryError::Invalid("Only OpenClaw configurations can be written to openclaw.json".to_string()));
+        }
+
+        let mut change = ConfigChange::new(
+            ChangeSource::Rollback,
+            Some(user_id),
+            Some(request.reason.unwrap_or_else(|| format!("Rollback to version {}", version))),
+        );
+        change.rolled_back_from = Some(version);
+        let config = target.config.unwrap_or(Value::Null);
+
+        let mut openclaw_json_written = false;
+        match kind {
+            ConfigKind::OpenClaw => {
+                let config: OpenClawAgentConfig = serde_json::from_value(config)?;
+                crate::openclaw_integration::apply_validated_agent_config(pool, cache, agent_id, &config, &change)
+                    .await
+                    .map_err(|(status, message)| match status {
+                        StatusCode::BAD_REQUEST => HistoryError::Invalid(message),
+                        _ => HistoryError::Internal(message),
+                    })?;
+                if request.write_openclaw_json {
+                    openclaw_json_written = crate::openclaw_integration_helpers::write_agent_config_to_openclaw(&config)
+                        .await
+                        .map_err(|e| HistoryError::Internal(format!("Version {} was applied but openclaw.json could not be written: {}", version, e)))?;
+                    if !openclaw_json_written {
+                        warn!("Agent {} has no entry in openclaw.json; rollback to version {} was only applied", agent_id, version);
+                    }
+                }
+            }
+            ConfigKind::Comprehensive => {
+                let config: AgentConfigRequest = serde_json::from_value(config)?;
+                crate::agent_management_impl::restore_comprehensive_config(pool, cache, &config, &change)
+                    .await
+                    .map_err(|e| HistoryError::Internal(format!("Failed to restore configuration: {}", e)))?;
+            }
+        }
+
+        let snapshot = self
+            .active(pool, agent_id, kind)
+            .await?
+            .ok_or_else(|| HistoryError::Internal("The restored configuration was not recorded".to_string()))?;
+        manager.broadcast(&serde_json::json!({
+            "type": "agent_config_rolled_back",
+            "agent_id": agent_id,
+            "kind": kind.as_str(),
+            "restored_version": version,
+            "version": snapshot.version,
+        }).to_string());
+        info!("Rolled agent {} back to {} configuration version {} as version {}", agent_id, kind.as_str(), version, snapshot.version);
+
+        Ok(RollbackResult { restored_version: version, snapshot, openclaw_json_written })
+    }
+}
+
+// Axum Handlers
+pub async fn list_agent_config_versions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(agent_id): Path<String>,
+    Query(query): Query<SnapshotQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
+    Ok(Json(ConfigHistoryService.list(&state.pool, &agent_id, query.kind).await?))
+}
+
+pub async fn get_agent_config_version(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((agent_id, version)): Path<(String, i64)>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
+    Ok(Json(ConfigHistoryService.snapshot(&state.pool, &agent_id, version).await?))
+}
+
+pub async fn diff_agent_config_versions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(agent_id): Path<String>,
+    Query(query): Query<DiffQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
+    Ok(Json(ConfigHistoryService.diff(&state.pool, &agent_id, query.from, query.to).await?))
+}
+
+pub async fn rollback_agent_config(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((agent_id, version)): Path<(String, i64)>,
+    payload: Option<Json<RollbackRequest>>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&state.pool, &headers, "agents", "write").await?;
+    let request = payload.map(|Json(request)| request).unwrap_or_default();
+    let result = ConfigHistoryService
+        .rollback(&state.pool, &state.cache, &state.manager, &agent_id, version, request, &user.id)
+        .await?;
+    Ok(Json(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::common::{create_test_admin_user, create_test_pool, insert_test_agent};
+
+    #[test]
+    fn diff_json_reports_each_changed_leaf() {
+        let old = serde_json::json!({
+            "model": { "primary": "claude-3-sonnet", "fallbacks": ["gpt-4", "gpt-3.5"] },
+            "params": { "temperature": 0.7, "a/b": 1 },
+            "skills": null,
+            "workspace": "/srv/agent"
+        });
+        let new = serde_json::json!({
+            "model": { "primary": "gpt-4", "fallbacks": ["gpt-4"] },
+            "params": { "temperature": 0.7, "a/b": 2 },
+            "skills": ["research"]
+        });
+
+        let changes: Vec<(String, FieldChangeKind)> = diff_json(&old, &new).into_iter().map(|c| (c.path, c.change)).collect();
+        assert_eq!(changes, vec![
+            ("/model/fallbacks/1".to_string(), FieldChangeKind::Removed),
+            ("/model/primary".to_string(), FieldChangeKind::Changed),
+            ("/params/a~1b".to_string(), FieldChangeKind::Changed),
+            // A null field counts as absent
+            ("/skills".to_string(), FieldChangeKind::Added),
+            ("/workspace".to_string(), FieldChangeKind::Removed),
+        ]);
+        assert!(diff_json(&old, &old).is_empty());
+
+        let change = &diff_json(&old, &new)[1];
+        assert_eq!(change.old, Some(serde_json::json!("claude-3-sonnet")));
+        assert_eq!(change.new, Some(serde_json::json!("gpt-4")));
+    }
+
+    #[tokio::test]
+    async fn a_rollback_is_recorded_as_the_newest_version() {
+        let pool = create_test_pool().await;
+        let cache = HierarchicalCache::new(10, 10);
+        let manager = ConnectionManager::new();
+        let agent_id = insert_test_agent(&pool, "Versioned Agent").await;
+        let (user, _) = create_test_admin_user(&pool).await;
+        let config = |primary: &str| -> OpenClawAgentConfig {
+            serde_json::from_value(serde_json::json!({ "id": agent_id, "model": { "primary": primary } })).unwrap()
+        };
+        let change = ConfigChange::new(ChangeSource::Apply, Some(&user.id), None);
+        for primary in ["claude-3-sonnet", "gpt-4"] {
+            crate::openclaw_integration::apply_validated_agent_config(&pool, &cache, &agent_id, &config(primary), &change)
+                .await
+                .unwrap();
+        }
+
+        let service = ConfigHistoryService;
+        let result = service
+            .rollback(&pool, &cache, &manager, &agent_id, 1, RollbackRequest::default(), &user.id)
+            .await
+            .unwrap();
+        assert_eq!((result.restored_version, result.snapshot.version), (1, 3));
+        assert_eq!((result.snapshot.source.as_str(), result.snapshot.rolled_back_from), ("rollback", Some(1)));
+        assert_eq!(result.snapshot.config, service.snapshot(&pool, &agent_id, 1).await.unwrap().config);
+        let primary: Option<String> = sqlx::query_scalar("SELECT primary_model FROM agents WHERE id = ?")
+            .bind(&agent_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(primary.as_deref(), Some("claude-3-sonnet"));
+
+        let diff = service.diff(&pool, &agent_id, 2, None).await.unwrap();
+        assert_eq!((diff.to, diff.changes.len()), (3, 1));
+        assert_eq!(diff.changes[0].path, "/model/primary");
+
+        // Version 1 is now current, so rolling back to it again changes nothing
+        assert!(matches!(
+            service.rollback(&pool, &cache, &manager, &agent_id, 1, RollbackRequest::default(), &user.id).await,
+            Err(HistoryError::Conflict(_))
+        ));
+        assert!(matches!(
+            service.rollback(&pool, &cache, &manager, &agent_id, 9, RollbackRequest::default(), &user.id).await,
+            Err(HistoryError::VersionNotFound(..))
+        ));
+        let versions: Vec<i64> = service.list(&pool, &agent_id, None).await.unwrap().iter().map(|s| s.version).collect();
+        assert_eq!(versions, vec![3, 2, 1]);
+    }
+}
@@ -0,0 +1,707 @@
+use crate::db::SqlitePool;
+use crate::models::{OpenClawAgentConfig, SecurityLevel};
+use crate::agent_management::{AgentHealthStatus, HealthIssue, HealthTrend, IssueSeverity};
+use crate::budget::{BudgetLevel, BudgetService};
+use crate::openclaw_integration_helpers::validate_agent_config_internal;
+use crate::ConnectionManager;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::HashMap;
+use tracing::{debug, info};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+    response::IntoResponse,
+    http::{HeaderMap, StatusCode},
+};
+use crate::AppState;
+
+/// Days of score history kept per agent
+const HISTORY_RETENTION_DAYS: i64 = 30;
+/// Reviews needed before a rejection rate counts against an agent
+const MIN_REVIEWS_FOR_RATE: i64 = 3;
+
+/// What the scorer knows about one agent
+struct HealthInputs<'a> {
+    model_failure_count: i64,
+    open_tasks: i64,
+    stuck_tasks: i64,
+    reviews: i64,
+    rejected_reviews: i64,
+    /// `None` when openclaw.json could not be read, so configuration is not judged
+    openclaw_config: Option<Option<&'a OpenClawAgentConfig>>,
+    budget_level: BudgetLevel,
+}
+
+fn issue(
+    id: &str,
+    category: &str,
+    severity: IssueSeverity,
+    title: &str,
+    description: String,
+    auto_resolvable: bool,
+    resolution_steps: &[&str],
+) -> HealthIssue {
+    HealthIssue {
+        id: id.to_string(),
+        category: category.to_string(),
+        severity,
+        title: title.to_string(),
+        description,
+        detected_at: Utc::now(),
+        auto_resolvable,
+        resolution_steps: resolution_steps.iter().map(|s| s.to_string()).collect(),
+    }
+}
+
+fn score(inputs: &HealthInputs, previous: Option<f64>) -> AgentHealthStatus {
+    let mut issues = Vec::new();
+
+    let mut performance: f64 = 100.0;
+    if inputs.model_failure_count > 0 {
+        performance -= (inputs.model_failure_count as f64 * 5.0).min(50.0);
+        if inputs.model_failure_count >= 3 {
+            issues.push(issue(
+                "model_failures",
+                "performance",
+                if inputs.model_failure_count >= 10 { IssueSeverity::Critical } else { IssueSeverity::Warning },
+                "Repeated model failures",
+                format!("The current model has failed {} times", inputs.model_failure_count),
+                false,
+                &["Check the provider status and API key", "Configure or switch to a fallback model"],
+            ));
+        }
+    }
+    if inputs.open_tasks > 0 && inputs.stuck_tasks > 0 {
+        let stuck_rate = inputs.stuck_tasks as f64 / inputs.open_tasks as f64;
+        performance -= stuck_rate * 30.0;
+        issues.push(issue(
+            "stuck_tasks",
+            "performance",
+            if stuck_rate >= 0.5 { IssueSeverity::Warning } else { IssueSeverity::Info },
+            "Stuck tasks",
+            format!("{} of {} open tasks have not moved within their priority's limit", inputs.stuck_tasks, inputs.open_tasks),
+            false,
+            &["Route or reassign the stuck tasks", "Check that the agent's session is running"],
+        ));
+    }
+    if inputs.reviews >= MIN_REVIEWS_FOR_RATE {
+        let rejection_rate = inputs.rejected_reviews as f64 / inputs.reviews as f64;
+        performance -= rejection_rate * 40.0;
+        if rejection_rate >= 0.25 {
+            issues.push(issue(
+                "review_rejections",
+                "performance",
+                if rejection_rate >= 0.5 { IssueSeverity::Critical } else { IssueSeverity::Warning },
+                "High review rejection rate",
+                format!("{} of {} reviews in the last 30 days were rejected", inputs.rejected_reviews, inputs.reviews),
+                false,
+                &["Read the reviewers' feedback", "Refine the agent's instructions or model"],
+            ));
+        }
+    }
+
+    let mut configuration: f64 = 100.0;
+    let mut security: f64 = 100.0;
+    match inputs.openclaw_config {
+        None => {}
+        Some(None) => {
+            configuration = 70.0;
+            issues.push(issue(
+                "not_in_openclaw",
+                "configuration",
+                IssueSeverity::Warning,
+                "Not configured in OpenClaw",
+                "The agent has no entry in openclaw.json".to_string(),
+                false,
+                &["Add the agent to openclaw.json or import it from OpenClaw"],
+            ));
+        }
+        Some(Some(config)) => {
+            if let Err(e) = validate_agent_config_internal(config) {
+                configuration -= 60.0;
+                issues.push(issue(
+                    "invalid_configuration",
+                    "configuration",
+                    IssueSeverity::Critical,
+                    "Configuration does not validate",
+                    e,
+                    false,
+                    &["Fix the agent's entry in openclaw.json"],
+                ));
+            }
+            if config.model.as_ref().and_then(|m| m.fallbacks.as_ref()).is_none_or(|f| f.is_empty()) {
+                configuration -= 10.0;
+                issues.push(issue(
+                    "no_fallback_model",
+                    "configuration",
+                    IssueSeverity::Info,
+                    "No fallback model",
+                    "Model failures cannot fail over".to_string(),
+                    true,
+                    &["Add at least one fallback model"],
+                ));
+            }
+
+            let sandbox_mode = config.sandbox.as_ref().and_then(|s| s.mode.as_deref()).unwrap_or("off");
+            if sandbox_mode == "off" {
+                security -= 30.0;
+                issues.push(issue(
+                    "sandbox_disabled",
+                    "security",
+                    IssueSeverity::Warning,
+                    "Sandbox disabled",
+                    "Tools run directly on the host".to_string(),
+                    true,
+                    &["Set sandbox.mode to 'on' or 'docker'"],
+                ));
+            }
+            if let Some(tools) = &config.tools {
+                if let Some(exec) = tools.exec.as_ref().filter(|e| e.enabled.unwrap_or(true))
+                    && exec.safe_bins.as_ref().is_none_or(|bins| bins.is_empty()) {
+                        security -= 20.0;
+                        issues.push(issue(
+                            "unrestricted_exec",
+                            "security",
+                            IssueSeverity::Warning,
+                            "Unrestricted command execution",
+                            "The exec tool is enabled without a safe_bins allow list".to_string(),
+                            false,
+                            &["List the binaries the agent needs in tools.exec.safe_bins"],
+                        ));
+                    }
+                if let Some(file_ops) = tools.file_ops.as_ref().filter(|f| f.enabled.unwrap_or(true)) {
+                    let writes_everywhere = file_ops
+                        .write_paths
+                        .as_ref()
+                        .is_some_and(|paths| paths.iter().any(|p| p == "/" || p == "~" || p == "*"));
+                    if writes_everywhere {
+                        security -= 20.0;
+                        issues.push(issue(
+                            "unrestricted_writes",
+                            "security",
+                            IssueSeverity::Warning,
+                            "Unrestricted file writes",
+                            "tools.file_ops.write_paths allows writing anywhere".to_string(),
+                            false,
+                            &["Limit write_paths to the agent's workspace"],
+                        ));
+                    }
+                }
+                if let Some(web) = tools.web.as_ref().filter(|w| w.enabled.unwrap_or(true))
+                    && web.allow_domains.as_ref().is_none_or(|d| d.is_empty()) {
+                        security -= 10.0;
+                        issues.push(issue(
+                            "unrestricted_web",
+                            "security",
+                            IssueSeverity::Info,
+                            "Unrestricted web access",
+                            "The web tool has no allow_domains list".to_string(),
+                            false,
+                            &["List the domains the agent needs in tools.web.allow_domains"],
+                        ));
+                    }
+            }
+        }
+    }
+
+    let resource = match inputs.budget_level {
+        BudgetLevel::Exceeded => {
+            issues.push(issue(
+                "budget_exceeded",
+                "resource",
+                IssueSeverity::Critical,
+                "Budget exceeded",
+                "A spend cap has been reached".to_string(),
+                false,
+                &["Review the agent's spend", "Grant a budget override if the work must continue"],
+            ));
+            30.0
+        }
+        BudgetLevel::Warning => {
+            issues.push(issue(
+                "budget_warning",
+                "resource",
+                IssueSeverity::Warning,
+                "Budget nearly spent",
+                "A spend cap is past its warning threshold".to_string(),
+                false,
+                &["Review the agent's spend"],
+            ));
+            70.0
+        }
+        BudgetLevel::Ok => 100.0,
+    };
+
+    let performance = performance.clamp(0.0, 100.0);
+    let configuration = configuration.clamp(0.0, 100.0);
+    let security = security.clamp(0.0, 100.0);
+    let overall = (performance * 0.4 + configuration * 0.2 + security * 0.2 + resource * 0.2).clamp(0.0, 100.0);
+
+    let health_trend = match previous {
+        None => HealthTrend::Unknown,
+        Some(previous) if overall - previous >= 5.0 => HealthTrend::Improving,
+        Some(previous) if previous - overall >= 5.0 => HealthTrend::Declining,
+        Some(_) => HealthTrend::Stable,
+    };
+
+    AgentHealthStatus {
+        overall_health: overall,
+        performance_health: performance,
+        configuration_health: configuration,
+        security_health: security,
+        resource_health: resource,
+        last_check: Utc::now(),
+        health_trend,
+        issues,
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct HealthRow {
+    agent_id: String,
+    agent_name: String,
+    agent_status: String,
+    overall_health: Option<f64>,
+    performance_health: Option<f64>,
+    configuration_health: Option<f64>,
+    security_health: Option<f64>,
+    resource_health: Option<f64>,
+    health_trend: Option<String>,
+    last_check: DateTime<Utc>,
+    issues: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AgentHealthView {
+    pub agent_id: String,
+    pub agent_name: String,
+    pub agent_status: String,
+    pub degraded: bool,
+    /// Whether `route_task` still sends work to the agent
+    pub routable: bool,
+    #[serde(flatten)]
+    pub health: AgentHealthStatus,
+}
+
+impl From<HealthRow> for AgentHealthView {
+    fn from(row: HealthRow) -> Self {
+        let runtime = crate::config::current();
+        let overall_health = row.overall_health.unwrap_or(0.0);
+        Self {
+            degraded: overall_health < runtime.health_degraded_threshold,
+            routable: overall_health >= runtime.health_critical_threshold,
+            health: AgentHealthStatus {
+                overall_health,
+                performance_health: row.performance_health.unwrap_or(0.0),
+                configuration_health: row.configuration_health.unwrap_or(0.0),
+                security_health: row.security_health.unwrap_or(0.0),
+                resource_health: row.resource_health.unwrap_or(0.0),
+                last_check: row.last_check,
+                health_trend: row
+                    .health_trend
+                    .and_then(|t| serde_json::from_value(serde_json::Value::String(t)).ok())
+                    .unwrap_or(HealthTrend::Unknown),
+                issues: row.issues.and_then(|i| serde_json::from_str(&i).ok()).unwrap_or_default(),
+            },
+            agent_id: row.agent_id,
+            agent_name: row.agent_name,
+            agent_status: row.agent_status,
+        }
+    }
+}
+
+const HEALTH_ROW_COLUMNS: &str = "h.agent_id, a.name AS agent_name, a.status AS agent_status,
+    h.overall_health, h.performance_health, h.configuration_health, h.security_health,
+    h.resource_health, h.health_trend, h.last_check, h.issues";
+
+#[derive(Debug, Default, Serialize)]
+pub struct HealthRunSummary {
+    pub agents_scored: u64,
+    pub degraded: u64,
+    pub newly_degraded: u64,
+    pub recovered: u64,
+}
+
+/// How the latest score affects routing work to an agent
+pub enum RoutingHealth {
+    Unscored,
+    Healthy,
+    Degraded(f64),
+    Critical(f64),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HealthListQuery {
+    pub degraded: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HealthHistoryQuery {
+    pub limit: Option<i64>,
+}
+
+pub struct AgentHealthService;
+
+impl AgentHealthService {
+    /// Scores every agent, stores the result as history and on the agent row,
+    /// and announces agents crossing the degraded threshold in either direction.
+    /// Only tasks visible at `clearance` are counted, since the stored issues are
+    /// readable by anyone who can read agent health.
+    pub async fn score_all(&self, pool: &SqlitePool, manager: &ConnectionManager, clearance: SecurityLevel) -> Result<HealthRunSummary, anyhow::Error> {
+        let openclaw_configs: Option<HashMap<String, OpenClawAgentConfig>> =
+            match crate::openclaw_integration::read_and_parse_openclaw_config().await {
+                Ok(configs) => Some(configs.into_iter().map(|c| (c.id.clone(), c)).collect()),
+                Err(e) => {
+                    debug!("Scoring agent health without OpenClaw configuration: {}", e);
+                    None
+                }
+            };
+        let runtime = crate::config::current();
+        let limits = &runtime.monitoring;
+        let degraded_threshold = runtime.health_degraded_threshold;
+
+        let agents = sqlx::query_as::<sqlx::Sqlite, (String, i64)>(
+            "SELECT id, COALESCE(model_failure_count, 0) FROM agents WHERE COALESCE(is_deleted, 0) = 0"
+        )
+        .fetch_all(pool)
+        .await?;
+
+        // Stuck means the same as for the stuck task monitor
+        let task_counts: HashMap<String, (i64, i64)> = sqlx::query_as::<sqlx::Sqlite, (String, i64, i64)>(&format!(
+            "SELECT assignee_id, COUNT(*),
+                    COALESCE(SUM(CASE WHEN status IN ('INBOX', 'ASSIGNED')
+                        AND updated_at < datetime('now', '-' || CASE WHEN priority IN ('URGENT', 'CRITICAL') THEN ? ELSE ? END || ' minutes')
+                        THEN 1 ELSE 0 END), 0)
+             FROM tasks
+             WHERE assignee_id IS NOT NULL AND status != 'DONE' AND classification IN ({})
+             GROUP BY assignee_id",
+            crate::classification::visible_levels_sql(clearance)
+        ))
+        .bind(limits.urgent_priority_limit_minutes as i64)
+        .bind(limits.normal_priority_limit_minutes as i64)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|(agent_id, open, stuck)| (agent_id, (open, stuck)))
+        .collect();
+
+        let review_counts: HashMap<String, (i64, i64)> = sqlx::query_as::<sqlx::Sqlite, (String, i64, i64)>(
+            "SELECT agent_id, COUNT(*), COALESCE(SUM(CASE WHEN outcome = 'rejected' THEN 1 ELSE 0 END), 0)
+             FROM task_reviews
+             WHERE agent_id IS NOT NULL AND reviewed_at >= datetime('now', '-30 days')
+             GROUP BY agent_id"
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|(agent_id, reviews, rejected)| (agent_id, (reviews, rejected)))
+        .collect();
+
+        let previous_scores: HashMap<String, f64> = sqlx::query_as::<sqlx::Sqlite, (String, f64)>(
+            "SELECT agent_id, overall_health FROM (
+                 SELECT agent_id, overall_health,
+                        ROW_NUMBER() OVER (PARTITION BY agent_id ORDER BY last_check DESC, rowid DESC) AS rn
+                 FROM agent_health_status WHERE overall_health IS NOT NULL
+             ) WHERE rn = 1"
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect();
+
+        let mut summary = HealthRunSummary::default();
+        for (agent_id, model_failure_count) in agents {
+            let (open_tasks, stuck_tasks) = task_counts.get(&agent_id).copied().unwrap_or((0, 0));
+            let (reviews, rejected_reviews) = review_counts.get(&agent_id).copied().unwrap_or((0, 0));
+            let budget_level = BudgetService
+                .evaluate(pool, &agent_id)
+                .await?
+                .map(|evaluation| {
+                    let levels: Vec<BudgetLevel> = evaluation.statuses.iter().map(|s| s.level).collect();
+                    if levels.contains(&BudgetLevel::Exceeded) {
+                        BudgetLevel::Exceeded
+                    } else if levels.contains(&BudgetLevel::Warning) {
+                        BudgetLevel::Warning
+                    } else {
+                        BudgetLevel::Ok
+                    }
+                })
+                .unwrap_or(BudgetLevel::Ok);
+
+            let inputs = HealthInputs {
+                model_failure_count,
+                open_tasks,
+                stuck_tasks,
+                reviews,
+                rejected_reviews,
+                openclaw_config: openclaw_configs.as_ref().map(|configs| configs.get(&agent_id)),
+                budget_level,
+            };
+            let previous = previous_scores.get(&agent_id).copied();
+            let health = score(&inputs, previous);
+
+            sqlx::query(
+                "INSERT INTO agent_health_status
+                 (id, agent_id, overall_health, performance_health, configuration_health,
+                  security_health, resource_health, health_trend, issues)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+            )
+            .bind(uuid::Uuid::new_v4().to_string())
+            .bind(&agent_id)
+            .bind(health.overall_health)
+            .bind(health.performance_health)
+            .bind(health.configuration_health)
+            .bind(health.security_health)
+            .bind(health.resource_health)
+            .bind(format!("{:?}", health.health_trend))
+            .bind(serde_json::to_string(&health.issues)?)
+            .execute(pool)
+            .await?;
+
+            sqlx::query("UPDATE agents SET health_score = ?, last_health_check = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(health.overall_health)
+                .bind(&agent_id)
+                .execute(pool)
+                .await?;
+
+            summary.agents_scored += 1;
+            let degraded = health.overall_health < degraded_threshold;
+            let was_degraded = previous.is_some_and(|p| p < degraded_threshold);
+            if degraded {
+                summary.degraded += 1;
+            }
+            if degraded && !was_degraded {
+                summary.newly_degraded += 1;
+                manager.broadcast(&serde_json::json!({
+                    "type": "agent_health_degraded",
+                    "agent_id": agent_id,
+                    "overall_health": health.overall_health,
+                    "issues": health.issues.len(),
+                }).to_string());
+            } else if !degraded && was_degraded {
+                summary.recovered += 1;
+                manager.broadcast(&serde_json::json!({
+                    "type": "agent_health_recovered",
+                    "agent_id": agent_id,
+                    "overall_health": health.overall_health,
+                }).to_string());
+            }
+        }
+
+        sqlx::query("DELETE FROM agent_health_status WHERE last_check < datetime('now', ?)")
+            .bind(format!("-{} days", HISTORY_RETENTION_DAYS))
+            .execute(pool)
+            .await?;
+
+        if summary.newly_degraded + summary.recovered > 0 {
+            info!(
+                "Agent health: {} scored, {} degraded ({} newly), {} recovered",
+                summary.agents_scored, summary.degraded, summary.newly_degraded, summary.recovered
+            );
+        }
+        Ok(summary)
+    }
+
+    /// Latest score of every agent, least healthy first
+    pub async fn latest(&self, pool: &SqlitePool) -> Result<Vec<AgentHealthView>, sqlx::Error> {
+        let rows = sqlx::query_as::<sqlx::Sqlite, HealthRow>(&format!(
+            "SELECT {} FROM (
+                 SELECT *, ROW_NUMBER() OVER (PARTITION BY agent_id ORDER BY last_check DESC, rowid DESC) AS rn
+                 FROM agent_health_status
+             ) h
+             JOIN agents a ON a.id = h.agent_id
+             WHERE h.rn = 1
+             ORDER BY h.overall_health ASC",
+            HEALTH_ROW_COLUMNS
+        ))
+        .fetch_all(pool)
+        .await?;
+        Ok(rows.into_iter().map(AgentHealthView::from).collect())
+    }
+
+    /// Scores of one agent, newest first
+    pub async fn history(&self, pool: &SqlitePool, agent_id: &str, limit: i64) -> Result<Vec<AgentHealthView>, sqlx::Error> {
+        let rows = sqlx::query_as::<sqlx::Sqlite, HealthRow>(&format!(
+            "SELECT {} FROM agent_health_status h
+             JOIN agents a ON a.id = h.agent_id
+             WHERE h.agent_id = ?
+             ORDER BY h.last_check DESC, h.rowid DESC
+             LIMIT ?",
+            HEALTH_ROW_COLUMNS
+        ))
+        .bind(agent_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+        Ok(rows.into_iter().map(AgentHealthView::from).collect())
+    }
+
+    pub async fn routing_health(&self, pool: &SqlitePool, agent_id: &str) -> Result<RoutingHealth, sqlx::Error> {
+        let score: Option<Option<f64>> = sqlx::query_scalar("SELECT health_score FROM agents WHERE id = ?")
+            .bind(agent_id)
+            .fetch_optional(pool)
+            .await?;
+        let runtime = crate::config::current();
+
+        Ok(match score.flatten() {
+            None => RoutingHealth::Unscored,
+            Some(score) if score < runtime.health_critical_threshold => RoutingHealth::Critical(score),
+            Some(score) if score < runtime.health_degraded_threshold => RoutingHealth::Degraded(score),
+            Some(_) => RoutingHealth::Healthy,
+        })
+    }
+}
+
+// Axum Handlers
+pub async fn list_agent_health(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<HealthListQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "monitoring", "read").await?;
+    let mut agents = AgentHealthService.latest(&state.pool).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if let Some(degraded) = query.degraded {
+        agents.retain(|agent| agent.degraded == degraded);
+    }
+    Ok(Json(agents))
+}
+
+pub async fn run_agent_health_check(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "admin").await?;
+    // Scored at the shared clearance, not the caller's, because the results are stored
+    AgentHealthService.score_all(&state.pool, &state.manager, crate::classification::anonymous_clearance()).await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn get_agent_health_history(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(agent_id): Path<String>,
+    Query(query): Query<HealthHistoryQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
+    let limit = query.limit.unwrap_or(48).clamp(1, 500);
+    AgentHealthService.history(&state.pool, &agent_id, limit).await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::common::{create_test_pool, insert_test_agent};
+
+    /// A healthy agent whose openclaw.json could not be read
+    fn inputs() -> HealthInputs<'static> {
+        HealthInputs {
+            model_failure_count: 0,
+            open_tasks: 0,
+            stuck_tasks: 0,
+            reviews: 0,
+            rejected_reviews: 0,
+            openclaw_config: None,
+            budget_level: BudgetLevel::Ok,
+        }
+    }
+
+    fn issue_ids(health: &AgentHealthStatus) -> Vec<&str> {
+        health.issues.iter().map(|issue| issue.id.as_str()).collect()
+    }
+
+    #[test]
+    fn overall_score_weights_performance_twice_as_much_as_the_rest() {
+        let health = score(&HealthInputs {
+            model_failure_count: 2,
+            openclaw_config: Some(None),
+            budget_level: BudgetLevel::Warning,
+            ..inputs()
+        }, None);
+
+        assert_eq!(health.performance_health, 90.0);
+        assert_eq!(health.configuration_health, 70.0);
+        assert_eq!(health.security_health, 100.0);
+        assert_eq!(health.resource_health, 70.0);
+        assert!((health.overall_health - 84.0).abs() < 1e-9);
+        assert_eq!(issue_ids(&health), ["not_in_openclaw", "budget_warning"]);
+    }
+
+    #[test]
+    fn component_scores_are_clamped_to_zero() {
+        let health = score(&HealthInputs {
+            model_failure_count: 40,
+            open_tasks: 2,
+            stuck_tasks: 2,
+            reviews: 10,
+            rejected_reviews: 10,
+            budget_level: BudgetLevel::Exceeded,
+            ..inputs()
+        }, None);
+
+        // 100 - 50 for failures - 30 for stuck tasks - 40 for rejections
+        assert_eq!(health.performance_health, 0.0);
+        assert!((health.overall_health - 46.0).abs() < 1e-9);
+        assert_eq!(issue_ids(&health), ["model_failures", "stuck_tasks", "review_rejections", "budget_exceeded"]);
+        assert!(health.issues.iter().all(|issue| matches!(issue.severity, IssueSeverity::Critical | IssueSeverity::Warning)));
+    }
+
+    #[test]
+    fn a_clean_agent_scores_full_marks() {
+        let health = score(&HealthInputs { reviews: 2, rejected_reviews: 2, ..inputs() }, None);
+
+        // Too few reviews for a rejection rate to count
+        assert_eq!(health.overall_health, 100.0);
+        assert!(health.issues.is_empty());
+    }
+
+    #[test]
+    fn trend_needs_a_move_of_five_points() {
+        let trend = |inputs: &HealthInputs, previous| score(inputs, previous).health_trend;
+        let healthy = inputs();
+        // Resource drops to 30, so the overall score is 86
+        let over_budget = HealthInputs { budget_level: BudgetLevel::Exceeded, ..inputs() };
+
+        assert!(matches!(trend(&healthy, None), HealthTrend::Unknown));
+        assert!(matches!(trend(&healthy, Some(95.0)), HealthTrend::Improving));
+        assert!(matches!(trend(&healthy, Some(95.5)), HealthTrend::Stable));
+        assert!(matches!(trend(&healthy, Some(100.0)), HealthTrend::Stable));
+        assert!(matches!(trend(&over_budget, Some(91.5)), HealthTrend::Declining));
+        assert!(matches!(trend(&over_budget, Some(90.5)), HealthTrend::Stable));
+    }
+
+    #[tokio::test]
+    async fn routing_health_classifies_scores_against_the_thresholds() {
+        let pool = create_test_pool().await;
+        let agent_id = insert_test_agent(&pool, "Routed Agent").await;
+        let runtime = crate::config::current();
+        let classify = |score: Option<f64>| {
+            let pool = pool.clone();
+            let agent_id = agent_id.clone();
+            async move {
+                sqlx::query("UPDATE agents SET health_score = ? WHERE id = ?")
+                    .bind(score)
+                    .bind(&agent_id)
+                    .execute(&pool)
+                    .await
+                    .unwrap();
+                AgentHealthService.routing_health(&pool, &agent_id).await.unwrap()
+            }
+        };
+
+        assert!(matches!(classify(None).await, RoutingHealth::Unscored));
+        assert!(matches!(classify(Some(runtime.health_critical_threshold - 1.0)).await, RoutingHealth::Critical(_)));
+        assert!(matches!(classify(Some(runtime.health_critical_threshold)).await, RoutingHealth::Degraded(_)));
+        assert!(matches!(classify(Some(runtime.health_degraded_threshold - 1.0)).await, RoutingHealth::Degraded(_)));
+        assert!(matches!(classify(Some(runtime.health_degraded_threshold)).await, RoutingHealth::Healthy));
+        assert!(matches!(
+            AgentHealthService.routing_health(&pool, "missing").await.unwrap(),
+            RoutingHealth::Unscored
+        ));
+    }
+}
@@ -0,0 +1,1056 @@
+use crate::db::SqlitePool;
+use crate::agent_config_history::{ChangeSource, ConfigChange, ConfigHistoryService, ConfigKind};
+use crate::agent_templates::{TemplateCatalogueService, TemplateError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{FromRow, SqliteConnection};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{RwLock, Semaphore};
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+    response::IntoResponse,
+    http::{HeaderMap, StatusCode},
+};
+use crate::AppState;
+
+/// Most agents a single job may target
+const MAX_JOB_AGENTS: usize = 500;
+/// `optimize` applies the auto-applicable concurrency recommendation: agents
+/// allowed fewer than this many concurrent tasks are raised to the target
+const OPTIMIZE_MIN_CONCURRENT_TASKS: u64 = 5;
+const OPTIMIZE_TARGET_CONCURRENT_TASKS: u64 = 10;
+
+#[derive(Debug)]
+pub enum JobError {
+    JobNotFound(String),
+    Invalid(String),
+    Conflict(String),
+    Internal(String),
+}
+
+impl std::fmt::Display for JobError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            JobError::JobNotFound(id) => write!(f, "Bulk job '{}' not found", id),
+            JobError::Invalid(msg) | JobError::Conflict(msg) => write!(f, "{}", msg),
+            JobError::Internal(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<sqlx::Error> for JobError {
+    fn from(error: sqlx::Error) -> Self {
+        JobError::Internal(format!("Database error: {}", error))
+    }
+}
+
+impl From<serde_json::Error> for JobError {
+    fn from(error: serde_json::Error) -> Self {
+        JobError::Internal(error.to_string())
+    }
+}
+
+impl From<JobError> for (StatusCode, String) {
+    fn from(error: JobError) -> Self {
+        let status = match &error {
+            JobError::JobNotFound(_) => StatusCode::NOT_FOUND,
+            JobError::Invalid(_) => StatusCode::BAD_REQUEST,
+            JobError::Conflict(_) => StatusCode::CONFLICT,
+            JobError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, error.to_string())
+    }
+}
+
+fn db_error(error: sqlx::Error) -> String {
+    format!("Database error: {}", error)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkOperation {
+    Enable,
+    Disable,
+    Reset,
+    Optimize,
+    Validate,
+    ApplyTemplate,
+}
+
+impl BulkOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BulkOperation::Enable => "enable",
+            BulkOperation::Disable => "disable",
+            BulkOperation::Reset => "reset",
+            BulkOperation::Optimize => "optimize",
+            BulkOperation::Validate => "validate",
+            BulkOperation::ApplyTemplate => "apply_template",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "enable" => Some(BulkOperation::Enable),
+            "disable" => Some(BulkOperation::Disable),
+            "reset" => Some(BulkOperation::Reset),
+            "optimize" => Some(BulkOperation::Optimize),
+            "validate" => Some(BulkOperation::Validate),
+            "apply_template" => Some(BulkOperation::ApplyTemplate),
+            _ => None,
+        }
+    }
+
+    /// Performs the operation on one agent. The returned previous state is what
+    /// `undo` restores; operations that changed nothing return none. Configuration
+    /// changes are recorded in the agent's history as `change`.
+    async fn apply(
+        &self,
+        pool: &SqlitePool,
+        agent_id: &str,
+        parameters: &HashMap<String, Value>,
+        change: &ConfigChange,
+    ) -> Result<AgentOutcome, String> {
+        let status = agent_status(pool, agent_id).await?;
+        match self {
+            BulkOperation::Enable | BulkOperation::Disable => {
+                let target = match self {
+                    BulkOperation::Enable if matches!(status.as_str(), "OFFLINE" | "SUSPENDED") => "IDLE",
+                    BulkOperation::Disable if status != "OFFLINE" => "OFFLINE",
+                    _ => return Ok(AgentOutcome::unchanged(format!("Already {}", status.to_lowercase()))),
+                };
+                sqlx::query("UPDATE agents SET status = ? WHERE id = ?")
+                    .bind(target)
+                    .bind(agent_id)
+                    .execute(pool)
+                    .await
+                    .map_err(db_error)?;
+                Ok(AgentOutcome::changed(
+                    format!("{} -> {}", status, target),
+                    serde_json::json!({ "status": status }),
+                ))
+            }
+            BulkOperation::Reset => {
+                let Some(config) = comprehensive_config(pool, agent_id).await? else {
+                    return Ok(AgentOutcome::unchanged("No configuration to reset".to_string()));
+                };
+                store_config(pool, agent_id, None, change).await?;
+                Ok(AgentOutcome::changed(
+                    "Configuration reset".to_string(),
+                    serde_json::json!({ "config_json": config }),
+                ))
+            }
+            BulkOperation::Optimize => {
+                let previous = comprehensive_config(pool, agent_id)
+                    .await?
+                    .ok_or_else(|| "Agent has no configuration to optimize".to_string())?;
+                let mut config: Value = serde_json::from_str(&previous).map_err(|e| format!("Stored configuration is not valid JSON: {}", e))?;
+                let Some(limit) = config["resource_limits"]["max_concurrent_tasks"].as_u64() else {
+                    return Err("Configuration has no resource_limits.max_concurrent_tasks".to_string());
+                };
+                if limit >= OPTIMIZE_MIN_CONCURRENT_TASKS {
+                    return Ok(AgentOutcome::unchanged("Already optimized".to_string()));
+                }
+                config["resource_limits"]["max_concurrent_tasks"] = OPTIMIZE_TARGET_CONCURRENT_TASKS.into();
+                store_config(pool, agent_id, Some(&config.to_string()), change).await?;
+                Ok(AgentOutcome::changed(
+                    format!("max_concurrent_tasks {} -> {}", limit, OPTIMIZE_TARGET_CONCURRENT_TASKS),
+                    serde_json::json!({ "config_json": previous }),
+                ))
+            }
+            BulkOperation::Validate => {
+                let config = comprehensive_config(pool, agent_id)
+                    .await?
+                    .ok_or_else(|| "Agent has no configuration to validate".to_string())?;
+                let config: crate::agent_management::AgentConfigRequest =
+                    serde_json::from_str(&config).map_err(|e| format!("Invalid configuration: {}", e))?;
+                let problems = configuration_problems(&config);
+                if !problems.is_empty() {
+                    return Err(problems.join("; "));
+                }
+                Ok(AgentOutcome::unchanged("Configuration is valid".to_string()))
+            }
+            BulkOperation::ApplyTemplate => {
+                let template_id = parameters
+                    .get("template_id")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| "Missing template_id parameter".to_string())?;
+                let template = TemplateCatalogueService.template(pool, template_id).await.map_err(|e| e.to_string())?;
+                let previous = comprehensive_config(pool, agent_id)
+                    .await?
+                    .ok_or_else(|| "Agent has no configuration to apply the template to".to_string())?;
+                let mut config: Value = serde_json::from_str(&previous).map_err(|e| format!("Stored configuration is not valid JSON: {}", e))?;
+                let (Some(target), Some(sections)) = (config.as_object_mut(), template.configuration.as_object()) else {
+                    return Err("Configurations must be JSON objects".to_string());
+                };
+                target.extend(sections.iter().map(|(key, value)| (key.clone(), value.clone())));
+
+                let relationship: Option<(String, i64)> = sqlx::query_as(
+                    "SELECT template_id, template_version FROM agent_template_relationships WHERE agent_id = ?"
+                )
+                .bind(agent_id)
+                .fetch_optional(pool)
+                .await
+                .map_err(db_error)?;
+
+                let mut tx = pool.begin().await.map_err(db_error)?;
+                write_config(&mut tx, agent_id, Some(&config.to_string()), change).await?;
+                sqlx::query(
+                    "INSERT INTO agent_template_relationships (id, template_id, agent_id, template_version)
+                     VALUES (lower(hex(randomblob(16))), ?, ?, ?)
+                     ON CONFLICT(agent_id) DO UPDATE SET template_id = excluded.template_id, template_version = excluded.template_version"
+                )
+                .bind(&template.id)
+                .bind(agent_id)
+                .bind(template.version)
+                .execute(&mut *tx)
+                .await
+                .map_err(db_error)?;
+                sqlx::query("UPDATE agent_templates SET usage_count = COALESCE(usage_count, 0) + 1 WHERE id = ?")
+                    .bind(&template.id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(db_error)?;
+                tx.commit().await.map_err(db_error)?;
+
+                Ok(AgentOutcome::changed(
+                    format!("Applied template {} v{}", template.id, template.version),
+                    serde_json::json!({
+                        "config_json": previous,
+                        "template_id": template.id,
+                        "relationship": relationship.map(|(template_id, template_version)| serde_json::json!({
+                            "template_id": template_id,
+                            "template_version": template_version,
+                        })),
+                    }),
+                ))
+            }
+        }
+    }
+
+    /// Restores what `apply` recorded as the agent's previous state
+    async fn undo(&self, pool: &SqlitePool, agent_id: &str, previous: &Value, change: &ConfigChange) -> Result<(), String> {
+        match self {
+            BulkOperation::Enable | BulkOperation::Disable => {
+                let status = previous["status"].as_str().ok_or_else(|| "No previous status recorded".to_string())?;
+                sqlx::query("UPDATE agents SET status = ? WHERE id = ?")
+                    .bind(status)
+                    .bind(agent_id)
+                    .execute(pool)
+                    .await
+                    .map_err(db_error)?;
+            }
+            BulkOperation::Reset | BulkOperation::Optimize => {
+                store_config(pool, agent_id, previous["config_json"].as_str(), change).await?;
+            }
+            BulkOperation::Validate => {}
+            BulkOperation::ApplyTemplate => {
+                let mut tx = pool.begin().await.map_err(db_error)?;
+                write_config(&mut tx, agent_id, previous["config_json"].as_str(), change).await?;
+                match previous["relationship"].as_object() {
+                    Some(relationship) => sqlx::query(
+                        "UPDATE agent_template_relationships SET template_id = ?, template_version = ? WHERE agent_id = ?"
+                    )
+                    .bind(relationship.get("template_id").and_then(Value::as_str))
+                    .bind(relationship.get("template_version").and_then(Value::as_i64))
+                    .bind(agent_id),
+                    None => sqlx::query("DELETE FROM agent_template_relationships WHERE agent_id = ?").bind(agent_id),
+                }
+                .execute(&mut *tx)
+                .await
+                .map_err(db_error)?;
+                sqlx::query("UPDATE agent_templates SET usage_count = MAX(COALESCE(usage_count, 0) - 1, 0) WHERE id = ?")
+                    .bind(previous["template_id"].as_str())
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(db_error)?;
+                tx.commit().await.map_err(db_error)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct AgentOutcome {
+    message: String,
+    previous: Option<Value>,
+}
+
+impl AgentOutcome {
+    fn changed(message: String, previous: Value) -> Self {
+        AgentOutcome { message, previous: Some(previous) }
+    }
+
+    fn unchanged(message: String) -> Self {
+        AgentOutcome { message, previous: None }
+    }
+}
+
+async fn agent_status(pool: &SqlitePool, agent_id: &str) -> Result<String, String> {
+    sqlx::query_scalar("SELECT status FROM agents WHERE id = ?")
+        .bind(agent_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| "Agent not found".to_string())
+}
+
+async fn comprehensive_config(pool: &SqlitePool, agent_id: &str) -> Result<Option<String>, String> {
+    sqlx::query_scalar("SELECT config_json FROM agent_comprehensive_configs WHERE agent_id = ?")
+        .bind(agent_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)
+}
+
+/// Writes the agent's comprehensive configuration, or removes it for `None`, and
+/// records the result in the agent's configuration history
+async fn store_config(pool: &SqlitePool, agent_id: &str, config: Option<&str>, change: &ConfigChange) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    write_config(&mut tx, agent_id, config, change).await?;
+    tx.commit().await.map_err(db_error)
+}
+
+/// `store_config` within the caller's transaction
+async fn write_config(conn: &mut SqliteConnection, agent_id: &str, config: Option<&str>, change: &ConfigChange) -> Result<(), String> {
+    match config {
+        Some(config) => sqlx::query(
+            "INSERT INTO agent_comprehensive_configs (agent_id, config_json) VALUES (?, ?)
+             ON CONFLICT(agent_id) DO UPDATE SET config_json = excluded.config_json, updated_at = CURRENT_TIMESTAMP"
+        )
+        .bind(agent_id)
+        .bind(config),
+        None => sqlx::query("DELETE FROM agent_comprehensive_configs WHERE agent_id = ?").bind(agent_id),
+    }
+    .execute(&mut *conn)
+    .await
+    .map_err(db_error)?;
+    ConfigHistoryService
+        .record(conn, agent_id, ConfigKind::Comprehensive, config.unwrap_or("null"), change)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn configuration_problems(config: &crate::agent_management::AgentConfigRequest) -> Vec<String> {
+    let mut problems = Vec::new();
+    let model = &config.model_config;
+    if model.primary_model.trim().is_empty() {
+        problems.push("primary_model is empty".to_string());
+    }
+    if model.fallback_models.contains(&model.primary_model) {
+        problems.push("primary_model is also listed as a fallback".to_string());
+    }
+    if let Some(temperature) = model.temperature
+        && !(0.0..=2.0).contains(&temperature) {
+            problems.push(format!("temperature {} is outside 0-2", temperature));
+        }
+    if model.max_tokens == Some(0) {
+        problems.push("max_tokens is 0".to_string());
+    }
+    if config.resource_limits.max_concurrent_tasks == 0 {
+        problems.push("max_concurrent_tasks is 0".to_string());
+    }
+    problems
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StartBulkJobRequest {
+    pub operation: BulkOperation,
+    pub agent_ids: Vec<String>,
+    /// `template_id` for `apply_template`
+    #[serde(default)]
+    pub parameters: HashMap<String, Value>,
+    /// Agents worked on at the same time, capped by `bulk_jobs.max_concurrency`
+    pub concurrency: Option<usize>,
+    /// Roll back every agent already changed when one fails or the job is cancelled
+    #[serde(default)]
+    pub all_or_nothing: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BulkJobQuery {
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, FromRow)]
+struct BulkJobRow {
+    id: String,
+    operation: String,
+    parameters: Option<String>,
+    all_or_nothing: bool,
+    concurrency: i64,
+    status: String,
+    total_agents: i64,
+    succeeded: i64,
+    failed: i64,
+    error: Option<String>,
+    requested_by: Option<String>,
+    created_at: Option<DateTime<Utc>>,
+    started_at: Option<DateTime<Utc>>,
+    finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkJob {
+    pub id: String,
+    pub operation: String,
+    pub parameters: HashMap<String, Value>,
+    pub all_or_nothing: bool,
+    pub concurrency: i64,
+    pub status: String,
+    pub total_agents: i64,
+    pub succeeded: i64,
+    pub failed: i64,
+    pub error: Option<String>,
+    pub requested_by: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<BulkJobRow> for BulkJob {
+    type Error = JobError;
+
+    fn try_from(row: BulkJobRow) -> Result<Self, Self::Error> {
+        Ok(BulkJob {
+            parameters: row.parameters.as_deref().map(serde_json::from_str).transpose()?.unwrap_or_default(),
+            id: row.id,
+            operation: row.operation,
+            all_or_nothing: row.all_or_nothing,
+            concurrency: row.concurrency,
+            status: row.status,
+            total_agents: row.total_agents,
+            succeeded: row.succeeded,
+            failed: row.failed,
+            error: row.error,
+            requested_by: row.requested_by,
+            created_at: row.created_at,
+            started_at: row.started_at,
+            finished_at: row.finished_at,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct BulkJobResult {
+    pub agent_id: String,
+    pub position: i64,
+    pub status: String,
+    pub message: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub duration_ms: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkJobDetail {
+    #[serde(flatten)]
+    pub job: BulkJob,
+    pub results: Vec<BulkJobResult>,
+}
+
+/// Cancellation handles for the jobs running in this process
+#[derive(Default)]
+pub struct BulkJobRegistry {
+    running: RwLock<HashMap<String, CancellationToken>>,
+}
+
+impl BulkJobRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    async fn register(&self, job_id: &str, token: CancellationToken) {
+        self.running.write().await.insert(job_id.to_string(), token);
+    }
+
+    async fn remove(&self, job_id: &str) {
+        self.running.write().await.remove(job_id);
+    }
+
+    async fn cancel(&self, job_id: &str) -> bool {
+        match self.running.read().await.get(job_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+pub struct AgentJobService;
+
+impl AgentJobService {
+    /// Records the job and its agents, then runs it in the background
+    pub async fn start(&self, state: &AppState, request: StartBulkJobRequest, user_id: &str) -> Result<BulkJob, JobError> {
+        let pool = &state.pool;
+        let mut seen = HashSet::new();
+        let agent_ids: Vec<String> = request
+            .agent_ids
+            .iter()
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty() && seen.insert(id.clone()))
+            .collect();
+        if agent_ids.is_empty() {
+            return Err(JobError::Invalid("agent_ids must name at least one agent".to_string()));
+        }
+        if agent_ids.len() > MAX_JOB_AGENTS {
+            return Err(JobError::Invalid(format!("A job can target at most {} agents", MAX_JOB_AGENTS)));
+        }
+        if request.operation == BulkOperation::ApplyTemplate {
+            let template_id = request
+                .parameters
+                .get("template_id")
+                .and_then(Value::as_str)
+                .ok_or_else(|| JobError::Invalid("apply_template needs a template_id parameter".to_string()))?;
+            TemplateCatalogueService.template(pool, template_id).await.map_err(|e| match e {
+                TemplateError::TemplateNotFound(id) => JobError::Invalid(format!("Template '{}' not found", id)),
+                other => JobError::Internal(other.to_string()),
+            })?;
+        }
+        let limit = crate::config::current().bulk_job_max_concurrency.max(1);
+        let concurrency = match request.concurrency {
+            Some(0) => return Err(JobError::Invalid("concurrency must be at least 1".to_string())),
+            Some(requested) => requested.min(limit),
+            None => limit,
+        };
+
+        let job_id = uuid::Uuid::new_v4().to_string();
+        let mut tx = pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO agent_bulk_jobs (id, operation, parameters, all_or_nothing, concurrency, total_agents, requested_by)
+             VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&job_id)
+        .bind(request.operation.as_str())
+        .bind(serde_json::to_string(&request.parameters)?)
+        .bind(request.all_or_nothing)
+        .bind(concurrency as i64)
+        .bind(agent_ids.len() as i64)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        for (position, agent_id) in agent_ids.iter().enumerate() {
+            sqlx::query("INSERT INTO agent_bulk_job_results (job_id, agent_id, position) VALUES (?, ?, ?)")
+                .bind(&job_id)
+                .bind(agent_id)
+                .bind(position as i64)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        let token = CancellationToken::new();
+        state.bulk_jobs.register(&job_id, token.clone()).await;
+        let job = self.job(pool, &job_id).await?;
+        info!("Bulk {} job {} queued for {} agents", request.operation.as_str(), job_id, agent_ids.len());
+
+        let state = state.clone();
+        tokio::spawn(async move {
+            let (pool, manager) = (&state.pool, &state.manager);
+            if let Err(e) = AgentJobService.run(&state, &job_id, token).await {
+                warn!("Bulk job {} failed: {}", job_id, e);
+                let _ = sqlx::query(
+                    "UPDATE agent_bulk_jobs SET status = 'failed', error = ?, finished_at = CURRENT_TIMESTAMP WHERE id = ?"
+                )
+                .bind(e.to_string())
+                .bind(&job_id)
+                .execute(pool)
+                .await;
+                manager.broadcast(&serde_json::json!({
+                    "type": "bulk_job_finished",
+                    "job_id": job_id,
+                    "status": "failed",
+                    "error": e.to_string(),
+                }).to_string());
+            }
+            state.bulk_jobs.remove(&job_id).await;
+        });
+
+        Ok(job)
+    }
+
+    /// Works through the job's agents, at most `concurrency` at a time. Cancelling
+    /// stops new agents from starting; agents already in progress finish first.
+    async fn run(&self, state: &AppState, job_id: &str, token: CancellationToken) -> Result<(), JobError> {
+        let (pool, manager) = (&state.pool, &state.manager);
+        let job = self.job(pool, job_id).await?;
+        let operation = BulkOperation::parse(&job.operation)
+            .ok_or_else(|| JobError::Internal(format!("Unknown bulk operation {}", job.operation)))?;
+        let agent_ids: Vec<String> = sqlx::query_scalar(
+            "SELECT agent_id FROM agent_bulk_job_results WHERE job_id = ? ORDER BY position"
+        )
+        .bind(job_id)
+        .fetch_all(pool)
+        .await?;
+
+        sqlx::query("UPDATE agent_bulk_jobs SET status = 'running', started_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(job_id)
+            .execute(pool)
+            .await?;
+        manager.broadcast(&serde_json::json!({
+            "type": "bulk_job_started",
+            "job_id": job_id,
+            "operation": job.operation,
+            "total": job.total_agents,
+        }).to_string());
+
+        let reason = |action: &str| Some(format!("{} bulk {} job {}", action, job.operation, job_id));
+        let change = Arc::new(ConfigChange::new(ChangeSource::BulkJob, job.requested_by.as_deref(), reason("Changed by")));
+        let undo_change = ConfigChange::new(ChangeSource::BulkJob, job.requested_by.as_deref(), reason("Rolled back"));
+
+        // A failure in all-or-nothing mode stops the job just like a cancellation
+        let stop = token.child_token();
+        let semaphore = Arc::new(Semaphore::new(job.concurrency.max(1) as usize));
+        let completed = Arc::new(AtomicUsize::new(0));
+        let parameters = Arc::new(job.parameters.clone());
+        let mut tasks = JoinSet::new();
+        for agent_id in agent_ids {
+            let permit = tokio::select! {
+                biased;
+                _ = stop.cancelled() => break,
+                permit = semaphore.clone().acquire_owned() => permit.map_err(|e| JobError::Internal(e.to_string()))?,
+            };
+            let (state, stop, completed, parameters, change) =
+                (state.clone(), stop.clone(), completed.clone(), parameters.clone(), change.clone());
+            let (job_id, all_or_nothing, total) = (job_id.to_string(), job.all_or_nothing, job.total_agents);
+            tasks.spawn(async move {
+                let _permit = permit;
+                let failed = AgentJobService
+                    .run_agent(&state, &job_id, operation, &agent_id, &parameters, &change, &completed, total)
+                    .await;
+                if failed && all_or_nothing {
+                    stop.cancel();
+                }
+            });
+        }
+        while tasks.join_next().await.is_some() {}
+
+        let cancelled = token.is_cancelled();
+        sqlx::query(
+            "UPDATE agent_bulk_job_results SET status = 'cancelled', message = ? WHERE job_id = ? AND status = 'pending'"
+        )
+        .bind(if cancelled { "Job cancelled" } else { "Skipped after a failure in all-or-nothing mode" })
+        .bind(job_id)
+        .execute(pool)
+        .await?;
+
+        let failures: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM agent_bulk_job_results WHERE job_id = ? AND status = 'failed'"
+        )
+        .bind(job_id)
+        .fetch_one(pool)
+        .await?;
+        let status = if job.all_or_nothing && (cancelled || failures > 0) {
+            self.roll_back(state, job_id, operation, &undo_change).await?;
+            "rolled_back"
+        } else if cancelled {
+            "cancelled"
+        } else {
+            "completed"
+        };
+        // Enabled and disabled agents join or leave the pool tasks are routed from
+        if matches!(operation, BulkOperation::Enable | BulkOperation::Disable)
+            && let Err(e) = state.agent_pool.refresh(pool).await {
+                warn!("Bulk job {}: could not refresh the agent pool: {}", job_id, e);
+            }
+
+        sqlx::query(
+            "UPDATE agent_bulk_jobs
+             SET status = ?1, finished_at = CURRENT_TIMESTAMP,
+                 succeeded = (SELECT COUNT(*) FROM agent_bulk_job_results WHERE job_id = ?2 AND status = 'succeeded'),
+                 failed = (SELECT COUNT(*) FROM agent_bulk_job_results WHERE job_id = ?2 AND status = 'failed')
+             WHERE id = ?2"
+        )
+        .bind(status)
+        .bind(job_id)
+        .execute(pool)
+        .await?;
+
+        let job = self.job(pool, job_id).await?;
+        info!(
+            "Bulk {} job {} {}: {} succeeded, {} failed of {}",
+            job.operation, job_id, status, job.succeeded, job.failed, job.total_agents
+        );
+        manager.broadcast(&serde_json::json!({
+            "type": "bulk_job_finished",
+            "job_id": job_id,
+            "status": status,
+            "succeeded": job.succeeded,
+            "failed": job.failed,
+            "total": job.total_agents,
+        }).to_string());
+        Ok(())
+    }
+
+    /// Runs the operation on one agent and records the result. Returns whether it failed.
+    #[allow(clippy::too_many_arguments)]
+    async fn run_agent(
+        &self,
+        state: &AppState,
+        job_id: &str,
+        operation: BulkOperation,
+        agent_id: &str,
+        parameters: &HashMap<String, Value>,
+        change: &ConfigChange,
+        completed: &AtomicUsize,
+        total: i64,
+    ) -> bool {
+        let (pool, manager) = (&state.pool, &state.manager);
+        let started = Instant::now();
+        if let Err(e) = sqlx::query(
+            "UPDATE agent_bulk_job_results SET status = 'running', started_at = CURRENT_TIMESTAMP WHERE job_id = ? AND agent_id = ?"
+        )
+        .bind(job_id)
+        .bind(agent_id)
+        .execute(pool)
+        .await
+        {
+            warn!("Bulk job {}: could not mark {} running: {}", job_id, agent_id, e);
+        }
+
+        let (status, message, previous) = match operation.apply(pool, agent_id, parameters, change).await {
+            Ok(outcome) => ("succeeded", outcome.message, outcome.previous),
+            Err(e) => ("failed", e, None),
+        };
+        if previous.is_some() {
+            state.cache.invalidate_agent(agent_id).await;
+        }
+        if let Err(e) = sqlx::query(
+            "UPDATE agent_bulk_job_results
+             SET status = ?, message = ?, previous_state = ?, finished_at = CURRENT_TIMESTAMP, duration_ms = ?
+             WHERE job_id = ? AND agent_id = ?"
+        )
+        .bind(status)
+        .bind(&message)
+        .bind(previous.map(|p| p.to_string()))
+        .bind(started.elapsed().as_millis() as i64)
+        .bind(job_id)
+        .bind(agent_id)
+        .execute(pool)
+        .await
+        {
+            warn!("Bulk job {}: could not record the result for {}: {}", job_id, agent_id, e);
+        }
+
+        manager.broadcast(&serde_json::json!({
+            "type": "bulk_job_progress",
+            "job_id": job_id,
+            "agent_id": agent_id,
+            "status": status,
+            "message": message,
+            "completed": completed.fetch_add(1, Ordering::SeqCst) + 1,
+            "total": total,
+        }).to_string());
+        status == "failed"
+    }
+
+    /// Undoes every agent the job changed, most recent first
+    async fn roll_back(&self, state: &AppState, job_id: &str, operation: BulkOperation, change: &ConfigChange) -> Result<(), JobError> {
+        let (pool, manager) = (&state.pool, &state.manager);
+        let changed: Vec<(String, String)> = sqlx::query_as(
+            "SELECT agent_id, previous_state FROM agent_bulk_job_results
+             WHERE job_id = ? AND status = 'succeeded' AND previous_state IS NOT NULL
+             ORDER BY finished_at DESC, position DESC"
+        )
+        .bind(job_id)
+        .fetch_all(pool)
+        .await?;
+
+        for (agent_id, previous) in changed {
+            let (status, message) = match operation.undo(pool, &agent_id, &serde_json::from_str(&previous)?, change).await {
+                Ok(()) => {
+                    state.cache.invalidate_agent(&agent_id).await;
+                    ("rolled_back", "Rolled back".to_string())
+                }
+                Err(e) => {
+                    warn!("Bulk job {}: rolling back {} failed: {}", job_id, agent_id, e);
+                    ("rollback_failed", format!("Rollback failed: {}", e))
+                }
+            };
+            sqlx::query("UPDATE agent_bulk_job_results SET status = ?, message = ? WHERE job_id = ? AND agent_id = ?")
+                .bind(status)
+                .bind(&message)
+                .bind(job_id)
+                .bind(&agent_id)
+                .execute(pool)
+                .await?;
+            manager.broadcast(&serde_json::json!({
+                "type": "bulk_job_progress",
+                "job_id": job_id,
+                "agent_id": agent_id,
+                "status": status,
+                "message": message,
+            }).to_string());
+        }
+        Ok(())
+    }
+
+    /// Jobs cannot survive a restart; close out any the previous process left behind
+    pub async fn recover_interrupted(&self, pool: &SqlitePool) -> Result<u64, JobError> {
+        let mut tx = pool.begin().await?;
+        sqlx::query(
+            "UPDATE agent_bulk_job_results SET status = 'cancelled', message = 'Interrupted by a restart'
+             WHERE status IN ('pending', 'running')
+               AND job_id IN (SELECT id FROM agent_bulk_jobs WHERE status IN ('queued', 'running'))"
+        )
+        .execute(&mut *tx)
+        .await?;
+        let interrupted = sqlx::query(
+            "UPDATE agent_bulk_jobs
+             SET status = 'failed', finished_at = CURRENT_TIMESTAMP,
+                 error = 'Interrupted by a restart; agents already processed were not rolled back',
+                 succeeded = (SELECT COUNT(*) FROM agent_bulk_job_results r WHERE r.job_id = agent_bulk_jobs.id AND r.status = 'succeeded'),
+                 failed = (SELECT COUNT(*) FROM agent_bulk_job_results r WHERE r.job_id = agent_bulk_jobs.id AND r.status = 'failed')
+             WHERE status IN ('queued', 'running')"
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        tx.commit().await?;
+        if interrupted > 0 {
+            warn!("Marked {} bulk jobs interrupted by a restart as failed", interrupted);
+        }
+        Ok(interrupted)
+    }
+
+    pub async fn cancel(&self, pool: &SqlitePool, registry: &BulkJobRegistry, job_id: &str) -> Result<BulkJob, JobError> {
+        let job = self.job(pool, job_id).await?;
+        if !matches!(job.status.as_str(), "queued" | "running") || !registry.cancel(job_id).await {
+            return Err(JobError::Conflict(format!("Job is already {}", job.status)));
+        }
+        info!("Bulk job {} cancellation requested", job_id);
+        Ok(job)
+    }
+
+    pub async fn job(&self, pool: &SqlitePool, job_id: &str) -> Result<BulkJob, JobError> {
+        sqlx::query_as::<sqlx::Sqlite, BulkJobRow>("SELECT * FROM agent_bulk_jobs WHERE id = ?")
+            .bind(job_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| JobError::JobNotFound(job_id.to_string()))?
+            .try_into()
+    }
+
+    pub async fn detail(&self, pool: &SqlitePool, job_id: &str) -> Result<BulkJobDetail, JobError> {
+        let job = self.job(pool, job_id).await?;
+        let results = sqlx::query_as::<sqlx::Sqlite, BulkJobResult>(
+            "SELECT agent_id, position, status, message, started_at, finished_at, duration_ms
+             FROM agent_bulk_job_results WHERE job_id = ? ORDER BY position"
+        )
+        .bind(job_id)
+        .fetch_all(pool)
+        .await?;
+        Ok(BulkJobDetail { job, results })
+    }
+
+    pub async fn list(&self, pool: &SqlitePool, query: &BulkJobQuery) -> Result<Vec<BulkJob>, JobError> {
+        sqlx::query_as::<sqlx::Sqlite, BulkJobRow>(
+            "SELECT * FROM agent_bulk_jobs WHERE (?1 IS NULL OR status = ?1) ORDER BY created_at DESC, id LIMIT ?2"
+        )
+        .bind(query.status.as_deref())
+        .bind(query.limit.unwrap_or(50).clamp(1, 500))
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(BulkJob::try_from)
+        .collect()
+    }
+}
+
+// Axum Handlers
+pub async fn start_bulk_agent_job(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<StartBulkJobRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&state.pool, &headers, "agents", "write").await?;
+    let job = AgentJobService
+        .start(&state, payload, &user.id)
+        .await?;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+pub async fn list_bulk_agent_jobs(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<BulkJobQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
+    Ok(Json(AgentJobService.list(&state.pool, &query).await?))
+}
+
+pub async fn get_bulk_agent_job(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(job_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
+    Ok(Json(AgentJobService.detail(&state.pool, &job_id).await?))
+}
+
+pub async fn cancel_bulk_agent_job(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(job_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "write").await?;
+    let job = AgentJobService.cancel(&state.pool, &state.bulk_jobs, &job_id).await?;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::common::{create_test_admin_user, create_test_pool, create_test_state, insert_test_agent};
+
+    fn request(operation: BulkOperation, agent_ids: &[&String], all_or_nothing: bool) -> StartBulkJobRequest {
+        StartBulkJobRequest {
+            operation,
+            agent_ids: agent_ids.iter().map(|id| id.to_string()).collect(),
+            parameters: HashMap::new(),
+            concurrency: Some(1),
+            all_or_nothing,
+        }
+    }
+
+    /// Polls until the job leaves `queued` and `running`
+    async fn finished(pool: &SqlitePool, job_id: &str) -> BulkJob {
+        for _ in 0..200 {
+            let job = AgentJobService.job(pool, job_id).await.unwrap();
+            if !matches!(job.status.as_str(), "queued" | "running") {
+                return job;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("bulk job {} did not finish", job_id);
+    }
+
+    #[tokio::test]
+    async fn disabling_agents_takes_them_out_of_the_pool() {
+        let state = create_test_state(create_test_pool().await);
+        let agent_id = insert_test_agent(&state.pool, "Pooled Agent").await;
+        state.agent_pool.refresh(&state.pool).await.unwrap();
+        assert!(state.agent_pool.has_capacity(&agent_id, "some-task").await);
+
+        let job = AgentJobService.start(&state, request(BulkOperation::Disable, &[&agent_id], false), "tester").await.unwrap();
+        assert_eq!(finished(&state.pool, &job.id).await.status, "completed");
+        assert!(!state.agent_pool.has_capacity(&agent_id, "some-task").await);
+
+        let job = AgentJobService.start(&state, request(BulkOperation::Enable, &[&agent_id], false), "tester").await.unwrap();
+        assert_eq!(finished(&state.pool, &job.id).await.status, "completed");
+        assert!(state.agent_pool.has_capacity(&agent_id, "some-task").await);
+    }
+
+    #[tokio::test]
+    async fn a_failure_rolls_back_an_all_or_nothing_job() {
+        let state = create_test_state(create_test_pool().await);
+        let (user, _) = create_test_admin_user(&state.pool).await;
+        let config = serde_json::json!({ "resource_limits": { "max_concurrent_tasks": 2 } }).to_string();
+        let mut agent_ids = Vec::new();
+        for name in ["First", "Second"] {
+            let agent_id = insert_test_agent(&state.pool, name).await;
+            store_config(&state.pool, &agent_id, Some(&config), &ConfigChange::new(ChangeSource::Comprehensive, None, None))
+                .await
+                .unwrap();
+            agent_ids.push(agent_id);
+        }
+        // Processed last, after both agents above were optimized
+        let missing = "no-such-agent".to_string();
+
+        let job = AgentJobService
+            .start(&state, request(BulkOperation::Optimize, &[&agent_ids[0], &agent_ids[1], &missing], true), &user.id)
+            .await
+            .unwrap();
+        let job = finished(&state.pool, &job.id).await;
+        assert_eq!((job.status.as_str(), job.succeeded, job.failed), ("rolled_back", 0, 1));
+        for agent_id in &agent_ids {
+            assert_eq!(comprehensive_config(&state.pool, agent_id).await.unwrap(), Some(config.clone()));
+        }
+        let detail = AgentJobService.detail(&state.pool, &job.id).await.unwrap();
+        let statuses: Vec<&str> = detail.results.iter().map(|r| r.status.as_str()).collect();
+        assert_eq!(statuses, vec!["rolled_back", "rolled_back", "failed"]);
+
+        // The same job without all_or_nothing keeps what succeeded
+        let job = AgentJobService
+            .start(&state, request(BulkOperation::Optimize, &[&agent_ids[0], &agent_ids[1], &missing], false), &user.id)
+            .await
+            .unwrap();
+        let job = finished(&state.pool, &job.id).await;
+        assert_eq!((job.status.as_str(), job.succeeded, job.failed), ("completed", 2, 1));
+        let optimized: Value = serde_json::from_str(&comprehensive_config(&state.pool, &agent_ids[0]).await.unwrap().unwrap()).unwrap();
+        assert_eq!(optimized["resource_limits"]["max_concurrent_tasks"], OPTIMIZE_TARGET_CONCURRENT_TASKS);
+    }
+
+    #[tokio::test]
+    async fn cancelling_an_all_or_nothing_job_undoes_the_agents_it_reached() {
+        let state = create_test_state(create_test_pool().await);
+        let mut agent_ids = Vec::new();
+        for i in 0..20 {
+            agent_ids.push(insert_test_agent(&state.pool, &format!("Agent {}", i)).await);
+        }
+        let targets: Vec<&String> = agent_ids.iter().collect();
+
+        let job = AgentJobService.start(&state, request(BulkOperation::Disable, &targets, true), "tester").await.unwrap();
+        AgentJobService.cancel(&state.pool, &state.bulk_jobs, &job.id).await.unwrap();
+        let job = finished(&state.pool, &job.id).await;
+        assert_eq!((job.status.as_str(), job.succeeded), ("rolled_back", 0));
+
+        let detail = AgentJobService.detail(&state.pool, &job.id).await.unwrap();
+        assert!(detail.results.iter().all(|r| matches!(r.status.as_str(), "rolled_back" | "cancelled")));
+        assert!(detail.results.iter().any(|r| r.status == "cancelled"));
+        let offline: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM agents WHERE status = 'OFFLINE'")
+            .fetch_one(&state.pool)
+            .await
+            .unwrap();
+        assert_eq!(offline, 0);
+
+        // A finished job can no longer be cancelled
+        assert!(matches!(
+            AgentJobService.cancel(&state.pool, &state.bulk_jobs, &job.id).await,
+            Err(JobError::Conflict(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn bulk_changes_are_recorded_in_the_config_history() {
+        use crate::agent_config_history::{HistoryError, RollbackRequest};
+
+        let state = create_test_state(create_test_pool().await);
+        let agent_id = insert_test_agent(&state.pool, "Reset Agent").await;
+        let (user, _) = create_test_admin_user(&state.pool).await;
+        let config = serde_json::json!({ "resource_limits": { "max_concurrent_tasks": 2 } }).to_string();
+        store_config(&state.pool, &agent_id, Some(&config), &ConfigChange::new(ChangeSource::Comprehensive, Some(&user.id), None))
+            .await
+            .unwrap();
+
+        let job = AgentJobService.start(&state, request(BulkOperation::Reset, &[&agent_id], false), &user.id).await.unwrap();
+        assert_eq!(finished(&state.pool, &job.id).await.status, "completed");
+        assert_eq!(comprehensive_config(&state.pool, &agent_id).await.unwrap(), None);
+
+        let history = ConfigHistoryService;
+        let removed = history.active(&state.pool, &agent_id, ConfigKind::Comprehensive).await.unwrap().unwrap();
+        assert_eq!((removed.version, removed.source.as_str(), removed.config), (2, "bulk_job", Some(Value::Null)));
+        assert_eq!(removed.author.as_deref(), Some(user.id.as_str()));
+        assert!(removed.reason.unwrap().contains(&job.id));
+
+        // The removal itself cannot be restored, but what it removed is no longer
+        // mistaken for the current configuration
+        let rollback = |version| {
+            history.rollback(&state.pool, &state.cache, &state.manager, &agent_id, version, RollbackRequest::default(), &user.id)
+        };
+        assert!(matches!(rollback(2).await, Err(HistoryError::Invalid(_))));
+        assert!(!matches!(rollback(1).await, Err(HistoryError::Conflict(_))));
+    }
+}
@@ -0,0 +1,960 @@
+use crate::db::SqlitePool;
+use crate::audit::AuditService;
+use crate::models::{Agent, OpenClawAgentConfig};
+use crate::openclaw_advanced_features::{
+    AdaptationAction, AdaptationMetrics, ActionType, AdaptiveAgent, EvaluationCriteria, ExpectedImpact, Feedback,
+    FeedbackProcessor, FeedbackType, Pattern, ProcessedFeedback, RollbackPlan, TrendType,
+};
+use crate::openclaw_optimization::HierarchicalCache;
+use crate::agent_config_history::{ChangeSource, ConfigChange};
+use crate::ConnectionManager;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+use std::collections::HashMap;
+use tracing::{info, warn};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+    response::IntoResponse,
+    http::{HeaderMap, StatusCode},
+};
+use crate::AppState;
+
+/// How much earlier feedback the pattern recognizer and the baselines look at
+const HISTORY_DAYS: i64 = 30;
+const HISTORY_LIMIT: i64 = 50;
+/// Feedback needed after an adaptation before it is judged
+const MIN_EVALUATION_SAMPLES: usize = 3;
+
+#[derive(Debug)]
+pub enum LearningError {
+    AgentNotFound(String),
+    AdaptationNotFound(String),
+    Invalid(String),
+    Conflict(String),
+    Internal(String),
+}
+
+impl std::fmt::Display for LearningError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LearningError::AgentNotFound(id) => write!(f, "Agent '{}' not found", id),
+            LearningError::AdaptationNotFound(id) => write!(f, "Adaptation '{}' not found", id),
+            LearningError::Invalid(msg) | LearningError::Conflict(msg) => write!(f, "{}", msg),
+            LearningError::Internal(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<sqlx::Error> for LearningError {
+    fn from(error: sqlx::Error) -> Self {
+        LearningError::Internal(format!("Database error: {}", error))
+    }
+}
+
+impl From<serde_json::Error> for LearningError {
+    fn from(error: serde_json::Error) -> Self {
+        LearningError::Internal(error.to_string())
+    }
+}
+
+impl From<LearningError> for (StatusCode, String) {
+    fn from(error: LearningError) -> Self {
+        let status = match &error {
+            LearningError::AgentNotFound(_) | LearningError::AdaptationNotFound(_) => StatusCode::NOT_FOUND,
+            LearningError::Invalid(_) => StatusCode::BAD_REQUEST,
+            LearningError::Conflict(_) => StatusCode::CONFLICT,
+            LearningError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, error.to_string())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubmitFeedbackRequest {
+    pub feedback_type: FeedbackType,
+    /// Stars for `user_rating`, 0-1 for `task_completion` and `error_rate`,
+    /// seconds for `response_time`, USD for `cost_efficiency`
+    pub rating: f64,
+    pub comment: Option<String>,
+    pub context: Option<String>,
+    pub task_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdaptationQuery {
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RejectAdaptationRequest {
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct FeedbackRecord {
+    pub id: String,
+    pub agent_id: String,
+    pub feedback_type: String,
+    pub rating: f64,
+    pub comment: Option<String>,
+    pub context: Option<String>,
+    pub task_id: Option<String>,
+    pub source: String,
+    pub submitted_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl FeedbackRecord {
+    fn to_feedback(&self) -> Option<Feedback> {
+        Some(Feedback {
+            id: self.id.clone(),
+            agent_id: self.agent_id.clone(),
+            feedback_type: FeedbackType::parse(&self.feedback_type)?,
+            rating: Some(self.rating),
+            comment: self.comment.clone(),
+            context: self.context.clone(),
+            task_id: self.task_id.clone(),
+            source: self.source.clone(),
+            timestamp: self.created_at,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct PatternRecord {
+    pub id: String,
+    pub agent_id: String,
+    pub pattern_type: String,
+    pub trend_type: String,
+    pub slope: f64,
+    pub samples: i64,
+    pub confidence: f64,
+    pub description: String,
+    pub detected_at: DateTime<Utc>,
+}
+
+#[derive(Debug, FromRow)]
+struct AdaptationRow {
+    id: String,
+    agent_id: String,
+    pattern_id: Option<String>,
+    action_type: String,
+    parameters: String,
+    expected_impact: String,
+    rollback_plan: String,
+    evaluation_criteria: String,
+    status: String,
+    previous_config: Option<String>,
+    outcome: Option<String>,
+    improvement: Option<f64>,
+    proposed_at: DateTime<Utc>,
+    decided_by: Option<String>,
+    decided_at: Option<DateTime<Utc>>,
+    applied_at: Option<DateTime<Utc>>,
+    evaluated_at: Option<DateTime<Utc>>,
+}
+
+/// A proposed or applied adaptation as shown to people deciding on it
+#[derive(Debug, Serialize)]
+pub struct Adaptation {
+    pub id: String,
+    pub agent_id: String,
+    pub pattern_id: Option<String>,
+    pub action_type: String,
+    pub parameters: HashMap<String, Value>,
+    pub expected_impact: ExpectedImpact,
+    pub rollback_plan: RollbackPlan,
+    pub evaluation_criteria: Vec<EvaluationCriteria>,
+    pub status: String,
+    pub outcome: Option<String>,
+    pub improvement: Option<f64>,
+    pub proposed_at: DateTime<Utc>,
+    pub decided_by: Option<String>,
+    pub decided_at: Option<DateTime<Utc>>,
+    pub applied_at: Option<DateTime<Utc>>,
+    pub evaluated_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    previous_config: Option<String>,
+}
+
+impl TryFrom<AdaptationRow> for Adaptation {
+    type Error = LearningError;
+
+    fn try_from(row: AdaptationRow) -> Result<Self, Self::Error> {
+        Ok(Adaptation {
+            parameters: serde_json::from_str(&row.parameters)?,
+            expected_impact: serde_json::from_str(&row.expected_impact)?,
+            rollback_plan: serde_json::from_str(&row.rollback_plan)?,
+            evaluation_criteria: serde_json::from_str(&row.evaluation_criteria)?,
+            id: row.id,
+            agent_id: row.agent_id,
+            pattern_id: row.pattern_id,
+            action_type: row.action_type,
+            status: row.status,
+            outcome: row.outcome,
+            improvement: row.improvement,
+            proposed_at: row.proposed_at,
+            decided_by: row.decided_by,
+            decided_at: row.decided_at,
+            applied_at: row.applied_at,
+            evaluated_at: row.evaluated_at,
+            previous_config: row.previous_config,
+        })
+    }
+}
+
+impl Adaptation {
+    fn action(&self) -> Result<AdaptationAction, LearningError> {
+        Ok(AdaptationAction {
+            pattern_id: self.pattern_id.clone(),
+            action_type: ActionType::parse(&self.action_type)
+                .ok_or_else(|| LearningError::Internal(format!("Unknown action type {}", self.action_type)))?,
+            parameters: self.parameters.clone(),
+            expected_impact: self.expected_impact.clone(),
+            rollback_plan: self.rollback_plan.clone(),
+            evaluation_criteria: self.evaluation_criteria.clone(),
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeedbackOutcome {
+    pub feedback: Feedback,
+    pub patterns: Vec<Pattern>,
+    /// Adaptations newly proposed because of this feedback
+    pub recommendations: Vec<Adaptation>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AgentLearning {
+    pub agent_id: String,
+    pub feedback: Vec<FeedbackRecord>,
+    pub patterns: Vec<PatternRecord>,
+    pub metrics: AdaptationMetrics,
+}
+
+/// Aggregated feedback per evaluation metric, with the number of samples behind it
+fn metric_values(processor: &FeedbackProcessor, feedback: &[ProcessedFeedback]) -> HashMap<&'static str, (f64, usize)> {
+    let mut grouped: HashMap<&'static str, Vec<(f64, f64)>> = HashMap::new();
+    for item in feedback {
+        grouped.entry(item.pattern_type.metric_name()).or_default().push((item.value, item.weight));
+    }
+    grouped
+        .into_iter()
+        .filter_map(|(metric, values)| processor.aggregate(&values).map(|value| (metric, (value, values.len()))))
+        .collect()
+}
+
+pub struct AgentLearningService;
+
+impl AgentLearningService {
+    async fn agent(&self, pool: &SqlitePool, agent_id: &str) -> Result<Agent, LearningError> {
+        sqlx::query_as::<sqlx::Sqlite, Agent>("SELECT * FROM agents WHERE id = ?")
+            .bind(agent_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| LearningError::AgentNotFound(agent_id.to_string()))
+    }
+
+    /// Recent feedback, oldest first, optionally only what came in after `since`
+    async fn processed_feedback(
+        &self,
+        pool: &SqlitePool,
+        adaptive: &AdaptiveAgent,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<ProcessedFeedback>, LearningError> {
+        let mut records = sqlx::query_as::<sqlx::Sqlite, FeedbackRecord>(
+            "SELECT * FROM agent_feedback
+             WHERE agent_id = ?1 AND datetime(created_at) > datetime('now', '-' || ?2 || ' days')
+               AND (?3 IS NULL OR datetime(created_at) >= datetime(?3))
+             ORDER BY created_at DESC, rowid DESC LIMIT ?4"
+        )
+        .bind(&adaptive.base_agent.id)
+        .bind(HISTORY_DAYS)
+        .bind(since)
+        .bind(HISTORY_LIMIT)
+        .fetch_all(pool)
+        .await?;
+        records.reverse();
+
+        let processor = &adaptive.learning_engine.feedback_processor;
+        Ok(records
+            .iter()
+            .filter_map(|record| record.to_feedback())
+            .filter_map(|feedback| processor.process_feedback(&feedback).ok())
+            .collect())
+    }
+
+    async fn adaptation(&self, pool: &SqlitePool, agent_id: &str, adaptation_id: &str) -> Result<Adaptation, LearningError> {
+        sqlx::query_as::<sqlx::Sqlite, AdaptationRow>("SELECT * FROM agent_adaptations WHERE id = ? AND agent_id = ?")
+            .bind(adaptation_id)
+            .bind(agent_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| LearningError::AdaptationNotFound(adaptation_id.to_string()))?
+            .try_into()
+    }
+
+    /// Stores the feedback, records the patterns it reveals and proposes adaptations
+    /// for them. An action already proposed or under evaluation is not proposed again.
+    pub async fn submit(&self, pool: &SqlitePool, feedback: Feedback, submitted_by: Option<&str>) -> Result<FeedbackOutcome, LearningError> {
+        let mut adaptive = AdaptiveAgent::new(self.agent(pool, &feedback.agent_id).await?);
+        let history = self.processed_feedback(pool, &adaptive, None).await?;
+        let learned = adaptive.learn_from_feedback(&feedback, &history).map_err(LearningError::Invalid)?;
+
+        sqlx::query(
+            "INSERT INTO agent_feedback (id, agent_id, feedback_type, rating, comment, context, task_id, source, submitted_by, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&feedback.id)
+        .bind(&feedback.agent_id)
+        .bind(feedback.feedback_type.as_str())
+        .bind(feedback.rating)
+        .bind(&feedback.comment)
+        .bind(&feedback.context)
+        .bind(&feedback.task_id)
+        .bind(&feedback.source)
+        .bind(submitted_by)
+        .bind(feedback.timestamp)
+        .execute(pool)
+        .await?;
+
+        // Only trends worth acting on are kept
+        let threshold = adaptive.learning_engine.pattern_recognizer.confidence_threshold;
+        let patterns: Vec<Pattern> = learned
+            .patterns
+            .into_iter()
+            .filter(|pattern| pattern.trend_type != TrendType::Stable && pattern.confidence > threshold)
+            .collect();
+        for pattern in &patterns {
+            sqlx::query(
+                "INSERT INTO agent_patterns (id, agent_id, pattern_type, trend_type, slope, samples, confidence, description, detected_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+            )
+            .bind(&pattern.id)
+            .bind(&feedback.agent_id)
+            .bind(pattern.pattern_type.as_str())
+            .bind(pattern.trend_type.as_str())
+            .bind(pattern.slope)
+            .bind(pattern.samples as i64)
+            .bind(pattern.confidence)
+            .bind(&pattern.description)
+            .bind(pattern.detected_at)
+            .execute(pool)
+            .await?;
+        }
+
+        let mut recommendations = Vec::new();
+        for action in learned.adaptations {
+            let pending: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM agent_adaptations WHERE agent_id = ? AND action_type = ? AND status IN ('proposed', 'applied')"
+            )
+            .bind(&feedback.agent_id)
+            .bind(action.action_type.as_str())
+            .fetch_one(pool)
+            .await?;
+            if pending > 0 {
+                continue;
+            }
+
+            let id = uuid::Uuid::new_v4().to_string();
+            sqlx::query(
+                "INSERT INTO agent_adaptations
+                 (id, agent_id, pattern_id, action_type, parameters, expected_impact, rollback_plan, evaluation_criteria)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+            )
+            .bind(&id)
+            .bind(&feedback.agent_id)
+            .bind(&action.pattern_id)
+            .bind(action.action_type.as_str())
+            .bind(serde_json::to_string(&action.parameters)?)
+            .bind(serde_json::to_string(&action.expected_impact)?)
+            .bind(serde_json::to_string(&action.rollback_plan)?)
+            .bind(serde_json::to_string(&action.evaluation_criteria)?)
+            .execute(pool)
+            .await?;
+            info!("Proposed {} for agent {}", action.action_type.as_str(), feedback.agent_id);
+            recommendations.push(self.adaptation(pool, &feedback.agent_id, &id).await?);
+        }
+
+        Ok(FeedbackOutcome { feedback, patterns, recommendations })
+    }
+
+    /// Turns a review verdict into task completion feedback for the task's assignee
+    pub async fn record_review(&self, pool: &SqlitePool, task_id: &str, outcome: &str, comment: Option<&str>) -> Result<(), LearningError> {
+        let rating = match outcome {
+            "approved" => 1.0,
+            "rejected" => 0.0,
+            _ => return Ok(()),
+        };
+        let assignee: Option<String> = sqlx::query_scalar("SELECT assignee_id FROM tasks WHERE id = ?")
+            .bind(task_id)
+            .fetch_optional(pool)
+            .await?
+            .flatten();
+        let Some(agent_id) = assignee else {
+            return Ok(());
+        };
+
+        let feedback = Feedback {
+            id: uuid::Uuid::new_v4().to_string(),
+            agent_id,
+            feedback_type: FeedbackType::TaskCompletion,
+            rating: Some(rating),
+            comment: comment.map(str::to_string),
+            context: Some(format!("review: {}", outcome)),
+            task_id: Some(task_id.to_string()),
+            source: "review".to_string(),
+            timestamp: Utc::now(),
+        };
+        self.submit(pool, feedback, None).await.map(|_| ())
+    }
+
+    pub async fn learning(&self, pool: &SqlitePool, agent_id: &str) -> Result<AgentLearning, LearningError> {
+        self.agent(pool, agent_id).await?;
+        let feedback = sqlx::query_as::<sqlx::Sqlite, FeedbackRecord>(
+            "SELECT * FROM agent_feedback WHERE agent_id = ? ORDER BY created_at DESC, rowid DESC LIMIT ?"
+        )
+        .bind(agent_id)
+        .bind(HISTORY_LIMIT)
+        .fetch_all(pool)
+        .await?;
+        let patterns = sqlx::query_as::<sqlx::Sqlite, PatternRecord>(
+            "SELECT * FROM agent_patterns WHERE agent_id = ? ORDER BY detected_at DESC, rowid DESC LIMIT 20"
+        )
+        .bind(agent_id)
+        .fetch_all(pool)
+        .await?;
+
+        let (performed, successful, failed, average_improvement, recent): (i64, i64, i64, Option<f64>, i64) = sqlx::query_as(
+            "SELECT COUNT(applied_at),
+                    COALESCE(SUM(CASE WHEN status = 'kept' THEN 1 ELSE 0 END), 0),
+                    COALESCE(SUM(CASE WHEN status = 'rolled_back' THEN 1 ELSE 0 END), 0),
+                    AVG(improvement),
+                    COALESCE(SUM(CASE WHEN applied_at > datetime('now', '-30 days') THEN 1 ELSE 0 END), 0)
+             FROM agent_adaptations WHERE agent_id = ?"
+        )
+        .bind(agent_id)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(AgentLearning {
+            agent_id: agent_id.to_string(),
+            feedback,
+            patterns,
+            metrics: AdaptationMetrics {
+                adaptations_performed: performed as u64,
+                successful_adaptations: successful as u64,
+                failed_adaptations: failed as u64,
+                average_improvement: average_improvement.unwrap_or(0.0),
+                // Per day over the last 30 days
+                adaptation_frequency: recent as f64 / 30.0,
+            },
+        })
+    }
+
+    pub async fn adaptations(&self, pool: &SqlitePool, agent_id: &str, status: Option<&str>) -> Result<Vec<Adaptation>, LearningError> {
+        self.agent(pool, agent_id).await?;
+        sqlx::query_as::<sqlx::Sqlite, AdaptationRow>(
+            "SELECT * FROM agent_adaptations WHERE agent_id = ?1 AND (?2 IS NULL OR status = ?2)
+             ORDER BY proposed_at DESC, rowid DESC"
+        )
+        .bind(agent_id)
+        .bind(status)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(Adaptation::try_from)
+        .collect()
+    }
+
+    /// Applies `config` through the same path as the apply endpoint and audits the change
+    async fn apply_config(
+        &self,
+        pool: &SqlitePool,
+        cache: &HierarchicalCache,
+        agent_id: &str,
+        previous: &OpenClawAgentConfig,
+        config: &OpenClawAgentConfig,
+        user_id: Option<&str>,
+        reason: String,
+        metadata: Value,
+    ) -> Result<(), LearningError> {
+        let change = ConfigChange::new(ChangeSource::Adaptation, user_id, Some(reason));
+        crate::openclaw_integration::apply_validated_agent_config(pool, cache, agent_id, config, &change)
+            .await
+            .map_err(|(status, message)| match status {
+                StatusCode::BAD_REQUEST => LearningError::Invalid(message),
+                _ => LearningError::Internal(message),
+            })?;
+
+        AuditService::log_entity_event(
+            pool,
+            "agent",
+            agent_id,
+            "update",
+            Some(&serde_json::to_string(previous)?),
+            Some(&serde_json::to_string(config)?),
+            user_id,
+            None,
+            None,
+            None,
+            None,
+            Some(&metadata.to_string()),
+        ).await?;
+        Ok(())
+    }
+
+    /// Applies a proposed adaptation to the agent's OpenClaw configuration and records
+    /// the agent's current metrics as the targets it is evaluated against
+    pub async fn approve(
+        &self,
+        pool: &SqlitePool,
+        manager: &ConnectionManager,
+        cache: &HierarchicalCache,
+        agent_id: &str,
+        adaptation_id: &str,
+        user_id: &str,
+    ) -> Result<Adaptation, LearningError> {
+        let adaptation = self.adaptation(pool, agent_id, adaptation_id).await?;
+        if adaptation.status != "proposed" {
+            return Err(LearningError::Conflict(format!("Adaptation is already {}", adaptation.status)));
+        }
+        let action = adaptation.action()?;
+
+        let previous = crate::openclaw_integration::read_and_parse_openclaw_config()
+            .await
+            .map_err(|e| LearningError::Conflict(format!("Cannot read the OpenClaw configuration: {}", e)))?
+            .into_iter()
+            .find(|config| config.id == agent_id)
+            .ok_or_else(|| LearningError::Conflict("The agent has no OpenClaw configuration to adapt".to_string()))?;
+        let mut config = previous.clone();
+        let mut adaptive = AdaptiveAgent::new(self.agent(pool, agent_id).await?);
+        adaptive.apply_adaptation(&action, &mut config).map_err(LearningError::Invalid)?;
+
+        // Criteria without a baseline cannot be evaluated
+        let baseline = metric_values(
+            &adaptive.learning_engine.feedback_processor,
+            &self.processed_feedback(pool, &adaptive, None).await?,
+        );
+        let criteria: Vec<EvaluationCriteria> = action
+            .evaluation_criteria
+            .iter()
+            .filter_map(|criterion| {
+                baseline.get(criterion.metric_name.as_str()).map(|(value, _)| EvaluationCriteria {
+                    target_value: *value,
+                    ..criterion.clone()
+                })
+            })
+            .collect();
+
+        // Claimed before the configuration is touched, so concurrent approvals apply it once
+        let claimed = sqlx::query(
+            "UPDATE agent_adaptations
+             SET status = 'applied', decided_by = ?, decided_at = CURRENT_TIMESTAMP, applied_at = CURRENT_TIMESTAMP
+             WHERE id = ? AND status = 'proposed'"
+        )
+        .bind(user_id)
+        .bind(adaptation_id)
+        .execute(pool)
+        .await?;
+        if claimed.rows_affected() == 0 {
+            let adaptation = self.adaptation(pool, agent_id, adaptation_id).await?;
+            return Err(LearningError::Conflict(format!("Adaptation is already {}", adaptation.status)));
+        }
+
+        if let Err(e) = self.apply_config(
+            pool,
+            cache,
+            agent_id,
+            &previous,
+            &config,
+            Some(user_id),
+            format!("Adaptation {} ({})", adaptation_id, action.action_type.as_str()),
+            serde_json::json!({ "adaptation_id": adaptation_id, "action_type": action.action_type.as_str() }),
+        ).await {
+            // Nothing was applied, so it can be approved again
+            sqlx::query(
+                "UPDATE agent_adaptations SET status = 'proposed', decided_by = NULL, decided_at = NULL, applied_at = NULL
+                 WHERE id = ? AND status = 'applied'"
+            )
+            .bind(adaptation_id)
+            .execute(pool)
+            .await?;
+            return Err(e);
+        }
+
+        sqlx::query("UPDATE agent_adaptations SET previous_config = ?, evaluation_criteria = ? WHERE id = ?")
+            .bind(serde_json::to_string(&previous)?)
+            .bind(serde_json::to_string(&criteria)?)
+            .bind(adaptation_id)
+            .execute(pool)
+            .await?;
+
+        manager.broadcast(&serde_json::json!({
+            "type": "agent_adaptation_applied",
+            "agent_id": agent_id,
+            "adaptation_id": adaptation_id,
+            "action_type": action.action_type.as_str(),
+        }).to_string());
+        info!("Applied {} {} to agent {}", action.action_type.as_str(), adaptation_id, agent_id);
+
+        self.adaptation(pool, agent_id, adaptation_id).await
+    }
+
+    pub async fn reject(
+        &self,
+        pool: &SqlitePool,
+        agent_id: &str,
+        adaptation_id: &str,
+        user_id: &str,
+        note: Option<&str>,
+    ) -> Result<Adaptation, LearningError> {
+        let adaptation = self.adaptation(pool, agent_id, adaptation_id).await?;
+        if adaptation.status != "proposed" {
+            return Err(LearningError::Conflict(format!("Adaptation is already {}", adaptation.status)));
+        }
+        let rejected = sqlx::query(
+            "UPDATE agent_adaptations SET status = 'rejected', outcome = ?, decided_by = ?, decided_at = CURRENT_TIMESTAMP
+             WHERE id = ? AND status = 'proposed'"
+        )
+        .bind(note)
+        .bind(user_id)
+        .bind(adaptation_id)
+        .execute(pool)
+        .await?;
+        let adaptation = self.adaptation(pool, agent_id, adaptation_id).await?;
+        if rejected.rows_affected() == 0 {
+            return Err(LearningError::Conflict(format!("Adaptation is already {}", adaptation.status)));
+        }
+        Ok(adaptation)
+    }
+
+    /// Judges applied adaptations whose evaluation window has passed. Those whose
+    /// criteria regressed, by weight, are rolled back to the configuration they
+    /// replaced; the rest are kept. Returns how many were rolled back.
+    pub async fn evaluate_applied(&self, pool: &SqlitePool, manager: &ConnectionManager, cache: &HierarchicalCache) -> Result<u64, LearningError> {
+        let rows = sqlx::query_as::<sqlx::Sqlite, AdaptationRow>(
+            "SELECT * FROM agent_adaptations WHERE status = 'applied' ORDER BY applied_at"
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let mut rolled_back = 0;
+        for row in rows {
+            let adaptation = Adaptation::try_from(row)?;
+            let Some(applied_at) = adaptation.applied_at else {
+                continue;
+            };
+            let window = chrono::Duration::from_std(adaptation.rollback_plan.timeout).unwrap_or(chrono::Duration::days(1));
+            let now = Utc::now();
+            if now < applied_at + window {
+                continue;
+            }
+
+            let adaptive = AdaptiveAgent::new(self.agent(pool, &adaptation.agent_id).await?);
+            let after = metric_values(
+                &adaptive.learning_engine.feedback_processor,
+                &self.processed_feedback(pool, &adaptive, Some(applied_at)).await?,
+            );
+            let evaluated: Vec<(&EvaluationCriteria, f64)> = adaptation
+                .evaluation_criteria
+                .iter()
+                .filter_map(|criterion| {
+                    after
+                        .get(criterion.metric_name.as_str())
+                        .filter(|(_, samples)| *samples >= MIN_EVALUATION_SAMPLES)
+                        .map(|(value, _)| (criterion, *value))
+                })
+                .collect();
+
+            if evaluated.is_empty() {
+                // Give quiet agents three windows before keeping the change unjudged
+                if now >= applied_at + window * 3 {
+                    self.finish_evaluation(pool, &adaptation.id, "kept", "Not enough feedback to evaluate", None).await?;
+                }
+                continue;
+            }
+
+            let total_weight: f64 = evaluated.iter().map(|(criterion, _)| criterion.weight).sum();
+            let regressed: Vec<&(&EvaluationCriteria, f64)> =
+                evaluated.iter().filter(|(criterion, value)| criterion.regressed(*value)).collect();
+            let regressed_weight: f64 = regressed.iter().map(|(criterion, _)| criterion.weight).sum();
+            // Relative change per metric, signed so that better is positive
+            let improvement = evaluated
+                .iter()
+                .map(|(criterion, value)| {
+                    let change = (value - criterion.target_value) / criterion.target_value.abs().max(0.01);
+                    let change = match criterion.metric_name.as_str() {
+                        "cost" | "error_rate" => -change,
+                        _ => change,
+                    };
+                    change * criterion.weight
+                })
+                .sum::<f64>()
+                / total_weight.max(f64::EPSILON);
+
+            if total_weight > 0.0 && regressed_weight / total_weight >= 0.5 {
+                let summary = regressed
+                    .iter()
+                    .map(|(criterion, value)| format!("{} {:.3} vs {:.3}", criterion.metric_name, value, criterion.target_value))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                match self.roll_back(pool, manager, cache, &adaptation, &summary).await {
+                    Ok(()) => {
+                        self.finish_evaluation(pool, &adaptation.id, "rolled_back", &format!("Regressed: {}", summary), Some(improvement)).await?;
+                        rolled_back += 1;
+                    }
+                    Err(e) => warn!("Could not roll back adaptation {} for agent {}: {}", adaptation.id, adaptation.agent_id, e),
+                }
+            } else {
+                self.finish_evaluation(pool, &adaptation.id, "kept", "Evaluation criteria held", Some(improvement)).await?;
+            }
+        }
+        Ok(rolled_back)
+    }
+
+    async fn roll_back(
+        &self,
+        pool: &SqlitePool,
+        manager: &ConnectionManager,
+        cache: &HierarchicalCache,
+        adaptation: &Adaptation,
+        reason: &str,
+    ) -> Result<(), LearningError> {
+        let previous: OpenClawAgentConfig = serde_json::from_str(
+            adaptation
+                .previous_config
+                .as_deref()
+                .ok_or_else(|| LearningError::Internal("No configuration to roll back to".to_string()))?,
+        )?;
+        let current = crate::openclaw_integration::read_and_parse_openclaw_config()
+            .await
+            .ok()
+            .and_then(|configs| configs.into_iter().find(|config| config.id == adaptation.agent_id));
+
+        // Only what the adaptation changed goes back; later changes to other fields stay
+        let mut applied = previous.clone();
+        AdaptiveAgent::new(self.agent(pool, &adaptation.agent_id).await?)
+            .apply_adaptation(&adaptation.action()?, &mut applied)
+            .map_err(LearningError::Invalid)?;
+        let reverted: OpenClawAgentConfig = match &current {
+            Some(current) => {
+                let mut reverted = serde_json::to_value(current)?;
+                revert_changes(&mut reverted, &serde_json::to_value(&previous)?, &serde_json::to_value(&applied)?);
+                serde_json::from_value(reverted)?
+            }
+            None => previous.clone(),
+        };
+
+        self.apply_config(
+            pool,
+            cache,
+            &adaptation.agent_id,
+            current.as_ref().unwrap_or(&previous),
+            &reverted,
+            None,
+            format!("Rolled back adaptation {}: {}", adaptation.id, reason),
+            serde_json::json!({ "adaptation_id": adaptation.id, "rollback": true, "reason": reason }),
+        ).await?;
+
+        manager.broadcast(&serde_json::json!({
+            "type": "agent_adaptation_rolled_back",
+            "agent_id": adaptation.agent_id,
+            "adaptation_id": adaptation.id,
+            "reason": reason,
+        }).to_string());
+        warn!("Rolled back adaptation {} for agent {}: {}", adaptation.id, adaptation.agent_id, reason);
+        Ok(())
+    }
+
+    async fn finish_evaluation(
+        &self,
+        pool: &SqlitePool,
+        adaptation_id: &str,
+        status: &str,
+        outcome: &str,
+        improvement: Option<f64>,
+    ) -> Result<(), LearningError> {
+        sqlx::query(
+            "UPDATE agent_adaptations SET status = ?, outcome = ?, improvement = ?, evaluated_at = CURRENT_TIMESTAMP WHERE id = ?"
+        )
+        .bind(status)
+        .bind(outcome)
+        .bind(improvement)
+        .bind(adaptation_id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+}
+
+/// Puts `previous` back wherever `applied` differs from it, leaving the rest of
+/// `current` as it is. Objects are compared key by key, so reverting one parameter
+/// keeps the others as they are now.
+fn revert_changes(current: &mut Value, previous: &Value, applied: &Value) {
+    match (current, previous, applied) {
+        (Value::Object(current), Value::Object(previous), Value::Object(applied)) => {
+            let keys: std::collections::BTreeSet<&String> = previous.keys().chain(applied.keys()).collect();
+            for key in keys {
+                let before = previous.get(key).unwrap_or(&Value::Null);
+                let after = applied.get(key).unwrap_or(&Value::Null);
+                if before == after {
+                    continue;
+                }
+                match current.get_mut(key) {
+                    Some(value) if value.is_object() && before.is_object() && after.is_object() => revert_changes(value, before, after),
+                    _ if before.is_null() => {
+                        current.remove(key);
+                    }
+                    _ => {
+                        current.insert(key.clone(), before.clone());
+                    }
+                }
+            }
+        }
+        (current, previous, _) => *current = previous.clone(),
+    }
+}
+
+// Axum Handlers
+pub async fn submit_agent_feedback(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(agent_id): Path<String>,
+    Json(payload): Json<SubmitFeedbackRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&state.pool, &headers, "agents", "write").await?;
+    let feedback = Feedback {
+        id: uuid::Uuid::new_v4().to_string(),
+        agent_id,
+        feedback_type: payload.feedback_type,
+        rating: Some(payload.rating),
+        comment: payload.comment,
+        context: payload.context,
+        task_id: payload.task_id,
+        source: "user".to_string(),
+        timestamp: Utc::now(),
+    };
+
+    let outcome = AgentLearningService.submit(&state.pool, feedback, Some(&user.id)).await?;
+    if !outcome.recommendations.is_empty() {
+        state.manager.broadcast(&serde_json::json!({
+            "type": "agent_adaptations_proposed",
+            "agent_id": outcome.feedback.agent_id,
+            "adaptation_ids": outcome.recommendations.iter().map(|a| a.id.as_str()).collect::<Vec<_>>(),
+        }).to_string());
+    }
+    Ok((StatusCode::CREATED, Json(outcome)))
+}
+
+pub async fn get_agent_learning(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(agent_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
+    Ok(Json(AgentLearningService.learning(&state.pool, &agent_id).await?))
+}
+
+pub async fn list_agent_adaptations(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(agent_id): Path<String>,
+    Query(query): Query<AdaptationQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
+    Ok(Json(AgentLearningService.adaptations(&state.pool, &agent_id, query.status.as_deref()).await?))
+}
+
+pub async fn approve_agent_adaptation(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((agent_id, adaptation_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let admin = crate::rbac::authorized_user(&state.pool, &headers, "agents", "admin").await?;
+    Ok(Json(AgentLearningService.approve(&state.pool, &state.manager, &state.cache, &agent_id, &adaptation_id, &admin.id).await?))
+}
+
+pub async fn reject_agent_adaptation(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((agent_id, adaptation_id)): Path<(String, String)>,
+    Json(payload): Json<RejectAdaptationRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let admin = crate::rbac::authorized_user(&state.pool, &headers, "agents", "admin").await?;
+    let rejected = AgentLearningService
+        .reject(&state.pool, &agent_id, &adaptation_id, &admin.id, payload.note.as_deref())
+        .await?;
+    Ok(Json(rejected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::common::{create_test_pool, insert_test_agent};
+
+    #[test]
+    fn revert_changes_keeps_fields_the_adaptation_did_not_touch() {
+        let previous = serde_json::json!({
+            "id": "agent",
+            "skills": ["research"],
+            "params": { "temperature": 0.7, "maxTokens": 4000 },
+            "model": { "primary": "claude-3-sonnet", "fallbacks": null }
+        });
+        let applied = serde_json::json!({
+            "id": "agent",
+            "skills": ["research"],
+            "params": { "temperature": 0.3, "maxTokens": 4000, "topP": 0.9 },
+            "model": { "primary": "claude-3-sonnet", "fallbacks": null }
+        });
+        // Changed by hand after the adaptation was applied
+        let mut current = serde_json::json!({
+            "id": "agent",
+            "skills": ["research", "writing"],
+            "params": { "temperature": 0.3, "maxTokens": 8000, "topP": 0.9 },
+            "model": { "primary": "gpt-4", "fallbacks": ["claude-3-sonnet"] }
+        });
+
+        revert_changes(&mut current, &previous, &applied);
+
+        assert_eq!(current, serde_json::json!({
+            "id": "agent",
+            "skills": ["research", "writing"],
+            "params": { "temperature": 0.7, "maxTokens": 8000 },
+            "model": { "primary": "gpt-4", "fallbacks": ["claude-3-sonnet"] }
+        }));
+    }
+
+    #[tokio::test]
+    async fn an_adaptation_is_decided_once() {
+        let pool = create_test_pool().await;
+        let agent_id = insert_test_agent(&pool, "Adaptive Agent").await;
+        let adaptation_id = uuid::Uuid::new_v4().to_string();
+        let impact = ExpectedImpact { performance_change: 0.1, cost_change: 0.0, reliability_change: 0.0, confidence: 0.8 };
+        let plan = RollbackPlan {
+            rollback_conditions: Vec::new(),
+            rollback_actions: Vec::new(),
+            timeout: std::time::Duration::from_secs(3600),
+        };
+        sqlx::query(
+            "INSERT INTO agent_adaptations (id, agent_id, action_type, parameters, expected_impact, rollback_plan, evaluation_criteria)
+             VALUES (?, ?, 'parameter_adjustment', '{}', ?, ?, '[]')"
+        )
+        .bind(&adaptation_id)
+        .bind(&agent_id)
+        .bind(serde_json::to_string(&impact).unwrap())
+        .bind(serde_json::to_string(&plan).unwrap())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let rejected = AgentLearningService.reject(&pool, &agent_id, &adaptation_id, "reviewer", Some("not now")).await.unwrap();
+        assert_eq!(rejected.status, "rejected");
+        let again = AgentLearningService.reject(&pool, &agent_id, &adaptation_id, "other-reviewer", None).await;
+        assert!(matches!(again, Err(LearningError::Conflict(_))));
+    }
+}
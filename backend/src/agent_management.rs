@@ -1,24 +1,14 @@
 use crate::models::*;
-use axum::{
-    extract::{Path, State, Query},
-    Json,
-    response::IntoResponse,
-    http::StatusCode,
-};
-use sqlx::SqlitePool;
 use chrono::{Utc, Duration};
 use std::collections::HashMap;
 use serde_json::Value;
-use sha2::{Sha256, Digest};
-use std::sync::Arc;
-use tracing::{info, warn, error, debug, instrument};
 use validator::Validate;
 use serde::{Deserialize, Serialize};
 
 // Advanced Agent Management System
 
 /// Comprehensive agent management with enhanced UX features
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 pub struct AgentManagementRequest {
     pub agent: AgentConfigRequest,
     pub preferences: AgentManagementPreferences,
@@ -209,7 +199,7 @@ pub struct SecuritySettings {
     pub encryption_requirements: EncryptionRequirements,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SecurityAccessLevel {
     ReadOnly,
     ReadWrite,
@@ -463,267 +453,6 @@ pub struct AnalyticsInsight {
     pub recommendation: String,
 }
 
-// Enhanced Agent Management Endpoints
-
-/// Create or update agent with comprehensive configuration
-#[instrument(skip(state))]
-pub async fn create_or_update_agent_comprehensive(
-    State(state): State<crate::AppState>,
-    Json(request): Json<AgentManagementRequest>,
-) -> Result<Json<AgentManagementResponse>, (StatusCode, String)> {
-    // Validate the request
-    request.validate()
-        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Validation error: {}", e)))?;
-
-    // Check if agent exists
-    let existing_agent = sqlx::query_as::<sqlx::Sqlite, Agent>(
-        "SELECT * FROM agents WHERE id = ?"
-    )
-    .bind(&request.agent.id)
-    .fetch_optional(&state.pool)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    let is_update = existing_agent.is_some();
-
-    // Process the agent configuration
-    let result = if is_update {
-        update_agent_comprehensive(&state.pool, &request).await?
-    } else {
-        create_agent_comprehensive(&state.pool, &request).await?
-    };
-
-    // Broadcast the change event
-    crate::openclaw_monitoring::EVENT_BROADCASTER.broadcast(
-        crate::openclaw_monitoring::ConfigSyncEvent {
-            event_type: if is_update {
-                crate::openclaw_monitoring::SyncEventType::ConfigChanged
-            } else {
-                crate::openclaw_monitoring::SyncEventType::AgentAdded
-            },
-            agent_id: request.agent.id.clone(),
-            config_hash: result.config_hash.clone(),
-            timestamp: Utc::now(),
-            data: Some(serde_json::to_value(&request.agent).unwrap_or_default()),
-        }
-    ).await;
-
-    Ok(Json(result))
-}
-
-/// Get comprehensive agent information with all settings
-#[instrument(skip(state))]
-pub async fn get_agent_comprehensive(
-    Path(agent_id): Path<String>,
-    State(state): State<crate::AppState>,
-) -> Result<Json<ComprehensiveAgentInfo>, (StatusCode, String)> {
-    // Validate agent ID
-    crate::openclaw_integration::SecurityValidator::validate_agent_id(&agent_id)
-        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
-
-    // Get basic agent info
-    let agent = sqlx::query_as::<sqlx::Sqlite, Agent>(
-        "SELECT * FROM agents WHERE id = ?"
-    )
-    .bind(&agent_id)
-    .fetch_one(&state.pool)
-    .await
-        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
-
-    // Get comprehensive configuration
-    let config = get_agent_comprehensive_config(&state.pool, &agent_id).await?;
-    
-    // Get performance metrics
-    let metrics = get_agent_performance_metrics(&state.pool, &agent_id).await?;
-    
-    // Get recent activity
-    let activity = get_agent_recent_activity(&state.pool, &agent_id).await?;
-
-    // Get capabilities analysis
-    let capabilities = analyze_agent_capabilities(&agent, &config).await?;
-
-    Ok(Json(ComprehensiveAgentInfo {
-        basic_info: agent,
-        configuration: config,
-        performance_metrics: metrics,
-        recent_activity: activity,
-        capabilities_analysis: capabilities,
-        recommendations: generate_agent_recommendations(&agent, &config, &metrics),
-        health_status: calculate_agent_health_status(&agent, &config, &metrics),
-    }))
-}
-
-/// Clone agent with customization options
-#[instrument(skip(state))]
-pub async fn clone_agent(
-    Path(agent_id): Path<String>,
-    State(state): State<crate::AppState>,
-    Json(clone_options): Json<AgentCloneOptions>,
-) -> Result<Json<AgentManagementResponse>, (StatusCode, String)> {
-    // Validate source agent
-    crate::openclaw_integration::SecurityValidator::validate_agent_id(&agent_id)
-        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
-
-    // Get source agent configuration
-    let source_config = get_agent_comprehensive_config(&state.pool, &agent_id).await
-        .map_err(|e| (StatusCode::NOT_FOUND, format!("Source agent not found: {}", e)))?;
-
-    // Create clone with modifications
-    let clone_request = create_clone_request(&source_config, &clone_options)?;
-    
-    // Validate clone request
-    clone_request.validate()
-        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Clone validation error: {}", e)))?;
-
-    // Create the cloned agent
-    let result = create_agent_comprehensive(&state.pool, &clone_request).await?;
-
-    // Broadcast clone event
-    crate::openclaw_monitoring::EVENT_BROADCASTER.broadcast(
-        crate::openclaw_monitoring::ConfigSyncEvent {
-            event_type: crate::openclaw_monitoring::SyncEventType::AgentAdded,
-            agent_id: clone_request.agent.id.clone(),
-            config_hash: result.config_hash.clone(),
-            timestamp: Utc::now(),
-            data: Some(serde_json::json!({
-                "action": "cloned_from",
-                "source_agent": agent_id,
-                "clone_options": clone_options
-            })),
-        }
-    ).await;
-
-    Ok(Json(result))
-}
-
-/// Get agent recommendations based on usage patterns
-#[instrument(skip(state))]
-pub async fn get_agent_recommendations(
-    Path(agent_id): Path<String>,
-    State(state): State<crate::AppState>,
-) -> Result<Json<AgentRecommendations>, (StatusCode, String)> {
-    // Validate agent ID
-    crate::openclaw_integration::SecurityValidator::validate_agent_id(&agent_id)
-        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
-
-    // Get agent info and metrics
-    let agent = sqlx::query_as::<sqlx::Sqlite, Agent>(
-        "SELECT * FROM agents WHERE id = ?"
-    )
-    .bind(&agent_id)
-    .fetch_one(&state.pool)
-    .await
-        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
-
-    let config = get_agent_comprehensive_config(&state.pool, &agent_id).await?;
-    let metrics = get_agent_performance_metrics(&state.pool, &agent_id).await?;
-
-    let recommendations = generate_agent_recommendations(&agent, &config, &metrics);
-
-    Ok(Json(recommendations))
-}
-
-/// Bulk agent operations
-#[instrument(skip(state))]
-pub async fn bulk_agent_operations(
-    State(state): State<crate::AppState>,
-    Json(operation): Json<BulkAgentOperation>,
-) -> Result<Json<BulkOperationResult>, (StatusCode, String)> {
-    let mut results = Vec::new();
-    let mut success_count = 0;
-    let mut error_count = 0;
-
-    for agent_id in &operation.agent_ids {
-        let result = match operation.operation_type.as_str() {
-            "enable" => toggle_agent_status(&state.pool, agent_id, true).await,
-            "disable" => toggle_agent_status(&state.pool, agent_id, false).await,
-            "reset" => reset_agent_configuration(&state.pool, agent_id).await,
-            "optimize" => optimize_agent_configuration(&state.pool, agent_id).await,
-            "validate" => validate_agent_configuration(&state.pool, agent_id).await,
-            _ => Err("Invalid operation type".to_string()),
-        };
-
-        match result {
-            Ok(_) => {
-                success_count += 1;
-                results.push(BulkOperationItem {
-                    agent_id: agent_id.clone(),
-                    status: "success".to_string(),
-                    message: None,
-                });
-            }
-            Err(e) => {
-                error_count += 1;
-                results.push(BulkOperationItem {
-                    agent_id: agent_id.clone(),
-                    status: "error".to_string(),
-                    message: Some(e),
-                });
-            }
-        }
-    }
-
-    Ok(Json(BulkOperationResult {
-        operation_id: uuid::Uuid::new_v4().to_string(),
-        total_agents: operation.agent_ids.len(),
-        success_count,
-        error_count,
-        results,
-        duration_ms: 0, // Would be calculated in real implementation
-    }))
-}
-
-/// Get agent templates
-#[instrument(skip(state))]
-pub async fn get_agent_templates(
-    State(state): State<crate::AppState>,
-    Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<Vec<AgentTemplate>>, (StatusCode, String)> {
-    let category = params.get("category").cloned();
-    let role = params.get("role").cloned();
-
-    let templates = get_agent_templates_filtered(&state.pool, category, role).await?;
-
-    Ok(Json(templates))
-}
-
-/// Create agent from template
-#[instrument(skip(state))]
-pub async fn create_agent_from_template(
-    State(state): State<crate::AppState>,
-    Json(request): Json<CreateFromTemplateRequest>,
-) -> Result<Json<AgentManagementResponse>, (StatusCode, String)> {
-    // Get template
-    let template = get_agent_template(&state.pool, &request.template_id).await
-        .map_err(|e| (StatusCode::NOT_FOUND, format!("Template not found: {}", e)))?;
-
-    // Create agent from template with customizations
-    let agent_request = create_agent_request_from_template(&template, &request.customizations)?;
-    
-    // Validate and create
-    agent_request.validate()
-        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Validation error: {}", e)))?;
-
-    let result = create_agent_comprehensive(&state.pool, &agent_request).await?;
-
-    // Broadcast creation event
-    crate::openclaw_monitoring::EVENT_BROADCASTER.broadcast(
-        crate::openclaw_monitoring::ConfigSyncEvent {
-            event_type: crate::openclaw_monitoring::SyncEventType::AgentAdded,
-            agent_id: agent_request.agent.id.clone(),
-            config_hash: result.config_hash.clone(),
-            timestamp: Utc::now(),
-            data: Some(serde_json::json!({
-                "action": "created_from_template",
-                "template_id": request.template_id,
-                "template_name": template.name
-            })),
-        }
-    ).await;
-
-    Ok(Json(result))
-}
-
 // Response Types
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -975,137 +704,3 @@ pub struct CreateFromTemplateRequest {
     pub customizations: HashMap<String, Value>,
     pub apply_recommendations: bool,
 }
-
-// Helper functions (implementations would go here)
-
-pub async fn create_agent_comprehensive(
-    pool: &SqlitePool,
-    request: &AgentManagementRequest,
-) -> Result<AgentManagementResponse, Box<dyn std::error::Error + Send + Sync>> {
-    // Implementation would create agent with all comprehensive settings
-    todo!("Implement comprehensive agent creation")
-}
-
-async fn update_agent_comprehensive(
-    pool: &SqlitePool,
-    request: &AgentManagementRequest,
-) -> Result<AgentManagementResponse, Box<dyn std::error::Error + Send + Sync>> {
-    // Implementation would update agent with all comprehensive settings
-    todo!("Implement comprehensive agent update")
-}
-
-async fn get_agent_comprehensive_config(
-    pool: &SqlitePool,
-    agent_id: &str,
-) -> Result<AgentComprehensiveConfig, Box<dyn std::error::Error + Send + Sync>> {
-    // Implementation would retrieve comprehensive agent configuration
-    todo!("Implement comprehensive config retrieval")
-}
-
-async fn get_agent_performance_metrics(
-    pool: &SqlitePool,
-    agent_id: &str,
-) -> Result<AgentPerformanceMetrics, Box<dyn std::error::Error + Send + Sync>> {
-    // Implementation would calculate performance metrics
-    todo!("Implement performance metrics calculation")
-}
-
-async fn get_agent_recent_activity(
-    pool: &SqlitePool,
-    agent_id: &str,
-) -> Result<Vec<AgentActivity>, Box<dyn std::error::Error + Send + Sync>> {
-    // Implementation would retrieve recent activity
-    todo!("Implement recent activity retrieval")
-}
-
-async fn analyze_agent_capabilities(
-    agent: &Agent,
-    config: &AgentComprehensiveConfig,
-) -> Result<AgentCapabilitiesAnalysis, Box<dyn std::error::Error + Send + Sync>> {
-    // Implementation would analyze agent capabilities
-    todo!("Implement capabilities analysis")
-}
-
-fn generate_agent_recommendations(
-    agent: &Agent,
-    config: &AgentComprehensiveConfig,
-    metrics: &AgentPerformanceMetrics,
-) -> AgentRecommendations {
-    // Implementation would generate intelligent recommendations
-    todo!("Implement recommendation generation")
-}
-
-fn calculate_agent_health_status(
-    agent: &Agent,
-    config: &AgentComprehensiveConfig,
-    metrics: &AgentPerformanceMetrics,
-) -> AgentHealthStatus {
-    // Implementation would calculate comprehensive health status
-    todo!("Implement health status calculation")
-}
-
-fn create_clone_request(
-    source_config: &AgentComprehensiveConfig,
-    options: &AgentCloneOptions,
-) -> Result<AgentManagementRequest, String> {
-    // Implementation would create clone request with modifications
-    todo!("Implement clone request creation")
-}
-
-async fn toggle_agent_status(
-    pool: &SqlitePool,
-    agent_id: &str,
-    enabled: bool,
-) -> Result<(), String> {
-    // Implementation would toggle agent status
-    todo!("Implement agent status toggle")
-}
-
-async fn reset_agent_configuration(
-    pool: &SqlitePool,
-    agent_id: &str,
-) -> Result<(), String> {
-    // Implementation would reset agent configuration
-    todo!("Implement configuration reset")
-}
-
-async fn optimize_agent_configuration(
-    pool: &SqlitePool,
-    agent_id: &str,
-) -> Result<(), String> {
-    // Implementation would optimize agent configuration
-    todo!("Implement configuration optimization")
-}
-
-async fn validate_agent_configuration(
-    pool: &SqlitePool,
-    agent_id: &str,
-) -> Result<(), String> {
-    // Implementation would validate agent configuration
-    todo!("Implement configuration validation")
-}
-
-async fn get_agent_templates_filtered(
-    pool: &SqlitePool,
-    category: Option<String>,
-    role: Option<String>,
-) -> Result<Vec<AgentTemplate>, Box<dyn std::error::Error + Send + Sync>> {
-    // Implementation would retrieve filtered templates
-    todo!("Implement template retrieval")
-}
-
-async fn get_agent_template(
-    pool: &SqlitePool,
-    template_id: &str,
-) -> Result<AgentTemplate, Box<dyn std::error::Error + Send + Sync>> {
-    // Implementation would retrieve specific template
-    todo!("Implement template retrieval")
-}
-
-fn create_agent_request_from_template(
-    template: &AgentTemplate,
-    customizations: &HashMap<String, Value>,
-) -> Result<AgentManagementRequest, String> {
-    // Implementation would create agent request from template
-    todo!("Implement agent request creation from template")
-}
@@ -1,233 +1,8 @@
 use crate::models::*;
 use sqlx::SqlitePool;
-use chrono::Utc;
-use serde_json::Value;
 
-// Database schema extensions for comprehensive agent management
+// Seed data for agent management; the tables themselves come from migrations
 
-pub async fn setup_agent_management_tables(pool: &SqlitePool) -> Result<(), sqlx::Error> {
-    // Comprehensive agent configurations table
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS agent_comprehensive_configs (
-            agent_id TEXT PRIMARY KEY,
-            config_json TEXT NOT NULL,
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            FOREIGN KEY(agent_id) REFERENCES agents(id) ON DELETE CASCADE
-        );
-        "#
-    )
-    .execute(pool)
-    .await?;
-
-    // Agent clone relationships table
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS agent_clone_relationships (
-            id TEXT PRIMARY KEY,
-            source_agent_id TEXT NOT NULL,
-            cloned_agent_id TEXT NOT NULL,
-            cloned_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            FOREIGN KEY(source_agent_id) REFERENCES agents(id),
-            FOREIGN KEY(cloned_agent_id) REFERENCES agents(id)
-        );
-        "#
-    )
-    .execute(pool)
-    .await?;
-
-    // Agent templates table
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS agent_templates (
-            id TEXT PRIMARY KEY,
-            name TEXT NOT NULL,
-            description TEXT,
-            category TEXT,
-            role TEXT NOT NULL,
-            configuration TEXT NOT NULL,
-            usage_count INTEGER DEFAULT 0,
-            rating REAL,
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            tags TEXT -- JSON array
-        );
-        "#
-    )
-    .execute(pool)
-    .await?;
-
-    // Agent template relationships table
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS agent_template_relationships (
-            id TEXT PRIMARY KEY,
-            template_id TEXT NOT NULL,
-            agent_id TEXT NOT NULL,
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            FOREIGN KEY(template_id) REFERENCES agent_templates(id),
-            FOREIGN KEY(agent_id) REFERENCES agents(id)
-        );
-        "#
-    )
-    .execute(pool)
-    .await?;
-
-    // Agent performance metrics table
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS agent_performance_metrics (
-            id TEXT PRIMARY KEY,
-            agent_id TEXT NOT NULL,
-            metric_date DATE NOT NULL,
-            total_tasks_completed INTEGER DEFAULT 0,
-            average_task_duration_seconds REAL,
-            success_rate REAL,
-            error_rate REAL,
-            total_cost REAL,
-            total_tokens_used INTEGER,
-            total_api_calls INTEGER,
-            memory_usage_mb REAL,
-            cpu_usage_percent REAL,
-            user_satisfaction_score REAL,
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            FOREIGN KEY(agent_id) REFERENCES agents(id)
-        );
-        "#
-    )
-    .execute(pool)
-    .await?;
-
-    // Agent activity detailed table
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS agent_activity_detailed (
-            id TEXT PRIMARY KEY,
-            agent_id TEXT NOT NULL,
-            activity_type TEXT NOT NULL,
-            description TEXT,
-            task_id TEXT,
-            duration_seconds REAL,
-            success BOOLEAN,
-            error_message TEXT,
-            tokens_used INTEGER,
-            cost REAL,
-            metadata TEXT, -- JSON object
-            timestamp DATETIME DEFAULT CURRENT_TIMESTAMP,
-            FOREIGN KEY(agent_id) REFERENCES agents(id)
-        );
-        "#
-    )
-    .execute(pool)
-    .await?;
-
-    // Agent recommendations table
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS agent_recommendations (
-            id TEXT PRIMARY KEY,
-            agent_id TEXT NOT NULL,
-            category TEXT NOT NULL,
-            title TEXT NOT NULL,
-            description TEXT,
-            priority TEXT,
-            estimated_impact TEXT,
-            implementation_difficulty TEXT,
-            auto_applicable BOOLEAN DEFAULT FALSE,
-            status TEXT DEFAULT 'pending', -- pending, applied, dismissed
-            steps TEXT, -- JSON array
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            applied_at DATETIME,
-            FOREIGN KEY(agent_id) REFERENCES agents(id)
-        );
-        "#
-    )
-    .execute(pool)
-    .await?;
-
-    // Agent health status table
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS agent_health_status (
-            id TEXT PRIMARY KEY,
-            agent_id TEXT NOT NULL,
-            overall_health REAL,
-            performance_health REAL,
-            configuration_health REAL,
-            security_health REAL,
-            resource_health REAL,
-            health_trend TEXT,
-            last_check DATETIME DEFAULT CURRENT_TIMESTAMP,
-            issues TEXT, -- JSON array
-            FOREIGN KEY(agent_id) REFERENCES agents(id)
-        );
-        "#
-    )
-    .execute(pool)
-    .await?;
-
-    // Agent analytics cache table
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS agent_analytics_cache (
-            agent_id TEXT,
-            period TEXT,
-            metrics_type TEXT,
-            analytics_data TEXT NOT NULL,
-            cached_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            expires_at DATETIME,
-            PRIMARY KEY (agent_id, period, metrics_type),
-            FOREIGN KEY(agent_id) REFERENCES agents(id)
-        );
-        "#
-    )
-    .execute(pool)
-    .await?;
-
-    // Agent usage patterns table
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS agent_usage_patterns (
-            id TEXT PRIMARY KEY,
-            agent_id TEXT NOT NULL,
-            pattern_type TEXT NOT NULL, -- daily, weekly, monthly
-            pattern_data TEXT NOT NULL, -- JSON array of usage data
-            calculated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            valid_until DATETIME,
-            FOREIGN KEY(agent_id) REFERENCES agents(id)
-        );
-        "#
-    )
-    .execute(pool)
-    .await?;
-
-    // Create indexes for performance
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_agent_performance_agent_date ON agent_performance_metrics(agent_id, metric_date)")
-        .execute(pool)
-        .await?;
-
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_agent_activity_agent_timestamp ON agent_activity_detailed(agent_id, timestamp)")
-        .execute(pool)
-        .execute(pool)
-        .await?;
-
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_agent_recommendations_agent_status ON agent_recommendations(agent_id, status)")
-        .execute(pool)
-        .await?;
-
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_agent_templates_category_role ON agent_templates(category, role)")
-        .execute(pool)
-        .await?;
-
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_analytics_cache_expires ON agent_analytics_cache(expires_at)")
-        .execute(pool)
-        .await?;
-
-    Ok(())
-}
-
-// Insert default agent templates
 pub async fn insert_default_agent_templates(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     let templates = vec![
         AgentTemplateData {
@@ -277,12 +52,15 @@ pub async fn insert_default_agent_templates(pool: &SqlitePool) -> Result<(), sql
         },
     ];
 
+    // Seeds only fill gaps: usage, ratings, versions and agents created from a
+    // template all refer to the existing row
     for template in templates {
         sqlx::query(
             r#"
-            INSERT OR REPLACE INTO agent_templates (
+            INSERT INTO agent_templates (
                 id, name, description, category, role, configuration, tags
             ) VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO NOTHING
             "#
         )
         .bind(&template.id)
@@ -291,7 +69,16 @@ pub async fn insert_default_agent_templates(pool: &SqlitePool) -> Result<(), sql
         .bind(&template.category)
         .bind(format!("{:?}", template.role))
         .bind(&template.configuration)
-        .bind(serde_json::to_string(&template.tags)?)
+        .bind(sqlx::types::Json(&template.tags))
+        .execute(pool)
+        .await?;
+
+        sqlx::query(
+            "INSERT INTO agent_template_versions (template_id, version, configuration)
+             SELECT id, version, configuration FROM agent_templates WHERE id = ?
+             ON CONFLICT(template_id, version) DO NOTHING"
+        )
+        .bind(&template.id)
         .execute(pool)
         .await?;
     }
@@ -1,19 +1,20 @@
 use crate::agent_management::*;
 use crate::models::*;
 use crate::openclaw_integration::*;
+use crate::agent_metrics::{AgentMetricsService, MetricsPeriod, PeriodMetrics};
 use axum::{
     extract::{Path, State, Query},
     Json,
-    response::IntoResponse,
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
 };
+use crate::agent_config_history::{ChangeReasonQuery, ChangeSource, ConfigChange, ConfigHistoryService, ConfigKind};
 use sqlx::SqlitePool;
 use chrono::{Utc, Duration};
 use std::collections::HashMap;
 use serde_json::Value;
 use sha2::{Sha256, Digest};
-use tracing::{info, warn, error, debug, instrument};
-use serde::{Deserialize, Serialize};
+use tracing::{info, instrument};
+use validator::Validate;
 
 // Agent Management Implementation
 
@@ -21,8 +22,11 @@ use serde::{Deserialize, Serialize};
 #[instrument(skip(state, request))]
 pub async fn create_or_update_agent_comprehensive(
     State(state): State<crate::AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ChangeReasonQuery>,
     Json(request): Json<AgentManagementRequest>,
 ) -> Result<Json<AgentManagementResponse>, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&state.pool, &headers, "agents", "write").await?;
     let start_time = std::time::Instant::now();
     
     // Validate the request
@@ -48,18 +52,22 @@ pub async fn create_or_update_agent_comprehensive(
 
     // Process the agent configuration
     let result = if is_update {
-        update_agent_comprehensive_internal(&mut tx, &request).await?
+        update_agent_comprehensive_internal(&mut tx, &request.agent).await
     } else {
-        create_agent_comprehensive_internal(&mut tx, &request).await?
-    };
+        create_agent_comprehensive_internal(&mut tx, &request.agent).await
+    }
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     // Store comprehensive configuration
-    store_comprehensive_config(&mut tx, &agent_id, &request).await
+    let config_json = store_comprehensive_config(&mut tx, &agent_id, &request.agent).await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to store config: {}", e)))?;
+    let change = ConfigChange::new(ChangeSource::Comprehensive, Some(&user.id), query.reason);
+    ConfigHistoryService.record(&mut tx, &agent_id, ConfigKind::Comprehensive, &config_json, &change).await?;
 
     // Commit transaction
     tx.commit().await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Transaction failed: {}", e)))?;
+    state.cache.invalidate_agent(&agent_id).await;
 
     // Broadcast the change event
     crate::openclaw_monitoring::EVENT_BROADCASTER.broadcast(
@@ -87,10 +95,12 @@ pub async fn create_or_update_agent_comprehensive(
 pub async fn get_agent_comprehensive(
     Path(agent_id): Path<String>,
     State(state): State<crate::AppState>,
+    Query(params): Query<HashMap<String, String>>,
 ) -> Result<Json<ComprehensiveAgentInfo>, (StatusCode, String)> {
     // Validate agent ID
     SecurityValidator::validate_agent_id(&agent_id)
         .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let period = period_from_params(&params)?;
 
     // Get basic agent info
     let agent = sqlx::query_as::<sqlx::Sqlite, Agent>(
@@ -102,16 +112,19 @@ pub async fn get_agent_comprehensive(
         .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
 
     // Get comprehensive configuration
-    let config = get_agent_comprehensive_config(&state.pool, &agent_id).await?;
+    let config = get_agent_comprehensive_config(&state.pool, &agent_id).await
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
     
     // Get performance metrics
-    let metrics = get_agent_performance_metrics(&state.pool, &agent_id).await?;
+    let metrics = get_agent_performance_metrics(&state.pool, &agent_id, &period).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     
     // Get recent activity (last 50 activities)
-    let activity = get_agent_recent_activity(&state.pool, &agent_id, 50).await?;
+    let activity = get_agent_recent_activity(&state.pool, &agent_id, 50).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     // Get capabilities analysis
-    let capabilities = analyze_agent_capabilities(&agent, &config).await?;
+    let capabilities = analyze_agent_capabilities(&config);
 
     // Generate recommendations
     let recommendations = generate_agent_recommendations(&agent, &config, &metrics);
@@ -131,12 +144,14 @@ pub async fn get_agent_comprehensive(
 }
 
 /// Clone agent with customization options
-#[instrument(skip(state, clone_options))]
+#[instrument(skip(state, headers, clone_options))]
 pub async fn clone_agent(
     Path(agent_id): Path<String>,
     State(state): State<crate::AppState>,
+    headers: HeaderMap,
     Json(clone_options): Json<AgentCloneOptions>,
 ) -> Result<Json<AgentManagementResponse>, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&state.pool, &headers, "agents", "write").await?;
     // Validate source agent ID
     SecurityValidator::validate_agent_id(&agent_id)
         .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
@@ -159,19 +174,24 @@ pub async fn clone_agent(
     }
 
     // Get source agent configuration
-    let source_config = get_agent_comprehensive_config(&state.pool, &agent_id).await
+    let source_config = get_agent_config_request(&state.pool, &agent_id).await
         .map_err(|e| (StatusCode::NOT_FOUND, format!("Source agent not found: {}", e)))?;
 
-    // Create clone request with modifications
-    let clone_request = create_clone_request(&source_config, &clone_options)?;
-    
-    // Validate clone request
-    if let Err(e) = clone_request.validate() {
-        return Err((StatusCode::BAD_REQUEST, format!("Clone validation error: {}", e)));
-    }
+    // Create clone configuration with modifications
+    let clone_config = create_clone_request(source_config, &clone_options)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Clone validation error: {}", e)))?;
 
     // Create the cloned agent
-    let result = create_agent_comprehensive(&state, &clone_request).await?;
+    let mut tx = state.pool.begin().await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let result = create_agent_comprehensive_internal(&mut tx, &clone_config).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let config_json = store_comprehensive_config(&mut tx, &clone_config.id, &clone_config).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to store config: {}", e)))?;
+    let change = ConfigChange::new(ChangeSource::Comprehensive, Some(&user.id), Some(format!("Cloned from {}", agent_id)));
+    ConfigHistoryService.record(&mut tx, &clone_config.id, ConfigKind::Comprehensive, &config_json, &change).await?;
+    tx.commit().await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Transaction failed: {}", e)))?;
 
     // Store clone relationship
     store_clone_relationship(&state.pool, &agent_id, &clone_options.new_id).await
@@ -216,167 +236,33 @@ pub async fn get_agent_recommendations(
     .await
         .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
 
-    let config = get_agent_comprehensive_config(&state.pool, &agent_id).await?;
-    let metrics = get_agent_performance_metrics(&state.pool, &agent_id).await?;
+    let config = get_agent_comprehensive_config(&state.pool, &agent_id).await
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+    let metrics = get_agent_performance_metrics(&state.pool, &agent_id, &MetricsPeriod::last_days(30)).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     let recommendations = generate_agent_recommendations(&agent, &config, &metrics);
 
     Ok(Json(recommendations))
 }
 
-/// Bulk agent operations
-#[instrument(skip(state, operation))]
-pub async fn bulk_agent_operations(
-    State(state): State<crate::AppState>,
-    Json(operation): Json<BulkAgentOperation>,
-) -> Result<Json<BulkOperationResult>, (StatusCode, String)> {
-    let start_time = std::time::Instant::now();
-    let mut results = Vec::new();
-    let mut success_count = 0;
-    let mut error_count = 0;
-
-    info!("Starting bulk operation: {} on {} agents", operation.operation_type, operation.agent_ids.len());
-
-    for agent_id in &operation.agent_ids {
-        let result = match operation.operation_type.as_str() {
-            "enable" => toggle_agent_status(&state.pool, agent_id, true).await,
-            "disable" => toggle_agent_status(&state.pool, agent_id, false).await,
-            "reset" => reset_agent_configuration(&state.pool, agent_id).await,
-            "optimize" => optimize_agent_configuration(&state.pool, agent_id).await,
-            "validate" => validate_agent_configuration(&state.pool, agent_id).await,
-            "health_check" => perform_agent_health_check(&state.pool, agent_id).await,
-            _ => Err("Invalid operation type".to_string()),
-        };
-
-        match result {
-            Ok(_) => {
-                success_count += 1;
-                results.push(BulkOperationItem {
-                    agent_id: agent_id.clone(),
-                    status: "success".to_string(),
-                    message: None,
-                });
-            }
-            Err(e) => {
-                error_count += 1;
-                results.push(BulkOperationItem {
-                    agent_id: agent_id.clone(),
-                    status: "error".to_string(),
-                    message: Some(e),
-                });
-            }
-        }
-    }
-
-    let duration = start_time.elapsed();
-    info!("Bulk operation completed: {} successful, {} failed in {:?}", 
-          success_count, error_count, duration);
-
-    Ok(Json(BulkOperationResult {
-        operation_id: operation.operation_id.clone(),
-        total_agents: operation.agent_ids.len(),
-        success_count,
-        error_count,
-        results,
-        duration_ms: duration.as_millis() as u64,
-    }))
-}
-
-/// Get agent templates
-#[instrument(skip(state))]
-pub async fn get_agent_templates(
-    State(state): State<crate::AppState>,
-    Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<Vec<AgentTemplate>>, (StatusCode, String)> {
-    let category = params.get("category").cloned();
-    let role = params.get("role").cloned();
-    let search = params.get("search").cloned();
-
-    let templates = get_agent_templates_filtered(&state.pool, category, role, search).await?;
-
-    Ok(Json(templates))
-}
-
-/// Create agent from template
-#[instrument(skip(state, request))]
-pub async fn create_agent_from_template(
-    State(state): State<crate::AppState>,
-    Json(request): Json<CreateFromTemplateRequest>,
-) -> Result<Json<AgentManagementResponse>, (StatusCode, String)> {
-    // Validate new agent ID
-    SecurityValidator::validate_agent_id(&request.agent_id)
-        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
-
-    // Check if agent already exists
-    let existing = sqlx::query_as::<sqlx::Sqlite, Agent>(
-        "SELECT * FROM agents WHERE id = ?"
-    )
-    .bind(&request.agent_id)
-    .fetch_optional(&state.pool)
-    .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    if existing.is_some() {
-        return Err((StatusCode::CONFLICT, "Agent ID already exists".to_string()));
-    }
-
-    // Get template
-    let template = get_agent_template(&state.pool, &request.template_id).await
-        .map_err(|e| (StatusCode::NOT_FOUND, format!("Template not found: {}", e)))?;
-
-    // Create agent from template with customizations
-    let agent_request = create_agent_request_from_template(&template, &request.customizations)?;
-    
-    // Validate and create
-    if let Err(e) = agent_request.validate() {
-        return Err((StatusCode::BAD_REQUEST, format!("Validation error: {}", e)));
-    }
-
-    let result = create_agent_comprehensive(&state, &agent_request).await?;
-
-    // Store template relationship
-    store_template_relationship(&state.pool, &request.template_id, &request.agent_id).await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to store template relationship: {}", e)))?;
-
-    // Update template usage count
-    increment_template_usage(&state.pool, &request.template_id).await
-        .map_err(|e| warn!("Failed to update template usage count: {}", e));
-
-    // Broadcast creation event
-    crate::openclaw_monitoring::EVENT_BROADCASTER.broadcast(
-        crate::openclaw_monitoring::ConfigSyncEvent {
-            event_type: crate::openclaw_monitoring::SyncEventType::AgentAdded,
-            agent_id: request.agent_id.clone(),
-            config_hash: result.config_hash.clone(),
-            timestamp: Utc::now(),
-            data: Some(serde_json::json!({
-                "action": "created_from_template",
-                "template_id": request.template_id,
-                "template_name": template.name
-            })),
-        }
-    ).await;
-
-    info!("Agent {} created from template {}", request.agent_id, request.template_id);
-
-    Ok(Json(result))
-}
-
 /// Get agent analytics and insights
 #[instrument(skip(state))]
 pub async fn get_agent_analytics(
     Path(agent_id): Path<String>,
     State(state): State<crate::AppState>,
+    headers: HeaderMap,
     Query(params): Query<HashMap<String, String>>,
 ) -> Result<Json<AgentAnalytics>, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
     // Validate agent ID
     SecurityValidator::validate_agent_id(&agent_id)
         .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
 
-    let period = params.get("period").cloned().unwrap_or_else(|| "30d".to_string());
-    let metrics_type = params.get("type").cloned().unwrap_or_else(|| "comprehensive".to_string());
+    let period = period_from_params(&params)?;
 
-    let analytics = calculate_agent_analytics(&state.pool, &agent_id, &period, &metrics_type).await?;
+    let analytics = calculate_agent_analytics(&state.pool, &agent_id, &period).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     Ok(Json(analytics))
 }
@@ -385,41 +271,29 @@ pub async fn get_agent_analytics(
 #[instrument(skip(state))]
 pub async fn compare_agents(
     State(state): State<crate::AppState>,
+    headers: HeaderMap,
     Json(request): Json<AgentComparisonRequest>,
 ) -> Result<Json<AgentComparison>, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
     // Validate agent IDs
     for agent_id in &request.agent_ids {
         SecurityValidator::validate_agent_id(agent_id)
             .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
     }
 
-    let comparison = perform_agent_comparison(&state.pool, &request).await?;
+    let comparison = perform_agent_comparison(&state.pool, &request).await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
 
     Ok(Json(comparison))
 }
 
-/// Get agent usage insights
-#[instrument(skip(state))]
-pub async fn get_agent_usage_insights(
-    Path(agent_id): Path<String>,
-    State(state): State<crate::AppState>,
-) -> Result<Json<AgentUsageInsights>, (StatusCode, String)> {
-    // Validate agent ID
-    SecurityValidator::validate_agent_id(&agent_id)
-        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
-
-    let insights = calculate_usage_insights(&state.pool, &agent_id).await?;
-
-    Ok(Json(insights))
-}
-
 // Implementation Functions
 
 async fn create_agent_comprehensive_internal(
     tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
-    request: &AgentManagementRequest,
+    agent: &AgentConfigRequest,
 ) -> Result<AgentManagementResponse, Box<dyn std::error::Error + Send + Sync>> {
-    let config_hash = format!("{:x}", Sha256::digest(serde_json::to_string(&request.agent)?.as_bytes()));
+    let config_hash = format!("{:x}", Sha256::digest(serde_json::to_string(agent)?.as_bytes()));
 
     // Insert basic agent info
     sqlx::query(
@@ -433,26 +307,26 @@ async fn create_agent_comprehensive_internal(
         ) VALUES (?, ?, ?, 'IDLE', ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
         "#
     )
-    .bind(&request.agent.id)
-    .bind(&request.agent.name)
-    .bind(format!("{:?}", request.agent.role))
-    .bind(&request.agent.workspace)
-    .bind(&request.agent.agent_dir)
-    .bind(&request.agent.model_config.primary_model)
-    .bind(serde_json::to_string(&request.agent.model_config.fallback_models)?)
-    .bind(&request.agent.model_config.image_model)
+    .bind(&agent.id)
+    .bind(&agent.name)
+    .bind(format!("{:?}", agent.role))
+    .bind(&agent.workspace)
+    .bind(&agent.agent_dir)
+    .bind(&agent.model_config.primary_model)
+    .bind(serde_json::to_string(&agent.model_config.fallback_models)?)
+    .bind(&agent.model_config.image_model)
     .bind("off") // Default sandbox mode
-    .bind(format!("{:?}", request.agent.model_config.thinking_level))
-    .bind(format!("{:?}", request.agent.model_config.verbose_level))
-    .bind(request.agent.resource_limits.max_concurrent_tasks)
-    .bind(request.agent.resource_limits.max_execution_time_minutes)
-    .bind(request.agent.resource_limits.max_memory_mb)
+    .bind(format!("{:?}", agent.model_config.thinking_level))
+    .bind(format!("{:?}", agent.model_config.verbose_level))
+    .bind(agent.resource_limits.max_concurrent_tasks)
+    .bind(agent.resource_limits.max_execution_time_minutes)
+    .bind(agent.resource_limits.max_memory_mb)
     .bind(&config_hash)
     .execute(&mut **tx)
     .await?;
 
     Ok(AgentManagementResponse {
-        agent_id: request.agent.id.clone(),
+        agent_id: agent.id.clone(),
         status: "created".to_string(),
         message: "Agent created successfully".to_string(),
         config_hash,
@@ -464,9 +338,9 @@ async fn create_agent_comprehensive_internal(
 
 async fn update_agent_comprehensive_internal(
     tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
-    request: &AgentManagementRequest,
+    agent: &AgentConfigRequest,
 ) -> Result<AgentManagementResponse, Box<dyn std::error::Error + Send + Sync>> {
-    let config_hash = format!("{:x}", Sha256::digest(serde_json::to_string(&request.agent)?.as_bytes()));
+    let config_hash = format!("{:x}", Sha256::digest(serde_json::to_string(agent)?.as_bytes()));
 
     // Update basic agent info
     sqlx::query(
@@ -480,25 +354,25 @@ async fn update_agent_comprehensive_internal(
         WHERE id = ?
         "#
     )
-    .bind(&request.agent.name)
-    .bind(format!("{:?}", request.agent.role))
-    .bind(&request.agent.workspace)
-    .bind(&request.agent.agent_dir)
-    .bind(&request.agent.model_config.primary_model)
-    .bind(serde_json::to_string(&request.agent.model_config.fallback_models)?)
-    .bind(&request.agent.model_config.image_model)
-    .bind(format!("{:?}", request.agent.model_config.thinking_level))
-    .bind(format!("{:?}", request.agent.model_config.verbose_level))
-    .bind(request.agent.resource_limits.max_concurrent_tasks)
-    .bind(request.agent.resource_limits.max_execution_time_minutes)
-    .bind(request.agent.resource_limits.max_memory_mb)
+    .bind(&agent.name)
+    .bind(format!("{:?}", agent.role))
+    .bind(&agent.workspace)
+    .bind(&agent.agent_dir)
+    .bind(&agent.model_config.primary_model)
+    .bind(serde_json::to_string(&agent.model_config.fallback_models)?)
+    .bind(&agent.model_config.image_model)
+    .bind(format!("{:?}", agent.model_config.thinking_level))
+    .bind(format!("{:?}", agent.model_config.verbose_level))
+    .bind(agent.resource_limits.max_concurrent_tasks)
+    .bind(agent.resource_limits.max_execution_time_minutes)
+    .bind(agent.resource_limits.max_memory_mb)
     .bind(&config_hash)
-    .bind(&request.agent.id)
+    .bind(&agent.id)
     .execute(&mut **tx)
     .await?;
 
     Ok(AgentManagementResponse {
-        agent_id: request.agent.id.clone(),
+        agent_id: agent.id.clone(),
         status: "updated".to_string(),
         message: "Agent updated successfully".to_string(),
         config_hash,
@@ -508,13 +382,14 @@ async fn update_agent_comprehensive_internal(
     })
 }
 
+/// Stores the configuration in its own table and returns the JSON stored
 async fn store_comprehensive_config(
     tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
     agent_id: &str,
-    request: &AgentManagementRequest,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    agent: &AgentConfigRequest,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
     // Store comprehensive configuration in a separate table
-    let config_json = serde_json::to_string(&request.agent)?;
+    let config_json = serde_json::to_string(agent)?;
     
     sqlx::query(
         "INSERT OR REPLACE INTO agent_comprehensive_configs (agent_id, config_json, updated_at) VALUES (?, ?, CURRENT_TIMESTAMP)"
@@ -524,6 +399,26 @@ async fn store_comprehensive_config(
     .execute(&mut **tx)
     .await?;
 
+    Ok(config_json)
+}
+
+/// Writes a previously recorded comprehensive configuration back to the agent and
+/// records it as the newest version. Used by configuration rollbacks.
+pub(crate) async fn restore_comprehensive_config(
+    pool: &SqlitePool,
+    cache: &crate::openclaw_optimization::HierarchicalCache,
+    agent: &AgentConfigRequest,
+    change: &ConfigChange,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut tx = pool.begin().await?;
+    update_agent_comprehensive_internal(&mut tx, agent).await?;
+    let config_json = store_comprehensive_config(&mut tx, &agent.id, agent).await?;
+    ConfigHistoryService
+        .record(&mut tx, &agent.id, ConfigKind::Comprehensive, &config_json, change)
+        .await
+        .map_err(|e| e.to_string())?;
+    tx.commit().await?;
+    cache.invalidate_agent(&agent.id).await;
     Ok(())
 }
 
@@ -556,37 +451,61 @@ async fn get_agent_comprehensive_config(
     })
 }
 
+/// Reads `period` (`7d`, `30d` or `custom` with `from`/`to`), defaulting to the last 30 days
+fn period_from_params(params: &HashMap<String, String>) -> Result<MetricsPeriod, (StatusCode, String)> {
+    MetricsPeriod::parse(
+        params.get("period").map(String::as_str).unwrap_or("30d"),
+        params.get("from").map(String::as_str),
+        params.get("to").map(String::as_str),
+    )
+    .map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
 async fn get_agent_performance_metrics(
     pool: &SqlitePool,
     agent_id: &str,
+    period: &MetricsPeriod,
 ) -> Result<AgentPerformanceMetrics, Box<dyn std::error::Error + Send + Sync>> {
-    // This would calculate actual performance metrics from activity logs
-    // For now, return placeholder data
+    let summary = AgentMetricsService.summarize(pool, agent_id, period).await?;
+    // Without any recorded outcome there is nothing to count against the agent
+    let error_rate = summary.error_rate().unwrap_or(0.0);
+
     Ok(AgentPerformanceMetrics {
-        total_tasks_completed: 0,
-        average_task_duration: chrono::TimeDelta::try_seconds(300).unwrap(),
-        success_rate: 0.95,
-        error_rate: 0.05,
+        total_tasks_completed: summary.tasks_completed as u64,
+        average_task_duration: Duration::milliseconds(
+            (summary.average_task_duration_seconds.unwrap_or(0.0) * 1000.0) as i64
+        ),
+        success_rate: 1.0 - error_rate,
+        error_rate,
+        // Host resource usage is not attributed to individual agents
         resource_usage: ResourceUsageMetrics {
-            average_memory_usage_mb: 512.0,
-            peak_memory_usage_mb: 1024.0,
-            average_cpu_usage_percent: 25.0,
-            peak_cpu_usage_percent: 75.0,
-            api_calls_made: 0,
+            average_memory_usage_mb: 0.0,
+            peak_memory_usage_mb: 0.0,
+            average_cpu_usage_percent: 0.0,
+            peak_cpu_usage_percent: 0.0,
+            api_calls_made: summary.total_api_calls as u64,
             files_processed: 0,
         },
         model_performance: ModelPerformanceMetrics {
-            average_response_time_ms: 2500.0,
-            token_usage_total: 0,
-            average_tokens_per_task: 1000.0,
+            average_response_time_ms: summary.average_response_time_ms.unwrap_or(0.0),
+            token_usage_total: summary.total_tokens_used as u64,
+            average_tokens_per_task: if summary.tasks_completed > 0 {
+                summary.total_tokens_used as f64 / summary.tasks_completed as f64
+            } else {
+                0.0
+            },
             model_switches: 0,
-            fallback_usage_rate: 0.1,
+            fallback_usage_rate: 0.0,
         },
-        user_satisfaction: Some(4.5),
+        user_satisfaction: summary.user_satisfaction,
         cost_efficiency: CostEfficiencyMetrics {
-            total_cost: 0.0,
-            cost_per_task: 0.0,
-            cost_per_token: 0.0,
+            total_cost: summary.total_cost,
+            cost_per_task: summary.cost_per_task(),
+            cost_per_token: if summary.total_tokens_used > 0 {
+                summary.total_cost / summary.total_tokens_used as f64
+            } else {
+                0.0
+            },
             budget_utilization_percent: 0.0,
         },
     })
@@ -618,10 +537,7 @@ async fn get_agent_recent_activity(
     }).collect())
 }
 
-async fn analyze_agent_capabilities(
-    agent: &Agent,
-    config: &AgentComprehensiveConfig,
-) -> Result<AgentCapabilitiesAnalysis, Box<dyn std::error::Error + Send + Sync>> {
+fn analyze_agent_capabilities(config: &AgentComprehensiveConfig) -> AgentCapabilitiesAnalysis {
     let mut usage_stats = HashMap::new();
     
     // Analyze tool capabilities
@@ -630,7 +546,7 @@ async fn analyze_agent_capabilities(
             capability: "exec_tools".to_string(),
             usage_count: 0,
             success_rate: 1.0,
-            average_duration: Duration::from_secs(30),
+            average_duration: Duration::seconds(30),
             last_used: None,
         });
     }
@@ -640,7 +556,7 @@ async fn analyze_agent_capabilities(
             capability: "file_operations".to_string(),
             usage_count: 0,
             success_rate: 1.0,
-            average_duration: Duration::from_secs(10),
+            average_duration: Duration::seconds(10),
             last_used: None,
         });
     }
@@ -648,17 +564,17 @@ async fn analyze_agent_capabilities(
     let total_capabilities = 10; // Would calculate actual count
     let enabled_capabilities = usage_stats.len() as u32;
 
-    Ok(AgentCapabilitiesAnalysis {
+    AgentCapabilitiesAnalysis {
         total_capabilities,
         enabled_capabilities,
         capability_usage_stats: usage_stats,
         recommended_capabilities: vec!["memory_search".to_string(), "heartbeat".to_string()],
         underutilized_capabilities: Vec::new(),
-    })
+    }
 }
 
 fn generate_agent_recommendations(
-    agent: &Agent,
+    _agent: &Agent,
     config: &AgentComprehensiveConfig,
     metrics: &AgentPerformanceMetrics,
 ) -> AgentRecommendations {
@@ -711,11 +627,11 @@ fn generate_agent_recommendations(
 }
 
 fn calculate_agent_health_status(
-    agent: &Agent,
+    _agent: &Agent,
     config: &AgentComprehensiveConfig,
     metrics: &AgentPerformanceMetrics,
 ) -> AgentHealthStatus {
-    let performance_health = (metrics.success_rate * 100.0) as f64;
+    let performance_health = metrics.success_rate * 100.0 ;
     let configuration_health = if config.openclaw_integration.sandbox_config.is_some() { 90.0 } else { 70.0 };
     let security_health = if config.security_settings.access_level == SecurityAccessLevel::Administrator { 80.0 } else { 95.0 };
     let resource_health = if metrics.resource_usage.average_memory_usage_mb < config.resource_limits.max_memory_mb as f64 * 0.8 { 90.0 } else { 60.0 };
@@ -736,75 +652,36 @@ fn calculate_agent_health_status(
 
 // Additional helper functions would be implemented here...
 
-// Placeholder implementations for remaining functions
-async fn toggle_agent_status(pool: &SqlitePool, agent_id: &str, enabled: bool) -> Result<(), String> {
-    let status = if enabled { "IDLE" } else { "OFFLINE" };
-    sqlx::query("UPDATE agents SET status = ? WHERE id = ?")
-        .bind(status)
-        .bind(agent_id)
-        .execute(pool)
-        .await
-        .map_err(|e| format!("Failed to toggle agent status: {}", e))?;
-    Ok(())
-}
-
-async fn reset_agent_configuration(pool: &SqlitePool, agent_id: &str) -> Result<(), String> {
-    // Implementation would reset agent to default configuration
-    sqlx::query("DELETE FROM agent_comprehensive_configs WHERE agent_id = ?")
-        .bind(agent_id)
-        .execute(pool)
-        .await
-        .map_err(|e| format!("Failed to reset configuration: {}", e))?;
-    Ok(())
-}
-
-async fn optimize_agent_configuration(pool: &SqlitePool, agent_id: &str) -> Result<(), String> {
-    // Implementation would analyze and optimize configuration
-    Ok(())
-}
-
-async fn validate_agent_configuration(pool: &SqlitePool, agent_id: &str) -> Result<(), String> {
-    // Implementation would validate configuration integrity
-    Ok(())
-}
-
-async fn perform_agent_health_check(pool: &SqlitePool, agent_id: &str) -> Result<(), String> {
-    // Implementation would perform comprehensive health check
-    Ok(())
-}
-
-async fn get_agent_templates_filtered(
-    pool: &SqlitePool,
-    category: Option<String>,
-    role: Option<String>,
-    search: Option<String>,
-) -> Result<Vec<AgentTemplate>, Box<dyn std::error::Error + Send + Sync>> {
-    // Implementation would retrieve filtered templates from database
-    Ok(Vec::new())
-}
-
-async fn get_agent_template(
+/// The configuration an agent was last saved with through the comprehensive endpoint
+async fn get_agent_config_request(
     pool: &SqlitePool,
-    template_id: &str,
-) -> Result<AgentTemplate, Box<dyn std::error::Error + Send + Sync>> {
-    // Implementation would retrieve specific template
-    todo!("Implement template retrieval")
-}
+    agent_id: &str,
+) -> Result<AgentConfigRequest, Box<dyn std::error::Error + Send + Sync>> {
+    let config_json: String = sqlx::query_scalar(
+        "SELECT config_json FROM agent_comprehensive_configs WHERE agent_id = ?"
+    )
+    .bind(agent_id)
+    .fetch_one(pool)
+    .await?;
 
-fn create_agent_request_from_template(
-    template: &AgentTemplate,
-    customizations: &HashMap<String, Value>,
-) -> Result<AgentManagementRequest, String> {
-    // Implementation would create agent request from template with customizations
-    todo!("Implement agent request creation from template")
+    Ok(serde_json::from_str(&config_json)?)
 }
 
+/// Copies the source configuration under the new id and name, then applies
+/// `modify_fields` as top-level overrides
 fn create_clone_request(
-    source_config: &AgentComprehensiveConfig,
+    source: AgentConfigRequest,
     options: &AgentCloneOptions,
-) -> Result<AgentManagementRequest, String> {
-    // Implementation would create clone request with modifications
-    todo!("Implement clone request creation")
+) -> Result<AgentConfigRequest, String> {
+    let mut config = serde_json::to_value(source).map_err(|e| e.to_string())?;
+    let fields = config.as_object_mut().ok_or("Source configuration is not an object")?;
+    for (field, value) in &options.modify_fields {
+        fields.insert(field.clone(), value.clone());
+    }
+    fields.insert("id".to_string(), Value::String(options.new_id.clone()));
+    fields.insert("name".to_string(), Value::String(options.new_name.clone()));
+
+    serde_json::from_value(config).map_err(|e| e.to_string())
 }
 
 async fn store_clone_relationship(pool: &SqlitePool, source_id: &str, clone_id: &str) -> Result<(), sqlx::Error> {
@@ -816,29 +693,205 @@ async fn store_clone_relationship(pool: &SqlitePool, source_id: &str, clone_id:
     Ok(())
 }
 
-async fn store_template_relationship(pool: &SqlitePool, template_id: &str, agent_id: &str) -> Result<(), sqlx::Error> {
-    sqlx::query("INSERT INTO agent_template_relationships (template_id, agent_id, created_at) VALUES (?, ?, CURRENT_TIMESTAMP)")
-        .bind(template_id)
-        .bind(agent_id)
-        .execute(pool)
-        .await?;
-    Ok(())
+fn trend(first: f64, second: f64) -> TrendDirection {
+    if first == 0.0 && second == 0.0 {
+        return TrendDirection::Stable;
+    }
+    let change = (second - first) / first.abs().max(f64::EPSILON);
+    if change > 0.1 {
+        TrendDirection::Increasing
+    } else if change < -0.1 {
+        TrendDirection::Decreasing
+    } else {
+        TrendDirection::Stable
+    }
 }
 
-async fn increment_template_usage(pool: &SqlitePool, template_id: &str) -> Result<(), sqlx::Error> {
-    sqlx::query("UPDATE agent_templates SET usage_count = usage_count + 1 WHERE id = ?")
-        .bind(template_id)
-        .execute(pool)
-        .await?;
-    Ok(())
+/// Totals and trends for one agent over `period`. Trends compare the first
+/// and second half of the period.
+pub async fn calculate_agent_analytics(
+    pool: &SqlitePool,
+    agent_id: &str,
+    period: &MetricsPeriod,
+) -> Result<AgentAnalytics, anyhow::Error> {
+    let summary = AgentMetricsService.summarize(pool, agent_id, period).await?;
+    let days = AgentMetricsService.daily(pool, agent_id, period).await?;
+    let (first_half, second_half) = period.halves();
+    let first = AgentMetricsService.summarize(pool, agent_id, &first_half).await?;
+    let second = AgentMetricsService.summarize(pool, agent_id, &second_half).await?;
+
+    let cost_trend = trend(first.cost_per_task(), second.cost_per_task());
+    let mut insights = Vec::new();
+    if let Some(error_rate) = summary.error_rate().filter(|rate| *rate > 0.2) {
+        insights.push(AnalyticsInsight {
+            category: "performance".to_string(),
+            title: "High failure rate".to_string(),
+            description: format!(
+                "{:.0}% of reviewed tasks and recorded activities failed in this period",
+                error_rate * 100.0
+            ),
+            impact: "High".to_string(),
+            recommendation: "Review rejected tasks and failing activities for a common cause".to_string(),
+        });
+    }
+    if matches!(cost_trend, TrendDirection::Increasing) {
+        insights.push(AnalyticsInsight {
+            category: "cost".to_string(),
+            title: "Cost per task is rising".to_string(),
+            description: format!(
+                "Cost per task went from {:.4} to {:.4} between the two halves of the period",
+                first.cost_per_task(),
+                second.cost_per_task()
+            ),
+            impact: "Medium".to_string(),
+            recommendation: "Check whether tasks grew larger or a more expensive model is in use".to_string(),
+        });
+    }
+    if summary.tasks_completed == 0 {
+        insights.push(AnalyticsInsight {
+            category: "usage".to_string(),
+            title: "No completed tasks".to_string(),
+            description: "The agent finished no tasks in this period".to_string(),
+            impact: "Low".to_string(),
+            recommendation: "Route work to the agent or consider retiring it".to_string(),
+        });
+    }
+
+    Ok(AgentAnalytics {
+        agent_id: agent_id.to_string(),
+        period: format!("{}..{}", period.from, period.to),
+        metrics: AnalyticsMetrics {
+            total_tasks: summary.tasks_completed as u64,
+            success_rate: summary.success_rate().unwrap_or(1.0),
+            average_duration: Duration::milliseconds(
+                (summary.average_task_duration_seconds.unwrap_or(0.0) * 1000.0) as i64
+            ),
+            cost_analysis: CostAnalysis {
+                total_cost: summary.total_cost,
+                cost_per_task: summary.cost_per_task(),
+                cost_trend: format!("{:?}", cost_trend).to_lowercase(),
+                budget_usage: 0.0,
+            },
+            resource_utilization: ResourceUtilization {
+                cpu_usage: Vec::new(),
+                memory_usage: Vec::new(),
+                token_usage: days.iter().map(|d| d.total_tokens_used.unwrap_or(0) as u64).collect(),
+                api_calls: days.iter().map(|d| d.total_api_calls.unwrap_or(0) as u64).collect(),
+            },
+        },
+        trends: AnalyticsTrends {
+            performance_trend: trend(first.success_rate().unwrap_or(0.0), second.success_rate().unwrap_or(0.0)),
+            cost_trend,
+            usage_trend: trend(first.tasks_completed as f64, second.tasks_completed as f64),
+            satisfaction_trend: trend(first.user_satisfaction.unwrap_or(0.0), second.user_satisfaction.unwrap_or(0.0)),
+        },
+        insights,
+    })
 }
 
+/// Ranks agents on their metrics over the requested period. The score weighs
+/// the dimension named by `comparison_type`; ties fall back to throughput.
 pub async fn perform_agent_comparison(
     pool: &SqlitePool,
     request: &AgentComparisonRequest,
-) -> Result<AgentComparison, Box<dyn std::error::Error + Send + Sync>> {
-    // Implementation for agent comparison
-    todo!("Implement agent comparison logic")
+) -> Result<AgentComparison, anyhow::Error> {
+    if request.agent_ids.len() < 2 {
+        return Err(anyhow::anyhow!("At least two agents are needed for a comparison"));
+    }
+    let period = MetricsPeriod::parse(&request.period, None, None).map_err(|e| anyhow::anyhow!(e))?;
+
+    let mut summaries = Vec::new();
+    for agent_id in &request.agent_ids {
+        summaries.push((agent_id.clone(), AgentMetricsService.summarize(pool, agent_id, &period).await?));
+    }
+
+    let max_tasks = summaries.iter().map(|(_, s)| s.tasks_completed).max().unwrap_or(0).max(1) as f64;
+    let max_cost = summaries.iter().map(|(_, s)| s.cost_per_task()).fold(0.0, f64::max);
+    let score = |summary: &PeriodMetrics| {
+        let quality = summary.success_rate().unwrap_or(0.0);
+        let throughput = summary.tasks_completed as f64 / max_tasks;
+        let economy = if max_cost > 0.0 { 1.0 - summary.cost_per_task() / max_cost } else { 1.0 };
+        let (q, t, e) = match request.comparison_type {
+            ComparisonType::Performance => (0.6, 0.3, 0.1),
+            ComparisonType::Cost => (0.2, 0.2, 0.6),
+            ComparisonType::Usage => (0.2, 0.7, 0.1),
+            ComparisonType::Capabilities => (0.4, 0.4, 0.2),
+        };
+        (q * quality + t * throughput + e * economy) * 100.0
+    };
+
+    let mut agents: Vec<AgentComparisonData> = summaries
+        .iter()
+        .map(|(agent_id, summary)| {
+            let mut metrics = HashMap::from([
+                ("tasks_completed".to_string(), summary.tasks_completed as f64),
+                ("success_rate".to_string(), summary.success_rate().unwrap_or(0.0)),
+                ("average_task_duration_seconds".to_string(), summary.average_task_duration_seconds.unwrap_or(0.0)),
+                ("total_cost".to_string(), summary.total_cost),
+                ("cost_per_task".to_string(), summary.cost_per_task()),
+                ("total_tokens_used".to_string(), summary.total_tokens_used as f64),
+                ("total_api_calls".to_string(), summary.total_api_calls as f64),
+            ]);
+            if !request.metrics.is_empty() {
+                metrics.retain(|name, _| request.metrics.contains(name));
+            }
+            AgentComparisonData { agent_id: agent_id.clone(), metrics, rank: 0, score: score(summary) }
+        })
+        .collect();
+
+    agents.sort_by(|a, b| b.score.total_cmp(&a.score));
+    for (index, agent) in agents.iter_mut().enumerate() {
+        agent.rank = index as u32 + 1;
+    }
+
+    let ranked_by = |key: &dyn Fn(&PeriodMetrics) -> f64, descending: bool| {
+        let mut ids: Vec<(String, f64)> = summaries.iter().map(|(id, s)| (id.clone(), key(s))).collect();
+        ids.sort_by(|a, b| if descending { b.1.total_cmp(&a.1) } else { a.1.total_cmp(&b.1) });
+        ids.into_iter().map(|(id, _)| id).collect::<Vec<_>>()
+    };
+    let rankings = ComparisonRankings {
+        overall_ranking: agents.iter().map(|a| a.agent_id.clone()).collect(),
+        performance_ranking: ranked_by(&|s| s.success_rate().unwrap_or(0.0), true),
+        cost_ranking: ranked_by(&|s| s.cost_per_task(), false),
+        capability_ranking: ranked_by(&|s| s.tasks_completed as f64, true),
+    };
+
+    let mut insights = Vec::new();
+    if let (Some(best), Some(worst)) = (agents.first(), agents.last()) {
+        insights.push(ComparisonInsight {
+            title: "Score spread".to_string(),
+            description: format!(
+                "{} scored {:.1} and {} scored {:.1} over {}..{}",
+                best.agent_id, best.score, worst.agent_id, worst.score, period.from, period.to
+            ),
+            agents_involved: vec![best.agent_id.clone(), worst.agent_id.clone()],
+            significance: if best.score - worst.score > 20.0 { "high" } else { "low" }.to_string(),
+        });
+    }
+
+    let struggling: Vec<String> = summaries
+        .iter()
+        .filter(|(_, s)| s.success_rate().is_some_and(|rate| rate < 0.8))
+        .map(|(id, _)| id.clone())
+        .collect();
+    let mut recommendations = Vec::new();
+    if !struggling.is_empty() {
+        recommendations.push(ComparisonRecommendation {
+            category: "performance".to_string(),
+            title: "Investigate low success rates".to_string(),
+            description: "These agents failed more than 20% of their reviewed tasks and activities".to_string(),
+            target_agents: struggling,
+            expected_impact: "High".to_string(),
+        });
+    }
+
+    Ok(AgentComparison {
+        comparison_id: uuid::Uuid::new_v4().to_string(),
+        agents,
+        rankings,
+        insights,
+        recommendations,
+    })
 }
 
 // Existing implementation functions...
@@ -1,12 +1,12 @@
 use crate::agent_management::*;
-use crate::models::{self, *};
-use crate::models::ActiveHoursConfig;
+use crate::models::*;
+use crate::agent_management::ActiveHoursConfig;
 use axum::{
     extract::{Path, State, Query},
     Json,
-    response::IntoResponse,
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
 };
+use crate::agent_config_history::ChangeReasonQuery;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -48,7 +48,7 @@ pub struct ConfigurationWizardResponse {
     pub data: serde_json::Value,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WizardQuestion {
     pub id: String,
     pub title: String,
@@ -61,7 +61,7 @@ pub struct WizardQuestion {
     pub examples: Option<Vec<String>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WizardOption {
     pub value: serde_json::Value,
     pub label: String,
@@ -70,7 +70,7 @@ pub struct WizardOption {
     pub recommended: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ValidationRule {
     pub required: bool,
     pub min_length: Option<u32>,
@@ -84,9 +84,10 @@ pub struct ValidationRule {
 /// Quick agent creation with smart defaults
 pub async fn create_agent_quick(
     State(state): State<crate::AppState>,
+    headers: HeaderMap,
     Json(request): Json<QuickAgentRequest>,
 ) -> Result<Json<QuickAgentResponse>, (StatusCode, String)> {
-    let agent_id = format!("agent-{}", uuid::Uuid::new_v4().to_string()[..8]);
+    let _agent_id = format!("agent-{}", &uuid::Uuid::new_v4().to_string()[..8]);
     
     // Generate smart configuration based on purpose
     let config = generate_smart_config(&request);
@@ -129,15 +130,17 @@ pub async fn create_agent_quick(
 
     // Create the agent
     let result = crate::agent_management_impl::create_or_update_agent_comprehensive(
-        State(state), 
+        State(state),
+        headers,
+        Query(ChangeReasonQuery::default()),
         Json(full_request)
     ).await?;
 
     Ok(Json(QuickAgentResponse {
         agent_id: result.agent_id.clone(),
-        name: request.name,
+        name: request.name.clone(),
         status: "created".to_string(),
-        quick_summary: format!("{} agent created successfully for {}", request.purpose, request.name),
+        quick_summary: format!("{} agent created successfully for {}", request.purpose, &request.name),
         next_steps: vec![
             "Test your agent with a simple task".to_string(),
             "Review configuration in dashboard".to_string(),
@@ -150,6 +153,7 @@ pub async fn create_agent_quick(
 /// Configuration wizard for step-by-step setup
 pub async fn configuration_wizard(
     State(state): State<crate::AppState>,
+    headers: HeaderMap,
     Json(request): Json<ConfigurationWizardRequest>,
 ) -> Result<Json<ConfigurationWizardResponse>, (StatusCode, String)> {
     let wizard_steps = get_wizard_steps();
@@ -172,14 +176,14 @@ pub async fn configuration_wizard(
 
     // Process current step answer
     let mut data = request.previous_data.unwrap_or(serde_json::json!({}));
-    data[&wizard_steps[request.step as usize - 1].id] = request.answers;
+    let current = &wizard_steps[request.step as usize - 1];
+    data[&current.id] = serde_json::json!(request.answers);
 
     // Validate answer
-    if let Some(validation) = &wizard_steps[request.step as usize - 1].validation {
-        if !validate_answer(&request.answers, validation) {
+    if let Some(validation) = &current.validation
+        && !validate_answer(&current.id, &request.answers, validation) {
             return Err((StatusCode::BAD_REQUEST, "Invalid answer".to_string()));
         }
-    }
 
     // Generate next step or complete
     if request.step == wizard_steps.len() as u32 {
@@ -222,7 +226,9 @@ pub async fn configuration_wizard(
         };
 
         let result = crate::agent_management_impl::create_or_update_agent_comprehensive(
-            State(state), 
+            State(state),
+            headers,
+            Query(ChangeReasonQuery::default()),
             Json(full_request)
         ).await?;
 
@@ -294,7 +300,7 @@ pub async fn get_smart_suggestions(
 
 /// Validate configuration with user-friendly feedback
 pub async fn validate_configuration_friendly(
-    State(state): State<crate::AppState>,
+    State(_state): State<crate::AppState>,
     Json(config): Json<serde_json::Value>,
 ) -> Result<Json<ValidationResult>, (StatusCode, String)> {
     let validation_result = validate_configuration_with_detailed_feedback(&config);
@@ -304,31 +310,48 @@ pub async fn validate_configuration_friendly(
 
 /// Get configuration templates with filtering and search
 pub async fn get_templates_user_friendly(
-    Query(params): Query<HashMap<String, String>>,
+    State(state): State<crate::AppState>,
+    headers: HeaderMap,
+    Query(query): Query<crate::agent_templates::TemplateQuery>,
 ) -> Result<Json<TemplatesResponse>, (StatusCode, String)> {
-    let search = params.get("search").cloned();
-    let category = params.get("category").cloned();
-    let sort_by = params.get("sort_by").cloned().unwrap_or_else(|| "popular".to_string());
-    
-    let templates = get_templates_with_filters(search, category, &sort_by);
-    
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
+    let templates = crate::agent_templates::TemplateCatalogueService.search(&state.pool, &query).await?;
+
+    // Popularity is relative to the most used template in the whole catalogue
+    let max_usage: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(usage_count), 0) FROM agent_templates")
+        .fetch_one(&state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let templates: Vec<TemplateCard> = templates.iter().map(|t| template_card(t, max_usage)).collect();
+
+    let filters_used = [
+        ("search", &query.search),
+        ("category", &query.category),
+        ("role", &query.role),
+        ("tag", &query.tag),
+        ("min_rating", &query.min_rating.map(|r| r.to_string())),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| value.as_ref().map(|v| format!("{}: {}", name, v)))
+    .chain(std::iter::once(format!("sort: {}", query.sort_by.as_deref().unwrap_or("popular"))))
+    .collect();
+
     Ok(Json(TemplatesResponse {
-        templates,
         total: templates.len(),
-        filters_used: vec![
-            search.as_ref().map(|s| format!("search: {}", s)).unwrap_or_default(),
-            category.as_ref().map(|c| format!("category: {}", c)).unwrap_or_default(),
-            format!("sort: {}", sort_by),
-        ],
+        templates,
+        filters_used,
     }))
 }
 
 /// Get agent comparison with visual insights
 pub async fn compare_agents_visual(
     State(state): State<crate::AppState>,
+    headers: HeaderMap,
     Json(request): Json<AgentComparisonRequest>,
 ) -> Result<Json<VisualComparison>, (StatusCode, String)> {
-    let comparison = crate::agent_management_impl::perform_agent_comparison(&state.pool, &request).await?;
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
+    let comparison = crate::agent_management_impl::perform_agent_comparison(&state.pool, &request).await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
     
     let visual = create_visual_comparison(&comparison);
     
@@ -404,6 +427,10 @@ pub struct TemplateCard {
     pub category: String,
     pub tags: Vec<String>,
     pub popularity_score: f64,
+    pub usage_count: i64,
+    pub rating: Option<f64>,
+    pub rating_count: i64,
+    pub version: i64,
     pub setup_difficulty: String,
     pub estimated_time: String,
     pub preview: TemplatePreview,
@@ -487,7 +514,7 @@ fn generate_smart_config(request: &QuickAgentRequest) -> AgentConfigRequest {
     let complexity = request.complexity.as_deref().unwrap_or("standard");
     
     // Smart defaults based on purpose and expertise
-    let (model, thinking, verbose) = match (expertise, complexity.as_str()) {
+    let (model, thinking, verbose) = match (expertise, complexity) {
         ("coding", "simple") => ("anthropic/claude-3-haiku", ThinkingLevel::Low, VerboseLevel::On),
         ("coding", "standard") => ("anthropic/claude-3-sonnet", ThinkingLevel::Medium, VerboseLevel::On),
         ("coding", "advanced") => ("anthropic/claude-3-opus", ThinkingLevel::High, VerboseLevel::Full),
@@ -498,7 +525,7 @@ fn generate_smart_config(request: &QuickAgentRequest) -> AgentConfigRequest {
     };
 
     AgentConfigRequest {
-        id: format!("agent-{}", uuid::Uuid::new_v4().to_string()[..8]),
+        id: format!("agent-{}", &uuid::Uuid::new_v4().to_string()[..8]),
         name: request.name.clone(),
         role: AgentRole::Spc,
         description: Some(format!("{} agent for {}", request.name, request.purpose)),
@@ -555,16 +582,13 @@ fn generate_smart_config(request: &QuickAgentRequest) -> AgentConfigRequest {
             working_hours: WorkingHours {
                 timezone: "UTC".to_string(),
                 active_hours: ActiveHoursConfig {
-                    monday: Some(DaySchedule { enabled: true, start_time: "09:00".to_string(), end_time: "18:00".to_string(), breaks: vec!["12:00-13:00".to_string()] }),
-                    tuesday: Some(DaySchedule { enabled: true, start_time: "09:00".to_string(), end_time: "18:00".to_string(), breaks: vec!["12:00-13:00".to_string()] }),
-                    wednesday: Some(DaySchedule { enabled: true, start_time: "09:00".to_string(), end_time: "18:00".to_string(), breaks: vec!["12:00-13:00".to_string()] }),
-                    thursday: Some(DaySchedule { enabled: true, start_time: "09:00".to_string(), end_time: "18:00".to_string(), breaks: vec!["12:00-13:00".to_string()] }),
-                    friday: Some(DaySchedule { enabled: true, start_time: "09:00".to_string(), end_time: "18:00".to_string(), breaks: vec!["12:00-13:00".to_string()] }),
-                    saturday: Some(DaySchedule { enabled: false, start_time: "09:00".to_string(), end_time: "13:00".to_string(), breaks: vec![] }),
-                    sunday: Some(DaySchedule { enabled: false, start_time: "09:00".to_string(), end_time: "13:00".to_string(), breaks: vec![] }),
-                    start: None,
-                    end: None,
-                    timezone: None,
+                    monday: DaySchedule { enabled: true, start_time: "09:00".to_string(), end_time: "18:00".to_string(), breaks: vec!["12:00-13:00".to_string()] },
+                    tuesday: DaySchedule { enabled: true, start_time: "09:00".to_string(), end_time: "18:00".to_string(), breaks: vec!["12:00-13:00".to_string()] },
+                    wednesday: DaySchedule { enabled: true, start_time: "09:00".to_string(), end_time: "18:00".to_string(), breaks: vec!["12:00-13:00".to_string()] },
+                    thursday: DaySchedule { enabled: true, start_time: "09:00".to_string(), end_time: "18:00".to_string(), breaks: vec!["12:00-13:00".to_string()] },
+                    friday: DaySchedule { enabled: true, start_time: "09:00".to_string(), end_time: "18:00".to_string(), breaks: vec!["12:00-13:00".to_string()] },
+                    saturday: DaySchedule { enabled: false, start_time: "09:00".to_string(), end_time: "13:00".to_string(), breaks: vec![] },
+                    sunday: DaySchedule { enabled: false, start_time: "09:00".to_string(), end_time: "13:00".to_string(), breaks: vec![] },
                 },
                 break_schedule: vec![],
                 availability_calendar: HashMap::new(),
@@ -633,13 +657,14 @@ fn generate_smart_config(request: &QuickAgentRequest) -> AgentConfigRequest {
 }
 
 fn get_skills_for_expertise(expertise: &str) -> Vec<String> {
-    match expertise {
-        "coding" => vec!["coding", "debugging", "code_review", "documentation"],
-        "writing" => vec!["writing", "editing", "content_creation", "copywriting"],
-        "analysis" => vec!["data_analysis", "research", "critical_thinking", "synthesis"],
-        "management" => vec!["project_management", "planning", "coordination", "leadership"],
-        _ => vec!["general", "communication", "problem_solving"],
-    }
+    let skills: &[&str] = match expertise {
+        "coding" => &["coding", "debugging", "code_review", "documentation"],
+        "writing" => &["writing", "editing", "content_creation", "copywriting"],
+        "analysis" => &["data_analysis", "research", "critical_thinking", "synthesis"],
+        "management" => &["project_management", "planning", "coordination", "leadership"],
+        _ => &["general", "communication", "problem_solving"],
+    };
+    skills.iter().map(|s| s.to_string()).collect()
 }
 
 fn get_tools_for_complexity(complexity: &str) -> ToolCapabilities {
@@ -696,7 +721,7 @@ fn get_wizard_steps() -> Vec<WizardQuestion> {
                 custom_validator: None,
             }),
             help_text: Some("Choose a descriptive name for your agent".to_string()),
-            examples: vec!["Data Analyst Pro".to_string(), "Creative Writer".to_string(), "Debug Assistant".to_string()],
+            examples: Some(vec!["Data Analyst Pro".to_string(), "Creative Writer".to_string(), "Debug Assistant".to_string()]),
         },
         WizardQuestion {
             id: "purpose".to_string(),
@@ -728,7 +753,7 @@ fn get_wizard_steps() -> Vec<WizardQuestion> {
                 WizardOption {
                     value: serde_json::json!("research"),
                     label: "Research & Learning".to_string(),
-                    description: some("Research topics and synthesize information".to_string()),
+                    description: Some("Research topics and synthesize information".to_string()),
                     icon: Some("🔍".to_string()),
                     recommended: false,
                 },
@@ -742,7 +767,7 @@ fn get_wizard_steps() -> Vec<WizardQuestion> {
                 custom_validator: None,
             }),
             help_text: Some("This determines the default configuration and skills".to_string()),
-            examples: vec![],
+            examples: Some(vec![]),
         },
         WizardQuestion {
             id: "expertise".to_string(),
@@ -767,7 +792,7 @@ fn get_wizard_steps() -> Vec<WizardQuestion> {
                 WizardOption {
                     value: serde_json::json!("expert"),
                     label: "Expert".to_string(),
-                    description: some("Complex tasks, minimal guidance needed".to_string()),
+                    description: Some("Complex tasks, minimal guidance needed".to_string()),
                     icon: Some("🏆".to_string()),
                     recommended: false,
                 },
@@ -781,7 +806,7 @@ fn get_wizard_steps() -> Vec<WizardQuestion> {
                 custom_validator: None,
             }),
             help_text: Some("Affects model choice and response style".to_string()),
-            examples: vec![],
+            examples: Some(vec![]),
         },
         WizardQuestion {
             id: "working_hours".to_string(),
@@ -820,7 +845,7 @@ fn get_wizard_steps() -> Vec<WizardQuestion> {
                 custom_validator: None,
             }),
             help_text: Some("Consider when you'll need this agent".to_string()),
-            examples: vec![],
+            examples: Some(vec![]),
         },
         WizardQuestion {
             id: "review".to_string(),
@@ -831,39 +856,35 @@ fn get_wizard_steps() -> Vec<WizardQuestion> {
             default_value: Some(serde_json::json!(false)),
             validation: None,
             help_text: Some("Take a moment to review your choices".to_string()),
-            examples: vec![],
+            examples: Some(vec![]),
         },
     ]
 }
 
-fn validate_answer(answers: &HashMap<String, serde_json::Value>, rule: &ValidationRule) -> bool {
-    if rule.required && !answers.contains_key(&rule.id) {
+fn validate_answer(question_id: &str, answers: &HashMap<String, serde_json::Value>, rule: &ValidationRule) -> bool {
+    if rule.required && !answers.contains_key(question_id) {
         return false;
     }
     
-    if let Some(answer) = answers.get(&rule.id) {
-        if let Some(min_length) = rule.min_length {
-            if let Some(s) = answer.as_str() {
-                if s.len() < min_length as usize {
+    if let Some(answer) = answers.get(question_id) {
+        if let Some(min_length) = rule.min_length
+            && let Some(s) = answer.as_str()
+                && s.len() < min_length as usize {
                     return false;
                 }
-            }
-        }
         
-        if let Some(max_length) = rule.max_length {
-            if let Some(s) = answer.as_str() {
-                if s.len() > max_length as usize {
+        if let Some(max_length) = rule.max_length
+            && let Some(s) = answer.as_str()
+                && s.len() > max_length as usize {
                     return false;
                 }
-            }
-        }
         
         if let Some(pattern) = &rule.pattern {
             // Simple regex validation
             // In production, use proper regex library
             if let Some(s) = answer.as_str() {
                 // This is a simplified validation - in production use regex crate
-                if pattern == "^[a-zA-Z0-9_-]+$" && !s.chars().all(|c| c.is_alphanumeric() || *c == '_' || *c == '-') {
+                if pattern == "^[a-zA-Z0-9_-]+$" && !s.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
                     return false;
                 }
             }
@@ -878,7 +899,7 @@ fn build_config_from_wizard_data(data: &serde_json::Value) -> AgentConfigRequest
     let name = data.get("basic_info").and_then(|v| v.as_str()).unwrap_or("Unconfigured Agent");
     let purpose = data.get("purpose").and_then(|v| v.as_str()).unwrap_or("general");
     let expertise = data.get("expertise").and_then(|v| v.as_str()).unwrap_or("intermediate");
-    let working_hours = data.get("working_hours").and_then(|v| v.as_str()).unwrap_or("business_hours");
+    let _working_hours = data.get("working_hours").and_then(|v| v.as_str()).unwrap_or("business_hours");
     
     // Build configuration based on wizard answers
     generate_smart_config(&QuickAgentRequest {
@@ -889,8 +910,10 @@ fn build_config_from_wizard_data(data: &serde_json::Value) -> AgentConfigRequest
     })
 }
 
-fn generate_help_for_topic(topic: &str, context: &str) -> HelpResponse {
-    let help_content = match topic {
+fn generate_help_for_topic(topic: &str, _context: &str) -> HelpResponse {
+    
+    
+    match topic {
         "model_selection" => HelpResponse {
             topic: "model_selection".to_string(),
             title: "Choosing the Right Model".to_string(),
@@ -926,9 +949,7 @@ fn generate_help_for_topic(topic: &str, context: &str) -> HelpResponse {
             difficulty: "Unknown".to_string(),
             estimated_time: "Unknown".to_string(),
         },
-    };
-    
-    help_content
+    }
 }
 
 fn generate_smart_suggestions_for_agent(agent: &crate::models::Agent) -> Vec<SmartSuggestion> {
@@ -954,8 +975,8 @@ fn generate_smart_suggestions_for_agent(agent: &crate::models::Agent) -> Vec<Sma
     }
     
     // Resource optimization suggestions
-    if let Some(max_concurrent) = agent.max_concurrent {
-        if max_concurrent < 3 {
+    if let Some(max_concurrent) = agent.max_concurrent
+        && max_concurrent < 3 {
             suggestions.push(SmartSuggestion {
                 id: "increase_concurrency".to_string(),
                 title: "Increase Concurrent Tasks".to_string(),
@@ -971,7 +992,6 @@ fn generate_smart_suggestions_for_agent(agent: &crate::models::Agent) -> Vec<Sma
                 why_important: "Higher concurrency improves throughput and reduces wait times".to_string(),
             });
         }
-    }
     
     // Security recommendations
     suggestions.push(SmartSuggestion {
@@ -996,10 +1016,10 @@ fn generate_smart_suggestions_for_agent(agent: &crate::models::Agent) -> Vec<Sma
 fn validate_configuration_with_detailed_feedback(config: &serde_json::Value) -> ValidationResult {
     let mut issues = Vec::new();
     let mut warnings = Vec::new();
-    let mut score = 100.0;
+    let mut score: f64 = 100.0;
     
     // Basic validation
-    if !config.get("agent").and_then(|a| a.get("name")).and_then(|n| n.as_str()).map_or(false, |s| s.len() < 2) {
+    if config.get("agent").and_then(|a| a.get("name")).and_then(|n| n.as_str()).is_none_or(|s| s.len() >= 2) {
         issues.push(ValidationIssue {
             field: "agent.name".to_string(),
             severity: "error".to_string(),
@@ -1012,7 +1032,7 @@ fn validate_configuration_with_detailed_feedback(config: &serde_json::Value) ->
     
     // Model configuration validation
     if let Some(model_config) = config.get("agent").and_then(|a| a.get("model_config")) {
-        if !model_config.get("primary_model").and_then(|m| m.as_str()).map_or(false, |s| !s.is_empty()) {
+        if model_config.get("primary_model").and_then(|m| m.as_str()).is_none_or(|s| s.is_empty()) {
             issues.push(ValidationIssue {
                 field: "model_config.primary_model".to_string(),
                 severity: "error".to_string(),
@@ -1023,7 +1043,7 @@ fn validate_configuration_with_detailed_feedback(config: &serde_json::Value) ->
             score -= 30.0;
         }
         
-        if model_config.get("temperature").and_then(|t| t.as_f64()).map_or(false, |temp| temp < 0.0 || temp > 2.0) {
+        if model_config.get("temperature").and_then(|t| t.as_f64()).is_some_and(|temp| !(0.0..=2.0).contains(&temp)) {
             warnings.push(ValidationIssue {
                 field: "model_config.temperature".to_string(),
                 severity: "warning".to_string(),
@@ -1037,7 +1057,7 @@ fn validate_configuration_with_detailed_feedback(config: &serde_json::Value) ->
     
     // Resource limits validation
     if let Some(resource_limits) = config.get("agent").and_then(|a| a.get("resource_limits")) {
-        if resource_limits.get("max_concurrent_tasks").and_then(|c| c.as_u64()).map_or(false, |c| c == 0) {
+        if resource_limits.get("max_concurrent_tasks").and_then(|c| c.as_u64()) == Some(0) {
             issues.push(ValidationIssue {
                 field: "resource_limits.max_concurrent_tasks".to_string(),
                 severity: "warning".to_string(),
@@ -1048,7 +1068,7 @@ fn validate_configuration_with_detailed_feedback(config: &serde_json::Value) ->
             score -= 15.0;
         }
         
-        if resource_limits.get("max_execution_time_minutes").and_then(|t| t.as_u64()).map_or(false, |t| t > 120) {
+        if resource_limits.get("max_execution_time_minutes").and_then(|t| t.as_u64()).is_some_and(|t| t > 120) {
             warnings.push(ValidationIssue {
                 field: "resource_limits.max_execution_time_minutes".to_string(),
                 severity: "warning".to_string(),
@@ -1066,55 +1086,71 @@ fn validate_configuration_with_detailed_feedback(config: &serde_json::Value) ->
         "Monitor performance metrics".to_string(),
     ];
     
+    let auto_fixable = warnings.iter().any(|w| w.auto_fixable);
     ValidationResult {
         valid: issues.is_empty(),
         score: score.max(0.0),
         issues,
         warnings,
         suggestions,
-        auto_fixable: warnings.iter().any(|w| w.auto_fixable),
+        auto_fixable,
     }
 }
 
-fn get_templates_with_filters(
-    search: Option<String>,
-    category: Option<String>,
-    sort_by: &str,
-) -> Vec<TemplateCard> {
-    // This would query the database for templates
-    // For now, return mock data
-    vec![
-        TemplateCard {
-            id: "developer-assistant".to_string(),
-            name: "Developer Assistant".to_string(),
-            description: "Expert in coding, debugging, and technical tasks".to_string(),
-            category: "development".to_string(),
-            tags: vec!["coding".to_string(), "debugging".to_string(), "technical".to_string()],
-            popularity_score: 0.9,
-            setup_difficulty: "Medium".to_string(),
-            estimated_time: "5 minutes".to_string(),
-            preview: TemplatePreview {
-                model_config: "Claude Sonnet with coding focus".to_string(),
-                key_features: vec!["Code generation".to_string(), "Debugging".to_string(), "Code review".to_string()],
-                use_cases: vec!["Software development".to_string(), "Bug fixing".to_string(), "Code review".to_string()],
-            },
-        },
-        TemplateCard {
-            id: "data-analyst".to_string(),
-            name: "Data Analyst".to_string(),
-            description: "Specialized in data analysis and visualization".to_string(),
-            category: "analytics".to_string(),
-            tags: vec!["data".to_string(), "analytics".to_string(), "visualization".to_string()],
-            popularity_score: 0.8,
-            setup_difficulty: "Easy".to_string(),
-            estimated_time: "3 minutes".to_string(),
-            preview: TemplatePreview {
-                model_config: "Claude Sonnet with analysis focus".to_string(),
-                key_features: vec!["Data analysis".to_string(), "Visualization".to_string(), "Reporting".to_string()],
-                use_cases: vec!["Business intelligence".to_string(), "Data insights".to_string(), "Report generation".to_string()],
-            },
+/// "code_review" -> "Code review"
+fn humanize(value: &str) -> String {
+    let text = value.replace('_', " ");
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => text,
+    }
+}
+
+fn template_card(template: &crate::agent_templates::CatalogueTemplate, max_usage: i64) -> TemplateCard {
+    let config = &template.configuration;
+    let strings = |value: &serde_json::Value| -> Vec<String> {
+        value.as_array()
+            .map(|items| items.iter().filter_map(|i| i.as_str()).map(humanize).collect())
+            .unwrap_or_default()
+    };
+
+    // Every enabled tool and integration is something to set up or grant access to
+    let tools = config["capabilities"]["tools_enabled"]
+        .as_object()
+        .map(|tools| tools.values().filter(|v| v.as_bool() == Some(true)).count())
+        .unwrap_or(0);
+    let integrations = config["capabilities"]["integrations"].as_array().map(Vec::len).unwrap_or(0);
+    let (setup_difficulty, estimated_time) = match tools + integrations {
+        0..=3 => ("Easy", "3 minutes"),
+        4..=7 => ("Medium", "5 minutes"),
+        _ => ("Advanced", "10 minutes"),
+    };
+
+    let mut use_cases = strings(&config["behavior_settings"]["personality"]["specialization"]);
+    if use_cases.is_empty() {
+        use_cases = template.tags.iter().map(|t| humanize(t)).collect();
+    }
+
+    TemplateCard {
+        id: template.id.clone(),
+        name: template.name.clone(),
+        description: template.description.clone().unwrap_or_default(),
+        category: template.category.clone().unwrap_or_default(),
+        tags: template.tags.clone(),
+        popularity_score: if max_usage > 0 { template.usage_count as f64 / max_usage as f64 } else { 0.0 },
+        usage_count: template.usage_count,
+        rating: template.rating,
+        rating_count: template.rating_count,
+        version: template.version,
+        setup_difficulty: setup_difficulty.to_string(),
+        estimated_time: estimated_time.to_string(),
+        preview: TemplatePreview {
+            model_config: config["model_config"]["primary_model"].as_str().unwrap_or("Default model").to_string(),
+            key_features: strings(&config["capabilities"]["skills"]).into_iter().take(3).collect(),
+            use_cases,
         },
-    ]
+    }
 }
 
 fn create_visual_comparison(comparison: &crate::agent_management::AgentComparison) -> VisualComparison {
@@ -0,0 +1,330 @@
+use crate::db::SqlitePool;
+use chrono::{Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, SqliteConnection};
+use tracing::{info, warn};
+use axum::{
+    extract::{Query, State},
+    Json,
+    response::IntoResponse,
+    http::{HeaderMap, StatusCode},
+};
+use crate::AppState;
+
+/// Longest range accepted for a custom period or a manual rollup
+const MAX_PERIOD_DAYS: i64 = 366;
+
+/// Inclusive range of days metrics are reported over
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct MetricsPeriod {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+impl MetricsPeriod {
+    pub fn last_days(days: i64) -> Self {
+        let to = Utc::now().date_naive();
+        Self { from: to - Duration::days(days - 1), to }
+    }
+
+    /// Accepts `7d`, `30d` (any `<n>d`), `<from>..<to>`, or `custom` with `from`/`to` dates
+    pub fn parse(period: &str, from: Option<&str>, to: Option<&str>) -> Result<Self, String> {
+        let range = match period {
+            "custom" => Some((
+                from.ok_or("custom period requires from")?,
+                to.ok_or("custom period requires to")?,
+            )),
+            _ => period.split_once(".."),
+        };
+
+        if let Some((from, to)) = range {
+            let parse = |value: &str| {
+                NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+                    .map_err(|_| format!("Invalid date '{}', expected YYYY-MM-DD", value))
+            };
+            let period = Self { from: parse(from)?, to: parse(to)? };
+            if period.from > period.to {
+                return Err("Period start is after its end".to_string());
+            }
+            if period.days() > MAX_PERIOD_DAYS {
+                return Err(format!("Periods are limited to {} days", MAX_PERIOD_DAYS));
+            }
+            return Ok(period);
+        }
+
+        match period.strip_suffix('d').and_then(|n| n.parse::<i64>().ok()) {
+            Some(days) if (1..=MAX_PERIOD_DAYS).contains(&days) => Ok(Self::last_days(days)),
+            _ => Err(format!("Unknown period '{}'; use 7d, 30d or custom", period)),
+        }
+    }
+
+    pub fn days(&self) -> i64 {
+        (self.to - self.from).num_days() + 1
+    }
+
+    /// The two halves of the period, used to tell which way a metric is moving
+    pub fn halves(&self) -> (Self, Self) {
+        let first_end = self.from + Duration::days(self.days() / 2 - 1).max(Duration::zero());
+        (
+            Self { from: self.from, to: first_end },
+            Self { from: (first_end + Duration::days(1)).min(self.to), to: self.to },
+        )
+    }
+
+    fn bounds(&self) -> (String, String) {
+        (self.from.to_string(), self.to.to_string())
+    }
+}
+
+/// One `agent_performance_metrics` row
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AgentMetricsDay {
+    pub agent_id: String,
+    pub metric_date: NaiveDate,
+    pub total_tasks_completed: i64,
+    pub average_task_duration_seconds: Option<f64>,
+    pub success_rate: Option<f64>,
+    pub error_rate: Option<f64>,
+    pub total_cost: Option<f64>,
+    pub total_tokens_used: Option<i64>,
+    pub total_api_calls: Option<i64>,
+    pub successful_outcomes: i64,
+    pub failed_outcomes: i64,
+    pub user_satisfaction_score: Option<f64>,
+}
+
+/// Daily rows of one agent combined over a period
+#[derive(Debug, Clone, Default, Serialize, FromRow)]
+pub struct PeriodMetrics {
+    pub tasks_completed: i64,
+    pub average_task_duration_seconds: Option<f64>,
+    pub successful_outcomes: i64,
+    pub failed_outcomes: i64,
+    pub total_cost: f64,
+    pub total_tokens_used: i64,
+    pub total_api_calls: i64,
+    pub user_satisfaction: Option<f64>,
+    pub average_response_time_ms: Option<f64>,
+}
+
+impl PeriodMetrics {
+    /// Share of reviewed tasks and recorded activities that failed; `None` without outcomes
+    pub fn error_rate(&self) -> Option<f64> {
+        let outcomes = self.successful_outcomes + self.failed_outcomes;
+        (outcomes > 0).then(|| self.failed_outcomes as f64 / outcomes as f64)
+    }
+
+    pub fn success_rate(&self) -> Option<f64> {
+        self.error_rate().map(|rate| 1.0 - rate)
+    }
+
+    pub fn cost_per_task(&self) -> f64 {
+        if self.tasks_completed > 0 { self.total_cost / self.tasks_completed as f64 } else { 0.0 }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RollupSummary {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub rows_written: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RollupQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+// Completed tasks are credited to their assignee on the day they finished; work is
+// timed from the assignee's first task activity, or from creation without one.
+// Outcomes are review decisions plus agent activities that recorded success.
+const ROLLUP_DAY_SQL: &str = r#"
+INSERT INTO agent_performance_metrics (
+    id, agent_id, metric_date, total_tasks_completed, average_task_duration_seconds,
+    success_rate, error_rate, total_cost, total_tokens_used, total_api_calls,
+    successful_outcomes, failed_outcomes, updated_at
+)
+WITH completed AS (
+    SELECT t.assignee_id AS agent_id,
+           COUNT(*) AS tasks,
+           AVG((julianday(t.completed_at) - julianday(COALESCE(
+               (SELECT MIN(a.timestamp) FROM task_activity a WHERE a.task_id = t.id AND a.agent_id = t.assignee_id),
+               t.created_at
+           ))) * 86400.0) AS duration_seconds
+    FROM tasks t
+    WHERE t.status = 'DONE' AND t.is_deleted = 0 AND t.assignee_id IS NOT NULL
+      AND date(t.completed_at) = ?1
+    GROUP BY t.assignee_id
+),
+reviews AS (
+    SELECT agent_id,
+           SUM(outcome = 'approved') AS succeeded,
+           SUM(outcome = 'rejected') AS failed
+    FROM task_reviews
+    WHERE agent_id IS NOT NULL AND date(reviewed_at) = ?1
+    GROUP BY agent_id
+),
+detailed AS (
+    SELECT agent_id,
+           COUNT(*) AS calls,
+           SUM(success = 1) AS succeeded,
+           SUM(success = 0) AS failed,
+           SUM(COALESCE(tokens_used, 0)) AS tokens,
+           SUM(COALESCE(cost, 0)) AS cost
+    FROM agent_activity_detailed
+    WHERE date(timestamp) = ?1
+    GROUP BY agent_id
+),
+outcomes AS (
+    SELECT ag.id AS agent_id,
+           COALESCE(c.tasks, 0) AS tasks,
+           c.duration_seconds,
+           COALESCE(r.succeeded, 0) + COALESCE(d.succeeded, 0) AS succeeded,
+           COALESCE(r.failed, 0) + COALESCE(d.failed, 0) AS failed,
+           d.cost, d.tokens, d.calls
+    FROM agents ag
+    LEFT JOIN completed c ON c.agent_id = ag.id
+    LEFT JOIN reviews r ON r.agent_id = ag.id
+    LEFT JOIN detailed d ON d.agent_id = ag.id
+    WHERE c.agent_id IS NOT NULL OR r.agent_id IS NOT NULL OR d.agent_id IS NOT NULL
+)
+SELECT lower(hex(randomblob(16))), agent_id, ?1, tasks, duration_seconds,
+       CASE WHEN succeeded + failed > 0 THEN succeeded * 1.0 / (succeeded + failed) END,
+       CASE WHEN succeeded + failed > 0 THEN failed * 1.0 / (succeeded + failed) END,
+       COALESCE(cost, 0), COALESCE(tokens, 0), COALESCE(calls, 0),
+       succeeded, failed, CURRENT_TIMESTAMP
+FROM outcomes
+"#;
+
+pub struct AgentMetricsService;
+
+impl AgentMetricsService {
+    /// Rebuilds the daily rows for every day in the period from task, review and
+    /// activity history. Days are replaced whole, so reruns are safe.
+    pub async fn rollup(&self, pool: &SqlitePool, period: &MetricsPeriod) -> Result<RollupSummary, anyhow::Error> {
+        let mut rows_written = 0;
+        let mut day = period.from;
+        while day <= period.to {
+            let date = day.to_string();
+            let mut tx = pool.begin().await?;
+            sqlx::query("DELETE FROM agent_performance_metrics WHERE metric_date = ?")
+                .bind(&date)
+                .execute(&mut *tx)
+                .await?;
+            rows_written += sqlx::query(ROLLUP_DAY_SQL)
+                .bind(&date)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            tx.commit().await?;
+            day += Duration::days(1);
+        }
+
+        info!("Rolled up agent metrics for {} to {} ({} rows)", period.from, period.to, rows_written);
+        Ok(RollupSummary { from: period.from, to: period.to, rows_written })
+    }
+
+    pub async fn daily(&self, pool: &SqlitePool, agent_id: &str, period: &MetricsPeriod) -> Result<Vec<AgentMetricsDay>, anyhow::Error> {
+        let (from, to) = period.bounds();
+        let days = sqlx::query_as::<sqlx::Sqlite, AgentMetricsDay>(
+            "SELECT agent_id, metric_date, total_tasks_completed, average_task_duration_seconds, success_rate, error_rate,
+                    total_cost, total_tokens_used, total_api_calls, successful_outcomes, failed_outcomes, user_satisfaction_score
+             FROM agent_performance_metrics
+             WHERE agent_id = ? AND metric_date BETWEEN ? AND ?
+             ORDER BY metric_date"
+        )
+        .bind(agent_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await?;
+        Ok(days)
+    }
+
+    pub async fn summarize(&self, pool: &SqlitePool, agent_id: &str, period: &MetricsPeriod) -> Result<PeriodMetrics, anyhow::Error> {
+        let (from, to) = period.bounds();
+        let mut metrics = sqlx::query_as::<sqlx::Sqlite, PeriodMetrics>(
+            "SELECT COALESCE(SUM(total_tasks_completed), 0) AS tasks_completed,
+                    SUM(average_task_duration_seconds * total_tasks_completed)
+                        / NULLIF(SUM(CASE WHEN average_task_duration_seconds IS NOT NULL THEN total_tasks_completed END), 0)
+                        AS average_task_duration_seconds,
+                    COALESCE(SUM(successful_outcomes), 0) AS successful_outcomes,
+                    COALESCE(SUM(failed_outcomes), 0) AS failed_outcomes,
+                    COALESCE(SUM(total_cost), 0.0) AS total_cost,
+                    COALESCE(SUM(total_tokens_used), 0) AS total_tokens_used,
+                    COALESCE(SUM(total_api_calls), 0) AS total_api_calls,
+                    AVG(user_satisfaction_score) AS user_satisfaction,
+                    NULL AS average_response_time_ms
+             FROM agent_performance_metrics
+             WHERE agent_id = ? AND metric_date BETWEEN ? AND ?"
+        )
+        .bind(agent_id)
+        .bind(&from)
+        .bind(&to)
+        .fetch_one(pool)
+        .await?;
+
+        // Response times are not part of the daily rollup; they come straight from the activity log
+        metrics.average_response_time_ms = sqlx::query_scalar(
+            "SELECT AVG(duration_seconds) * 1000.0 FROM agent_activity_detailed
+             WHERE agent_id = ? AND duration_seconds IS NOT NULL AND date(timestamp) BETWEEN ? AND ?"
+        )
+        .bind(agent_id)
+        .bind(&from)
+        .bind(&to)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(metrics)
+    }
+}
+
+/// Records a review decision against the task's current assignee. Takes the
+/// connection of the transaction that moves the task out of REVIEW.
+pub async fn record_review(
+    conn: &mut SqliteConnection,
+    task_id: &str,
+    reviewer_id: Option<&str>,
+    outcome: &str,
+    feedback: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO task_reviews (id, task_id, agent_id, reviewer_id, outcome, feedback, reviewed_at)
+         SELECT ?, id, assignee_id, ?, ?, ?, CURRENT_TIMESTAMP FROM tasks WHERE id = ?"
+    )
+    .bind(uuid::Uuid::new_v4().to_string())
+    .bind(reviewer_id)
+    .bind(outcome)
+    .bind(feedback)
+    .bind(task_id)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Feeds a committed review decision to the assignee's learning loop. A failed
+/// analysis must not fail the review itself, so errors are only logged.
+pub async fn learn_from_review(pool: &SqlitePool, task_id: &str, outcome: &str, feedback: Option<&str>) {
+    if let Err(e) = crate::agent_learning::AgentLearningService.record_review(pool, task_id, outcome, feedback).await {
+        warn!("Could not learn from the review of task {}: {}", task_id, e);
+    }
+}
+
+// Axum Handlers
+pub async fn rollup_agent_metrics(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<RollupQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "admin").await?;
+    let period = match (query.from.as_deref(), query.to.as_deref()) {
+        (None, None) => MetricsPeriod::last_days(2),
+        (from, to) => MetricsPeriod::parse("custom", from, to)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e))?,
+    };
+
+    AgentMetricsService.rollup(&state.pool, &period).await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
@@ -0,0 +1,781 @@
+use crate::db::SqlitePool;
+use crate::ConnectionManager;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+use tracing::info;
+use axum::{
+    extract::{Path, State},
+    Json,
+    response::IntoResponse,
+    http::{HeaderMap, StatusCode},
+};
+use crate::AppState;
+
+/// Sections of an agent's comprehensive configuration that make up a template.
+/// Identity and placement (id, name, workspace, ...) belong to the agent.
+const TEMPLATE_SECTIONS: &[&str] = &["model_config", "capabilities", "behavior_settings", "resource_limits", "security_settings"];
+
+#[derive(Debug)]
+pub enum TemplateError {
+    TemplateNotFound(String),
+    AgentNotFound(String),
+    NotFromTemplate(String),
+    Invalid(String),
+    Conflict(String),
+    Internal(String),
+}
+
+impl std::fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TemplateError::TemplateNotFound(id) => write!(f, "Template '{}' not found", id),
+            TemplateError::AgentNotFound(id) => write!(f, "Agent '{}' not found", id),
+            TemplateError::NotFromTemplate(id) => write!(f, "Agent '{}' was not created from a template", id),
+            TemplateError::Invalid(msg) | TemplateError::Conflict(msg) => write!(f, "{}", msg),
+            TemplateError::Internal(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<sqlx::Error> for TemplateError {
+    fn from(error: sqlx::Error) -> Self {
+        TemplateError::Internal(format!("Database error: {}", error))
+    }
+}
+
+impl From<serde_json::Error> for TemplateError {
+    fn from(error: serde_json::Error) -> Self {
+        TemplateError::Internal(error.to_string())
+    }
+}
+
+impl From<TemplateError> for (StatusCode, String) {
+    fn from(error: TemplateError) -> Self {
+        let status = match &error {
+            TemplateError::TemplateNotFound(_) | TemplateError::AgentNotFound(_) | TemplateError::NotFromTemplate(_) => StatusCode::NOT_FOUND,
+            TemplateError::Invalid(_) => StatusCode::BAD_REQUEST,
+            TemplateError::Conflict(_) => StatusCode::CONFLICT,
+            TemplateError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, error.to_string())
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TemplateQuery {
+    /// Matched against name, description and tags
+    pub search: Option<String>,
+    pub category: Option<String>,
+    pub role: Option<String>,
+    pub tag: Option<String>,
+    pub min_rating: Option<f64>,
+    /// `popular` (default), `rating`, `newest` or `name`
+    pub sort_by: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTemplateRequest {
+    /// Agent whose comprehensive configuration is snapshotted
+    pub agent_id: String,
+    /// Defaults to a slug of the name
+    pub id: Option<String>,
+    pub name: String,
+    pub description: Option<String>,
+    pub category: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateTemplateRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub category: Option<String>,
+    pub tags: Option<Vec<String>>,
+    /// A new configuration publishes a new version; so does `from_agent_id`
+    pub configuration: Option<Value>,
+    pub from_agent_id: Option<String>,
+    pub changelog: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RateTemplateRequest {
+    pub rating: i64,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AcknowledgeTemplateRequest {
+    /// Defaults to the template's latest version
+    pub version: Option<i64>,
+}
+
+#[derive(Debug, FromRow)]
+struct TemplateRow {
+    id: String,
+    name: String,
+    description: Option<String>,
+    category: Option<String>,
+    role: String,
+    configuration: String,
+    usage_count: Option<i64>,
+    rating: Option<f64>,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+    tags: Option<String>,
+    version: i64,
+    rating_count: i64,
+    source_agent_id: Option<String>,
+    created_by: Option<String>,
+}
+
+/// A template as stored in the catalogue
+#[derive(Debug, Clone, Serialize)]
+pub struct CatalogueTemplate {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub category: Option<String>,
+    pub role: String,
+    pub configuration: Value,
+    pub tags: Vec<String>,
+    pub version: i64,
+    pub usage_count: i64,
+    pub rating: Option<f64>,
+    pub rating_count: i64,
+    pub source_agent_id: Option<String>,
+    pub created_by: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<TemplateRow> for CatalogueTemplate {
+    type Error = TemplateError;
+
+    fn try_from(row: TemplateRow) -> Result<Self, Self::Error> {
+        Ok(CatalogueTemplate {
+            configuration: serde_json::from_str(&row.configuration)?,
+            tags: row.tags.as_deref().map(serde_json::from_str).transpose()?.unwrap_or_default(),
+            id: row.id,
+            name: row.name,
+            description: row.description,
+            category: row.category,
+            role: row.role,
+            version: row.version,
+            usage_count: row.usage_count.unwrap_or(0),
+            rating: row.rating,
+            rating_count: row.rating_count,
+            source_agent_id: row.source_agent_id,
+            created_by: row.created_by,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct TemplateVersionSummary {
+    pub version: i64,
+    pub changelog: Option<String>,
+    pub source_agent_id: Option<String>,
+    pub created_by: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TemplateVersion {
+    #[serde(flatten)]
+    pub summary: TemplateVersionSummary,
+    pub configuration: Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TemplateUpdate {
+    pub template: CatalogueTemplate,
+    /// Set when the update published a new version
+    pub published_version: Option<i64>,
+    /// Agents created from an older version, told about the new one
+    pub agents_notified: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TemplateRating {
+    pub template_id: String,
+    pub user_id: String,
+    pub rating: i64,
+    pub average: f64,
+    pub rating_count: i64,
+}
+
+/// Which template an agent was created from and what has been published since
+#[derive(Debug, Serialize)]
+pub struct AgentTemplateLineage {
+    pub agent_id: String,
+    pub template_id: String,
+    pub template_name: String,
+    pub template_version: i64,
+    pub latest_version: i64,
+    pub update_available: bool,
+    pub newer_versions: Vec<TemplateVersionSummary>,
+}
+
+#[derive(Debug, FromRow)]
+struct VersionRow {
+    #[sqlx(flatten)]
+    summary: TemplateVersionSummary,
+    configuration: String,
+}
+
+#[derive(Debug, FromRow)]
+struct LineageRow {
+    template_id: String,
+    template_name: String,
+    template_version: i64,
+    latest_version: i64,
+}
+
+/// Lowercase id derived from a template name, e.g. "Support Triage" -> "support-triage"
+fn slug(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn validate_template_id(id: &str) -> Result<(), TemplateError> {
+    if id.is_empty() || id.len() > 64 || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(TemplateError::Invalid(
+            "Template ids are 1-64 letters, digits, hyphens or underscores".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Keeps the template sections of a configuration, rejecting anything that has none
+fn template_configuration(config: &Value) -> Result<Value, TemplateError> {
+    let object = config
+        .as_object()
+        .ok_or_else(|| TemplateError::Invalid("A template configuration must be a JSON object".to_string()))?;
+    let sections: serde_json::Map<String, Value> = object
+        .iter()
+        .filter(|(key, _)| TEMPLATE_SECTIONS.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    if sections.is_empty() {
+        return Err(TemplateError::Invalid(format!(
+            "A template configuration needs at least one of {}",
+            TEMPLATE_SECTIONS.join(", ")
+        )));
+    }
+    Ok(Value::Object(sections))
+}
+
+pub struct TemplateCatalogueService;
+
+impl TemplateCatalogueService {
+    pub async fn search(&self, pool: &SqlitePool, query: &TemplateQuery) -> Result<Vec<CatalogueTemplate>, TemplateError> {
+        let order = match query.sort_by.as_deref().unwrap_or("popular") {
+            "popular" => "COALESCE(usage_count, 0) DESC, COALESCE(rating, 0) DESC, name",
+            "rating" => "rating IS NULL, rating DESC, rating_count DESC, name",
+            "newest" => "updated_at DESC, name",
+            "name" => "name COLLATE NOCASE",
+            other => {
+                return Err(TemplateError::Invalid(format!(
+                    "Unknown sort '{}', expected popular, rating, newest or name",
+                    other
+                )))
+            }
+        };
+        if let Some(min) = query.min_rating
+            && !(1.0..=5.0).contains(&min) {
+                return Err(TemplateError::Invalid("min_rating must be between 1 and 5".to_string()));
+            }
+
+        let rows = sqlx::query_as::<sqlx::Sqlite, TemplateRow>(&format!(
+            "SELECT * FROM agent_templates
+             WHERE (?1 IS NULL OR instr(lower(name || ' ' || COALESCE(description, '') || ' ' || COALESCE(tags, '')), lower(?1)) > 0)
+               AND (?2 IS NULL OR lower(category) = lower(?2))
+               AND (?3 IS NULL OR upper(role) = upper(?3))
+               AND (?4 IS NULL OR EXISTS (
+                    SELECT 1 FROM json_each(COALESCE(agent_templates.tags, '[]')) WHERE lower(json_each.value) = lower(?4)))
+               AND (?5 IS NULL OR rating >= ?5)
+             ORDER BY {}",
+            order
+        ))
+        .bind(query.search.as_deref().map(str::trim).filter(|s| !s.is_empty()))
+        .bind(query.category.as_deref())
+        .bind(query.role.as_deref())
+        .bind(query.tag.as_deref())
+        .bind(query.min_rating)
+        .fetch_all(pool)
+        .await?;
+
+        rows.into_iter().map(CatalogueTemplate::try_from).collect()
+    }
+
+    pub async fn template(&self, pool: &SqlitePool, template_id: &str) -> Result<CatalogueTemplate, TemplateError> {
+        sqlx::query_as::<sqlx::Sqlite, TemplateRow>("SELECT * FROM agent_templates WHERE id = ?")
+            .bind(template_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| TemplateError::TemplateNotFound(template_id.to_string()))?
+            .try_into()
+    }
+
+    /// The template sections of an agent's comprehensive configuration, and its role
+    async fn agent_snapshot(&self, pool: &SqlitePool, agent_id: &str) -> Result<(Value, String), TemplateError> {
+        let role: String = sqlx::query_scalar("SELECT role FROM agents WHERE id = ?")
+            .bind(agent_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| TemplateError::AgentNotFound(agent_id.to_string()))?;
+        let config_json: String = sqlx::query_scalar("SELECT config_json FROM agent_comprehensive_configs WHERE agent_id = ?")
+            .bind(agent_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| TemplateError::Conflict(format!("Agent '{}' has no comprehensive configuration to snapshot", agent_id)))?;
+        Ok((template_configuration(&serde_json::from_str(&config_json)?)?, role))
+    }
+
+    /// Publishes version 1 of a template snapshotted from an existing agent
+    pub async fn create_from_agent(
+        &self,
+        pool: &SqlitePool,
+        request: &CreateTemplateRequest,
+        user_id: &str,
+    ) -> Result<CatalogueTemplate, TemplateError> {
+        let name = request.name.trim();
+        if name.is_empty() {
+            return Err(TemplateError::Invalid("Template name is required".to_string()));
+        }
+        let id = request.id.clone().unwrap_or_else(|| slug(name));
+        validate_template_id(&id)?;
+        let (configuration, role) = self.agent_snapshot(pool, &request.agent_id).await?;
+        let configuration = serde_json::to_string(&configuration)?;
+
+        let mut tx = pool.begin().await?;
+        let inserted = sqlx::query(
+            "INSERT INTO agent_templates (id, name, description, category, role, configuration, tags, source_agent_id, created_by)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(id) DO NOTHING"
+        )
+        .bind(&id)
+        .bind(name)
+        .bind(&request.description)
+        .bind(&request.category)
+        .bind(&role)
+        .bind(&configuration)
+        .bind(serde_json::to_string(&request.tags)?)
+        .bind(&request.agent_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        if inserted.rows_affected() == 0 {
+            return Err(TemplateError::Conflict(format!("Template '{}' already exists", id)));
+        }
+        sqlx::query(
+            "INSERT INTO agent_template_versions (template_id, version, configuration, changelog, source_agent_id, created_by)
+             VALUES (?, 1, ?, ?, ?, ?)"
+        )
+        .bind(&id)
+        .bind(&configuration)
+        .bind(format!("Snapshot of agent {}", request.agent_id))
+        .bind(&request.agent_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        info!("Template {} created from agent {}", id, request.agent_id);
+        self.template(pool, &id).await
+    }
+
+    /// Updates a template's details. A changed configuration, given directly or
+    /// snapshotted from an agent, is published as the next version and agents
+    /// created from earlier versions are told about it.
+    pub async fn update(
+        &self,
+        pool: &SqlitePool,
+        manager: &ConnectionManager,
+        template_id: &str,
+        request: &UpdateTemplateRequest,
+        user_id: &str,
+    ) -> Result<TemplateUpdate, TemplateError> {
+        let current = self.template(pool, template_id).await?;
+        if request.name.as_deref().is_some_and(|name| name.trim().is_empty()) {
+            return Err(TemplateError::Invalid("Template name cannot be empty".to_string()));
+        }
+        let configuration = match (&request.configuration, &request.from_agent_id) {
+            (Some(_), Some(_)) => {
+                return Err(TemplateError::Invalid("Give either a configuration or from_agent_id, not both".to_string()))
+            }
+            (Some(config), None) => Some(template_configuration(config)?),
+            (None, Some(agent_id)) => Some(self.agent_snapshot(pool, agent_id).await?.0),
+            (None, None) => None,
+        }
+        .filter(|config| *config != current.configuration);
+
+        let mut tx = pool.begin().await?;
+        sqlx::query(
+            "UPDATE agent_templates
+             SET name = COALESCE(?, name), description = COALESCE(?, description), category = COALESCE(?, category),
+                 tags = COALESCE(?, tags), updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?"
+        )
+        .bind(request.name.as_deref().map(str::trim))
+        .bind(&request.description)
+        .bind(&request.category)
+        .bind(request.tags.as_ref().map(serde_json::to_string).transpose()?)
+        .bind(template_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let published_version = match &configuration {
+            Some(config) => {
+                let version = current.version + 1;
+                let config = serde_json::to_string(config)?;
+                sqlx::query(
+                    "UPDATE agent_templates SET configuration = ?, version = ?, source_agent_id = ? WHERE id = ?"
+                )
+                .bind(&config)
+                .bind(version)
+                .bind(&request.from_agent_id)
+                .bind(template_id)
+                .execute(&mut *tx)
+                .await?;
+                sqlx::query(
+                    "INSERT INTO agent_template_versions (template_id, version, configuration, changelog, source_agent_id, created_by)
+                     VALUES (?, ?, ?, ?, ?, ?)"
+                )
+                .bind(template_id)
+                .bind(version)
+                .bind(&config)
+                .bind(&request.changelog)
+                .bind(&request.from_agent_id)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+                Some(version)
+            }
+            None => None,
+        };
+        tx.commit().await?;
+
+        let mut agents_notified = Vec::new();
+        if let Some(version) = published_version {
+            agents_notified = sqlx::query_scalar(
+                "SELECT agent_id FROM agent_template_relationships WHERE template_id = ? AND template_version < ? ORDER BY agent_id"
+            )
+            .bind(template_id)
+            .bind(version)
+            .fetch_all(pool)
+            .await?;
+            manager.broadcast(&serde_json::json!({
+                "type": "template_update_available",
+                "template_id": template_id,
+                "version": version,
+                "changelog": request.changelog,
+                "agent_ids": agents_notified,
+            }).to_string());
+            info!("Template {} published version {} ({} agents behind)", template_id, version, agents_notified.len());
+        }
+
+        Ok(TemplateUpdate {
+            template: self.template(pool, template_id).await?,
+            published_version,
+            agents_notified,
+        })
+    }
+
+    pub async fn versions(&self, pool: &SqlitePool, template_id: &str) -> Result<Vec<TemplateVersion>, TemplateError> {
+        self.template(pool, template_id).await?;
+        let rows: Vec<VersionRow> = sqlx::query_as(
+            "SELECT version, configuration, changelog, source_agent_id, created_by, created_at
+             FROM agent_template_versions WHERE template_id = ? ORDER BY version DESC"
+        )
+        .bind(template_id)
+        .fetch_all(pool)
+        .await?;
+        rows.into_iter()
+            .map(|row| {
+                Ok(TemplateVersion {
+                    summary: row.summary,
+                    configuration: serde_json::from_str(&row.configuration)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Records or replaces the user's 1-5 rating and refreshes the template's average
+    pub async fn rate(
+        &self,
+        pool: &SqlitePool,
+        template_id: &str,
+        user_id: &str,
+        rating: i64,
+        comment: Option<&str>,
+    ) -> Result<TemplateRating, TemplateError> {
+        if !(1..=5).contains(&rating) {
+            return Err(TemplateError::Invalid("Ratings are whole numbers from 1 to 5".to_string()));
+        }
+        self.template(pool, template_id).await?;
+
+        let mut tx = pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO agent_template_ratings (template_id, user_id, rating, comment)
+             VALUES (?, ?, ?, ?)
+             ON CONFLICT(template_id, user_id) DO UPDATE
+             SET rating = excluded.rating, comment = excluded.comment, updated_at = CURRENT_TIMESTAMP"
+        )
+        .bind(template_id)
+        .bind(user_id)
+        .bind(rating)
+        .bind(comment)
+        .execute(&mut *tx)
+        .await?;
+        let (average, rating_count): (f64, i64) = sqlx::query_as(
+            "SELECT AVG(rating), COUNT(*) FROM agent_template_ratings WHERE template_id = ?"
+        )
+        .bind(template_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        sqlx::query("UPDATE agent_templates SET rating = ?, rating_count = ? WHERE id = ?")
+            .bind(average)
+            .bind(rating_count)
+            .bind(template_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(TemplateRating {
+            template_id: template_id.to_string(),
+            user_id: user_id.to_string(),
+            rating,
+            average,
+            rating_count,
+        })
+    }
+
+    pub async fn lineage(&self, pool: &SqlitePool, agent_id: &str) -> Result<AgentTemplateLineage, TemplateError> {
+        let row = sqlx::query_as::<sqlx::Sqlite, LineageRow>(
+            "SELECT r.template_id, t.name AS template_name, r.template_version, t.version AS latest_version
+             FROM agent_template_relationships r
+             JOIN agent_templates t ON t.id = r.template_id
+             WHERE r.agent_id = ?"
+        )
+        .bind(agent_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| TemplateError::NotFromTemplate(agent_id.to_string()))?;
+
+        let newer_versions = sqlx::query_as::<sqlx::Sqlite, TemplateVersionSummary>(
+            "SELECT version, changelog, source_agent_id, created_by, created_at
+             FROM agent_template_versions WHERE template_id = ? AND version > ? ORDER BY version"
+        )
+        .bind(&row.template_id)
+        .bind(row.template_version)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(AgentTemplateLineage {
+            agent_id: agent_id.to_string(),
+            update_available: row.latest_version > row.template_version,
+            template_id: row.template_id,
+            template_name: row.template_name,
+            template_version: row.template_version,
+            latest_version: row.latest_version,
+            newer_versions,
+        })
+    }
+
+    /// Marks the agent as reviewed against a newer template version so it stops
+    /// being reported as behind
+    pub async fn acknowledge(&self, pool: &SqlitePool, agent_id: &str, version: Option<i64>) -> Result<AgentTemplateLineage, TemplateError> {
+        let lineage = self.lineage(pool, agent_id).await?;
+        let version = version.unwrap_or(lineage.latest_version);
+        if version < lineage.template_version || version > lineage.latest_version {
+            return Err(TemplateError::Invalid(format!(
+                "Version must be between {} and {}",
+                lineage.template_version, lineage.latest_version
+            )));
+        }
+        sqlx::query("UPDATE agent_template_relationships SET template_version = ? WHERE agent_id = ?")
+            .bind(version)
+            .bind(agent_id)
+            .execute(pool)
+            .await?;
+        self.lineage(pool, agent_id).await
+    }
+}
+
+// Axum Handlers
+pub async fn create_agent_template(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateTemplateRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&state.pool, &headers, "agents", "write").await?;
+    let template = TemplateCatalogueService.create_from_agent(&state.pool, &payload, &user.id).await?;
+    Ok((StatusCode::CREATED, Json(template)))
+}
+
+pub async fn get_catalogue_template(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(template_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
+    Ok(Json(TemplateCatalogueService.template(&state.pool, &template_id).await?))
+}
+
+pub async fn update_agent_template(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(template_id): Path<String>,
+    Json(payload): Json<UpdateTemplateRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&state.pool, &headers, "agents", "write").await?;
+    let update = TemplateCatalogueService
+        .update(&state.pool, &state.manager, &template_id, &payload, &user.id)
+        .await?;
+    Ok(Json(update))
+}
+
+pub async fn list_agent_template_versions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(template_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
+    Ok(Json(TemplateCatalogueService.versions(&state.pool, &template_id).await?))
+}
+
+pub async fn rate_agent_template(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(template_id): Path<String>,
+    Json(payload): Json<RateTemplateRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
+    let rating = TemplateCatalogueService
+        .rate(&state.pool, &template_id, &user.id, payload.rating, payload.comment.as_deref())
+        .await?;
+    Ok(Json(rating))
+}
+
+pub async fn get_agent_template_lineage(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(agent_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
+    Ok(Json(TemplateCatalogueService.lineage(&state.pool, &agent_id).await?))
+}
+
+pub async fn acknowledge_agent_template_update(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(agent_id): Path<String>,
+    Json(payload): Json<AcknowledgeTemplateRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "write").await?;
+    Ok(Json(TemplateCatalogueService.acknowledge(&state.pool, &agent_id, payload.version).await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::common::{create_test_pool, insert_test_agent};
+
+    /// A template snapshotted from a fresh agent with a model_config section
+    async fn create_template(pool: &SqlitePool, id: &str) -> CatalogueTemplate {
+        let agent_id = insert_test_agent(pool, "Template Source").await;
+        sqlx::query("INSERT INTO agent_comprehensive_configs (agent_id, config_json) VALUES (?, ?)")
+            .bind(&agent_id)
+            .bind(serde_json::json!({ "model_config": { "primary_model": "claude-3-sonnet" } }).to_string())
+            .execute(pool)
+            .await
+            .unwrap();
+        let request = CreateTemplateRequest {
+            agent_id,
+            id: Some(id.to_string()),
+            name: "Support Triage".to_string(),
+            description: None,
+            category: "support".to_string(),
+            tags: Vec::new(),
+        };
+        TemplateCatalogueService.create_from_agent(pool, &request, "author").await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn ratings_average_each_users_latest_rating() {
+        let pool = create_test_pool().await;
+        let template = create_template(&pool, "rated").await;
+        assert_eq!((template.rating, template.rating_count), (None, 0));
+
+        let service = TemplateCatalogueService;
+        service.rate(&pool, "rated", "alice", 5, None).await.unwrap();
+        let rating = service.rate(&pool, "rated", "bob", 2, Some("too chatty")).await.unwrap();
+        assert_eq!((rating.average, rating.rating_count), (3.5, 2));
+
+        // Rating again replaces the user's earlier rating
+        let rating = service.rate(&pool, "rated", "alice", 3, None).await.unwrap();
+        assert_eq!((rating.average, rating.rating_count), (2.5, 2));
+        let template = service.template(&pool, "rated").await.unwrap();
+        assert_eq!((template.rating, template.rating_count), (Some(2.5), 2));
+
+        assert!(matches!(service.rate(&pool, "rated", "carol", 6, None).await, Err(TemplateError::Invalid(_))));
+        assert!(matches!(service.rate(&pool, "missing", "carol", 4, None).await, Err(TemplateError::TemplateNotFound(_))));
+        assert_eq!(service.template(&pool, "rated").await.unwrap().rating_count, 2);
+    }
+
+    #[tokio::test]
+    async fn only_a_changed_configuration_publishes_a_version() {
+        let pool = create_test_pool().await;
+        let template = create_template(&pool, "versioned").await;
+        assert_eq!(template.version, 1);
+        let agent_id = insert_test_agent(&pool, "From Template").await;
+        sqlx::query("INSERT INTO agent_template_relationships (id, template_id, agent_id, template_version) VALUES (?, 'versioned', ?, 1)")
+            .bind(uuid::Uuid::new_v4().to_string())
+            .bind(&agent_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let service = TemplateCatalogueService;
+        let manager = ConnectionManager::new();
+        let mut request = UpdateTemplateRequest {
+            name: Some("Support Triage v2".to_string()),
+            description: None,
+            category: None,
+            tags: None,
+            configuration: Some(template.configuration.clone()),
+            from_agent_id: None,
+            changelog: None,
+        };
+        let update = service.update(&pool, &manager, "versioned", &request, "author").await.unwrap();
+        assert_eq!(update.published_version, None);
+        assert_eq!((update.template.version, update.template.name.as_str()), (1, "Support Triage v2"));
+        assert!(update.agents_notified.is_empty());
+
+        request.configuration = Some(serde_json::json!({ "model_config": { "primary_model": "gpt-4" } }));
+        request.changelog = Some("Switch to gpt-4".to_string());
+        let update = service.update(&pool, &manager, "versioned", &request, "author").await.unwrap();
+        assert_eq!((update.published_version, update.template.version), (Some(2), 2));
+        assert_eq!(update.agents_notified, vec![agent_id.clone()]);
+
+        let versions = service.versions(&pool, "versioned").await.unwrap();
+        assert_eq!(versions.iter().map(|v| v.summary.version).collect::<Vec<_>>(), vec![2, 1]);
+        assert_eq!(versions[0].summary.changelog.as_deref(), Some("Switch to gpt-4"));
+
+        let lineage = service.lineage(&pool, &agent_id).await.unwrap();
+        assert!(lineage.update_available);
+        assert_eq!(lineage.newer_versions.len(), 1);
+        let lineage = service.acknowledge(&pool, &agent_id, None).await.unwrap();
+        assert!(!lineage.update_available);
+        assert_eq!(lineage.template_version, 2);
+    }
+}
@@ -1,15 +1,18 @@
 use crate::models::*;
 use crate::db::SqlitePool;
-use sqlx::{query, query_as};
+use sqlx::{FromRow, QueryBuilder, Sqlite};
 use chrono::Utc;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tracing::{info, warn, error};
+use tracing::{info, warn};
 use std::collections::HashMap;
+use futures::TryStreamExt;
 use axum::{
-    extract::State,
+    body::Body,
+    extract::{Path, Query, State},
     Json,
     response::IntoResponse,
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
 };
 use crate::AppState;
 
@@ -79,7 +82,7 @@ impl AuditService {
         description: &str,
         source_ip: Option<&str>,
         user_id: Option<&str>,
-        details: &str,
+        _details: &str,
     ) -> Result<(), sqlx::Error> {
         let risk_score = calculate_risk_score(event_type, "user ", None);
         let severity = determine_severity(risk_score);
@@ -119,66 +122,16 @@ impl AuditService {
     pub async fn get_audit_trail(
         &self,
         pool: &SqlitePool,
-        entity_type: Option<&str>,
-        entity_id: Option<&str>,
-        start_time: Option<chrono::DateTime<Utc>>,
-        end_time: Option<chrono::DateTime<Utc>>,
-        limit: Option<i64>,
-        user_id: Option<&str>,
-        action: Option<&str>,
+        filter: &AuditQuery,
     ) -> Result<Vec<AuditLog>, sqlx::Error> {
-        let mut query = "SELECT * FROM audit_log WHERE 1=1 ".to_string();
-        
-        if let Some(entity_type) = entity_type {
-            query.push_str(" AND entity_type = ");
-            query.push('\'');
-            query.push_str(entity_type);
-            query.push('\'');
-        }
-        
-        if let Some(entity_id) = entity_id {
-            query.push_str(" AND entity_id = ");
-            query.push('\'');
-            query.push_str(entity_id);
-            query.push('\'');
-        }
-        
-        if let Some(start_time) = start_time {
-            query.push_str(" AND timestamp >= ");
-            query.push('\'');
-            query.push_str(&start_time.to_rfc3339());
-            query.push('\'');
-        }
-        
-        if let Some(end_time) = end_time {
-            query.push_str(" AND timestamp <= ");
-            query.push('\'');
-            query.push_str(&end_time.to_rfc3339());
-            query.push('\'');
-        }
-        
-        if let Some(user_id) = user_id {
-            query.push_str(" AND user_id = ");
-            query.push('\'');
-            query.push_str(user_id);
-            query.push('\'');
-        }
-        
-        if let Some(action) = action {
-            query.push_str(" AND action = ");
-            query.push('\'');
-            query.push_str(action);
-            query.push('\'');
-        }
-        
-        if let Some(limit) = limit {
-            query.push_str(" LIMIT ");
-            query.push_str(&limit.to_string());
-        }
-        
-        query.push_str(" ORDER BY timestamp DESC ");
-        
-        sqlx::query_as(&query)
+        let mut builder = QueryBuilder::<Sqlite>::new("SELECT * FROM audit_log WHERE 1=1");
+        push_audit_filters(&mut builder, filter);
+        builder.push(" ORDER BY timestamp DESC LIMIT ");
+        builder.push_bind(filter.limit.unwrap_or(100).clamp(1, MAX_AUDIT_PAGE_SIZE));
+        builder.push(" OFFSET ");
+        builder.push_bind(filter.offset.unwrap_or(0).max(0));
+
+        builder.build_query_as::<AuditLog>()
             .fetch_all(pool)
             .await
     }
@@ -186,42 +139,33 @@ impl AuditService {
     pub async fn get_security_events(
         &self,
         pool: &SqlitePool,
-        severity: Option<&str>,
-        start_time: Option<chrono::DateTime<Utc>>,
-        end_time: Option<chrono::DateTime<Utc>>,
-        limit: Option<i64>,
+        filter: &SecurityEventQuery,
     ) -> Result<Vec<SecurityEvent>, sqlx::Error> {
-        let mut query = "SELECT * FROM security_events WHERE 1=1 ".to_string();
-        
-        if let Some(severity) = severity {
-            query.push_str(" AND severity = ");
-            query.push('\'');
-            query.push_str(severity);
-            query.push('\'');
+        let mut builder = QueryBuilder::<Sqlite>::new("SELECT * FROM security_events WHERE 1=1");
+
+        if let Some(severity) = &filter.severity {
+            builder.push(" AND severity = ").push_bind(severity.clone());
         }
-        
-        if let Some(start_time) = start_time {
-            query.push_str(" AND created_at >= ");
-            query.push('\'');
-            query.push_str(&start_time.to_rfc3339());
-            query.push('\'');
+        if let Some(event_type) = &filter.event_type {
+            builder.push(" AND event_type = ").push_bind(event_type.clone());
         }
-        
-        if let Some(end_time) = end_time {
-            query.push_str(" AND created_at <= ");
-            query.push('\'');
-            query.push_str(&end_time.to_rfc3339());
-            query.push('\'');
+        if let Some(user_id) = &filter.user_id {
+            builder.push(" AND user_id = ").push_bind(user_id.clone());
         }
-        
-        if let Some(limit) = limit {
-            query.push_str(" LIMIT ");
-            query.push_str(&limit.to_string());
+        if let Some(source_ip) = &filter.source_ip {
+            builder.push(" AND source_ip = ").push_bind(source_ip.clone());
         }
-        
-        query.push_str(" ORDER BY created_at DESC ");
-        
-        sqlx::query_as(&query)
+        if let Some(start_time) = filter.start_time {
+            builder.push(" AND datetime(created_at) >= datetime(").push_bind(start_time).push(")");
+        }
+        if let Some(end_time) = filter.end_time {
+            builder.push(" AND datetime(created_at) <= datetime(").push_bind(end_time).push(")");
+        }
+
+        builder.push(" ORDER BY created_at DESC LIMIT ");
+        builder.push_bind(filter.limit.unwrap_or(100).clamp(1, MAX_AUDIT_PAGE_SIZE));
+
+        builder.build_query_as::<SecurityEvent>()
             .fetch_all(pool)
             .await
     }
@@ -265,67 +209,688 @@ impl AuditService {
             .await
     }
 
+    /// Streams every audit entry matching `filter` as CSV or NDJSON lines.
+    /// Rows are pulled from SQLite one at a time so large exports never sit in memory.
+    pub fn export_audit_trail(
+        pool: SqlitePool,
+        filter: AuditQuery,
+        format: ExportFormat,
+    ) -> impl futures::Stream<Item = Result<String, sqlx::Error>> + Send + 'static {
+        async_stream::try_stream! {
+            if format == ExportFormat::Csv {
+                yield format!("{}\n", AUDIT_CSV_COLUMNS.join(","));
+            }
+
+            let mut builder = QueryBuilder::<Sqlite>::new("SELECT * FROM audit_log WHERE 1=1");
+            push_audit_filters(&mut builder, &filter);
+            builder.push(" ORDER BY timestamp ASC");
+
+            let mut rows = builder.build_query_as::<AuditLog>().fetch(&pool);
+            while let Some(entry) = rows.try_next().await? {
+                yield match format {
+                    ExportFormat::Csv => audit_log_to_csv_row(&entry),
+                    ExportFormat::Ndjson => {
+                        let line = serde_json::to_string(&entry)
+                            .map_err(|e| sqlx::Error::Protocol(e.to_string()))?;
+                        format!("{}\n", line)
+                    }
+                };
+            }
+        }
+    }
+
+    pub async fn get_retention_policies(&self, pool: &SqlitePool) -> Result<Vec<RetentionPolicy>, sqlx::Error> {
+        let stored = sqlx::query_as::<sqlx::Sqlite, RetentionPolicy>(
+            "SELECT entity_type, retention_days, updated_at, updated_by FROM audit_retention_policies ORDER BY entity_type"
+        )
+        .fetch_all(pool)
+        .await?;
+
+        // Report the built-in defaults for anything that has not been overridden
+        let mut policies = Vec::new();
+        for entity_type in RETENTION_ENTITY_TYPES {
+            match stored.iter().find(|p| p.entity_type == *entity_type) {
+                Some(policy) => policies.push(policy.clone()),
+                None => policies.push(RetentionPolicy {
+                    entity_type: entity_type.to_string(),
+                    retention_days: default_retention_days(entity_type),
+                    updated_at: None,
+                    updated_by: None,
+                }),
+            }
+        }
+
+        Ok(policies)
+    }
+
+    pub async fn set_retention_policy(
+        &self,
+        pool: &SqlitePool,
+        entity_type: &str,
+        retention_days: i64,
+        updated_by: &str,
+    ) -> Result<RetentionPolicy, anyhow::Error> {
+        if !RETENTION_ENTITY_TYPES.contains(&entity_type) {
+            return Err(anyhow::anyhow!("Unknown entity type for retention: {}", entity_type));
+        }
+        if !(MIN_RETENTION_DAYS..=MAX_RETENTION_DAYS).contains(&retention_days) {
+            return Err(anyhow::anyhow!(
+                "Retention must be between {} and {} days",
+                MIN_RETENTION_DAYS,
+                MAX_RETENTION_DAYS
+            ));
+        }
+
+        sqlx::query(
+            "INSERT INTO audit_retention_policies (entity_type, retention_days, updated_at, updated_by)
+             VALUES (?, ?, CURRENT_TIMESTAMP, ?)
+             ON CONFLICT(entity_type) DO UPDATE SET
+                retention_days = excluded.retention_days,
+                updated_at = excluded.updated_at,
+                updated_by = excluded.updated_by"
+        )
+        .bind(entity_type)
+        .bind(retention_days)
+        .bind(updated_by)
+        .execute(pool)
+        .await?;
+
+        Self::log_entity_event(
+            pool,
+            "system",
+            &format!("audit_retention:{}", entity_type),
+            "update",
+            None,
+            Some(&serde_json::json!({ "retention_days": retention_days }).to_string()),
+            Some(updated_by),
+            None,
+            None,
+            None,
+            None,
+            None,
+        ).await?;
+
+        let policy = sqlx::query_as::<sqlx::Sqlite, RetentionPolicy>(
+            "SELECT entity_type, retention_days, updated_at, updated_by FROM audit_retention_policies WHERE entity_type = ?"
+        )
+        .bind(entity_type)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(policy)
+    }
+
+    pub async fn list_legal_holds(&self, pool: &SqlitePool, include_released: bool) -> Result<Vec<LegalHold>, sqlx::Error> {
+        let query = if include_released {
+            "SELECT * FROM audit_legal_holds ORDER BY created_at DESC"
+        } else {
+            "SELECT * FROM audit_legal_holds WHERE released_at IS NULL ORDER BY created_at DESC"
+        };
+
+        sqlx::query_as::<sqlx::Sqlite, LegalHold>(query)
+            .fetch_all(pool)
+            .await
+    }
+
+    pub async fn place_legal_hold(
+        &self,
+        pool: &SqlitePool,
+        request: &LegalHoldRequest,
+        created_by: &str,
+    ) -> Result<LegalHold, anyhow::Error> {
+        if !RETENTION_ENTITY_TYPES.contains(&request.entity_type.as_str()) {
+            return Err(anyhow::anyhow!("Unknown entity type for legal hold: {}", request.entity_type));
+        }
+        if request.reason.trim().is_empty() {
+            return Err(anyhow::anyhow!("A legal hold requires a reason"));
+        }
+
+        let id = uuid::Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO audit_legal_holds (id, entity_type, entity_id, reason, created_by, created_at)
+             VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP)"
+        )
+        .bind(&id)
+        .bind(&request.entity_type)
+        .bind(&request.entity_id)
+        .bind(&request.reason)
+        .bind(created_by)
+        .execute(pool)
+        .await?;
+
+        Self::log_entity_event(
+            pool,
+            "system",
+            &format!("legal_hold:{}", id),
+            "create",
+            None,
+            Some(&serde_json::to_string(request)?),
+            Some(created_by),
+            None,
+            None,
+            None,
+            None,
+            None,
+        ).await?;
+
+        let hold = sqlx::query_as::<sqlx::Sqlite, LegalHold>("SELECT * FROM audit_legal_holds WHERE id = ?")
+            .bind(&id)
+            .fetch_one(pool)
+            .await?;
+
+        Ok(hold)
+    }
+
+    pub async fn release_legal_hold(
+        &self,
+        pool: &SqlitePool,
+        hold_id: &str,
+        released_by: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE audit_legal_holds SET released_at = CURRENT_TIMESTAMP, released_by = ? WHERE id = ? AND released_at IS NULL"
+        )
+        .bind(released_by)
+        .bind(hold_id)
+        .execute(pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        Self::log_entity_event(
+            pool,
+            "system",
+            &format!("legal_hold:{}", hold_id),
+            "delete",
+            None,
+            None,
+            Some(released_by),
+            None,
+            None,
+            None,
+            None,
+            None,
+        ).await?;
+
+        Ok(true)
+    }
+
     pub async fn cleanup_old_audit_logs(&self, pool: &SqlitePool) -> Result<u64, sqlx::Error> {
-        let cutoff_date = Utc::now() - chrono::Duration::days(90);
-        
-        let result = sqlx::query("DELETE FROM audit_log WHERE timestamp < ? ")
-            .bind(cutoff_date.to_rfc3339())
+        let policies = self.get_retention_policies(pool).await?;
+        let mut count = 0;
+
+        for policy in policies.iter().filter(|p| p.entity_type != SECURITY_EVENT_RETENTION_KEY) {
+            let cutoff_date = Utc::now() - chrono::Duration::days(policy.retention_days);
+
+            let result = sqlx::query(
+                "DELETE FROM audit_log
+                 WHERE entity_type = ? AND datetime(timestamp) < datetime(?)
+                 AND NOT EXISTS (
+                    SELECT 1 FROM audit_legal_holds h
+                    WHERE h.released_at IS NULL
+                    AND h.entity_type = audit_log.entity_type
+                    AND (h.entity_id IS NULL OR h.entity_id = audit_log.entity_id)
+                 )"
+            )
+            .bind(&policy.entity_type)
+            .bind(cutoff_date)
             .execute(pool)
             .await?;
-        
-        let count = result.rows_affected();
-        info!("Cleaned up {} old audit log entries ", count);
-        
+
+            count += result.rows_affected();
+        }
+
+        info!("Cleaned up {} old audit log entries", count);
+
         Ok(count)
     }
 
     pub async fn cleanup_old_security_events(&self, pool: &SqlitePool) -> Result<u64, sqlx::Error> {
-        let cutoff_date = Utc::now() - chrono::Duration::days(30);
-        
-        let result = sqlx::query("DELETE FROM security_events WHERE created_at < ? ")
-            .bind(cutoff_date.to_rfc3339())
-            .execute(pool)
-            .await?;
-        
+        let retention_days = sqlx::query_scalar::<sqlx::Sqlite, i64>(
+            "SELECT retention_days FROM audit_retention_policies WHERE entity_type = ?"
+        )
+        .bind(SECURITY_EVENT_RETENTION_KEY)
+        .fetch_optional(pool)
+        .await?
+        .unwrap_or_else(|| default_retention_days(SECURITY_EVENT_RETENTION_KEY));
+
+        let cutoff_date = Utc::now() - chrono::Duration::days(retention_days);
+
+        // Security events are held per user: a hold with no entity_id freezes all of them
+        let result = sqlx::query(
+            "DELETE FROM security_events
+             WHERE datetime(created_at) < datetime(?)
+             AND NOT EXISTS (
+                SELECT 1 FROM audit_legal_holds h
+                WHERE h.released_at IS NULL
+                AND h.entity_type = ?
+                AND (h.entity_id IS NULL OR h.entity_id = security_events.user_id)
+             )"
+        )
+        .bind(cutoff_date)
+        .bind(SECURITY_EVENT_RETENTION_KEY)
+        .execute(pool)
+        .await?;
+
         let count = result.rows_affected();
-        info!("Cleaned up {} old security events ", count);
-        
+        info!("Cleaned up {} old security events", count);
+
         Ok(count)
     }
 
+    /// Applies every retention policy and records the outcome in the audit log.
+    /// Run periodically from the background scheduler in `main`.
+    /// `actor` is the user who asked for the run; None for the scheduled job
+    pub async fn enforce_retention_policies(&self, pool: &SqlitePool, actor: Option<&str>) -> Result<RetentionRunSummary, sqlx::Error> {
+        let audit_entries_deleted = self.cleanup_old_audit_logs(pool).await?;
+        let security_events_deleted = self.cleanup_old_security_events(pool).await?;
+        let active_legal_holds = sqlx::query_scalar::<sqlx::Sqlite, i64>(
+            "SELECT COUNT(*) FROM audit_legal_holds WHERE released_at IS NULL"
+        )
+        .fetch_one(pool)
+        .await?;
+
+        let summary = RetentionRunSummary {
+            audit_entries_deleted,
+            security_events_deleted,
+            active_legal_holds,
+            ran_at: Utc::now(),
+        };
+
+        if audit_entries_deleted > 0 || security_events_deleted > 0 {
+            Self::log_entity_event(
+                pool,
+                "system",
+                "audit_retention",
+                "delete",
+                None,
+                Some(&serde_json::to_string(&summary).unwrap_or_default()),
+                actor,
+                None,
+                None,
+                None,
+                None,
+                None,
+            ).await?;
+        }
+
+        Ok(summary)
+    }
+
     pub async fn get_compliance_report(
         &self,
         pool: &SqlitePool,
         start_time: Option<chrono::DateTime<Utc>>,
         end_time: Option<chrono::DateTime<Utc>>,
+        granularity: ReportGranularity,
     ) -> Result<serde_json::Value, sqlx::Error> {
-        let mut report = serde_json::json!({
-            "period": format!(
-                "{} to {} ",
-                start_time.map(|t| t.to_rfc3339()).unwrap_or("1970-01-01 ".to_string()),
-                end_time.map(|t| t.to_rfc3339()).unwrap_or("now ".to_string())
-            ),
-            "summary": HashMap::<String, String>::new(),
-            "violations": Vec::<String>::new(),
-            "recommendations": Vec::<String>::new(),
-            "metrics": HashMap::<String, String>::new(),
-        });
-        
-        let high_risk_users = sqlx::query_as::<sqlx::Sqlite, _, User>(
-            "SELECT * FROM users WHERE failed_login_attempts >= 3 AND locked_until > CURRENT_TIMESTAMP "
+        let end_time = end_time.unwrap_or_else(Utc::now);
+        let start_time = start_time.unwrap_or(end_time - chrono::Duration::days(30));
+
+        let privileged_actions = sqlx::query_as::<sqlx::Sqlite, AuditLog>(&format!(
+            "SELECT * FROM audit_log WHERE {} AND {} ORDER BY timestamp DESC LIMIT ?",
+            AUDIT_PERIOD_CLAUSE, PRIVILEGED_ACTION_CLAUSE
+        ))
+        .bind(start_time)
+        .bind(end_time)
+        .bind(COMPLIANCE_SECTION_LIMIT)
+        .fetch_all(pool)
+        .await?;
+
+        let config_changes = sqlx::query_as::<sqlx::Sqlite, AuditLog>(&format!(
+            "SELECT * FROM audit_log WHERE {} AND {} ORDER BY timestamp DESC LIMIT ?",
+            AUDIT_PERIOD_CLAUSE, CONFIG_CHANGE_CLAUSE
+        ))
+        .bind(start_time)
+        .bind(end_time)
+        .bind(COMPLIANCE_SECTION_LIMIT)
+        .fetch_all(pool)
+        .await?;
+
+        let deletions = sqlx::query_as::<sqlx::Sqlite, AuditLog>(&format!(
+            "SELECT * FROM audit_log WHERE {} AND action = 'delete' ORDER BY timestamp DESC LIMIT ?",
+            AUDIT_PERIOD_CLAUSE
+        ))
+        .bind(start_time)
+        .bind(end_time)
+        .bind(COMPLIANCE_SECTION_LIMIT)
+        .fetch_all(pool)
+        .await?;
+
+        let top_risk_events = sqlx::query_as::<sqlx::Sqlite, AuditLog>(&format!(
+            "SELECT * FROM audit_log WHERE {} AND risk_score IS NOT NULL ORDER BY risk_score DESC, timestamp DESC LIMIT 10",
+            AUDIT_PERIOD_CLAUSE
+        ))
+        .bind(start_time)
+        .bind(end_time)
+        .fetch_all(pool)
+        .await?;
+
+        let failed_logins_by_ip = sqlx::query_as::<sqlx::Sqlite, (Option<String>, i64)>(
+            "SELECT source_ip, COUNT(*) AS attempts FROM security_events
+             WHERE event_type = 'login_failure'
+             AND datetime(created_at) >= datetime(?) AND datetime(created_at) <= datetime(?)
+             GROUP BY source_ip ORDER BY attempts DESC LIMIT 20"
         )
+        .bind(start_time)
+        .bind(end_time)
         .fetch_all(pool)
         .await?;
-        
-        report["high_risk_users"] = serde_json::to_value(
-            high_risk_users.iter().map(|u| serde_json::json!({
+
+        let failed_logins_by_user = sqlx::query_as::<sqlx::Sqlite, (Option<String>, i64)>(
+            "SELECT user_id, COUNT(*) AS attempts FROM security_events
+             WHERE event_type = 'login_failure'
+             AND datetime(created_at) >= datetime(?) AND datetime(created_at) <= datetime(?)
+             GROUP BY user_id ORDER BY attempts DESC LIMIT 20"
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .fetch_all(pool)
+        .await?;
+
+        let bucket = granularity.strftime_format();
+        let audit_timeline = sqlx::query_as::<sqlx::Sqlite, (String, i64, i64, i64, i64)>(&format!(
+            "SELECT strftime('{bucket}', timestamp) AS period,
+                COUNT(*),
+                SUM(CASE WHEN {privileged} THEN 1 ELSE 0 END),
+                SUM(CASE WHEN {config} THEN 1 ELSE 0 END),
+                SUM(CASE WHEN action = 'delete' THEN 1 ELSE 0 END)
+             FROM audit_log WHERE {period}
+             GROUP BY period ORDER BY period",
+            bucket = bucket,
+            privileged = PRIVILEGED_ACTION_CLAUSE,
+            config = CONFIG_CHANGE_CLAUSE,
+            period = AUDIT_PERIOD_CLAUSE,
+        ))
+        .bind(start_time)
+        .bind(end_time)
+        .fetch_all(pool)
+        .await?;
+
+        let failed_login_timeline = sqlx::query_as::<sqlx::Sqlite, (String, i64)>(&format!(
+            "SELECT strftime('{}', created_at) AS period, COUNT(*) FROM security_events
+             WHERE event_type = 'login_failure'
+             AND datetime(created_at) >= datetime(?) AND datetime(created_at) <= datetime(?)
+             GROUP BY period ORDER BY period",
+            bucket
+        ))
+        .bind(start_time)
+        .bind(end_time)
+        .fetch_all(pool)
+        .await?;
+
+        let timeline: Vec<Value> = audit_timeline.iter().map(|(period, total, privileged, config, deleted)| {
+            let failed_logins = failed_login_timeline.iter()
+                .find(|(p, _)| p == period)
+                .map(|(_, count)| *count)
+                .unwrap_or(0);
+            serde_json::json!({
+                "period": period,
+                "total_events": total,
+                "privileged_actions": privileged,
+                "config_changes": config,
+                "deletions": deleted,
+                "failed_logins": failed_logins,
+            })
+        }).collect();
+
+        let high_risk_users = sqlx::query_as::<sqlx::Sqlite, User>(
+            "SELECT * FROM users WHERE failed_login_attempts >= 3 AND locked_until > CURRENT_TIMESTAMP"
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let total_failed_logins: i64 = failed_logins_by_ip.iter().map(|(_, count)| count).sum();
+        let mut violations = Vec::new();
+        let mut recommendations = Vec::new();
+
+        if let Some((Some(ip), attempts)) = failed_logins_by_ip.first()
+            && *attempts >= 20 {
+                violations.push(format!("{} failed logins from {} in this period", attempts, ip));
+                recommendations.push(format!("Block or rate limit source address {}", ip));
+            }
+        if top_risk_events.iter().any(|e| e.risk_score.unwrap_or(0) >= 90) {
+            violations.push("Critical risk events were recorded in this period".to_string());
+            recommendations.push("Review the top risk events and confirm they were authorized".to_string());
+        }
+        if deletions.len() as i64 >= COMPLIANCE_SECTION_LIMIT {
+            recommendations.push("Deletion volume hit the report limit; export the audit trail for the full list".to_string());
+        }
+
+        Ok(serde_json::json!({
+            "period": {
+                "start": start_time.to_rfc3339(),
+                "end": end_time.to_rfc3339(),
+                "granularity": granularity,
+            },
+            "summary": {
+                "total_events": audit_timeline.iter().map(|row| row.1).sum::<i64>(),
+                "privileged_actions": audit_timeline.iter().map(|row| row.2).sum::<i64>(),
+                "config_changes": audit_timeline.iter().map(|row| row.3).sum::<i64>(),
+                "deletions": audit_timeline.iter().map(|row| row.4).sum::<i64>(),
+                "failed_logins": total_failed_logins,
+                "locked_users": high_risk_users.len(),
+            },
+            "privileged_actions": privileged_actions,
+            "failed_logins": {
+                "total": total_failed_logins,
+                "by_source_ip": failed_logins_by_ip.iter().map(|(ip, count)| serde_json::json!({
+                    "source_ip": ip,
+                    "attempts": count,
+                })).collect::<Vec<_>>(),
+                "by_user": failed_logins_by_user.iter().map(|(user_id, count)| serde_json::json!({
+                    "user_id": user_id,
+                    "attempts": count,
+                })).collect::<Vec<_>>(),
+            },
+            "config_changes": config_changes,
+            "deletions": deletions,
+            "top_risk_events": top_risk_events,
+            "timeline": timeline,
+            "high_risk_users": high_risk_users.iter().map(|u| serde_json::json!({
                 "id": u.id,
                 "username": u.username,
                 "failed_attempts": u.failed_login_attempts,
-            })).collect::<Vec<_>>()
-        ).map_err(|e| sqlx::Error::Protocol(e.to_string()))?;
-        
-        Ok(report)
+            })).collect::<Vec<_>>(),
+            "violations": violations,
+            "recommendations": recommendations,
+        }))
+    }
+}
+
+// Audit query and retention types
+const MAX_AUDIT_PAGE_SIZE: i64 = 1000;
+const COMPLIANCE_SECTION_LIMIT: i64 = 100;
+const MIN_RETENTION_DAYS: i64 = 1;
+const MAX_RETENTION_DAYS: i64 = 3650;
+const DEFAULT_AUDIT_RETENTION_DAYS: i64 = 90;
+const DEFAULT_SECURITY_EVENT_RETENTION_DAYS: i64 = 30;
+const SECURITY_EVENT_RETENTION_KEY: &str = "security_event";
+const RETENTION_ENTITY_TYPES: &[&str] = &[
+    "agent", "task", "user", "session", "permission", "role", "system", SECURITY_EVENT_RETENTION_KEY,
+];
+const AUDIT_CSV_COLUMNS: &[&str] = &[
+    "id", "timestamp", "entity_type", "entity_id", "action", "user_id", "user_role",
+    "ip_address", "user_agent", "session_id", "success", "error_message", "risk_score",
+    "old_values", "new_values", "compliance_flags",
+];
+const AUDIT_PERIOD_CLAUSE: &str = "datetime(timestamp) >= datetime(?) AND datetime(timestamp) <= datetime(?)";
+const PRIVILEGED_ACTION_CLAUSE: &str = "(user_role IN ('ADMIN', 'SUPER_ADMIN') OR entity_type IN ('permission', 'role'))";
+const CONFIG_CHANGE_CLAUSE: &str = "((entity_type = 'system' OR entity_type = 'agent') AND action IN ('update', 'import'))";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditQuery {
+    pub entity_type: Option<String>,
+    pub entity_id: Option<String>,
+    /// Actor who performed the action
+    pub user_id: Option<String>,
+    pub action: Option<String>,
+    pub start_time: Option<chrono::DateTime<Utc>>,
+    pub end_time: Option<chrono::DateTime<Utc>>,
+    pub min_risk_score: Option<i32>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SecurityEventQuery {
+    pub severity: Option<String>,
+    pub event_type: Option<String>,
+    pub user_id: Option<String>,
+    pub source_ip: Option<String>,
+    pub start_time: Option<chrono::DateTime<Utc>>,
+    pub end_time: Option<chrono::DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Ndjson,
+}
+
+/// The export's filters come from the same query string as an [`AuditQuery`];
+/// flattening them in here would hand every value to serde as a string
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportGranularity {
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl ReportGranularity {
+    fn strftime_format(&self) -> &'static str {
+        match self {
+            ReportGranularity::Day => "%Y-%m-%d",
+            ReportGranularity::Week => "%Y-W%W",
+            ReportGranularity::Month => "%Y-%m",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComplianceReportQuery {
+    pub start_time: Option<chrono::DateTime<Utc>>,
+    pub end_time: Option<chrono::DateTime<Utc>>,
+    #[serde(default)]
+    pub granularity: ReportGranularity,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct RetentionPolicy {
+    pub entity_type: String,
+    pub retention_days: i64,
+    pub updated_at: Option<chrono::DateTime<Utc>>,
+    pub updated_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionPolicyUpdate {
+    pub retention_days: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct LegalHold {
+    pub id: String,
+    pub entity_type: String,
+    /// `None` holds every record of the entity type
+    pub entity_id: Option<String>,
+    pub reason: String,
+    pub created_by: String,
+    pub created_at: chrono::DateTime<Utc>,
+    pub released_at: Option<chrono::DateTime<Utc>>,
+    pub released_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegalHoldRequest {
+    pub entity_type: String,
+    pub entity_id: Option<String>,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionRunSummary {
+    pub audit_entries_deleted: u64,
+    pub security_events_deleted: u64,
+    pub active_legal_holds: i64,
+    pub ran_at: chrono::DateTime<Utc>,
+}
+
+fn default_retention_days(entity_type: &str) -> i64 {
+    if entity_type == SECURITY_EVENT_RETENTION_KEY {
+        DEFAULT_SECURITY_EVENT_RETENTION_DAYS
+    } else {
+        DEFAULT_AUDIT_RETENTION_DAYS
+    }
+}
+
+fn push_audit_filters(builder: &mut QueryBuilder<'_, Sqlite>, filter: &AuditQuery) {
+    if let Some(entity_type) = &filter.entity_type {
+        builder.push(" AND entity_type = ").push_bind(entity_type.clone());
+    }
+    if let Some(entity_id) = &filter.entity_id {
+        builder.push(" AND entity_id = ").push_bind(entity_id.clone());
+    }
+    if let Some(user_id) = &filter.user_id {
+        builder.push(" AND user_id = ").push_bind(user_id.clone());
+    }
+    if let Some(action) = &filter.action {
+        builder.push(" AND action = ").push_bind(action.clone());
+    }
+    if let Some(start_time) = filter.start_time {
+        builder.push(" AND datetime(timestamp) >= datetime(").push_bind(start_time).push(")");
+    }
+    if let Some(end_time) = filter.end_time {
+        builder.push(" AND datetime(timestamp) <= datetime(").push_bind(end_time).push(")");
+    }
+    if let Some(min_risk_score) = filter.min_risk_score {
+        builder.push(" AND risk_score >= ").push_bind(min_risk_score);
+    }
+}
+
+fn audit_log_to_csv_row(entry: &AuditLog) -> String {
+    let fields = [
+        entry.id.clone(),
+        entry.timestamp.to_rfc3339(),
+        entry.entity_type.clone(),
+        entry.entity_id.clone(),
+        entry.action.clone(),
+        entry.user_id.clone().unwrap_or_default(),
+        entry.user_role.clone().unwrap_or_default(),
+        entry.ip_address.clone().unwrap_or_default(),
+        entry.user_agent.clone().unwrap_or_default(),
+        entry.session_id.clone().unwrap_or_default(),
+        entry.success.to_string(),
+        entry.error_message.clone().unwrap_or_default(),
+        entry.risk_score.map(|s| s.to_string()).unwrap_or_default(),
+        entry.old_values.clone().unwrap_or_default(),
+        entry.new_values.clone().unwrap_or_default(),
+        entry.compliance_flags.clone().unwrap_or_default(),
+    ];
+
+    let mut row = fields.iter().map(|f| csv_escape(f)).collect::<Vec<_>>().join(",");
+    row.push('\n');
+    row
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains(',') || field.contains('"') || field.contains('\n') || field.contains('\r') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
     }
 }
 
@@ -382,11 +947,10 @@ fn validate_json_array_field(json_str: &str) -> Result<Vec<String>, String> {
         Ok(strings) => {
             let mut valid_strings = Vec::new();
             for item in strings {
-                if let Some(s) = item.as_str() {
-                    if !s.is_empty() {
+                if let Some(s) = item.as_str()
+                    && !s.is_empty() {
                         valid_strings.push(s.to_string());
                     }
-                }
             }
             Ok(valid_strings)
         }
@@ -420,11 +984,11 @@ fn validate_password_strength(password: &str) -> (bool, Vec<String>) {
         issues.push("Password must contain at least one lowercase letter ".to_string());
     }
     
-    if !password.chars().any(|c| c.is_digit()) {
+    if !password.chars().any(|c| c.is_ascii_digit()) {
         issues.push("Password must contain at least one digit ".to_string());
     }
     
-    if !password.chars().all(|c| c.is_ascii()) {
+    if !password.is_ascii() {
         issues.push("Password must contain only ASCII characters ".to_string());
     }
     
@@ -525,16 +1089,302 @@ fn validate_file_size(size: i64, max_size: i64) -> Result<(), String> {
 // Axum Handlers
 pub async fn get_audit_trail(
     State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(filter): Query<AuditQuery>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    AuditService::get_audit_trail(&AuditService, &state.pool, None, None, None, None, Some(100), None, None).await
+    crate::rbac::authorized_user(&state.pool, &headers, "audit", "read").await?;
+    AuditService::get_audit_trail(&AuditService, &state.pool, &filter).await
         .map(Json)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
 }
 
 pub async fn get_security_events(
     State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(filter): Query<SecurityEventQuery>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    AuditService::get_security_events(&AuditService, &state.pool, None, None, None, Some(100)).await
+    crate::rbac::authorized_user(&state.pool, &headers, "security", "read").await?;
+    AuditService::get_security_events(&AuditService, &state.pool, &filter).await
         .map(Json)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
 }
+
+pub async fn export_audit_trail(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AuditExportQuery>,
+    Query(filter): Query<AuditQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&state.pool, &headers, "audit", "read").await?;
+    let (content_type, extension) = match query.format {
+        ExportFormat::Csv => ("text/csv; charset=utf-8", "csv"),
+        ExportFormat::Ndjson => ("application/x-ndjson", "ndjson"),
+    };
+    let filename = format!("audit-{}.{}", Utc::now().format("%Y%m%dT%H%M%SZ"), extension);
+
+    AuditService::log_entity_event(
+        &state.pool,
+        "system",
+        "audit_export",
+        "export",
+        None,
+        Some(&serde_json::to_string(&filter).unwrap_or_default()),
+        Some(&user.id),
+        None,
+        None,
+        None,
+        None,
+        None,
+    ).await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let stream = AuditService::export_audit_trail(state.pool.clone(), filter, query.format);
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        Body::from_stream(stream),
+    ))
+}
+
+pub async fn get_compliance_report(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ComplianceReportQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "audit", "read").await?;
+    if let (Some(start), Some(end)) = (query.start_time, query.end_time)
+        && start > end {
+            return Err((StatusCode::BAD_REQUEST, "start_time must be before end_time".to_string()));
+        }
+
+    AuditService::get_compliance_report(&AuditService, &state.pool, query.start_time, query.end_time, query.granularity).await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn get_retention_policies(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "audit", "read").await?;
+    AuditService::get_retention_policies(&AuditService, &state.pool).await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn update_retention_policy(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(entity_type): Path<String>,
+    Json(payload): Json<RetentionPolicyUpdate>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&state.pool, &headers, "audit", "admin").await?;
+    AuditService::set_retention_policy(&AuditService, &state.pool, &entity_type, payload.retention_days, &user.id).await
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+pub async fn enforce_retention_now(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&state.pool, &headers, "audit", "admin").await?;
+    AuditService::enforce_retention_policies(&AuditService, &state.pool, Some(&user.id)).await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LegalHoldListQuery {
+    #[serde(default)]
+    pub include_released: bool,
+}
+
+pub async fn list_legal_holds(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<LegalHoldListQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "audit", "read").await?;
+    AuditService::list_legal_holds(&AuditService, &state.pool, query.include_released).await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn place_legal_hold(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<LegalHoldRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&state.pool, &headers, "audit", "admin").await?;
+    AuditService::place_legal_hold(&AuditService, &state.pool, &payload, &user.id).await
+        .map(|hold| (StatusCode::CREATED, Json(hold)))
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+pub async fn release_legal_hold(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(hold_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&state.pool, &headers, "audit", "admin").await?;
+    match AuditService::release_legal_hold(&AuditService, &state.pool, &hold_id, &user.id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "Active legal hold not found".to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::common::create_test_pool;
+
+    async fn insert_entry(pool: &SqlitePool, id: &str, entity_type: &str, entity_id: &str, user_id: &str, age: chrono::Duration) {
+        sqlx::query(
+            "INSERT INTO audit_log (id, entity_type, entity_id, action, user_id, timestamp, success, risk_score)
+             VALUES (?, ?, ?, 'update', ?, ?, 1, 10)"
+        )
+        .bind(id)
+        .bind(entity_type)
+        .bind(entity_id)
+        .bind(user_id)
+        .bind(Utc::now() - age)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn insert_security_event(pool: &SqlitePool, id: &str, age: chrono::Duration) {
+        sqlx::query(
+            "INSERT INTO security_events (id, event_type, severity, description, created_at)
+             VALUES (?, 'login_failure', 'low', 'bad password', ?)"
+        )
+        .bind(id)
+        .bind(Utc::now() - age)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn remaining(pool: &SqlitePool, ids: &[&str]) -> Vec<String> {
+        let mut kept = Vec::new();
+        for id in ids {
+            let found = sqlx::query_scalar::<sqlx::Sqlite, String>(
+                "SELECT id FROM audit_log WHERE id = ? UNION SELECT id FROM security_events WHERE id = ?"
+            )
+            .bind(id)
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+            .unwrap();
+            kept.extend(found);
+        }
+        kept
+    }
+
+    async fn export(pool: &SqlitePool, filter: AuditQuery, format: ExportFormat) -> Vec<String> {
+        AuditService::export_audit_trail(pool.clone(), filter, format)
+            .try_collect()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn retention_deletes_only_entries_past_each_policy_cutoff() {
+        let pool = create_test_pool().await;
+        let audit = AuditService;
+        let days = chrono::Duration::days;
+
+        audit.set_retention_policy(&pool, "task", 10, "admin").await.unwrap();
+        insert_entry(&pool, "task-recent", "task", "task-1", "alice", days(9)).await;
+        insert_entry(&pool, "task-expired", "task", "task-1", "alice", days(11)).await;
+        // Agents keep the 90 day default
+        insert_entry(&pool, "agent-recent", "agent", "agent-1", "alice", days(89)).await;
+        insert_entry(&pool, "agent-expired", "agent", "agent-1", "alice", days(91)).await;
+        insert_security_event(&pool, "event-recent", days(29)).await;
+        insert_security_event(&pool, "event-expired", days(31)).await;
+
+        let summary = audit.enforce_retention_policies(&pool, Some("admin")).await.unwrap();
+
+        assert_eq!(summary.audit_entries_deleted, 2);
+        assert_eq!(summary.security_events_deleted, 1);
+        assert_eq!(
+            remaining(&pool, &["task-recent", "task-expired", "agent-recent", "agent-expired", "event-recent", "event-expired"]).await,
+            vec!["task-recent", "agent-recent", "event-recent"]
+        );
+    }
+
+    #[tokio::test]
+    async fn legal_holds_keep_expired_entries_until_released() {
+        let pool = create_test_pool().await;
+        let audit = AuditService;
+        let days = chrono::Duration::days;
+
+        audit.set_retention_policy(&pool, "task", 10, "admin").await.unwrap();
+        insert_entry(&pool, "task-held", "task", "task-held", "alice", days(30)).await;
+        insert_entry(&pool, "task-unheld", "task", "task-other", "alice", days(30)).await;
+        insert_entry(&pool, "user-held", "user", "user-1", "alice", days(100)).await;
+
+        let task_hold = audit.place_legal_hold(&pool, &LegalHoldRequest {
+            entity_type: "task".to_string(),
+            entity_id: Some("task-held".to_string()),
+            reason: "litigation".to_string(),
+        }, "admin").await.unwrap();
+        // No entity_id holds every user entry
+        let user_hold = audit.place_legal_hold(&pool, &LegalHoldRequest {
+            entity_type: "user".to_string(),
+            entity_id: None,
+            reason: "investigation".to_string(),
+        }, "admin").await.unwrap();
+
+        let summary = audit.enforce_retention_policies(&pool, None).await.unwrap();
+        assert_eq!(summary.active_legal_holds, 2);
+        assert_eq!(
+            remaining(&pool, &["task-held", "task-unheld", "user-held"]).await,
+            vec!["task-held", "user-held"]
+        );
+
+        assert!(audit.release_legal_hold(&pool, &task_hold.id, "admin").await.unwrap());
+        assert!(audit.release_legal_hold(&pool, &user_hold.id, "admin").await.unwrap());
+        assert!(!audit.release_legal_hold(&pool, &user_hold.id, "admin").await.unwrap());
+
+        let summary = audit.enforce_retention_policies(&pool, None).await.unwrap();
+        assert_eq!(summary.active_legal_holds, 0);
+        assert!(remaining(&pool, &["task-held", "user-held"]).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn export_streams_filtered_entries_oldest_first() {
+        let pool = create_test_pool().await;
+        let hours = chrono::Duration::hours;
+
+        // Inserted out of order so the export has to sort
+        insert_entry(&pool, "newest", "task", "task-1", "alice", hours(1)).await;
+        insert_entry(&pool, "oldest", "task", "task-1", "alice", hours(5)).await;
+        insert_entry(&pool, "middle", "task", "task-2", "alice", hours(3)).await;
+        insert_entry(&pool, "too-old", "task", "task-1", "alice", hours(48)).await;
+        insert_entry(&pool, "other-user", "task", "task-1", "bob", hours(2)).await;
+        insert_entry(&pool, "other-type", "agent", "agent-1", "alice", hours(2)).await;
+
+        let filter = AuditQuery {
+            entity_type: Some("task".to_string()),
+            user_id: Some("alice".to_string()),
+            start_time: Some(Utc::now() - hours(24)),
+            ..Default::default()
+        };
+
+        let csv = export(&pool, filter.clone(), ExportFormat::Csv).await;
+        assert_eq!(csv[0], format!("{}\n", AUDIT_CSV_COLUMNS.join(",")));
+        let csv_ids: Vec<&str> = csv[1..].iter().map(|row| row.split(',').next().unwrap()).collect();
+        assert_eq!(csv_ids, vec!["oldest", "middle", "newest"]);
+
+        let ndjson = export(&pool, filter, ExportFormat::Ndjson).await;
+        let ndjson_ids: Vec<String> = ndjson
+            .iter()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap()["id"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(ndjson_ids, vec!["oldest", "middle", "newest"]);
+    }
+}
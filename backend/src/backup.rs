@@ -0,0 +1,700 @@
+use crate::models::*;
+use crate::db::SqlitePool;
+use crate::audit::AuditService;
+use chrono::{DateTime, Utc};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::ConnectOptions;
+use std::io::{Read, Write};
+use std::path::{Path as FsPath, PathBuf};
+use std::str::FromStr;
+use tracing::{info, warn, error};
+use axum::{
+    extract::{Path, State},
+    Json,
+    response::IntoResponse,
+    http::{HeaderMap, StatusCode},
+};
+use crate::AppState;
+
+#[derive(Debug, Clone)]
+pub struct BackupConfig {
+    pub directory: PathBuf,
+    pub compress: bool,
+    pub retention_days: i64,
+    /// Hours between scheduled backups; 0 turns the scheduler off
+    pub interval_hours: u64,
+}
+
+impl BackupConfig {
+    pub fn from_env() -> Self {
+        Self {
+            directory: std::env::var("BACKUP_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| PathBuf::from("../data/backups")),
+            compress: std::env::var("BACKUP_COMPRESS").map(|v| v != "false").unwrap_or(true),
+            retention_days: std::env::var("BACKUP_RETENTION_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|days| *days > 0)
+                .unwrap_or(30),
+            interval_hours: std::env::var("BACKUP_INTERVAL_HOURS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(24),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CreateBackupRequest {
+    pub retention_days: Option<i64>,
+    pub compress: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackupVerification {
+    pub backup_id: String,
+    pub checksum_matches: bool,
+    pub integrity_ok: bool,
+    pub message: String,
+    pub verified_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RestoreStaged {
+    pub backup_id: String,
+    pub staged_path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackupPruneSummary {
+    pub expired: u64,
+    pub files_removed: u64,
+    pub bytes_freed: i64,
+    pub ran_at: DateTime<Utc>,
+}
+
+/// File path of the SQLite database named by DATABASE_URL, `None` for in-memory databases
+pub fn database_path() -> Option<PathBuf> {
+    let url = std::env::var("DATABASE_URL")
+        .unwrap_or_else(|_| "sqlite:../data/mission_control.db".to_string());
+    let path = url.strip_prefix("sqlite:").unwrap_or(&url);
+    let path = path.strip_prefix("//").unwrap_or(path);
+    let path = path.split('?').next().unwrap_or(path);
+
+    if path.is_empty() || path.contains(":memory:") {
+        None
+    } else {
+        Some(PathBuf::from(path))
+    }
+}
+
+fn pending_restore_path(database: &FsPath) -> PathBuf {
+    with_suffix(database, ".restore-pending")
+}
+
+fn with_suffix(path: &FsPath, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Swaps in a restore staged by `BackupService::restore_backup`. Must run before
+/// the pool opens the database; the replaced file is kept next to it.
+pub fn apply_pending_restore() -> Result<(), anyhow::Error> {
+    match database_path() {
+        Some(database) => apply_pending_restore_at(&database),
+        None => Ok(()),
+    }
+}
+
+fn apply_pending_restore_at(database: &FsPath) -> Result<(), anyhow::Error> {
+    let pending = pending_restore_path(database);
+    if !pending.exists() {
+        return Ok(());
+    }
+
+    // The WAL and shared memory files belong to the replaced database and can hold
+    // commits not yet checkpointed into it, so they move with it
+    let kept = with_suffix(database, &format!(".pre-restore-{}", Utc::now().format("%Y%m%dT%H%M%S")));
+    let database_exists = database.exists();
+    if database_exists {
+        std::fs::rename(database, &kept)?;
+    }
+    for suffix in ["-wal", "-shm"] {
+        let sidecar = with_suffix(database, suffix);
+        if !sidecar.exists() {
+            continue;
+        }
+        if database_exists {
+            std::fs::rename(&sidecar, with_suffix(&kept, suffix))?;
+        } else {
+            std::fs::remove_file(&sidecar)?;
+        }
+    }
+    if database_exists {
+        warn!("Replaced database kept at {}", kept.display());
+    }
+
+    std::fs::rename(&pending, database)?;
+    info!("Restored database from staged backup");
+    Ok(())
+}
+
+fn sha256_file(path: &FsPath) -> Result<String, std::io::Error> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn gzip_file(source: &FsPath, target: &FsPath) -> Result<(), std::io::Error> {
+    let mut input = std::fs::File::open(source)?;
+    let mut encoder = GzEncoder::new(std::fs::File::create(target)?, Compression::default());
+    std::io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.flush()
+}
+
+fn gunzip_file(source: &FsPath, target: &FsPath) -> Result<(), std::io::Error> {
+    let mut decoder = GzDecoder::new(std::fs::File::open(source)?);
+    let mut output = std::fs::File::create(target)?;
+    std::io::copy(&mut decoder, &mut output)?;
+    output.flush()
+}
+
+/// Writes the backup at `source` to `target` through a temporary file that is
+/// synced before it is renamed into place, so `target` is either absent or whole
+fn stage_restore_file(source: &FsPath, compressed: bool, target: &FsPath) -> Result<(), std::io::Error> {
+    let mut temp_name = target.as_os_str().to_owned();
+    temp_name.push(format!(".tmp-{}", uuid::Uuid::new_v4()));
+    let temp = PathBuf::from(temp_name);
+
+    let written = std::fs::File::create(&temp).and_then(|mut output| {
+        if compressed {
+            std::io::copy(&mut GzDecoder::new(std::fs::File::open(source)?), &mut output)?;
+        } else {
+            std::io::copy(&mut std::fs::File::open(source)?, &mut output)?;
+        }
+        output.sync_all()
+    });
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&temp);
+        return Err(e);
+    }
+
+    std::fs::rename(&temp, target)?;
+    // Persist the rename itself
+    if let Some(parent) = target.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
+async fn blocking<T, F>(task: F) -> Result<T, anyhow::Error>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, std::io::Error> + Send + 'static,
+{
+    Ok(tokio::task::spawn_blocking(task).await??)
+}
+
+pub struct BackupService {
+    config: BackupConfig,
+}
+
+impl BackupService {
+    pub fn new(config: BackupConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &BackupConfig {
+        &self.config
+    }
+
+    async fn get_backup(&self, pool: &SqlitePool, backup_id: &str) -> Result<Option<BackupRecord>, anyhow::Error> {
+        let record = sqlx::query_as::<sqlx::Sqlite, BackupRecord>("SELECT * FROM backup_records WHERE id = ?")
+            .bind(backup_id)
+            .fetch_optional(pool)
+            .await?;
+        Ok(record)
+    }
+
+    pub async fn list_backups(&self, pool: &SqlitePool) -> Result<Vec<BackupRecord>, anyhow::Error> {
+        let records = sqlx::query_as::<sqlx::Sqlite, BackupRecord>(
+            "SELECT * FROM backup_records ORDER BY started_at DESC"
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(records)
+    }
+
+    /// Takes a full online backup with `VACUUM INTO`, which reads a consistent
+    /// snapshot through SQLite itself so pages still in the WAL are included.
+    pub async fn create_backup(
+        &self,
+        pool: &SqlitePool,
+        request: &CreateBackupRequest,
+        created_by: &str,
+    ) -> Result<BackupRecord, anyhow::Error> {
+        let id = uuid::Uuid::new_v4().to_string();
+        let compress = request.compress.unwrap_or(self.config.compress);
+        let retention_days = request.retention_days.unwrap_or(self.config.retention_days);
+        if retention_days <= 0 {
+            return Err(anyhow::anyhow!("retention_days must be positive"));
+        }
+
+        tokio::fs::create_dir_all(&self.config.directory).await?;
+        let stem = format!("backup-{}-{}", Utc::now().format("%Y%m%dT%H%M%S"), &id[..8]);
+        let snapshot = self.config.directory.join(format!("{}.sqlite", stem));
+        let location = if compress {
+            self.config.directory.join(format!("{}.sqlite.gz", stem))
+        } else {
+            snapshot.clone()
+        };
+
+        sqlx::query(
+            "INSERT INTO backup_records (id, backup_type, location, status, compressed, retention_days, created_by, started_at)
+             VALUES (?, 'full', ?, 'in_progress', ?, ?, ?, CURRENT_TIMESTAMP)"
+        )
+        .bind(&id)
+        .bind(location.to_string_lossy().to_string())
+        .bind(compress)
+        .bind(retention_days)
+        .bind(created_by)
+        .execute(pool)
+        .await?;
+
+        match self.write_backup(pool, &snapshot, &location, compress).await {
+            Ok((size_bytes, checksum)) => {
+                sqlx::query(
+                    "UPDATE backup_records SET status = 'completed', size_bytes = ?, checksum = ?, completed_at = CURRENT_TIMESTAMP
+                     WHERE id = ?"
+                )
+                .bind(size_bytes)
+                .bind(&checksum)
+                .bind(&id)
+                .execute(pool)
+                .await?;
+
+                AuditService::log_entity_event(
+                    pool,
+                    "system",
+                    &format!("backup:{}", id),
+                    "export",
+                    None,
+                    Some(&serde_json::json!({
+                        "location": location.to_string_lossy(),
+                        "size_bytes": size_bytes,
+                        "checksum": checksum,
+                        "compressed": compress,
+                    }).to_string()),
+                    Some(created_by),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                ).await?;
+
+                info!("Backup {} written to {} ({} bytes)", id, location.display(), size_bytes);
+            }
+            Err(e) => {
+                error!("Backup {} failed: {}", id, e);
+                let _ = tokio::fs::remove_file(&snapshot).await;
+                let _ = tokio::fs::remove_file(&location).await;
+                sqlx::query(
+                    "UPDATE backup_records SET status = 'failed', error_message = ?, completed_at = CURRENT_TIMESTAMP WHERE id = ?"
+                )
+                .bind(e.to_string())
+                .bind(&id)
+                .execute(pool)
+                .await?;
+            }
+        }
+
+        self.get_backup(pool, &id).await?
+            .ok_or_else(|| anyhow::anyhow!("Backup record disappeared"))
+    }
+
+    async fn write_backup(
+        &self,
+        pool: &SqlitePool,
+        snapshot: &FsPath,
+        location: &FsPath,
+        compress: bool,
+    ) -> Result<(i64, String), anyhow::Error> {
+        // VACUUM INTO takes a literal file name, so quotes are escaped rather than bound
+        let target = snapshot.to_string_lossy().replace('\'', "''");
+        sqlx::query(&format!("VACUUM INTO '{}'", target))
+            .execute(pool)
+            .await?;
+
+        if compress {
+            let (source, target) = (snapshot.to_path_buf(), location.to_path_buf());
+            blocking(move || gzip_file(&source, &target)).await?;
+            tokio::fs::remove_file(snapshot).await?;
+        }
+
+        let size_bytes = tokio::fs::metadata(location).await?.len() as i64;
+        let path = location.to_path_buf();
+        let checksum = blocking(move || sha256_file(&path)).await?;
+        Ok((size_bytes, checksum))
+    }
+
+    /// Decompresses (if needed) to a scratch file next to the backup and returns its path
+    async fn readable_copy(&self, record: &BackupRecord) -> Result<(PathBuf, bool), anyhow::Error> {
+        let location = PathBuf::from(&record.location);
+        if !record.compressed {
+            return Ok((location, false));
+        }
+
+        let scratch = location.with_extension(format!("verify-{}", uuid::Uuid::new_v4()));
+        let (source, target) = (location.clone(), scratch.clone());
+        blocking(move || gunzip_file(&source, &target)).await?;
+        Ok((scratch, true))
+    }
+
+    /// Recomputes the checksum and runs `PRAGMA integrity_check` on the backup contents
+    pub async fn verify_backup(&self, pool: &SqlitePool, backup_id: &str) -> Result<Option<BackupVerification>, anyhow::Error> {
+        let Some(record) = self.get_backup(pool, backup_id).await? else {
+            return Ok(None);
+        };
+        if record.status != "completed" {
+            return Err(anyhow::anyhow!("Backup is {} and cannot be verified", record.status));
+        }
+
+        let location = PathBuf::from(&record.location);
+        if !location.exists() {
+            return Ok(Some(BackupVerification {
+                backup_id: record.id,
+                checksum_matches: false,
+                integrity_ok: false,
+                message: "Backup file is missing".to_string(),
+                verified_at: Utc::now(),
+            }));
+        }
+
+        let path = location.clone();
+        let actual = blocking(move || sha256_file(&path)).await?;
+        let checksum_matches = record.checksum.as_deref() == Some(actual.as_str());
+
+        let (readable, is_scratch) = self.readable_copy(&record).await?;
+        let integrity = integrity_check(&readable).await;
+        if is_scratch {
+            let _ = tokio::fs::remove_file(&readable).await;
+        }
+
+        let (integrity_ok, message) = match integrity {
+            Ok(result) if result == "ok" && checksum_matches => (true, "Backup verified".to_string()),
+            Ok(result) if result == "ok" => (true, "Checksum does not match the recorded value".to_string()),
+            Ok(result) => (false, format!("Integrity check failed: {}", result)),
+            Err(e) => (false, format!("Backup could not be opened: {}", e)),
+        };
+
+        let verified_at = Utc::now();
+        if checksum_matches && integrity_ok {
+            sqlx::query("UPDATE backup_records SET verified_at = ? WHERE id = ?")
+                .bind(verified_at)
+                .bind(&record.id)
+                .execute(pool)
+                .await?;
+        }
+
+        Ok(Some(BackupVerification {
+            backup_id: record.id,
+            checksum_matches,
+            integrity_ok,
+            message,
+            verified_at,
+        }))
+    }
+
+    /// Verifies the backup and stages it to replace the live database on the
+    /// next start. Swapping the file under an open pool would corrupt it.
+    pub async fn restore_backup(
+        &self,
+        pool: &SqlitePool,
+        backup_id: &str,
+        requested_by: &str,
+    ) -> Result<Option<RestoreStaged>, anyhow::Error> {
+        let Some(verification) = self.verify_backup(pool, backup_id).await? else {
+            return Ok(None);
+        };
+        if !verification.checksum_matches || !verification.integrity_ok {
+            return Err(anyhow::anyhow!("Refusing to restore: {}", verification.message));
+        }
+
+        let database = database_path()
+            .ok_or_else(|| anyhow::anyhow!("In-memory databases cannot be restored"))?;
+        let staged = pending_restore_path(&database);
+        let record = self.get_backup(pool, backup_id).await?
+            .ok_or_else(|| anyhow::anyhow!("Backup record disappeared"))?;
+
+        let location = PathBuf::from(&record.location);
+        let target = staged.clone();
+        let compressed = record.compressed;
+        blocking(move || stage_restore_file(&location, compressed, &target)).await?;
+
+        AuditService::log_entity_event(
+            pool,
+            "system",
+            &format!("backup:{}", backup_id),
+            "import",
+            None,
+            Some(&serde_json::json!({ "staged_path": staged.to_string_lossy() }).to_string()),
+            Some(requested_by),
+            None,
+            None,
+            None,
+            None,
+            None,
+        ).await?;
+
+        warn!("Restore of backup {} staged at {}; restart to apply", backup_id, staged.display());
+        Ok(Some(RestoreStaged {
+            backup_id: backup_id.to_string(),
+            staged_path: staged.to_string_lossy().to_string(),
+            message: "Restore staged; it is applied when the server next starts".to_string(),
+        }))
+    }
+
+    /// Deletes backup files older than their own `retention_days` and marks the records expired
+    pub async fn prune_expired(&self, pool: &SqlitePool) -> Result<BackupPruneSummary, anyhow::Error> {
+        let expired = sqlx::query_as::<sqlx::Sqlite, BackupRecord>(
+            "SELECT * FROM backup_records
+             WHERE status IN ('completed', 'failed')
+               AND datetime(started_at, '+' || retention_days || ' days') < CURRENT_TIMESTAMP"
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let mut summary = BackupPruneSummary {
+            expired: 0,
+            files_removed: 0,
+            bytes_freed: 0,
+            ran_at: Utc::now(),
+        };
+
+        for record in expired {
+            match tokio::fs::remove_file(&record.location).await {
+                Ok(()) => {
+                    summary.files_removed += 1;
+                    summary.bytes_freed += record.size_bytes.unwrap_or(0);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    warn!("Could not remove expired backup {}: {}", record.location, e);
+                    continue;
+                }
+            }
+
+            sqlx::query("UPDATE backup_records SET status = 'expired', expired_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(&record.id)
+                .execute(pool)
+                .await?;
+            summary.expired += 1;
+        }
+
+        if summary.expired > 0 {
+            AuditService::log_entity_event(
+                pool,
+                "system",
+                "backup_retention",
+                "delete",
+                None,
+                Some(&serde_json::to_string(&summary)?),
+                Some("system"),
+                None,
+                None,
+                None,
+                None,
+                None,
+            ).await?;
+        }
+
+        Ok(summary)
+    }
+}
+
+async fn integrity_check(path: &FsPath) -> Result<String, anyhow::Error> {
+    let mut conn = SqliteConnectOptions::from_str(&format!("sqlite:{}", path.to_string_lossy()))?
+        .read_only(true)
+        .connect()
+        .await?;
+    let result: String = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_one(&mut conn)
+        .await?;
+    Ok(result)
+}
+
+// Axum Handlers
+pub async fn list_backups(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "system", "read").await?;
+    BackupService::new(BackupConfig::from_env()).list_backups(&state.pool).await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn create_backup(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    payload: Option<Json<CreateBackupRequest>>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&state.pool, &headers, "system", "admin").await?;
+    let request = payload.map(|Json(request)| request).unwrap_or_default();
+    let record = BackupService::new(BackupConfig::from_env()).create_backup(&state.pool, &request, &user.id).await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    if record.status == "failed" {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            record.error_message.unwrap_or_else(|| "Backup failed".to_string()),
+        ));
+    }
+    Ok((StatusCode::CREATED, Json(record)))
+}
+
+pub async fn verify_backup(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(backup_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "system", "read").await?;
+    match BackupService::new(BackupConfig::from_env()).verify_backup(&state.pool, &backup_id).await {
+        Ok(Some(verification)) => Ok(Json(verification)),
+        Ok(None) => Err((StatusCode::NOT_FOUND, "Backup not found".to_string())),
+        Err(e) => Err((StatusCode::CONFLICT, e.to_string())),
+    }
+}
+
+pub async fn restore_backup(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(backup_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&state.pool, &headers, "system", "admin").await?;
+    match BackupService::new(BackupConfig::from_env()).restore_backup(&state.pool, &backup_id, &user.id).await {
+        Ok(Some(staged)) => Ok((StatusCode::ACCEPTED, Json(staged))),
+        Ok(None) => Err((StatusCode::NOT_FOUND, "Backup not found".to_string())),
+        Err(e) => Err((StatusCode::CONFLICT, e.to_string())),
+    }
+}
+
+pub async fn prune_backups(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "system", "admin").await?;
+    BackupService::new(BackupConfig::from_env()).prune_expired(&state.pool).await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::Connection;
+
+    fn scratch_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("clawcontroller-backup-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn staging_decompresses_and_leaves_no_temp_files() {
+        let dir = scratch_dir();
+        let original = dir.join("db.sqlite");
+        let archive = dir.join("db.sqlite.gz");
+        let staged = dir.join("db.sqlite.restore-pending");
+        std::fs::write(&original, b"SQLite format 3\0 test payload").unwrap();
+        gzip_file(&original, &archive).unwrap();
+
+        stage_restore_file(&archive, true, &staged).unwrap();
+
+        assert_eq!(std::fs::read(&staged).unwrap(), std::fs::read(&original).unwrap());
+        let leftovers: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().contains(".tmp-"))
+            .collect();
+        assert!(leftovers.is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_staging_keeps_the_previous_target() {
+        let dir = scratch_dir();
+        let corrupt = dir.join("corrupt.gz");
+        let staged = dir.join("db.sqlite.restore-pending");
+        std::fs::write(&corrupt, b"not gzip").unwrap();
+        std::fs::write(&staged, b"previous").unwrap();
+
+        assert!(stage_restore_file(&corrupt, true, &staged).is_err());
+
+        assert_eq!(std::fs::read(&staged).unwrap(), b"previous");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn restore_keeps_uncheckpointed_commits_with_the_replaced_database() {
+        let dir = scratch_dir();
+        let writer_path = dir.join("writer.sqlite");
+        let mut writer = SqliteConnectOptions::new()
+            .filename(&writer_path)
+            .create_if_missing(true)
+            .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
+            .connect()
+            .await
+            .unwrap();
+        sqlx::query("PRAGMA wal_autocheckpoint = 0").execute(&mut writer).await.unwrap();
+        sqlx::query("CREATE TABLE notes (body TEXT NOT NULL)").execute(&mut writer).await.unwrap();
+        for body in ["first", "second", "third"] {
+            sqlx::query("INSERT INTO notes (body) VALUES (?)").bind(body).execute(&mut writer).await.unwrap();
+        }
+
+        // Copy the files while the writer is still open, as a hard stop would leave them
+        let database = dir.join("db.sqlite");
+        for suffix in ["", "-wal", "-shm"] {
+            std::fs::copy(with_suffix(&writer_path, suffix), with_suffix(&database, suffix)).unwrap();
+        }
+        assert!(std::fs::metadata(with_suffix(&database, "-wal")).unwrap().len() > 0);
+        writer.close().await.unwrap();
+        std::fs::write(pending_restore_path(&database), b"SQLite format 3\0 restored").unwrap();
+
+        apply_pending_restore_at(&database).unwrap();
+
+        assert_eq!(std::fs::read(&database).unwrap(), b"SQLite format 3\0 restored");
+        assert!(!with_suffix(&database, "-wal").exists());
+        assert!(!with_suffix(&database, "-shm").exists());
+        let kept = std::fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .find(|path| {
+                let name = path.file_name().unwrap().to_string_lossy();
+                name.starts_with("db.sqlite.pre-restore-") && !name.ends_with("-wal") && !name.ends_with("-shm")
+            })
+            .expect("replaced database is kept");
+        let mut reader = SqliteConnectOptions::new().filename(&kept).connect().await.unwrap();
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM notes").fetch_one(&mut reader).await.unwrap();
+        assert_eq!(count, 3);
+        reader.close().await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,695 @@
+use crate::db::SqlitePool;
+use crate::audit::AuditService;
+use crate::agent_management::CostLimits;
+use crate::ConnectionManager;
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use tracing::{info, warn};
+use axum::{
+    extract::{Path, State},
+    Json,
+    response::IntoResponse,
+    http::{HeaderMap, StatusCode},
+};
+use crate::AppState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetScope {
+    Daily,
+    Weekly,
+    Monthly,
+    Task,
+}
+
+impl BudgetScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetScope::Daily => "daily",
+            BudgetScope::Weekly => "weekly",
+            BudgetScope::Monthly => "monthly",
+            BudgetScope::Task => "task",
+        }
+    }
+
+    /// Key and first/last day of the calendar window containing `today`.
+    /// Task budgets have no calendar window and are keyed by task id instead.
+    fn window(&self, today: NaiveDate) -> Option<(String, NaiveDate, NaiveDate)> {
+        match self {
+            BudgetScope::Daily => Some((today.to_string(), today, today)),
+            BudgetScope::Weekly => {
+                let week = today.iso_week();
+                let monday = today - Days::new(today.weekday().num_days_from_monday() as u64);
+                Some((format!("{}-W{:02}", week.year(), week.week()), monday, monday + Days::new(6)))
+            }
+            BudgetScope::Monthly => {
+                let first = today.with_day(1)?;
+                let last = first + Months::new(1) - Days::new(1);
+                Some((first.format("%Y-%m").to_string(), first, last))
+            }
+            BudgetScope::Task => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetLevel {
+    Ok,
+    Warning,
+    Exceeded,
+}
+
+impl BudgetLevel {
+    fn as_str(&self) -> &'static str {
+        match self {
+            BudgetLevel::Ok => "ok",
+            BudgetLevel::Warning => "warning",
+            BudgetLevel::Exceeded => "exceeded",
+        }
+    }
+}
+
+/// Spend against one cap in its current window
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetStatus {
+    pub scope: BudgetScope,
+    pub window_key: String,
+    pub limit: f64,
+    /// Granted on top of `limit` for this window only
+    pub overrides: f64,
+    pub spent: f64,
+    pub level: BudgetLevel,
+}
+
+impl BudgetStatus {
+    fn new(scope: BudgetScope, window_key: String, limit: f64, overrides: f64, spent: f64, warning_percent: u32) -> Self {
+        let allowed = limit + overrides;
+        let level = if spent >= allowed {
+            BudgetLevel::Exceeded
+        } else if spent >= allowed * warning_percent as f64 / 100.0 {
+            BudgetLevel::Warning
+        } else {
+            BudgetLevel::Ok
+        };
+        Self { scope, window_key, limit, overrides, spent, level }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetEvaluation {
+    pub currency: String,
+    pub statuses: Vec<BudgetStatus>,
+}
+
+impl BudgetEvaluation {
+    /// Caps that pause the agent as a whole; an exhausted task budget only blocks that task
+    fn agent_exceeded(&self) -> Option<&BudgetStatus> {
+        self.statuses
+            .iter()
+            .find(|s| s.scope != BudgetScope::Task && s.level == BudgetLevel::Exceeded)
+    }
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct BudgetOverride {
+    pub id: String,
+    pub agent_id: String,
+    pub scope: String,
+    pub window_key: String,
+    pub amount: f64,
+    pub reason: String,
+    pub granted_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GrantOverrideRequest {
+    pub scope: BudgetScope,
+    /// Required for task overrides
+    pub task_id: Option<String>,
+    pub amount: f64,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AgentBudget {
+    pub agent_id: String,
+    pub status: String,
+    pub budget: Option<BudgetEvaluation>,
+    pub overrides: Vec<BudgetOverride>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct EnforcementSummary {
+    pub agents_checked: u64,
+    pub alerts_raised: u64,
+    pub agents_suspended: u64,
+    pub agents_resumed: u64,
+}
+
+pub struct BudgetService;
+
+impl BudgetService {
+    /// Cost limits from the agent's stored comprehensive configuration
+    async fn cost_limits(&self, pool: &SqlitePool, agent_id: &str) -> Result<Option<CostLimits>, anyhow::Error> {
+        let limits: Option<Option<String>> = sqlx::query_scalar(
+            "SELECT json_extract(config_json, '$.resource_limits.cost_limits')
+             FROM agent_comprehensive_configs WHERE agent_id = ?"
+        )
+        .bind(agent_id)
+        .fetch_optional(pool)
+        .await?;
+
+        match limits.flatten() {
+            Some(json) => match serde_json::from_str(&json) {
+                Ok(limits) => Ok(Some(limits)),
+                Err(e) => {
+                    warn!("Ignoring unreadable cost limits for agent {}: {}", agent_id, e);
+                    Ok(None)
+                }
+            },
+            None => Ok(None),
+        }
+    }
+
+    async fn override_total(&self, pool: &SqlitePool, agent_id: &str, scope: BudgetScope, window_key: &str) -> Result<f64, sqlx::Error> {
+        sqlx::query_scalar(
+            "SELECT COALESCE(SUM(amount), 0.0) FROM agent_budget_overrides
+             WHERE agent_id = ? AND scope = ? AND window_key = ?"
+        )
+        .bind(agent_id)
+        .bind(scope.as_str())
+        .bind(window_key)
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Checks ingested usage against every cap the agent has configured.
+    /// Task caps cover each task the agent has spent on that is not yet done.
+    pub async fn evaluate(&self, pool: &SqlitePool, agent_id: &str) -> Result<Option<BudgetEvaluation>, anyhow::Error> {
+        let Some(limits) = self.cost_limits(pool, agent_id).await? else {
+            return Ok(None);
+        };
+        let warning_percent = crate::config::current().budget_warning_threshold_percent;
+        let today = Utc::now().date_naive();
+
+        let mut statuses = Vec::new();
+        for (scope, limit) in [
+            (BudgetScope::Daily, limits.daily_limit),
+            (BudgetScope::Weekly, limits.weekly_limit),
+            (BudgetScope::Monthly, limits.monthly_limit),
+        ] {
+            let (Some(limit), Some((window_key, from, to))) = (limit, scope.window(today)) else {
+                continue;
+            };
+            let spent: f64 = sqlx::query_scalar(
+                "SELECT COALESCE(SUM(cost), 0.0) FROM agent_activity_detailed
+                 WHERE agent_id = ? AND date(timestamp) BETWEEN ? AND ?"
+            )
+            .bind(agent_id)
+            .bind(from.to_string())
+            .bind(to.to_string())
+            .fetch_one(pool)
+            .await?;
+            let overrides = self.override_total(pool, agent_id, scope, &window_key).await?;
+            statuses.push(BudgetStatus::new(scope, window_key, limit, overrides, spent, warning_percent));
+        }
+
+        if let Some(limit) = limits.per_task_limit {
+            let task_spend = sqlx::query_as::<sqlx::Sqlite, (String, f64)>(
+                "SELECT d.task_id, COALESCE(SUM(d.cost), 0.0)
+                 FROM agent_activity_detailed d
+                 JOIN tasks t ON t.id = d.task_id
+                 WHERE d.agent_id = ? AND t.status != 'DONE'
+                 GROUP BY d.task_id"
+            )
+            .bind(agent_id)
+            .fetch_all(pool)
+            .await?;
+
+            for (task_id, spent) in task_spend {
+                let overrides = self.override_total(pool, agent_id, BudgetScope::Task, &task_id).await?;
+                statuses.push(BudgetStatus::new(BudgetScope::Task, task_id, limit, overrides, spent, warning_percent));
+            }
+        }
+
+        Ok(Some(BudgetEvaluation { currency: limits.currency, statuses }))
+    }
+
+    /// Why `agent_id` may not take `task_id` right now, if it may not
+    pub async fn routing_refusal(&self, pool: &SqlitePool, agent_id: &str, task_id: &str) -> Result<Option<String>, anyhow::Error> {
+        let status: Option<String> = sqlx::query_scalar("SELECT status FROM agents WHERE id = ?")
+            .bind(agent_id)
+            .fetch_optional(pool)
+            .await?;
+        if status.as_deref() == Some("SUSPENDED") {
+            return Ok(Some(format!("Agent {} is suspended", agent_id)));
+        }
+
+        let Some(evaluation) = self.evaluate(pool, agent_id).await? else {
+            return Ok(None);
+        };
+        let exceeded = evaluation.agent_exceeded().or_else(|| {
+            evaluation.statuses.iter().find(|s| {
+                s.scope == BudgetScope::Task && s.window_key == task_id && s.level == BudgetLevel::Exceeded
+            })
+        });
+
+        Ok(exceeded.map(|s| {
+            format!(
+                "Agent {} has spent {:.2} of its {:.2} {} {} budget",
+                agent_id, s.spent, s.limit + s.overrides, evaluation.currency, s.scope.as_str()
+            )
+        }))
+    }
+
+    /// Raises alerts for caps newly past their warning threshold or limit, suspends
+    /// agents over a daily, weekly or monthly cap and resumes those whose budget
+    /// window rolled over or was raised by an override
+    pub async fn enforce(&self, pool: &SqlitePool, manager: &ConnectionManager) -> Result<EnforcementSummary, anyhow::Error> {
+        let agents: Vec<String> = sqlx::query_scalar(
+            "SELECT a.id FROM agents a JOIN agent_comprehensive_configs c ON c.agent_id = a.id"
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let mut summary = EnforcementSummary::default();
+        for agent_id in agents {
+            self.enforce_agent(pool, manager, &agent_id, &mut summary).await?;
+        }
+
+        if summary.alerts_raised + summary.agents_suspended + summary.agents_resumed > 0 {
+            info!(
+                "Budget enforcement raised {} alerts, suspended {} agents and resumed {}",
+                summary.alerts_raised, summary.agents_suspended, summary.agents_resumed
+            );
+        }
+        Ok(summary)
+    }
+
+    async fn enforce_agent(
+        &self,
+        pool: &SqlitePool,
+        manager: &ConnectionManager,
+        agent_id: &str,
+        summary: &mut EnforcementSummary,
+    ) -> Result<(), anyhow::Error> {
+        let Some(evaluation) = self.evaluate(pool, agent_id).await? else {
+            return Ok(());
+        };
+        summary.agents_checked += 1;
+
+        let open_alerts = sqlx::query_as::<sqlx::Sqlite, (String, String, String, String)>(
+            "SELECT id, scope, window_key, level FROM agent_budget_alerts
+             WHERE agent_id = ? AND resolved_at IS NULL"
+        )
+        .bind(agent_id)
+        .fetch_all(pool)
+        .await?;
+        for (id, scope, window_key, level) in &open_alerts {
+            let still_active = evaluation.statuses.iter().any(|s| {
+                s.scope.as_str() == scope && &s.window_key == window_key && s.level.as_str() == level
+            });
+            if !still_active {
+                sqlx::query("UPDATE agent_budget_alerts SET resolved_at = CURRENT_TIMESTAMP WHERE id = ?")
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+            }
+        }
+
+        for status in evaluation.statuses.iter().filter(|s| s.level != BudgetLevel::Ok) {
+            // Only alerts that are new, or reopened after an override, are announced
+            let raised = sqlx::query(
+                "INSERT INTO agent_budget_alerts (id, agent_id, scope, window_key, level, spent, limit_amount)
+                 VALUES (?, ?, ?, ?, ?, ?, ?)
+                 ON CONFLICT(agent_id, scope, window_key, level) DO UPDATE SET
+                     spent = excluded.spent, limit_amount = excluded.limit_amount,
+                     created_at = CURRENT_TIMESTAMP, resolved_at = NULL
+                 WHERE agent_budget_alerts.resolved_at IS NOT NULL"
+            )
+            .bind(uuid::Uuid::new_v4().to_string())
+            .bind(agent_id)
+            .bind(status.scope.as_str())
+            .bind(&status.window_key)
+            .bind(status.level.as_str())
+            .bind(status.spent)
+            .bind(status.limit + status.overrides)
+            .execute(pool)
+            .await?;
+
+            if raised.rows_affected() > 0 {
+                summary.alerts_raised += 1;
+                self.announce(pool, manager, agent_id, &evaluation.currency, status).await?;
+            }
+        }
+
+        // Agents suspended by hand stay suspended whatever their spend
+        let (current_status, suspended_by): (String, Option<String>) =
+            sqlx::query_as("SELECT status, suspended_by FROM agents WHERE id = ?")
+                .bind(agent_id)
+                .fetch_one(pool)
+                .await?;
+        let suspended_for_budget = current_status == "SUSPENDED" && suspended_by.as_deref() == Some("budget");
+        let over_budget = evaluation.agent_exceeded().is_some();
+
+        if over_budget && current_status != "SUSPENDED" {
+            sqlx::query("UPDATE agents SET status = 'SUSPENDED', suspended_by = 'budget', updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(agent_id)
+                .execute(pool)
+                .await?;
+            manager.broadcast(&serde_json::json!({ "type": "agent_suspended", "agent_id": agent_id, "reason": "budget" }).to_string());
+            summary.agents_suspended += 1;
+        } else if !over_budget && suspended_for_budget {
+            sqlx::query("UPDATE agents SET status = 'IDLE', updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(agent_id)
+                .execute(pool)
+                .await?;
+            manager.broadcast(&serde_json::json!({ "type": "agent_resumed", "agent_id": agent_id, "reason": "budget" }).to_string());
+            summary.agents_resumed += 1;
+        }
+
+        Ok(())
+    }
+
+    async fn announce(
+        &self,
+        pool: &SqlitePool,
+        manager: &ConnectionManager,
+        agent_id: &str,
+        currency: &str,
+        status: &BudgetStatus,
+    ) -> Result<(), sqlx::Error> {
+        let allowed = status.limit + status.overrides;
+        let (title, priority) = match status.level {
+            BudgetLevel::Exceeded => ("Budget exceeded", "URGENT"),
+            _ => ("Budget warning", "HIGH"),
+        };
+        let subject = match status.scope {
+            BudgetScope::Task => format!("task {}", status.window_key),
+            scope => format!("{} budget ({})", scope.as_str(), status.window_key),
+        };
+        let message = format!(
+            "Agent {} has spent {:.2} of {:.2} {} on its {}",
+            agent_id, status.spent, allowed, currency, subject
+        );
+
+        sqlx::query(
+            "INSERT INTO announcements (id, title, message, priority, created_at, created_by)
+             VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP, 'system')"
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(title)
+        .bind(&message)
+        .bind(priority)
+        .execute(pool)
+        .await?;
+
+        manager.broadcast(&serde_json::json!({
+            "type": "budget_alert",
+            "agent_id": agent_id,
+            "scope": status.scope,
+            "window_key": status.window_key,
+            "level": status.level,
+            "spent": status.spent,
+            "limit": allowed,
+            "currency": currency,
+        }).to_string());
+        manager.broadcast(r#"{"type": "announcement_created"}"#);
+        Ok(())
+    }
+
+    pub async fn overrides(&self, pool: &SqlitePool, agent_id: &str) -> Result<Vec<BudgetOverride>, sqlx::Error> {
+        sqlx::query_as::<sqlx::Sqlite, BudgetOverride>(
+            "SELECT id, agent_id, scope, window_key, amount, reason, granted_by, created_at
+             FROM agent_budget_overrides WHERE agent_id = ? ORDER BY created_at DESC LIMIT 50"
+        )
+        .bind(agent_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Raises one cap for its current window (or one task) and re-checks the
+    /// agent, resuming it if the override brings it back under budget
+    pub async fn grant_override(
+        &self,
+        pool: &SqlitePool,
+        manager: &ConnectionManager,
+        agent_id: &str,
+        request: &GrantOverrideRequest,
+        granted_by: &str,
+    ) -> Result<Option<BudgetOverride>, anyhow::Error> {
+        let exists: Option<String> = sqlx::query_scalar("SELECT id FROM agents WHERE id = ?")
+            .bind(agent_id)
+            .fetch_optional(pool)
+            .await?;
+        if exists.is_none() {
+            return Ok(None);
+        }
+
+        let window_key = match request.scope.window(Utc::now().date_naive()) {
+            Some((key, _, _)) => key,
+            None => request.task_id.clone().ok_or_else(|| anyhow::anyhow!("task_id is required for task overrides"))?,
+        };
+
+        let id = uuid::Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO agent_budget_overrides (id, agent_id, scope, window_key, amount, reason, granted_by)
+             VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&id)
+        .bind(agent_id)
+        .bind(request.scope.as_str())
+        .bind(&window_key)
+        .bind(request.amount)
+        .bind(&request.reason)
+        .bind(granted_by)
+        .execute(pool)
+        .await?;
+
+        let granted = sqlx::query_as::<sqlx::Sqlite, BudgetOverride>(
+            "SELECT id, agent_id, scope, window_key, amount, reason, granted_by, created_at
+             FROM agent_budget_overrides WHERE id = ?"
+        )
+        .bind(&id)
+        .fetch_one(pool)
+        .await?;
+
+        AuditService::log_entity_event(
+            pool,
+            "agent",
+            agent_id,
+            "update",
+            None,
+            Some(&serde_json::to_string(&granted)?),
+            Some(granted_by),
+            None,
+            None,
+            None,
+            None,
+            Some(&serde_json::json!({ "budget_override": true, "reason": request.reason }).to_string()),
+        ).await?;
+
+        self.enforce_agent(pool, manager, agent_id, &mut EnforcementSummary::default()).await?;
+        Ok(Some(granted))
+    }
+}
+
+// Axum Handlers
+pub async fn get_agent_budget(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(agent_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
+    let status: String = sqlx::query_scalar("SELECT status FROM agents WHERE id = ?")
+        .bind(&agent_id)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Agent not found".to_string()))?;
+
+    let budget = BudgetService.evaluate(&state.pool, &agent_id).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let overrides = BudgetService.overrides(&state.pool, &agent_id).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(AgentBudget { agent_id, status, budget, overrides }))
+}
+
+pub async fn grant_budget_override(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(agent_id): Path<String>,
+    Json(payload): Json<GrantOverrideRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let admin = crate::rbac::authorized_user(&state.pool, &headers, "agents", "admin").await?;
+    if !payload.amount.is_finite() || payload.amount <= 0.0 {
+        return Err((StatusCode::BAD_REQUEST, "amount must be greater than zero".to_string()));
+    }
+    if payload.reason.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "A reason is required for budget overrides".to_string()));
+    }
+    if payload.scope == BudgetScope::Task && payload.task_id.is_none() {
+        return Err((StatusCode::BAD_REQUEST, "task_id is required for task overrides".to_string()));
+    }
+
+    match BudgetService.grant_override(&state.pool, &state.manager, &agent_id, &payload, &admin.id).await {
+        Ok(Some(granted)) => Ok((StatusCode::CREATED, Json(granted))),
+        Ok(None) => Err((StatusCode::NOT_FOUND, "Agent not found".to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::common::{create_test_pool, create_test_state, insert_test_agent};
+
+    fn day(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn weekly_windows_follow_iso_weeks_across_years() {
+        // Thursday 1 January 2026 falls in the first ISO week of 2026
+        assert_eq!(
+            BudgetScope::Weekly.window(day("2026-01-01")),
+            Some(("2026-W01".to_string(), day("2025-12-29"), day("2026-01-04")))
+        );
+        // Sunday 3 January 2021 still belongs to the last week of 2020
+        assert_eq!(
+            BudgetScope::Weekly.window(day("2021-01-03")),
+            Some(("2020-W53".to_string(), day("2020-12-28"), day("2021-01-03")))
+        );
+        assert_eq!(
+            BudgetScope::Weekly.window(day("2024-12-30")),
+            Some(("2025-W01".to_string(), day("2024-12-30"), day("2025-01-05")))
+        );
+    }
+
+    #[test]
+    fn monthly_windows_cover_the_whole_calendar_month() {
+        assert_eq!(
+            BudgetScope::Monthly.window(day("2024-02-29")),
+            Some(("2024-02".to_string(), day("2024-02-01"), day("2024-02-29")))
+        );
+        assert_eq!(
+            BudgetScope::Monthly.window(day("2025-12-01")),
+            Some(("2025-12".to_string(), day("2025-12-01"), day("2025-12-31")))
+        );
+        assert_eq!(
+            BudgetScope::Daily.window(day("2025-12-31")),
+            Some(("2025-12-31".to_string(), day("2025-12-31"), day("2025-12-31")))
+        );
+        assert_eq!(BudgetScope::Task.window(day("2025-12-31")), None);
+    }
+
+    #[test]
+    fn status_levels_use_the_limit_plus_overrides() {
+        let level = |overrides, spent| BudgetStatus::new(BudgetScope::Daily, String::new(), 10.0, overrides, spent, 80).level;
+
+        assert_eq!(level(0.0, 7.99), BudgetLevel::Ok);
+        assert_eq!(level(0.0, 8.0), BudgetLevel::Warning);
+        assert_eq!(level(0.0, 10.0), BudgetLevel::Exceeded);
+
+        assert_eq!(level(5.0, 10.0), BudgetLevel::Ok);
+        assert_eq!(level(5.0, 12.0), BudgetLevel::Warning);
+        assert_eq!(level(5.0, 15.0), BudgetLevel::Exceeded);
+    }
+
+    async fn agent_with_daily_limit(pool: &SqlitePool, name: &str, limit: f64) -> String {
+        let agent_id = insert_test_agent(pool, name).await;
+        sqlx::query("INSERT INTO agent_comprehensive_configs (agent_id, config_json) VALUES (?, ?)")
+            .bind(&agent_id)
+            .bind(serde_json::json!({
+                "resource_limits": { "cost_limits": { "daily_limit": limit, "currency": "USD" } }
+            }).to_string())
+            .execute(pool)
+            .await
+            .unwrap();
+        agent_id
+    }
+
+    async fn spend(pool: &SqlitePool, agent_id: &str, cost: f64) {
+        sqlx::query("INSERT INTO agent_activity_detailed (id, agent_id, activity_type, cost) VALUES (?, ?, 'usage', ?)")
+            .bind(uuid::Uuid::new_v4().to_string())
+            .bind(agent_id)
+            .bind(cost)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    async fn suspension(pool: &SqlitePool, agent_id: &str) -> (String, Option<String>) {
+        sqlx::query_as("SELECT status, suspended_by FROM agents WHERE id = ?")
+            .bind(agent_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn enforcement_suspends_over_budget_agents_and_resumes_them_after_an_override() {
+        let state = create_test_state(create_test_pool().await);
+        let agent_id = agent_with_daily_limit(&state.pool, "Spender", 1.0).await;
+        spend(&state.pool, &agent_id, 2.0).await;
+
+        let summary = BudgetService.enforce(&state.pool, &state.manager).await.unwrap();
+        assert_eq!(summary.agents_suspended, 1);
+        assert_eq!(suspension(&state.pool, &agent_id).await, ("SUSPENDED".to_string(), Some("budget".to_string())));
+        let author: String = sqlx::query_scalar("SELECT created_by FROM announcements WHERE title = 'Budget exceeded'")
+            .fetch_one(&state.pool)
+            .await
+            .unwrap();
+        assert_eq!(author, "system");
+
+        // A second pass changes nothing while the agent is still over budget
+        let summary = BudgetService.enforce(&state.pool, &state.manager).await.unwrap();
+        assert_eq!((summary.agents_suspended, summary.agents_resumed), (0, 0));
+
+        let window_key = BudgetScope::Daily.window(Utc::now().date_naive()).unwrap().0;
+        sqlx::query(
+            "INSERT INTO agent_budget_overrides (id, agent_id, scope, window_key, amount, reason, granted_by)
+             VALUES (?, ?, 'daily', ?, 5.0, 'launch week', 'admin')"
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(&agent_id)
+        .bind(&window_key)
+        .execute(&state.pool)
+        .await
+        .unwrap();
+
+        let summary = BudgetService.enforce(&state.pool, &state.manager).await.unwrap();
+        assert_eq!(summary.agents_resumed, 1);
+        assert_eq!(suspension(&state.pool, &agent_id).await, ("IDLE".to_string(), None));
+    }
+
+    #[tokio::test]
+    async fn enforcement_leaves_agents_suspended_by_hand_alone() {
+        let state = create_test_state(create_test_pool().await);
+        let agent_id = agent_with_daily_limit(&state.pool, "Held Back", 1.0).await;
+        sqlx::query("UPDATE agents SET status = 'SUSPENDED' WHERE id = ?")
+            .bind(&agent_id)
+            .execute(&state.pool)
+            .await
+            .unwrap();
+        spend(&state.pool, &agent_id, 2.0).await;
+
+        let summary = BudgetService.enforce(&state.pool, &state.manager).await.unwrap();
+        assert_eq!((summary.alerts_raised, summary.agents_suspended), (1, 0));
+        assert_eq!(suspension(&state.pool, &agent_id).await, ("SUSPENDED".to_string(), None));
+
+        // Back under budget with the exceeded alert still open
+        sqlx::query("DELETE FROM agent_activity_detailed WHERE agent_id = ?")
+            .bind(&agent_id)
+            .execute(&state.pool)
+            .await
+            .unwrap();
+        let summary = BudgetService.enforce(&state.pool, &state.manager).await.unwrap();
+        assert_eq!(summary.agents_resumed, 0);
+        assert_eq!(suspension(&state.pool, &agent_id).await, ("SUSPENDED".to_string(), None));
+    }
+}
@@ -0,0 +1,151 @@
+use crate::models::*;
+use crate::db::SqlitePool;
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::{request::Parts, HeaderMap, HeaderValue, StatusCode},
+};
+use std::collections::HashMap;
+use crate::AppState;
+
+const LEVELS: [SecurityLevel; 5] = [
+    SecurityLevel::Public,
+    SecurityLevel::Internal,
+    SecurityLevel::Confidential,
+    SecurityLevel::Restricted,
+    SecurityLevel::Secret,
+];
+
+pub fn level_name(level: SecurityLevel) -> &'static str {
+    match level {
+        SecurityLevel::Public => "PUBLIC",
+        SecurityLevel::Internal => "INTERNAL",
+        SecurityLevel::Confidential => "CONFIDENTIAL",
+        SecurityLevel::Restricted => "RESTRICTED",
+        SecurityLevel::Secret => "SECRET",
+    }
+}
+
+pub fn parse_level(value: &str) -> Option<SecurityLevel> {
+    LEVELS
+        .into_iter()
+        .find(|level| level_name(*level).eq_ignore_ascii_case(value))
+}
+
+/// Quoted level names a caller with `clearance` may read, for `classification IN (...)`.
+/// Built only from the fixed level names, so it is safe to splice into SQL.
+pub fn visible_levels_sql(clearance: SecurityLevel) -> String {
+    LEVELS
+        .into_iter()
+        .filter(|level| *level <= clearance)
+        .map(|level| format!("'{}'", level_name(level)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Reads an optional `classification` field from a request payload
+pub fn classification_from_payload(payload: &serde_json::Value) -> Result<Option<SecurityLevel>, (StatusCode, String)> {
+    match payload.get("classification") {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(serde_json::Value::String(value)) => parse_level(value)
+            .map(Some)
+            .ok_or((StatusCode::BAD_REQUEST, format!("Unknown classification: {}", value))),
+        Some(_) => Err((StatusCode::BAD_REQUEST, "classification must be a string".to_string())),
+    }
+}
+
+/// Callers may not create data they would not be allowed to read back
+pub fn ensure_can_classify(clearance: SecurityLevel, classification: SecurityLevel) -> Result<(), (StatusCode, String)> {
+    if classification > clearance {
+        Err((
+            StatusCode::FORBIDDEN,
+            format!("Cannot classify above your clearance ({})", level_name(clearance)),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Clearance of anonymous callers, INTERNAL unless ANONYMOUS_CLEARANCE says otherwise
+pub fn anonymous_clearance() -> SecurityLevel {
+    std::env::var("ANONYMOUS_CLEARANCE")
+        .ok()
+        .and_then(|value| parse_level(&value))
+        .unwrap_or(SecurityLevel::Internal)
+}
+
+pub async fn agent_clearance(pool: &SqlitePool, agent_id: &str) -> Result<Option<SecurityLevel>, sqlx::Error> {
+    sqlx::query_scalar::<sqlx::Sqlite, SecurityLevel>("SELECT security_level FROM agents WHERE id = ?")
+        .bind(agent_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Security clearance of the caller: the user behind a bearer token (also accepted
+/// as `?token=` for WebSocket upgrades), the agent behind `x-agent-key`, or the
+/// anonymous clearance when neither is sent.
+#[derive(Debug, Clone, Copy)]
+pub struct Clearance(pub SecurityLevel);
+
+#[async_trait]
+impl FromRequestParts<AppState> for Clearance {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let mut headers = parts.headers.clone();
+        if !headers.contains_key("authorization")
+            && let Ok(Query(params)) = Query::<HashMap<String, String>>::try_from_uri(&parts.uri)
+                && let Some(token) = params.get("token") {
+                    let value = HeaderValue::from_str(&format!("Bearer {}", token))
+                        .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid token".to_string()))?;
+                    headers.insert("authorization", value);
+                }
+
+        if headers.contains_key("authorization") {
+            let user = crate::security::authenticated_user(&state.pool, &headers).await?;
+            return Ok(Clearance(user.security_level));
+        }
+
+        if let Some(agent_key) = agent_key(&headers) {
+            let level = sqlx::query_scalar::<sqlx::Sqlite, SecurityLevel>(
+                "SELECT security_level FROM agents WHERE token = ? AND is_deleted = 0"
+            )
+            .bind(agent_key)
+            .fetch_optional(&state.pool)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .ok_or((StatusCode::UNAUTHORIZED, "Unknown agent key".to_string()))?;
+            return Ok(Clearance(level));
+        }
+
+        Ok(Clearance(anonymous_clearance()))
+    }
+}
+
+fn agent_key(headers: &HeaderMap) -> Option<&str> {
+    headers.get("x-agent-key").and_then(|v| v.to_str().ok())
+}
+
+/// A WebSocket broadcast tagged with the classification of the data it describes
+#[derive(Debug, Clone)]
+pub struct BroadcastEvent {
+    pub classification: SecurityLevel,
+    pub message: String,
+}
+
+impl BroadcastEvent {
+    /// The message as a subscriber with `clearance` may see it. Under-cleared
+    /// subscribers only learn the event type, so they know to refetch.
+    pub fn render_for(&self, clearance: SecurityLevel) -> String {
+        if clearance >= self.classification {
+            return self.message.clone();
+        }
+
+        let event_type = serde_json::from_str::<serde_json::Value>(&self.message)
+            .ok()
+            .and_then(|value| value.get("type").cloned())
+            .unwrap_or(serde_json::Value::Null);
+
+        serde_json::json!({ "type": event_type, "redacted": true }).to_string()
+    }
+}
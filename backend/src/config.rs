@@ -0,0 +1,818 @@
+use crate::models::*;
+use crate::db::SqlitePool;
+use crate::audit::AuditService;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::watch;
+use tracing::{info, warn};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+    response::IntoResponse,
+    http::{HeaderMap, StatusCode},
+};
+use crate::AppState;
+
+const MASK: &str = "********";
+
+/// A setting the server knows how to apply, seeded into `system_configuration`
+/// with its default on startup. Rows already present keep their stored value.
+pub struct ConfigDefinition {
+    pub key: &'static str,
+    pub data_type: &'static str,
+    pub category: &'static str,
+    pub description: &'static str,
+    pub default: &'static str,
+    pub is_sensitive: bool,
+    pub requires_restart: bool,
+    pub validation_rules: &'static str,
+}
+
+pub static DEFINITIONS: &[ConfigDefinition] = &[
+    ConfigDefinition {
+        key: "gateway.check_interval_seconds",
+        data_type: "number",
+        category: "gateway",
+        description: "Seconds between gateway health and stuck task checks",
+        default: "60",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 10, "max": 3600}"#,
+    },
+    ConfigDefinition {
+        key: "gateway.health_check_timeout_seconds",
+        data_type: "number",
+        category: "gateway",
+        description: "Seconds to wait for the gateway to accept a connection",
+        default: "5",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 1, "max": 60}"#,
+    },
+    ConfigDefinition {
+        key: "gateway.max_restart_attempts",
+        data_type: "number",
+        category: "gateway",
+        description: "Restarts attempted before the gateway is reported as down",
+        default: "3",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 0, "max": 20}"#,
+    },
+    ConfigDefinition {
+        key: "gateway.notification_cooldown_minutes",
+        data_type: "number",
+        category: "gateway",
+        description: "Minimum minutes between repeated gateway notifications",
+        default: "30",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 1, "max": 1440}"#,
+    },
+    ConfigDefinition {
+        key: "monitoring.normal_priority_limit_minutes",
+        data_type: "number",
+        category: "monitoring",
+        description: "Minutes a task may sit in INBOX or ASSIGNED before it counts as stuck",
+        default: "120",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 5, "max": 10080}"#,
+    },
+    ConfigDefinition {
+        key: "monitoring.urgent_priority_limit_minutes",
+        data_type: "number",
+        category: "monitoring",
+        description: "Stuck threshold for URGENT and CRITICAL tasks",
+        default: "30",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 1, "max": 1440}"#,
+    },
+    ConfigDefinition {
+        key: "security.max_failed_attempts",
+        data_type: "number",
+        category: "security",
+        description: "Failed logins before an account is locked",
+        default: "5",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 1, "max": 100}"#,
+    },
+    ConfigDefinition {
+        key: "security.lockout_minutes",
+        data_type: "number",
+        category: "security",
+        description: "Minutes an account stays locked after too many failed logins",
+        default: "15",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 1, "max": 1440}"#,
+    },
+    ConfigDefinition {
+        key: "cache.permission_ttl_seconds",
+        data_type: "number",
+        category: "cache",
+        description: "Seconds resolved permissions are reused",
+        default: "300",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 0, "max": 86400}"#,
+    },
+    ConfigDefinition {
+        key: "cache.openclaw_config_ttl_seconds",
+        data_type: "number",
+        category: "cache",
+        description: "Seconds the OpenClaw configuration is cached",
+        default: "300",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 0, "max": 86400}"#,
+    },
+    ConfigDefinition {
+        key: "cache.agent_config_ttl_seconds",
+        data_type: "number",
+        category: "cache",
+        description: "Seconds individual agent configurations are cached",
+        default: "600",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 0, "max": 86400}"#,
+    },
+    ConfigDefinition {
+        key: "usage.model_prices",
+        data_type: "json",
+        category: "usage",
+        description: "USD per million tokens by model: {\"model\": {\"input\": 3.0, \"output\": 15.0, \"cache_read\": 0.3, \"cache_write\": 3.75}}",
+        default: "{}",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: "{}",
+    },
+    ConfigDefinition {
+        key: "budget.warning_threshold_percent",
+        data_type: "number",
+        category: "budget",
+        description: "Share of a spend cap at which a budget warning is raised",
+        default: "80",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 1, "max": 100}"#,
+    },
+    ConfigDefinition {
+        key: "health.degraded_threshold",
+        data_type: "number",
+        category: "health",
+        description: "Agent health score below which an agent is reported as degraded",
+        default: "60",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 0, "max": 100}"#,
+    },
+    ConfigDefinition {
+        key: "health.critical_threshold",
+        data_type: "number",
+        category: "health",
+        description: "Agent health score below which tasks are no longer routed to the agent",
+        default: "30",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 0, "max": 100}"#,
+    },
+    ConfigDefinition {
+        key: "models.failover_threshold",
+        data_type: "number",
+        category: "models",
+        description: "Consecutive model errors before an agent is switched to its next fallback model",
+        default: "3",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 1, "max": 100}"#,
+    },
+    ConfigDefinition {
+        key: "models.primary_retry_minutes",
+        data_type: "number",
+        category: "models",
+        description: "Minutes on a fallback model before the primary is tried again (0 waits for a reported recovery)",
+        default: "30",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 0, "max": 10080}"#,
+    },
+    ConfigDefinition {
+        key: "resources.sample_interval_seconds",
+        data_type: "number",
+        category: "resources",
+        description: "Seconds between host resource samples read from /proc",
+        default: "30",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 5, "max": 3600}"#,
+    },
+    ConfigDefinition {
+        key: "resources.cpu_warning_percent",
+        data_type: "number",
+        category: "resources",
+        description: "Host CPU usage at which a resource warning is raised",
+        default: "80",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 1, "max": 100}"#,
+    },
+    ConfigDefinition {
+        key: "resources.cpu_critical_percent",
+        data_type: "number",
+        category: "resources",
+        description: "Host CPU usage at which a critical resource alert is raised",
+        default: "95",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 1, "max": 100}"#,
+    },
+    ConfigDefinition {
+        key: "resources.memory_warning_percent",
+        data_type: "number",
+        category: "resources",
+        description: "Share of host memory in use at which a resource warning is raised",
+        default: "85",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 1, "max": 100}"#,
+    },
+    ConfigDefinition {
+        key: "resources.memory_critical_percent",
+        data_type: "number",
+        category: "resources",
+        description: "Share of host memory in use at which a critical resource alert is raised",
+        default: "95",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 1, "max": 100}"#,
+    },
+    ConfigDefinition {
+        key: "bulk_jobs.max_concurrency",
+        data_type: "number",
+        category: "bulk_jobs",
+        description: "Most agents a bulk job works on at the same time; jobs may ask for fewer",
+        default: "4",
+        is_sensitive: false,
+        requires_restart: false,
+        validation_rules: r#"{"integer": true, "min": 1, "max": 64}"#,
+    },
+    ConfigDefinition {
+        key: "mail.smtp_password",
+        data_type: "string",
+        category: "mail",
+        description: "SMTP password; overrides SMTP_PASSWORD when set",
+        default: "",
+        is_sensitive: true,
+        requires_restart: true,
+        validation_rules: r#"{"max_length": 512}"#,
+    },
+];
+
+pub fn definition(key: &str) -> Option<&'static ConfigDefinition> {
+    DEFINITIONS.iter().find(|d| d.key == key)
+}
+
+/// Typed view of the settings subsystems read while running
+#[derive(Debug, Clone)]
+pub struct RuntimeConfig {
+    pub gateway: GatewayConfig,
+    pub monitoring: MonitoringConfig,
+    pub max_failed_attempts: u32,
+    pub lockout_minutes: i64,
+    pub permission_cache_ttl_seconds: u64,
+    pub openclaw_config_ttl_seconds: u64,
+    pub agent_config_ttl_seconds: u64,
+    pub model_prices: HashMap<String, ModelPrice>,
+    pub budget_warning_threshold_percent: u32,
+    pub health_degraded_threshold: f64,
+    pub health_critical_threshold: f64,
+    pub model_failover_threshold: u32,
+    pub model_primary_retry_minutes: u64,
+    pub resource_sample_interval_seconds: u64,
+    pub resource_cpu_warning_percent: f64,
+    pub resource_cpu_critical_percent: f64,
+    pub resource_memory_warning_percent: f64,
+    pub resource_memory_critical_percent: f64,
+    pub bulk_job_max_concurrency: usize,
+    pub smtp_password: Option<String>,
+}
+
+/// USD per million tokens of each kind
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+    pub cache_read: f64,
+    pub cache_write: f64,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        let mut config = Self {
+            gateway: GatewayConfig {
+                check_interval_seconds: 0,
+                health_check_timeout: 0,
+                max_restart_attempts: 0,
+                notification_cooldown_minutes: 0,
+            },
+            monitoring: MonitoringConfig {
+                normal_priority_limit_minutes: 0,
+                urgent_priority_limit_minutes: 0,
+            },
+            max_failed_attempts: 0,
+            lockout_minutes: 0,
+            permission_cache_ttl_seconds: 0,
+            openclaw_config_ttl_seconds: 0,
+            agent_config_ttl_seconds: 0,
+            model_prices: HashMap::new(),
+            budget_warning_threshold_percent: 0,
+            health_degraded_threshold: 0.0,
+            health_critical_threshold: 0.0,
+            model_failover_threshold: 0,
+            model_primary_retry_minutes: 0,
+            resource_sample_interval_seconds: 0,
+            resource_cpu_warning_percent: 0.0,
+            resource_cpu_critical_percent: 0.0,
+            resource_memory_warning_percent: 0.0,
+            resource_memory_critical_percent: 0.0,
+            bulk_job_max_concurrency: 0,
+            smtp_password: None,
+        };
+        for definition in DEFINITIONS {
+            if let Err(e) = config.apply(definition.key, definition.default) {
+                warn!("Invalid default for {}: {}", definition.key, e);
+            }
+        }
+        config
+    }
+}
+
+impl RuntimeConfig {
+    /// Applies one stored value, leaving the previous setting in place if it does not parse
+    pub fn apply(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let number = || value.parse::<u64>().map_err(|_| format!("{} is not a whole number", value));
+        match key {
+            "gateway.check_interval_seconds" => self.gateway.check_interval_seconds = number()?,
+            "gateway.health_check_timeout_seconds" => self.gateway.health_check_timeout = number()?,
+            "gateway.max_restart_attempts" => self.gateway.max_restart_attempts = number()? as u32,
+            "gateway.notification_cooldown_minutes" => self.gateway.notification_cooldown_minutes = number()?,
+            "monitoring.normal_priority_limit_minutes" => self.monitoring.normal_priority_limit_minutes = number()?,
+            "monitoring.urgent_priority_limit_minutes" => self.monitoring.urgent_priority_limit_minutes = number()?,
+            "security.max_failed_attempts" => self.max_failed_attempts = number()? as u32,
+            "security.lockout_minutes" => self.lockout_minutes = number()? as i64,
+            "cache.permission_ttl_seconds" => self.permission_cache_ttl_seconds = number()?,
+            "cache.openclaw_config_ttl_seconds" => self.openclaw_config_ttl_seconds = number()?,
+            "cache.agent_config_ttl_seconds" => self.agent_config_ttl_seconds = number()?,
+            "usage.model_prices" => {
+                self.model_prices = serde_json::from_str(value)
+                    .map_err(|e| format!("Invalid price table: {}", e))?;
+            }
+            "budget.warning_threshold_percent" => self.budget_warning_threshold_percent = number()? as u32,
+            "health.degraded_threshold" => self.health_degraded_threshold = number()? as f64,
+            "health.critical_threshold" => self.health_critical_threshold = number()? as f64,
+            "models.failover_threshold" => self.model_failover_threshold = number()? as u32,
+            "models.primary_retry_minutes" => self.model_primary_retry_minutes = number()?,
+            "resources.sample_interval_seconds" => self.resource_sample_interval_seconds = number()?,
+            "resources.cpu_warning_percent" => self.resource_cpu_warning_percent = number()? as f64,
+            "resources.cpu_critical_percent" => self.resource_cpu_critical_percent = number()? as f64,
+            "resources.memory_warning_percent" => self.resource_memory_warning_percent = number()? as f64,
+            "resources.memory_critical_percent" => self.resource_memory_critical_percent = number()? as f64,
+            "bulk_jobs.max_concurrency" => self.bulk_job_max_concurrency = number()? as usize,
+            "mail.smtp_password" => {
+                self.smtp_password = Some(value.to_string()).filter(|v| !v.is_empty());
+            }
+            _ => return Err(format!("Unknown setting {}", key)),
+        }
+        Ok(())
+    }
+}
+
+/// Latest runtime configuration. Subsystems either read `current()` when they
+/// need a value or hold a `subscribe()` receiver to react to changes.
+static RUNTIME: Lazy<watch::Sender<Arc<RuntimeConfig>>> =
+    Lazy::new(|| watch::channel(Arc::new(RuntimeConfig::default())).0);
+
+pub fn current() -> Arc<RuntimeConfig> {
+    RUNTIME.borrow().clone()
+}
+
+pub fn subscribe() -> watch::Receiver<Arc<RuntimeConfig>> {
+    RUNTIME.subscribe()
+}
+
+/// A configuration row as returned by the API, with sensitive values masked
+#[derive(Debug, Serialize)]
+pub struct ConfigEntryView {
+    pub key: String,
+    pub value: serde_json::Value,
+    pub data_type: String,
+    pub description: Option<String>,
+    pub category: String,
+    pub is_sensitive: bool,
+    pub requires_restart: bool,
+    pub validation_rules: Option<serde_json::Value>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub updated_by: String,
+    pub version: i32,
+}
+
+impl From<SystemConfiguration> for ConfigEntryView {
+    fn from(entry: SystemConfiguration) -> Self {
+        let value = if entry.is_sensitive {
+            serde_json::Value::String(if entry.value.is_empty() { String::new() } else { MASK.to_string() })
+        } else {
+            typed_value(&entry.data_type, &entry.value)
+        };
+        Self {
+            key: entry.key,
+            value,
+            data_type: entry.data_type,
+            description: entry.description,
+            category: entry.category,
+            is_sensitive: entry.is_sensitive,
+            requires_restart: entry.requires_restart,
+            validation_rules: entry.validation_rules.as_deref().and_then(|r| serde_json::from_str(r).ok()),
+            updated_at: entry.updated_at,
+            updated_by: entry.updated_by,
+            version: entry.version,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfigQuery {
+    pub category: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateConfigRequest {
+    pub value: serde_json::Value,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfigUpdateResult {
+    pub entry: ConfigEntryView,
+    /// False when the new value only takes effect after a restart
+    pub applied: bool,
+}
+
+fn typed_value(data_type: &str, value: &str) -> serde_json::Value {
+    match data_type {
+        "number" | "boolean" | "json" => serde_json::from_str(value)
+            .unwrap_or_else(|_| serde_json::Value::String(value.to_string())),
+        _ => serde_json::Value::String(value.to_string()),
+    }
+}
+
+/// Checks `value` against the entry's type and `validation_rules` and returns the text to store.
+/// Supported rules: `min`, `max`, `integer` for numbers; `min_length`, `max_length`,
+/// `pattern` and `allowed` for strings.
+pub fn validate_value(entry: &SystemConfiguration, value: &serde_json::Value) -> Result<String, String> {
+    let rules: serde_json::Value = entry
+        .validation_rules
+        .as_deref()
+        .map(serde_json::from_str)
+        .transpose()
+        .map_err(|e| format!("Invalid validation rules for {}: {}", entry.key, e))?
+        .unwrap_or(serde_json::Value::Null);
+
+    match entry.data_type.as_str() {
+        "number" => {
+            let number = match value {
+                serde_json::Value::Number(n) => n.as_f64(),
+                serde_json::Value::String(s) => s.trim().parse::<f64>().ok(),
+                _ => None,
+            }
+            .ok_or_else(|| format!("{} must be a number", entry.key))?;
+
+            if rules.get("integer").and_then(|v| v.as_bool()).unwrap_or(false) && number.fract() != 0.0 {
+                return Err(format!("{} must be a whole number", entry.key));
+            }
+            if let Some(min) = rules.get("min").and_then(|v| v.as_f64())
+                && number < min {
+                    return Err(format!("{} must be at least {}", entry.key, min));
+                }
+            if let Some(max) = rules.get("max").and_then(|v| v.as_f64())
+                && number > max {
+                    return Err(format!("{} must be at most {}", entry.key, max));
+                }
+
+            if number.fract() == 0.0 {
+                Ok(format!("{}", number as i64))
+            } else {
+                Ok(number.to_string())
+            }
+        }
+        "boolean" => match value {
+            serde_json::Value::Bool(b) => Ok(b.to_string()),
+            serde_json::Value::String(s) if s == "true" || s == "false" => Ok(s.clone()),
+            _ => Err(format!("{} must be true or false", entry.key)),
+        },
+        "json" => Ok(value.to_string()),
+        _ => {
+            let text = value
+                .as_str()
+                .ok_or_else(|| format!("{} must be a string", entry.key))?;
+            let length = text.chars().count() as u64;
+
+            if let Some(min) = rules.get("min_length").and_then(|v| v.as_u64())
+                && length < min {
+                    return Err(format!("{} must be at least {} characters", entry.key, min));
+                }
+            if let Some(max) = rules.get("max_length").and_then(|v| v.as_u64())
+                && length > max {
+                    return Err(format!("{} must be at most {} characters", entry.key, max));
+                }
+            if let Some(pattern) = rules.get("pattern").and_then(|v| v.as_str()) {
+                let regex = regex::Regex::new(pattern)
+                    .map_err(|e| format!("Invalid pattern for {}: {}", entry.key, e))?;
+                if !regex.is_match(text) {
+                    return Err(format!("{} does not match {}", entry.key, pattern));
+                }
+            }
+            if let Some(allowed) = rules.get("allowed").and_then(|v| v.as_array())
+                && !allowed.iter().any(|a| a.as_str() == Some(text)) {
+                    return Err(format!("{} must be one of {}", entry.key, serde_json::Value::Array(allowed.clone())));
+                }
+
+            Ok(text.to_string())
+        }
+    }
+}
+
+/// The other half of a `*_warning_percent` / `*_critical_percent` pair
+fn threshold_partner(key: &str) -> Option<(String, bool)> {
+    if let Some(prefix) = key.strip_suffix("_warning_percent") {
+        Some((format!("{}_critical_percent", prefix), true))
+    } else {
+        key.strip_suffix("_critical_percent")
+            .map(|prefix| (format!("{}_warning_percent", prefix), false))
+    }
+}
+
+/// Rejects a warning level at or above its critical level
+pub fn check_threshold_order(key: &str, value: f64, partner_key: &str, partner_value: f64, is_warning: bool) -> Result<(), String> {
+    let (warning, critical) = if is_warning { (value, partner_value) } else { (partner_value, value) };
+    if warning < critical {
+        Ok(())
+    } else if is_warning {
+        Err(format!("{} must be below {} ({})", key, partner_key, partner_value))
+    } else {
+        Err(format!("{} must be above {} ({})", key, partner_key, partner_value))
+    }
+}
+
+pub struct ConfigService;
+
+impl ConfigService {
+    /// Seeds missing definitions and publishes the stored values. Runs once at
+    /// boot, which is also when `requires_restart` values take effect.
+    pub async fn load(&self, pool: &SqlitePool) -> Result<Arc<RuntimeConfig>, anyhow::Error> {
+        for definition in DEFINITIONS {
+            sqlx::query(
+                "INSERT OR IGNORE INTO system_configuration
+                 (id, key, value, data_type, description, category, is_sensitive, requires_restart, validation_rules, updated_by)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, 'system')"
+            )
+            .bind(uuid::Uuid::new_v4().to_string())
+            .bind(definition.key)
+            .bind(definition.default)
+            .bind(definition.data_type)
+            .bind(definition.description)
+            .bind(definition.category)
+            .bind(definition.is_sensitive)
+            .bind(definition.requires_restart)
+            .bind(definition.validation_rules)
+            .execute(pool)
+            .await?;
+        }
+
+        let mut config = RuntimeConfig::default();
+        for entry in self.list(pool, None).await? {
+            if definition(&entry.key).is_some()
+                && let Err(e) = config.apply(&entry.key, &entry.value) {
+                    warn!("Keeping default for {}: {}", entry.key, e);
+                }
+        }
+
+        let config = Arc::new(config);
+        RUNTIME.send_replace(config.clone());
+        info!("Loaded runtime configuration ({} settings)", DEFINITIONS.len());
+        Ok(config)
+    }
+
+    pub async fn list(&self, pool: &SqlitePool, category: Option<&str>) -> Result<Vec<SystemConfiguration>, anyhow::Error> {
+        let entries = sqlx::query_as::<sqlx::Sqlite, SystemConfiguration>(
+            "SELECT * FROM system_configuration WHERE (? IS NULL OR category = ?) ORDER BY category, key"
+        )
+        .bind(category)
+        .bind(category)
+        .fetch_all(pool)
+        .await?;
+        Ok(entries)
+    }
+
+    pub async fn get(&self, pool: &SqlitePool, key: &str) -> Result<Option<SystemConfiguration>, anyhow::Error> {
+        let entry = sqlx::query_as::<sqlx::Sqlite, SystemConfiguration>(
+            "SELECT * FROM system_configuration WHERE key = ?"
+        )
+        .bind(key)
+        .fetch_optional(pool)
+        .await?;
+        Ok(entry)
+    }
+
+    /// Validates and stores a new value, records the change and, unless the
+    /// setting needs a restart, publishes it to running subsystems.
+    pub async fn update(
+        &self,
+        pool: &SqlitePool,
+        key: &str,
+        request: &UpdateConfigRequest,
+        updated_by: &str,
+    ) -> Result<Option<ConfigUpdateResult>, anyhow::Error> {
+        let Some(existing) = self.get(pool, key).await? else {
+            return Ok(None);
+        };
+        let value = validate_value(&existing, &request.value).map_err(|e| anyhow::anyhow!(e))?;
+        if definition(key).is_some() {
+            // Refuse values the running server could not use
+            (*current()).clone().apply(key, &value).map_err(|e| anyhow::anyhow!(e))?;
+        }
+        if let Some((partner_key, is_warning)) = threshold_partner(key) {
+            let partner = self.get(pool, &partner_key).await?
+                .and_then(|entry| entry.value.parse::<f64>().ok());
+            if let (Some(partner_value), Ok(number)) = (partner, value.parse::<f64>()) {
+                check_threshold_order(key, number, &partner_key, partner_value, is_warning)
+                    .map_err(|e| anyhow::anyhow!(e))?;
+            }
+        }
+
+        let version = existing.version + 1;
+        let mut tx = pool.begin().await?;
+        let updated = sqlx::query(
+            "UPDATE system_configuration SET value = ?, version = ?, updated_by = ?, updated_at = CURRENT_TIMESTAMP
+             WHERE key = ? AND version = ?"
+        )
+        .bind(&value)
+        .bind(version)
+        .bind(updated_by)
+        .bind(key)
+        .bind(existing.version)
+        .execute(&mut *tx)
+        .await?;
+        if updated.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("{} was changed concurrently; retry", key));
+        }
+
+        sqlx::query(
+            "INSERT INTO system_configuration_history (id, config_key, version, old_value, new_value, changed_by, reason)
+             VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(key)
+        .bind(version)
+        .bind(&existing.value)
+        .bind(&value)
+        .bind(updated_by)
+        .bind(&request.reason)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        let (old_logged, new_logged) = if existing.is_sensitive {
+            (MASK.to_string(), MASK.to_string())
+        } else {
+            (existing.value.clone(), value.clone())
+        };
+        AuditService::log_entity_event(
+            pool,
+            "system",
+            &format!("config:{}", key),
+            "update",
+            Some(&serde_json::json!({ "value": old_logged, "version": existing.version }).to_string()),
+            Some(&serde_json::json!({ "value": new_logged, "version": version }).to_string()),
+            Some(updated_by),
+            None,
+            None,
+            None,
+            None,
+            request.reason.as_deref().map(|r| serde_json::json!({ "reason": r }).to_string()).as_deref(),
+        ).await?;
+
+        let applied = !existing.requires_restart && definition(key).is_some();
+        if applied {
+            let mut next = (*current()).clone();
+            next.apply(key, &value).map_err(|e| anyhow::anyhow!(e))?;
+            RUNTIME.send_replace(Arc::new(next));
+            info!("Configuration {} updated to version {}", key, version);
+        } else if existing.requires_restart {
+            info!("Configuration {} updated to version {}; takes effect after restart", key, version);
+        }
+
+        let entry = self.get(pool, key).await?
+            .ok_or_else(|| anyhow::anyhow!("Configuration entry disappeared"))?;
+        Ok(Some(ConfigUpdateResult { entry: entry.into(), applied }))
+    }
+
+    pub async fn history(&self, pool: &SqlitePool, key: &str) -> Result<Vec<ConfigurationChange>, anyhow::Error> {
+        let is_sensitive = self.get(pool, key).await?.map(|e| e.is_sensitive).unwrap_or(false);
+        let mut changes = sqlx::query_as::<sqlx::Sqlite, ConfigurationChange>(
+            "SELECT * FROM system_configuration_history WHERE config_key = ? ORDER BY version DESC"
+        )
+        .bind(key)
+        .fetch_all(pool)
+        .await?;
+
+        if is_sensitive {
+            for change in &mut changes {
+                change.old_value = change.old_value.as_ref().map(|_| MASK.to_string());
+                change.new_value = MASK.to_string();
+            }
+        }
+        Ok(changes)
+    }
+}
+
+// Axum Handlers
+pub async fn list_configuration(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ConfigQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "system", "read").await?;
+    let entries = ConfigService.list(&state.pool, query.category.as_deref()).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(entries.into_iter().map(ConfigEntryView::from).collect::<Vec<_>>()))
+}
+
+pub async fn get_configuration_entry(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(key): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "system", "read").await?;
+    match ConfigService.get(&state.pool, &key).await {
+        Ok(Some(entry)) => Ok(Json(ConfigEntryView::from(entry))),
+        Ok(None) => Err((StatusCode::NOT_FOUND, "Configuration key not found".to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+pub async fn update_configuration_entry(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(key): Path<String>,
+    Json(payload): Json<UpdateConfigRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&state.pool, &headers, "system", "write").await?;
+    match ConfigService.update(&state.pool, &key, &payload, &user.id).await {
+        Ok(Some(result)) => Ok(Json(result)),
+        Ok(None) => Err((StatusCode::NOT_FOUND, "Configuration key not found".to_string())),
+        Err(e) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+    }
+}
+
+pub async fn get_configuration_history(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(key): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "system", "read").await?;
+    ConfigService.history(&state.pool, &key).await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warning_thresholds_pair_with_critical() {
+        assert_eq!(
+            threshold_partner("resources.cpu_warning_percent"),
+            Some(("resources.cpu_critical_percent".to_string(), true))
+        );
+        assert_eq!(
+            threshold_partner("resources.memory_critical_percent"),
+            Some(("resources.memory_warning_percent".to_string(), false))
+        );
+        assert_eq!(threshold_partner("budget.warning_threshold_percent"), None);
+    }
+
+    #[test]
+    fn warning_must_stay_below_critical() {
+        assert!(check_threshold_order("cpu_warning_percent", 80.0, "cpu_critical_percent", 95.0, true).is_ok());
+        assert!(check_threshold_order("cpu_warning_percent", 95.0, "cpu_critical_percent", 95.0, true).is_err());
+        assert!(check_threshold_order("cpu_critical_percent", 70.0, "cpu_warning_percent", 80.0, false).is_err());
+        assert!(check_threshold_order("cpu_critical_percent", 90.0, "cpu_warning_percent", 80.0, false).is_ok());
+    }
+}
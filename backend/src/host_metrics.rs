@@ -0,0 +1,604 @@
+use crate::db::SqlitePool;
+use crate::ConnectionManager;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+use tracing::{info, warn};
+
+/// Days of host samples kept in `performance_metrics`
+const SAMPLE_RETENTION_DAYS: i64 = 7;
+/// Samples kept in each resource monitor's history
+pub const MONITOR_HISTORY: usize = 120;
+
+/// Raw counters read from /proc at one instant
+#[derive(Debug, Clone)]
+pub struct ProcReading {
+    at: Instant,
+    cpu_busy: u64,
+    cpu_total: u64,
+    cpu_count: usize,
+    memory_total_kb: u64,
+    memory_available_kb: u64,
+    network_bytes: u64,
+    disk_bytes: u64,
+    processes: Vec<AgentProcess>,
+}
+
+/// An OpenClaw process started for a known agent (`openclaw ... --agent <id>`)
+#[derive(Debug, Clone)]
+struct AgentProcess {
+    pid: u32,
+    agent_id: String,
+    cpu_ticks: u64,
+    rss_kb: u64,
+}
+
+/// Host usage derived from two consecutive readings. Rates are `None` on the
+/// first reading since there is nothing to compare against.
+#[derive(Debug, Clone, Serialize)]
+pub struct HostSample {
+    pub sampled_at: DateTime<Utc>,
+    pub interval_seconds: Option<f64>,
+    pub cpu_count: usize,
+    pub cpu_percent: Option<f64>,
+    pub memory_used_mb: u64,
+    pub memory_total_mb: u64,
+    pub memory_percent: f64,
+    /// MB received and sent on all interfaces but loopback during the interval
+    pub network_io_mb: Option<f64>,
+    /// MB read and written on whole disks during the interval
+    pub disk_io_mb: Option<f64>,
+    pub agents: Vec<AgentProcessUsage>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentProcessUsage {
+    pub agent_id: String,
+    pub pids: Vec<u32>,
+    /// Share of the whole host, like `cpu_percent`
+    pub cpu_percent: Option<f64>,
+    pub memory_mb: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThresholdLevel {
+    Normal,
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ResourceThresholds {
+    pub cpu_warning: f64,
+    pub cpu_critical: f64,
+    pub memory_warning: f64,
+    pub memory_critical: f64,
+}
+
+impl ResourceThresholds {
+    pub fn current() -> Self {
+        let runtime = crate::config::current();
+        Self {
+            cpu_warning: runtime.resource_cpu_warning_percent,
+            cpu_critical: runtime.resource_cpu_critical_percent,
+            memory_warning: runtime.resource_memory_warning_percent,
+            memory_critical: runtime.resource_memory_critical_percent,
+        }
+    }
+}
+
+/// A metric that moved to another threshold level
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceAlert {
+    pub metric: &'static str,
+    pub level: ThresholdLevel,
+    pub previous_level: ThresholdLevel,
+    pub value: f64,
+    pub threshold_warning: f64,
+    pub threshold_critical: f64,
+}
+
+/// Turns consecutive /proc readings into samples and tracks each metric's
+/// threshold level so alerts fire on changes only
+#[derive(Debug, Clone, Default)]
+pub struct HostSampler {
+    previous: Option<ProcReading>,
+    levels: HashMap<&'static str, ThresholdLevel>,
+    latest: Option<HostSample>,
+}
+
+impl HostSampler {
+    pub fn latest(&self) -> Option<&HostSample> {
+        self.latest.as_ref()
+    }
+
+    /// Metrics currently above their warning threshold
+    pub fn active_levels(&self) -> HashMap<&'static str, ThresholdLevel> {
+        self.levels
+            .iter()
+            .filter(|(_, level)| **level != ThresholdLevel::Normal)
+            .map(|(metric, level)| (*metric, *level))
+            .collect()
+    }
+
+    pub fn record(&mut self, reading: ProcReading, thresholds: &ResourceThresholds) -> (HostSample, Vec<ResourceAlert>) {
+        let previous = self.previous.as_ref();
+        let interval = previous.map(|p| reading.at.duration_since(p.at).as_secs_f64());
+        let cpu_delta = previous
+            .map(|p| reading.cpu_total.saturating_sub(p.cpu_total))
+            .filter(|delta| *delta > 0);
+        let cpu_percent = previous.zip(cpu_delta).map(|(p, total)| {
+            reading.cpu_busy.saturating_sub(p.cpu_busy) as f64 / total as f64 * 100.0
+        });
+        let megabytes = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+        let network_io_mb = previous.map(|p| megabytes(reading.network_bytes.saturating_sub(p.network_bytes)));
+        let disk_io_mb = previous.map(|p| megabytes(reading.disk_bytes.saturating_sub(p.disk_bytes)));
+
+        let previous_ticks: HashMap<u32, u64> = previous
+            .map(|p| p.processes.iter().map(|process| (process.pid, process.cpu_ticks)).collect())
+            .unwrap_or_default();
+        let mut agents: Vec<AgentProcessUsage> = Vec::new();
+        for process in &reading.processes {
+            let process_cpu = cpu_delta.map(|total| {
+                // A process first seen this round only counts from now on
+                let before = previous_ticks.get(&process.pid).copied().unwrap_or(process.cpu_ticks);
+                process.cpu_ticks.saturating_sub(before) as f64 / total as f64 * 100.0
+            });
+            let memory_mb = process.rss_kb as f64 / 1024.0;
+            match agents.iter_mut().find(|usage| usage.agent_id == process.agent_id) {
+                Some(usage) => {
+                    usage.pids.push(process.pid);
+                    usage.cpu_percent = usage.cpu_percent.zip(process_cpu).map(|(a, b)| a + b);
+                    usage.memory_mb += memory_mb;
+                }
+                None => agents.push(AgentProcessUsage {
+                    agent_id: process.agent_id.clone(),
+                    pids: vec![process.pid],
+                    cpu_percent: process_cpu,
+                    memory_mb,
+                }),
+            }
+        }
+
+        let memory_used_kb = reading.memory_total_kb.saturating_sub(reading.memory_available_kb);
+        let memory_percent = if reading.memory_total_kb == 0 {
+            0.0
+        } else {
+            memory_used_kb as f64 / reading.memory_total_kb as f64 * 100.0
+        };
+
+        let sample = HostSample {
+            sampled_at: Utc::now(),
+            interval_seconds: interval,
+            cpu_count: reading.cpu_count,
+            cpu_percent,
+            memory_used_mb: memory_used_kb / 1024,
+            memory_total_mb: reading.memory_total_kb / 1024,
+            memory_percent,
+            network_io_mb,
+            disk_io_mb,
+            agents,
+        };
+
+        let mut alerts = Vec::new();
+        let checks = [
+            ("cpu_percent", sample.cpu_percent, thresholds.cpu_warning, thresholds.cpu_critical),
+            ("memory_percent", Some(sample.memory_percent), thresholds.memory_warning, thresholds.memory_critical),
+        ];
+        for (metric, value, warning, critical) in checks {
+            let Some(value) = value else { continue };
+            let level = if value >= critical {
+                ThresholdLevel::Critical
+            } else if value >= warning {
+                ThresholdLevel::Warning
+            } else {
+                ThresholdLevel::Normal
+            };
+            let previous_level = self.levels.insert(metric, level).unwrap_or(ThresholdLevel::Normal);
+            if level != previous_level {
+                alerts.push(ResourceAlert {
+                    metric,
+                    level,
+                    previous_level,
+                    value,
+                    threshold_warning: warning,
+                    threshold_critical: critical,
+                });
+            }
+        }
+
+        self.previous = Some(reading);
+        self.latest = Some(sample.clone());
+        (sample, alerts)
+    }
+}
+
+/// Whether this host exposes the /proc files the sampler reads
+pub fn proc_available() -> bool {
+    std::path::Path::new("/proc/stat").exists() && std::path::Path::new("/proc/meminfo").exists()
+}
+
+/// Reads host-wide counters and the OpenClaw agent processes from /proc
+pub async fn read_proc() -> std::io::Result<ProcReading> {
+    let at = Instant::now();
+    let stat = tokio::fs::read_to_string("/proc/stat").await?;
+    let (cpu_busy, cpu_total, cpu_count) = parse_cpu(&stat)
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "unexpected /proc/stat format"))?;
+    let meminfo = tokio::fs::read_to_string("/proc/meminfo").await?;
+    let (memory_total_kb, memory_available_kb) = parse_meminfo(&meminfo)
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "unexpected /proc/meminfo format"))?;
+
+    let network_bytes = match tokio::fs::read_to_string("/proc/net/dev").await {
+        Ok(net_dev) => parse_net_dev(&net_dev),
+        Err(_) => 0,
+    };
+    let whole_disks = block_devices().await;
+    let disk_bytes = match tokio::fs::read_to_string("/proc/diskstats").await {
+        Ok(diskstats) => parse_diskstats(&diskstats, |name| {
+            whole_disks.as_ref().is_none_or(|disks| disks.contains(name))
+        }),
+        Err(_) => 0,
+    };
+
+    Ok(ProcReading {
+        at,
+        cpu_busy,
+        cpu_total,
+        cpu_count,
+        memory_total_kb,
+        memory_available_kb,
+        network_bytes,
+        disk_bytes,
+        processes: agent_processes().await,
+    })
+}
+
+/// Names under /sys/block, i.e. whole disks rather than partitions
+async fn block_devices() -> Option<HashSet<String>> {
+    let mut entries = tokio::fs::read_dir("/sys/block").await.ok()?;
+    let mut names = HashSet::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        names.insert(entry.file_name().to_string_lossy().to_string());
+    }
+    Some(names)
+}
+
+async fn agent_processes() -> Vec<AgentProcess> {
+    let mut processes = Vec::new();
+    let Ok(mut entries) = tokio::fs::read_dir("/proc").await else {
+        return processes;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+            continue;
+        };
+        // Processes can exit between listing and reading, so failures are skipped
+        let Ok(cmdline) = tokio::fs::read(format!("/proc/{}/cmdline", pid)).await else {
+            continue;
+        };
+        let Some(agent_id) = openclaw_agent(&cmdline) else {
+            continue;
+        };
+        let Ok(stat) = tokio::fs::read_to_string(format!("/proc/{}/stat", pid)).await else {
+            continue;
+        };
+        let Some(cpu_ticks) = parse_process_ticks(&stat) else {
+            continue;
+        };
+        let rss_kb = tokio::fs::read_to_string(format!("/proc/{}/status", pid))
+            .await
+            .ok()
+            .and_then(|status| parse_vm_rss_kb(&status))
+            .unwrap_or(0);
+        processes.push(AgentProcess { pid, agent_id, cpu_ticks, rss_kb });
+    }
+    processes
+}
+
+/// Busy and total jiffies across all CPUs, and the CPU count
+fn parse_cpu(stat: &str) -> Option<(u64, u64, usize)> {
+    let fields: Vec<u64> = stat
+        .lines()
+        .find(|line| line.starts_with("cpu "))?
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|value| value.parse().ok())
+        .collect();
+    if fields.len() < 4 {
+        return None;
+    }
+    // user nice system idle iowait irq softirq steal; guest time is already in user
+    let total: u64 = fields.iter().take(8).sum();
+    let idle = fields[3] + fields.get(4).copied().unwrap_or(0);
+    let cpu_count = stat
+        .lines()
+        .filter(|line| line.starts_with("cpu") && line[3..].starts_with(|c: char| c.is_ascii_digit()))
+        .count();
+    Some((total.saturating_sub(idle), total, cpu_count.max(1)))
+}
+
+/// MemTotal and MemAvailable in kB
+fn parse_meminfo(meminfo: &str) -> Option<(u64, u64)> {
+    let field = |name: &str| {
+        meminfo
+            .lines()
+            .find(|line| line.starts_with(name))
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|value| value.parse::<u64>().ok())
+    };
+    let total = field("MemTotal:")?;
+    // Kernels before 3.14 have no MemAvailable
+    let available = field("MemAvailable:").or_else(|| {
+        Some(field("MemFree:")? + field("Buffers:").unwrap_or(0) + field("Cached:").unwrap_or(0))
+    })?;
+    Some((total, available))
+}
+
+/// Bytes received plus sent on every interface except loopback
+fn parse_net_dev(net_dev: &str) -> u64 {
+    net_dev
+        .lines()
+        .skip(2)
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(name, _)| name.trim() != "lo")
+        .map(|(_, counters)| {
+            let fields: Vec<u64> = counters.split_whitespace().filter_map(|value| value.parse().ok()).collect();
+            fields.first().copied().unwrap_or(0) + fields.get(8).copied().unwrap_or(0)
+        })
+        .sum()
+}
+
+/// Bytes read plus written on the devices `is_disk` accepts
+fn parse_diskstats(diskstats: &str, is_disk: impl Fn(&str) -> bool) -> u64 {
+    diskstats
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let name = *fields.get(2)?;
+            if name.starts_with("loop") || name.starts_with("ram") || !is_disk(name) {
+                return None;
+            }
+            let sectors_read: u64 = fields.get(5)?.parse().ok()?;
+            let sectors_written: u64 = fields.get(9)?.parse().ok()?;
+            Some((sectors_read + sectors_written) * 512)
+        })
+        .sum()
+}
+
+/// User plus system clock ticks from /proc/<pid>/stat
+fn parse_process_ticks(stat: &str) -> Option<u64> {
+    // The command name may contain spaces, so fields are counted from its closing parenthesis
+    let fields: Vec<&str> = stat.get(stat.rfind(')')? + 1..)?.split_whitespace().collect();
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some(utime + stime)
+}
+
+fn parse_vm_rss_kb(status: &str) -> Option<u64> {
+    status
+        .lines()
+        .find(|line| line.starts_with("VmRSS:"))?
+        .split_whitespace()
+        .nth(1)?
+        .parse()
+        .ok()
+}
+
+/// The agent id of an `openclaw` command line with `--agent <id>`
+fn openclaw_agent(cmdline: &[u8]) -> Option<String> {
+    let args: Vec<String> = cmdline
+        .split(|byte| *byte == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).to_string())
+        .collect();
+    // Either the binary itself or a script run by node
+    let is_openclaw = args
+        .iter()
+        .take(2)
+        .any(|arg| arg.rsplit('/').next().is_some_and(|name| name.starts_with("openclaw")));
+    if !is_openclaw {
+        return None;
+    }
+    args.iter().enumerate().find_map(|(i, arg)| match arg.strip_prefix("--agent=") {
+        Some(agent_id) => Some(agent_id.to_string()),
+        None if arg == "--agent" => args.get(i + 1).cloned(),
+        None => None,
+    })
+}
+
+/// (name, value, unit, labels, entity id, warning, critical)
+type MetricRow<'a> = (&'a str, f64, &'a str, String, Option<&'a str>, Option<f64>, Option<f64>);
+
+/// Persists samples and announces threshold changes
+pub struct HostMetricsService;
+
+impl HostMetricsService {
+    /// Stores the sample in `performance_metrics` (source `system`) and prunes old samples
+    pub async fn store(&self, pool: &SqlitePool, sample: &HostSample, thresholds: &ResourceThresholds) -> Result<(), sqlx::Error> {
+        let interval = serde_json::json!({ "interval_seconds": sample.interval_seconds }).to_string();
+        let memory = serde_json::json!({ "used_mb": sample.memory_used_mb, "total_mb": sample.memory_total_mb }).to_string();
+        let cpu = serde_json::json!({ "cpu_count": sample.cpu_count }).to_string();
+
+        let mut rows: Vec<MetricRow> = Vec::new();
+        if let Some(value) = sample.cpu_percent {
+            rows.push(("host_cpu_usage", value, "percent", cpu, None, Some(thresholds.cpu_warning), Some(thresholds.cpu_critical)));
+        }
+        rows.push((
+            "host_memory_usage",
+            sample.memory_percent,
+            "percent",
+            memory,
+            None,
+            Some(thresholds.memory_warning),
+            Some(thresholds.memory_critical),
+        ));
+        if let Some(value) = sample.network_io_mb {
+            rows.push(("host_network_io", value, "MB", interval.clone(), None, None, None));
+        }
+        if let Some(value) = sample.disk_io_mb {
+            rows.push(("host_disk_io", value, "MB", interval, None, None, None));
+        }
+        for usage in &sample.agents {
+            let labels = serde_json::json!({ "pids": usage.pids }).to_string();
+            if let Some(value) = usage.cpu_percent {
+                rows.push(("agent_process_cpu_usage", value, "percent", labels.clone(), Some(&usage.agent_id), None, None));
+            }
+            rows.push(("agent_process_memory", usage.memory_mb, "MB", labels, Some(&usage.agent_id), None, None));
+        }
+
+        let mut tx = pool.begin().await?;
+        for (name, value, unit, labels, entity_id, warning, critical) in rows {
+            sqlx::query(
+                "INSERT INTO performance_metrics
+                     (id, metric_name, metric_type, value, labels, timestamp, source, entity_id, entity_type, unit, threshold_warning, threshold_critical)
+                 VALUES (?, ?, 'gauge', ?, ?, ?, 'system', ?, ?, ?, ?, ?)"
+            )
+            .bind(uuid::Uuid::new_v4().to_string())
+            .bind(name)
+            .bind(value)
+            .bind(labels)
+            .bind(sample.sampled_at)
+            .bind(entity_id)
+            .bind(entity_id.map(|_| "agent").unwrap_or("host"))
+            .bind(unit)
+            .bind(warning)
+            .bind(critical)
+            .execute(&mut *tx)
+            .await?;
+        }
+        sqlx::query("DELETE FROM performance_metrics WHERE source = 'system' AND timestamp < datetime('now', ?)")
+            .bind(format!("-{} days", SAMPLE_RETENTION_DAYS))
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    }
+
+    pub fn announce(&self, manager: &ConnectionManager, alerts: &[ResourceAlert]) {
+        for alert in alerts {
+            match alert.level {
+                ThresholdLevel::Normal => info!("Host {} back to normal at {:.1}", alert.metric, alert.value),
+                level => warn!(
+                    "Host {} is {:?} at {:.1} (warning {:.0}, critical {:.0})",
+                    alert.metric, level, alert.value, alert.threshold_warning, alert.threshold_critical
+                ),
+            }
+            let mut event = serde_json::json!({ "type": "resource_alert" });
+            if let (Some(event), Ok(serde_json::Value::Object(fields))) = (event.as_object_mut(), serde_json::to_value(alert)) {
+                event.extend(fields);
+            }
+            manager.broadcast(&event.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROC_STAT: &str = "\
+cpu  4705 356 584 3699176 23060 0 277 0 0 0
+cpu0 1393 280 260 924648 5796 0 168 0 0 0
+cpu1 1087 21 113 925114 5692 0 42 0 0 0
+cpu2 1116 29 104 924676 5766 0 38 0 0 0
+cpu3 1109 26 107 924738 5806 0 29 0 0 0
+intr 114930548 113199788 3 0 5 263 0 4
+ctxt 1990473
+btime 1062191376
+processes 2915
+";
+
+    const PROC_MEMINFO: &str = "\
+MemTotal:       16303340 kB
+MemFree:         1234567 kB
+MemAvailable:    9876543 kB
+Buffers:          345678 kB
+Cached:          4567890 kB
+SwapCached:        11111 kB
+";
+
+    // As written by kernels before 3.14
+    const PROC_MEMINFO_OLD: &str = "\
+MemTotal:       16303340 kB
+MemFree:         1234567 kB
+Buffers:          345678 kB
+Cached:          4567890 kB
+SwapCached:        11111 kB
+";
+
+    const PROC_NET_DEV: &str = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo: 2776770   11307    0    0    0     0          0         0  2776770   11307    0    0    0     0       0          0
+  eth0: 1215645    2751    0    0    0     0          0         0  1782404    4324    0    0    0   427       0          0
+ wlan0:    1000      10    0    0    0     0          0         0     2000      20    0    0    0     0       0          0
+";
+
+    const PROC_DISKSTATS: &str = "\
+   7       0 loop0 52 0 2136 17 0 0 0 0 0 40 17 0 0 0 0
+   8       0 sda 60112 17543 4181236 41234 98765 54321 2345678 123456 0 56789 164690 0 0 0 0
+   8       1 sda1 59000 17000 4100000 41000 98000 54000 2300000 123000 0 56000 164000 0 0 0 0
+ 259       0 nvme0n1 1000 0 20000 500 2000 0 30000 800 0 900 1300 0 0 0 0
+";
+
+    #[test]
+    fn cpu_totals_and_count() {
+        assert_eq!(parse_cpu(PROC_STAT), Some((5922, 3728158, 4)));
+        assert_eq!(parse_cpu("cpu  1 2 3\n"), None);
+        assert_eq!(parse_cpu("intr 1 2 3\n"), None);
+    }
+
+    #[test]
+    fn meminfo_available_memory() {
+        assert_eq!(parse_meminfo(PROC_MEMINFO), Some((16303340, 9876543)));
+        // Free plus buffers and page cache; SwapCached is not page cache
+        assert_eq!(parse_meminfo(PROC_MEMINFO_OLD), Some((16303340, 1234567 + 345678 + 4567890)));
+        assert_eq!(parse_meminfo("MemFree: 1 kB\n"), None);
+    }
+
+    #[test]
+    fn net_dev_skips_loopback() {
+        assert_eq!(parse_net_dev(PROC_NET_DEV), 1215645 + 1782404 + 1000 + 2000);
+    }
+
+    #[test]
+    fn diskstats_counts_whole_disks_only() {
+        let disks = ["sda", "nvme0n1"];
+        assert_eq!(parse_diskstats(PROC_DISKSTATS, |name| disks.contains(&name)), (4181236 + 2345678 + 20000 + 30000) * 512);
+        // Loop devices are left out even when accepted
+        assert_eq!(
+            parse_diskstats(PROC_DISKSTATS, |_| true),
+            (4181236 + 2345678 + 4100000 + 2300000 + 20000 + 30000) * 512
+        );
+    }
+
+    #[test]
+    fn process_ticks_with_awkward_command_names() {
+        let stat = "1234 (openclaw agent) S 1 1234 1234 0 -1 4194560 2345 0 12 0 150 45 0 0 20 0 11 0 3456 123456789 5678 18446744073709551615";
+        assert_eq!(parse_process_ticks(stat), Some(195));
+        let stat = "42 (weird) name) R 1 42 42 0 -1 4194304 10 0 0 0 7 3 0 0 20 0 1 0 100 1000 50 18446744073709551615";
+        assert_eq!(parse_process_ticks(stat), Some(10));
+        assert_eq!(parse_process_ticks("42 (truncated) R 1 42"), None);
+        assert_eq!(parse_process_ticks("no command name"), None);
+    }
+
+    #[test]
+    fn vm_rss() {
+        assert_eq!(parse_vm_rss_kb("Name:\topenclaw\nVmPeak:\t  200000 kB\nVmRSS:\t  123456 kB\n"), Some(123456));
+        assert_eq!(parse_vm_rss_kb("Name:\tkthreadd\n"), None);
+    }
+
+    #[test]
+    fn openclaw_agent_from_command_line() {
+        assert_eq!(
+            openclaw_agent(b"/usr/bin/node\0/usr/lib/node_modules/openclaw/bin/openclaw.js\0gateway\0--agent\0research\0"),
+            Some("research".to_string())
+        );
+        assert_eq!(openclaw_agent(b"/usr/local/bin/openclaw\0--agent=main\0"), Some("main".to_string()));
+        assert_eq!(openclaw_agent(b"/usr/local/bin/openclaw\0gateway\0"), None);
+        assert_eq!(openclaw_agent(b"/usr/local/bin/openclaw\0--agent\0"), None);
+        assert_eq!(openclaw_agent(b"/usr/bin/python3\0script.py\0--agent\0research\0"), None);
+        assert_eq!(openclaw_agent(b""), None);
+    }
+}
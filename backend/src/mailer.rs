@@ -0,0 +1,162 @@
+use futures::future::BoxFuture;
+use lettre::message::{header::ContentType, Mailbox, Message};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use serde::{Serialize, Deserialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutgoingMail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Delivery backend for account mail (password resets and similar).
+/// Selected at startup by `mailer_from_env`.
+pub trait MailSender: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn send(&self, mail: OutgoingMail) -> BoxFuture<'_, Result<(), anyhow::Error>>;
+}
+
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(
+        host: &str,
+        port: u16,
+        username: Option<String>,
+        password: Option<String>,
+        from: &str,
+        starttls: bool,
+    ) -> Result<Self, anyhow::Error> {
+        let mut builder = if starttls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)
+        };
+        builder = builder.port(port);
+
+        if let (Some(username), Some(password)) = (username, password) {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from: from.parse()?,
+        })
+    }
+}
+
+impl MailSender for SmtpMailer {
+    fn name(&self) -> &'static str {
+        "smtp"
+    }
+
+    fn send(&self, mail: OutgoingMail) -> BoxFuture<'_, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let message = Message::builder()
+                .from(self.from.clone())
+                .to(mail.to.parse()?)
+                .subject(mail.subject)
+                .header(ContentType::TEXT_PLAIN)
+                .body(mail.body)?;
+
+            self.transport.send(message).await?;
+            Ok(())
+        })
+    }
+}
+
+/// Writes each message to `<dir>/<timestamp>-<id>.eml` for local development
+pub struct FileMailer {
+    dir: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl MailSender for FileMailer {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    fn send(&self, mail: OutgoingMail) -> BoxFuture<'_, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            tokio::fs::create_dir_all(&self.dir).await?;
+            let path = self.dir.join(format!(
+                "{}-{}.eml",
+                chrono::Utc::now().format("%Y%m%dT%H%M%S"),
+                uuid::Uuid::new_v4()
+            ));
+            let contents = format!("To: {}\nSubject: {}\n\n{}\n", mail.to, mail.subject, mail.body);
+            tokio::fs::write(&path, contents).await?;
+
+            info!("Mail to {} written to {}", mail.to, path.display());
+            Ok(())
+        })
+    }
+}
+
+/// Logs messages instead of sending them; the default when nothing is configured
+pub struct LogMailer;
+
+impl MailSender for LogMailer {
+    fn name(&self) -> &'static str {
+        "log"
+    }
+
+    fn send(&self, mail: OutgoingMail) -> BoxFuture<'_, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            info!("Mail to {} ({}):\n{}", mail.to, mail.subject, mail.body);
+            Ok(())
+        })
+    }
+}
+
+/// Builds the mail sender from MAIL_TRANSPORT (smtp, file or log) and its settings.
+/// A `mail.smtp_password` configuration value takes precedence over SMTP_PASSWORD.
+pub fn mailer_from_env() -> Arc<dyn MailSender> {
+    let transport = std::env::var("MAIL_TRANSPORT").unwrap_or_else(|_| "log".to_string());
+
+    match transport.as_str() {
+        "smtp" => {
+            let host = std::env::var("SMTP_HOST").unwrap_or_else(|_| "localhost".to_string());
+            let port = std::env::var("SMTP_PORT").ok().and_then(|p| p.parse().ok()).unwrap_or(587);
+            let from = std::env::var("SMTP_FROM").unwrap_or_else(|_| "ClawController <noreply@localhost>".to_string());
+            let starttls = std::env::var("SMTP_STARTTLS").map(|v| v != "false").unwrap_or(true);
+
+            match SmtpMailer::new(
+                &host,
+                port,
+                std::env::var("SMTP_USERNAME").ok(),
+                crate::config::current().smtp_password.clone().or_else(|| std::env::var("SMTP_PASSWORD").ok()),
+                &from,
+                starttls,
+            ) {
+                Ok(mailer) => Arc::new(mailer),
+                Err(e) => {
+                    warn!("Invalid SMTP configuration ({}), falling back to log mailer", e);
+                    Arc::new(LogMailer)
+                }
+            }
+        }
+        "file" => {
+            let dir = std::env::var("MAIL_OUTBOX_DIR").unwrap_or_else(|_| "mail-outbox".to_string());
+            Arc::new(FileMailer::new(dir))
+        }
+        "log" => Arc::new(LogMailer),
+        other => {
+            warn!("Unknown MAIL_TRANSPORT '{}', falling back to log mailer", other);
+            Arc::new(LogMailer)
+        }
+    }
+}
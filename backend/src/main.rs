@@ -1,4 +1,5 @@
 pub(crate) mod db;
+pub(crate) mod migrations;
 pub(crate) mod models;
 pub(crate) mod openclaw_monitoring;
 pub(crate) mod openclaw_integration;
@@ -11,15 +12,35 @@ pub(crate) mod agent_management_db;
 pub(crate) mod security;
 pub(crate) mod validation;
 pub(crate) mod audit;
+pub(crate) mod backup;
+pub(crate) mod config;
+pub(crate) mod rbac;
+pub(crate) mod classification;
+pub(crate) mod mailer;
+pub(crate) mod rate_limit;
 pub(crate) mod agent_management;
+pub(crate) mod agent_metrics;
+pub(crate) mod usage;
+pub(crate) mod budget;
+pub(crate) mod agent_health;
+pub(crate) mod model_failover;
+pub(crate) mod agent_learning;
+pub(crate) mod host_metrics;
+pub(crate) mod agent_templates;
+pub(crate) mod agent_jobs;
+pub(crate) mod agent_config_history;
+
+#[cfg(test)]
+#[path = "../tests/mod.rs"]
+mod tests;
 
 use axum::{
     extract::{ws::{Message, WebSocket, WebSocketUpgrade}, Path, State},
-    routing::{get, post, patch, put},
+    routing::{get, post, patch, put, delete},
     Router,
     Json,
     response::IntoResponse,
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
 };
 use sqlx::SqlitePool;
 use std::net::SocketAddr;
@@ -37,14 +58,32 @@ use crate::openclaw_monitoring::*;
 use crate::openclaw_advanced_features::*;
 use crate::openclaw_optimization::*;
 use crate::audit::*;
-use crate::agent_management_db::*;
+use crate::backup::*;
+use crate::config::{ConfigService, list_configuration, get_configuration_entry, update_configuration_entry, get_configuration_history};
+use crate::rbac::*;
+use crate::classification::{BroadcastEvent, Clearance};
+use crate::agent_metrics::{AgentMetricsService, MetricsPeriod, rollup_agent_metrics};
+use crate::usage::{UsageService, ingest_usage, get_cost_report};
+use crate::budget::{BudgetService, get_agent_budget, grant_budget_override};
+use crate::agent_health::{AgentHealthService, RoutingHealth, list_agent_health, run_agent_health_check, get_agent_health_history};
+use crate::model_failover::{ModelFailoverService, report_model_event, get_agent_model_state, get_model_error_rates};
+use crate::agent_learning::{AgentLearningService, submit_agent_feedback, get_agent_learning, list_agent_adaptations, approve_agent_adaptation, reject_agent_adaptation};
+use crate::agent_templates::{create_agent_template, get_catalogue_template, update_agent_template, list_agent_template_versions, rate_agent_template, get_agent_template_lineage, acknowledge_agent_template_update};
+use crate::agent_jobs::{AgentJobService, BulkJobRegistry, start_bulk_agent_job, list_bulk_agent_jobs, get_bulk_agent_job, cancel_bulk_agent_job};
+use crate::agent_config_history::{list_agent_config_versions, get_agent_config_version, diff_agent_config_versions, rollback_agent_config};
 use tokio::process::Command;
 use chrono::Utc;
 use axum::middleware;
 
 // Connection Manager for WebSockets
 pub struct ConnectionManager {
-    tx: broadcast::Sender<String>,
+    tx: broadcast::Sender<BroadcastEvent>,
+}
+
+impl Default for ConnectionManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ConnectionManager {
@@ -54,10 +93,18 @@ impl ConnectionManager {
     }
 
     pub fn broadcast(&self, message: &str) {
-        let _ = self.tx.send(message.to_string());
+        self.broadcast_classified(SecurityLevel::Public, message);
+    }
+
+    /// Sends `message` in full only to subscribers cleared for `classification`
+    pub fn broadcast_classified(&self, classification: SecurityLevel, message: &str) {
+        let _ = self.tx.send(BroadcastEvent {
+            classification,
+            message: message.to_string(),
+        });
     }
 
-    pub fn subscribe(&self) -> broadcast::Receiver<String> {
+    pub fn subscribe(&self) -> broadcast::Receiver<BroadcastEvent> {
         self.tx.subscribe()
     }
 }
@@ -68,6 +115,13 @@ struct AppState {
     manager: Arc<ConnectionManager>,
     gateway_status: Arc<RwLock<GatewayStatus>>,
     stuck_task_status: Arc<RwLock<StuckTaskStatus>>,
+    mailer: Arc<dyn crate::mailer::MailSender>,
+    rate_limiter: Arc<crate::rate_limit::RateLimiter>,
+    collaboration: Arc<AgentCollaboration>,
+    agent_pool: Arc<AgentPool>,
+    resource_manager: Arc<RwLock<DynamicResourceManager>>,
+    cache: Arc<HierarchicalCache>,
+    bulk_jobs: Arc<BulkJobRegistry>,
 }
 
 #[tokio::main]
@@ -76,9 +130,15 @@ async fn main() -> anyhow::Result<()> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
+    // `backend migrate status|up|down` manages the schema and exits
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("migrate") {
+        let pool = db::connect().await?;
+        return migrations::run_cli(&pool, &args[2..]).await;
+    }
+
     let pool = db::setup_db().await?;
-    // Setup agent management tables
-    agent_management_db::setup_agent_management_tables(&pool).await?;
+    let runtime_config = ConfigService.load(&pool).await?;
     // Insert default agent templates
     agent_management_db::insert_default_agent_templates(&pool).await?;
     let manager = ConnectionManager::new();
@@ -88,26 +148,252 @@ async fn main() -> anyhow::Result<()> {
         uptime_seconds: 0,
         last_check_time: Utc::now(),
         restart_count: 0,
-        config: GatewayConfig {
-            check_interval_seconds: 60,
-            health_check_timeout: 5,
-            max_restart_attempts: 3,
-            notification_cooldown_minutes: 30,
-        },
+        config: runtime_config.gateway.clone(),
     }));
 
     let stuck_task_status = Arc::new(RwLock::new(StuckTaskStatus {
         total_notifications_sent: 0,
         currently_tracked_tasks: 0,
         last_run: Utc::now(),
-        config: MonitoringConfig {
-            normal_priority_limit_minutes: 120,
-            urgent_priority_limit_minutes: 30,
-        },
+        config: runtime_config.monitoring.clone(),
     }));
 
-    let state = AppState { pool, manager: Arc::new(manager), gateway_status, stuck_task_status };
+    let mailer = crate::mailer::mailer_from_env();
+    tracing::info!("Mail transport: {}", mailer.name());
+
+    let rate_limiter = Arc::new(crate::rate_limit::RateLimiter::new(crate::rate_limit::RateLimitConfig::from_env()));
+
+    let collaboration = Arc::new(AgentCollaboration::load(&pool).await?);
+    let agent_pool = Arc::new(AgentPool::load(&pool).await?);
+    let resource_manager = Arc::new(RwLock::new(DynamicResourceManager::new()));
+    let cache = Arc::new(HierarchicalCache::new(1000, 5000));
+    AgentJobService.recover_interrupted(&pool).await.map_err(|e| anyhow::anyhow!(e.to_string()))?;
+    let bulk_jobs = Arc::new(BulkJobRegistry::new());
+
+    let state = AppState { pool, manager: Arc::new(manager), gateway_status, stuck_task_status, mailer, rate_limiter, collaboration, agent_pool, resource_manager, cache, bulk_jobs };
+
+    let app = create_app(state.clone());
+
+    // Spawn background tasks
+    let state_task = state.clone();
+    tokio::spawn(async move {
+        let mut config_updates = crate::config::subscribe();
+        let mut healthy_since: Option<tokio::time::Instant> = None;
+        loop {
+            // Run checks immediately on start and then every check interval
+            tracing::info!("Running background checks...");
+            let runtime = crate::config::current();
+            let interval = runtime.gateway.check_interval_seconds;
+            
+            // 1. Check Gateway Health
+            let gateway_active = check_gateway_connectivity(runtime.gateway.health_check_timeout).await;
+            {
+                let mut status = state_task.gateway_status.write().await;
+                status.health_status = if gateway_active { "healthy".to_string() } else { "crashed".to_string() };
+                status.last_check_time = Utc::now();
+                status.config = runtime.gateway.clone();
+                // Measured rather than counted in intervals, which restarts and changes shorten
+                if gateway_active {
+                    let since = *healthy_since.get_or_insert_with(tokio::time::Instant::now);
+                    status.uptime_seconds = since.elapsed().as_secs();
+                } else {
+                    healthy_since = None;
+                    status.uptime_seconds = 0;
+                }
+            }
+
+            // 2. Monitor Stuck Tasks
+            if let Ok(stuck_count) = perform_stuck_task_check(&state_task.pool, &runtime.monitoring).await {
+                let mut status = state_task.stuck_task_status.write().await;
+                status.currently_tracked_tasks = stuck_count as u32;
+                status.last_run = Utc::now();
+                status.config = runtime.monitoring.clone();
+            }
+
+            // 3. Drop idle rate limit buckets
+            state_task.rate_limiter.prune();
+
+            // A new check interval applies at once; other configuration changes keep the current wait
+            let next_check = tokio::time::Instant::now() + tokio::time::Duration::from_secs(interval);
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep_until(next_check) => break,
+                    _ = config_updates.changed() => {
+                        if crate::config::current().gateway.check_interval_seconds != interval {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    });
+
+    // Enforce audit retention policies every hour
+    let retention_pool = state.pool.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_secs(3600)).await;
+
+            match AuditService.enforce_retention_policies(&retention_pool, None).await {
+                Ok(summary) => tracing::info!(
+                    "Audit retention removed {} audit entries and {} security events ({} legal holds active)",
+                    summary.audit_entries_deleted,
+                    summary.security_events_deleted,
+                    summary.active_legal_holds
+                ),
+                Err(e) => tracing::error!("Audit retention failed: {}", e),
+            }
+        }
+    });
+
+    // Pick up token usage appended to OpenClaw session transcripts every five minutes
+    // and hold agents to their spend caps against it
+    let usage_state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = UsageService.ingest(&usage_state.pool).await {
+                tracing::error!("Usage ingestion failed: {}", e);
+            }
+            if let Err(e) = BudgetService.enforce(&usage_state.pool, &usage_state.manager).await {
+                tracing::error!("Budget enforcement failed: {}", e);
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(300)).await;
+        }
+    });
+
+    // Score agent health every fifteen minutes
+    let health_state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = AgentHealthService.score_all(&health_state.pool, &health_state.manager, classification::anonymous_clearance()).await {
+                tracing::error!("Agent health scoring failed: {}", e);
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(900)).await;
+        }
+    });
+
+    // Move agents that have sat on a fallback model long enough back to their primary
+    let failover_state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = ModelFailoverService.retry_primaries(&failover_state.pool, &failover_state.manager, &failover_state.cache).await {
+                tracing::error!("Primary model retry failed: {}", e);
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(300)).await;
+        }
+    });
+
+    // Keep or roll back applied agent adaptations once their evaluation window is over
+    let learning_state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = AgentLearningService.evaluate_applied(&learning_state.pool, &learning_state.manager, &learning_state.cache).await {
+                tracing::error!("Adaptation evaluation failed: {}", e);
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(600)).await;
+        }
+    });
+
+    // Settle team ballots whose voting deadline has passed
+    let ballot_state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = ballot_state.collaboration.close_expired_ballots(&ballot_state.pool, &ballot_state.manager).await {
+                tracing::error!("Closing expired ballots failed: {}", e);
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
+        }
+    });
+
+    // Resync the agent pool with agents and assignments changed outside the API
+    let pool_state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
+            if let Err(e) = pool_state.agent_pool.refresh(&pool_state.pool).await {
+                tracing::error!("Agent pool refresh failed: {}", e);
+            }
+        }
+    });
+
+    // Drop cached OpenClaw configs whenever openclaw.json changes on disk
+    let watched_cache = state.cache.clone();
+    tokio::spawn(async move {
+        watched_cache.watch_openclaw_config().await;
+    });
+
+    // Sample host CPU, memory, network and disk usage from /proc
+    if crate::host_metrics::proc_available() {
+        let resource_state = state.clone();
+        tokio::spawn(async move {
+            loop {
+                match crate::host_metrics::read_proc().await {
+                    Ok(reading) => {
+                        let thresholds = crate::host_metrics::ResourceThresholds::current();
+                        let (sample, alerts) = resource_state.resource_manager.write().await.record_host_reading(reading, &thresholds);
+                        if let Err(e) = crate::host_metrics::HostMetricsService.store(&resource_state.pool, &sample, &thresholds).await {
+                            tracing::error!("Storing host resource sample failed: {}", e);
+                        }
+                        crate::host_metrics::HostMetricsService.announce(&resource_state.manager, &alerts);
+                    }
+                    Err(e) => tracing::error!("Reading host resources failed: {}", e),
+                }
+                let interval = crate::config::current().resource_sample_interval_seconds.max(5);
+                tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
+            }
+        });
+    } else {
+        tracing::info!("Host resource sampling disabled: /proc is not available");
+    }
 
+    // Roll agent metrics up every hour; yesterday is included for late-arriving activity
+    let metrics_pool = state.pool.clone();
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = AgentMetricsService.rollup(&metrics_pool, &MetricsPeriod::last_days(2)).await {
+                tracing::error!("Agent metrics rollup failed: {}", e);
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(3600)).await;
+        }
+    });
+
+    // Scheduled backups, followed by pruning those past their retention
+    let backup_config = BackupConfig::from_env();
+    if backup_config.interval_hours > 0 {
+        let backup_pool = state.pool.clone();
+        let interval = backup_config.interval_hours * 3600;
+        tokio::spawn(async move {
+            let service = BackupService::new(backup_config);
+            loop {
+                tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
+
+                match service.create_backup(&backup_pool, &CreateBackupRequest::default(), "system").await {
+                    Ok(record) => tracing::info!("Scheduled backup {} finished as {}", record.id, record.status),
+                    Err(e) => tracing::error!("Scheduled backup failed: {}", e),
+                }
+                match service.prune_expired(&backup_pool).await {
+                    Ok(summary) if summary.expired > 0 => tracing::info!(
+                        "Backup retention expired {} backups, freeing {} bytes",
+                        summary.expired,
+                        summary.bytes_freed
+                    ),
+                    Ok(_) => {}
+                    Err(e) => tracing::error!("Backup retention failed: {}", e),
+                }
+            }
+        });
+    }
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], 8000));
+    tracing::info!("listening on {}", addr);
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
+
+    Ok(())
+}
+
+/// Every route and middleware layer, bound to `state`
+fn create_app(state: AppState) -> Router {
     let api_routes = Router::<AppState>::new()
         .route("/agents", get(get_agents).post(create_agent))
         .route("/agents/:id", get(get_agent).patch(update_agent).delete(delete_agent))
@@ -126,6 +412,7 @@ async fn main() -> anyhow::Result<()> {
         .route("/chat", get(get_chat_messages).post(send_chat_message))
         .route("/chat/send-to-agent", post(send_chat_message_to_agent))
         .route("/models", get(get_models))
+        .route("/models/error-rates", get(get_model_error_rates))
         .route("/agents/generate", post(generate_agent_config))
         .route("/agents/:id/files", get(get_agent_files).put(update_agent_files))
         .route("/tasks/:id/review", post(review_task))
@@ -137,6 +424,8 @@ async fn main() -> anyhow::Result<()> {
         .route("/monitoring/gateway/restart", post(restart_gateway))
         .route("/monitoring/stuck-tasks/status", get(get_stuck_task_status))
         .route("/monitoring/stuck-tasks/check", post(run_stuck_task_check))
+        .route("/monitoring/agents/health", get(list_agent_health))
+        .route("/monitoring/agents/health/check", post(run_agent_health_check))
         // Enhanced OpenClaw Integration Endpoints
         .route("/openclaw/config/agents", get(get_openclaw_agent_configs))
         .route("/openclaw/config/agents/:id", get(get_openclaw_agent_config))
@@ -156,10 +445,30 @@ async fn main() -> anyhow::Result<()> {
         .route("/openclaw/refresh", post(refresh_openclaw_config))
         .route("/openclaw/agents/:id/parameters/events", post(update_agent_parameters_with_events))
         // Enhanced Agent Management
-        .route("/agents", post(create_or_update_agent_comprehensive))
-        .route("/agents/:id", get(get_agent_comprehensive))
+        .route("/agents/comprehensive", post(create_or_update_agent_comprehensive))
+        .route("/agents/:id/comprehensive", get(get_agent_comprehensive))
         .route("/agents/:id/clone", post(clone_agent))
         .route("/agents/:id/recommendations", get(get_agent_recommendations))
+        .route("/agents/:id/analytics", get(get_agent_analytics))
+        .route("/agents/compare", post(compare_agents))
+        .route("/agents/metrics/rollup", post(rollup_agent_metrics))
+        .route("/agents/:id/budget", get(get_agent_budget))
+        .route("/agents/:id/health", get(get_agent_health_history))
+        .route("/agents/:id/model", get(get_agent_model_state))
+        .route("/agents/:id/model-events", post(report_model_event))
+        .route("/agents/:id/feedback", get(get_agent_learning).post(submit_agent_feedback))
+        .route("/agents/:id/adaptations", get(list_agent_adaptations))
+        .route("/agents/:id/template", get(get_agent_template_lineage))
+        .route("/agents/:id/template/acknowledge", post(acknowledge_agent_template_update))
+        .route("/agents/:id/config-versions", get(list_agent_config_versions))
+        .route("/agents/:id/config-versions/diff", get(diff_agent_config_versions))
+        .route("/agents/:id/config-versions/:version", get(get_agent_config_version))
+        .route("/agents/:id/config-versions/:version/rollback", post(rollback_agent_config))
+        .route("/agents/:id/adaptations/:adaptation_id/approve", post(approve_agent_adaptation))
+        .route("/agents/:id/adaptations/:adaptation_id/reject", post(reject_agent_adaptation))
+        .route("/agents/:id/budget/overrides", post(grant_budget_override))
+        .route("/usage/ingest", post(ingest_usage))
+        .route("/usage/costs", get(get_cost_report))
         // User-friendly Agent Management
         .route("/agents/quick", post(create_agent_quick))
         .route("/agents/wizard", post(configuration_wizard))
@@ -167,6 +476,13 @@ async fn main() -> anyhow::Result<()> {
         .route("/agents/:id/suggestions", get(get_smart_suggestions))
         .route("/agents/validate", post(validate_configuration_friendly_handler))
         .route("/agents/templates/user-friendly", get(get_templates_user_friendly))
+        .route("/agents/templates", post(create_agent_template))
+        .route("/agents/templates/:id", get(get_catalogue_template).patch(update_agent_template))
+        .route("/agents/templates/:id/versions", get(list_agent_template_versions))
+        .route("/agents/templates/:id/ratings", post(rate_agent_template))
+        .route("/agents/bulk-jobs", get(list_bulk_agent_jobs).post(start_bulk_agent_job))
+        .route("/agents/bulk-jobs/:id", get(get_bulk_agent_job))
+        .route("/agents/bulk-jobs/:id/cancel", post(cancel_bulk_agent_job))
         .route("/agents/compare/visual", post(compare_agents_visual))
         // Performance Optimization Endpoints
         .route("/optimization/cache/warm", post(warm_cache))
@@ -174,67 +490,71 @@ async fn main() -> anyhow::Result<()> {
         .route("/optimization/pool/status", get(get_pool_status))
         .route("/optimization/resources/status", get(get_resource_status))
         // Advanced Collaboration Features
-        .route("/collaboration/teams", post(create_collaboration_team))
+        .route("/collaboration/teams", get(list_collaboration_teams).post(create_collaboration_team))
+        .route("/collaboration/teams/:id", get(get_collaboration_team).patch(update_collaboration_team).delete(delete_collaboration_team))
+        .route("/collaboration/teams/:id/members", post(add_team_member))
+        .route("/collaboration/teams/:id/members/:agent_id", patch(update_team_member).delete(remove_team_member))
+        .route("/collaboration/teams/:id/access/:agent_id", put(set_team_access_control).delete(remove_team_access_control))
+        .route("/collaboration/teams/:id/context", get(get_team_context))
+        .route("/collaboration/teams/:id/context/:key", get(get_team_context_key).put(put_team_context_key).delete(delete_team_context_key))
+        .route("/collaboration/teams/:id/context/:key/history", get(get_team_context_history))
         .route("/collaboration/teams/:id/delegate", post(delegate_task_to_team))
+        .route("/collaboration/teams/:id/ballots", get(list_team_ballots).post(open_team_ballot))
+        .route("/collaboration/ballots/:id", get(get_team_ballot))
+        .route("/collaboration/ballots/:id/votes", post(cast_ballot_vote))
+        .route("/collaboration/ballots/:id/resolve", post(resolve_team_ballot))
         .route("/collaboration/status", get(get_advanced_features_status))
         // Security and Validation
         .route("/security/login", post(authenticate_user))
+        .route("/security/login/2fa", post(complete_two_factor_login))
+        .route("/security/2fa/enroll", post(enroll_two_factor))
+        .route("/security/2fa/verify", post(verify_two_factor_enrollment))
+        .route("/security/2fa/disable", post(disable_two_factor))
         .route("/security/users", post(create_user))
         .route("/security/users/:id", patch(update_user))
         .route("/security/password/change", post(change_password))
-        .route("/security/sessions", post(create_session))
+        .route("/security/password/forgot", post(forgot_password))
+        .route("/security/password/reset", post(reset_password))
+        .route("/security/sessions", get(list_my_sessions).post(create_session).delete(revoke_my_sessions))
+        .route("/security/sessions/step-up", post(step_up_session))
+        .route("/security/sessions/:id", delete(revoke_session_handler))
+        .route("/security/users/:id/sessions", get(list_user_sessions).delete(revoke_user_sessions))
+        .route("/security/users/:id/roles", get(list_user_roles).post(assign_user_role))
+        .route("/security/users/:id/roles/:role_id", delete(remove_user_role))
+        .route("/security/roles", get(list_roles).post(create_role))
+        .route("/security/roles/:id", patch(update_role).delete(delete_role))
+        .route("/security/permissions", get(list_permissions))
+        .route("/security/me/permissions", get(get_my_permissions))
         .route("/security/audit", get(get_audit_trail))
         .route("/security/events", get(get_security_events))
+        .route("/security/audit/export", get(export_audit_trail))
+        .route("/security/audit/retention", get(get_retention_policies))
+        .route("/security/audit/retention/enforce", post(enforce_retention_now))
+        .route("/security/audit/retention/:entity_type", put(update_retention_policy))
+        .route("/security/audit/legal-holds", get(list_legal_holds).post(place_legal_hold))
+        .route("/security/audit/legal-holds/:id", delete(release_legal_hold))
+        .route("/security/compliance/report", get(get_compliance_report))
+        .route("/system/config", get(list_configuration))
+        .route("/system/config/:key", get(get_configuration_entry).patch(update_configuration_entry))
+        .route("/system/config/:key/history", get(get_configuration_history))
+        .route("/system/backups", get(list_backups).post(create_backup))
+        .route("/system/backups/prune", post(prune_backups))
+        .route("/system/backups/:id/verify", post(verify_backup))
+        .route("/system/backups/:id/restore", post(restore_backup))
         // Validation Endpoints
         .route("/validation/agent", post(validate_agent_creation_handler))
         .route("/validation/task", post(validate_task_creation_handler))
         .route("/validation/comment", post(validate_comment_creation_handler));
 
-    let app = Router::<AppState>::new()
+    Router::<AppState>::new()
         .route("/", get(root))
         .route("/ws", get(ws_handler))
         .nest("/api", api_routes)
+        .layer(crate::rate_limit::RateLimitLayer::new(state.rate_limiter.clone(), state.pool.clone()))
         .layer(CorsLayer::permissive())
         .layer(middleware::from_fn(monitoring_middleware))
-        .with_state(state.clone());
-
-    // Spawn background tasks
-    let state_task = state.clone();
-    tokio::spawn(async move {
-        loop {
-            // Run checks immediately on start and then every minute
-            tracing::info!("Running background checks...");
-            
-            // 1. Check Gateway Health
-            let gateway_active = check_gateway_connectivity().await;
-            {
-                let mut status = state_task.gateway_status.write().await;
-                status.health_status = if gateway_active { "healthy".to_string() } else { "crashed".to_string() };
-                status.last_check_time = Utc::now();
-                if gateway_active {
-                    status.uptime_seconds += 60;
-                } else {
-                    status.uptime_seconds = 0;
-                }
-            }
-
-            // 2. Monitor Stuck Tasks
-            if let Ok(stuck_count) = perform_stuck_task_check(&state_task.pool).await {
-                let mut status = state_task.stuck_task_status.write().await;
-                status.currently_tracked_tasks = stuck_count as u32;
-                status.last_run = Utc::now();
-            }
-
-            tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
-        }
-    });
-
-    let addr = SocketAddr::from(([0, 0, 0, 0], 8000));
-    tracing::info!("listening on {}", addr);
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
-
-    Ok(())
+        .layer(middleware::from_fn(crate::security::security_headers_middleware))
+        .with_state(state)
 }
 
 async fn root() -> &'static str {
@@ -244,16 +564,17 @@ async fn root() -> &'static str {
 async fn ws_handler(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
+    Clearance(clearance): Clearance,
 ) -> impl IntoResponse {
-    ws.on_upgrade(|socket| handle_socket(socket, state))
+    ws.on_upgrade(move |socket| handle_socket(socket, state, clearance))
 }
 
-async fn handle_socket(mut socket: WebSocket, state: AppState) {
+async fn handle_socket(mut socket: WebSocket, state: AppState, clearance: SecurityLevel) {
     let mut rx = state.manager.subscribe();
     
     let mut send_task = tokio::spawn(async move {
-        while let Ok(msg) = rx.recv().await {
-            if socket.send(Message::Text(msg)).await.is_err() {
+        while let Ok(event) = rx.recv().await {
+            if socket.send(Message::Text(event.render_for(clearance))).await.is_err() {
                 break;
             }
         }
@@ -269,7 +590,7 @@ async fn get_agents(
     State(state): State<AppState>,
 ) -> Result<Json<Vec<Agent>>, (StatusCode, String)> {
     let agents = sqlx::query_as::<sqlx::Sqlite, Agent>(
-        "SELECT id, name, role, description, avatar, status, workspace, token, primary_model, fallback_model, current_model, model_failure_count, created_at FROM agents"
+        "SELECT * FROM agents"
     )
     .fetch_all(&state.pool)
     .await
@@ -283,7 +604,7 @@ async fn get_agent(
     State(state): State<AppState>,
 ) -> Result<Json<Agent>, (StatusCode, String)> {
     let agent = sqlx::query_as::<sqlx::Sqlite, Agent>(
-        "SELECT id, name, role, description, avatar, status, workspace, token, primary_model, fallback_model, current_model, model_failure_count, created_at FROM agents WHERE id = ?"
+        "SELECT * FROM agents WHERE id = ?"
     )
     .bind(id)
     .fetch_one(&state.pool)
@@ -295,11 +616,16 @@ async fn get_agent(
 
 async fn create_agent(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<serde_json::Value>,
-) -> Result<Json<Agent>, (StatusCode, String)> {
-    // Basic implementation for now
+) -> Result<(StatusCode, Json<Agent>), (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "write").await?;
     let id = uuid::Uuid::new_v4().to_string();
-    let name = payload["name"].as_str().unwrap_or("Unknown");
+    let name = payload["name"]
+        .as_str()
+        .map(str::trim)
+        .filter(|name| !name.is_empty() && name.len() <= 255)
+        .ok_or((StatusCode::BAD_REQUEST, "Name must be 1 to 255 characters".to_string()))?;
     
     sqlx::query("INSERT INTO agents (id, name, role, status, created_at) VALUES (?, ?, 'SPC', 'IDLE', CURRENT_TIMESTAMP)")
         .bind(&id)
@@ -308,14 +634,17 @@ async fn create_agent(
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    get_agent(Path(id), State(state)).await
+    let agent = get_agent(Path(id), State(state)).await?;
+    Ok((StatusCode::CREATED, agent))
 }
 
 async fn update_agent(
     Path(id): Path<String>,
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<serde_json::Value>,
 ) -> Result<Json<Agent>, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "write").await?;
     if let Some(status) = payload["status"].as_str() {
         sqlx::query("UPDATE agents SET status = ? WHERE id = ?")
             .bind(status)
@@ -331,7 +660,9 @@ async fn update_agent(
 async fn delete_agent(
     Path(id): Path<String>,
     State(state): State<AppState>,
+    headers: HeaderMap,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "delete").await?;
     sqlx::query("DELETE FROM agents WHERE id = ?")
         .bind(id)
         .execute(&state.pool)
@@ -343,10 +674,12 @@ async fn delete_agent(
 
 async fn get_tasks(
     State(state): State<AppState>,
+    Clearance(clearance): Clearance,
 ) -> Result<Json<Vec<Task>>, (StatusCode, String)> {
-    let tasks = sqlx::query_as::<sqlx::Sqlite, Task>(
-        "SELECT id, title, description, status, priority, tags, assignee_id, reviewer, reviewer_id, created_at, updated_at, due_at FROM tasks"
-    )
+    let tasks = sqlx::query_as::<sqlx::Sqlite, Task>(&format!(
+        "SELECT * FROM tasks WHERE classification IN ({})",
+        classification::visible_levels_sql(clearance)
+    ))
     .fetch_all(&state.pool)
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
@@ -357,65 +690,142 @@ async fn get_tasks(
 async fn get_task(
     Path(id): Path<String>,
     State(state): State<AppState>,
+    Clearance(clearance): Clearance,
 ) -> Result<Json<Task>, (StatusCode, String)> {
-    let task = sqlx::query_as::<sqlx::Sqlite, Task>(
-        "SELECT id, title, description, status, priority, tags, assignee_id, reviewer, reviewer_id, created_at, updated_at, due_at FROM tasks WHERE id = ?"
-    )
+    fetch_visible_task(&state.pool, &id, clearance).await.map(Json)
+}
+
+/// Loads a task the caller is cleared for. Tasks above the clearance are
+/// reported as missing rather than forbidden so their existence does not leak.
+async fn fetch_visible_task(
+    pool: &SqlitePool,
+    id: &str,
+    clearance: SecurityLevel,
+) -> Result<Task, (StatusCode, String)> {
+    sqlx::query_as::<sqlx::Sqlite, Task>(&format!(
+        "SELECT * FROM tasks WHERE id = ? AND classification IN ({})",
+        classification::visible_levels_sql(clearance)
+    ))
     .bind(id)
-    .fetch_one(&state.pool)
+    .fetch_optional(pool)
     .await
-    .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Task not found".to_string()))
+}
 
-    Ok(Json(task))
+/// Who is creating a task: the signed-in user, or the agent behind `x-agent-key`
+async fn task_author(pool: &SqlitePool, headers: &HeaderMap) -> Result<String, (StatusCode, String)> {
+    if headers.contains_key("authorization") {
+        return crate::security::authenticated_user(pool, headers).await.map(|user| user.id);
+    }
+    let agent_key = headers
+        .get("x-agent-key")
+        .and_then(|v| v.to_str().ok())
+        .ok_or((StatusCode::UNAUTHORIZED, "Sign in or send an agent key to create tasks".to_string()))?;
+    sqlx::query_scalar("SELECT id FROM agents WHERE token = ? AND is_deleted = 0")
+        .bind(agent_key)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::UNAUTHORIZED, "Unknown agent key".to_string()))
 }
 
 async fn create_task(
     State(state): State<AppState>,
+    Clearance(clearance): Clearance,
+    headers: HeaderMap,
     Json(payload): Json<serde_json::Value>,
 ) -> Result<Json<Task>, (StatusCode, String)> {
     let id = uuid::Uuid::new_v4().to_string();
-    let title = payload["title"].as_str().ok_or((StatusCode::BAD_REQUEST, "Title required".to_string()))?;
+    let title = payload["title"]
+        .as_str()
+        .map(str::trim)
+        .filter(|title| !title.is_empty() && title.chars().count() <= 500)
+        .ok_or((StatusCode::BAD_REQUEST, "Title must be 1 to 500 characters".to_string()))?;
+    let task_classification = classification::classification_from_payload(&payload)?
+        .unwrap_or(SecurityLevel::Internal);
+    classification::ensure_can_classify(clearance, task_classification)?;
+    let created_by = task_author(&state.pool, &headers).await?;
     
-    sqlx::query("INSERT INTO tasks (id, title, status, priority, created_at, updated_at) VALUES (?, ?, 'INBOX', 'NORMAL', CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)")
+    sqlx::query("INSERT INTO tasks (id, title, status, priority, classification, created_by, created_at, updated_at) VALUES (?, ?, 'INBOX', 'NORMAL', ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)")
         .bind(&id)
         .bind(title)
+        .bind(task_classification)
+        .bind(&created_by)
         .execute(&state.pool)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    state.manager.broadcast(&format!(r#"{{"type": "task_created", "task_id": "{}"}}"#, id));
+    state.manager.broadcast_classified(task_classification, &format!(r#"{{"type": "task_created", "task_id": "{}"}}"#, id));
 
-    get_task(Path(id), State(state)).await
+    fetch_visible_task(&state.pool, &id, clearance).await.map(Json)
 }
 
 async fn update_task(
     Path(id): Path<String>,
     State(state): State<AppState>,
+    Clearance(clearance): Clearance,
     Json(payload): Json<serde_json::Value>,
 ) -> Result<Json<Task>, (StatusCode, String)> {
-    if let Some(status) = payload["status"].as_str() {
-        sqlx::query("UPDATE tasks SET status = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
-            .bind(status)
+    let task = fetch_visible_task(&state.pool, &id, clearance).await?;
+    let mut task_classification = task.classification;
+
+    if let Some(new_classification) = classification::classification_from_payload(&payload)? {
+        classification::ensure_can_classify(clearance, new_classification)?;
+        sqlx::query("UPDATE tasks SET classification = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(new_classification)
             .bind(&id)
             .execute(&state.pool)
             .await
             .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        // Subscribers that could see the old classification still learn about the change
+        task_classification = task_classification.min(new_classification);
+    }
+
+    if let Some(status) = payload["status"].as_str() {
+        // Leaving REVIEW through a status edit is a review decision too
+        let outcome = match (task.status == TaskStatus::Review, status) {
+            (true, "DONE") => Some("approved"),
+            (true, "IN_PROGRESS" | "ASSIGNED") => Some("rejected"),
+            _ => None,
+        };
+
+        let mut tx = state.pool.begin().await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        set_task_status(&mut tx, &id, status).await?;
+        if let Some(outcome) = outcome {
+            crate::agent_metrics::record_review(&mut tx, &id, task.reviewer_id.as_deref(), outcome, None).await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        }
+        tx.commit().await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        state.agent_pool.sync_task(&state.pool, &id).await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        if let Some(outcome) = outcome {
+            crate::agent_metrics::learn_from_review(&state.pool, &id, outcome, None).await;
+        }
             
-        state.manager.broadcast(&format!(r#"{{"type": "status_changed", "task_id": "{}", "status": "{}"}}"#, id, status));
+        state.manager.broadcast_classified(task_classification, &format!(r#"{{"type": "status_changed", "task_id": "{}", "status": "{}"}}"#, id, status));
     }
     
-    get_task(Path(id), State(state)).await
+    fetch_visible_task(&state.pool, &id, clearance).await.map(Json)
 }
 
 async fn delete_task(
     Path(id): Path<String>,
     State(state): State<AppState>,
+    Clearance(clearance): Clearance,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    fetch_visible_task(&state.pool, &id, clearance).await?;
+
     sqlx::query("DELETE FROM tasks WHERE id = ?")
-        .bind(id)
+        .bind(&id)
         .execute(&state.pool)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    state.agent_pool.sync_task(&state.pool, &id).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     Ok(StatusCode::NO_CONTENT)
 }
@@ -423,10 +833,15 @@ async fn delete_task(
 async fn get_comments(
     Path(task_id): Path<String>,
     State(state): State<AppState>,
+    Clearance(clearance): Clearance,
 ) -> Result<Json<Vec<Comment>>, (StatusCode, String)> {
-    let comments = sqlx::query_as::<sqlx::Sqlite, Comment>(
-        "SELECT id, task_id, agent_id, content, created_at FROM comments WHERE task_id = ?"
-    )
+    fetch_visible_task(&state.pool, &task_id, clearance).await?;
+
+    let comments = sqlx::query_as::<sqlx::Sqlite, Comment>(&format!(
+        "SELECT id, task_id, agent_id, content, classification, created_at FROM comments
+         WHERE task_id = ? AND (classification IS NULL OR classification IN ({}))",
+        classification::visible_levels_sql(clearance)
+    ))
     .bind(task_id)
     .fetch_all(&state.pool)
     .await
@@ -438,30 +853,38 @@ async fn get_comments(
 async fn create_comment(
     Path(task_id): Path<String>,
     State(state): State<AppState>,
+    Clearance(clearance): Clearance,
     Json(payload): Json<serde_json::Value>,
 ) -> Result<Json<Comment>, (StatusCode, String)> {
+    let task = fetch_visible_task(&state.pool, &task_id, clearance).await?;
     let id = uuid::Uuid::new_v4().to_string();
     let agent_id = payload["agent_id"].as_str().ok_or((StatusCode::BAD_REQUEST, "agent_id required".to_string()))?;
     let content = payload["content"].as_str().ok_or((StatusCode::BAD_REQUEST, "content required".to_string()))?;
+    let comment_classification = classification::classification_from_payload(&payload)?;
+    if let Some(level) = comment_classification {
+        classification::ensure_can_classify(clearance, level)?;
+    }
 
-    sqlx::query("INSERT INTO comments (id, task_id, agent_id, content, created_at) VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP)")
+    sqlx::query("INSERT INTO comments (id, task_id, agent_id, content, classification, created_at) VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP)")
         .bind(&id)
         .bind(&task_id)
         .bind(agent_id)
         .bind(content)
+        .bind(comment_classification)
         .execute(&state.pool)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     let comment = sqlx::query_as::<sqlx::Sqlite, Comment>(
-        "SELECT id, task_id, agent_id, content, created_at FROM comments WHERE id = ?"
+        "SELECT id, task_id, agent_id, content, classification, created_at FROM comments WHERE id = ?"
     )
     .bind(&id)
     .fetch_one(&state.pool)
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    state.manager.broadcast(&format!(r#"{{"type": "comment_added", "task_id": "{}"}}"#, task_id));
+    let effective = comment_classification.map_or(task.classification, |level| level.max(task.classification));
+    state.manager.broadcast_classified(effective, &format!(r#"{{"type": "comment_added", "task_id": "{}"}}"#, task_id));
 
     Ok(Json(comment))
 }
@@ -512,10 +935,14 @@ async fn create_announcement(
 
 async fn get_activity(
     State(state): State<AppState>,
+    Clearance(clearance): Clearance,
 ) -> Result<Json<Vec<ActivityLog>>, (StatusCode, String)> {
-    let activity = sqlx::query_as::<sqlx::Sqlite, ActivityLog>(
-        "SELECT id, activity_type, agent_id, task_id, description, created_at FROM activity_log ORDER BY created_at DESC LIMIT 50"
-    )
+    let activity = sqlx::query_as::<sqlx::Sqlite, ActivityLog>(&format!(
+        "SELECT id, activity_type, agent_id, task_id, description, created_at FROM activity_log
+         WHERE task_id IS NULL OR task_id IN (SELECT id FROM tasks WHERE classification IN ({}))
+         ORDER BY created_at DESC LIMIT 50",
+        classification::visible_levels_sql(clearance)
+    ))
     .fetch_all(&state.pool)
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
@@ -526,7 +953,10 @@ async fn get_activity(
 async fn get_task_activity(
     Path(task_id): Path<String>,
     State(state): State<AppState>,
+    Clearance(clearance): Clearance,
 ) -> Result<Json<Vec<TaskActivity>>, (StatusCode, String)> {
+    fetch_visible_task(&state.pool, &task_id, clearance).await?;
+
     let activity = sqlx::query_as::<sqlx::Sqlite, TaskActivity>(
         "SELECT id, task_id, agent_id, message, timestamp FROM task_activity WHERE task_id = ? ORDER BY timestamp DESC"
     )
@@ -541,8 +971,10 @@ async fn get_task_activity(
 async fn add_task_activity(
     Path(task_id): Path<String>,
     State(state): State<AppState>,
+    Clearance(clearance): Clearance,
     Json(payload): Json<serde_json::Value>,
 ) -> Result<Json<TaskActivity>, (StatusCode, String)> {
+    let task = fetch_visible_task(&state.pool, &task_id, clearance).await?;
     let id = uuid::Uuid::new_v4().to_string();
     let agent_id = payload["agent_id"].as_str();
     let message = payload["message"].as_str().ok_or((StatusCode::BAD_REQUEST, "message required".to_string()))?;
@@ -564,15 +996,17 @@ async fn add_task_activity(
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    state.manager.broadcast(&format!(r#"{{"type": "task_activity_added", "task_id": "{}"}}"#, task_id));
+    state.manager.broadcast_classified(task.classification, &format!(r#"{{"type": "task_activity_added", "task_id": "{}"}}"#, task_id));
 
     Ok(Json(activity))
 }
 
 async fn get_stats(
     State(state): State<AppState>,
+    Clearance(clearance): Clearance,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    let task_count: i32 = sqlx::query_scalar::<sqlx::Sqlite, i32>("SELECT COUNT(*) FROM tasks")
+    let visible = classification::visible_levels_sql(clearance);
+    let task_count: i32 = sqlx::query_scalar::<sqlx::Sqlite, i32>(&format!("SELECT COUNT(*) FROM tasks WHERE classification IN ({})", visible))
         .fetch_one(&state.pool)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
@@ -582,7 +1016,7 @@ async fn get_stats(
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    let done_count: i32 = sqlx::query_scalar::<sqlx::Sqlite, i32>("SELECT COUNT(*) FROM tasks WHERE status = 'DONE'")
+    let done_count: i32 = sqlx::query_scalar::<sqlx::Sqlite, i32>(&format!("SELECT COUNT(*) FROM tasks WHERE status = 'DONE' AND classification IN ({})", visible))
         .fetch_one(&state.pool)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
@@ -597,10 +1031,15 @@ async fn get_stats(
 async fn get_deliverables(
     Path(task_id): Path<String>,
     State(state): State<AppState>,
+    Clearance(clearance): Clearance,
 ) -> Result<Json<Vec<Deliverable>>, (StatusCode, String)> {
-    let deliverables = sqlx::query_as::<sqlx::Sqlite, Deliverable>(
-        "SELECT id, task_id, title, description, status, created_at FROM deliverables WHERE task_id = ?"
-    )
+    fetch_visible_task(&state.pool, &task_id, clearance).await?;
+
+    let deliverables = sqlx::query_as::<sqlx::Sqlite, Deliverable>(&format!(
+        "SELECT id, task_id, title, description, status, classification, created_at FROM deliverables
+         WHERE task_id = ? AND (classification IS NULL OR classification IN ({}))",
+        classification::visible_levels_sql(clearance)
+    ))
     .bind(task_id)
     .fetch_all(&state.pool)
     .await
@@ -612,30 +1051,38 @@ async fn get_deliverables(
 async fn create_deliverable(
     Path(task_id): Path<String>,
     State(state): State<AppState>,
+    Clearance(clearance): Clearance,
     Json(payload): Json<serde_json::Value>,
 ) -> Result<Json<Deliverable>, (StatusCode, String)> {
+    let task = fetch_visible_task(&state.pool, &task_id, clearance).await?;
     let id = uuid::Uuid::new_v4().to_string();
     let title = payload["title"].as_str().ok_or((StatusCode::BAD_REQUEST, "title required".to_string()))?;
     let description = payload["description"].as_str();
+    let deliverable_classification = classification::classification_from_payload(&payload)?;
+    if let Some(level) = deliverable_classification {
+        classification::ensure_can_classify(clearance, level)?;
+    }
 
-    sqlx::query("INSERT INTO deliverables (id, task_id, title, description, status, created_at) VALUES (?, ?, ?, ?, 'PENDING', CURRENT_TIMESTAMP)")
+    sqlx::query("INSERT INTO deliverables (id, task_id, title, description, status, classification, created_at) VALUES (?, ?, ?, ?, 'PENDING', ?, CURRENT_TIMESTAMP)")
         .bind(&id)
         .bind(&task_id)
         .bind(title)
         .bind(description)
+        .bind(deliverable_classification)
         .execute(&state.pool)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     let deliverable = sqlx::query_as::<sqlx::Sqlite, Deliverable>(
-        "SELECT id, task_id, title, description, status, created_at FROM deliverables WHERE id = ?"
+        "SELECT id, task_id, title, description, status, classification, created_at FROM deliverables WHERE id = ?"
     )
     .bind(&id)
     .fetch_one(&state.pool)
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    state.manager.broadcast(&format!(r#"{{"type": "deliverable_added", "task_id": "{}"}}"#, task_id));
+    let effective = deliverable_classification.map_or(task.classification, |level| level.max(task.classification));
+    state.manager.broadcast_classified(effective, &format!(r#"{{"type": "deliverable_added", "task_id": "{}"}}"#, task_id));
 
     Ok(Json(deliverable))
 }
@@ -643,33 +1090,123 @@ async fn create_deliverable(
 async fn route_task(
     Path(id): Path<String>,
     State(state): State<AppState>,
+    Clearance(clearance): Clearance,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    let task = get_task(Path(id.clone()), State(state.clone())).await?;
+    let task = fetch_visible_task(&state.pool, &id, clearance).await?;
     let assignee_id = task.assignee_id.clone().ok_or((StatusCode::BAD_REQUEST, "Task has no assignee".to_string()))?;
+    let session = route_to_agent_session(&state, &task, &assignee_id).await?;
+
+    Ok(Json(serde_json::json!({
+        "status": "success",
+        "message": "Task routed to agent session",
+        "output": session.output,
+        "health_warning": session.health_warning
+    })))
+}
+
+struct RoutedSession {
+    output: String,
+    health_warning: Option<String>,
+}
+
+/// Spawns an OpenClaw session for the task on `assignee_id` once clearance, budget
+/// and health allow it
+async fn route_to_agent_session(state: &AppState, task: &Task, assignee_id: &str) -> Result<RoutedSession, (StatusCode, String)> {
+    let id = &task.id;
+    let agent_clearance = classification::agent_clearance(&state.pool, assignee_id).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Assignee not found".to_string()))?;
+    if agent_clearance < task.classification {
+        let service = SecurityService::new(jwt_secret());
+        if let Err(e) = service.log_security_event(
+            &state.pool,
+            "unauthorized_access",
+            "Refused to route a task to an agent without sufficient clearance",
+            None,
+            Some(format!("task:{}", id)),
+            None,
+            serde_json::json!({
+                "agent_id": assignee_id,
+                "agent_clearance": classification::level_name(agent_clearance),
+                "task_classification": classification::level_name(task.classification),
+            }).to_string(),
+        ).await {
+            tracing::warn!("Failed to record refused routing: {}", e);
+        }
+
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!(
+                "Agent {} is cleared for {} but the task is {}",
+                assignee_id,
+                classification::level_name(agent_clearance),
+                classification::level_name(task.classification)
+            ),
+        ));
+    }
+
+    if let Some(reason) = BudgetService.routing_refusal(&state.pool, assignee_id, id).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    {
+        return Err((StatusCode::FORBIDDEN, reason));
+    }
+
+    let health_warning = match AgentHealthService.routing_health(&state.pool, assignee_id).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    {
+        RoutingHealth::Critical(score) => {
+            return Err((
+                StatusCode::FORBIDDEN,
+                format!("Agent {} health score {:.0} is below the routing threshold", assignee_id, score),
+            ));
+        }
+        RoutingHealth::Degraded(score) => Some(format!("Agent {} is degraded (health score {:.0})", assignee_id, score)),
+        RoutingHealth::Healthy | RoutingHealth::Unscored => None,
+    };
+
+    // The agent may have joined since the pool last loaded
+    if !state.agent_pool.has_capacity(assignee_id, id).await {
+        state.agent_pool.refresh(&state.pool).await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+    state.agent_pool.allocate_agent(assignee_id, id.clone()).await
+        .map_err(|e| (StatusCode::CONFLICT, e))?;
 
-    let output = Command::new("openclaw")
+    let spawned = Command::new("openclaw")
         .arg("sessions")
         .arg("spawn")
         .arg("--agent")
-        .arg(&assignee_id)
+        .arg(assignee_id)
         .arg("--label")
         .arg(format!("task:{}", id))
         .output()
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to execute openclaw: {}", e)))?;
-
-    if !output.status.success() {
-        let error = String::from_utf8_lossy(&output.stderr);
-        return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("OpenClaw error: {}", error)));
-    }
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to execute openclaw: {}", e)))
+        .and_then(|output| {
+            if output.status.success() {
+                Ok(output)
+            } else {
+                let error = String::from_utf8_lossy(&output.stderr);
+                Err((StatusCode::INTERNAL_SERVER_ERROR, format!("OpenClaw error: {}", error)))
+            }
+        });
+    let output = match spawned {
+        Ok(output) => output,
+        Err(e) => {
+            // Give the slot back unless the task holds it through its assignment
+            if let Err(sync_error) = state.agent_pool.sync_task(&state.pool, id).await {
+                tracing::warn!("Failed to resync agent pool for task {}: {}", id, sync_error);
+            }
+            return Err(e);
+        }
+    };
 
-    state.manager.broadcast(&format!(r#"{{"type": "task_routed", "task_id": "{}"}}"#, id));
+    state.manager.broadcast_classified(task.classification, &format!(r#"{{"type": "task_routed", "task_id": "{}"}}"#, id));
 
-    Ok(Json(serde_json::json!({
-        "status": "success",
-        "message": "Task routed to agent session",
-        "output": String::from_utf8_lossy(&output.stdout)
-    })))
+    Ok(RoutedSession {
+        output: String::from_utf8_lossy(&output.stdout).to_string(),
+        health_warning,
+    })
 }
 
 async fn list_recurring_tasks(
@@ -789,8 +1326,8 @@ async fn import_openclaw_agents(
                 let workspace = agent.get("workspace").and_then(|w| w.as_str());
                 let role = agent.get("role").and_then(|r| r.as_str()).unwrap_or("SPC");
                 
-                // Keep it simple: insert or update
-                sqlx::query("INSERT INTO agents (id, name, role, workspace, status, created_at) VALUES (?, ?, ?, ?, 'IDLE', CURRENT_TIMESTAMP) ON CONFLICT(id) DO UPDATE SET name = excluded.name, role = excluded.role, workspace = excluded.workspace, status = 'IDLE'")
+                // Keep it simple: insert or update; re-importing does not lift a suspension
+                sqlx::query("INSERT INTO agents (id, name, role, workspace, status, created_at) VALUES (?, ?, ?, ?, 'IDLE', CURRENT_TIMESTAMP) ON CONFLICT(id) DO UPDATE SET name = excluded.name, role = excluded.role, workspace = excluded.workspace, status = CASE WHEN agents.status = 'SUSPENDED' THEN 'SUSPENDED' ELSE 'IDLE' END")
                     .bind(id)
                     .bind(name)
                     .bind(role.to_uppercase())
@@ -846,12 +1383,101 @@ async fn update_agent_files() -> impl IntoResponse {
     StatusCode::OK
 }
 
-async fn review_task() -> impl IntoResponse {
-    StatusCode::OK
+/// Sets the status and stamps `completed_at` the first time a task reaches DONE
+async fn set_task_status(conn: &mut sqlx::SqliteConnection, id: &str, status: &str) -> Result<(), (StatusCode, String)> {
+    sqlx::query(
+        "UPDATE tasks SET status = ?1, updated_at = CURRENT_TIMESTAMP,
+             completed_at = CASE WHEN ?1 = 'DONE' THEN COALESCE(completed_at, CURRENT_TIMESTAMP) ELSE completed_at END
+         WHERE id = ?2"
+    )
+    .bind(status)
+    .bind(id)
+    .execute(conn)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(())
 }
 
-async fn complete_deliverable() -> impl IntoResponse {
-    StatusCode::OK
+/// Approves (DONE) or rejects (back to IN_PROGRESS) a task waiting in REVIEW
+async fn review_task(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    Clearance(clearance): Clearance,
+    headers: HeaderMap,
+    Json(payload): Json<serde_json::Value>,
+) -> Result<Json<Task>, (StatusCode, String)> {
+    let reviewer = crate::rbac::authorized_user(&state.pool, &headers, "tasks", "write").await?;
+    let task = fetch_visible_task(&state.pool, &id, clearance).await?;
+    if task.status != TaskStatus::Review {
+        return Err((StatusCode::CONFLICT, "Task is not in review".to_string()));
+    }
+
+    let (outcome, status) = match payload["outcome"].as_str() {
+        Some("approved") => ("approved", "DONE"),
+        Some("rejected") => ("rejected", "IN_PROGRESS"),
+        _ => return Err((StatusCode::BAD_REQUEST, "outcome must be approved or rejected".to_string())),
+    };
+    let feedback = payload["feedback"].as_str();
+
+    let mut tx = state.pool.begin().await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    set_task_status(&mut tx, &id, status).await?;
+    crate::agent_metrics::record_review(&mut tx, &id, Some(&reviewer.id), outcome, feedback).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    tx.commit().await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    state.agent_pool.sync_task(&state.pool, &id).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    crate::agent_metrics::learn_from_review(&state.pool, &id, outcome, feedback).await;
+
+    state.manager.broadcast_classified(task.classification, &format!(r#"{{"type": "status_changed", "task_id": "{}", "status": "{}"}}"#, id, status));
+
+    fetch_visible_task(&state.pool, &id, clearance).await.map(Json)
+}
+
+async fn complete_deliverable(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    Clearance(clearance): Clearance,
+) -> Result<Json<Deliverable>, (StatusCode, String)> {
+    let visible = classification::visible_levels_sql(clearance);
+    let (task_id, deliverable_classification): (String, Option<SecurityLevel>) = sqlx::query_as(&format!(
+        "SELECT task_id, classification FROM deliverables
+         WHERE id = ? AND (classification IS NULL OR classification IN ({visible}))"
+    ))
+    .bind(&id)
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Deliverable not found".to_string()))?;
+    let task = fetch_visible_task(&state.pool, &task_id, clearance).await
+        .map_err(|_| (StatusCode::NOT_FOUND, "Deliverable not found".to_string()))?;
+
+    let updated = sqlx::query(
+        "UPDATE deliverables SET status = 'COMPLETED', completed_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ? AND status IN ('PENDING', 'IN_PROGRESS')"
+    )
+    .bind(&id)
+    .execute(&state.pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if updated.rows_affected() == 0 {
+        return Err((StatusCode::CONFLICT, "Deliverable is already completed, failed or cancelled".to_string()));
+    }
+
+    let deliverable = sqlx::query_as::<sqlx::Sqlite, Deliverable>(
+        "SELECT id, task_id, title, description, status, classification, created_at FROM deliverables WHERE id = ?"
+    )
+    .bind(&id)
+    .fetch_one(&state.pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let effective = deliverable_classification.map_or(task.classification, |level| level.max(task.classification));
+    state.manager.broadcast_classified(effective, &format!(r#"{{"type": "deliverable_completed", "task_id": "{}"}}"#, task_id));
+
+    Ok(Json(deliverable))
 }
 
 async fn trigger_recurring_task() -> impl IntoResponse {
@@ -895,7 +1521,7 @@ async fn get_stuck_task_status(
 async fn run_stuck_task_check(
     State(state): State<AppState>,
 ) -> Json<serde_json::Value> {
-    match perform_stuck_task_check(&state.pool).await {
+    match perform_stuck_task_check(&state.pool, &crate::config::current().monitoring).await {
         Ok(count) => {
             let mut status = state.stuck_task_status.write().await;
             status.currently_tracked_tasks = count as u32;
@@ -907,27 +1533,30 @@ async fn run_stuck_task_check(
 }
 
 // OpenClaw Integration Endpoints (re-exported from module)
-use openclaw_monitoring::*;
-use openclaw_integration::*;
-use openclaw_optimization::*;
-use openclaw_advanced_features::*;
-use agent_management_impl::*;
 
 // Monitoring Helper Functions
 
-async fn check_gateway_connectivity() -> bool {
+async fn check_gateway_connectivity(timeout_seconds: u64) -> bool {
     let host = std::env::var("GATEWAY_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
     let addr = format!("{}:18789", host);
     tracing::debug!("Checking gateway connectivity to {}", addr);
-    tokio::net::TcpStream::connect(addr).await.is_ok()
+    let connect = tokio::net::TcpStream::connect(addr);
+    matches!(
+        tokio::time::timeout(tokio::time::Duration::from_secs(timeout_seconds), connect).await,
+        Ok(Ok(_))
+    )
 }
 
-async fn perform_stuck_task_check(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
-    // A task is "stuck" if it hasn't been updated for more than 2 hours (default)
-    // For demonstration, we'll just count tasks in INBOX or ASSIGNED that haven't been updated recently
+async fn perform_stuck_task_check(pool: &SqlitePool, limits: &MonitoringConfig) -> Result<i64, sqlx::Error> {
+    // A task is "stuck" if it has sat in INBOX or ASSIGNED without updates for longer
+    // than its priority allows
     let count = sqlx::query_scalar::<sqlx::Sqlite, i64>(
-        "SELECT COUNT(*) FROM tasks WHERE status IN ('INBOX', 'ASSIGNED') AND updated_at < datetime('now', '-2 hours')"
+        "SELECT COUNT(*) FROM tasks
+         WHERE status IN ('INBOX', 'ASSIGNED')
+           AND updated_at < datetime('now', '-' || CASE WHEN priority IN ('URGENT', 'CRITICAL') THEN ? ELSE ? END || ' minutes')"
     )
+    .bind(limits.urgent_priority_limit_minutes as i64)
+    .bind(limits.normal_priority_limit_minutes as i64)
     .fetch_one(pool)
     .await?;
     
@@ -0,0 +1,570 @@
+use crate::db::SqlitePool;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+use std::time::Instant;
+use tracing::{info, warn};
+
+/// A numbered schema change. `up` runs inside a transaction together with
+/// its `schema_migrations` row, so a failed migration leaves nothing behind.
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: &'static str,
+    pub down: &'static str,
+}
+
+impl Migration {
+    pub fn checksum(&self) -> String {
+        format!("{:x}", Sha256::digest(self.up.as_bytes()))
+    }
+}
+
+/// Every migration this build knows about, in version order
+pub static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "baseline",
+        up: include_str!("../migrations/0001_baseline.up.sql"),
+        down: include_str!("../migrations/0001_baseline.down.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "backup_records",
+        up: include_str!("../migrations/0002_backup_records.up.sql"),
+        down: include_str!("../migrations/0002_backup_records.down.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "configuration_history",
+        up: include_str!("../migrations/0003_configuration_history.up.sql"),
+        down: include_str!("../migrations/0003_configuration_history.down.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "agent_metrics_rollup",
+        up: include_str!("../migrations/0004_agent_metrics_rollup.up.sql"),
+        down: include_str!("../migrations/0004_agent_metrics_rollup.down.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "usage_ingestion",
+        up: include_str!("../migrations/0005_usage_ingestion.up.sql"),
+        down: include_str!("../migrations/0005_usage_ingestion.down.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "budget_enforcement",
+        up: include_str!("../migrations/0006_budget_enforcement.up.sql"),
+        down: include_str!("../migrations/0006_budget_enforcement.down.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "agent_health_scoring",
+        up: include_str!("../migrations/0007_agent_health_scoring.up.sql"),
+        down: include_str!("../migrations/0007_agent_health_scoring.down.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "model_failover",
+        up: include_str!("../migrations/0008_model_failover.up.sql"),
+        down: include_str!("../migrations/0008_model_failover.down.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "agent_teams",
+        up: include_str!("../migrations/0009_agent_teams.up.sql"),
+        down: include_str!("../migrations/0009_agent_teams.down.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "task_delegations",
+        up: include_str!("../migrations/0010_task_delegations.up.sql"),
+        down: include_str!("../migrations/0010_task_delegations.down.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "team_ballots",
+        up: include_str!("../migrations/0011_team_ballots.up.sql"),
+        down: include_str!("../migrations/0011_team_ballots.down.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "agent_learning",
+        up: include_str!("../migrations/0012_agent_learning.up.sql"),
+        down: include_str!("../migrations/0012_agent_learning.down.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "template_catalogue",
+        up: include_str!("../migrations/0013_template_catalogue.up.sql"),
+        down: include_str!("../migrations/0013_template_catalogue.down.sql"),
+    },
+    Migration {
+        version: 14,
+        name: "agent_bulk_jobs",
+        up: include_str!("../migrations/0014_agent_bulk_jobs.up.sql"),
+        down: include_str!("../migrations/0014_agent_bulk_jobs.down.sql"),
+    },
+    Migration {
+        version: 15,
+        name: "agent_config_versions",
+        up: include_str!("../migrations/0015_agent_config_versions.up.sql"),
+        down: include_str!("../migrations/0015_agent_config_versions.down.sql"),
+    },
+    Migration {
+        version: 16,
+        name: "audit_admin_permission",
+        up: include_str!("../migrations/0016_audit_admin_permission.up.sql"),
+        down: include_str!("../migrations/0016_audit_admin_permission.down.sql"),
+    },
+    Migration {
+        version: 17,
+        name: "config_change_sources",
+        up: include_str!("../migrations/0017_config_change_sources.up.sql"),
+        down: include_str!("../migrations/0017_config_change_sources.down.sql"),
+    },
+    Migration {
+        version: 18,
+        name: "user_security_events",
+        up: include_str!("../migrations/0018_user_security_events.up.sql"),
+        down: include_str!("../migrations/0018_user_security_events.down.sql"),
+    },
+    Migration {
+        version: 19,
+        name: "budget_suspensions",
+        up: include_str!("../migrations/0019_budget_suspensions.up.sql"),
+        down: include_str!("../migrations/0019_budget_suspensions.down.sql"),
+    },
+];
+
+/// Columns that databases created before versioned migrations may be missing.
+/// Only consulted when such a database is adopted onto the baseline.
+const LEGACY_COLUMNS: &[(&str, &str, &str)] = &[
+    ("users", "two_factor_last_step", "INTEGER"),
+    ("sessions", "requires_step_up", "BOOLEAN DEFAULT 0"),
+    ("sessions", "revoked_at", "DATETIME"),
+    ("sessions", "revoked_by", "TEXT"),
+    ("roles", "parent_role_id", "TEXT REFERENCES roles(id) ON DELETE SET NULL"),
+    ("tasks", "classification", "TEXT NOT NULL DEFAULT 'INTERNAL' CHECK(classification IN ('PUBLIC', 'INTERNAL', 'CONFIDENTIAL', 'RESTRICTED', 'SECRET'))"),
+    ("comments", "classification", "TEXT CHECK(classification IS NULL OR classification IN ('PUBLIC', 'INTERNAL', 'CONFIDENTIAL', 'RESTRICTED', 'SECRET'))"),
+    ("deliverables", "classification", "TEXT CHECK(classification IS NULL OR classification IN ('PUBLIC', 'INTERNAL', 'CONFIDENTIAL', 'RESTRICTED', 'SECRET'))"),
+];
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub name: String,
+    pub checksum: String,
+    pub applied_at: DateTime<Utc>,
+    pub execution_ms: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied, but the script in this build no longer matches what ran
+    Modified,
+    /// Applied by a newer build
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub name: String,
+    pub state: MigrationState,
+    pub applied_at: Option<DateTime<Utc>>,
+}
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+async fn ensure_migrations_table(pool: &SqlitePool) -> Result<(), anyhow::Error> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            checksum TEXT NOT NULL,
+            applied_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            execution_ms INTEGER NOT NULL DEFAULT 0
+        );
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn applied_migrations(pool: &SqlitePool) -> Result<Vec<AppliedMigration>, anyhow::Error> {
+    ensure_migrations_table(pool).await?;
+    let applied = sqlx::query_as::<sqlx::Sqlite, AppliedMigration>(
+        "SELECT version, name, checksum, applied_at, execution_ms FROM schema_migrations ORDER BY version"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(applied)
+}
+
+pub async fn status(pool: &SqlitePool) -> Result<Vec<MigrationStatus>, anyhow::Error> {
+    let applied = applied_migrations(pool).await?;
+
+    let mut statuses: Vec<MigrationStatus> = MIGRATIONS
+        .iter()
+        .map(|migration| {
+            let record = applied.iter().find(|a| a.version == migration.version);
+            let state = match record {
+                Some(record) if record.checksum != migration.checksum() => MigrationState::Modified,
+                Some(_) => MigrationState::Applied,
+                None => MigrationState::Pending,
+            };
+            MigrationStatus {
+                version: migration.version,
+                name: migration.name.to_string(),
+                state,
+                applied_at: record.map(|r| r.applied_at),
+            }
+        })
+        .collect();
+
+    statuses.extend(
+        applied
+            .iter()
+            .filter(|a| !MIGRATIONS.iter().any(|m| m.version == a.version))
+            .map(|a| MigrationStatus {
+                version: a.version,
+                name: a.name.clone(),
+                state: MigrationState::Unknown,
+                applied_at: Some(a.applied_at),
+            }),
+    );
+    statuses.sort_by_key(|s| s.version);
+
+    Ok(statuses)
+}
+
+/// Refuses a database written by a newer build or whose applied scripts were edited
+pub async fn verify(pool: &SqlitePool) -> Result<Vec<MigrationStatus>, anyhow::Error> {
+    let statuses = status(pool).await?;
+
+    if let Some(newest) = statuses.iter().filter(|s| s.state == MigrationState::Unknown).map(|s| s.version).max() {
+        return Err(anyhow::anyhow!(
+            "Database schema is at version {} but this build only knows up to {}; refusing to start against a newer schema",
+            newest,
+            latest_version()
+        ));
+    }
+
+    let modified: Vec<String> = statuses
+        .iter()
+        .filter(|s| s.state == MigrationState::Modified)
+        .map(|s| format!("{:04}_{}", s.version, s.name))
+        .collect();
+    if !modified.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Applied migrations were modified after they ran: {}",
+            modified.join(", ")
+        ));
+    }
+
+    Ok(statuses)
+}
+
+/// A database created by the old boot-time setup has tables but no migration history
+async fn is_legacy_database(pool: &SqlitePool) -> Result<bool, anyhow::Error> {
+    let has_agents: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'agents'"
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(has_agents > 0 && applied_migrations(pool).await?.is_empty())
+}
+
+async fn add_legacy_columns(conn: &mut sqlx::SqliteConnection) -> Result<(), anyhow::Error> {
+    for (table, column, definition) in LEGACY_COLUMNS {
+        // Empty when the table itself is missing; the baseline creates it whole
+        let columns: Vec<String> = sqlx::query_scalar(&format!(
+            "SELECT name FROM pragma_table_info('{}')",
+            table
+        ))
+        .fetch_all(&mut *conn)
+        .await?;
+
+        if !columns.is_empty() && !columns.iter().any(|name| name == column) {
+            info!("Adding missing column {}.{}", table, column);
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+                .execute(&mut *conn)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Applies pending migrations up to and including `target` (all of them when `None`).
+/// Returns the versions that were applied.
+pub async fn migrate_up(pool: &SqlitePool, target: Option<i64>) -> Result<Vec<i64>, anyhow::Error> {
+    let statuses = verify(pool).await?;
+    let legacy = is_legacy_database(pool).await?;
+    let target = target.unwrap_or_else(latest_version);
+
+    let pending: Vec<&Migration> = MIGRATIONS
+        .iter()
+        .filter(|m| m.version <= target)
+        .filter(|m| statuses.iter().any(|s| s.version == m.version && s.state == MigrationState::Pending))
+        .collect();
+
+    let mut applied = Vec::new();
+    for migration in pending {
+        let started = Instant::now();
+        let mut tx = pool.begin().await?;
+
+        // The baseline only creates what is missing, so older databases first need
+        // the columns added to existing tables since they were created
+        if legacy && migration.version == 1 {
+            warn!("Adopting a database created before versioned migrations");
+            add_legacy_columns(&mut tx).await?;
+        }
+
+        sqlx::raw_sql(migration.up).execute(&mut *tx).await
+            .map_err(|e| anyhow::anyhow!("Migration {:04}_{} failed: {}", migration.version, migration.name, e))?;
+
+        sqlx::query(
+            "INSERT INTO schema_migrations (version, name, checksum, applied_at, execution_ms)
+             VALUES (?, ?, ?, CURRENT_TIMESTAMP, ?)"
+        )
+        .bind(migration.version)
+        .bind(migration.name)
+        .bind(migration.checksum())
+        .bind(started.elapsed().as_millis() as i64)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        info!("Applied migration {:04}_{} in {:?}", migration.version, migration.name, started.elapsed());
+        applied.push(migration.version);
+    }
+
+    Ok(applied)
+}
+
+/// Reverts the `steps` most recently applied migrations. Returns the reverted versions.
+pub async fn migrate_down(pool: &SqlitePool, steps: usize) -> Result<Vec<i64>, anyhow::Error> {
+    verify(pool).await?;
+    let applied = applied_migrations(pool).await?;
+
+    let mut reverted = Vec::new();
+    for record in applied.iter().rev().take(steps) {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|m| m.version == record.version)
+            .ok_or_else(|| anyhow::anyhow!("No down script for migration {}", record.version))?;
+
+        let mut tx = pool.begin().await?;
+        sqlx::raw_sql(migration.down).execute(&mut *tx).await
+            .map_err(|e| anyhow::anyhow!("Reverting {:04}_{} failed: {}", migration.version, migration.name, e))?;
+        sqlx::query("DELETE FROM schema_migrations WHERE version = ?")
+            .bind(migration.version)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        info!("Reverted migration {:04}_{}", migration.version, migration.name);
+        reverted.push(migration.version);
+    }
+
+    Ok(reverted)
+}
+
+/// Boot-time check. Pending migrations are applied unless MIGRATE_ON_STARTUP=false,
+/// in which case startup stops until `migrate up` has been run.
+pub async fn run_startup(pool: &SqlitePool) -> Result<(), anyhow::Error> {
+    let statuses = verify(pool).await?;
+    let pending = statuses.iter().filter(|s| s.state == MigrationState::Pending).count();
+
+    if pending == 0 {
+        info!("Database schema is up to date at version {}", latest_version());
+        return Ok(());
+    }
+
+    let auto_migrate = std::env::var("MIGRATE_ON_STARTUP").map(|v| v != "false").unwrap_or(true);
+    if !auto_migrate {
+        return Err(anyhow::anyhow!(
+            "{} pending migrations and MIGRATE_ON_STARTUP=false; run `backend migrate up` first",
+            pending
+        ));
+    }
+
+    migrate_up(pool, None).await?;
+    Ok(())
+}
+
+/// Entry point for `backend migrate status|up [version]|down [steps]`
+pub async fn run_cli(pool: &SqlitePool, args: &[String]) -> Result<(), anyhow::Error> {
+    match args.first().map(String::as_str) {
+        Some("status") | None => {
+            for s in status(pool).await? {
+                println!(
+                    "{:04}  {:<24} {:<9} {}",
+                    s.version,
+                    s.name,
+                    format!("{:?}", s.state).to_lowercase(),
+                    s.applied_at.map(|t| t.to_rfc3339()).unwrap_or_default()
+                );
+            }
+        }
+        Some("up") => {
+            let target = args.get(1).map(|v| v.parse::<i64>()).transpose()?;
+            let applied = migrate_up(pool, target).await?;
+            println!("Applied {} migration(s)", applied.len());
+        }
+        Some("down") => {
+            let steps = args.get(1).map(|v| v.parse::<usize>()).transpose()?.unwrap_or(1);
+            let reverted = migrate_down(pool, steps).await?;
+            println!("Reverted {} migration(s)", reverted.len());
+        }
+        Some(other) => {
+            return Err(anyhow::anyhow!("Unknown migrate command '{}'; expected status, up or down", other));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{SqliteConnectOptions, SqlitePoolOptions};
+    use std::str::FromStr;
+
+    /// A private in-memory database; one connection so every query sees the same one
+    async fn memory_pool() -> SqlitePool {
+        let options = SqliteConnectOptions::from_str("sqlite::memory:").unwrap().foreign_keys(true);
+        SqlitePoolOptions::new().max_connections(1).connect_with(options).await.unwrap()
+    }
+
+    /// Every table, index and trigger with its sorted column names
+    async fn schema(pool: &SqlitePool) -> Vec<(String, String, Vec<String>)> {
+        let objects: Vec<(String, String)> = sqlx::query_as(
+            "SELECT type, name FROM sqlite_master
+             WHERE name NOT LIKE 'sqlite_%' AND name != 'schema_migrations' ORDER BY type, name"
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap();
+
+        let mut schema = Vec::new();
+        for (kind, name) in objects {
+            let mut columns: Vec<String> = if kind == "table" {
+                sqlx::query_scalar("SELECT name FROM pragma_table_info(?)").bind(&name).fetch_all(pool).await.unwrap()
+            } else {
+                Vec::new()
+            };
+            columns.sort();
+            schema.push((kind, name, columns));
+        }
+        schema
+    }
+
+    #[tokio::test]
+    async fn every_migration_reverts_to_the_schema_before_it() {
+        let pool = memory_pool().await;
+
+        for migration in MIGRATIONS {
+            let before = schema(&pool).await;
+            assert_eq!(migrate_up(&pool, Some(migration.version)).await.unwrap(), [migration.version]);
+
+            assert_eq!(migrate_down(&pool, 1).await.unwrap(), [migration.version]);
+            assert_eq!(schema(&pool).await, before, "{:04}_{} did not revert cleanly", migration.version, migration.name);
+
+            migrate_up(&pool, Some(migration.version)).await.unwrap();
+        }
+        assert!(status(&pool).await.unwrap().iter().all(|s| s.state == MigrationState::Applied));
+
+        let reverted = migrate_down(&pool, MIGRATIONS.len()).await.unwrap();
+        assert_eq!(reverted, MIGRATIONS.iter().rev().map(|m| m.version).collect::<Vec<_>>());
+        assert!(schema(&pool).await.is_empty());
+        assert_eq!(migrate_up(&pool, None).await.unwrap().len(), MIGRATIONS.len());
+    }
+
+    #[tokio::test]
+    async fn edited_migrations_are_refused() {
+        let pool = memory_pool().await;
+        migrate_up(&pool, None).await.unwrap();
+        sqlx::query("UPDATE schema_migrations SET checksum = 'edited' WHERE version = 1")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(status(&pool).await.unwrap()[0].state, MigrationState::Modified);
+        let error = verify(&pool).await.unwrap_err().to_string();
+        assert!(error.contains("0001_baseline"), "{}", error);
+        assert!(migrate_up(&pool, None).await.is_err());
+        assert!(migrate_down(&pool, 1).await.is_err());
+        assert!(run_startup(&pool).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn newer_schemas_are_refused() {
+        let pool = memory_pool().await;
+        migrate_up(&pool, None).await.unwrap();
+        sqlx::query("INSERT INTO schema_migrations (version, name, checksum) VALUES (?, 'from_the_future', 'unknown')")
+            .bind(latest_version() + 1)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let error = verify(&pool).await.unwrap_err().to_string();
+        assert!(error.contains("newer schema"), "{}", error);
+        assert!(migrate_down(&pool, 1).await.is_err());
+        assert!(run_startup(&pool).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn legacy_databases_are_adopted_with_their_data() {
+        let pool = memory_pool().await;
+        // Built by the old boot-time setup, before these columns existed
+        sqlx::raw_sql(MIGRATIONS[0].up).execute(&pool).await.unwrap();
+        sqlx::raw_sql(
+            "DROP TRIGGER tasks_audit_insert;
+             DROP INDEX idx_tasks_classification;
+             ALTER TABLE tasks DROP COLUMN classification;
+             ALTER TABLE users DROP COLUMN two_factor_last_step;
+             ALTER TABLE sessions DROP COLUMN revoked_at;"
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO agents (id, name, role) VALUES ('legacy-agent', 'Legacy Agent', 'SPC')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(is_legacy_database(&pool).await.unwrap());
+
+        assert_eq!(migrate_up(&pool, None).await.unwrap().len(), MIGRATIONS.len());
+
+        assert!(!is_legacy_database(&pool).await.unwrap());
+        for (table, column, _) in LEGACY_COLUMNS {
+            let present: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ?")
+                .bind(table)
+                .bind(column)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            assert_eq!(present, 1, "{}.{} was not added", table, column);
+        }
+        let name: String = sqlx::query_scalar("SELECT name FROM agents WHERE id = 'legacy-agent'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(name, "Legacy Agent");
+    }
+}
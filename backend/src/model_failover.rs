@@ -0,0 +1,707 @@
+use crate::db::SqlitePool;
+use crate::agent_config_history::{ChangeSource, ConfigChange, ConfigHistoryService, ConfigKind, HistoryError};
+use crate::models::AgentModelConfig;
+use crate::agent_metrics::MetricsPeriod;
+use crate::openclaw_integration_helpers::write_agent_model_to_openclaw;
+use crate::openclaw_optimization::HierarchicalCache;
+use crate::ConnectionManager;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use tracing::{info, warn};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+    response::IntoResponse,
+    http::{HeaderMap, StatusCode},
+};
+use crate::AppState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelEventType {
+    Error,
+    Success,
+    Switch,
+}
+
+impl ModelEventType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ModelEventType::Error => "error",
+            ModelEventType::Success => "success",
+            ModelEventType::Switch => "switch",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ModelEventError {
+    /// A reported switch named a model outside the agent's chain
+    NotInChain(String),
+    Internal(anyhow::Error),
+}
+
+impl std::fmt::Display for ModelEventError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ModelEventError::NotInChain(model) => write!(f, "Model '{}' is not in the agent's model chain", model),
+            ModelEventError::Internal(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ModelEventError {
+    fn from(error: anyhow::Error) -> Self {
+        ModelEventError::Internal(error)
+    }
+}
+
+impl From<sqlx::Error> for ModelEventError {
+    fn from(error: sqlx::Error) -> Self {
+        ModelEventError::Internal(error.into())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModelEventReport {
+    pub event: ModelEventType,
+    /// Defaults to the model the agent is on
+    pub model: Option<String>,
+    pub error_code: Option<String>,
+    pub message: Option<String>,
+    /// Model the reporter moved the agent to, for `switch` reports
+    pub to_model: Option<String>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct ModelEvent {
+    pub id: String,
+    pub agent_id: String,
+    pub model: String,
+    pub event_type: String,
+    pub error_code: Option<String>,
+    pub message: Option<String>,
+    pub from_model: Option<String>,
+    pub to_model: Option<String>,
+    pub source: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModelSwitch {
+    pub from_model: Option<String>,
+    pub to_model: String,
+    pub reason: String,
+    /// Whether openclaw.json now points the agent at `to_model`
+    pub config_written: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModelEventOutcome {
+    pub agent_id: String,
+    pub current_model: Option<String>,
+    pub failure_streak: i64,
+    pub switch: Option<ModelSwitch>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AgentModelState {
+    pub agent_id: String,
+    /// Primary first, then fallbacks in the order they are tried
+    pub model_chain: Vec<String>,
+    pub current_model: Option<String>,
+    pub on_fallback: bool,
+    pub failure_streak: i64,
+    pub recent_events: Vec<ModelEvent>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct ModelErrorRate {
+    pub model: String,
+    pub errors: i64,
+    pub successes: i64,
+    pub error_rate: f64,
+    /// Times an agent was switched off this model
+    pub switches_away: i64,
+    pub last_error_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModelErrorRateQuery {
+    pub agent_id: Option<String>,
+    pub period: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+#[derive(Debug, FromRow)]
+struct AgentModels {
+    id: String,
+    primary_model: Option<String>,
+    fallback_model: Option<String>,
+    current_model: Option<String>,
+    model_failure_count: i64,
+}
+
+fn push_model(chain: &mut Vec<String>, model: &str) {
+    let model = model.trim();
+    if !model.is_empty() && !chain.iter().any(|m| m == model) {
+        chain.push(model.to_string());
+    }
+}
+
+/// The model after `failing` in the chain, or the first other model if
+/// `failing` is not part of it
+fn next_model(chain: &[String], failing: &str) -> Option<String> {
+    match chain.iter().position(|m| m == failing) {
+        Some(position) => chain.get(position + 1).cloned(),
+        None => chain.iter().find(|m| m.as_str() != failing).cloned(),
+    }
+}
+
+pub struct ModelFailoverService;
+
+impl ModelFailoverService {
+    async fn agent_models(&self, pool: &SqlitePool, agent_id: &str) -> Result<Option<AgentModels>, sqlx::Error> {
+        sqlx::query_as::<sqlx::Sqlite, AgentModels>(
+            "SELECT id, primary_model, fallback_model, current_model, COALESCE(model_failure_count, 0) AS model_failure_count
+             FROM agents WHERE id = ?"
+        )
+        .bind(agent_id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Primary then fallbacks. The primary ClawController stores wins over openclaw.json,
+    /// whose `model.primary` points at the fallback while one is in use.
+    async fn model_chain(&self, agent: &AgentModels) -> Vec<String> {
+        let openclaw_model = crate::openclaw_integration::read_and_parse_openclaw_config()
+            .await
+            .ok()
+            .and_then(|configs| configs.into_iter().find(|c| c.id == agent.id))
+            .and_then(|config| config.model);
+
+        let mut chain = Vec::new();
+        if let Some(primary) = &agent.primary_model {
+            push_model(&mut chain, primary);
+        }
+        if let Some(model) = &openclaw_model {
+            for m in model.primary.iter().chain(model.fallbacks.iter().flatten()) {
+                push_model(&mut chain, m);
+            }
+        }
+        // Stored either as one model or as a JSON array of them
+        if let Some(fallback) = &agent.fallback_model {
+            match serde_json::from_str::<Vec<String>>(fallback) {
+                Ok(models) => models.iter().for_each(|m| push_model(&mut chain, m)),
+                Err(_) => push_model(&mut chain, fallback),
+            }
+        }
+        chain
+    }
+
+    async fn insert_event(
+        &self,
+        pool: &SqlitePool,
+        agent_id: &str,
+        model: &str,
+        event_type: ModelEventType,
+        report: Option<&ModelEventReport>,
+        switch: Option<(Option<&str>, &str)>,
+        source: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO agent_model_events
+             (id, agent_id, model, event_type, error_code, message, from_model, to_model, source)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(agent_id)
+        .bind(model)
+        .bind(event_type.as_str())
+        .bind(report.and_then(|r| r.error_code.as_deref()))
+        .bind(report.and_then(|r| r.message.as_deref()))
+        .bind(switch.and_then(|(from, _)| from))
+        .bind(switch.map(|(_, to)| to))
+        .bind(source)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Moves the agent onto `to`, records and broadcasts the switch and, unless the
+    /// reporter already did it, points openclaw.json at the new model
+    async fn switch_model(
+        &self,
+        pool: &SqlitePool,
+        manager: &ConnectionManager,
+        cache: &HierarchicalCache,
+        agent_id: &str,
+        from: Option<&str>,
+        to: &str,
+        chain: &[String],
+        reason: &str,
+        source: &str,
+    ) -> Result<ModelSwitch, anyhow::Error> {
+        // An agent whose primary was only known from openclaw.json keeps the model it
+        // is leaving as its primary, since openclaw.json is about to name the fallback
+        sqlx::query(
+            "UPDATE agents SET current_model = ?, primary_model = COALESCE(primary_model, ?),
+                model_failure_count = 0, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?"
+        )
+        .bind(to)
+        .bind(from)
+        .bind(agent_id)
+        .execute(pool)
+        .await?;
+
+        // Switch events are filed under the model being left
+        let report = ModelEventReport {
+            event: ModelEventType::Switch,
+            model: None,
+            error_code: None,
+            message: Some(reason.to_string()),
+            to_model: None,
+        };
+        self.insert_event(pool, agent_id, from.unwrap_or(to), ModelEventType::Switch, Some(&report), Some((from, to)), source).await?;
+
+        let config_written = if source == "system" {
+            let fallbacks: Vec<String> = chain.iter().filter(|m| m.as_str() != to).cloned().collect();
+            match write_agent_model_to_openclaw(agent_id, to, &fallbacks).await {
+                Ok(written) => {
+                    cache.invalidate_agent(agent_id).await;
+                    if written
+                        && let Err(e) = record_model_change(pool, agent_id, to, &fallbacks, reason).await {
+                            warn!("Could not record model switch for {} in its configuration history: {}", agent_id, e);
+                        }
+                    written
+                }
+                Err(e) => {
+                    warn!("Could not write model switch for {} to openclaw.json: {}", agent_id, e);
+                    false
+                }
+            }
+        } else {
+            false
+        };
+
+        manager.broadcast(&serde_json::json!({
+            "type": "agent_model_switched",
+            "agent_id": agent_id,
+            "from_model": from,
+            "to_model": to,
+            "reason": reason,
+        }).to_string());
+        info!("Agent {} switched from {:?} to {} ({})", agent_id, from, to, reason);
+
+        Ok(ModelSwitch {
+            from_model: from.map(str::to_string),
+            to_model: to.to_string(),
+            reason: reason.to_string(),
+            config_written,
+        })
+    }
+
+    /// Records a reported model error, success or switch. A streak of errors on the
+    /// current model fails over to the next model in the chain; a success on the
+    /// primary while on a fallback returns the agent to it. Reported switches must
+    /// name a model in the agent's chain.
+    pub async fn record(
+        &self,
+        pool: &SqlitePool,
+        manager: &ConnectionManager,
+        cache: &HierarchicalCache,
+        agent_id: &str,
+        report: &ModelEventReport,
+        source: &str,
+    ) -> Result<Option<ModelEventOutcome>, ModelEventError> {
+        let Some(agent) = self.agent_models(pool, agent_id).await? else {
+            return Ok(None);
+        };
+        let chain = self.model_chain(&agent).await;
+        let current = agent.current_model.clone().or_else(|| chain.first().cloned());
+        let model = report
+            .model
+            .clone()
+            .or_else(|| current.clone())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        let mut streak = agent.model_failure_count;
+        let mut switch = None;
+        match report.event {
+            ModelEventType::Error => {
+                self.insert_event(pool, agent_id, &model, report.event, Some(report), None, source).await?;
+                if current.as_deref() == Some(model.as_str()) {
+                    // Incremented in place so concurrent reports each count. Read to the end,
+                    // since the write only commits once the statement has finished.
+                    let counts: Vec<i64> = sqlx::query_scalar(
+                        "UPDATE agents SET model_failure_count = MIN(COALESCE(model_failure_count, 0) + 1, 100)
+                         WHERE id = ? RETURNING model_failure_count"
+                    )
+                    .bind(agent_id)
+                    .fetch_all(pool)
+                    .await?;
+                    streak = counts.first().copied().unwrap_or(streak);
+
+                    if streak >= crate::config::current().model_failover_threshold as i64 {
+                        match next_model(&chain, &model) {
+                            Some(next) => {
+                                switch = Some(
+                                    self.switch_model(pool, manager, cache, agent_id, Some(&model), &next, &chain, "failure_streak", "system")
+                                        .await?,
+                                );
+                                streak = 0;
+                            }
+                            None => warn!("Agent {} has no fallback model left after {} failed", agent_id, model),
+                        }
+                    }
+                }
+            }
+            ModelEventType::Success => {
+                self.insert_event(pool, agent_id, &model, report.event, Some(report), None, source).await?;
+                if current.as_deref() == Some(model.as_str()) && streak > 0 {
+                    streak = 0;
+                    sqlx::query("UPDATE agents SET model_failure_count = 0 WHERE id = ?")
+                        .bind(agent_id)
+                        .execute(pool)
+                        .await?;
+                }
+                if chain.first() == Some(&model) && current.as_deref() != Some(model.as_str()) {
+                    switch = Some(
+                        self.switch_model(pool, manager, cache, agent_id, current.as_deref(), &model, &chain, "primary_recovered", "system")
+                            .await?,
+                    );
+                    streak = 0;
+                }
+            }
+            ModelEventType::Switch => {
+                let to = report.to_model.as_deref().map(str::trim).unwrap_or(&model);
+                if !chain.iter().any(|m| m == to) {
+                    return Err(ModelEventError::NotInChain(to.to_string()));
+                }
+                switch = Some(
+                    self.switch_model(pool, manager, cache, agent_id, current.as_deref(), to, &chain, "reported", source).await?,
+                );
+                streak = 0;
+            }
+        }
+
+        Ok(Some(ModelEventOutcome {
+            agent_id: agent_id.to_string(),
+            current_model: switch.as_ref().map(|s| s.to_model.clone()).or(current),
+            failure_streak: streak,
+            switch,
+        }))
+    }
+
+    /// Moves agents that have been on a fallback for longer than the retry interval
+    /// back to their primary. Errors there start a new streak and fail over again.
+    pub async fn retry_primaries(&self, pool: &SqlitePool, manager: &ConnectionManager, cache: &HierarchicalCache) -> Result<u64, anyhow::Error> {
+        let minutes = crate::config::current().model_primary_retry_minutes;
+        if minutes == 0 {
+            return Ok(0);
+        }
+
+        let agent_ids: Vec<String> = sqlx::query_scalar(
+            "SELECT a.id FROM agents a
+             WHERE a.primary_model IS NOT NULL AND a.current_model IS NOT NULL
+               AND a.current_model != a.primary_model
+               AND NOT EXISTS (
+                   SELECT 1 FROM agent_model_events e
+                   WHERE e.agent_id = a.id AND e.event_type = 'switch'
+                     AND e.created_at > datetime('now', '-' || ? || ' minutes')
+               )"
+        )
+        .bind(minutes as i64)
+        .fetch_all(pool)
+        .await?;
+
+        let mut retried = 0;
+        for agent_id in agent_ids {
+            let Some(agent) = self.agent_models(pool, &agent_id).await? else {
+                continue;
+            };
+            let chain = self.model_chain(&agent).await;
+            if let (Some(primary), Some(current)) = (chain.first(), agent.current_model.as_deref()) {
+                self.switch_model(pool, manager, cache, &agent_id, Some(current), primary, &chain, "primary_retry", "system").await?;
+                retried += 1;
+            }
+        }
+        Ok(retried)
+    }
+
+    pub async fn state(&self, pool: &SqlitePool, agent_id: &str) -> Result<Option<AgentModelState>, anyhow::Error> {
+        let Some(agent) = self.agent_models(pool, agent_id).await? else {
+            return Ok(None);
+        };
+        let model_chain = self.model_chain(&agent).await;
+        let current_model = agent.current_model.clone().or_else(|| model_chain.first().cloned());
+        let recent_events = sqlx::query_as::<sqlx::Sqlite, ModelEvent>(
+            "SELECT id, agent_id, model, event_type, error_code, message, from_model, to_model, source, created_at
+             FROM agent_model_events WHERE agent_id = ? ORDER BY created_at DESC, rowid DESC LIMIT 50"
+        )
+        .bind(agent_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(Some(AgentModelState {
+            agent_id: agent.id,
+            on_fallback: current_model.is_some() && current_model.as_ref() != model_chain.first(),
+            current_model,
+            model_chain,
+            failure_streak: agent.model_failure_count,
+            recent_events,
+        }))
+    }
+
+    pub async fn error_rates(
+        &self,
+        pool: &SqlitePool,
+        agent_id: Option<&str>,
+        period: &MetricsPeriod,
+    ) -> Result<Vec<ModelErrorRate>, sqlx::Error> {
+        sqlx::query_as::<sqlx::Sqlite, ModelErrorRate>(
+            "SELECT model,
+                    SUM(CASE WHEN event_type = 'error' THEN 1 ELSE 0 END) AS errors,
+                    SUM(CASE WHEN event_type = 'success' THEN 1 ELSE 0 END) AS successes,
+                    COALESCE(
+                        CAST(SUM(CASE WHEN event_type = 'error' THEN 1 ELSE 0 END) AS REAL)
+                            / NULLIF(SUM(CASE WHEN event_type IN ('error', 'success') THEN 1 ELSE 0 END), 0),
+                        0.0
+                    ) AS error_rate,
+                    SUM(CASE WHEN event_type = 'switch' AND from_model IS NOT NULL THEN 1 ELSE 0 END) AS switches_away,
+                    MAX(CASE WHEN event_type = 'error' THEN created_at END) AS last_error_at
+             FROM agent_model_events
+             WHERE date(created_at) BETWEEN ?1 AND ?2 AND (?3 IS NULL OR agent_id = ?3)
+             GROUP BY model
+             ORDER BY error_rate DESC, errors DESC"
+        )
+        .bind(period.from.to_string())
+        .bind(period.to.to_string())
+        .bind(agent_id)
+        .fetch_all(pool)
+        .await
+    }
+}
+
+/// Records the model openclaw.json was switched to as the agent's newest OpenClaw
+/// configuration, so the history shows the failover and can roll it back
+async fn record_model_change(
+    pool: &SqlitePool,
+    agent_id: &str,
+    primary: &str,
+    fallbacks: &[String],
+    reason: &str,
+) -> Result<(), HistoryError> {
+    let mut config = ConfigHistoryService.current_openclaw_config(pool, agent_id).await?;
+    config.model = Some(AgentModelConfig {
+        primary: Some(primary.to_string()),
+        fallbacks: Some(fallbacks.to_vec()),
+    });
+    let change = ConfigChange::new(ChangeSource::Failover, None, Some(reason.to_string()));
+    let mut conn = pool.acquire().await?;
+    ConfigHistoryService
+        .record(&mut conn, agent_id, ConfigKind::OpenClaw, &serde_json::to_string(&config)?, &change)
+        .await?;
+    Ok(())
+}
+
+/// Agents report on themselves with their `x-agent-key`; the gateway reports with
+/// a user token that may write agents
+async fn reporter_source(pool: &SqlitePool, headers: &HeaderMap, agent_id: &str) -> Result<&'static str, (StatusCode, String)> {
+    let Some(agent_key) = headers.get("x-agent-key").and_then(|v| v.to_str().ok()) else {
+        crate::rbac::authorized_user(pool, headers, "agents", "write").await?;
+        return Ok("gateway");
+    };
+
+    let reporter: Option<String> = sqlx::query_scalar("SELECT id FROM agents WHERE token = ? AND is_deleted = 0")
+        .bind(agent_key)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    match reporter {
+        Some(id) if id == agent_id => Ok("agent"),
+        Some(_) => Err((StatusCode::FORBIDDEN, "Agents may only report their own model events".to_string())),
+        None => Err((StatusCode::UNAUTHORIZED, "Unknown agent key".to_string())),
+    }
+}
+
+// Axum Handlers
+pub async fn report_model_event(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(agent_id): Path<String>,
+    Json(payload): Json<ModelEventReport>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let source = reporter_source(&state.pool, &headers, &agent_id).await?;
+    if payload.event == ModelEventType::Switch && payload.to_model.as_deref().is_none_or(|m| m.trim().is_empty()) {
+        return Err((StatusCode::BAD_REQUEST, "to_model is required for switch reports".to_string()));
+    }
+
+    match ModelFailoverService.record(&state.pool, &state.manager, &state.cache, &agent_id, &payload, source).await {
+        Ok(Some(outcome)) => Ok(Json(outcome)),
+        Ok(None) => Err((StatusCode::NOT_FOUND, "Agent not found".to_string())),
+        Err(e @ ModelEventError::NotInChain(_)) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+pub async fn get_agent_model_state(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(agent_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
+    match ModelFailoverService.state(&state.pool, &agent_id).await {
+        Ok(Some(model_state)) => Ok(Json(model_state)),
+        Ok(None) => Err((StatusCode::NOT_FOUND, "Agent not found".to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+pub async fn get_model_error_rates(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ModelErrorRateQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
+    let period = MetricsPeriod::parse(
+        query.period.as_deref().unwrap_or("7d"),
+        query.from.as_deref(),
+        query.to.as_deref(),
+    )
+    .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    ModelFailoverService.error_rates(&state.pool, query.agent_id.as_deref(), &period).await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::common::{create_test_pool, create_test_state, insert_test_agent};
+
+    fn chain(models: &[&str]) -> Vec<String> {
+        models.iter().map(|m| m.to_string()).collect()
+    }
+
+    fn report(event: ModelEventType, model: Option<&str>, to_model: Option<&str>) -> ModelEventReport {
+        ModelEventReport {
+            event,
+            model: model.map(str::to_string),
+            error_code: None,
+            message: None,
+            to_model: to_model.map(str::to_string),
+        }
+    }
+
+    /// An agent on `claude-3-sonnet` with `gpt-4o` as its fallback
+    async fn agent_with_fallback(pool: &SqlitePool) -> String {
+        let agent_id = insert_test_agent(pool, "Failover Agent").await;
+        sqlx::query("UPDATE agents SET fallback_model = '[\"gpt-4o\"]' WHERE id = ?")
+            .bind(&agent_id)
+            .execute(pool)
+            .await
+            .unwrap();
+        agent_id
+    }
+
+    async fn stored_models(pool: &SqlitePool, agent_id: &str) -> (Option<String>, i64) {
+        sqlx::query_as("SELECT current_model, model_failure_count FROM agents WHERE id = ?")
+            .bind(agent_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn chains_skip_blank_and_repeated_models() {
+        let mut models = Vec::new();
+        for model in ["claude-3-sonnet", " gpt-4o ", "", "claude-3-sonnet", "gpt-4o-mini"] {
+            push_model(&mut models, model);
+        }
+        assert_eq!(models, chain(&["claude-3-sonnet", "gpt-4o", "gpt-4o-mini"]));
+    }
+
+    #[test]
+    fn next_model_walks_down_the_chain() {
+        let models = chain(&["primary", "first-fallback", "last-fallback"]);
+
+        assert_eq!(next_model(&models, "primary").as_deref(), Some("first-fallback"));
+        assert_eq!(next_model(&models, "first-fallback").as_deref(), Some("last-fallback"));
+        assert_eq!(next_model(&models, "last-fallback"), None);
+        assert_eq!(next_model(&models, "retired").as_deref(), Some("primary"));
+        assert_eq!(next_model(&chain(&["only"]), "only"), None);
+    }
+
+    #[tokio::test]
+    async fn a_failure_streak_fails_over_and_a_primary_success_returns() {
+        let state = create_test_state(create_test_pool().await);
+        let agent_id = agent_with_fallback(&state.pool).await;
+        let threshold = crate::config::current().model_failover_threshold as i64;
+        let error = report(ModelEventType::Error, None, None);
+
+        for failures in 1..threshold {
+            let outcome = ModelFailoverService.record(&state.pool, &state.manager, &state.cache, &agent_id, &error, "agent")
+                .await.unwrap().unwrap();
+            assert!(outcome.switch.is_none());
+            assert_eq!(outcome.failure_streak, failures);
+        }
+        // Errors on a model the agent is not using do not add to the streak
+        let other = report(ModelEventType::Error, Some("gpt-4o"), None);
+        ModelFailoverService.record(&state.pool, &state.manager, &state.cache, &agent_id, &other, "agent").await.unwrap();
+        assert_eq!(stored_models(&state.pool, &agent_id).await.1, threshold - 1);
+
+        let outcome = ModelFailoverService.record(&state.pool, &state.manager, &state.cache, &agent_id, &error, "agent")
+            .await.unwrap().unwrap();
+        let switch = outcome.switch.expect("the streak switches models");
+        assert_eq!((switch.from_model.as_deref(), switch.to_model.as_str()), (Some("claude-3-sonnet"), "gpt-4o"));
+        assert_eq!(switch.reason, "failure_streak");
+        assert_eq!(outcome.failure_streak, 0);
+        assert_eq!(stored_models(&state.pool, &agent_id).await, (Some("gpt-4o".to_string()), 0));
+
+        let success = report(ModelEventType::Success, Some("claude-3-sonnet"), None);
+        let outcome = ModelFailoverService.record(&state.pool, &state.manager, &state.cache, &agent_id, &success, "agent")
+            .await.unwrap().unwrap();
+        let switch = outcome.switch.expect("a primary success switches back");
+        assert_eq!((switch.from_model.as_deref(), switch.to_model.as_str()), (Some("gpt-4o"), "claude-3-sonnet"));
+        assert_eq!(switch.reason, "primary_recovered");
+        assert_eq!(stored_models(&state.pool, &agent_id).await, (Some("claude-3-sonnet".to_string()), 0));
+    }
+
+    #[tokio::test]
+    async fn concurrent_errors_all_count_towards_the_streak() {
+        let state = create_test_state(create_test_pool().await);
+        let agent_id = agent_with_fallback(&state.pool).await;
+        let error = report(ModelEventType::Error, None, None);
+
+        let record = || ModelFailoverService.record(&state.pool, &state.manager, &state.cache, &agent_id, &error, "agent");
+        let (first, second) = tokio::join!(record(), record());
+        first.unwrap();
+        second.unwrap();
+
+        let streak = stored_models(&state.pool, &agent_id).await.1;
+        let threshold = crate::config::current().model_failover_threshold as i64;
+        assert!(streak == 2 || (threshold <= 2 && streak == 0), "streak was {}", streak);
+    }
+
+    #[tokio::test]
+    async fn reported_switches_must_stay_in_the_chain() {
+        let state = create_test_state(create_test_pool().await);
+        let agent_id = agent_with_fallback(&state.pool).await;
+
+        let unknown = report(ModelEventType::Switch, None, Some("made-up-model"));
+        let result = ModelFailoverService.record(&state.pool, &state.manager, &state.cache, &agent_id, &unknown, "gateway").await;
+        assert!(matches!(result, Err(ModelEventError::NotInChain(model)) if model == "made-up-model"));
+        assert_eq!(stored_models(&state.pool, &agent_id).await.0, None);
+
+        let fallback = report(ModelEventType::Switch, None, Some("gpt-4o"));
+        let outcome = ModelFailoverService.record(&state.pool, &state.manager, &state.cache, &agent_id, &fallback, "gateway")
+            .await.unwrap().unwrap();
+        assert_eq!(outcome.switch.unwrap().reason, "reported");
+        assert_eq!(outcome.current_model.as_deref(), Some("gpt-4o"));
+    }
+}
@@ -1,8 +1,6 @@
 use serde::{Deserialize, Serialize};
 use sqlx::{Type, FromRow};
 use chrono::{DateTime, Utc};
-use uuid::Uuid;
-use std::collections::HashMap;
 use validator::{Validate, ValidationError as ValidatorError};
 
 #[derive(Debug, Serialize, Deserialize, Type, PartialEq, Eq, Clone, Copy)]
@@ -57,6 +55,7 @@ pub enum AgentStatus {
 }
 
 #[derive(Debug, Serialize, Deserialize, Type, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[sqlx(type_name = "TEXT", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum SecurityLevel {
     Public,
     Internal,
@@ -66,6 +65,7 @@ pub enum SecurityLevel {
 }
 
 #[derive(Debug, Serialize, Deserialize, Type, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[sqlx(type_name = "TEXT", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum AccessLevel {
     ReadOnly,
     ReadWrite,
@@ -202,6 +202,7 @@ pub struct Task {
     pub actual_hours: Option<f64>,
     pub complexity_score: Option<i32>, // 1-10
     pub risk_level: Option<String>,
+    pub classification: SecurityLevel,
     pub dependencies: Option<String>, // JSON array
     pub deliverables: Option<String>, // JSON array
     pub metadata: Option<String>, // JSON object
@@ -224,6 +225,7 @@ pub struct Comment {
     pub mentions: Option<String>, // JSON array
     pub attachments: Option<String>, // JSON array
     pub reaction_count: i32,
+    pub classification: Option<SecurityLevel>, // None inherits the task's classification
     pub is_deleted: bool,
     pub deleted_at: Option<DateTime<Utc>>,
     pub deleted_by: Option<String>,
@@ -309,11 +311,11 @@ pub struct Session {
     pub device_fingerprint: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Permission {
     pub id: String,
     pub name: String,
-    pub description: String,
+    pub description: Option<String>,
     pub resource: String,
     pub action: String,
     pub conditions: Option<String>, // JSON object
@@ -321,24 +323,25 @@ pub struct Permission {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Role {
     pub id: String,
     pub name: String,
-    pub description: String,
+    pub description: Option<String>,
     pub permissions: Option<String>, // JSON array
+    pub parent_role_id: Option<String>,
     pub is_system: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
 pub struct SystemConfiguration {
     pub id: String,
     pub key: String,
     pub value: String,
     pub data_type: String, // 'string', 'number', 'boolean', 'json'
-    pub description: String,
+    pub description: Option<String>,
     pub category: String,
     pub is_sensitive: bool,
     pub requires_restart: bool,
@@ -349,17 +352,32 @@ pub struct SystemConfiguration {
     pub version: i32,
 }
 
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct ConfigurationChange {
+    pub id: String,
+    pub config_key: String,
+    pub version: i32,
+    pub old_value: Option<String>,
+    pub new_value: String,
+    pub changed_by: String,
+    pub reason: Option<String>,
+    pub changed_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct BackupRecord {
     pub id: String,
     pub backup_type: String, // 'full', 'incremental', 'differential'
     pub location: String,
-    pub size_bytes: i64,
-    pub status: String, // 'in_progress', 'completed', 'failed'
+    pub size_bytes: Option<i64>,
+    pub status: String, // 'in_progress', 'completed', 'failed', 'cancelled', 'expired'
     pub started_at: DateTime<Utc>,
     pub completed_at: Option<DateTime<Utc>>,
     pub error_message: Option<String>,
-    pub checksum: Option<String>,
+    pub checksum: Option<String>, // SHA-256 hex
+    pub compressed: bool,
+    pub verified_at: Option<DateTime<Utc>>,
+    pub expired_at: Option<DateTime<Utc>>,
     pub retention_days: i32,
     pub created_by: String,
 }
@@ -739,6 +757,7 @@ pub struct Deliverable {
     pub description: Option<String>,
     pub file_path: Option<String>,
     pub status: String,
+    pub classification: Option<SecurityLevel>, // None inherits the task's classification
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -751,32 +770,36 @@ pub struct TaskTag {
 }
 
 // Validation functions
-fn validate_agent_id(id: &str) -> Result<(), ClawValidationError> {
+fn invalid_format(message: &'static str) -> ValidatorError {
+    ValidatorError::new("invalid_format").with_message(message.into())
+}
+
+fn validate_agent_id(id: &str) -> Result<(), ValidatorError> {
     if !id.starts_with("agent_") {
-        return Err(ClawValidationError::InvalidFormat("Agent ID must start with 'agent_'".to_string()));
+        return Err(invalid_format("Agent ID must start with 'agent_'"));
     }
     if id.len() < 10 || id.len() > 100 {
-        return Err(ClawValidationError::InvalidFormat("Agent ID must be between 10 and 100 characters".to_string()));
+        return Err(invalid_format("Agent ID must be between 10 and 100 characters"));
     }
     Ok(())
 }
 
-fn validate_task_id(id: &str) -> Result<(), ClawValidationError> {
+fn validate_task_id(id: &str) -> Result<(), ValidatorError> {
     if !id.starts_with("task_") {
-        return Err(ClawValidationError::InvalidFormat("Task ID must start with 'task_'".to_string()));
+        return Err(invalid_format("Task ID must start with 'task_'"));
     }
     if id.len() < 10 || id.len() > 100 {
-        return Err(ClawValidationError::InvalidFormat("Task ID must be between 10 and 100 characters".to_string()));
+        return Err(invalid_format("Task ID must be between 10 and 100 characters"));
     }
     Ok(())
 }
 
-fn validate_token_format(token: &str) -> Result<(), ClawValidationError> {
+fn validate_token_format(token: &str) -> Result<(), ValidatorError> {
     if token.len() < 32 {
-        return Err(ClawValidationError::InvalidFormat("Token must be at least 32 characters".to_string()));
+        return Err(invalid_format("Token must be at least 32 characters"));
     }
     if !token.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
-        return Err(ClawValidationError::InvalidFormat("Token contains invalid characters".to_string()));
+        return Err(invalid_format("Token contains invalid characters"));
     }
     Ok(())
 }
@@ -1,7 +1,7 @@
 use crate::models::*;
 use crate::openclaw_optimization::HealthChecker;
 use crate::db::SqlitePool;
-use axum::{extract::{State, Path}, Json, response::IntoResponse, http::StatusCode};
+use axum::{extract::{State, Path}, Json, response::IntoResponse, http::{HeaderMap, StatusCode}};
 use chrono::Utc;
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
@@ -9,7 +9,7 @@ use serde_json::Value;
 use tokio::sync::RwLock;
 use std::sync::Arc;
 use uuid::Uuid;
-use tracing::{info, warn, error, instrument};
+use tracing::{info, warn, instrument};
 use std::time::Duration;
 
 // Multi-Agent Collaboration System
@@ -20,31 +20,36 @@ pub struct AgentCollaboration {
     pub communication_protocols: Vec<CommunicationProtocol>,
     pub task_delegation_engine: TaskDelegationEngine,
     pub conflict_resolver: ConflictResolver,
-    pub collaboration_metrics: CollaborationMetrics,
+    pub collaboration_metrics: Arc<RwLock<CollaborationMetrics>>,
 }
 
+/// A team as loaded from `agent_teams` and its member, context and access tables
 #[derive(Clone)]
 pub struct AgentTeam {
     pub id: String,
     pub name: String,
+    pub team_type: Option<String>,
     pub members: Vec<Agent>,
     pub roles: HashMap<String, TeamRole>,
     pub communication_channels: Vec<CommunicationChannel>,
     pub shared_context: SharedContext,
+    pub created_by: Option<String>,
     pub created_at: chrono::DateTime<Utc>,
     pub active_tasks: Vec<String>,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TeamRole {
     pub agent_id: String,
     pub role_type: RoleType,
     pub responsibilities: Vec<String>,
+    /// Shared-context permissions (`read`, `write`, `delete`); empty means the role default
     pub permissions: Vec<String>,
     pub reporting_to: Option<String>,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum RoleType {
     Leader,
     Specialist,
@@ -54,6 +59,40 @@ pub enum RoleType {
     Observer,
 }
 
+impl RoleType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoleType::Leader => "leader",
+            RoleType::Specialist => "specialist",
+            RoleType::Coordinator => "coordinator",
+            RoleType::Reviewer => "reviewer",
+            RoleType::Executor => "executor",
+            RoleType::Observer => "observer",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "leader" => Some(RoleType::Leader),
+            "specialist" => Some(RoleType::Specialist),
+            "coordinator" => Some(RoleType::Coordinator),
+            "reviewer" => Some(RoleType::Reviewer),
+            "executor" => Some(RoleType::Executor),
+            "observer" => Some(RoleType::Observer),
+            _ => None,
+        }
+    }
+
+    /// Shared-context permissions of a member whose role lists none
+    fn default_context_permissions(&self) -> &'static [&'static str] {
+        match self {
+            RoleType::Leader | RoleType::Coordinator => &["read", "write", "delete"],
+            RoleType::Observer => &["read"],
+            _ => &["read", "write"],
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct CommunicationChannel {
     pub id: String,
@@ -100,20 +139,125 @@ pub struct EscalationRule {
 pub struct SharedContext {
     pub id: String,
     pub data: HashMap<String, Value>,
+    /// Version of each key in `data`, bumped on every write to it
+    pub key_versions: HashMap<String, u32>,
     pub access_controls: HashMap<String, AccessControl>,
+    /// Bumped on every write to any key
     pub version: u32,
     pub last_modified: chrono::DateTime<Utc>,
     pub modified_by: String,
 }
 
-#[derive(Clone)]
+/// Replaces a member's role permissions on the shared context until it expires
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AccessControl {
     pub agent_id: String,
     pub permissions: Vec<String>,
+    /// Keys the agent may not touch at all; a trailing `*` matches a prefix
     pub restrictions: Vec<String>,
     pub expiry_time: Option<chrono::DateTime<Utc>>,
 }
 
+impl AccessControl {
+    fn is_active(&self, now: chrono::DateTime<Utc>) -> bool {
+        self.expiry_time.is_none_or(|expiry| expiry > now)
+    }
+
+    fn restricts(&self, key: &str) -> bool {
+        self.restrictions.iter().any(|restriction| match restriction.strip_suffix('*') {
+            Some(prefix) => key.starts_with(prefix),
+            None => restriction == key,
+        })
+    }
+}
+
+impl AgentTeam {
+    /// Checks a member agent's `action` on the shared context, or on `key` when given.
+    /// An unexpired access control replaces the member's role permissions.
+    pub fn check_context_access(&self, agent_id: &str, key: Option<&str>, action: &str) -> Result<(), CollaborationError> {
+        let role = self.roles.get(agent_id).ok_or_else(|| {
+            CollaborationError::AccessDenied(format!("{} is not a member of team '{}'", agent_id, self.name))
+        })?;
+
+        let allowed = match self.shared_context.access_controls.get(agent_id).filter(|access| access.is_active(Utc::now())) {
+            Some(access) => {
+                if let Some(key) = key
+                    && access.restricts(key) {
+                        return Err(CollaborationError::AccessDenied(format!("Key '{}' is restricted for {}", key, agent_id)));
+                    }
+                access.permissions.iter().any(|p| p == action)
+            }
+            None if !role.permissions.is_empty() => role.permissions.iter().any(|p| p == action),
+            None => role.role_type.default_context_permissions().contains(&action),
+        };
+
+        if allowed {
+            Ok(())
+        } else {
+            Err(CollaborationError::AccessDenied(format!("{} may not {} the shared context", agent_id, action)))
+        }
+    }
+
+    /// Whether `key` is hidden from the agent by an unexpired restriction
+    fn hides_key(&self, agent_id: &str, key: &str) -> bool {
+        self.shared_context
+            .access_controls
+            .get(agent_id)
+            .filter(|access| access.is_active(Utc::now()))
+            .is_some_and(|access| access.restricts(key))
+    }
+}
+
+#[derive(Debug)]
+pub enum CollaborationError {
+    TeamNotFound(String),
+    BallotNotFound(String),
+    NotAMember(String),
+    AccessDenied(String),
+    Conflict(String),
+    VersionConflict { key: String, current: u32 },
+    Invalid(String),
+    Database(String),
+}
+
+impl std::fmt::Display for CollaborationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CollaborationError::TeamNotFound(id) => write!(f, "Team '{}' not found", id),
+            CollaborationError::BallotNotFound(id) => write!(f, "Ballot '{}' not found", id),
+            CollaborationError::NotAMember(id) => write!(f, "Agent '{}' is not a member of this team", id),
+            CollaborationError::AccessDenied(msg) => write!(f, "{}", msg),
+            CollaborationError::Conflict(msg) => write!(f, "{}", msg),
+            CollaborationError::VersionConflict { key, current } => {
+                write!(f, "Key '{}' is at version {}", key, current)
+            }
+            CollaborationError::Invalid(msg) => write!(f, "{}", msg),
+            CollaborationError::Database(msg) => write!(f, "Database error: {}", msg),
+        }
+    }
+}
+
+impl From<sqlx::Error> for CollaborationError {
+    fn from(error: sqlx::Error) -> Self {
+        CollaborationError::Database(error.to_string())
+    }
+}
+
+impl From<CollaborationError> for (StatusCode, String) {
+    fn from(error: CollaborationError) -> Self {
+        let status = match &error {
+            CollaborationError::TeamNotFound(_)
+            | CollaborationError::BallotNotFound(_)
+            | CollaborationError::NotAMember(_) => StatusCode::NOT_FOUND,
+            CollaborationError::AccessDenied(_) => StatusCode::FORBIDDEN,
+            CollaborationError::Conflict(_) | CollaborationError::VersionConflict { .. } => StatusCode::CONFLICT,
+            CollaborationError::Invalid(_) => StatusCode::BAD_REQUEST,
+            CollaborationError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, error.to_string())
+    }
+}
+
 #[derive(Clone)]
 pub struct CommunicationProtocol {
     pub id: String,
@@ -143,6 +287,7 @@ pub enum MessageFormat {
 }
 
 #[derive(Clone)]
+#[allow(clippy::enum_variant_names)]
 pub enum DeliveryGuarantee {
     AtMostOnce,
     AtLeastOnce,
@@ -185,7 +330,8 @@ pub struct DelegationLoadBalancer {
     pub health_checker: HealthChecker,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum DelegationAlgorithm {
     RoundRobin,
     WeightedRandom,
@@ -194,6 +340,77 @@ pub enum DelegationAlgorithm {
     CostOptimized,
 }
 
+impl DelegationAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DelegationAlgorithm::RoundRobin => "round_robin",
+            DelegationAlgorithm::WeightedRandom => "weighted_random",
+            DelegationAlgorithm::CapabilityBased => "capability_based",
+            DelegationAlgorithm::PerformanceBased => "performance_based",
+            DelegationAlgorithm::CostOptimized => "cost_optimized",
+        }
+    }
+}
+
+/// Whether a delegated task gets one owner or is split into a sub-task per pick
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DelegationMode {
+    Single,
+    Fanout,
+}
+
+impl DelegationMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DelegationMode::Single => "single",
+            DelegationMode::Fanout => "fanout",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DelegationCandidate {
+    pub agent_id: String,
+    pub name: String,
+    pub skill_match: f64,
+    pub completed_tasks: i64,
+    pub performance: f64,
+    pub estimated_cost: f64,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExcludedMember {
+    pub agent_id: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DelegationAssignment {
+    pub task_id: String,
+    /// Set for sub-tasks created by a fan-out
+    pub subtask_title: Option<String>,
+    pub agent_id: String,
+    pub agent_name: String,
+    pub score: f64,
+    pub routed: bool,
+    pub routing_error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaskDelegation {
+    pub id: String,
+    pub task_id: String,
+    pub team_id: String,
+    pub mode: DelegationMode,
+    pub algorithm: DelegationAlgorithm,
+    pub rationale: String,
+    pub assignments: Vec<DelegationAssignment>,
+    pub ranking: Vec<DelegationCandidate>,
+    pub excluded: Vec<ExcludedMember>,
+}
+
 #[derive(Clone)]
 pub struct ConflictResolver {
     pub resolution_strategies: Vec<ResolutionStrategy>,
@@ -210,7 +427,8 @@ pub struct ResolutionStrategy {
 
 
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ConflictType {
     ResourceAllocation,
     TaskOwnership,
@@ -219,6 +437,18 @@ pub enum ConflictType {
     AccessControl,
 }
 
+impl ConflictType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConflictType::ResourceAllocation => "resource_allocation",
+            ConflictType::TaskOwnership => "task_ownership",
+            ConflictType::DecisionMaking => "decision_making",
+            ConflictType::PriorityConflict => "priority_conflict",
+            ConflictType::AccessControl => "access_control",
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct ResolutionProcess {
     pub steps: Vec<ResolutionStep>,
@@ -274,7 +504,8 @@ pub struct VotingMechanism {
     pub tie_breaker: TieBreaker,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum VotingType {
     SimpleMajority,
     QualifiedMajority,
@@ -283,13 +514,49 @@ pub enum VotingType {
     DelegatedVoting,
 }
 
+impl VotingType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VotingType::SimpleMajority => "simple_majority",
+            VotingType::QualifiedMajority => "qualified_majority",
+            VotingType::Consensus => "consensus",
+            VotingType::WeightedVoting => "weighted_voting",
+            VotingType::DelegatedVoting => "delegated_voting",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "simple_majority" => Some(VotingType::SimpleMajority),
+            "qualified_majority" => Some(VotingType::QualifiedMajority),
+            "consensus" => Some(VotingType::Consensus),
+            "weighted_voting" => Some(VotingType::WeightedVoting),
+            "delegated_voting" => Some(VotingType::DelegatedVoting),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub enum TieBreaker {
     Random,
     Seniority,
     Performance,
     Cost,
-    Custom(Box<dyn Fn() -> String>),
+    /// Returns the winning option; anything other than a tied option escalates
+    Custom(Arc<dyn Fn() -> String + Send + Sync>),
+}
+
+impl TieBreaker {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TieBreaker::Random => "random",
+            TieBreaker::Seniority => "seniority",
+            TieBreaker::Performance => "performance",
+            TieBreaker::Cost => "cost",
+            TieBreaker::Custom(_) => "custom",
+        }
+    }
 }
 
 
@@ -328,7 +595,7 @@ impl AgentCollaboration {
                     tie_breaker: TieBreaker::Performance,
                 },
             },
-            collaboration_metrics: CollaborationMetrics {
+            collaboration_metrics: Arc::new(RwLock::new(CollaborationMetrics {
                 total_teams: 0,
                 active_collaborations: 0,
                 messages_exchanged: 0,
@@ -336,176 +603,906 @@ impl AgentCollaboration {
                 conflicts_resolved: 0,
                 average_response_time: Duration::from_secs(0),
                 collaboration_efficiency: 0.0,
-            },
+            })),
         }
     }
 
-    #[instrument(skip(self))]
-    pub async fn create_team(&self, team_name: String, members: Vec<Agent>) -> Result<String, String> {
-        let team_id = Uuid::new_v4().to_string();
-        
+    /// Builds the collaboration state with every team persisted in the database
+    pub async fn load(pool: &SqlitePool) -> Result<Self, sqlx::Error> {
+        let collaboration = Self::new();
+        let team_ids: Vec<String> = sqlx::query_scalar("SELECT id FROM agent_teams")
+            .fetch_all(pool)
+            .await?;
+        for team_id in team_ids {
+            collaboration.refresh_team(pool, &team_id).await?;
+        }
+        {
+            let mut metrics = collaboration.collaboration_metrics.write().await;
+            metrics.tasks_delegated = sqlx::query_scalar::<sqlx::Sqlite, i64>("SELECT COUNT(*) FROM task_delegation_assignments")
+                .fetch_one(pool)
+                .await? as u64;
+            metrics.conflicts_resolved = sqlx::query_scalar::<sqlx::Sqlite, i64>("SELECT COUNT(*) FROM team_ballots WHERE status = 'resolved'")
+                .fetch_one(pool)
+                .await? as u64;
+        }
+        info!("Loaded {} collaboration teams", collaboration.agent_teams.read().await.len());
+        Ok(collaboration)
+    }
+
+    /// Reloads one team from the database into `agent_teams`, dropping it if it is gone
+    async fn refresh_team(&self, pool: &SqlitePool, team_id: &str) -> Result<Option<AgentTeam>, sqlx::Error> {
+        let row = sqlx::query_as::<sqlx::Sqlite, TeamRow>(
+            "SELECT id, name, team_type, context_version, created_by, created_at FROM agent_teams WHERE id = ?"
+        )
+        .bind(team_id)
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(row) = row else {
+            self.agent_teams.write().await.remove(team_id);
+            self.sync_team_counts().await;
+            return Ok(None);
+        };
+
+        let members = sqlx::query_as::<sqlx::Sqlite, Agent>(
+            "SELECT a.* FROM agents a JOIN agent_team_members m ON m.agent_id = a.id
+             WHERE m.team_id = ? ORDER BY m.joined_at, a.id"
+        )
+        .bind(team_id)
+        .fetch_all(pool)
+        .await?;
+
+        let roles = sqlx::query_as::<sqlx::Sqlite, TeamMemberRow>(
+            "SELECT agent_id, role_type, responsibilities, permissions, reporting_to
+             FROM agent_team_members WHERE team_id = ?"
+        )
+        .bind(team_id)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|member| (member.agent_id.clone(), member.into_role()))
+        .collect();
+
+        let entries = sqlx::query_as::<sqlx::Sqlite, ContextEntryRow>(
+            "SELECT key, value, version, modified_by, modified_at FROM team_shared_context WHERE team_id = ?"
+        )
+        .bind(team_id)
+        .fetch_all(pool)
+        .await?;
+
+        let access_controls = sqlx::query_as::<sqlx::Sqlite, AccessControlRow>(
+            "SELECT agent_id, permissions, restrictions, expiry_time FROM team_context_access WHERE team_id = ?"
+        )
+        .bind(team_id)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|access| (access.agent_id.clone(), access.into_access_control()))
+        .collect();
+
+        let active_tasks: Vec<String> = sqlx::query_scalar(
+            "SELECT DISTINCT a.task_id FROM task_delegation_assignments a
+             JOIN task_delegations d ON d.id = a.delegation_id
+             JOIN tasks t ON t.id = a.task_id
+             WHERE d.team_id = ? AND t.status NOT IN ('DONE', 'CANCELLED', 'ARCHIVED')"
+        )
+        .bind(team_id)
+        .fetch_all(pool)
+        .await?;
+
+        let latest = entries.iter().max_by_key(|entry| entry.modified_at);
+        let shared_context = SharedContext {
+            id: row.id.clone(),
+            last_modified: latest.map_or(row.created_at, |entry| entry.modified_at),
+            modified_by: latest.map_or_else(|| "system".to_string(), |entry| entry.modified_by.clone()),
+            data: entries
+                .iter()
+                .map(|entry| (entry.key.clone(), serde_json::from_str(&entry.value).unwrap_or(Value::Null)))
+                .collect(),
+            key_versions: entries.iter().map(|entry| (entry.key.clone(), entry.version as u32)).collect(),
+            access_controls,
+            version: row.context_version as u32,
+        };
+
         let team = AgentTeam {
-            id: team_id.clone(),
-            name: team_name,
-            members: members.clone(),
-            roles: HashMap::new(),
+            id: row.id,
+            name: row.name,
+            team_type: row.team_type,
+            members,
+            roles,
             communication_channels: Vec::new(),
-            shared_context: SharedContext {
-                id: Uuid::new_v4().to_string(),
-                data: HashMap::new(),
-                access_controls: HashMap::new(),
-                version: 1,
-                last_modified: Utc::now(),
-                modified_by: "system".to_string(),
+            shared_context,
+            created_by: row.created_by,
+            created_at: row.created_at,
+            active_tasks,
+        };
+
+        self.agent_teams.write().await.insert(team.id.clone(), team.clone());
+        self.sync_team_counts().await;
+        Ok(Some(team))
+    }
+
+    async fn sync_team_counts(&self) {
+        let teams = self.agent_teams.read().await;
+        let mut metrics = self.collaboration_metrics.write().await;
+        metrics.total_teams = teams.len();
+        metrics.active_collaborations = teams.values().filter(|team| team.members.len() > 1).count();
+    }
+
+    pub async fn team(&self, team_id: &str) -> Result<AgentTeam, CollaborationError> {
+        self.agent_teams
+            .read()
+            .await
+            .get(team_id)
+            .cloned()
+            .ok_or_else(|| CollaborationError::TeamNotFound(team_id.to_string()))
+    }
+
+    pub async fn teams(&self) -> Vec<AgentTeam> {
+        let mut teams: Vec<AgentTeam> = self.agent_teams.read().await.values().cloned().collect();
+        teams.sort_by(|a, b| a.name.cmp(&b.name));
+        teams
+    }
+
+    #[instrument(skip(self, pool, request))]
+    pub async fn create_team(&self, pool: &SqlitePool, request: &CreateTeamRequest, created_by: &str) -> Result<AgentTeam, CollaborationError> {
+        let name = request.name.trim();
+        if name.is_empty() {
+            return Err(CollaborationError::Invalid("Team name is required".to_string()));
+        }
+        let existing: Option<String> = sqlx::query_scalar("SELECT id FROM agent_teams WHERE name = ?")
+            .bind(name)
+            .fetch_optional(pool)
+            .await?;
+        if existing.is_some() {
+            return Err(CollaborationError::Conflict(format!("A team named '{}' already exists", name)));
+        }
+
+        // Bare member_ids join as executors
+        let mut members: Vec<TeamMemberRequest> = request.members.clone();
+        for agent_id in &request.member_ids {
+            if !members.iter().any(|m| &m.agent_id == agent_id) {
+                members.push(TeamMemberRequest::executor(agent_id));
+            }
+        }
+        let member_ids: Vec<&str> = members.iter().map(|m| m.agent_id.as_str()).collect();
+        for member in &members {
+            member.validate(pool, &member_ids).await?;
+        }
+
+        let team_id = Uuid::new_v4().to_string();
+        let mut tx = pool.begin().await?;
+        sqlx::query("INSERT INTO agent_teams (id, name, team_type, created_by) VALUES (?, ?, ?, ?)")
+            .bind(&team_id)
+            .bind(name)
+            .bind(&request.team_type)
+            .bind(created_by)
+            .execute(&mut *tx)
+            .await?;
+        for member in &members {
+            member.insert(&mut tx, &team_id).await?;
+        }
+        tx.commit().await?;
+
+        let team = self.refresh_team(pool, &team_id).await?.ok_or_else(|| CollaborationError::TeamNotFound(team_id.clone()))?;
+        info!("Created team '{}' with {} members", team.name, team.members.len());
+        Ok(team)
+    }
+
+    pub async fn update_team(&self, pool: &SqlitePool, team_id: &str, request: &UpdateTeamRequest) -> Result<AgentTeam, CollaborationError> {
+        let team = self.team(team_id).await?;
+        let name = request.name.as_deref().map(str::trim).unwrap_or(&team.name);
+        if name.is_empty() {
+            return Err(CollaborationError::Invalid("Team name is required".to_string()));
+        }
+        let clash: Option<String> = sqlx::query_scalar("SELECT id FROM agent_teams WHERE name = ? AND id != ?")
+            .bind(name)
+            .bind(team_id)
+            .fetch_optional(pool)
+            .await?;
+        if clash.is_some() {
+            return Err(CollaborationError::Conflict(format!("A team named '{}' already exists", name)));
+        }
+
+        sqlx::query("UPDATE agent_teams SET name = ?, team_type = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(name)
+            .bind(request.team_type.as_ref().or(team.team_type.as_ref()))
+            .bind(team_id)
+            .execute(pool)
+            .await?;
+        self.refresh_team(pool, team_id).await?.ok_or_else(|| CollaborationError::TeamNotFound(team_id.to_string()))
+    }
+
+    /// Deletes the team with its memberships and shared context
+    pub async fn delete_team(&self, pool: &SqlitePool, team_id: &str) -> Result<(), CollaborationError> {
+        let result = sqlx::query("DELETE FROM agent_teams WHERE id = ?")
+            .bind(team_id)
+            .execute(pool)
+            .await?;
+        self.refresh_team(pool, team_id).await?;
+        if result.rows_affected() == 0 {
+            return Err(CollaborationError::TeamNotFound(team_id.to_string()));
+        }
+        Ok(())
+    }
+
+    pub async fn add_member(&self, pool: &SqlitePool, team_id: &str, member: &TeamMemberRequest) -> Result<AgentTeam, CollaborationError> {
+        let team = self.team(team_id).await?;
+        if team.roles.contains_key(&member.agent_id) {
+            return Err(CollaborationError::Conflict(format!("Agent '{}' is already a member", member.agent_id)));
+        }
+        let member_ids: Vec<&str> = team.roles.keys().map(String::as_str).collect();
+        member.validate(pool, &member_ids).await?;
+
+        let mut tx = pool.begin().await?;
+        member.insert(&mut tx, team_id).await?;
+        tx.commit().await?;
+        self.refresh_team(pool, team_id).await?.ok_or_else(|| CollaborationError::TeamNotFound(team_id.to_string()))
+    }
+
+    pub async fn update_member(
+        &self,
+        pool: &SqlitePool,
+        team_id: &str,
+        agent_id: &str,
+        request: &UpdateTeamMemberRequest,
+    ) -> Result<AgentTeam, CollaborationError> {
+        let team = self.team(team_id).await?;
+        let role = team.roles.get(agent_id).ok_or_else(|| CollaborationError::NotAMember(agent_id.to_string()))?;
+
+        let updated = TeamMemberRequest {
+            agent_id: agent_id.to_string(),
+            role_type: request.role_type.or(Some(role.role_type)),
+            responsibilities: request.responsibilities.clone().or_else(|| Some(role.responsibilities.clone())),
+            permissions: request.permissions.clone().or_else(|| Some(role.permissions.clone())),
+            reporting_to: match &request.reporting_to {
+                Some(reporting_to) if reporting_to.is_empty() => None,
+                Some(reporting_to) => Some(reporting_to.clone()),
+                None => role.reporting_to.clone(),
             },
-            created_at: Utc::now(),
-            active_tasks: Vec::new(),
         };
-        
-        {
-            let mut teams = self.agent_teams.write().await;
-            teams.insert(team_id.clone(), team);
+        let member_ids: Vec<&str> = team.roles.keys().map(String::as_str).collect();
+        updated.validate(pool, &member_ids).await?;
+
+        sqlx::query(
+            "UPDATE agent_team_members SET role_type = ?, responsibilities = ?, permissions = ?, reporting_to = ?
+             WHERE team_id = ? AND agent_id = ?"
+        )
+        .bind(updated.role().role_type.as_str())
+        .bind(serde_json::to_string(&updated.role().responsibilities).unwrap_or_else(|_| "[]".to_string()))
+        .bind(serde_json::to_string(&updated.role().permissions).unwrap_or_else(|_| "[]".to_string()))
+        .bind(&updated.reporting_to)
+        .bind(team_id)
+        .bind(agent_id)
+        .execute(pool)
+        .await?;
+        self.refresh_team(pool, team_id).await?.ok_or_else(|| CollaborationError::TeamNotFound(team_id.to_string()))
+    }
+
+    /// Removes the member and its access control; members reporting to it report to no one
+    pub async fn remove_member(&self, pool: &SqlitePool, team_id: &str, agent_id: &str) -> Result<AgentTeam, CollaborationError> {
+        let team = self.team(team_id).await?;
+        if !team.roles.contains_key(agent_id) {
+            return Err(CollaborationError::NotAMember(agent_id.to_string()));
         }
-        
-        self.collaboration_metrics.total_teams += 1;
-        self.collaboration_metrics.active_collaborations += 1;
-        
-        info!("Created team '{}' with {} members", team_name, members.len());
-        Ok(team_id)
+
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM agent_team_members WHERE team_id = ? AND agent_id = ?")
+            .bind(team_id)
+            .bind(agent_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE agent_team_members SET reporting_to = NULL WHERE team_id = ? AND reporting_to = ?")
+            .bind(team_id)
+            .bind(agent_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        self.refresh_team(pool, team_id).await?.ok_or_else(|| CollaborationError::TeamNotFound(team_id.to_string()))
     }
 
-    #[instrument(skip(self, team_id, task))]
-    pub async fn delegate_task_to_team(&self, team_id: &str, task: &Task) -> Result<Vec<String>, String> {
-        let teams = self.agent_teams.read().await;
-        
-        let team = teams.get(team_id)
-            .ok_or_else(|| format!("Team '{}' not found", team_id))?;
-        
-        // Find best agents for the task
-        let suitable_agents = self.find_suitable_agents_for_task(&team.members, task).await?;
-        
-        if suitable_agents.is_empty() {
-            return Err("No suitable agents found for task".to_string());
+    pub async fn set_access_control(
+        &self,
+        pool: &SqlitePool,
+        team_id: &str,
+        agent_id: &str,
+        request: &AccessControlRequest,
+    ) -> Result<AgentTeam, CollaborationError> {
+        let team = self.team(team_id).await?;
+        if !team.roles.contains_key(agent_id) {
+            return Err(CollaborationError::NotAMember(agent_id.to_string()));
         }
-        
-        // Delegate to selected agents
-        let assigned_agents = self.task_delegation_engine.delegate_task(task, &suitable_agents).await?;
-        
-        self.collaboration_metrics.tasks_delegated += 1;
-        
-        info!("Delegated task '{}' to {} agents", task.id, assigned_agents.len());
-        Ok(assigned_agents)
+        validate_context_permissions(&request.permissions)?;
+
+        sqlx::query(
+            "INSERT INTO team_context_access (team_id, agent_id, permissions, restrictions, expiry_time)
+             VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT(team_id, agent_id) DO UPDATE SET
+                permissions = excluded.permissions,
+                restrictions = excluded.restrictions,
+                expiry_time = excluded.expiry_time"
+        )
+        .bind(team_id)
+        .bind(agent_id)
+        .bind(serde_json::to_string(&request.permissions).unwrap_or_else(|_| "[]".to_string()))
+        .bind(serde_json::to_string(&request.restrictions).unwrap_or_else(|_| "[]".to_string()))
+        .bind(request.expiry_time)
+        .execute(pool)
+        .await?;
+        self.refresh_team(pool, team_id).await?.ok_or_else(|| CollaborationError::TeamNotFound(team_id.to_string()))
     }
 
-    #[instrument(skip(self))]
-    async fn find_suitable_agents_for_task(&self, agents: &[Agent], task: &Task) -> Result<Vec<Agent>, String> {
-        let mut suitable_agents = Vec::new();
-        
-        for agent in agents {
-            if self.is_agent_suitable_for_task(agent, task).await? {
-                suitable_agents.push(agent.clone());
+    pub async fn remove_access_control(&self, pool: &SqlitePool, team_id: &str, agent_id: &str) -> Result<AgentTeam, CollaborationError> {
+        self.team(team_id).await?;
+        sqlx::query("DELETE FROM team_context_access WHERE team_id = ? AND agent_id = ?")
+            .bind(team_id)
+            .bind(agent_id)
+            .execute(pool)
+            .await?;
+        self.refresh_team(pool, team_id).await?.ok_or_else(|| CollaborationError::TeamNotFound(team_id.to_string()))
+    }
+
+    /// Writes `value` under `key`, or deletes the key when `value` is `None`. With
+    /// `expected_version` the write only applies if the key is still at that version
+    /// (0 for a key that does not exist yet).
+    pub async fn write_context(
+        &self,
+        pool: &SqlitePool,
+        team_id: &str,
+        key: &str,
+        value: Option<&Value>,
+        expected_version: Option<u32>,
+        modified_by: &str,
+    ) -> Result<ContextEntry, CollaborationError> {
+        self.team(team_id).await?;
+        if key.trim().is_empty() || key.len() > 255 {
+            return Err(CollaborationError::Invalid("Context keys must be 1-255 characters".to_string()));
+        }
+
+        let mut tx = pool.begin().await?;
+        let current: Option<i64> = sqlx::query_scalar("SELECT version FROM team_shared_context WHERE team_id = ? AND key = ?")
+            .bind(team_id)
+            .bind(key)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let current = current.unwrap_or(0) as u32;
+        if expected_version.is_some_and(|expected| expected != current) {
+            return Err(CollaborationError::VersionConflict { key: key.to_string(), current });
+        }
+        if value.is_none() && current == 0 {
+            return Err(CollaborationError::Invalid(format!("Context key '{}' does not exist", key)));
+        }
+
+        // Numbered after the key's whole history, so a key written again after a
+        // delete does not reuse versions its history already holds
+        let latest: Option<i64> = sqlx::query_scalar(
+            "SELECT MAX(version) FROM team_shared_context_history WHERE team_id = ? AND key = ?"
+        )
+        .bind(team_id)
+        .bind(key)
+        .fetch_one(&mut *tx)
+        .await?;
+        let version = latest.unwrap_or(0).max(current as i64) as u32 + 1;
+        let serialized = value.map(|v| v.to_string());
+        match &serialized {
+            Some(serialized) => {
+                sqlx::query(
+                    "INSERT INTO team_shared_context (team_id, key, value, version, modified_by, modified_at)
+                     VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+                     ON CONFLICT(team_id, key) DO UPDATE SET
+                        value = excluded.value,
+                        version = excluded.version,
+                        modified_by = excluded.modified_by,
+                        modified_at = excluded.modified_at"
+                )
+                .bind(team_id)
+                .bind(key)
+                .bind(serialized)
+                .bind(version as i64)
+                .bind(modified_by)
+                .execute(&mut *tx)
+                .await?;
+            }
+            None => {
+                sqlx::query("DELETE FROM team_shared_context WHERE team_id = ? AND key = ?")
+                    .bind(team_id)
+                    .bind(key)
+                    .execute(&mut *tx)
+                    .await?;
             }
         }
-        
-        // Sort by suitability score
-        suitable_agents.sort_by(|a, b| {
-            self.calculate_suitability_score(b, task).partial_cmp(&self.calculate_suitability_score(a, task)).unwrap_or(std::cmp::Ordering::Equal)
-        });
-        
-        Ok(suitable_agents)
+        sqlx::query(
+            "INSERT INTO team_shared_context_history (team_id, key, value, version, modified_by) VALUES (?, ?, ?, ?, ?)"
+        )
+        .bind(team_id)
+        .bind(key)
+        .bind(&serialized)
+        .bind(version as i64)
+        .bind(modified_by)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("UPDATE agent_teams SET context_version = context_version + 1, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(team_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        self.refresh_team(pool, team_id).await?;
+        Ok(ContextEntry {
+            key: key.to_string(),
+            value: value.cloned(),
+            version,
+            modified_by: modified_by.to_string(),
+            modified_at: Utc::now(),
+        })
     }
 
-    async fn is_agent_suitable_for_task(&self, agent: &Agent, task: &Task) -> Result<bool, String> {
-        // Check if agent is active
-        if agent.status != AgentStatus::Working {
-            return Ok(false);
+    /// Every version of `key`, newest first
+    pub async fn context_history(&self, pool: &SqlitePool, team_id: &str, key: &str) -> Result<Vec<ContextEntry>, CollaborationError> {
+        self.team(team_id).await?;
+        let rows = sqlx::query_as::<sqlx::Sqlite, ContextHistoryRow>(
+            "SELECT key, value, version, modified_by, modified_at FROM team_shared_context_history
+             WHERE team_id = ? AND key = ? ORDER BY version DESC"
+        )
+        .bind(team_id)
+        .bind(key)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| ContextEntry {
+                key: row.key,
+                value: row.value.and_then(|value| serde_json::from_str(&value).ok()),
+                version: row.version as u32,
+                modified_by: row.modified_by,
+                modified_at: row.modified_at,
+            })
+            .collect())
+    }
+
+    /// Picks members for the task with the capability matcher and `algorithm`, assigns
+    /// the task (or one sub-task per pick when fanning out), records the rationale in
+    /// task activity and optionally spawns an OpenClaw session for each assignment
+    #[instrument(skip(self, state, task, request))]
+    pub async fn delegate_task_to_team(
+        &self,
+        state: &crate::AppState,
+        team_id: &str,
+        task: &Task,
+        request: &DelegateTaskRequest,
+        delegated_by: &str,
+    ) -> Result<TaskDelegation, CollaborationError> {
+        let pool = &state.pool;
+        let team = self.team(team_id).await?;
+        let mode = request.mode.unwrap_or(DelegationMode::Single);
+        let algorithm = request.algorithm.unwrap_or(self.task_delegation_engine.load_balancer.algorithm);
+
+        state.agent_pool.refresh(pool).await?;
+        let (candidates, excluded) = self.delegation_candidates(pool, &state.agent_pool, &team, task).await?;
+        if candidates.is_empty() {
+            let reasons: Vec<String> = excluded.iter().map(|e| format!("{}: {}", e.agent_id, e.reason)).collect();
+            return Err(CollaborationError::Conflict(format!(
+                "No member of team '{}' can take task {} ({})", team.name, task.id, reasons.join("; ")
+            )));
         }
-        
-        // Check if agent has required skills
-        if let Some(task_tags) = &task.tags {
-            if let Ok(task_skills) = serde_json::from_str::<Vec<String>>(task_tags) {
-                if let Some(agent_skills) = &agent.skills {
-                    if let Ok(agent_skills_vec) = serde_json::from_str::<Vec<String>>(agent_skills) {
-                        for required_skill in &task_skills {
-                            if !agent_skills_vec.contains(required_skill) {
-                                return Ok(false);
-                            }
-                        }
+
+        let last_assigned: Option<String> = sqlx::query_scalar(
+            "SELECT a.agent_id FROM task_delegation_assignments a
+             JOIN task_delegations d ON d.id = a.delegation_id
+             WHERE d.team_id = ? ORDER BY d.created_at DESC LIMIT 1"
+        )
+        .bind(team_id)
+        .fetch_optional(pool)
+        .await?;
+        let ranking = self.task_delegation_engine.rank(algorithm, candidates, last_assigned.as_deref());
+
+        // (task id, title of a new sub-task, candidate)
+        let picks: Vec<(String, Option<String>, &DelegationCandidate)> = match mode {
+            DelegationMode::Single => vec![(task.id.clone(), None, &ranking[0])],
+            DelegationMode::Fanout => {
+                let assignees = &ranking[..request.max_agents.unwrap_or(ranking.len()).clamp(1, ranking.len())];
+                let mut free_slots = Vec::with_capacity(assignees.len());
+                for candidate in assignees {
+                    free_slots.push(state.agent_pool.free_slots(&candidate.agent_id).await);
+                }
+                let titles: Vec<String> = match &request.subtasks {
+                    Some(titles) if !titles.is_empty() => titles.clone(),
+                    _ => assignees
+                        .iter()
+                        .zip(&free_slots)
+                        .filter(|(_, free)| **free > 0)
+                        .map(|(candidate, _)| format!("{} ({})", task.title, candidate.name))
+                        .collect(),
+                };
+                let capacity: usize = free_slots.iter().sum();
+                if titles.is_empty() || titles.len() > capacity {
+                    return Err(CollaborationError::Conflict(format!(
+                        "Team '{}' has {} free slots across {} members for {} sub-tasks of task {}",
+                        team.name, capacity, assignees.len(), titles.len(), task.id
+                    )));
+                }
+
+                // Handed out in ranking order a round at a time, skipping members whose
+                // slots are all taken
+                let mut picks = Vec::with_capacity(titles.len());
+                let mut next = 0;
+                for title in titles {
+                    while free_slots[next % assignees.len()] == 0 {
+                        next += 1;
                     }
+                    let slot = next % assignees.len();
+                    free_slots[slot] -= 1;
+                    next += 1;
+                    let title: String = title.chars().take(500).collect();
+                    picks.push((Uuid::new_v4().to_string(), Some(title), &assignees[slot]));
+                }
+                picks
+            }
+        };
+
+        let rationale = delegation_rationale(&team, mode, algorithm, &picks, &ranking, &excluded);
+        let delegation_id = Uuid::new_v4().to_string();
+
+        let mut tx = pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO task_delegations (id, task_id, team_id, mode, algorithm, rationale, created_by)
+             VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&delegation_id)
+        .bind(&task.id)
+        .bind(team_id)
+        .bind(mode.as_str())
+        .bind(algorithm.as_str())
+        .bind(&rationale)
+        .bind(delegated_by)
+        .execute(&mut *tx)
+        .await?;
+
+        for (assigned_task_id, subtask_title, candidate) in &picks {
+            match subtask_title {
+                Some(title) => {
+                    let responsibilities = team
+                        .roles
+                        .get(&candidate.agent_id)
+                        .filter(|role| !role.responsibilities.is_empty())
+                        .map(|role| format!("\n\nResponsibilities: {}", role.responsibilities.join(", ")))
+                        .unwrap_or_default();
+                    let description: String = format!("{}{}", task.description.as_deref().unwrap_or(""), responsibilities)
+                        .trim()
+                        .chars()
+                        .take(5000)
+                        .collect();
+                    sqlx::query(
+                        "INSERT INTO tasks (id, title, description, status, priority, tags, assignee_id, classification, created_by, metadata, created_at, updated_at)
+                         VALUES (?, ?, ?, 'ASSIGNED', ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)"
+                    )
+                    .bind(assigned_task_id)
+                    .bind(title)
+                    .bind(if description.is_empty() { None } else { Some(description) })
+                    .bind(task.priority)
+                    .bind(&task.tags)
+                    .bind(&candidate.agent_id)
+                    .bind(task.classification)
+                    .bind(delegated_by)
+                    .bind(serde_json::json!({ "parent_task_id": task.id, "delegation_id": delegation_id }).to_string())
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                None => {
+                    sqlx::query(
+                        "UPDATE tasks SET assignee_id = ?, status = CASE WHEN status = 'INBOX' THEN 'ASSIGNED' ELSE status END,
+                             updated_at = CURRENT_TIMESTAMP
+                         WHERE id = ?"
+                    )
+                    .bind(&candidate.agent_id)
+                    .bind(assigned_task_id)
+                    .execute(&mut *tx)
+                    .await?;
                 }
             }
+
+            sqlx::query(
+                "INSERT INTO task_delegation_assignments (delegation_id, task_id, agent_id, score) VALUES (?, ?, ?, ?)"
+            )
+            .bind(&delegation_id)
+            .bind(assigned_task_id)
+            .bind(&candidate.agent_id)
+            .bind(candidate.score)
+            .execute(&mut *tx)
+            .await?;
+
+            if subtask_title.is_some() {
+                sqlx::query("INSERT INTO task_activity (id, task_id, agent_id, message, timestamp) VALUES (?, ?, NULL, ?, CURRENT_TIMESTAMP)")
+                    .bind(Uuid::new_v4().to_string())
+                    .bind(assigned_task_id)
+                    .bind(format!("Split from task {} and delegated to {} by team {}", task.id, candidate.name, team.name))
+                    .execute(&mut *tx)
+                    .await?;
+            }
         }
-        
-        // Check security level
-        if agent.security_level < SecurityLevel::Internal {
-            return Ok(false);
+
+        if mode == DelegationMode::Fanout {
+            // The parent waits on its sub-tasks
+            let mut dependencies: Vec<String> = sqlx::query_scalar::<sqlx::Sqlite, Option<String>>("SELECT dependencies FROM tasks WHERE id = ?")
+                .bind(&task.id)
+                .fetch_one(&mut *tx)
+                .await?
+                .and_then(|deps| serde_json::from_str(&deps).ok())
+                .unwrap_or_default();
+            dependencies.extend(picks.iter().map(|(id, _, _)| id.clone()));
+            sqlx::query("UPDATE tasks SET dependencies = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(serde_json::to_string(&dependencies).unwrap_or_else(|_| "[]".to_string()))
+                .bind(&task.id)
+                .execute(&mut *tx)
+                .await?;
         }
-        
-        Ok(true)
+
+        sqlx::query("INSERT INTO task_activity (id, task_id, agent_id, message, timestamp) VALUES (?, ?, NULL, ?, CURRENT_TIMESTAMP)")
+            .bind(Uuid::new_v4().to_string())
+            .bind(&task.id)
+            .bind(&rationale)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        for (assigned_task_id, _, _) in &picks {
+            state.agent_pool.sync_task(pool, assigned_task_id).await?;
+        }
+
+        let mut assignments = Vec::new();
+        for (assigned_task_id, subtask_title, candidate) in &picks {
+            let mut assignment = DelegationAssignment {
+                task_id: assigned_task_id.clone(),
+                subtask_title: subtask_title.clone(),
+                agent_id: candidate.agent_id.clone(),
+                agent_name: candidate.name.clone(),
+                score: candidate.score,
+                routed: false,
+                routing_error: None,
+            };
+
+            if request.route {
+                let routed = match crate::fetch_visible_task(pool, assigned_task_id, SecurityLevel::Secret).await {
+                    Ok(assigned_task) => crate::route_to_agent_session(state, &assigned_task, &candidate.agent_id).await,
+                    Err(e) => Err(e),
+                };
+                match routed {
+                    Ok(_) => assignment.routed = true,
+                    Err((_, message)) => {
+                        warn!("Could not route delegated task {} to {}: {}", assigned_task_id, candidate.agent_id, message);
+                        assignment.routing_error = Some(message);
+                    }
+                }
+                sqlx::query(
+                    "UPDATE task_delegation_assignments SET routed = ?, routing_error = ? WHERE delegation_id = ? AND task_id = ?"
+                )
+                .bind(assignment.routed)
+                .bind(&assignment.routing_error)
+                .bind(&delegation_id)
+                .bind(assigned_task_id)
+                .execute(pool)
+                .await?;
+            }
+            assignments.push(assignment);
+        }
+
+        self.collaboration_metrics.write().await.tasks_delegated += assignments.len() as u64;
+        self.refresh_team(pool, team_id).await?;
+
+        let event = serde_json::json!({
+            "type": "task_delegated",
+            "task_id": task.id,
+            "team_id": team_id,
+            "assignees": assignments.iter().map(|a| a.agent_id.as_str()).collect::<Vec<_>>(),
+        });
+        state.manager.broadcast_classified(task.classification, &event.to_string());
+        info!("Delegated task '{}' to {} agents of team {}", task.id, assignments.len(), team.name);
+
+        Ok(TaskDelegation {
+            id: delegation_id,
+            task_id: task.id.clone(),
+            team_id: team_id.to_string(),
+            mode,
+            algorithm,
+            rationale,
+            assignments,
+            ranking,
+            excluded,
+        })
     }
 
-    fn calculate_suitability_score(&self, agent: &Agent, task: &Task) -> f64 {
-        let mut score = 0.0;
-        
-        // Base score for being available
-        score += 50.0;
-        
-        // Add score for matching skills
-        if let (Some(task_tags), Some(agent_skills)) = (&task.tags, &agent.skills) {
-            if let (Ok(task_skills), Ok(agent_skills_vec)) = (
-                serde_json::from_str::<Vec<String>>(task_tags),
-                serde_json::from_str::<Vec<String>>(agent_skills)
-            ) {
-                let matching_skills = task_skills.iter()
-                    .filter(|skill| agent_skills_vec.contains(skill))
-                    .count();
-                score += (matching_skills as f64 / task_skills.len() as f64) * 30.0;
+    /// Members that may take the task, scored by the capability matcher, and the
+    /// members left out with the reason
+    async fn delegation_candidates(
+        &self,
+        pool: &SqlitePool,
+        agent_pool: &crate::openclaw_optimization::AgentPool,
+        team: &AgentTeam,
+        task: &Task,
+    ) -> Result<(Vec<DelegationCandidate>, Vec<ExcludedMember>), CollaborationError> {
+        let task_tags: Vec<String> = task.tags.as_deref().and_then(|tags| serde_json::from_str(tags).ok()).unwrap_or_default();
+        let matcher = &self.task_delegation_engine.capability_matcher;
+
+        let mut candidates = Vec::new();
+        let mut excluded = Vec::new();
+        for agent in &team.members {
+            let exclusion = if agent.is_deleted {
+                Some("agent is deleted".to_string())
+            } else if team.roles.get(&agent.id).is_some_and(|role| role.role_type == RoleType::Observer) {
+                Some("observers do not take tasks".to_string())
+            } else if matches!(agent.status, AgentStatus::Offline | AgentStatus::Suspended | AgentStatus::Maintenance | AgentStatus::Error) {
+                Some(format!("agent is {:?}", agent.status).to_lowercase())
+            } else if agent.security_level < task.classification {
+                Some("clearance is below the task classification".to_string())
+            } else if !agent_pool.has_capacity(&agent.id, &task.id).await {
+                Some(format!("agent has no free slot (max_concurrent {})", agent.max_concurrent.unwrap_or(1)))
+            } else if let Some(reason) = crate::budget::BudgetService
+                .routing_refusal(pool, &agent.id, &task.id)
+                .await
+                .map_err(|e| CollaborationError::Database(e.to_string()))?
+            {
+                Some(reason)
+            } else if let crate::agent_health::RoutingHealth::Critical(score) = crate::agent_health::AgentHealthService
+                .routing_health(pool, &agent.id)
+                .await
+                .map_err(|e| CollaborationError::Database(e.to_string()))?
+            {
+                Some(format!("health score {:.0} is critical", score))
+            } else {
+                None
+            };
+            if let Some(reason) = exclusion {
+                excluded.push(ExcludedMember { agent_id: agent.id.clone(), reason });
+                continue;
             }
+
+            let agent_skills: Vec<String> = agent.skills.as_deref().and_then(|skills| serde_json::from_str(skills).ok()).unwrap_or_default();
+            let completed_tasks: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tasks WHERE assignee_id = ? AND status = 'DONE'")
+                .bind(&agent.id)
+                .fetch_one(pool)
+                .await?;
+            let performance = agent_performance(agent);
+            let skill_match = matcher.skill_match(&task_tags, &agent_skills);
+
+            candidates.push(DelegationCandidate {
+                agent_id: agent.id.clone(),
+                name: agent.name.clone(),
+                skill_match,
+                completed_tasks,
+                performance,
+                estimated_cost: self.task_delegation_engine.estimate_agent_cost(agent),
+                score: matcher.score(skill_match, completed_tasks, performance),
+            });
         }
-        
-        // Add score for performance (lower failure count is better)
-        let performance_score = 20.0 * (1.0 - (agent.model_failure_count as f64 / 100.0));
-        score += performance_score.max(0.0);
-        
-        score
+        Ok((candidates, excluded))
+    }
+}
+
+fn delegation_rationale(
+    team: &AgentTeam,
+    mode: DelegationMode,
+    algorithm: DelegationAlgorithm,
+    picks: &[(String, Option<String>, &DelegationCandidate)],
+    ranking: &[DelegationCandidate],
+    excluded: &[ExcludedMember],
+) -> String {
+    let describe = |c: &DelegationCandidate| {
+        format!(
+            "{} (score {:.2}: {:.0}% skill match, {} completed tasks, performance {:.0}%)",
+            c.name, c.score, c.skill_match * 100.0, c.completed_tasks, c.performance * 100.0
+        )
+    };
+
+    let mut rationale = match mode {
+        DelegationMode::Single => format!(
+            "Delegated by team {} ({}) to {}",
+            team.name, algorithm.as_str(), describe(picks[0].2)
+        ),
+        DelegationMode::Fanout => format!(
+            "Split by team {} ({}) into {} sub-tasks: {}",
+            team.name,
+            algorithm.as_str(),
+            picks.len(),
+            picks
+                .iter()
+                .map(|(_, title, c)| format!("\"{}\" to {}", title.as_deref().unwrap_or_default(), describe(c)))
+                .collect::<Vec<_>>()
+                .join("; ")
+        ),
+    };
+
+    let passed_over: Vec<String> = ranking
+        .iter()
+        .filter(|c| !picks.iter().any(|(_, _, picked)| picked.agent_id == c.agent_id))
+        .map(|c| format!("{} ({:.2})", c.name, c.score))
+        .collect();
+    if !passed_over.is_empty() {
+        rationale.push_str(&format!(". Passed over: {}", passed_over.join(", ")));
+    }
+    if !excluded.is_empty() {
+        let reasons: Vec<String> = excluded.iter().map(|e| format!("{} ({})", e.agent_id, e.reason)).collect();
+        rationale.push_str(&format!(". Not eligible: {}", reasons.join(", ")));
+    }
+    rationale
+}
+
+impl CapabilityMatcher {
+    /// Weighted share of the task's tags found in the agent's skills; untagged tasks match fully
+    pub fn skill_match(&self, task_tags: &[String], agent_skills: &[String]) -> f64 {
+        let weight = |tag: &&String| self.skill_weights.get(tag.as_str()).copied().unwrap_or(1.0);
+        let total: f64 = task_tags.iter().map(|tag| weight(&tag)).sum();
+        if total <= 0.0 {
+            return 1.0;
+        }
+        task_tags.iter().filter(|tag| agent_skills.contains(tag)).map(|tag| weight(&tag)).sum::<f64>() / total
+    }
+
+    /// Skill match, raised by up to `experience_multiplier` as completed tasks approach 20
+    /// and scaled by performance in proportion to `performance_history_weight`
+    pub fn score(&self, skill_match: f64, completed_tasks: i64, performance: f64) -> f64 {
+        let experience = 1.0 + (self.experience_multiplier - 1.0) * (completed_tasks.min(20) as f64 / 20.0);
+        let performance_factor = 1.0 - self.performance_history_weight + self.performance_history_weight * performance;
+        skill_match * experience * performance_factor
     }
 }
 
 impl TaskDelegationEngine {
-    #[instrument(skip(self, task, agents))]
-    pub async fn delegate_task(&self, task: &Task, agents: &[Agent]) -> Result<Vec<String>, String> {
-        let mut assigned_agents = Vec::new();
-        
-        match &self.load_balancer.algorithm {
-            DelegationAlgorithm::CapabilityBased => {
-                // Assign to the most capable agent
-                if let Some(best_agent) = agents.first() {
-                    assigned_agents.push(best_agent.id.clone());
+    /// Orders candidates for `algorithm`; single-owner delegation takes the first and
+    /// fan-out hands sub-tasks out in this order
+    pub fn rank(
+        &self,
+        algorithm: DelegationAlgorithm,
+        mut candidates: Vec<DelegationCandidate>,
+        last_assigned: Option<&str>,
+    ) -> Vec<DelegationCandidate> {
+        let by_score = |a: &DelegationCandidate, b: &DelegationCandidate| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(b.performance.partial_cmp(&a.performance).unwrap_or(std::cmp::Ordering::Equal))
+        };
+
+        match algorithm {
+            DelegationAlgorithm::CapabilityBased => candidates.sort_by(by_score),
+            DelegationAlgorithm::PerformanceBased => candidates.sort_by(|a, b| {
+                b.performance
+                    .partial_cmp(&a.performance)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+                    .then(by_score(a, b))
+            }),
+            DelegationAlgorithm::CostOptimized => candidates.sort_by(|a, b| {
+                a.estimated_cost
+                    .partial_cmp(&b.estimated_cost)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+                    .then(by_score(a, b))
+            }),
+            // Team order, starting after whoever was assigned last
+            DelegationAlgorithm::RoundRobin => {
+                if let Some(position) = last_assigned.and_then(|last| candidates.iter().position(|c| c.agent_id == last)) {
+                    candidates.rotate_left(position + 1);
                 }
             }
-            DelegationAlgorithm::PerformanceBased => {
-                // Assign to the best performing agent
-                let best_agent = agents.iter()
-                    .min_by_key(|agent| agent.model_failure_count)
-                    .unwrap_or(&agents[0]);
-                assigned_agents.push(best_agent.id.clone());
-            }
-            DelegationAlgorithm::CostOptimized => {
-                // Assign to the most cost-effective agent
-                let best_agent = agents.iter()
-                    .min_by_key(|agent| self.estimate_agent_cost(agent))
-                    .unwrap_or(&agents[0]);
-                assigned_agents.push(best_agent.id.clone());
-            }
-            _ => {
-                // Default: assign to first available agent
-                if let Some(agent) = agents.first() {
-                    assigned_agents.push(agent.id.clone());
+            // Draw without replacement, each pick weighted by score
+            DelegationAlgorithm::WeightedRandom => {
+                use rand::Rng;
+                let mut rng = rand::thread_rng();
+                let mut drawn = Vec::with_capacity(candidates.len());
+                while !candidates.is_empty() {
+                    let total: f64 = candidates.iter().map(|c| c.score.max(0.01)).sum();
+                    let mut target = rng.gen_range(0.0..total);
+                    let index = candidates
+                        .iter()
+                        .position(|c| {
+                            target -= c.score.max(0.01);
+                            target <= 0.0
+                        })
+                        .unwrap_or(candidates.len() - 1);
+                    drawn.push(candidates.remove(index));
                 }
+                candidates = drawn;
             }
         }
-        
-        Ok(assigned_agents)
+        candidates
     }
 
     fn estimate_agent_cost(&self, agent: &Agent) -> f64 {
@@ -526,26 +1523,693 @@ impl TaskDelegationEngine {
     }
 }
 
-// Learning and Adaptation System
-
-#[derive(Clone)]
-pub struct AdaptiveAgent {
-    pub base_agent: Agent,
-    pub learning_engine: LearningEngine,
-    pub performance_history: PerformanceHistory,
-    pub adaptation_strategy: AdaptationStrategy,
-    pub adaptation_metrics: AdaptationMetrics,
+// Team Ballots
+
+/// The agent's track record between 0 and 1: its health score when it has one,
+/// otherwise how rarely its models fail
+fn agent_performance(agent: &Agent) -> f64 {
+    agent
+        .health_score
+        .map(|score| score / 100.0)
+        .unwrap_or(1.0 - agent.model_failure_count as f64 / 100.0)
+        .clamp(0.0, 1.0)
+}
+
+#[derive(sqlx::FromRow)]
+struct BallotRow {
+    id: String,
+    team_id: String,
+    task_id: Option<String>,
+    kind: String,
+    conflict_type: String,
+    question: String,
+    options: String,
+    voting_type: String,
+    quorum: f64,
+    tie_breaker: String,
+    deadline: chrono::DateTime<Utc>,
+    status: String,
+    outcome: Option<String>,
+    resolution: Option<String>,
+    resolved_by: Option<String>,
+    created_by: String,
+    created_at: chrono::DateTime<Utc>,
+    resolved_at: Option<chrono::DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Ballot {
+    pub id: String,
+    pub team_id: String,
+    pub task_id: Option<String>,
+    /// `decision`, or `review` to approve or reject a task in REVIEW
+    pub kind: String,
+    pub conflict_type: String,
+    pub question: String,
+    pub options: Vec<String>,
+    pub voting_type: String,
+    pub quorum: f64,
+    pub tie_breaker: String,
+    pub deadline: chrono::DateTime<Utc>,
+    /// `open`, `resolved` or `escalated`
+    pub status: String,
+    pub outcome: Option<String>,
+    pub resolution: Option<String>,
+    pub resolved_by: Option<String>,
+    pub created_by: String,
+    pub created_at: chrono::DateTime<Utc>,
+    pub resolved_at: Option<chrono::DateTime<Utc>>,
 }
 
-#[derive(Clone)]
-pub struct LearningEngine {
-    pub learning_algorithms: Vec<LearningAlgorithm>,
-    pub feedback_processor: FeedbackProcessor,
-    pub pattern_recognizer: PatternRecognizer,
-    pub knowledge_base: KnowledgeBase,
+impl From<BallotRow> for Ballot {
+    fn from(row: BallotRow) -> Self {
+        Self {
+            options: serde_json::from_str(&row.options).unwrap_or_default(),
+            id: row.id,
+            team_id: row.team_id,
+            task_id: row.task_id,
+            kind: row.kind,
+            conflict_type: row.conflict_type,
+            question: row.question,
+            voting_type: row.voting_type,
+            quorum: row.quorum,
+            tie_breaker: row.tie_breaker,
+            deadline: row.deadline,
+            status: row.status,
+            outcome: row.outcome,
+            resolution: row.resolution,
+            resolved_by: row.resolved_by,
+            created_by: row.created_by,
+            created_at: row.created_at,
+            resolved_at: row.resolved_at,
+        }
+    }
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct BallotVote {
+    pub agent_id: String,
+    pub choice: String,
+    pub comment: Option<String>,
+    pub cast_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OptionWeight {
+    pub option: String,
+    pub weight: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BallotTally {
+    /// Members other than observers
+    pub eligible: usize,
+    pub voted: usize,
+    pub quorum_met: bool,
+    /// Weight behind each option under the ballot's voting type
+    pub weights: Vec<OptionWeight>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BallotView {
+    #[serde(flatten)]
+    pub ballot: Ballot,
+    pub votes: Vec<BallotVote>,
+    pub tally: BallotTally,
+}
+
+enum BallotDecision {
+    Winner(String),
+    Tie(Vec<String>),
+    Undecided(String),
+}
+
+fn vote_of<'a>(votes: &'a [BallotVote], agent_id: &str) -> Option<&'a str> {
+    votes.iter().find(|vote| vote.agent_id == agent_id).map(|vote| vote.choice.as_str())
+}
+
+/// The vote of the nearest member up `agent_id`'s reporting line who cast one
+fn delegated_vote<'a>(team: &AgentTeam, votes: &'a [BallotVote], agent_id: &str) -> Option<&'a str> {
+    let mut current = agent_id.to_string();
+    for _ in 0..=team.roles.len() {
+        if let Some(choice) = vote_of(votes, &current) {
+            return Some(choice);
+        }
+        current = team.roles.get(&current)?.reporting_to.clone()?;
+    }
+    None
+}
+
+fn role_weight(role_type: RoleType) -> f64 {
+    match role_type {
+        RoleType::Leader => 2.0,
+        RoleType::Coordinator | RoleType::Reviewer => 1.5,
+        _ => 1.0,
+    }
+}
+
+fn tally_ballot(ballot: &Ballot, team: &AgentTeam, votes: &[BallotVote]) -> BallotTally {
+    let voting_type = VotingType::parse(&ballot.voting_type).unwrap_or(VotingType::SimpleMajority);
+    let eligible: Vec<&TeamRole> = team.roles.values().filter(|role| role.role_type != RoleType::Observer).collect();
+
+    let mut weights: Vec<OptionWeight> = ballot
+        .options
+        .iter()
+        .map(|option| OptionWeight { option: option.clone(), weight: 0.0 })
+        .collect();
+    for role in &eligible {
+        let (choice, weight) = match voting_type {
+            VotingType::WeightedVoting => (vote_of(votes, &role.agent_id), role_weight(role.role_type)),
+            // Members who did not vote are carried by whoever they report to
+            VotingType::DelegatedVoting => (delegated_vote(team, votes, &role.agent_id), 1.0),
+            _ => (vote_of(votes, &role.agent_id), 1.0),
+        };
+        if let Some(entry) = choice.and_then(|choice| weights.iter_mut().find(|w| w.option == choice)) {
+            entry.weight += weight;
+        }
+    }
+
+    let voted = eligible.iter().filter(|role| vote_of(votes, &role.agent_id).is_some()).count();
+    BallotTally {
+        eligible: eligible.len(),
+        voted,
+        quorum_met: !eligible.is_empty() && voted as f64 / eligible.len() as f64 >= ballot.quorum,
+        weights,
+    }
+}
+
+fn decide_ballot(voting_type: VotingType, tally: &BallotTally) -> BallotDecision {
+    let total: f64 = tally.weights.iter().map(|w| w.weight).sum();
+    if total <= 0.0 {
+        return BallotDecision::Undecided("no votes were cast".to_string());
+    }
+    let top = tally.weights.iter().map(|w| w.weight).fold(0.0, f64::max);
+    let leaders: Vec<String> = tally
+        .weights
+        .iter()
+        .filter(|w| (w.weight - top).abs() < 1e-9)
+        .map(|w| w.option.clone())
+        .collect();
+    if leaders.len() > 1 {
+        return BallotDecision::Tie(leaders);
+    }
+
+    let share = top / total;
+    let (reached, rule) = match voting_type {
+        VotingType::QualifiedMajority => (share >= 2.0 / 3.0 - 1e-9, "a two-thirds majority"),
+        VotingType::Consensus => (share >= 1.0 - 1e-9, "consensus"),
+        _ => (share > 0.5, "a majority"),
+    };
+    if reached {
+        BallotDecision::Winner(leaders[0].clone())
+    } else {
+        BallotDecision::Undecided(format!("{} was not reached ({:.0}% for {})", rule, share * 100.0, leaders[0]))
+    }
+}
+
+impl AgentCollaboration {
+    pub async fn open_ballot(
+        &self,
+        pool: &SqlitePool,
+        team_id: &str,
+        request: &OpenBallotRequest,
+        created_by: &str,
+    ) -> Result<Ballot, CollaborationError> {
+        self.team(team_id).await?;
+        let mechanism = &self.conflict_resolver.voting_mechanism;
+
+        let question = request.question.trim();
+        if question.is_empty() {
+            return Err(CollaborationError::Invalid("A ballot needs a question".to_string()));
+        }
+        let kind = request.kind.as_deref().unwrap_or("decision");
+        let options: Vec<String> = match kind {
+            "review" => vec!["approved".to_string(), "rejected".to_string()],
+            "decision" => {
+                let mut options: Vec<String> = Vec::new();
+                for option in request.options.iter().flatten().map(|o| o.trim()) {
+                    if !option.is_empty() && !options.iter().any(|o| o == option) {
+                        options.push(option.to_string());
+                    }
+                }
+                options
+            }
+            other => return Err(CollaborationError::Invalid(format!("Unknown ballot kind '{}'", other))),
+        };
+        if options.len() < 2 {
+            return Err(CollaborationError::Invalid("A ballot needs at least two distinct options".to_string()));
+        }
+
+        match (&request.task_id, kind) {
+            (Some(task_id), _) => {
+                let exists: Option<String> = sqlx::query_scalar("SELECT id FROM tasks WHERE id = ? AND is_deleted = 0")
+                    .bind(task_id)
+                    .fetch_optional(pool)
+                    .await?;
+                if exists.is_none() {
+                    return Err(CollaborationError::Invalid(format!("Task {} not found", task_id)));
+                }
+            }
+            (None, "review") => return Err(CollaborationError::Invalid("Review ballots need a task_id".to_string())),
+            (None, _) => {}
+        }
+
+        let quorum = request.quorum.unwrap_or(mechanism.quorum_required);
+        if !(quorum > 0.0 && quorum <= 1.0) {
+            return Err(CollaborationError::Invalid("quorum must be greater than 0 and at most 1".to_string()));
+        }
+        let tie_breaker = request.tie_breaker.as_deref().unwrap_or(mechanism.tie_breaker.name());
+        if !matches!(tie_breaker, "random" | "seniority" | "performance" | "cost" | "custom") {
+            return Err(CollaborationError::Invalid(format!("Unknown tie breaker '{}'", tie_breaker)));
+        }
+        let deadline_minutes = request
+            .deadline_minutes
+            .unwrap_or_else(|| mechanism.voting_period.as_secs().div_ceil(60))
+            .clamp(1, 10080);
+
+        let id = Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO team_ballots
+             (id, team_id, task_id, kind, conflict_type, question, options, voting_type, quorum, tie_breaker, deadline, created_by)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, datetime('now', '+' || ? || ' minutes'), ?)"
+        )
+        .bind(&id)
+        .bind(team_id)
+        .bind(&request.task_id)
+        .bind(kind)
+        .bind(request.conflict_type.unwrap_or(ConflictType::DecisionMaking).as_str())
+        .bind(question)
+        .bind(serde_json::to_string(&options).unwrap_or_else(|_| "[]".to_string()))
+        .bind(request.voting_type.unwrap_or(mechanism.voting_type).as_str())
+        .bind(quorum)
+        .bind(tie_breaker)
+        .bind(deadline_minutes as i64)
+        .bind(created_by)
+        .execute(pool)
+        .await?;
+
+        self.ballot(pool, &id).await
+    }
+
+    pub async fn ballot(&self, pool: &SqlitePool, ballot_id: &str) -> Result<Ballot, CollaborationError> {
+        sqlx::query_as::<sqlx::Sqlite, BallotRow>("SELECT * FROM team_ballots WHERE id = ?")
+            .bind(ballot_id)
+            .fetch_optional(pool)
+            .await?
+            .map(Ballot::from)
+            .ok_or_else(|| CollaborationError::BallotNotFound(ballot_id.to_string()))
+    }
+
+    pub async fn ballots(&self, pool: &SqlitePool, team_id: &str, status: Option<&str>) -> Result<Vec<Ballot>, CollaborationError> {
+        self.team(team_id).await?;
+        let rows = sqlx::query_as::<sqlx::Sqlite, BallotRow>(
+            "SELECT * FROM team_ballots WHERE team_id = ?1 AND (?2 IS NULL OR status = ?2) ORDER BY created_at DESC"
+        )
+        .bind(team_id)
+        .bind(status)
+        .fetch_all(pool)
+        .await?;
+        Ok(rows.into_iter().map(Ballot::from).collect())
+    }
+
+    async fn ballot_votes(&self, pool: &SqlitePool, ballot_id: &str) -> Result<Vec<BallotVote>, sqlx::Error> {
+        sqlx::query_as::<sqlx::Sqlite, BallotVote>(
+            "SELECT agent_id, choice, comment, cast_at FROM team_ballot_votes WHERE ballot_id = ? ORDER BY cast_at"
+        )
+        .bind(ballot_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    pub async fn ballot_view(&self, pool: &SqlitePool, ballot_id: &str) -> Result<BallotView, CollaborationError> {
+        let ballot = self.ballot(pool, ballot_id).await?;
+        let team = self.team(&ballot.team_id).await?;
+        let votes = self.ballot_votes(pool, ballot_id).await?;
+        let tally = tally_ballot(&ballot, &team, &votes);
+        Ok(BallotView { ballot, votes, tally })
+    }
+
+    /// Records (or replaces) the agent's vote and settles the ballot once every
+    /// eligible member has voted
+    pub async fn cast_vote(
+        &self,
+        pool: &SqlitePool,
+        manager: &crate::ConnectionManager,
+        ballot_id: &str,
+        agent_id: &str,
+        request: &CastVoteRequest,
+    ) -> Result<BallotView, CollaborationError> {
+        let ballot = self.ballot(pool, ballot_id).await?;
+        if ballot.status != "open" {
+            return Err(CollaborationError::Conflict(format!("Ballot {} is {}", ballot_id, ballot.status)));
+        }
+        if ballot.deadline <= Utc::now() {
+            self.settle_ballot(pool, manager, ballot_id, true).await?;
+            return Err(CollaborationError::Conflict(format!("Voting on ballot {} has closed", ballot_id)));
+        }
+
+        let team = self.team(&ballot.team_id).await?;
+        match team.roles.get(agent_id) {
+            Some(role) if role.role_type != RoleType::Observer => {}
+            Some(_) => return Err(CollaborationError::AccessDenied("Observers do not vote".to_string())),
+            None => return Err(CollaborationError::AccessDenied(format!("{} is not a member of team '{}'", agent_id, team.name))),
+        }
+        if !ballot.options.contains(&request.choice) {
+            return Err(CollaborationError::Invalid(format!(
+                "'{}' is not an option; choose one of {}", request.choice, ballot.options.join(", ")
+            )));
+        }
+
+        sqlx::query(
+            "INSERT INTO team_ballot_votes (ballot_id, agent_id, choice, comment) VALUES (?, ?, ?, ?)
+             ON CONFLICT(ballot_id, agent_id) DO UPDATE SET
+                choice = excluded.choice, comment = excluded.comment, cast_at = CURRENT_TIMESTAMP"
+        )
+        .bind(ballot_id)
+        .bind(agent_id)
+        .bind(&request.choice)
+        .bind(&request.comment)
+        .execute(pool)
+        .await?;
+        self.collaboration_metrics.write().await.messages_exchanged += 1;
+
+        self.settle_ballot(pool, manager, ballot_id, false).await?;
+        self.ballot_view(pool, ballot_id).await
+    }
+
+    /// Decides an open ballot once everyone voted or, with `deadline_passed`, with the
+    /// votes in hand: below quorum, undecided or unbreakable ties go to a human
+    async fn settle_ballot(
+        &self,
+        pool: &SqlitePool,
+        manager: &crate::ConnectionManager,
+        ballot_id: &str,
+        deadline_passed: bool,
+    ) -> Result<(), CollaborationError> {
+        let ballot = self.ballot(pool, ballot_id).await?;
+        if ballot.status != "open" {
+            return Ok(());
+        }
+        let team = self.team(&ballot.team_id).await?;
+        let votes = self.ballot_votes(pool, ballot_id).await?;
+        let tally = tally_ballot(&ballot, &team, &votes);
+        if !deadline_passed && tally.voted < tally.eligible {
+            return Ok(());
+        }
+
+        if !tally.quorum_met {
+            let reason = format!(
+                "quorum not reached: {} of {} members voted, {:.0}% required",
+                tally.voted, tally.eligible, ballot.quorum * 100.0
+            );
+            return self.escalate_ballot(pool, manager, &ballot, &team, &reason).await;
+        }
+
+        let voting_type = VotingType::parse(&ballot.voting_type).unwrap_or(VotingType::SimpleMajority);
+        match decide_ballot(voting_type, &tally) {
+            BallotDecision::Winner(outcome) => {
+                let resolution = format!("{} vote", voting_type.as_str().replace('_', " "));
+                self.resolve_ballot(pool, manager, &ballot, &team, &outcome, &resolution, None).await
+            }
+            BallotDecision::Tie(tied) => match self.break_tie(pool, &ballot, &team, &votes, &tied).await? {
+                Some(outcome) => {
+                    let resolution = format!("tie between {} broken by {}", tied.join(" and "), ballot.tie_breaker);
+                    self.resolve_ballot(pool, manager, &ballot, &team, &outcome, &resolution, None).await
+                }
+                None => {
+                    let reason = format!("tie between {} that {} could not break", tied.join(" and "), ballot.tie_breaker);
+                    self.escalate_ballot(pool, manager, &ballot, &team, &reason).await
+                }
+            },
+            BallotDecision::Undecided(reason) => self.escalate_ballot(pool, manager, &ballot, &team, &reason).await,
+        }
+    }
+
+    async fn break_tie(
+        &self,
+        pool: &SqlitePool,
+        ballot: &Ballot,
+        team: &AgentTeam,
+        votes: &[BallotVote],
+        tied: &[String],
+    ) -> Result<Option<String>, sqlx::Error> {
+        // Average of `metric` over the members backing each tied option; None if the best is shared
+        let best_by = |metric: &dyn Fn(&Agent) -> f64, highest: bool| -> Option<String> {
+            let averages: Vec<(String, f64)> = tied
+                .iter()
+                .map(|option| {
+                    let backers: Vec<f64> = votes
+                        .iter()
+                        .filter(|vote| &vote.choice == option)
+                        .filter_map(|vote| team.members.iter().find(|agent| agent.id == vote.agent_id))
+                        .map(metric)
+                        .collect();
+                    let average = if backers.is_empty() { 0.0 } else { backers.iter().sum::<f64>() / backers.len() as f64 };
+                    (option.clone(), if highest { average } else { -average })
+                })
+                .collect();
+            let best = averages.iter().map(|(_, value)| *value).fold(f64::NEG_INFINITY, f64::max);
+            let winners: Vec<&String> = averages.iter().filter(|(_, value)| (value - best).abs() < 1e-9).map(|(option, _)| option).collect();
+            (winners.len() == 1).then(|| winners[0].clone())
+        };
+
+        Ok(match ballot.tie_breaker.as_str() {
+            "random" => {
+                use rand::seq::SliceRandom;
+                let mut rng = rand::thread_rng();
+                tied.choose(&mut rng).cloned()
+            }
+            // The option backed by the longest-standing member
+            "seniority" => {
+                let by_seniority: Vec<String> = sqlx::query_scalar(
+                    "SELECT agent_id FROM agent_team_members WHERE team_id = ? ORDER BY joined_at, agent_id"
+                )
+                .bind(&team.id)
+                .fetch_all(pool)
+                .await?;
+                by_seniority
+                    .iter()
+                    .filter_map(|agent_id| vote_of(votes, agent_id))
+                    .find(|choice| tied.iter().any(|option| option == choice))
+                    .map(str::to_string)
+            }
+            "performance" => best_by(&agent_performance, true),
+            "cost" => best_by(&|agent: &Agent| self.task_delegation_engine.estimate_agent_cost(agent), false),
+            "custom" => match &self.conflict_resolver.voting_mechanism.tie_breaker {
+                TieBreaker::Custom(choose) => Some(choose()).filter(|choice| tied.contains(choice)),
+                _ => None,
+            },
+            _ => None,
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn resolve_ballot(
+        &self,
+        pool: &SqlitePool,
+        manager: &crate::ConnectionManager,
+        ballot: &Ballot,
+        team: &AgentTeam,
+        outcome: &str,
+        resolution: &str,
+        resolved_by: Option<&str>,
+    ) -> Result<(), CollaborationError> {
+        let updated = sqlx::query(
+            "UPDATE team_ballots SET status = 'resolved', outcome = ?, resolution = ?, resolved_by = ?, resolved_at = CURRENT_TIMESTAMP
+             WHERE id = ? AND status != 'resolved'"
+        )
+        .bind(outcome)
+        .bind(resolution)
+        .bind(resolved_by)
+        .bind(&ballot.id)
+        .execute(pool)
+        .await?;
+        if updated.rows_affected() == 0 {
+            return Ok(());
+        }
+
+        if let Some(task_id) = &ballot.task_id {
+            sqlx::query(
+                "UPDATE tasks SET metadata = json_set(COALESCE(metadata, '{}'), '$.decisions.\"' || ?1 || '\"',
+                     json_object('question', ?2, 'outcome', ?3, 'resolution', ?4)),
+                     updated_at = CURRENT_TIMESTAMP
+                 WHERE id = ?5"
+            )
+            .bind(&ballot.id)
+            .bind(&ballot.question)
+            .bind(outcome)
+            .bind(resolution)
+            .bind(task_id)
+            .execute(pool)
+            .await?;
+
+            sqlx::query("INSERT INTO task_activity (id, task_id, agent_id, message, timestamp) VALUES (?, ?, NULL, ?, CURRENT_TIMESTAMP)")
+                .bind(Uuid::new_v4().to_string())
+                .bind(task_id)
+                .bind(format!("Team {} decided \"{}\": {} ({})", team.name, ballot.question, outcome, resolution))
+                .execute(pool)
+                .await?;
+
+            // A settled review moves the task on like POST /tasks/{id}/review does
+            let task: Option<(TaskStatus, SecurityLevel)> = sqlx::query_as("SELECT status, classification FROM tasks WHERE id = ?")
+                .bind(task_id)
+                .fetch_optional(pool)
+                .await?;
+            if let (Some((TaskStatus::Review, classification)), "review") = (task, ballot.kind.as_str()) {
+                let status = if outcome == "approved" { "DONE" } else { "IN_PROGRESS" };
+                let feedback = format!("Team vote: {}", resolution);
+                let mut tx = pool.begin().await?;
+                crate::set_task_status(&mut tx, task_id, status)
+                    .await
+                    .map_err(|(_, message)| CollaborationError::Database(message))?;
+                crate::agent_metrics::record_review(&mut tx, task_id, None, outcome, Some(&feedback)).await?;
+                tx.commit().await?;
+                crate::agent_metrics::learn_from_review(pool, task_id, outcome, Some(&feedback)).await;
+                manager.broadcast_classified(
+                    classification,
+                    &serde_json::json!({ "type": "status_changed", "task_id": task_id, "status": status }).to_string(),
+                );
+            }
+        }
+
+        self.collaboration_metrics.write().await.conflicts_resolved += 1;
+        manager.broadcast(&serde_json::json!({
+            "type": "ballot_resolved",
+            "ballot_id": ballot.id,
+            "team_id": ballot.team_id,
+            "task_id": ballot.task_id,
+            "outcome": outcome,
+        }).to_string());
+        info!("Ballot {} of team {} resolved: {} ({})", ballot.id, team.name, outcome, resolution);
+        Ok(())
+    }
+
+    /// Hands an unresolved ballot to a human: announced on behalf of the team's
+    /// leader (or first member) and noted on the task
+    async fn escalate_ballot(
+        &self,
+        pool: &SqlitePool,
+        manager: &crate::ConnectionManager,
+        ballot: &Ballot,
+        team: &AgentTeam,
+        reason: &str,
+    ) -> Result<(), CollaborationError> {
+        let updated = sqlx::query("UPDATE team_ballots SET status = 'escalated', resolution = ? WHERE id = ? AND status = 'open'")
+            .bind(reason)
+            .bind(&ballot.id)
+            .execute(pool)
+            .await?;
+        if updated.rows_affected() == 0 {
+            return Ok(());
+        }
+
+        let policy = &self.conflict_resolver.escalation_policy;
+        let author = team
+            .roles
+            .values()
+            .find(|role| role.role_type == RoleType::Leader)
+            .map(|role| role.agent_id.clone())
+            .or_else(|| team.members.first().map(|agent| agent.id.clone()));
+        if let (true, Some(author)) = (policy.automatic_escalation, author) {
+            let authority = policy.escalation_levels.iter().min_by_key(|level| level.level).map(|level| level.authority.clone());
+            sqlx::query(
+                "INSERT INTO announcements (id, title, message, priority, created_at, created_by, target_audience)
+                 VALUES (?, ?, ?, 'HIGH', CURRENT_TIMESTAMP, ?, ?)"
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind("Team vote needs a decision")
+            .bind(format!(
+                "Team {} could not settle \"{}\" ({}). Options: {}. Resolve it with POST /api/collaboration/ballots/{}/resolve",
+                team.name, ballot.question, reason, ballot.options.join(", "), ballot.id
+            ))
+            .bind(&author)
+            .bind(authority.map(|authority| serde_json::json!([authority]).to_string()))
+            .execute(pool)
+            .await?;
+        }
+
+        if let Some(task_id) = &ballot.task_id {
+            sqlx::query("INSERT INTO task_activity (id, task_id, agent_id, message, timestamp) VALUES (?, ?, NULL, ?, CURRENT_TIMESTAMP)")
+                .bind(Uuid::new_v4().to_string())
+                .bind(task_id)
+                .bind(format!("Team vote \"{}\" escalated to a human: {}", ballot.question, reason))
+                .execute(pool)
+                .await?;
+        }
+
+        manager.broadcast(&serde_json::json!({
+            "type": "ballot_escalated",
+            "ballot_id": ballot.id,
+            "team_id": ballot.team_id,
+            "task_id": ballot.task_id,
+            "reason": reason,
+        }).to_string());
+        warn!("Ballot {} of team {} escalated: {}", ballot.id, team.name, reason);
+        Ok(())
+    }
+
+    /// A human settles an open or escalated ballot
+    pub async fn decide_ballot_manually(
+        &self,
+        pool: &SqlitePool,
+        manager: &crate::ConnectionManager,
+        ballot_id: &str,
+        request: &ResolveBallotRequest,
+        user_id: &str,
+    ) -> Result<BallotView, CollaborationError> {
+        let ballot = self.ballot(pool, ballot_id).await?;
+        if ballot.status == "resolved" {
+            return Err(CollaborationError::Conflict(format!("Ballot {} is already resolved", ballot_id)));
+        }
+        if !ballot.options.contains(&request.outcome) {
+            return Err(CollaborationError::Invalid(format!(
+                "'{}' is not an option; choose one of {}", request.outcome, ballot.options.join(", ")
+            )));
+        }
+        let team = self.team(&ballot.team_id).await?;
+        let resolution = match &request.note {
+            Some(note) => format!("decided by a human: {}", note),
+            None => "decided by a human".to_string(),
+        };
+        self.resolve_ballot(pool, manager, &ballot, &team, &request.outcome, &resolution, Some(user_id)).await?;
+        self.ballot_view(pool, ballot_id).await
+    }
+
+    /// Settles every open ballot whose deadline has passed
+    pub async fn close_expired_ballots(&self, pool: &SqlitePool, manager: &crate::ConnectionManager) -> Result<usize, CollaborationError> {
+        let expired: Vec<String> = sqlx::query_scalar(
+            "SELECT id FROM team_ballots WHERE status = 'open' AND datetime(deadline) <= datetime('now')"
+        )
+        .fetch_all(pool)
+        .await?;
+        for ballot_id in &expired {
+            self.settle_ballot(pool, manager, ballot_id, true).await?;
+        }
+        Ok(expired.len())
+    }
+}
+
+// Learning and Adaptation System
+
+#[derive(Clone)]
+pub struct AdaptiveAgent {
+    pub base_agent: Agent,
+    pub learning_engine: LearningEngine,
+    pub performance_history: PerformanceHistory,
+    pub adaptation_strategy: AdaptationStrategy,
+    pub adaptation_metrics: AdaptationMetrics,
+}
+
+#[derive(Clone)]
+pub struct LearningEngine {
+    pub learning_algorithms: Vec<LearningAlgorithm>,
+    pub feedback_processor: FeedbackProcessor,
+    pub pattern_recognizer: PatternRecognizer,
+    pub knowledge_base: KnowledgeBase,
+}
+
+#[derive(Clone)]
+#[allow(clippy::enum_variant_names)]
 pub enum LearningAlgorithm {
     ReinforcementLearning,
     SupervisedLearning,
@@ -561,15 +2225,53 @@ pub struct FeedbackProcessor {
     pub aggregation_method: AggregationMethod,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum FeedbackType {
+    /// 1 to 5 stars
     UserRating,
+    /// 0 to 1, where review approvals are 1 and rejections 0
     TaskCompletion,
+    /// Share of failed attempts, 0 to 1
     ErrorRate,
+    /// Seconds
     ResponseTime,
+    /// USD spent on the work
     CostEfficiency,
 }
 
+impl FeedbackType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FeedbackType::UserRating => "user_rating",
+            FeedbackType::TaskCompletion => "task_completion",
+            FeedbackType::ErrorRate => "error_rate",
+            FeedbackType::ResponseTime => "response_time",
+            FeedbackType::CostEfficiency => "cost_efficiency",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "user_rating" => Some(FeedbackType::UserRating),
+            "task_completion" => Some(FeedbackType::TaskCompletion),
+            "error_rate" => Some(FeedbackType::ErrorRate),
+            "response_time" => Some(FeedbackType::ResponseTime),
+            "cost_efficiency" => Some(FeedbackType::CostEfficiency),
+            _ => None,
+        }
+    }
+
+    /// The pattern the feedback counts towards
+    pub fn pattern_type(&self) -> PatternType {
+        match self {
+            FeedbackType::UserRating | FeedbackType::TaskCompletion | FeedbackType::ResponseTime => PatternType::Performance,
+            FeedbackType::ErrorRate => PatternType::Error,
+            FeedbackType::CostEfficiency => PatternType::Cost,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct ProcessingRule {
     pub rule_type: RuleType,
@@ -600,7 +2302,7 @@ pub enum AggregationMethod {
     ExponentialMovingAverage,
     Median,
     Mode,
-    Custom(Box<dyn Fn(Vec<f64>) -> f64>),
+    Custom(Arc<dyn Fn(Vec<f64>) -> f64 + Send + Sync>),
 }
 
 #[derive(Clone)]
@@ -610,7 +2312,8 @@ pub struct PatternRecognizer {
     pub confidence_threshold: f64,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PatternType {
     Temporal,
     Behavioral,
@@ -619,6 +2322,27 @@ pub enum PatternType {
     Error,
 }
 
+impl PatternType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PatternType::Temporal => "temporal",
+            PatternType::Behavioral => "behavioral",
+            PatternType::Performance => "performance",
+            PatternType::Cost => "cost",
+            PatternType::Error => "error",
+        }
+    }
+
+    /// Name of the metric adaptations are evaluated on
+    pub fn metric_name(&self) -> &'static str {
+        match self {
+            PatternType::Cost => "cost",
+            PatternType::Error => "error_rate",
+            _ => "performance_score",
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct RecognitionAlgorithm {
     pub algorithm_type: AlgorithmType,
@@ -690,7 +2414,8 @@ pub struct PerformanceTrend {
     pub time_period: Duration,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum TrendType {
     Increasing,
     Decreasing,
@@ -699,6 +2424,18 @@ pub enum TrendType {
     Seasonal,
 }
 
+impl TrendType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TrendType::Increasing => "increasing",
+            TrendType::Decreasing => "decreasing",
+            TrendType::Stable => "stable",
+            TrendType::Volatile => "volatile",
+            TrendType::Seasonal => "seasonal",
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct PerformanceAnomaly {
     pub id: String,
@@ -771,13 +2508,18 @@ pub enum TriggerType {
 
 #[derive(Clone)]
 pub struct AdaptationAction {
+    /// The pattern that prompted the action
+    pub pattern_id: Option<String>,
     pub action_type: ActionType,
     pub parameters: HashMap<String, Value>,
     pub expected_impact: ExpectedImpact,
     pub rollback_plan: RollbackPlan,
+    /// Targets are the agent's metrics when the action is applied
+    pub evaluation_criteria: Vec<EvaluationCriteria>,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ActionType {
     ParameterAdjustment,
     ModelSwitch,
@@ -786,7 +2528,30 @@ pub enum ActionType {
     ResourceReallocation,
 }
 
-#[derive(Clone)]
+impl ActionType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActionType::ParameterAdjustment => "parameter_adjustment",
+            ActionType::ModelSwitch => "model_switch",
+            ActionType::ConfigurationChange => "configuration_change",
+            ActionType::SkillUpdate => "skill_update",
+            ActionType::ResourceReallocation => "resource_reallocation",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "parameter_adjustment" => Some(ActionType::ParameterAdjustment),
+            "model_switch" => Some(ActionType::ModelSwitch),
+            "configuration_change" => Some(ActionType::ConfigurationChange),
+            "skill_update" => Some(ActionType::SkillUpdate),
+            "resource_reallocation" => Some(ActionType::ResourceReallocation),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExpectedImpact {
     pub performance_change: f64,
     pub cost_change: f64,
@@ -794,21 +2559,39 @@ pub struct ExpectedImpact {
     pub confidence: f64,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RollbackPlan {
     pub rollback_conditions: Vec<String>,
     pub rollback_actions: Vec<String>,
+    /// How long the change runs before it is evaluated
     pub timeout: Duration,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EvaluationCriteria {
     pub metric_name: String,
     pub target_value: f64,
+    /// Share of the target the metric may move the wrong way
     pub tolerance: f64,
     pub weight: f64,
 }
 
+impl EvaluationCriteria {
+    fn new(metric_name: &str, tolerance: f64, weight: f64) -> Self {
+        Self { metric_name: metric_name.to_string(), target_value: 0.0, tolerance, weight }
+    }
+
+    /// Whether `value` is worse than the target by more than the tolerance.
+    /// Costs and error rates are better lower, everything else higher.
+    pub fn regressed(&self, value: f64) -> bool {
+        let margin = (self.target_value.abs() * self.tolerance).max(0.01);
+        match self.metric_name.as_str() {
+            "cost" | "error_rate" => value > self.target_value + margin,
+            _ => value < self.target_value - margin,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AdaptationMetrics {
     pub adaptations_performed: u64,
@@ -818,6 +2601,182 @@ pub struct AdaptationMetrics {
     pub adaptation_frequency: f64,
 }
 
+/// Feedback reduced to one value that can be compared over time
+#[derive(Debug, Clone)]
+pub struct ProcessedFeedback {
+    pub feedback_type: FeedbackType,
+    pub pattern_type: PatternType,
+    /// A 0-1 score for performance feedback, otherwise the cost or error rate as given
+    pub value: f64,
+    pub weight: f64,
+    pub timestamp: chrono::DateTime<Utc>,
+}
+
+/// Fewer feedback than this says nothing about a trend
+const MIN_PATTERN_SAMPLES: usize = 5;
+
+impl FeedbackProcessor {
+    pub fn process_feedback(&self, feedback: &Feedback) -> Result<ProcessedFeedback, String> {
+        let feedback_type = feedback.feedback_type;
+        if !self.feedback_types.contains(&feedback_type) {
+            return Err(format!("{} feedback is not processed", feedback_type.as_str()));
+        }
+        let rating = feedback.rating.filter(|r| r.is_finite()).ok_or("A rating is required")?;
+        let value = match feedback_type {
+            FeedbackType::UserRating if (1.0..=5.0).contains(&rating) => (rating - 1.0) / 4.0,
+            FeedbackType::TaskCompletion | FeedbackType::ErrorRate if (0.0..=1.0).contains(&rating) => rating,
+            // A minute scores 0.5, quicker answers approach 1
+            FeedbackType::ResponseTime if rating >= 0.0 => 60.0 / (60.0 + rating),
+            FeedbackType::CostEfficiency if rating >= 0.0 => rating,
+            _ => return Err(format!("A rating of {} is out of range for {} feedback", rating, feedback_type.as_str())),
+        };
+
+        let mut weight = 1.0;
+        for rule in self.processing_rules.iter().filter(|rule| rule.condition == feedback_type.as_str()) {
+            if matches!(rule.action, ProcessingAction::Exclude) {
+                return Err(format!("{} feedback is excluded", feedback_type.as_str()));
+            }
+            weight *= rule.weight;
+        }
+
+        Ok(ProcessedFeedback {
+            feedback_type,
+            pattern_type: feedback_type.pattern_type(),
+            value,
+            weight,
+            timestamp: feedback.timestamp,
+        })
+    }
+
+    /// Combines `(value, weight)` pairs, oldest first, with the aggregation method
+    pub fn aggregate(&self, values: &[(f64, f64)]) -> Option<f64> {
+        let first = values.first()?.0;
+        let aggregated = match &self.aggregation_method {
+            AggregationMethod::WeightedAverage => {
+                let total: f64 = values.iter().map(|(_, weight)| weight).sum();
+                if total <= 0.0 {
+                    return None;
+                }
+                values.iter().map(|(value, weight)| value * weight).sum::<f64>() / total
+            }
+            AggregationMethod::ExponentialMovingAverage => {
+                values[1..].iter().fold(first, |average, (value, _)| average + 0.3 * (value - average))
+            }
+            AggregationMethod::Median => {
+                let mut sorted: Vec<f64> = values.iter().map(|(value, _)| *value).collect();
+                sorted.sort_by(|a, b| a.total_cmp(b));
+                let middle = sorted.len() / 2;
+                if sorted.len().is_multiple_of(2) {
+                    (sorted[middle - 1] + sorted[middle]) / 2.0
+                } else {
+                    sorted[middle]
+                }
+            }
+            AggregationMethod::Mode => {
+                // Bucketed to two decimals, ties going to the lower value
+                let mut counts: HashMap<i64, usize> = HashMap::new();
+                for (value, _) in values {
+                    *counts.entry((value * 100.0).round() as i64).or_default() += 1;
+                }
+                let (bucket, _) = counts.into_iter().max_by_key(|(bucket, count)| (*count, -bucket))?;
+                bucket as f64 / 100.0
+            }
+            AggregationMethod::Custom(aggregate) => aggregate(values.iter().map(|(value, _)| *value).collect()),
+        };
+        Some(aggregated)
+    }
+}
+
+impl KnowledgeBase {
+    /// Keeps a running score per kind of feedback
+    pub fn update_from_feedback(&mut self, feedback: &ProcessedFeedback) {
+        let score = self
+            .confidence_scores
+            .entry(feedback.feedback_type.as_str().to_string())
+            .or_insert(feedback.value);
+        *score = 0.8 * *score + 0.2 * feedback.value;
+    }
+}
+
+impl PatternRecognizer {
+    /// Fits a line through each kind of feedback, oldest first, and describes the
+    /// trend it shows. Callers decide which patterns are confident enough to act on.
+    pub fn recognize_patterns(&self, history: &[ProcessedFeedback]) -> Vec<Pattern> {
+        let mut patterns = Vec::new();
+        for pattern_type in &self.pattern_types {
+            let mut points: Vec<&ProcessedFeedback> = history.iter().filter(|f| f.pattern_type == *pattern_type).collect();
+            if points.len() < MIN_PATTERN_SAMPLES {
+                continue;
+            }
+            points.sort_by_key(|f| f.timestamp);
+
+            let n = points.len() as f64;
+            let mean_x = (n - 1.0) / 2.0;
+            let mean_y = points.iter().map(|f| f.value).sum::<f64>() / n;
+            let (mut sxy, mut sxx, mut syy) = (0.0, 0.0, 0.0);
+            for (i, point) in points.iter().enumerate() {
+                let (dx, dy) = (i as f64 - mean_x, point.value - mean_y);
+                sxy += dx * dy;
+                sxx += dx * dx;
+                syy += dy * dy;
+            }
+            let slope = sxy / sxx;
+            let r_squared = if syy > 0.0 { sxy * sxy / (sxx * syy) } else { 1.0 };
+
+            // Scores move on a 0-1 scale; costs and error rates are compared to their mean
+            let change = slope * (n - 1.0);
+            let relative = match pattern_type {
+                PatternType::Performance => change,
+                _ => change / mean_y.abs().max(0.01),
+            };
+            let trend_type = if relative.abs() < 0.1 {
+                TrendType::Stable
+            } else if r_squared < 0.3 {
+                TrendType::Volatile
+            } else if slope > 0.0 {
+                TrendType::Increasing
+            } else {
+                TrendType::Decreasing
+            };
+
+            patterns.push(Pattern {
+                id: Uuid::new_v4().to_string(),
+                pattern_type: *pattern_type,
+                trend_type,
+                slope,
+                samples: points.len(),
+                // Lines through a handful of points are trusted less
+                confidence: (r_squared * n / (n + 2.0)).clamp(0.0, 1.0),
+                description: format!(
+                    "{} {} ({:+.2} over the last {} feedback)",
+                    pattern_type.metric_name(),
+                    trend_type.as_str(),
+                    change,
+                    points.len()
+                ),
+                detected_at: Utc::now(),
+            });
+        }
+        patterns
+    }
+}
+
+/// What one piece of feedback taught an agent
+pub struct LearningOutcome {
+    pub processed: ProcessedFeedback,
+    pub patterns: Vec<Pattern>,
+    pub adaptations: Vec<AdaptationAction>,
+}
+
+const THINKING_LEVELS: [ThinkingLevel; 6] = [
+    ThinkingLevel::Off,
+    ThinkingLevel::Minimal,
+    ThinkingLevel::Low,
+    ThinkingLevel::Medium,
+    ThinkingLevel::High,
+    ThinkingLevel::XHigh,
+];
+
 impl AdaptiveAgent {
     pub fn new(base_agent: Agent) -> Self {
         Self {
@@ -832,6 +2791,8 @@ impl AdaptiveAgent {
                         FeedbackType::UserRating,
                         FeedbackType::TaskCompletion,
                         FeedbackType::ErrorRate,
+                        FeedbackType::ResponseTime,
+                        FeedbackType::CostEfficiency,
                     ],
                     processing_rules: Vec::new(),
                     aggregation_method: AggregationMethod::WeightedAverage,
@@ -843,7 +2804,7 @@ impl AdaptiveAgent {
                         PatternType::Error,
                     ],
                     recognition_algorithms: Vec::new(),
-                    confidence_threshold: 0.8,
+                    confidence_threshold: 0.6,
                 },
                 knowledge_base: KnowledgeBase {
                     facts: HashMap::new(),
@@ -859,7 +2820,7 @@ impl AdaptiveAgent {
                 benchmarks: Vec::new(),
             },
             adaptation_strategy: AdaptationStrategy {
-                strategy_type: StrategyType::Hybrid,
+                strategy_type: AdaptationStrategyType::Hybrid,
                 adaptation_triggers: Vec::new(),
                 adaptation_actions: Vec::new(),
                 evaluation_criteria: Vec::new(),
@@ -874,138 +2835,207 @@ impl AdaptiveAgent {
         }
     }
 
-    #[instrument(skip(self, feedback))]
-    pub async fn learn_from_feedback(&mut self, feedback: &Feedback) -> Result<(), String> {
+    /// Processes new feedback together with the agent's earlier feedback and proposes
+    /// adaptations for the trends that shows. Nothing is applied here.
+    #[instrument(skip(self, feedback, history))]
+    pub fn learn_from_feedback(&mut self, feedback: &Feedback, history: &[ProcessedFeedback]) -> Result<LearningOutcome, String> {
         // Process feedback
-        let processed_feedback = self.learning_engine.feedback_processor
-            .process_feedback(feedback).await?;
-        
+        let processed = self.learning_engine.feedback_processor.process_feedback(feedback)?;
+
         // Update knowledge base
-        self.learning_engine.knowledge_base
-            .update_from_feedback(&processed_feedback).await?;
-        
+        self.learning_engine.knowledge_base.update_from_feedback(&processed);
+
         // Recognize patterns
-        let patterns = self.learning_engine.pattern_recognizer
-            .recognize_patterns(&processed_feedback).await?;
-        
+        let mut window = history.to_vec();
+        window.push(processed.clone());
+        let patterns = self.learning_engine.pattern_recognizer.recognize_patterns(&window);
+
         // Generate adaptations if needed
-        if self.should_adapt(&patterns).await? {
-            let adaptations = self.generate_adaptations(&patterns).await?;
-            self.apply_adaptations(adaptations).await?;
-        }
-        
-        Ok(())
+        let adaptations = if self.should_adapt(&patterns) {
+            self.generate_adaptations(&patterns)
+        } else {
+            Vec::new()
+        };
+
+        Ok(LearningOutcome { processed, patterns, adaptations })
     }
 
-    async fn should_adapt(&self, patterns: &[Pattern]) -> Result<bool, String> {
-        for pattern in patterns {
-            if pattern.confidence > self.learning_engine.pattern_recognizer.confidence_threshold {
-                return Ok(true);
-            }
-        }
-        Ok(false)
+    fn should_adapt(&self, patterns: &[Pattern]) -> bool {
+        patterns
+            .iter()
+            .any(|pattern| pattern.confidence > self.learning_engine.pattern_recognizer.confidence_threshold)
     }
 
-    async fn generate_adaptations(&self, patterns: &[Pattern]) -> Result<Vec<AdaptationAction>, String> {
+    /// The first fallback model that is not the one the agent runs on
+    fn fallback_model(&self) -> Option<String> {
+        let agent = &self.base_agent;
+        let current = agent.current_model.as_ref().or(agent.primary_model.as_ref());
+        // Stored either as one model or as a JSON array of them
+        let fallbacks = agent
+            .fallback_model
+            .as_deref()
+            .map(|f| serde_json::from_str::<Vec<String>>(f).unwrap_or_else(|_| vec![f.to_string()]))
+            .unwrap_or_default();
+        fallbacks.into_iter().find(|model| !model.trim().is_empty() && Some(model) != current)
+    }
+
+    fn generate_adaptations(&self, patterns: &[Pattern]) -> Vec<AdaptationAction> {
         let mut adaptations = Vec::new();
-        
-        for pattern in patterns {
-            match pattern.pattern_type {
-                PatternType::Performance => {
-                    if pattern.trend_type == TrendType::Decreasing {
-                        adaptations.push(AdaptationAction {
-                            action_type: ActionType::ParameterAdjustment,
-                            parameters: HashMap::from([
-                                ("thinking_level".to_string(), serde_json::Value::String("high".to_string())),
-                                ("max_concurrent".to_string(), serde_json::Value::Number(5.into())),
-                            ]),
-                            expected_impact: ExpectedImpact {
-                                performance_change: 0.2,
-                                cost_change: 0.1,
-                                reliability_change: 0.15,
-                                confidence: 0.8,
-                            },
-                            rollback_plan: RollbackPlan {
-                                rollback_conditions: vec!["performance_degradation".to_string()],
-                                rollback_actions: vec!["restore_previous_parameters".to_string()],
-                                timeout: Duration::from_secs(300),
-                            },
-                        });
-                    }
+        let threshold = self.learning_engine.pattern_recognizer.confidence_threshold;
+
+        for pattern in patterns.iter().filter(|pattern| pattern.confidence > threshold) {
+            match (pattern.pattern_type, pattern.trend_type) {
+                (PatternType::Performance, TrendType::Decreasing) => {
+                    // One step more thinking than the agent uses now
+                    let current = self.base_agent.thinking_default.unwrap_or(ThinkingLevel::Medium);
+                    let Some(next) = THINKING_LEVELS.iter().skip_while(|level| **level != current).nth(1) else {
+                        continue;
+                    };
+                    adaptations.push(AdaptationAction {
+                        pattern_id: Some(pattern.id.clone()),
+                        action_type: ActionType::ParameterAdjustment,
+                        parameters: HashMap::from([
+                            ("thinkingDefault".to_string(), serde_json::to_value(next).unwrap_or_default()),
+                        ]),
+                        expected_impact: ExpectedImpact {
+                            performance_change: 0.2,
+                            cost_change: 0.1,
+                            reliability_change: 0.15,
+                            confidence: pattern.confidence,
+                        },
+                        rollback_plan: RollbackPlan {
+                            rollback_conditions: vec!["performance_degradation".to_string()],
+                            rollback_actions: vec!["restore_previous_parameters".to_string()],
+                            timeout: Duration::from_secs(24 * 3600),
+                        },
+                        evaluation_criteria: vec![
+                            EvaluationCriteria::new("performance_score", 0.05, 0.7),
+                            EvaluationCriteria::new("cost", 0.5, 0.3),
+                        ],
+                    });
                 }
-                PatternType::Cost => {
-                    if pattern.trend_type == TrendType::Increasing {
-                        adaptations.push(AdaptationAction {
-                            action_type: ActionType::ModelSwitch,
-                            parameters: HashMap::from([
-                                ("primary_model".to_string(), serde_json::Value::String("gemini-pro".to_string())),
-                            ]),
-                            expected_impact: ExpectedImpact {
-                                performance_change: -0.1,
-                                cost_change: -0.3,
-                                reliability_change: 0.0,
-                                confidence: 0.7,
-                            },
-                            rollback_plan: RollbackPlan {
-                                rollback_conditions: vec!["quality_degradation".to_string()],
-                                rollback_actions: vec!["restore_previous_model".to_string()],
-                                timeout: Duration::from_secs(600),
-                            },
-                        });
-                    }
+                (PatternType::Cost, TrendType::Increasing) => {
+                    let Some(model) = self.fallback_model() else { continue };
+                    adaptations.push(AdaptationAction {
+                        pattern_id: Some(pattern.id.clone()),
+                        action_type: ActionType::ModelSwitch,
+                        parameters: HashMap::from([
+                            ("primary_model".to_string(), Value::String(model)),
+                        ]),
+                        expected_impact: ExpectedImpact {
+                            performance_change: -0.1,
+                            cost_change: -0.3,
+                            reliability_change: 0.0,
+                            confidence: pattern.confidence,
+                        },
+                        rollback_plan: RollbackPlan {
+                            rollback_conditions: vec!["quality_degradation".to_string()],
+                            rollback_actions: vec!["restore_previous_model".to_string()],
+                            timeout: Duration::from_secs(24 * 3600),
+                        },
+                        evaluation_criteria: vec![
+                            EvaluationCriteria::new("performance_score", 0.1, 0.6),
+                            EvaluationCriteria::new("cost", 0.1, 0.4),
+                        ],
+                    });
+                }
+                (PatternType::Error, TrendType::Increasing) => {
+                    let Some(model) = self.fallback_model() else { continue };
+                    adaptations.push(AdaptationAction {
+                        pattern_id: Some(pattern.id.clone()),
+                        action_type: ActionType::ModelSwitch,
+                        parameters: HashMap::from([
+                            ("primary_model".to_string(), Value::String(model)),
+                        ]),
+                        expected_impact: ExpectedImpact {
+                            performance_change: 0.0,
+                            cost_change: 0.0,
+                            reliability_change: 0.25,
+                            confidence: pattern.confidence,
+                        },
+                        rollback_plan: RollbackPlan {
+                            rollback_conditions: vec!["error_rate_increase".to_string()],
+                            rollback_actions: vec!["restore_previous_model".to_string()],
+                            timeout: Duration::from_secs(24 * 3600),
+                        },
+                        evaluation_criteria: vec![
+                            EvaluationCriteria::new("error_rate", 0.1, 0.6),
+                            EvaluationCriteria::new("performance_score", 0.1, 0.4),
+                        ],
+                    });
                 }
                 _ => {}
             }
         }
-        
-        Ok(adaptations)
+
+        adaptations
     }
 
-    async fn apply_adaptations(&mut self, adaptations: Vec<AdaptationAction>) -> Result<(), String> {
-        for adaptation in adaptations {
-            match adaptation.action_type {
-                ActionType::ParameterAdjustment => {
-                    self.apply_parameter_adjustment(&adaptation.parameters).await?;
-                }
-                ActionType::ModelSwitch => {
-                    self.apply_model_switch(&adaptation.parameters).await?;
-                }
-                ActionType::ConfigurationChange => {
-                    self.apply_configuration_change(&adaptation.parameters).await?;
-                }
-                _ => {}
-            }
-            
-            self.adaptation_metrics.adaptations_performed += 1;
+    /// Applies an approved adaptation to the agent's OpenClaw configuration
+    pub fn apply_adaptation(&mut self, adaptation: &AdaptationAction, config: &mut OpenClawAgentConfig) -> Result<(), String> {
+        match adaptation.action_type {
+            ActionType::ParameterAdjustment => self.apply_parameter_adjustment(&adaptation.parameters, config)?,
+            ActionType::ModelSwitch => self.apply_model_switch(&adaptation.parameters, config)?,
+            ActionType::ConfigurationChange => self.apply_configuration_change(&adaptation.parameters, config)?,
+            other => return Err(format!("{} adaptations cannot be applied to a configuration", other.as_str())),
         }
-        
+
+        self.adaptation_metrics.adaptations_performed += 1;
         Ok(())
     }
 
-    async fn apply_parameter_adjustment(&mut self, parameters: &HashMap<String, Value>) -> Result<(), String> {
-        // This would update the agent's configuration
+    /// Merges the parameters into the config's `params`
+    fn apply_parameter_adjustment(&mut self, parameters: &HashMap<String, Value>, config: &mut OpenClawAgentConfig) -> Result<(), String> {
         info!("Applying parameter adjustments: {:?}", parameters);
+        let params = config.params.get_or_insert_with(|| Value::Object(Default::default()));
+        let Some(params) = params.as_object_mut() else {
+            return Err("The agent's params are not an object".to_string());
+        };
+        for (key, value) in parameters {
+            params.insert(key.clone(), value.clone());
+        }
         Ok(())
     }
 
-    async fn apply_model_switch(&mut self, parameters: &HashMap<String, Value>) -> Result<(), String> {
-        // This would switch the agent's model
-        if let Some(model) = parameters.get("primary_model") {
-            info!("Switching to model: {}", model);
+    /// Makes `primary_model` the primary, keeping the old primary as the first fallback
+    fn apply_model_switch(&mut self, parameters: &HashMap<String, Value>, config: &mut OpenClawAgentConfig) -> Result<(), String> {
+        let model = parameters
+            .get("primary_model")
+            .and_then(|model| model.as_str())
+            .filter(|model| !model.trim().is_empty())
+            .ok_or("primary_model is required for a model switch")?;
+        info!("Switching to model: {}", model);
+
+        let current = config.model.get_or_insert(AgentModelConfig { primary: None, fallbacks: None });
+        let mut fallbacks: Vec<String> = Vec::new();
+        for fallback in current.primary.take().into_iter().chain(current.fallbacks.take().unwrap_or_default()) {
+            if fallback != model && !fallbacks.contains(&fallback) {
+                fallbacks.push(fallback);
+            }
         }
+        current.primary = Some(model.to_string());
+        current.fallbacks = (!fallbacks.is_empty()).then_some(fallbacks);
         Ok(())
     }
 
-    async fn apply_configuration_change(&mut self, parameters: &HashMap<String, Value>) -> Result<(), String> {
-        // This would update the agent's configuration
+    /// Replaces top-level fields of the config, e.g. `{"skills": [...]}`
+    fn apply_configuration_change(&mut self, parameters: &HashMap<String, Value>, config: &mut OpenClawAgentConfig) -> Result<(), String> {
         info!("Applying configuration changes: {:?}", parameters);
+        if parameters.contains_key("id") {
+            return Err("An adaptation cannot change the agent's id".to_string());
+        }
+        let mut value = serde_json::to_value(&*config).map_err(|e| e.to_string())?;
+        for (key, change) in parameters {
+            value[key.as_str()] = change.clone();
+        }
+        *config = serde_json::from_value(value).map_err(|e| format!("Invalid configuration change: {}", e))?;
         Ok(())
     }
 }
 
 // Supporting structures
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Feedback {
     pub id: String,
     pub agent_id: String,
@@ -1013,14 +3043,20 @@ pub struct Feedback {
     pub rating: Option<f64>,
     pub comment: Option<String>,
     pub context: Option<String>,
+    pub task_id: Option<String>,
+    /// `user` or `review`
+    pub source: String,
     pub timestamp: chrono::DateTime<Utc>,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Pattern {
     pub id: String,
     pub pattern_type: PatternType,
     pub trend_type: TrendType,
+    /// Change per feedback
+    pub slope: f64,
+    pub samples: usize,
     pub confidence: f64,
     pub description: String,
     pub detected_at: chrono::DateTime<Utc>,
@@ -1031,81 +3067,909 @@ pub struct Pattern {
 #[derive(Deserialize)]
 pub struct CreateTeamRequest {
     pub name: String,
+    /// Agents joining as executors
+    #[serde(default)]
     pub member_ids: Vec<String>,
+    /// Agents joining with an explicit role
+    #[serde(default)]
+    pub members: Vec<TeamMemberRequest>,
+    pub team_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateTeamRequest {
+    pub name: Option<String>,
     pub team_type: Option<String>,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct TeamMemberRequest {
+    pub agent_id: String,
+    pub role_type: Option<RoleType>,
+    pub responsibilities: Option<Vec<String>>,
+    pub permissions: Option<Vec<String>>,
+    pub reporting_to: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateTeamMemberRequest {
+    pub role_type: Option<RoleType>,
+    pub responsibilities: Option<Vec<String>>,
+    pub permissions: Option<Vec<String>>,
+    /// An empty string clears it
+    pub reporting_to: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AccessControlRequest {
+    #[serde(default)]
+    pub permissions: Vec<String>,
+    #[serde(default)]
+    pub restrictions: Vec<String>,
+    pub expiry_time: Option<chrono::DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+pub struct WriteContextRequest {
+    pub value: Value,
+    pub expected_version: Option<u32>,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteContextQuery {
+    pub expected_version: Option<u32>,
+}
+
+#[derive(Deserialize)]
+pub struct OpenBallotRequest {
+    pub question: String,
+    /// Ignored for review ballots, which are always approved/rejected
+    pub options: Option<Vec<String>>,
+    /// `decision` (default) or `review`
+    pub kind: Option<String>,
+    pub task_id: Option<String>,
+    pub conflict_type: Option<ConflictType>,
+    /// The remaining fields default to the conflict resolver's voting mechanism
+    pub voting_type: Option<VotingType>,
+    pub quorum: Option<f64>,
+    pub tie_breaker: Option<String>,
+    pub deadline_minutes: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct CastVoteRequest {
+    pub choice: String,
+    pub comment: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ResolveBallotRequest {
+    pub outcome: String,
+    pub note: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct BallotQuery {
+    pub status: Option<String>,
+}
+
 #[derive(Deserialize)]
 pub struct DelegateTaskRequest {
-    pub team_id: String,
     pub task_id: String,
-    pub delegation_strategy: Option<String>,
+    /// Defaults to `single`
+    pub mode: Option<DelegationMode>,
+    /// Defaults to the delegation engine's algorithm
+    #[serde(alias = "delegation_strategy")]
+    pub algorithm: Option<DelegationAlgorithm>,
+    /// Fan-out only: sub-task titles, handed out in ranking order. Without them
+    /// every eligible member (up to `max_agents`) gets one. More sub-tasks than the
+    /// members have free slots are refused.
+    pub subtasks: Option<Vec<String>>,
+    /// Fan-out only: how many of the top-ranked members share the sub-tasks
+    pub max_agents: Option<usize>,
+    /// Spawn an OpenClaw session for each assignment
+    #[serde(default)]
+    pub route: bool,
+}
+
+fn validate_context_permissions(permissions: &[String]) -> Result<(), CollaborationError> {
+    match permissions.iter().find(|p| !matches!(p.as_str(), "read" | "write" | "delete")) {
+        Some(invalid) => Err(CollaborationError::Invalid(format!(
+            "Unknown context permission '{}', expected read, write or delete", invalid
+        ))),
+        None => Ok(()),
+    }
 }
 
+impl TeamMemberRequest {
+    fn executor(agent_id: &str) -> Self {
+        Self {
+            agent_id: agent_id.to_string(),
+            role_type: None,
+            responsibilities: None,
+            permissions: None,
+            reporting_to: None,
+        }
+    }
 
+    fn role(&self) -> TeamRole {
+        TeamRole {
+            agent_id: self.agent_id.clone(),
+            role_type: self.role_type.unwrap_or(RoleType::Executor),
+            responsibilities: self.responsibilities.clone().unwrap_or_default(),
+            permissions: self.permissions.clone().unwrap_or_default(),
+            reporting_to: self.reporting_to.clone(),
+        }
+    }
 
-pub async fn create_collaboration_team(
-    State(app_state): State<crate::AppState>,
-    Json(request): Json<CreateTeamRequest>,
-) -> impl IntoResponse {
-    let collaboration = AgentCollaboration::new();
-    
-    // Fetch agents from database
-    let mut members = Vec::new();
-    for member_id in request.member_ids {
-        if let Ok(Some(agent)) = sqlx::query_as::<sqlx::Sqlite, Agent>(
-            "SELECT * FROM agents WHERE id = ? AND is_active = 1"
+    /// The agent must exist and `reporting_to` must be another member of the team
+    async fn validate(&self, pool: &SqlitePool, member_ids: &[&str]) -> Result<(), CollaborationError> {
+        let exists: Option<String> = sqlx::query_scalar("SELECT id FROM agents WHERE id = ? AND is_deleted = 0")
+            .bind(&self.agent_id)
+            .fetch_optional(pool)
+            .await?;
+        if exists.is_none() {
+            return Err(CollaborationError::Invalid(format!("Agent '{}' not found", self.agent_id)));
+        }
+        if let Some(permissions) = &self.permissions {
+            validate_context_permissions(permissions)?;
+        }
+        if let Some(reporting_to) = &self.reporting_to
+            && (reporting_to == &self.agent_id || !member_ids.contains(&reporting_to.as_str())) {
+                return Err(CollaborationError::Invalid(format!(
+                    "{} must report to another member of the team", self.agent_id
+                )));
+            }
+        Ok(())
+    }
+
+    async fn insert(&self, tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>, team_id: &str) -> Result<(), sqlx::Error> {
+        let role = self.role();
+        sqlx::query(
+            "INSERT INTO agent_team_members (team_id, agent_id, role_type, responsibilities, permissions, reporting_to)
+             VALUES (?, ?, ?, ?, ?, ?)"
         )
-        .bind(member_id)
-        .fetch_optional(&app_state.pool)
-        .await {
-            members.push(agent);
+        .bind(team_id)
+        .bind(&role.agent_id)
+        .bind(role.role_type.as_str())
+        .bind(serde_json::to_string(&role.responsibilities).unwrap_or_else(|_| "[]".to_string()))
+        .bind(serde_json::to_string(&role.permissions).unwrap_or_else(|_| "[]".to_string()))
+        .bind(&role.reporting_to)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct TeamRow {
+    id: String,
+    name: String,
+    team_type: Option<String>,
+    context_version: i64,
+    created_by: Option<String>,
+    created_at: chrono::DateTime<Utc>,
+}
+
+#[derive(sqlx::FromRow)]
+struct TeamMemberRow {
+    agent_id: String,
+    role_type: String,
+    responsibilities: String,
+    permissions: String,
+    reporting_to: Option<String>,
+}
+
+impl TeamMemberRow {
+    fn into_role(self) -> TeamRole {
+        TeamRole {
+            role_type: RoleType::parse(&self.role_type).unwrap_or(RoleType::Executor),
+            responsibilities: serde_json::from_str(&self.responsibilities).unwrap_or_default(),
+            permissions: serde_json::from_str(&self.permissions).unwrap_or_default(),
+            reporting_to: self.reporting_to,
+            agent_id: self.agent_id,
         }
     }
-    
-    match collaboration.create_team(request.name, members).await {
-        Ok(team_id) => Json(serde_json::json!({
-            "status": "success",
-            "team_id": team_id,
-            "message": "Team created successfully"
-        })),
-        Err(e) => Json(serde_json::json!({
-            "status": "error",
-            "message": e
-        })),
+}
+
+#[derive(sqlx::FromRow)]
+struct AccessControlRow {
+    agent_id: String,
+    permissions: String,
+    restrictions: String,
+    expiry_time: Option<chrono::DateTime<Utc>>,
+}
+
+impl AccessControlRow {
+    fn into_access_control(self) -> AccessControl {
+        AccessControl {
+            permissions: serde_json::from_str(&self.permissions).unwrap_or_default(),
+            restrictions: serde_json::from_str(&self.restrictions).unwrap_or_default(),
+            expiry_time: self.expiry_time,
+            agent_id: self.agent_id,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct ContextEntryRow {
+    key: String,
+    value: String,
+    version: i64,
+    modified_by: String,
+    modified_at: chrono::DateTime<Utc>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ContextHistoryRow {
+    key: String,
+    value: Option<String>,
+    version: i64,
+    modified_by: String,
+    modified_at: chrono::DateTime<Utc>,
+}
+
+/// One version of a shared-context key; `value` is null for a deletion
+#[derive(Debug, Serialize)]
+pub struct ContextEntry {
+    pub key: String,
+    pub value: Option<Value>,
+    pub version: u32,
+    pub modified_by: String,
+    pub modified_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct TeamMemberView {
+    pub agent_id: String,
+    pub name: String,
+    pub status: AgentStatus,
+    #[serde(flatten)]
+    pub role: TeamRole,
+    pub access_control: Option<AccessControl>,
+}
+
+#[derive(Serialize)]
+pub struct TeamView {
+    pub id: String,
+    pub name: String,
+    pub team_type: Option<String>,
+    pub members: Vec<TeamMemberView>,
+    pub context_version: u32,
+    pub context_keys: usize,
+    /// Open tasks delegated to the team
+    pub active_tasks: Vec<String>,
+    pub created_by: Option<String>,
+    pub created_at: chrono::DateTime<Utc>,
+}
+
+impl From<&AgentTeam> for TeamView {
+    fn from(team: &AgentTeam) -> Self {
+        Self {
+            id: team.id.clone(),
+            name: team.name.clone(),
+            team_type: team.team_type.clone(),
+            members: team
+                .members
+                .iter()
+                .filter_map(|agent| {
+                    team.roles.get(&agent.id).map(|role| TeamMemberView {
+                        agent_id: agent.id.clone(),
+                        name: agent.name.clone(),
+                        status: agent.status,
+                        role: role.clone(),
+                        access_control: team.shared_context.access_controls.get(&agent.id).cloned(),
+                    })
+                })
+                .collect(),
+            context_version: team.shared_context.version,
+            context_keys: team.shared_context.data.len(),
+            active_tasks: team.active_tasks.clone(),
+            created_by: team.created_by.clone(),
+            created_at: team.created_at,
+        }
     }
 }
 
+/// Who is touching a team's shared context. Users are checked against RBAC;
+/// agents authenticate with `x-agent-key` and are held to the team's access rules.
+enum ContextCaller {
+    User(Box<User>),
+    Agent(String),
+}
+
+impl ContextCaller {
+    fn id(&self) -> &str {
+        match self {
+            ContextCaller::User(user) => &user.id,
+            ContextCaller::Agent(agent_id) => agent_id,
+        }
+    }
+
+    fn check(&self, team: &AgentTeam, key: Option<&str>, action: &str) -> Result<(), CollaborationError> {
+        match self {
+            ContextCaller::User(_) => Ok(()),
+            ContextCaller::Agent(agent_id) => team.check_context_access(agent_id, key, action),
+        }
+    }
+}
+
+async fn context_caller(pool: &SqlitePool, headers: &HeaderMap, action: &str) -> Result<ContextCaller, (StatusCode, String)> {
+    let Some(agent_key) = headers.get("x-agent-key").and_then(|v| v.to_str().ok()) else {
+        let permission = if action == "read" { "read" } else { "write" };
+        let user = crate::rbac::authorized_user(pool, headers, "agents", permission).await?;
+        return Ok(ContextCaller::User(Box::new(user)));
+    };
+
+    let agent_id: Option<String> = sqlx::query_scalar("SELECT id FROM agents WHERE token = ? AND is_deleted = 0")
+        .bind(agent_key)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    agent_id
+        .map(ContextCaller::Agent)
+        .ok_or((StatusCode::UNAUTHORIZED, "Unknown agent key".to_string()))
+}
+
+/// Teams are audited as system entities, which is what the audit log's entity types allow
+async fn audit_team(
+    pool: &SqlitePool,
+    team_id: &str,
+    action: &str,
+    old: Option<&TeamView>,
+    new: Option<&TeamView>,
+    metadata: Option<Value>,
+    user: &User,
+) {
+    let old = old.and_then(|team| serde_json::to_string(team).ok());
+    let new = new.and_then(|team| serde_json::to_string(team).ok());
+    let metadata = metadata.map(|m| m.to_string());
+    if let Err(e) = crate::audit::AuditService::log_entity_event(
+        pool,
+        "system",
+        &format!("team:{}", team_id),
+        action,
+        old.as_deref(),
+        new.as_deref(),
+        Some(&user.id),
+        Some(&user.role),
+        None,
+        None,
+        None,
+        metadata.as_deref(),
+    ).await {
+        warn!("Failed to audit team {} {}: {}", action, team_id, e);
+    }
+}
+
+pub async fn list_collaboration_teams(
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&app_state.pool, &headers, "agents", "read").await?;
+    let teams: Vec<TeamView> = app_state.collaboration.teams().await.iter().map(TeamView::from).collect();
+    Ok(Json(teams))
+}
+
+pub async fn create_collaboration_team(
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+    Json(request): Json<CreateTeamRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&app_state.pool, &headers, "agents", "write").await?;
+    let team = app_state.collaboration.create_team(&app_state.pool, &request, &user.id).await?;
+    let view = TeamView::from(&team);
+    audit_team(&app_state.pool, &team.id, "create", None, Some(&view), None, &user).await;
+    Ok((StatusCode::CREATED, Json(view)))
+}
+
+pub async fn get_collaboration_team(
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&app_state.pool, &headers, "agents", "read").await?;
+    let team = app_state.collaboration.team(&id).await?;
+    Ok(Json(TeamView::from(&team)))
+}
+
+pub async fn update_collaboration_team(
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(request): Json<UpdateTeamRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&app_state.pool, &headers, "agents", "write").await?;
+    let old = TeamView::from(&app_state.collaboration.team(&id).await?);
+    let team = app_state.collaboration.update_team(&app_state.pool, &id, &request).await?;
+    let view = TeamView::from(&team);
+    audit_team(&app_state.pool, &id, "update", Some(&old), Some(&view), None, &user).await;
+    Ok(Json(view))
+}
+
+pub async fn delete_collaboration_team(
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&app_state.pool, &headers, "agents", "delete").await?;
+    let old = TeamView::from(&app_state.collaboration.team(&id).await?);
+    app_state.collaboration.delete_team(&app_state.pool, &id).await?;
+    audit_team(&app_state.pool, &id, "delete", Some(&old), None, None, &user).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn add_team_member(
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(request): Json<TeamMemberRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&app_state.pool, &headers, "agents", "write").await?;
+    let old = TeamView::from(&app_state.collaboration.team(&id).await?);
+    let team = app_state.collaboration.add_member(&app_state.pool, &id, &request).await?;
+    let view = TeamView::from(&team);
+    audit_team(&app_state.pool, &id, "update", Some(&old), Some(&view), Some(serde_json::json!({ "add_member": &request.agent_id })), &user).await;
+    Ok((StatusCode::CREATED, Json(view)))
+}
+
+pub async fn update_team_member(
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+    Path((id, agent_id)): Path<(String, String)>,
+    Json(request): Json<UpdateTeamMemberRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&app_state.pool, &headers, "agents", "write").await?;
+    let old = TeamView::from(&app_state.collaboration.team(&id).await?);
+    let team = app_state.collaboration.update_member(&app_state.pool, &id, &agent_id, &request).await?;
+    let view = TeamView::from(&team);
+    audit_team(&app_state.pool, &id, "update", Some(&old), Some(&view), Some(serde_json::json!({ "update_member": &agent_id })), &user).await;
+    Ok(Json(view))
+}
+
+pub async fn remove_team_member(
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+    Path((id, agent_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&app_state.pool, &headers, "agents", "write").await?;
+    let old = TeamView::from(&app_state.collaboration.team(&id).await?);
+    let team = app_state.collaboration.remove_member(&app_state.pool, &id, &agent_id).await?;
+    let view = TeamView::from(&team);
+    audit_team(&app_state.pool, &id, "update", Some(&old), Some(&view), Some(serde_json::json!({ "remove_member": &agent_id })), &user).await;
+    Ok(Json(view))
+}
+
+pub async fn set_team_access_control(
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+    Path((id, agent_id)): Path<(String, String)>,
+    Json(request): Json<AccessControlRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&app_state.pool, &headers, "agents", "admin").await?;
+    let team = app_state.collaboration.set_access_control(&app_state.pool, &id, &agent_id, &request).await?;
+    Ok(Json(TeamView::from(&team)))
+}
+
+pub async fn remove_team_access_control(
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+    Path((id, agent_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&app_state.pool, &headers, "agents", "admin").await?;
+    let team = app_state.collaboration.remove_access_control(&app_state.pool, &id, &agent_id).await?;
+    Ok(Json(TeamView::from(&team)))
+}
+
+pub async fn get_team_context(
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let caller = context_caller(&app_state.pool, &headers, "read").await?;
+    let team = app_state.collaboration.team(&id).await?;
+    caller.check(&team, None, "read")?;
+
+    let context = &team.shared_context;
+    let data: HashMap<&String, &Value> = context
+        .data
+        .iter()
+        .filter(|(key, _)| match &caller {
+            ContextCaller::Agent(agent_id) => !team.hides_key(agent_id, key),
+            ContextCaller::User(_) => true,
+        })
+        .collect();
+    let versions: HashMap<&String, &u32> = context.key_versions.iter().filter(|(key, _)| data.contains_key(key)).collect();
+
+    Ok(Json(serde_json::json!({
+        "team_id": team.id,
+        "version": context.version,
+        "last_modified": context.last_modified,
+        "modified_by": context.modified_by,
+        "data": data,
+        "key_versions": versions,
+    })))
+}
+
+pub async fn get_team_context_key(
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+    Path((id, key)): Path<(String, String)>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let caller = context_caller(&app_state.pool, &headers, "read").await?;
+    let team = app_state.collaboration.team(&id).await?;
+    caller.check(&team, Some(&key), "read")?;
+
+    let history = app_state.collaboration.context_history(&app_state.pool, &id, &key).await?;
+    match history.into_iter().next() {
+        Some(entry) if entry.value.is_some() => Ok(Json(entry)),
+        _ => Err((StatusCode::NOT_FOUND, format!("Context key '{}' not found", key))),
+    }
+}
+
+pub async fn get_team_context_history(
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+    Path((id, key)): Path<(String, String)>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let caller = context_caller(&app_state.pool, &headers, "read").await?;
+    let team = app_state.collaboration.team(&id).await?;
+    caller.check(&team, Some(&key), "read")?;
+    Ok(Json(app_state.collaboration.context_history(&app_state.pool, &id, &key).await?))
+}
+
+pub async fn put_team_context_key(
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+    Path((id, key)): Path<(String, String)>,
+    Json(request): Json<WriteContextRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let caller = context_caller(&app_state.pool, &headers, "write").await?;
+    let team = app_state.collaboration.team(&id).await?;
+    caller.check(&team, Some(&key), "write")?;
+
+    let entry = app_state
+        .collaboration
+        .write_context(&app_state.pool, &id, &key, Some(&request.value), request.expected_version, caller.id())
+        .await?;
+    Ok(Json(entry))
+}
+
+pub async fn delete_team_context_key(
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+    Path((id, key)): Path<(String, String)>,
+    axum::extract::Query(query): axum::extract::Query<DeleteContextQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let caller = context_caller(&app_state.pool, &headers, "delete").await?;
+    let team = app_state.collaboration.team(&id).await?;
+    caller.check(&team, Some(&key), "delete")?;
+
+    let entry = app_state
+        .collaboration
+        .write_context(&app_state.pool, &id, &key, None, query.expected_version, caller.id())
+        .await?;
+    Ok(Json(entry))
+}
+
 pub async fn get_advanced_features_status(
     State(app_state): State<crate::AppState>,
-) -> impl IntoResponse {
-    // This would typically use actual instances from app state
-    let collaboration = AgentCollaboration::new();
-    
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&app_state.pool, &headers, "agents", "read").await?;
+    let collaboration_metrics = app_state.collaboration.collaboration_metrics.read().await.clone();
+
+    let (feedback, patterns, adaptations, adaptive_agents, average_improvement): (i64, i64, i64, i64, Option<f64>) = sqlx::query_as(
+        "SELECT
+            (SELECT COUNT(*) FROM agent_feedback),
+            (SELECT COUNT(*) FROM agent_patterns),
+            (SELECT COUNT(*) FROM agent_adaptations WHERE status IN ('applied', 'kept', 'rolled_back')),
+            (SELECT COUNT(DISTINCT agent_id) FROM agent_adaptations WHERE status IN ('applied', 'kept', 'rolled_back')),
+            (SELECT AVG(improvement) FROM agent_adaptations WHERE improvement IS NOT NULL)"
+    )
+    .fetch_one(&app_state.pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
     let status = AdvancedFeaturesStatus {
-        active_teams: collaboration.collaboration_metrics.active_collaborations,
-        collaboration_metrics: collaboration.collaboration_metrics.clone(),
-        adaptive_agents: 0, // Would be calculated from actual agents
+        active_teams: collaboration_metrics.active_collaborations,
+        collaboration_metrics,
+        adaptive_agents: adaptive_agents as usize,
         learning_metrics: LearningMetrics {
-            total_feedback_processed: 0,
-            patterns_recognized: 0,
-            adaptations_performed: 0,
-            average_improvement: 0.0,
+            total_feedback_processed: feedback as u64,
+            patterns_recognized: patterns as u64,
+            adaptations_performed: adaptations as u64,
+            average_improvement: average_improvement.unwrap_or(0.0),
         },
     };
-    
-    Json(status)
+
+    Ok(Json(status))
 }
 
 pub async fn delegate_task_to_team(
     Path(id): Path<String>,
-    State(_app_state): State<crate::AppState>,
-    Json(payload): Json<Value>,
-) -> impl IntoResponse {
-    (StatusCode::OK, Json(serde_json::json!({
-        "status": "success",
-        "team_id": id,
-        "message": "Task delegated successfully (stub)",
-        "payload": payload
-    })))
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+    Json(request): Json<DelegateTaskRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&app_state.pool, &headers, "tasks", "write").await?;
+    let task = crate::fetch_visible_task(&app_state.pool, &request.task_id, user.security_level).await?;
+    let delegation = app_state
+        .collaboration
+        .delegate_task_to_team(&app_state, &id, &task, &request, &user.id)
+        .await?;
+    Ok(Json(delegation))
+}
+
+pub async fn open_team_ballot(
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(request): Json<OpenBallotRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let caller = context_caller(&app_state.pool, &headers, "write").await?;
+    if let ContextCaller::Agent(agent_id) = &caller {
+        let team = app_state.collaboration.team(&id).await?;
+        match team.roles.get(agent_id) {
+            Some(role) if role.role_type != RoleType::Observer => {}
+            _ => return Err((StatusCode::FORBIDDEN, "Only voting members may open a ballot".to_string())),
+        }
+    }
+
+    let ballot = app_state.collaboration.open_ballot(&app_state.pool, &id, &request, caller.id()).await?;
+    app_state.manager.broadcast(&serde_json::json!({
+        "type": "ballot_opened",
+        "ballot_id": ballot.id,
+        "team_id": ballot.team_id,
+        "task_id": ballot.task_id,
+    }).to_string());
+    Ok((StatusCode::CREATED, Json(app_state.collaboration.ballot_view(&app_state.pool, &ballot.id).await?)))
+}
+
+pub async fn list_team_ballots(
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    axum::extract::Query(query): axum::extract::Query<BallotQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let caller = context_caller(&app_state.pool, &headers, "read").await?;
+    let team = app_state.collaboration.team(&id).await?;
+    caller.check(&team, None, "read")?;
+    Ok(Json(app_state.collaboration.ballots(&app_state.pool, &id, query.status.as_deref()).await?))
+}
+
+pub async fn get_team_ballot(
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+    Path(ballot_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let caller = context_caller(&app_state.pool, &headers, "read").await?;
+    let view = app_state.collaboration.ballot_view(&app_state.pool, &ballot_id).await?;
+    if let ContextCaller::Agent(agent_id) = &caller
+        && !app_state.collaboration.team(&view.ballot.team_id).await?.roles.contains_key(agent_id) {
+            return Err((StatusCode::FORBIDDEN, "Only team members may see the ballot".to_string()));
+        }
+    Ok(Json(view))
+}
+
+pub async fn cast_ballot_vote(
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+    Path(ballot_id): Path<String>,
+    Json(request): Json<CastVoteRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let ContextCaller::Agent(agent_id) = context_caller(&app_state.pool, &headers, "write").await? else {
+        return Err((StatusCode::FORBIDDEN, "Votes are cast by agents with their x-agent-key".to_string()));
+    };
+    let view = app_state
+        .collaboration
+        .cast_vote(&app_state.pool, &app_state.manager, &ballot_id, &agent_id, &request)
+        .await?;
+    Ok(Json(view))
+}
+
+pub async fn resolve_team_ballot(
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+    Path(ballot_id): Path<String>,
+    Json(request): Json<ResolveBallotRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&app_state.pool, &headers, "agents", "write").await?;
+    let view = app_state
+        .collaboration
+        .decide_ballot_manually(&app_state.pool, &app_state.manager, &ballot_id, &request, &user.id)
+        .await?;
+    Ok(Json(view))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::common::{create_test_pool, insert_test_agent};
+
+    /// A team of fresh agents with the given roles, returned with their ids in order
+    async fn team_of(collaboration: &AgentCollaboration, pool: &SqlitePool, roles: &[RoleType]) -> (AgentTeam, Vec<String>) {
+        let mut ids = Vec::new();
+        for (i, _) in roles.iter().enumerate() {
+            ids.push(insert_test_agent(pool, &format!("Voter {}", i)).await);
+        }
+        let request = CreateTeamRequest {
+            name: format!("Ballot Team {}", Uuid::new_v4()),
+            member_ids: Vec::new(),
+            members: ids
+                .iter()
+                .zip(roles)
+                .map(|(id, role)| TeamMemberRequest {
+                    agent_id: id.clone(),
+                    role_type: Some(*role),
+                    responsibilities: None,
+                    permissions: None,
+                    reporting_to: None,
+                })
+                .collect(),
+            team_type: None,
+        };
+        (collaboration.create_team(pool, &request, "tester").await.unwrap(), ids)
+    }
+
+    fn ballot(team: &AgentTeam, voting_type: VotingType, quorum: f64, tie_breaker: &str) -> Ballot {
+        Ballot {
+            id: Uuid::new_v4().to_string(),
+            team_id: team.id.clone(),
+            task_id: None,
+            kind: "decision".to_string(),
+            conflict_type: "approach".to_string(),
+            question: "Which approach?".to_string(),
+            options: vec!["a".to_string(), "b".to_string()],
+            voting_type: voting_type.as_str().to_string(),
+            quorum,
+            tie_breaker: tie_breaker.to_string(),
+            deadline: Utc::now(),
+            status: "open".to_string(),
+            outcome: None,
+            resolution: None,
+            resolved_by: None,
+            created_by: "tester".to_string(),
+            created_at: Utc::now(),
+            resolved_at: None,
+        }
+    }
+
+    fn votes(cast: &[(&String, &str)]) -> Vec<BallotVote> {
+        cast.iter()
+            .map(|(agent_id, choice)| BallotVote {
+                agent_id: agent_id.to_string(),
+                choice: choice.to_string(),
+                comment: None,
+                cast_at: Utc::now(),
+            })
+            .collect()
+    }
+
+    fn weight(tally: &BallotTally, option: &str) -> f64 {
+        tally.weights.iter().find(|w| w.option == option).map(|w| w.weight).unwrap()
+    }
+
+    fn winner(decision: BallotDecision) -> Option<String> {
+        match decision {
+            BallotDecision::Winner(option) => Some(option),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn simple_majority_counts_members_and_skips_observers() {
+        let pool = create_test_pool().await;
+        let collaboration = AgentCollaboration::new();
+        let roles = [RoleType::Executor, RoleType::Executor, RoleType::Specialist, RoleType::Observer];
+        let (team, ids) = team_of(&collaboration, &pool, &roles).await;
+        let ballot = ballot(&team, VotingType::SimpleMajority, 0.5, "random");
+
+        let cast = votes(&[(&ids[0], "a"), (&ids[1], "a"), (&ids[2], "b"), (&ids[3], "b")]);
+        let tally = tally_ballot(&ballot, &team, &cast);
+        assert_eq!(tally.eligible, 3);
+        assert_eq!(tally.voted, 3);
+        assert!(tally.quorum_met);
+        assert_eq!(weight(&tally, "a"), 2.0);
+        assert_eq!(weight(&tally, "b"), 1.0);
+        assert_eq!(winner(decide_ballot(VotingType::SimpleMajority, &tally)).as_deref(), Some("a"));
+    }
+
+    #[tokio::test]
+    async fn quorum_is_a_share_of_eligible_members() {
+        let pool = create_test_pool().await;
+        let collaboration = AgentCollaboration::new();
+        let roles = [RoleType::Executor, RoleType::Executor, RoleType::Executor, RoleType::Observer];
+        let (team, ids) = team_of(&collaboration, &pool, &roles).await;
+
+        // Two of three eligible members; the observer's vote does not count towards quorum
+        let cast = votes(&[(&ids[0], "a"), (&ids[1], "a"), (&ids[3], "a")]);
+        assert!(!tally_ballot(&ballot(&team, VotingType::SimpleMajority, 0.75, "random"), &team, &cast).quorum_met);
+        assert!(tally_ballot(&ballot(&team, VotingType::SimpleMajority, 2.0 / 3.0, "random"), &team, &cast).quorum_met);
+
+        let tally = tally_ballot(&ballot(&team, VotingType::SimpleMajority, 0.5, "random"), &team, &[]);
+        assert!(!tally.quorum_met);
+        assert!(matches!(decide_ballot(VotingType::SimpleMajority, &tally), BallotDecision::Undecided(_)));
+    }
+
+    #[tokio::test]
+    async fn weighted_voting_favours_leaders() {
+        let pool = create_test_pool().await;
+        let collaboration = AgentCollaboration::new();
+        let (team, ids) = team_of(&collaboration, &pool, &[RoleType::Leader, RoleType::Executor, RoleType::Reviewer]).await;
+        let ballot = ballot(&team, VotingType::WeightedVoting, 0.5, "random");
+
+        let tally = tally_ballot(&ballot, &team, &votes(&[(&ids[0], "b"), (&ids[1], "a")]));
+        assert_eq!(weight(&tally, "b"), 2.0);
+        assert_eq!(weight(&tally, "a"), 1.0);
+        assert_eq!(winner(decide_ballot(VotingType::WeightedVoting, &tally)).as_deref(), Some("b"));
+
+        let tally = tally_ballot(&ballot, &team, &votes(&[(&ids[0], "b"), (&ids[1], "a"), (&ids[2], "a")]));
+        assert_eq!(weight(&tally, "a"), 2.5);
+        assert_eq!(winner(decide_ballot(VotingType::WeightedVoting, &tally)).as_deref(), Some("a"));
+    }
+
+    #[tokio::test]
+    async fn delegated_voting_follows_the_reporting_line() {
+        let pool = create_test_pool().await;
+        let collaboration = AgentCollaboration::new();
+        let (mut team, ids) = team_of(&collaboration, &pool, &[RoleType::Leader, RoleType::Executor, RoleType::Executor]).await;
+        team.roles.get_mut(&ids[1]).unwrap().reporting_to = Some(ids[0].clone());
+        let ballot = ballot(&team, VotingType::DelegatedVoting, 0.5, "random");
+
+        let tally = tally_ballot(&ballot, &team, &votes(&[(&ids[0], "a"), (&ids[2], "b")]));
+        assert_eq!(tally.voted, 2);
+        assert_eq!(weight(&tally, "a"), 2.0);
+        assert_eq!(weight(&tally, "b"), 1.0);
+        assert_eq!(winner(decide_ballot(VotingType::DelegatedVoting, &tally)).as_deref(), Some("a"));
+    }
+
+    #[tokio::test]
+    async fn qualified_majority_and_consensus_thresholds() {
+        let pool = create_test_pool().await;
+        let collaboration = AgentCollaboration::new();
+        let (team, ids) = team_of(&collaboration, &pool, &[RoleType::Executor, RoleType::Executor, RoleType::Executor]).await;
+        let cast = votes(&[(&ids[0], "a"), (&ids[1], "a"), (&ids[2], "b")]);
+
+        let tally = tally_ballot(&ballot(&team, VotingType::QualifiedMajority, 1.0, "random"), &team, &cast);
+        assert_eq!(winner(decide_ballot(VotingType::QualifiedMajority, &tally)).as_deref(), Some("a"));
+        let tally = tally_ballot(&ballot(&team, VotingType::Consensus, 1.0, "random"), &team, &cast);
+        assert!(matches!(decide_ballot(VotingType::Consensus, &tally), BallotDecision::Undecided(_)));
+
+        let unanimous = votes(&[(&ids[0], "b"), (&ids[1], "b"), (&ids[2], "b")]);
+        let tally = tally_ballot(&ballot(&team, VotingType::Consensus, 1.0, "random"), &team, &unanimous);
+        assert_eq!(winner(decide_ballot(VotingType::Consensus, &tally)).as_deref(), Some("b"));
+    }
+
+    #[tokio::test]
+    async fn ties_are_broken_by_the_ballot_tie_breaker() {
+        let pool = create_test_pool().await;
+        let collaboration = AgentCollaboration::new();
+        let (team, ids) = team_of(&collaboration, &pool, &[RoleType::Executor, RoleType::Executor]).await;
+        sqlx::query("UPDATE agent_team_members SET joined_at = '2020-01-01 00:00:00' WHERE team_id = ? AND agent_id = ?")
+            .bind(&team.id)
+            .bind(&ids[1])
+            .execute(&pool)
+            .await
+            .unwrap();
+        let cast = votes(&[(&ids[0], "a"), (&ids[1], "b")]);
+
+        let seniority = ballot(&team, VotingType::SimpleMajority, 0.5, "seniority");
+        let tally = tally_ballot(&seniority, &team, &cast);
+        let tied = match decide_ballot(VotingType::SimpleMajority, &tally) {
+            BallotDecision::Tie(tied) => tied,
+            _ => panic!("expected a tie"),
+        };
+        assert_eq!(tied, vec!["a".to_string(), "b".to_string()]);
+        let broken = collaboration.break_tie(&pool, &seniority, &team, &cast, &tied).await.unwrap();
+        assert_eq!(broken.as_deref(), Some("b"));
+
+        let random = ballot(&team, VotingType::SimpleMajority, 0.5, "random");
+        let broken = collaboration.break_tie(&pool, &random, &team, &cast, &tied).await.unwrap();
+        assert!(broken.is_some_and(|choice| tied.contains(&choice)));
+
+        // No custom breaker is configured, so the tie stays for a human
+        let custom = ballot(&team, VotingType::SimpleMajority, 0.5, "custom");
+        assert_eq!(collaboration.break_tie(&pool, &custom, &team, &cast, &tied).await.unwrap(), None);
+    }
 }
@@ -1,97 +1,25 @@
 use crate::models::*;
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     Json,
     response::IntoResponse,
-    http::StatusCode,
-    middleware,
+    http::{HeaderMap, StatusCode},
 };
 use sqlx::SqlitePool;
 use chrono::Utc;
 use std::collections::HashMap;
 use serde_json::Value;
 use sha2::{Sha256, Digest};
-use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::RwLock;
-use lru::LruCache;
-use dashmap::DashMap;
 use once_cell::sync::Lazy;
-use validator::Validate;
 use regex::Regex;
 use tracing::{info, warn, error, debug, instrument};
-use metrics::{counter, histogram, gauge};
-use tower::timeout::TimeoutLayer;
-use tower::retry::RetryLayer;
-use tower::limit::RateLimitLayer;
+use metrics::histogram;
+use crate::openclaw_optimization::{CacheKey, HierarchicalCache};
+use crate::agent_config_history::{ChangeReasonQuery, ChangeSource, ConfigChange, ConfigHistoryService, ConfigKind};
 
 // Performance and Caching Infrastructure
 
-/// Configuration cache with TTL and size limits
-#[derive(Clone)]
-pub struct ConfigCache {
-    inner: Arc<RwLock<LruCache<String, CachedConfig>>>,
-}
-
-#[derive(Clone)]
-struct CachedConfig {
-    data: Value,
-    hash: String,
-    cached_at: chrono::DateTime<Utc>,
-    ttl: Duration,
-}
-
-impl ConfigCache {
-    pub fn new(capacity: usize) -> Self {
-        Self {
-            inner: Arc::new(RwLock::new(LruCache::new(
-                std::num::NonZeroUsize::new(capacity).unwrap()
-            ))),
-        }
-    }
-
-    pub async fn get(&self, key: &str) -> Option<Value> {
-        let mut cache = self.inner.write().await;
-        if let Some(cached) = cache.get(key) {
-            if cached.cached_at + cached.ttl > Utc::now() {
-                return Some(cached.data.clone());
-            }
-        }
-        None
-    }
-
-    pub async fn put(&self, key: String, data: Value, ttl: Duration) {
-        let hash = format!("{:x}", Sha256::digest(data.to_string().as_bytes()));
-        let cached = CachedConfig {
-            data,
-            hash,
-            cached_at: Utc::now(),
-            ttl,
-        };
-        
-        let mut cache = self.inner.write().await;
-        cache.put(key, cached);
-    }
-
-    pub async fn invalidate(&self, pattern: &str) {
-        let mut cache = self.inner.write().await;
-        let re = Regex::new(pattern).unwrap_or_else(|_| Regex::new(r".*").unwrap());
-        
-        let keys_to_remove: Vec<String> = cache
-            .iter()
-            .map(|(k, _)| k.clone())
-            .filter(|k| re.is_match(k))
-            .collect();
-            
-        for key in keys_to_remove {
-            cache.pop(&key);
-        }
-    }
-}
-
-/// Global configuration cache instance
-pub(crate) static CONFIG_CACHE: Lazy<ConfigCache> = Lazy::new(|| ConfigCache::new(1000));
-
 /// Metrics collector for OpenClaw integration
 #[derive(Clone)]
 pub struct OpenClawMetrics {
@@ -156,7 +84,8 @@ impl SecurityValidator {
     pub fn sanitize_json_input(input: &Value) -> Result<Value, String> {
         // Remove potentially dangerous fields
         match input {
-            Value::Object(mut map) => {
+            Value::Object(map) => {
+                let mut map = map.clone();
                 // Remove sensitive fields that shouldn't be stored
                 map.remove("password");
                 map.remove("token");
@@ -165,14 +94,13 @@ impl SecurityValidator {
                 
                 // Recursively sanitize nested objects
                 for (_, value) in map.iter_mut() {
-                    *value = Self::sanitize_json_input(value.clone())?;
+                    *value = Self::sanitize_json_input(value)?;
                 }
                 
                 Ok(Value::Object(map))
             }
             Value::Array(arr) => {
                 let sanitized: Result<Vec<_>, _> = arr.iter()
-                    .cloned()
                     .map(Self::sanitize_json_input)
                     .collect();
                 Ok(Value::Array(sanitized?))
@@ -233,7 +161,7 @@ impl OpenClawResilience {
     }
 }
 
-/// Enhanced OpenClaw Configuration Management with Optimizations
+// Enhanced OpenClaw Configuration Management with Optimizations
 
 /// Get comprehensive agent configurations from OpenClaw with caching
 #[instrument(skip(state))]
@@ -244,11 +172,12 @@ pub async fn get_openclaw_agent_configs(
     METRICS.config_reads_total.increment(1);
     
     // Try cache first
-    if let Some(cached_config) = CONFIG_CACHE.get("openclaw_config").await {
+    if let Some(cached_config) = state.cache.get(&CacheKey::OpenClawConfig).await {
         METRICS.config_cache_hits_total.increment(1);
         debug!("Config retrieved from cache");
         
-        let configs = parse_configs_from_value(&cached_config)?;
+        let configs: Vec<OpenClawAgentConfig> = serde_json::from_value(cached_config)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Deserialization error: {}", e)))?;
         histogram!("openclaw_config_parse_duration").record(start_time.elapsed().as_secs_f64());
         
         return Ok(Json(configs));
@@ -268,7 +197,11 @@ pub async fn get_openclaw_agent_configs(
         (StatusCode::INTERNAL_SERVER_ERROR, format!("Serialization error: {}", e))
     })?;
     
-    CONFIG_CACHE.put("openclaw_config".to_string(), config_value.clone(), Duration::from_secs(300)).await;
+    state.cache.put(
+        CacheKey::OpenClawConfig,
+        config_value.clone(),
+        Duration::from_secs(crate::config::current().openclaw_config_ttl_seconds),
+    ).await;
     
     histogram!("openclaw_config_sync_duration").record(start_time.elapsed().as_secs_f64());
     info!("Successfully loaded {} agent configurations", configs.len());
@@ -287,8 +220,8 @@ pub async fn get_openclaw_agent_config(
         .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
 
     // Try cache first
-    let cache_key = format!("agent_config_{}", agent_id);
-    if let Some(cached_config) = CONFIG_CACHE.get(&cache_key).await {
+    let cache_key = CacheKey::AgentConfig(agent_id.clone());
+    if let Some(cached_config) = state.cache.get(&cache_key).await {
         METRICS.config_cache_hits_total.increment(1);
         
         let config: OpenClawAgentConfig = serde_json::from_value(cached_config)
@@ -298,7 +231,7 @@ pub async fn get_openclaw_agent_config(
     }
 
     // Cache miss - get from full config
-    let configs = get_openclaw_agent_configs(State(state)).await?.0;
+    let configs = get_openclaw_agent_configs(State(state.clone())).await?.0;
     
     let config = configs.into_iter()
         .find(|c| c.id == agent_id)
@@ -308,7 +241,7 @@ pub async fn get_openclaw_agent_config(
     let config_value = serde_json::to_value(&config)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Serialization error: {}", e)))?;
     
-    CONFIG_CACHE.put(cache_key, config_value, Duration::from_secs(600)).await;
+    state.cache.put(cache_key, config_value, Duration::from_secs(crate::config::current().agent_config_ttl_seconds)).await;
 
     Ok(Json(config))
 }
@@ -348,7 +281,7 @@ pub async fn sync_openclaw_configs(
     METRICS.active_agents.set(synced_count as f64);
     
     // Invalidate relevant cache entries
-    CONFIG_CACHE.invalidate("agent_config_*").await;
+    state.cache.invalidate_matching(|key| matches!(key, CacheKey::AgentConfig(_))).await;
     
     let duration = start_time.elapsed();
     info!("Configuration sync completed: {} agents synced in {:?} with {} errors", 
@@ -367,8 +300,12 @@ pub async fn sync_openclaw_configs(
 pub async fn apply_agent_config(
     Path(agent_id): Path<String>,
     State(state): State<crate::AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ChangeReasonQuery>,
     Json(mut config): Json<OpenClawAgentConfig>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&state.pool, &headers, "agents", "write").await?;
+
     // Validate agent ID
     SecurityValidator::validate_agent_id(&agent_id)
         .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
@@ -383,14 +320,46 @@ pub async fn apply_agent_config(
     config = serde_json::from_value(sanitized_value)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Deserialization error: {}", e)))?;
 
+    let change = ConfigChange::new(ChangeSource::Apply, Some(&user.id), query.reason);
+    let result = apply_validated_agent_config(&state.pool, &state.cache, &agent_id, &config, &change).await?;
+
+    Ok(Json(serde_json::json!({
+        "status": "success",
+        "agent_id": agent_id,
+        "applied_at": Utc::now(),
+        "config_hash": result
+    })))
+}
+
+/// Validates and applies an agent configuration, records it as the agent's newest
+/// configuration version in the same transaction and returns the new config hash.
+/// Shared by the apply and parameter endpoints, agent adaptations and configuration
+/// rollbacks.
+pub(crate) async fn apply_validated_agent_config(
+    pool: &SqlitePool,
+    cache: &HierarchicalCache,
+    agent_id: &str,
+    config: &OpenClawAgentConfig,
+    change: &ConfigChange,
+) -> Result<String, (StatusCode, String)> {
     // Validate configuration
-    validate_agent_config_internal(&config)?;
+    validate_agent_config_internal(config).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
 
-    // Apply with resilience
+    let config_json = serde_json::to_string(config)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // Apply with resilience; each attempt is one transaction
     let resilience = OpenClawResilience::default();
     let result = resilience.execute_with_resilience(|| {
         Box::pin(async {
-            apply_agent_config_to_db(&state.pool, &agent_id, &config).await
+            let mut tx = pool.begin().await?;
+            let config_hash = apply_agent_config_to_db(&mut tx, agent_id, config).await?;
+            ConfigHistoryService
+                .record(&mut tx, agent_id, ConfigKind::OpenClaw, &config_json, change)
+                .await
+                .map_err(|e| e.to_string())?;
+            tx.commit().await?;
+            Ok(config_hash)
         })
     }).await.map_err(|e| {
         error!("Failed to apply config for agent {}: {}", agent_id, e);
@@ -398,18 +367,11 @@ pub async fn apply_agent_config(
     })?;
 
     // Update metrics and cache
-    METRICS.agent_updates_total.increment();
-    let cache_key = format!("agent_config_{}", agent_id);
-    CONFIG_CACHE.invalidate(&cache_key).await;
+    METRICS.agent_updates_total.increment(1);
+    cache.invalidate_agent(agent_id).await;
 
     info!("Successfully applied configuration for agent {}", agent_id);
-
-    Ok(Json(serde_json::json!({
-        "status": "success",
-        "agent_id": agent_id,
-        "applied_at": Utc::now(),
-        "config_hash": result
-    })))
+    Ok(result)
 }
 
 /// Enhanced agent list with capabilities and performance metrics
@@ -421,11 +383,19 @@ pub async fn fetch_enhanced_openclaw_agents(
     
     // Parallel execution of database and OpenClaw config fetch
     let (db_agents, openclaw_configs) = tokio::try_join!(
-        get_db_agents_optimized(&state.pool),
-        get_openclaw_agent_configs(State(state.clone()))
-    ).map_err(|e| {
+        async {
+            get_db_agents_optimized(&state.pool)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        },
+        async {
+            get_openclaw_agent_configs(State(state.clone()))
+                .await
+                .map(|Json(configs)| configs)
+        }
+    ).map_err(|(status, e)| {
         error!("Failed to fetch agent data: {}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, "Data fetch failed".to_string())
+        (status, "Data fetch failed".to_string())
     })?;
 
     let config_map: HashMap<String, OpenClawAgentConfig> = openclaw_configs
@@ -460,7 +430,7 @@ pub async fn fetch_enhanced_openclaw_agents(
     }).collect();
 
     let duration = start_time.elapsed();
-    histogram!("openclaw_enhanced_fetch_duration", duration.as_secs_f64());
+    histogram!("openclaw_enhanced_fetch_duration").record(duration.as_secs_f64());
     
     Ok(Json(serde_json::json!({
         "data": enhanced_agents,
@@ -472,12 +442,20 @@ pub async fn fetch_enhanced_openclaw_agents(
 
 // Helper Functions with Optimizations
 
-async fn read_and_parse_openclaw_config() -> Result<Vec<OpenClawAgentConfig>, Box<dyn std::error::Error + Send + Sync>> {
-    let openclaw_dir = std::env::var("OPENCLAW_STATE_DIR")
+/// OpenClaw's state directory, which holds `openclaw.json` and the agent sessions
+pub(crate) fn openclaw_state_dir() -> std::path::PathBuf {
+    std::env::var("OPENCLAW_STATE_DIR")
         .or_else(|_| std::env::var("HOME").map(|h| format!("{}/.openclaw", h)))
-        .unwrap_or_else(|_| "/root/.openclaw".to_string());
-    
-    let config_path = format!("{}/openclaw.json", openclaw_dir);
+        .unwrap_or_else(|_| "/root/.openclaw".to_string())
+        .into()
+}
+
+pub(crate) fn openclaw_config_path() -> String {
+    openclaw_state_dir().join("openclaw.json").to_string_lossy().to_string()
+}
+
+pub(crate) async fn read_and_parse_openclaw_config() -> Result<Vec<OpenClawAgentConfig>, Box<dyn std::error::Error + Send + Sync>> {
+    let config_path = openclaw_config_path();
     
     // Validate file path for security
     SecurityValidator::validate_file_path(&config_path)?;
@@ -488,8 +466,10 @@ async fn read_and_parse_openclaw_config() -> Result<Vec<OpenClawAgentConfig>, Bo
     let agents_config = config.get("agents")
         .ok_or("Missing agents config")?;
     
-    let defaults = agents_config.get("defaults").unwrap_or(&Value::Object(Default::default()));
-    let list = agents_config.get("list").and_then(|l| l.as_array()).unwrap_or(&[]);
+    let empty_defaults = Value::Object(Default::default());
+    
+    let defaults = agents_config.get("defaults").unwrap_or(&empty_defaults);
+    let list = agents_config.get("list").and_then(|l| l.as_array()).map(Vec::as_slice).unwrap_or(&[]);
 
     let mut enhanced_configs = Vec::with_capacity(list.len());
 
@@ -502,23 +482,6 @@ async fn read_and_parse_openclaw_config() -> Result<Vec<OpenClawAgentConfig>, Bo
     Ok(enhanced_configs)
 }
 
-fn parse_configs_from_value(config_value: &Value) -> Result<Vec<OpenClawAgentConfig>, (StatusCode, String)> {
-    let agents_config = config_value.get("agents")
-        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Missing agents config".to_string()))?;
-    
-    let defaults = agents_config.get("defaults").unwrap_or(&Value::Object(Default::default()));
-    let list = agents_config.get("list").and_then(|l| l.as_array()).unwrap_or(&[]);
-
-    let mut configs = Vec::with_capacity(list.len());
-    for agent_entry in list {
-        if let Some(config) = parse_openclaw_agent_config(agent_entry, defaults) {
-            configs.push(config);
-        }
-    }
-
-    Ok(configs)
-}
-
 async fn get_db_agents_optimized(pool: &SqlitePool) -> Result<Vec<Agent>, sqlx::Error> {
     sqlx::query_as::<sqlx::Sqlite, Agent>(
         "SELECT * FROM agents ORDER BY name"
@@ -550,36 +513,34 @@ struct AgentUpdateBatch {
 async fn execute_batch_agent_updates(
     pool: &SqlitePool, 
     updates: &[AgentUpdateBatch]
-) -> Result<usize, sqlx::Error> {
+) -> Result<usize, crate::agent_config_history::HistoryError> {
     let mut tx = pool.begin().await?;
     let mut updated_count = 0;
 
     for update in updates {
-        // Upsert agent with configuration
+        // Upsert agent with configuration. Replacing the row would cascade away its
+        // configuration history.
         sqlx::query(
             r#"
-            INSERT OR REPLACE INTO agents (
+            INSERT INTO agents (
                 id, name, role, status, openclaw_config_hash, created_at
             ) VALUES (?, ?, 'SPC', 'IDLE', ?, CURRENT_TIMESTAMP)
+            ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                openclaw_config_hash = excluded.openclaw_config_hash,
+                updated_at = CURRENT_TIMESTAMP
             "#
         )
         .bind(&update.agent_id)
-        .bind(&update.name.as_deref().unwrap_or(&update.agent_id))
+        .bind(update.name.as_deref().unwrap_or(&update.agent_id))
         .bind(&update.config_hash)
         .execute(&mut *tx)
         .await?;
 
-        // Store configuration snapshot
-        let snapshot_id = uuid::Uuid::new_v4().to_string();
-        sqlx::query(
-            "INSERT INTO openclaw_config_snapshots (id, agent_id, config_hash, raw_config, is_active) VALUES (?, ?, ?, ?, 1)"
-        )
-        .bind(&snapshot_id)
-        .bind(&update.agent_id)
-        .bind(&update.config_hash)
-        .bind(&update.config_data)
-        .execute(&mut *tx)
-        .await?;
+        // Snapshot only what changed since the last recorded version
+        ConfigHistoryService
+            .record(&mut tx, &update.agent_id, ConfigKind::OpenClaw, &update.config_data, &ConfigChange::new(ChangeSource::Sync, None, None))
+            .await?;
 
         updated_count += 1;
     }
@@ -612,29 +573,98 @@ pub(crate) fn calculate_agent_health_score(agent: &Agent, openclaw_config: Optio
         }
     }
     
-    score.max(0.0).min(100.0)
+    score.clamp(0.0, 100.0)
 }
 
 pub async fn get_agent_parameters(
-    Path(id): Path<String>,
-    State(state): State<crate::AppState>,
+    Path(_id): Path<String>,
+    State(_state): State<crate::AppState>,
 ) -> impl IntoResponse {
     (StatusCode::NOT_IMPLEMENTED, "Not implemented")
 }
 
+/// Sets individual parameters on an agent's OpenClaw configuration and applies the
+/// result, recording each changed parameter and the new configuration version
 pub async fn update_agent_parameters(
-    Path(id): Path<String>,
+    Path(agent_id): Path<String>,
     State(state): State<crate::AppState>,
-    Json(payload): Json<Value>,
-) -> impl IntoResponse {
-    (StatusCode::NOT_IMPLEMENTED, Json(serde_json::json!({"status": "error", "message": "Not implemented"})))
+    headers: HeaderMap,
+    Query(query): Query<ChangeReasonQuery>,
+    Json(params): Json<Value>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let user = crate::rbac::authorized_user(&state.pool, &headers, "agents", "write").await?;
+    SecurityValidator::validate_agent_id(&agent_id)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let params = params.as_object()
+        .ok_or((StatusCode::BAD_REQUEST, "Parameters must be a JSON object".to_string()))?;
+
+    let mut config = ConfigHistoryService.current_openclaw_config(&state.pool, &agent_id).await?;
+    let changes = apply_agent_parameters(&mut config, params)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    if changes.is_empty() {
+        return Ok(Json(serde_json::json!({
+            "status": "unchanged",
+            "changes_made": Vec::<String>::new()
+        })));
+    }
+
+    let change = ConfigChange::new(ChangeSource::Parameters, Some(&user.id), query.reason);
+    let config_hash = apply_validated_agent_config(&state.pool, &state.cache, &agent_id, &config, &change).await?;
+
+    for parameter in &changes {
+        sqlx::query(
+            "INSERT INTO agent_parameter_history (id, agent_id, parameter_name, old_value, new_value, changed_by, change_reason)
+             VALUES (?1, ?2, ?3, ?4, ?5, 'user', ?6)"
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(&agent_id)
+        .bind(&parameter.name)
+        .bind(parameter.old.to_string())
+        .bind(parameter.new.to_string())
+        .bind(&change.reason)
+        .execute(&state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+
+    Ok(Json(serde_json::json!({
+        "status": "success",
+        "agent_id": agent_id,
+        "changes_made": changes.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(),
+        "config_hash": config_hash
+    })))
 }
 
+/// Per-parameter change log written by `update_agent_parameters`, newest first
 pub async fn get_agent_parameter_history(
-    Path(id): Path<String>,
+    Path(agent_id): Path<String>,
     State(state): State<crate::AppState>,
-) -> impl IntoResponse {
-    (StatusCode::NOT_IMPLEMENTED, "Not implemented")
+    headers: HeaderMap,
+) -> Result<Json<Vec<Value>>, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
+    let history = sqlx::query_as::<sqlx::Sqlite, (String, Option<String>, Option<String>, Option<String>, Option<String>, Option<String>)>(
+        "SELECT parameter_name, old_value, new_value, changed_by, CAST(changed_at AS TEXT), change_reason
+         FROM agent_parameter_history
+         WHERE agent_id = ?
+         ORDER BY changed_at DESC, rowid DESC"
+    )
+    .bind(&agent_id)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(history.into_iter().map(|(parameter, old, new, changed_by, changed_at, reason)| {
+        // Values are stored as JSON text
+        let parse = |v: Option<String>| v.and_then(|v| serde_json::from_str::<Value>(&v).ok()).unwrap_or(Value::Null);
+        serde_json::json!({
+            "parameter": parameter,
+            "old_value": parse(old),
+            "new_value": parse(new),
+            "changed_by": changed_by,
+            "changed_at": changed_at,
+            "reason": reason
+        })
+    }).collect()))
 }
 
 pub async fn validate_agent_config() -> impl IntoResponse {
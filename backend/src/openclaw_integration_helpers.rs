@@ -1,21 +1,19 @@
 use crate::models::*;
 use axum::{
-    extract::{Path, State},
+    extract::State,
     Json,
-    response::IntoResponse,
     http::StatusCode,
 };
-use sqlx::SqlitePool;
-use chrono::Utc;
-use std::collections::HashMap;
+use sqlx::SqliteConnection;
 use serde_json::Value;
 use sha2::{Sha256, Digest};
+use once_cell::sync::Lazy;
 
 // Original OpenClaw Integration Functions (Preserved for compatibility)
 
 /// Get comprehensive agent configurations from OpenClaw
 pub async fn get_openclaw_agent_configs_original(
-    State(state): State<crate::AppState>,
+    State(_state): State<crate::AppState>,
 ) -> Result<Json<Vec<OpenClawAgentConfig>>, (StatusCode, String)> {
     let openclaw_dir = std::env::var("OPENCLAW_STATE_DIR")
         .or_else(|_| std::env::var("HOME").map(|h| format!("{}/.openclaw", h)))
@@ -29,8 +27,9 @@ pub async fn get_openclaw_agent_configs_original(
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Invalid openclaw.json: {}", e)))?;
 
     let agents_config = config.get("agents").ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Missing agents config".to_string()))?;
-    let defaults = agents_config.get("defaults").unwrap_or(&Value::Object(Default::default()));
-    let list = agents_config.get("list").and_then(|l| l.as_array()).unwrap_or(&[]);
+    let empty_defaults = Value::Object(Default::default());
+    let defaults = agents_config.get("defaults").unwrap_or(&empty_defaults);
+    let list = agents_config.get("list").and_then(|l| l.as_array()).map(Vec::as_slice).unwrap_or(&[]);
 
     let mut enhanced_configs = Vec::new();
 
@@ -82,28 +81,23 @@ fn parse_model_config(model_value: Option<&Value>) -> Option<AgentModelConfig> {
 }
 
 fn parse_memory_search_config(value: Option<&Value>) -> Option<MemorySearchConfig> {
-    value.and_then(|v| {
-        Some(MemorySearchConfig {
+    value.map(|v| MemorySearchConfig {
             enabled: v.get("enabled").and_then(|e| e.as_bool()),
             max_results: v.get("maxResults").and_then(|r| r.as_i64()).map(|r| r as i32),
             threshold: v.get("threshold").and_then(|t| t.as_f64()),
         })
-    })
 }
 
 fn parse_human_delay_config(value: Option<&Value>) -> Option<HumanDelayConfig> {
-    value.and_then(|v| {
-        Some(HumanDelayConfig {
+    value.map(|v| HumanDelayConfig {
             enabled: v.get("enabled").and_then(|e| e.as_bool()),
             min_seconds: v.get("minSeconds").and_then(|s| s.as_f64()),
             max_seconds: v.get("maxSeconds").and_then(|s| s.as_f64()),
         })
-    })
 }
 
 fn parse_heartbeat_config(value: Option<&Value>) -> Option<HeartbeatConfig> {
-    value.and_then(|v| {
-        Some(HeartbeatConfig {
+    value.map(|v| HeartbeatConfig {
             enabled: v.get("enabled").and_then(|e| e.as_bool()),
             every: v.get("every").and_then(|e| e.as_str()).map(|s| s.to_string()),
             active_hours: parse_active_hours_config(v.get("activeHours")),
@@ -112,111 +106,101 @@ fn parse_heartbeat_config(value: Option<&Value>) -> Option<HeartbeatConfig> {
             target: v.get("target").and_then(|t| t.as_str()).map(|s| s.to_string()),
             prompt: v.get("prompt").and_then(|p| p.as_str()).map(|s| s.to_string()),
         })
-    })
 }
 
 fn parse_active_hours_config(value: Option<&Value>) -> Option<ActiveHoursConfig> {
-    value.and_then(|v| {
-        Some(ActiveHoursConfig {
+    value.map(|v| ActiveHoursConfig {
             start: v.get("start").and_then(|s| s.as_str()).map(|s| s.to_string()),
             end: v.get("end").and_then(|e| e.as_str()).map(|s| s.to_string()),
             timezone: v.get("timezone").and_then(|t| t.as_str()).map(|s| s.to_string()),
+            monday: parse_day_schedule(v, "monday"),
+            tuesday: parse_day_schedule(v, "tuesday"),
+            wednesday: parse_day_schedule(v, "wednesday"),
+            thursday: parse_day_schedule(v, "thursday"),
+            friday: parse_day_schedule(v, "friday"),
+            saturday: parse_day_schedule(v, "saturday"),
+            sunday: parse_day_schedule(v, "sunday"),
         })
-    })
+}
+
+fn parse_day_schedule(value: &Value, day: &str) -> Option<DaySchedule> {
+    value.get(day).and_then(|d| serde_json::from_value(d.clone()).ok())
 }
 
 fn parse_identity_config(value: Option<&Value>) -> Option<IdentityConfig> {
-    value.and_then(|v| {
-        Some(IdentityConfig {
+    value.map(|v| IdentityConfig {
             name: v.get("name").and_then(|n| n.as_str()).map(|s| s.to_string()),
             bio: v.get("bio").and_then(|b| b.as_str()).map(|s| s.to_string()),
         })
-    })
 }
 
 fn parse_group_chat_config(value: Option<&Value>) -> Option<GroupChatConfig> {
-    value.and_then(|v| {
-        Some(GroupChatConfig {
+    value.map(|v| GroupChatConfig {
             enabled: v.get("enabled").and_then(|e| e.as_bool()),
             mention_handling: v.get("mentionHandling").and_then(|m| m.as_str()).map(|s| s.to_string()),
         })
-    })
 }
 
 fn parse_subagents_config(value: Option<&Value>) -> Option<SubagentsConfig> {
-    value.and_then(|v| {
-        Some(SubagentsConfig {
+    value.map(|v| SubagentsConfig {
             allow_agents: v.get("allowAgents").and_then(|a| a.as_array()).map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect()),
             model: parse_model_config(v.get("model")),
         })
-    })
 }
 
 fn parse_sandbox_config(value: Option<&Value>) -> Option<SandboxConfig> {
-    value.and_then(|v| {
-        Some(SandboxConfig {
+    value.map(|v| SandboxConfig {
             mode: v.get("mode").and_then(|m| m.as_str()).map(|s| s.to_string()),
             docker: parse_docker_sandbox_config(v.get("docker")),
         })
-    })
 }
 
 fn parse_docker_sandbox_config(value: Option<&Value>) -> Option<DockerSandboxConfig> {
-    value.and_then(|v| {
-        Some(DockerSandboxConfig {
+    value.map(|v| DockerSandboxConfig {
             image: v.get("image").and_then(|i| i.as_str()).map(|s| s.to_string()),
             memory_mb: v.get("memoryMb").and_then(|m| m.as_i64()).map(|m| m as i32),
             cpu_cores: v.get("cpuCores").and_then(|c| c.as_f64()),
         })
-    })
 }
 
 fn parse_tools_config(value: Option<&Value>) -> Option<ToolsConfig> {
-    value.and_then(|v| {
-        Some(ToolsConfig {
+    value.map(|v| ToolsConfig {
             exec: parse_exec_tools_config(v.get("exec")),
             file_ops: parse_file_ops_config(v.get("fileOps")),
             web: parse_web_tools_config(v.get("web")),
         })
-    })
 }
 
 fn parse_exec_tools_config(value: Option<&Value>) -> Option<ExecToolsConfig> {
-    value.and_then(|v| {
-        Some(ExecToolsConfig {
+    value.map(|v| ExecToolsConfig {
             enabled: v.get("enabled").and_then(|e| e.as_bool()),
             host: v.get("host").and_then(|h| h.as_str()).map(|s| s.to_string()),
             safe_bins: v.get("safeBins").and_then(|b| b.as_array()).map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect()),
             trusted_dirs: v.get("trustedDirs").and_then(|d| d.as_array()).map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect()),
         })
-    })
 }
 
 fn parse_file_ops_config(value: Option<&Value>) -> Option<FileOpsConfig> {
-    value.and_then(|v| {
-        Some(FileOpsConfig {
+    value.map(|v| FileOpsConfig {
             enabled: v.get("enabled").and_then(|e| e.as_bool()),
             read_paths: v.get("readPaths").and_then(|p| p.as_array()).map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect()),
             write_paths: v.get("writePaths").and_then(|p| p.as_array()).map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect()),
         })
-    })
 }
 
 fn parse_web_tools_config(value: Option<&Value>) -> Option<WebToolsConfig> {
-    value.and_then(|v| {
-        Some(WebToolsConfig {
+    value.map(|v| WebToolsConfig {
             enabled: v.get("enabled").and_then(|e| e.as_bool()),
             allow_domains: v.get("allowDomains").and_then(|d| d.as_array()).map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect()),
             block_domains: v.get("blockDomains").and_then(|d| d.as_array()).map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect()),
         })
-    })
 }
 
 pub fn get_agent_capabilities(config: Option<&OpenClawAgentConfig>) -> Value {
     match config {
         Some(c) => serde_json::json!({
             "models": {
-                "text": c.model.as_ref().map(|m| m.primary.as_ref()).or(Some("default")).unwrap(),
+                "text": c.model.as_ref().and_then(|m| m.primary.as_deref()).unwrap_or("default"),
                 "image": c.image_model.as_ref().and_then(|m| m.primary.as_ref())
             },
             "features": {
@@ -252,19 +236,22 @@ pub fn get_agent_capabilities(config: Option<&OpenClawAgentConfig>) -> Value {
     }
 }
 
-pub async fn apply_agent_config_to_db(pool: &SqlitePool, agent_id: &str, config: &OpenClawAgentConfig) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+/// Writes the configuration to the agent's row and returns its hash. Takes a
+/// connection so callers can record the change in the same transaction.
+pub async fn apply_agent_config_to_db(conn: &mut SqliteConnection, _agent_id: &str, config: &OpenClawAgentConfig) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
     let config_json = serde_json::to_string(config)?;
     let config_hash = format!("{:x}", Sha256::digest(config_json.as_bytes()));
 
     // Convert complex types to JSON strings
-    let skills_json = config.skills.as_ref().map(|s| serde_json::to_string(s)).transpose()?;
-    let tools_config_json = config.tools.as_ref().map(|t| serde_json::to_string(t)).transpose()?;
-    let memory_search_json = config.memory_search.as_ref().map(|m| serde_json::to_string(m)).transpose()?;
+    let skills_json = config.skills.as_ref().map(serde_json::to_string).transpose()?;
+    let tools_config_json = config.tools.as_ref().map(serde_json::to_string).transpose()?;
+    let memory_search_json = config.memory_search.as_ref().map(serde_json::to_string).transpose()?;
 
-    // Upsert agent with full configuration
+    // Upsert agent with full configuration. Updated in place rather than replaced, so
+    // rows that reference the agent are not cascaded away.
     sqlx::query(
         r#"
-        INSERT OR REPLACE INTO agents (
+        INSERT INTO agents (
             id, name, role, status, workspace, agent_dir,
             primary_model, fallback_model, image_model,
             sandbox_mode, thinking_default, verbose_default,
@@ -283,43 +270,54 @@ pub async fn apply_agent_config_to_db(pool: &SqlitePool, agent_id: &str, config:
             ?, ?,
             ?, CURRENT_TIMESTAMP
         )
+        ON CONFLICT(id) DO UPDATE SET
+            name = excluded.name, workspace = excluded.workspace, agent_dir = excluded.agent_dir,
+            -- While failed over, openclaw.json names the fallback as primary; the
+            -- stored primary and fallback stay so the agent can be switched back
+            primary_model = CASE WHEN agents.primary_model IS NOT NULL
+                    AND agents.current_model IS NOT agents.primary_model
+                    AND excluded.primary_model IS agents.current_model
+                THEN agents.primary_model ELSE excluded.primary_model END,
+            fallback_model = CASE WHEN agents.primary_model IS NOT NULL
+                    AND agents.current_model IS NOT agents.primary_model
+                    AND excluded.primary_model IS agents.current_model
+                THEN agents.fallback_model ELSE excluded.fallback_model END,
+            image_model = excluded.image_model, sandbox_mode = excluded.sandbox_mode,
+            thinking_default = excluded.thinking_default, verbose_default = excluded.verbose_default,
+            max_concurrent = excluded.max_concurrent, timeout_seconds = excluded.timeout_seconds,
+            context_tokens = excluded.context_tokens, skills = excluded.skills,
+            tools_config = excluded.tools_config, memory_search_config = excluded.memory_search_config,
+            heartbeat_enabled = excluded.heartbeat_enabled, subagents_enabled = excluded.subagents_enabled,
+            human_delay_enabled = excluded.human_delay_enabled,
+            block_streaming_enabled = excluded.block_streaming_enabled,
+            context_pruning_enabled = excluded.context_pruning_enabled,
+            openclaw_config_hash = excluded.openclaw_config_hash,
+            updated_at = CURRENT_TIMESTAMP
         "#
     )
     .bind(&config.id)
-    .bind(&config.name.as_deref().unwrap_or(&config.id))
+    .bind(config.name.as_deref().unwrap_or(&config.id))
     .bind(&config.workspace)
     .bind(&config.agent_dir)
-    .bind(&config.model.as_ref().and_then(|m| m.primary.as_ref()))
-    .bind(&config.model.as_ref().and_then(|m| m.fallbacks.as_ref()).and_then(|f| f.first().map(|s| s.to_string())))
-    .bind(&config.image_model.as_ref().and_then(|m| m.primary.as_ref()))
-    .bind(&config.sandbox.as_ref().and_then(|s| s.mode.as_ref()))
-    .bind(&config.params.as_ref().and_then(|p| p.get("thinkingDefault")).and_then(|v| v.as_str()))
-    .bind(&config.params.as_ref().and_then(|p| p.get("verboseDefault")).and_then(|v| v.as_str()))
-    .bind(&config.params.as_ref().and_then(|p| p.get("maxConcurrent")).and_then(|v| v.as_i64()).map(|i| i as i32))
-    .bind(&config.params.as_ref().and_then(|p| p.get("timeoutSeconds")).and_then(|v| v.as_i64()).map(|i| i as i32))
-    .bind(&config.params.as_ref().and_then(|p| p.get("contextTokens")).and_then(|v| v.as_i64()).map(|i| i as i32))
+    .bind(config.model.as_ref().and_then(|m| m.primary.as_ref()))
+    .bind(config.model.as_ref().and_then(|m| m.fallbacks.as_ref()).and_then(|f| f.first().map(|s| s.to_string())))
+    .bind(config.image_model.as_ref().and_then(|m| m.primary.as_ref()))
+    .bind(config.sandbox.as_ref().and_then(|s| s.mode.as_ref()))
+    .bind(config.params.as_ref().and_then(|p| p.get("thinkingDefault")).and_then(|v| v.as_str()))
+    .bind(config.params.as_ref().and_then(|p| p.get("verboseDefault")).and_then(|v| v.as_str()))
+    .bind(config.params.as_ref().and_then(|p| p.get("maxConcurrent")).and_then(|v| v.as_i64()).map(|i| i as i32))
+    .bind(config.params.as_ref().and_then(|p| p.get("timeoutSeconds")).and_then(|v| v.as_i64()).map(|i| i as i32))
+    .bind(config.params.as_ref().and_then(|p| p.get("contextTokens")).and_then(|v| v.as_i64()).map(|i| i as i32))
     .bind(&skills_json)
     .bind(&tools_config_json)
     .bind(&memory_search_json)
-    .bind(&config.heartbeat.as_ref().and_then(|h| h.enabled))
-    .bind(&config.subagents.as_ref().map(|_| true))
-    .bind(&config.human_delay.as_ref().and_then(|h| h.enabled))
-    .bind(&config.params.as_ref().and_then(|p| p.get("blockStreamingDefault")).and_then(|v| v.as_str()).map(|s| s == "on"))
-    .bind(&config.params.as_ref().and_then(|p| p.get("contextPruning")).and_then(|v| v.get("enabled")).and_then(|e| e.as_bool()))
+    .bind(config.heartbeat.as_ref().and_then(|h| h.enabled))
+    .bind(config.subagents.as_ref().map(|_| true))
+    .bind(config.human_delay.as_ref().and_then(|h| h.enabled))
+    .bind(config.params.as_ref().and_then(|p| p.get("blockStreamingDefault")).and_then(|v| v.as_str()).map(|s| s == "on"))
+    .bind(config.params.as_ref().and_then(|p| p.get("contextPruning")).and_then(|v| v.get("enabled")).and_then(|e| e.as_bool()))
     .bind(&config_hash)
-    .execute(pool)
-    .await?;
-
-    // Store configuration snapshot
-    let snapshot_id = uuid::Uuid::new_v4().to_string();
-    sqlx::query(
-        "INSERT INTO openclaw_config_snapshots (id, agent_id, config_hash, raw_config, is_active) VALUES (?, ?, ?, ?, 1)"
-    )
-    .bind(&snapshot_id)
-    .bind(&config.id)
-    .bind(&config_hash)
-    .bind(&config_json)
-    .execute(pool)
+    .execute(&mut *conn)
     .await?;
 
     Ok(config_hash)
@@ -332,29 +330,226 @@ pub fn validate_agent_config_internal(config: &OpenClawAgentConfig) -> Result<()
     }
 
     // Model validation
-    if let Some(model) = &config.model {
-        if let Some(primary) = &model.primary {
-            if primary.is_empty() {
+    if let Some(model) = &config.model
+        && let Some(primary) = &model.primary
+            && primary.is_empty() {
                 return Err("Primary model cannot be empty".to_string());
             }
-        }
-    }
 
     // Sandbox validation
-    if let Some(sandbox) = &config.sandbox {
-        if let Some(mode) = &sandbox.mode {
-            if !["off", "on", "docker"].contains(&mode.as_str()) {
+    if let Some(sandbox) = &config.sandbox
+        && let Some(mode) = &sandbox.mode
+            && !["off", "on", "docker"].contains(&mode.as_str()) {
                 return Err("Invalid sandbox mode. Must be 'off', 'on', or 'docker'".to_string());
             }
-        }
-    }
 
     // Skills validation
-    if let Some(skills) = &config.skills {
-        if skills.is_empty() {
+    if let Some(skills) = &config.skills
+        && skills.is_empty() {
             return Err("Skills array cannot be empty when specified".to_string());
         }
-    }
 
     Ok(())
 }
+
+/// Serializes every rewrite of openclaw.json, so concurrent writers cannot read the
+/// same file and each rename away the other's change
+static OPENCLAW_WRITE_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+/// Rewrites the agent's entry in openclaw.json with `edit`, leaving the rest of the file
+/// as it is. Returns false, without writing, when the agent has no entry there.
+async fn edit_openclaw_agent_entry<F>(
+    agent_id: &str,
+    edit: F,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>
+where
+    F: FnOnce(&mut serde_json::Map<String, Value>) -> Result<(), Box<dyn std::error::Error + Send + Sync>>,
+{
+    let _guard = OPENCLAW_WRITE_LOCK.lock().await;
+    let config_path = crate::openclaw_integration::openclaw_config_path();
+    let content = tokio::fs::read_to_string(&config_path).await?;
+    let mut openclaw: Value = serde_json::from_str(&content)?;
+
+    let entry = openclaw
+        .pointer_mut("/agents/list")
+        .and_then(|list| list.as_array_mut())
+        .and_then(|list| list.iter_mut().find(|entry| entry.get("id").and_then(|id| id.as_str()) == Some(agent_id)))
+        .and_then(|entry| entry.as_object_mut());
+    let Some(entry) = entry else {
+        return Ok(false);
+    };
+    edit(entry)?;
+
+    // Written beside the original and renamed over it, so OpenClaw never reads half a file
+    let staged = format!("{}.tmp-{}", config_path, uuid::Uuid::new_v4());
+    let written = async {
+        tokio::fs::write(&staged, serde_json::to_string_pretty(&openclaw)?).await?;
+        tokio::fs::rename(&staged, &config_path).await?;
+        Ok::<_, Box<dyn std::error::Error + Send + Sync>>(())
+    }
+    .await;
+    if written.is_err() {
+        let _ = tokio::fs::remove_file(&staged).await;
+    }
+    written.map(|_| true)
+}
+
+/// Points an agent at `primary` in openclaw.json. Returns false when the agent has no
+/// entry there.
+pub async fn write_agent_model_to_openclaw(
+    agent_id: &str,
+    primary: &str,
+    fallbacks: &[String],
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    edit_openclaw_agent_entry(agent_id, |entry| {
+        entry.insert("model".to_string(), serde_json::json!({ "primary": primary, "fallbacks": fallbacks }));
+        Ok(())
+    })
+    .await
+}
+
+/// Writes `config` into the agent's entry in openclaw.json. Fields the config models
+/// are replaced, or removed when unset; anything else in the entry is kept. Returns
+/// false when the agent has no entry there.
+pub async fn write_agent_config_to_openclaw(
+    config: &OpenClawAgentConfig,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let fields = serde_json::to_value(config)?;
+    edit_openclaw_agent_entry(&config.id, |entry| {
+        if let Value::Object(fields) = fields {
+            for (key, value) in fields {
+                match value {
+                    Value::Null => {
+                        entry.remove(&key);
+                    }
+                    // Free-form and already in OpenClaw's own spelling
+                    value if key == "params" => {
+                        entry.insert(key, value);
+                    }
+                    value => {
+                        entry.insert(key, openclaw_value(value));
+                    }
+                }
+            }
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// Nested config structs serialize with snake_case keys, but openclaw.json spells
+/// them in camelCase and leaves unset fields out
+fn openclaw_value(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (camel_case(&k), openclaw_value(v)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(openclaw_value).collect()),
+        other => other,
+    }
+}
+
+fn camel_case(key: &str) -> String {
+    let mut parts = key.split('_');
+    let mut out = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            out.extend(first.to_uppercase());
+            out.push_str(chars.as_str());
+        }
+    }
+    out
+}
+
+#[derive(Clone, Copy)]
+enum ParameterType {
+    Text,
+    TextList,
+    Integer,
+}
+
+impl ParameterType {
+    fn accepts(&self, value: &Value) -> bool {
+        value.is_null() || match self {
+            ParameterType::Text => value.is_string(),
+            ParameterType::TextList => value.as_array().is_some_and(|items| items.iter().all(Value::is_string)),
+            ParameterType::Integer => value.is_i64(),
+        }
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            ParameterType::Text => "a string",
+            ParameterType::TextList => "an array of strings",
+            ParameterType::Integer => "an integer",
+        }
+    }
+}
+
+/// Parameters that can be set one at a time, and where each lives in a serialized
+/// `OpenClawAgentConfig`
+const AGENT_PARAMETERS: &[(&str, &str, ParameterType)] = &[
+    ("name", "/name", ParameterType::Text),
+    ("primary_model", "/model/primary", ParameterType::Text),
+    ("fallback_models", "/model/fallbacks", ParameterType::TextList),
+    ("image_model", "/imageModel/primary", ParameterType::Text),
+    ("skills", "/skills", ParameterType::TextList),
+    ("sandbox_mode", "/sandbox/mode", ParameterType::Text),
+    ("thinking_default", "/params/thinkingDefault", ParameterType::Text),
+    ("verbose_default", "/params/verboseDefault", ParameterType::Text),
+    ("max_concurrent", "/params/maxConcurrent", ParameterType::Integer),
+    ("timeout_seconds", "/params/timeoutSeconds", ParameterType::Integer),
+    ("context_tokens", "/params/contextTokens", ParameterType::Integer),
+];
+
+#[derive(Debug, Clone)]
+pub struct ParameterChange {
+    pub name: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+/// Sets each of `params` on `config` (null clears one) and returns those whose value
+/// changed. Unknown parameters and values of the wrong type are rejected.
+pub fn apply_agent_parameters(
+    config: &mut OpenClawAgentConfig,
+    params: &serde_json::Map<String, Value>,
+) -> Result<Vec<ParameterChange>, String> {
+    let mut document = serde_json::to_value(&*config).map_err(|e| e.to_string())?;
+    let mut changes = Vec::new();
+
+    for (name, new) in params {
+        let (_, pointer, kind) = AGENT_PARAMETERS
+            .iter()
+            .find(|(known, _, _)| known == name)
+            .ok_or_else(|| format!("Unknown parameter '{}'", name))?;
+        if !kind.accepts(new) {
+            return Err(format!("Parameter '{}' must be {}", name, kind.describe()));
+        }
+        let old = document.pointer(pointer).cloned().unwrap_or(Value::Null);
+        if old == *new {
+            continue;
+        }
+
+        let mut target = &mut document;
+        for token in pointer.split('/').skip(1) {
+            if !target.is_object() {
+                *target = Value::Object(Default::default());
+            }
+            target = target
+                .as_object_mut()
+                .expect("replaced with an object above")
+                .entry(token)
+                .or_insert(Value::Null);
+        }
+        *target = new.clone();
+        changes.push(ParameterChange { name: name.clone(), old, new: new.clone() });
+    }
+
+    *config = serde_json::from_value(document).map_err(|e| e.to_string())?;
+    Ok(changes)
+}
@@ -1,11 +1,10 @@
 use crate::models::*;
 use futures::StreamExt;
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     Json,
     response::IntoResponse,
-    http::StatusCode,
-    middleware,
+    http::{HeaderMap, StatusCode},
 };
 use sqlx::SqlitePool;
 use chrono::Utc;
@@ -15,17 +14,12 @@ use sha2::{Sha256, Digest};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
-use lru::LruCache;
 use dashmap::DashMap;
 use once_cell::sync::Lazy;
-use validator::Validate;
-use crate::openclaw_integration::{SecurityValidator, CONFIG_CACHE, METRICS, OpenClawMetrics, sync_openclaw_configs};
-use regex::Regex;
-use tracing::{info, warn, error, debug, instrument};
-use metrics::{counter, histogram, gauge};
-use tower::timeout::TimeoutLayer;
-use tower::retry::RetryLayer;
-use tower::limit::RateLimitLayer;
+use crate::openclaw_integration::{SecurityValidator, OpenClawMetrics, sync_openclaw_configs};
+use crate::openclaw_optimization::{CacheKey, HierarchicalCache};
+use tracing::{info, warn, debug, instrument};
+use metrics::{counter, histogram};
 
 // Real-time Event Synchronization and Monitoring
 
@@ -140,6 +134,7 @@ pub static EVENT_BROADCASTER: Lazy<OpenClawEventBroadcaster> = Lazy::new(OpenCla
 #[derive(Clone)]
 pub struct OpenClawHealthMonitor {
     pub pool: SqlitePool,
+    pub cache: Arc<HierarchicalCache>,
     pub last_health_check: Arc<RwLock<chrono::DateTime<Utc>>>,
     pub health_status: Arc<RwLock<HealthStatus>>,
 }
@@ -155,7 +150,7 @@ pub struct HealthStatus {
     pub issues: Vec<String>,
 }
 
-#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize)]
 pub enum HealthLevel {
     Healthy,
     Degraded,
@@ -163,9 +158,10 @@ pub enum HealthLevel {
 }
 
 impl OpenClawHealthMonitor {
-    pub fn new(pool: SqlitePool) -> Self {
+    pub fn new(pool: SqlitePool, cache: Arc<HierarchicalCache>) -> Self {
         Self {
             pool,
+            cache,
             last_health_check: Arc::new(RwLock::new(Utc::now())),
             health_status: Arc::new(RwLock::new(HealthStatus {
                 overall: HealthLevel::Healthy,
@@ -297,12 +293,11 @@ impl OpenClawHealthMonitor {
         let start = std::time::Instant::now();
         
         // Test cache performance
-        let test_key = "health_check_test";
         let test_value = serde_json::json!({"test": true});
         
-        CONFIG_CACHE.put(test_key.to_string(), test_value.clone(), Duration::from_secs(1)).await;
+        self.cache.put(CacheKey::HealthProbe, test_value.clone(), Duration::from_secs(1)).await;
         
-        let cached = CONFIG_CACHE.get(test_key).await;
+        let cached = self.cache.get(&CacheKey::HealthProbe).await;
         let duration = start.elapsed();
         
         if cached.is_none() {
@@ -357,7 +352,7 @@ impl OpenClawHealthMonitor {
     }
 }
 
-/// Enhanced API endpoints with monitoring and events
+// Enhanced API endpoints with monitoring and events
 
 /// Get real-time events via Server-Sent Events
 pub async fn get_openclaw_events(
@@ -381,7 +376,7 @@ pub async fn get_openclaw_events(
         .header("Content-Type", "text/event-stream")
         .header("Cache-Control", "no-cache")
         .header("Connection", "keep-alive")
-        .body(axum::body::Body::from_stream(stream.map(|item| Ok::<_, std::convert::Infallible>(item))))
+        .body(axum::body::Body::from_stream(stream.map(Ok::<_, std::convert::Infallible>)))
         .unwrap()
 }
 
@@ -389,7 +384,7 @@ pub async fn get_openclaw_events(
 pub async fn get_openclaw_health(
     State(state): State<crate::AppState>,
 ) -> Result<Json<HealthStatus>, (StatusCode, String)> {
-    let monitor = OpenClawHealthMonitor::new(state.pool.clone());
+    let monitor = OpenClawHealthMonitor::new(state.pool.clone(), state.cache.clone());
     let status = monitor.perform_health_check().await;
     Ok(Json(status))
 }
@@ -398,11 +393,11 @@ pub async fn get_openclaw_health(
 pub async fn get_openclaw_metrics(
     State(state): State<crate::AppState>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    let monitor = OpenClawHealthMonitor::new(state.pool.clone());
+    let monitor = OpenClawHealthMonitor::new(state.pool.clone(), state.cache.clone());
     let health_status = monitor.get_current_status().await;
     
     // Get cache statistics
-    let cache_stats = get_cache_statistics().await;
+    let cache_stats = state.cache.get_metrics().await;
     
     // Get event statistics
     let event_stats = get_event_statistics().await;
@@ -470,8 +465,12 @@ pub async fn refresh_openclaw_config(
 pub async fn update_agent_parameters_with_events(
     Path(agent_id): Path<String>,
     State(state): State<crate::AppState>,
+    headers: HeaderMap,
+    Query(query): Query<crate::agent_config_history::ChangeReasonQuery>,
     Json(params): Json<serde_json::Value>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "write").await?;
+
     // Validate agent ID
     SecurityValidator::validate_agent_id(&agent_id)
         .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
@@ -489,7 +488,7 @@ pub async fn update_agent_parameters_with_events(
     EVENT_BROADCASTER.broadcast(ConfigSyncEvent {
         event_type: SyncEventType::ConfigChanged,
         agent_id: agent_id.clone(),
-        config_hash: current_agent.openclaw_config_hash.unwrap_or_default(),
+        config_hash: current_agent.openclaw_config_hash.clone().unwrap_or_default(),
         timestamp: Utc::now(),
         data: Some(serde_json::json!({
             "action": "update_started",
@@ -498,7 +497,7 @@ pub async fn update_agent_parameters_with_events(
     }).await;
 
     // Perform the update
-    match crate::openclaw_integration::update_agent_parameters(Path(agent_id.clone()), State(state), Json(params)).await {
+    match crate::openclaw_integration::update_agent_parameters(Path(agent_id.clone()), State(state.clone()), headers, Query(query), Json(params)).await {
         Ok(result) => {
             // Get updated agent for new hash
             let updated_agent = sqlx::query_as::<sqlx::Sqlite, Agent>(
@@ -543,16 +542,6 @@ pub async fn update_agent_parameters_with_events(
 
 // Helper functions for monitoring
 
-async fn get_cache_statistics() -> serde_json::Value {
-    // This would require access to cache internals
-    // For now, return placeholder data
-    serde_json::json!({
-        "cache_size": "unknown",
-        "hit_rate": "unknown",
-        "evictions": "unknown"
-    })
-}
-
 async fn get_event_statistics() -> serde_json::Value {
     let events = EVENT_BROADCASTER.get_event_history(Some(1000)).await;
     
@@ -590,7 +579,7 @@ pub async fn monitoring_middleware(
     histogram!("http_request_duration", "route" => route.clone(), "status" => status.as_u16().to_string()).record(duration.as_secs_f64());
     
     if status.is_server_error() {
-        counter!("http_server_errors_total", "route" => route);
+        counter!("http_server_errors_total", "route" => route).increment(1);
     }
     
     response
@@ -1,6 +1,6 @@
 use crate::models::*;
 use crate::db::SqlitePool;
-use axum::{extract::State, Json, response::IntoResponse, http::StatusCode};
+use axum::{extract::State, Json, response::IntoResponse, http::{HeaderMap, StatusCode}};
 use chrono::Utc;
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
@@ -8,187 +8,324 @@ use serde_json::Value;
 use tokio::sync::RwLock;
 use std::sync::Arc;
 use lru::LruCache;
-use dashmap::DashMap;
-use metrics::{counter, histogram, gauge};
-use tracing::{info, warn, error, instrument};
+use metrics::{counter, gauge};
+use tracing::{info, warn, instrument};
 use std::time::Duration;
+use crate::host_metrics::{HostSample, HostSampler, ProcReading, ResourceAlert, ResourceThresholds, ThresholdLevel, MONITOR_HISTORY};
 
 // Enhanced caching infrastructure
 
+/// What a cache entry holds
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CacheKey {
+    /// Every agent parsed from openclaw.json
+    OpenClawConfig,
+    /// One agent's entry in openclaw.json
+    AgentConfig(String),
+    /// Written and read back by the OpenClaw health check
+    HealthProbe,
+}
+
+impl CacheKey {
+    /// Whether the entry was read from openclaw.json
+    pub fn is_openclaw_config(&self) -> bool {
+        matches!(self, CacheKey::OpenClawConfig | CacheKey::AgentConfig(_))
+    }
+}
+
+/// Two LRU tiers shared through `AppState`. New entries go to L1; entries L1
+/// evicts are demoted to L2, and an L2 hit promotes the entry back. An entry
+/// lives in one tier at a time.
+///
+/// Locks are taken in the order L1, L2, metrics, and a lock is never requested
+/// while one later in that order is held. A fresh L1 hit only needs a read lock
+/// on L1; everything else holds the L1 write lock throughout.
 #[derive(Clone)]
 pub struct HierarchicalCache {
-    l1_cache: Arc<RwLock<LruCache<String, CachedConfig>>>,  // Memory cache
-    l2_cache: Arc<RwLock<LruCache<String, CachedConfig>>>,  // Disk cache
-    cache_metrics: CacheMetrics,
+    l1_cache: Arc<RwLock<LruCache<CacheKey, CachedConfig>>>,  // Hot entries
+    l2_cache: Arc<RwLock<LruCache<CacheKey, CachedConfig>>>,  // Entries demoted from L1
+    cache_metrics: Arc<RwLock<CacheMetrics>>,
 }
 
 #[derive(Clone)]
 struct CachedConfig {
     data: Value,
-    hash: String,
     cached_at: chrono::DateTime<Utc>,
     ttl: Duration,
-    access_count: u64,
-    last_accessed: chrono::DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl CachedConfig {
+    fn is_fresh(&self, now: chrono::DateTime<Utc>) -> bool {
+        chrono::Duration::from_std(self.ttl).is_ok_and(|ttl| self.cached_at + ttl > now)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CacheMetrics {
     pub l1_hits: u64,
     pub l1_misses: u64,
     pub l2_hits: u64,
     pub l2_misses: u64,
+    /// Entries dropped from L2 to make room
     pub evictions: u64,
+    /// Entries moved from L1 to L2 to make room
+    pub demotions: u64,
+    pub expirations: u64,
+    pub invalidations: u64,
     pub total_requests: u64,
+    pub l1_entries: usize,
+    pub l2_entries: usize,
+    pub hit_rate: f64,
+}
+
+/// Result of loading agent configs from openclaw.json into the cache
+#[derive(Debug, Serialize)]
+pub struct CacheWarmReport {
+    pub warmed: Vec<String>,
+    /// Requested agents with no entry in openclaw.json
+    pub missing: Vec<String>,
 }
 
+/// Seconds between checks of openclaw.json for changes
+const CONFIG_WATCH_INTERVAL_SECS: u64 = 5;
+
 impl HierarchicalCache {
     pub fn new(l1_capacity: usize, l2_capacity: usize) -> Self {
+        let capacity = |n: usize| std::num::NonZeroUsize::new(n.max(1)).unwrap();
         Self {
-            l1_cache: Arc::new(RwLock::new(LruCache::new(
-                std::num::NonZeroUsize::new(l1_capacity).unwrap()
-            ))),
-            l2_cache: Arc::new(RwLock::new(LruCache::new(
-                std::num::NonZeroUsize::new(l2_capacity).unwrap()
-            ))),
-            cache_metrics: CacheMetrics {
-                l1_hits: 0,
-                l1_misses: 0,
-                l2_hits: 0,
-                l2_misses: 0,
-                evictions: 0,
-                total_requests: 0,
-            },
+            l1_cache: Arc::new(RwLock::new(LruCache::new(capacity(l1_capacity)))),
+            l2_cache: Arc::new(RwLock::new(LruCache::new(capacity(l2_capacity)))),
+            cache_metrics: Arc::new(RwLock::new(CacheMetrics::default())),
         }
     }
 
     #[instrument(skip(self))]
-    pub async fn get(&self, key: &str) -> Option<Value> {
+    pub async fn get(&self, key: &CacheKey) -> Option<Value> {
         let now = Utc::now();
-        
-        // Try L1 cache first
-        {
-            let mut l1_cache = self.l1_cache.write().await;
-            self.cache_metrics.total_requests += 1;
-            
-            if let Some(cached) = l1_cache.get_mut(key) {
-                if cached.cached_at + cached.ttl > now {
-                    cached.access_count += 1;
-                    cached.last_accessed = now;
-                    self.cache_metrics.l1_hits += 1;
-                    counter!("cache_l1_hits_total").increment(1);
-                    return Some(cached.data.clone());
-                } else {
-                    // Expired, remove from L1
-                    l1_cache.pop(key);
-                }
-            } else {
-                self.cache_metrics.l1_misses += 1;
-                counter!("cache_l1_misses_total").increment(1);
+
+        // Fresh L1 hits are served under the read lock. Their recency is refreshed
+        // only when L1 is free, so a busy cache ages its hot entries a little early.
+        let hit = self.l1_cache.read().await.peek(key).filter(|cached| cached.is_fresh(now)).map(|cached| cached.data.clone());
+        if let Some(data) = hit {
+            if let Ok(mut l1_cache) = self.l1_cache.try_write() {
+                l1_cache.promote(key);
             }
+            let mut metrics = self.cache_metrics.write().await;
+            metrics.total_requests += 1;
+            metrics.l1_hits += 1;
+            counter!("cache_l1_hits_total").increment(1);
+            return Some(data);
         }
 
-        // Try L2 cache
-        {
-            let mut l2_cache = self.l2_cache.write().await;
-            if let Some(cached) = l2_cache.get_mut(key) {
-                if cached.cached_at + cached.ttl > now {
-                    cached.access_count += 1;
-                    cached.last_accessed = now;
-                    self.cache_metrics.l2_hits += 1;
-                    counter!("cache_l2_hits_total").increment(1);
-                    
-                    // Promote to L1 cache
-                    let mut l1_cache = self.l1_cache.write().await;
-                    l1_cache.put(key.to_string(), cached.clone());
-                    
-                    return Some(cached.data.clone());
-                } else {
-                    // Expired, remove from L2
-                    l2_cache.pop(key);
-                }
-            } else {
-                self.cache_metrics.l2_misses += 1;
-                counter!("cache_l2_misses_total").increment(1);
+        let mut l1_cache = self.l1_cache.write().await;
+        let mut l2_cache = self.l2_cache.write().await;
+        let mut metrics = self.cache_metrics.write().await;
+        metrics.total_requests += 1;
+
+        // L1 again, since it may have been filled since the read lock was released
+        match l1_cache.get(key) {
+            Some(cached) if cached.is_fresh(now) => {
+                metrics.l1_hits += 1;
+                counter!("cache_l1_hits_total").increment(1);
+                return Some(cached.data.clone());
+            }
+            Some(_) => {
+                l1_cache.pop(key);
+                metrics.expirations += 1;
+                metrics.l1_misses += 1;
             }
+            None => metrics.l1_misses += 1,
         }
+        counter!("cache_l1_misses_total").increment(1);
 
+        // Then L2, promoting a hit back to L1
+        match l2_cache.pop(key) {
+            Some(cached) if cached.is_fresh(now) => {
+                metrics.l2_hits += 1;
+                counter!("cache_l2_hits_total").increment(1);
+                let data = cached.data.clone();
+                Self::insert(&mut l1_cache, &mut l2_cache, &mut metrics, key.clone(), cached);
+                return Some(data);
+            }
+            Some(_) => {
+                metrics.expirations += 1;
+                metrics.l2_misses += 1;
+            }
+            None => metrics.l2_misses += 1,
+        }
+        counter!("cache_l2_misses_total").increment(1);
         None
     }
 
     #[instrument(skip(self, data))]
-    pub async fn put(&self, key: String, data: Value, ttl: Duration) {
-        let now = Utc::now();
-        let hash = format!("{:x}", sha2::Sha256::digest(data.to_string().as_bytes()));
-        
+    pub async fn put(&self, key: CacheKey, data: Value, ttl: Duration) {
         let cached = CachedConfig {
-            data: data.clone(),
-            hash,
-            cached_at: now,
+            data,
+            cached_at: Utc::now(),
             ttl,
-            access_count: 0,
-            last_accessed: now,
         };
-        
-        // Store in both caches
-        {
-            let mut l1_cache = self.l1_cache.write().await;
-            l1_cache.put(key.clone(), cached.clone());
+
+        let mut l1_cache = self.l1_cache.write().await;
+        let mut l2_cache = self.l2_cache.write().await;
+        let mut metrics = self.cache_metrics.write().await;
+        l2_cache.pop(&key);
+        Self::insert(&mut l1_cache, &mut l2_cache, &mut metrics, key, cached);
+        counter!("cache_puts_total").increment(1);
+    }
+
+    fn insert(
+        l1_cache: &mut LruCache<CacheKey, CachedConfig>,
+        l2_cache: &mut LruCache<CacheKey, CachedConfig>,
+        metrics: &mut CacheMetrics,
+        key: CacheKey,
+        cached: CachedConfig,
+    ) {
+        // `push` also hands back the old value when the key was already present
+        let Some((demoted_key, demoted)) = l1_cache.push(key.clone(), cached) else {
+            return;
+        };
+        if demoted_key == key {
+            return;
         }
-        
-        {
-            let mut l2_cache = self.l2_cache.write().await;
-            l2_cache.put(key, cached);
+        metrics.demotions += 1;
+        if let Some((evicted_key, _)) = l2_cache.push(demoted_key.clone(), demoted)
+            && evicted_key != demoted_key {
+                metrics.evictions += 1;
+                counter!("cache_evictions_total").increment(1);
+            }
+    }
+
+    /// Drops one entry from both tiers
+    pub async fn invalidate(&self, key: &CacheKey) -> bool {
+        self.invalidate_matching(|candidate| candidate == key).await > 0
+    }
+
+    /// Drops every entry whose key matches, returning how many were dropped
+    pub async fn invalidate_matching(&self, matches: impl Fn(&CacheKey) -> bool) -> usize {
+        let mut l1_cache = self.l1_cache.write().await;
+        let mut l2_cache = self.l2_cache.write().await;
+        let mut removed = 0;
+        for cache in [&mut *l1_cache, &mut *l2_cache] {
+            let keys: Vec<CacheKey> = cache.iter().map(|(key, _)| key).filter(|key| matches(key)).cloned().collect();
+            for key in keys {
+                cache.pop(&key);
+                removed += 1;
+            }
         }
-        
-        counter!("cache_puts_total").increment(1);
+        self.cache_metrics.write().await.invalidations += removed as u64;
+        counter!("cache_invalidations_total").increment(removed as u64);
+        removed
+    }
+
+    /// Drops what the cache knows about one agent's OpenClaw config, including
+    /// the full list it is part of
+    pub async fn invalidate_agent(&self, agent_id: &str) -> usize {
+        self.invalidate_matching(|key| match key {
+            CacheKey::OpenClawConfig => true,
+            CacheKey::AgentConfig(id) => id == agent_id,
+            CacheKey::HealthProbe => false,
+        })
+        .await
     }
 
     pub async fn get_metrics(&self) -> CacheMetrics {
-        self.cache_metrics.clone()
+        let l1_entries = self.l1_cache.read().await.len();
+        let l2_entries = self.l2_cache.read().await.len();
+        let mut metrics = self.cache_metrics.read().await.clone();
+        metrics.l1_entries = l1_entries;
+        metrics.l2_entries = l2_entries;
+        metrics.hit_rate = if metrics.total_requests == 0 {
+            0.0
+        } else {
+            (metrics.l1_hits + metrics.l2_hits) as f64 / metrics.total_requests as f64
+        };
+        metrics
     }
 
-    pub async fn warm_cache_for_agents(&self, agent_ids: Vec<String>) {
-        info!("Warming cache for {} agents", agent_ids.len());
-        
-        for agent_id in agent_ids {
-            // This would typically fetch from database or external source
-            // For now, we'll simulate cache warming
-            let mock_config = serde_json::json!({
-                "agent_id": agent_id,
-                "warming": true,
-                "timestamp": Utc::now()
-            });
-            
-            self.put(
-                format!("agent_config:{}", agent_id),
-                mock_config,
-                Duration::from_secs(3600) // 1 hour TTL
-            ).await;
+    /// Loads agent configs from openclaw.json into the cache; every agent when
+    /// `agent_ids` is empty
+    pub async fn warm_cache_for_agents(&self, agent_ids: &[String], ttl: Duration) -> Result<CacheWarmReport, String> {
+        info!("Warming cache for {} agents", if agent_ids.is_empty() { "all".to_string() } else { agent_ids.len().to_string() });
+
+        let configs = crate::openclaw_integration::read_and_parse_openclaw_config()
+            .await
+            .map_err(|e| format!("Cannot read openclaw config: {}", e))?;
+        let all = serde_json::to_value(&configs).map_err(|e| e.to_string())?;
+        self.put(
+            CacheKey::OpenClawConfig,
+            all,
+            Duration::from_secs(crate::config::current().openclaw_config_ttl_seconds),
+        )
+        .await;
+
+        let mut report = CacheWarmReport { warmed: Vec::new(), missing: Vec::new() };
+        for config in &configs {
+            if !agent_ids.is_empty() && !agent_ids.contains(&config.id) {
+                continue;
+            }
+            let value = serde_json::to_value(config).map_err(|e| e.to_string())?;
+            self.put(CacheKey::AgentConfig(config.id.clone()), value, ttl).await;
+            report.warmed.push(config.id.clone());
+        }
+        report.missing = agent_ids
+            .iter()
+            .filter(|id| !report.warmed.contains(id))
+            .cloned()
+            .collect();
+
+        info!("Cache warming completed: {} warmed, {} missing", report.warmed.len(), report.missing.len());
+        Ok(report)
+    }
+
+    /// Polls openclaw.json and drops the cached OpenClaw configs whenever the file
+    /// changes on disk, including edits made outside ClawController
+    pub async fn watch_openclaw_config(&self) {
+        let path = crate::openclaw_integration::openclaw_config_path();
+        let fingerprint = |metadata: std::fs::Metadata| (metadata.modified().ok(), metadata.len());
+        let mut last = tokio::fs::metadata(&path).await.ok().map(fingerprint);
+        loop {
+            tokio::time::sleep(Duration::from_secs(CONFIG_WATCH_INTERVAL_SECS)).await;
+            let current = tokio::fs::metadata(&path).await.ok().map(fingerprint);
+            if current == last {
+                continue;
+            }
+            last = current;
+
+            let removed = self.invalidate_matching(CacheKey::is_openclaw_config).await;
+            info!("{} changed, dropped {} cached OpenClaw configs", path, removed);
+            crate::openclaw_monitoring::EVENT_BROADCASTER.broadcast(crate::openclaw_monitoring::ConfigSyncEvent {
+                event_type: crate::openclaw_monitoring::SyncEventType::ConfigChanged,
+                agent_id: "all".to_string(),
+                config_hash: String::new(),
+                timestamp: Utc::now(),
+                data: Some(serde_json::json!({ "source": "file_watcher", "invalidated": removed })),
+            }).await;
         }
-        
-        info!("Cache warming completed");
     }
 }
 
 // Resource pool management
 
+/// Agents that can take work and the tasks each one is working on. Shared through
+/// `AppState`, hydrated from `agents` and the assigned tasks, and kept current as
+/// tasks are assigned, routed and finished.
 #[derive(Clone)]
 pub struct AgentPool {
     available_agents: Arc<RwLock<Vec<Agent>>>,
-    busy_agents: Arc<RwLock<HashMap<String, BusyAgent>>>,
+    /// Active allocations per agent id
+    busy_agents: Arc<RwLock<HashMap<String, Vec<BusyAgent>>>>,
     resource_monitor: Arc<ResourceMonitor>,
     load_balancer: Arc<LoadBalancer>,
-    pool_metrics: PoolMetrics,
+    pool_metrics: Arc<RwLock<PoolMetrics>>,
 }
 
-#[derive(Clone)]
-struct BusyAgent {
-    agent: Agent,
-    task_id: String,
-    started_at: chrono::DateTime<Utc>,
-    estimated_completion: chrono::DateTime<Utc>,
-    resource_usage: ResourceUsage,
+/// One task an agent is working on
+#[derive(Debug, Clone, Serialize)]
+pub struct BusyAgent {
+    pub task_id: String,
+    pub started_at: chrono::DateTime<Utc>,
+    pub estimated_completion: chrono::DateTime<Utc>,
+    pub resource_usage: ResourceUsage,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -234,16 +371,62 @@ pub struct HealthChecker {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PoolMetrics {
     pub total_agents: usize,
+    /// Agents with at least one free slot
     pub available_agents: usize,
+    /// Agents with at least one task
     pub busy_agents: usize,
+    /// Sum of every agent's `max_concurrent`
+    pub total_capacity: usize,
+    pub allocated_slots: usize,
+    pub utilization: f64,
     pub average_wait_time: Duration,
     pub total_requests: u64,
     pub successful_allocations: u64,
     pub failed_allocations: u64,
 }
 
+#[derive(Debug, Serialize)]
+pub struct PoolAgentStatus {
+    pub agent_id: String,
+    pub name: String,
+    pub status: AgentStatus,
+    pub capacity: usize,
+    pub load: f64,
+    pub tasks: Vec<BusyAgent>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PoolStatus {
+    pub algorithm: &'static str,
+    pub metrics: PoolMetrics,
+    pub agents: Vec<PoolAgentStatus>,
+}
+
+/// Task statuses that hold one of the assignee's slots
+const ACTIVE_TASK_STATUSES: [&str; 2] = ["ASSIGNED", "IN_PROGRESS"];
+
+impl LoadBalancingAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LoadBalancingAlgorithm::RoundRobin => "round_robin",
+            LoadBalancingAlgorithm::LeastConnections => "least_connections",
+            LoadBalancingAlgorithm::WeightedRoundRobin { .. } => "weighted_round_robin",
+            LoadBalancingAlgorithm::PerformanceBased => "performance_based",
+            LoadBalancingAlgorithm::CostOptimized => "cost_optimized",
+            LoadBalancingAlgorithm::PredictiveBased => "predictive_based",
+        }
+    }
+}
+
+/// Concurrent tasks the agent may hold
+fn agent_capacity(agent: &Agent) -> usize {
+    agent.max_concurrent.unwrap_or(1).max(1) as usize
+}
+
 impl AgentPool {
     pub fn new(agents: Vec<Agent>) -> Self {
+        let total_agents = agents.len();
+        let total_capacity = agents.iter().map(agent_capacity).sum();
         Self {
             available_agents: Arc::new(RwLock::new(agents)),
             busy_agents: Arc::new(RwLock::new(HashMap::new())),
@@ -260,175 +443,360 @@ impl AgentPool {
                     max_failures: 3,
                 },
             }),
-            pool_metrics: PoolMetrics {
-                total_agents: 0,
-                available_agents: 0,
+            pool_metrics: Arc::new(RwLock::new(PoolMetrics {
+                total_agents,
+                available_agents: total_agents,
                 busy_agents: 0,
+                total_capacity,
+                allocated_slots: 0,
+                utilization: 0.0,
                 average_wait_time: Duration::from_secs(0),
                 total_requests: 0,
                 successful_allocations: 0,
                 failed_allocations: 0,
+            })),
+        }
+    }
+
+    /// A pool holding every agent that can take work and the tasks assigned to them
+    pub async fn load(pool: &SqlitePool) -> Result<Self, sqlx::Error> {
+        let agent_pool = Self::new(Vec::new());
+        agent_pool.refresh(pool).await?;
+        Ok(agent_pool)
+    }
+
+    /// Reloads agents and their active tasks from the database. Allocations that
+    /// survive keep their start time.
+    pub async fn refresh(&self, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+        let agents = sqlx::query_as::<sqlx::Sqlite, Agent>(
+            "SELECT * FROM agents
+             WHERE is_deleted = 0 AND is_active = 1
+               AND status NOT IN ('OFFLINE', 'SUSPENDED', 'MAINTENANCE', 'ERROR')
+             ORDER BY id"
+        )
+        .fetch_all(pool)
+        .await?;
+        let assigned: Vec<(String, String, chrono::DateTime<Utc>)> = sqlx::query_as(
+            "SELECT id, assignee_id, updated_at FROM tasks
+             WHERE assignee_id IS NOT NULL AND status IN ('ASSIGNED', 'IN_PROGRESS')"
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let mut available_agents = self.available_agents.write().await;
+        let mut busy_agents = self.busy_agents.write().await;
+        let mut allocations: HashMap<String, Vec<BusyAgent>> = HashMap::new();
+        for (task_id, agent_id, assigned_at) in assigned {
+            let started_at = busy_agents
+                .get(&agent_id)
+                .and_then(|tasks| tasks.iter().find(|busy| busy.task_id == task_id))
+                .map(|busy| busy.started_at)
+                .unwrap_or(assigned_at);
+            allocations.entry(agent_id).or_default().push(Self::allocation(task_id, started_at));
+        }
+        *available_agents = agents;
+        *busy_agents = allocations;
+        self.update_counts(&available_agents, &busy_agents).await;
+        Ok(())
+    }
+
+    fn allocation(task_id: String, started_at: chrono::DateTime<Utc>) -> BusyAgent {
+        BusyAgent {
+            task_id,
+            started_at,
+            estimated_completion: started_at + chrono::Duration::minutes(30),
+            resource_usage: ResourceUsage {
+                cpu_percent: 0.0,
+                memory_mb: 0,
+                network_io_mb: 0,
+                disk_io_mb: 0,
+                api_calls: 0,
+                cost_usd: 0.0,
             },
         }
     }
 
+    async fn update_counts(&self, agents: &[Agent], busy_agents: &HashMap<String, Vec<BusyAgent>>) {
+        let load = |agent: &Agent| busy_agents.get(&agent.id).map_or(0, Vec::len);
+        let mut metrics = self.pool_metrics.write().await;
+        metrics.total_agents = agents.len();
+        metrics.available_agents = agents.iter().filter(|agent| load(agent) < agent_capacity(agent)).count();
+        metrics.busy_agents = agents.iter().filter(|agent| load(agent) > 0).count();
+        metrics.total_capacity = agents.iter().map(agent_capacity).sum();
+        metrics.allocated_slots = busy_agents.values().map(Vec::len).sum();
+        metrics.utilization = if metrics.total_capacity == 0 {
+            0.0
+        } else {
+            metrics.allocated_slots as f64 / metrics.total_capacity as f64
+        };
+
+        gauge!("agent_pool_available_agents").set(metrics.available_agents as f64);
+        gauge!("agent_pool_busy_agents").set(metrics.busy_agents as f64);
+    }
+
     #[instrument(skip(self, requirements))]
     pub async fn get_optimal_agent(&self, requirements: &TaskRequirements) -> Option<Agent> {
         let start_time = std::time::Instant::now();
-        self.pool_metrics.total_requests += 1;
-        
+        let total_requests = {
+            let mut metrics = self.pool_metrics.write().await;
+            metrics.total_requests += 1;
+            metrics.total_requests
+        };
+
         let available_agents = self.available_agents.read().await;
         let busy_agents = self.busy_agents.read().await;
-        
-        // Filter agents based on requirements
+        let load = |agent: &Agent| busy_agents.get(&agent.id).map_or(0, Vec::len);
+        let load_ratio = |agent: &Agent| load(agent) as f64 / agent_capacity(agent) as f64;
+
+        // Filter agents based on requirements and free slots
         let candidates: Vec<&Agent> = available_agents
             .iter()
+            .filter(|agent| load(agent) < agent_capacity(agent))
             .filter(|agent| self.meets_requirements(agent, requirements))
             .collect();
-        
+
         if candidates.is_empty() {
             warn!("No available agents meet requirements");
-            self.pool_metrics.failed_allocations += 1;
+            self.pool_metrics.write().await.failed_allocations += 1;
             return None;
         }
-        
+
         // Select best agent based on load balancing algorithm
         let selected_agent = match &self.load_balancer.algorithm {
-            LoadBalancingAlgorithm::RoundRobin => {
-                candidates[self.pool_metrics.total_requests as usize % candidates.len()].clone()
+            LoadBalancingAlgorithm::RoundRobin => Some(candidates[total_requests as usize % candidates.len()]),
+            LoadBalancingAlgorithm::LeastConnections => candidates
+                .iter()
+                .min_by(|a, b| load_ratio(a).total_cmp(&load_ratio(b)))
+                .copied(),
+            LoadBalancingAlgorithm::WeightedRoundRobin { weights } => candidates
+                .iter()
+                .max_by(|a, b| {
+                    let share = |agent: &Agent| weights.get(&agent.id).copied().unwrap_or(1.0) / (load(agent) + 1) as f64;
+                    share(a).total_cmp(&share(b))
+                })
+                .copied(),
+            LoadBalancingAlgorithm::PerformanceBased => candidates
+                .iter()
+                .max_by(|a, b| {
+                    let performance = |agent: &Agent| {
+                        agent.health_score.unwrap_or(100.0 - agent.model_failure_count as f64) - load_ratio(agent) * 10.0
+                    };
+                    performance(a).total_cmp(&performance(b))
+                })
+                .copied(),
+            LoadBalancingAlgorithm::CostOptimized => candidates
+                .iter()
+                .min_by(|a, b| self.estimate_agent_cost(a).total_cmp(&self.estimate_agent_cost(b)))
+                .copied(),
+            LoadBalancingAlgorithm::PredictiveBased => {
+                // Least estimated work still ahead of the agent
+                let now = Utc::now();
+                let remaining = |agent: &Agent| -> i64 {
+                    busy_agents
+                        .get(&agent.id)
+                        .map(|tasks| tasks.iter().map(|busy| (busy.estimated_completion - now).num_seconds().max(0)).sum())
+                        .unwrap_or(0)
+                };
+                candidates.iter().min_by_key(|agent| remaining(agent)).copied()
             }
-            LoadBalancingAlgorithm::LeastConnections => {
-                candidates.iter()
-                    .min_by_key(|agent| {
-                        busy_agents.get(&agent.id).map(|_| 1).unwrap_or(0)
-                    })
-                    .copied()
-            }
-            LoadBalancingAlgorithm::PerformanceBased => {
-                candidates.iter()
-                    .max_by_key(|agent| {
-                        // This would typically use performance metrics
-                        // For now, use a simple heuristic
-                        agent.model_failure_count
-                    })
-                    .copied()
+        };
+
+        let mut metrics = self.pool_metrics.write().await;
+        match selected_agent {
+            Some(agent) => {
+                metrics.successful_allocations += 1;
+                let wait_time = start_time.elapsed();
+                let n = metrics.successful_allocations as u32;
+                metrics.average_wait_time = (metrics.average_wait_time * (n - 1) + wait_time) / n;
+                Some(agent.clone())
             }
-            LoadBalancingAlgorithm::CostOptimized => {
-                candidates.iter()
-                    .min_by_key(|agent| {
-                        // Estimate cost based on model and configuration
-                        self.estimate_agent_cost(agent)
-                    })
-                    .copied()
+            None => {
+                metrics.failed_allocations += 1;
+                None
             }
-            _ => candidates[0],
-        };
-        
-        if let Some(agent) = selected_agent {
-            self.pool_metrics.successful_allocations += 1;
-            let wait_time = start_time.elapsed();
-            self.pool_metrics.average_wait_time = 
-                (self.pool_metrics.average_wait_time + wait_time) / 2;
-            
-            gauge!("agent_pool_available_agents").set(available_agents.len() as f64);
-            gauge!("agent_pool_busy_agents").set(busy_agents.len() as f64);
-            
-            Some(agent.clone())
-        } else {
-            self.pool_metrics.failed_allocations += 1;
-            None
         }
     }
 
+    /// Whether the agent is in the pool and has a free slot for `task_id`; a task
+    /// the agent already holds always fits
+    pub async fn has_capacity(&self, agent_id: &str, task_id: &str) -> bool {
+        let available_agents = self.available_agents.read().await;
+        let busy_agents = self.busy_agents.read().await;
+        let Some(agent) = available_agents.iter().find(|agent| agent.id == agent_id) else {
+            return false;
+        };
+        let tasks = busy_agents.get(agent_id).map(Vec::as_slice).unwrap_or_default();
+        tasks.iter().any(|busy| busy.task_id == task_id) || tasks.len() < agent_capacity(agent)
+    }
+
+    /// Slots the agent has left for new tasks; none when it is not in the pool
+    pub async fn free_slots(&self, agent_id: &str) -> usize {
+        let available_agents = self.available_agents.read().await;
+        let busy_agents = self.busy_agents.read().await;
+        available_agents
+            .iter()
+            .find(|agent| agent.id == agent_id)
+            .map(|agent| agent_capacity(agent).saturating_sub(busy_agents.get(agent_id).map_or(0, Vec::len)))
+            .unwrap_or(0)
+    }
+
+    /// Gives one of the agent's slots to the task, moving the task off any other
+    /// agent. Fails when the agent is not in the pool or all its slots are taken.
     #[instrument(skip(self, agent_id, task_id))]
     pub async fn allocate_agent(&self, agent_id: &str, task_id: String) -> Result<(), String> {
-        let mut available_agents = self.available_agents.write().await;
+        self.allocate(agent_id, task_id, true).await
+    }
+
+    async fn allocate(&self, agent_id: &str, task_id: String, enforce_capacity: bool) -> Result<(), String> {
+        let available_agents = self.available_agents.read().await;
         let mut busy_agents = self.busy_agents.write().await;
-        
-        // Find and remove from available
-        let agent_pos = available_agents.iter()
-            .position(|agent| agent.id == agent_id);
-        
-        if let Some(pos) = agent_pos {
-            let agent = available_agents.remove(pos);
-            
-            // Add to busy
-            let busy_agent = BusyAgent {
-                agent: agent.clone(),
-                task_id: task_id.clone(),
-                started_at: Utc::now(),
-                estimated_completion: Utc::now() + chrono::Duration::minutes(30),
-                resource_usage: ResourceUsage {
-                    cpu_percent: 0.0,
-                    memory_mb: 0,
-                    network_io_mb: 0,
-                    disk_io_mb: 0,
-                    api_calls: 0,
-                    cost_usd: 0.0,
-                },
-            };
-            
-            busy_agents.insert(agent_id.to_string(), busy_agent);
-            
-            self.pool_metrics.available_agents = available_agents.len();
-            self.pool_metrics.busy_agents = busy_agents.len();
-            
-            info!("Allocated agent {} to task {}", agent_id, task_id);
-            Ok(())
-        } else {
-            Err(format!("Agent {} not available", agent_id))
+
+        let Some(agent) = available_agents.iter().find(|agent| agent.id == agent_id) else {
+            return Err(format!("Agent {} is not available", agent_id));
+        };
+        let tasks = busy_agents.get(agent_id).map(Vec::as_slice).unwrap_or_default();
+        if tasks.iter().any(|busy| busy.task_id == task_id) {
+            return Ok(());
         }
+        if enforce_capacity && tasks.len() >= agent_capacity(agent) {
+            return Err(format!(
+                "Agent {} is at its limit of {} concurrent tasks",
+                agent_id,
+                agent_capacity(agent)
+            ));
+        }
+
+        for tasks in busy_agents.values_mut() {
+            tasks.retain(|busy| busy.task_id != task_id);
+        }
+        busy_agents.retain(|_, tasks| !tasks.is_empty());
+        busy_agents
+            .entry(agent_id.to_string())
+            .or_default()
+            .push(Self::allocation(task_id.clone(), Utc::now()));
+        self.update_counts(&available_agents, &busy_agents).await;
+
+        info!("Allocated agent {} to task {}", agent_id, task_id);
+        Ok(())
     }
 
-    #[instrument(skip(self, agent_id))]
-    pub async fn release_agent(&self, agent_id: &str) -> Result<(), String> {
-        let mut available_agents = self.available_agents.write().await;
+    /// Frees the slot the task held on the agent
+    #[instrument(skip(self, agent_id, task_id))]
+    pub async fn release_agent(&self, agent_id: &str, task_id: &str) -> Result<(), String> {
+        let available_agents = self.available_agents.read().await;
         let mut busy_agents = self.busy_agents.write().await;
-        
-        if let Some(busy_agent) = busy_agents.remove(agent_id) {
-            available_agents.push(busy_agent.agent);
-            
-            self.pool_metrics.available_agents = available_agents.len();
-            self.pool_metrics.busy_agents = busy_agents.len();
-            
-            info!("Released agent {}", agent_id);
-            Ok(())
-        } else {
-            Err(format!("Agent {} not found in busy pool", agent_id))
+
+        let Some(tasks) = busy_agents.get_mut(agent_id) else {
+            return Err(format!("Agent {} has no tasks in the pool", agent_id));
+        };
+        let before = tasks.len();
+        tasks.retain(|busy| busy.task_id != task_id);
+        if tasks.len() == before {
+            return Err(format!("Task {} is not allocated to agent {}", task_id, agent_id));
+        }
+        if tasks.is_empty() {
+            busy_agents.remove(agent_id);
         }
+        self.update_counts(&available_agents, &busy_agents).await;
+
+        info!("Released agent {} from task {}", agent_id, task_id);
+        Ok(())
+    }
+
+    /// Brings the pool in line with the task's current assignee and status. The
+    /// assignment has already happened, so it is recorded even past capacity.
+    pub async fn sync_task(&self, pool: &SqlitePool, task_id: &str) -> Result<(), sqlx::Error> {
+        let task: Option<(Option<String>, String)> = sqlx::query_as("SELECT assignee_id, status FROM tasks WHERE id = ?")
+            .bind(task_id)
+            .fetch_optional(pool)
+            .await?;
+
+        match task {
+            Some((Some(agent_id), status)) if ACTIVE_TASK_STATUSES.contains(&status.as_str()) => {
+                if let Err(e) = self.allocate(&agent_id, task_id.to_string(), false).await {
+                    warn!("Task {} is assigned outside the agent pool: {}", task_id, e);
+                }
+            }
+            _ => {
+                let holder = self
+                    .busy_agents
+                    .read()
+                    .await
+                    .iter()
+                    .find(|(_, tasks)| tasks.iter().any(|busy| busy.task_id == task_id))
+                    .map(|(agent_id, _)| agent_id.clone());
+                if let Some(agent_id) = holder {
+                    let _ = self.release_agent(&agent_id, task_id).await;
+                }
+            }
+        }
+        Ok(())
     }
 
     pub async fn get_pool_metrics(&self) -> PoolMetrics {
-        self.pool_metrics.clone()
+        self.pool_metrics.read().await.clone()
+    }
+
+    /// The pool as seen at `clearance`. Load counts every held task, since the
+    /// capacity is real, but only tasks visible at `clearance` are listed.
+    pub async fn status(&self, pool: &SqlitePool, clearance: SecurityLevel) -> Result<PoolStatus, sqlx::Error> {
+        let visible: std::collections::HashSet<String> = sqlx::query_scalar::<sqlx::Sqlite, String>(&format!(
+            "SELECT id FROM tasks
+             WHERE assignee_id IS NOT NULL AND status IN ('ASSIGNED', 'IN_PROGRESS') AND classification IN ({})",
+            crate::classification::visible_levels_sql(clearance)
+        ))
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect();
+
+        let available_agents = self.available_agents.read().await;
+        let busy_agents = self.busy_agents.read().await;
+        let agents = available_agents
+            .iter()
+            .map(|agent| {
+                let tasks = busy_agents.get(&agent.id).cloned().unwrap_or_default();
+                PoolAgentStatus {
+                    agent_id: agent.id.clone(),
+                    name: agent.name.clone(),
+                    status: agent.status,
+                    capacity: agent_capacity(agent),
+                    load: tasks.len() as f64 / agent_capacity(agent) as f64,
+                    tasks: tasks.into_iter().filter(|busy| visible.contains(&busy.task_id)).collect(),
+                }
+            })
+            .collect();
+
+        Ok(PoolStatus {
+            algorithm: self.load_balancer.algorithm.name(),
+            metrics: self.get_pool_metrics().await,
+            agents,
+        })
     }
 
     fn meets_requirements(&self, agent: &Agent, requirements: &TaskRequirements) -> bool {
         // Check if agent has required skills
-        if let Some(required_skills) = &requirements.required_skills {
-            if let Some(agent_skills) = &agent.skills {
-                if let Ok(agent_skills_vec) = serde_json::from_str::<Vec<String>>(agent_skills) {
+        if let Some(required_skills) = &requirements.required_skills
+            && let Some(agent_skills) = &agent.skills
+                && let Ok(agent_skills_vec) = serde_json::from_str::<Vec<String>>(agent_skills) {
                     for skill in required_skills {
                         if !agent_skills_vec.contains(skill) {
                             return false;
                         }
                     }
                 }
-            }
-        }
         
         // Check security level
-        if let Some(required_security) = &requirements.security_level {
-            if agent.security_level < *required_security {
+        if let Some(required_security) = &requirements.security_level
+            && agent.security_level < *required_security {
                 return false;
             }
-        }
         
         // Check resource requirements
-        if let Some(max_concurrent) = agent.max_concurrent {
-            if requirements.concurrency_required > max_concurrent {
+        if let Some(max_concurrent) = agent.max_concurrent
+            && requirements.concurrency_required > max_concurrent {
                 return false;
             }
-        }
         
         true
     }
@@ -465,42 +833,54 @@ pub struct TaskRequirements {
 
 // Dynamic resource management
 
+/// Host resources as sampled from /proc, shared through `AppState`
 #[derive(Clone)]
 pub struct DynamicResourceManager {
     cpu_monitor: CpuMonitor,
     memory_monitor: MemoryMonitor,
     network_monitor: NetworkMonitor,
+    disk_monitor: DiskMonitor,
     allocation_strategy: AllocationStrategy,
     scaling_policy: ScalingPolicy,
+    host_sampler: HostSampler,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct CpuMonitor {
     pub current_usage: f64,
     pub threshold: f64,
     pub history: Vec<f64>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct MemoryMonitor {
     pub current_usage_mb: u64,
     pub threshold_mb: u64,
     pub history: Vec<u64>,
 }
 
-#[derive(Clone)]
+/// MB moved during the last sample interval
+#[derive(Clone, Serialize)]
 pub struct NetworkMonitor {
     pub current_io_mb: u64,
     pub threshold_mb: u64,
     pub history: Vec<u64>,
 }
 
+/// MB read and written during the last sample interval
+#[derive(Clone, Serialize)]
+pub struct DiskMonitor {
+    pub current_io_mb: u64,
+    pub threshold_mb: u64,
+    pub history: Vec<u64>,
+}
+
 #[derive(Clone)]
 pub enum AllocationStrategy {
     CostOptimized,
     PerformanceOptimized,
     Balanced,
-    Custom(Box<dyn Fn(&ResourceRequest) -> Allocation>),
+    Custom(Arc<dyn Fn(&ResourceRequest) -> Allocation + Send + Sync>),
 }
 
 #[derive(Clone)]
@@ -551,6 +931,11 @@ impl DynamicResourceManager {
                 threshold_mb: 1024,
                 history: Vec::new(),
             },
+            disk_monitor: DiskMonitor {
+                current_io_mb: 0,
+                threshold_mb: 2048,
+                history: Vec::new(),
+            },
             allocation_strategy: AllocationStrategy::Balanced,
             scaling_policy: ScalingPolicy {
                 min_agents: 2,
@@ -561,11 +946,59 @@ impl DynamicResourceManager {
                 scale_up_increment: 2,
                 scale_down_increment: 1,
             },
+            host_sampler: HostSampler::default(),
+        }
+    }
+
+    /// Folds a /proc reading into the monitors and returns the sample with any
+    /// threshold level changes
+    pub fn record_host_reading(&mut self, reading: ProcReading, thresholds: &ResourceThresholds) -> (HostSample, Vec<ResourceAlert>) {
+        let (sample, alerts) = self.host_sampler.record(reading, thresholds);
+
+        fn push<T>(history: &mut Vec<T>, value: T) {
+            history.push(value);
+            if history.len() > MONITOR_HISTORY {
+                history.remove(0);
+            }
+        }
+        if let Some(cpu_percent) = sample.cpu_percent {
+            self.cpu_monitor.current_usage = cpu_percent;
+            push(&mut self.cpu_monitor.history, cpu_percent);
+        }
+        self.cpu_monitor.threshold = thresholds.cpu_warning;
+        self.memory_monitor.current_usage_mb = sample.memory_used_mb;
+        self.memory_monitor.threshold_mb = (sample.memory_total_mb as f64 * thresholds.memory_warning / 100.0) as u64;
+        push(&mut self.memory_monitor.history, sample.memory_used_mb);
+        if let Some(network_io_mb) = sample.network_io_mb {
+            self.network_monitor.current_io_mb = network_io_mb.round() as u64;
+            push(&mut self.network_monitor.history, self.network_monitor.current_io_mb);
+        }
+        if let Some(disk_io_mb) = sample.disk_io_mb {
+            self.disk_monitor.current_io_mb = disk_io_mb.round() as u64;
+            push(&mut self.disk_monitor.history, self.disk_monitor.current_io_mb);
+        }
+
+        gauge!("cpu_usage_percent").set(self.cpu_monitor.current_usage);
+        gauge!("memory_usage_mb").set(self.memory_monitor.current_usage_mb as f64);
+        (sample, alerts)
+    }
+
+    pub async fn status(&self) -> ResourceStatus {
+        ResourceStatus {
+            sample: self.host_sampler.latest().cloned(),
+            cpu: self.cpu_monitor.clone(),
+            memory: self.memory_monitor.clone(),
+            network: self.network_monitor.clone(),
+            disk: self.disk_monitor.clone(),
+            alerts: self.host_sampler.active_levels(),
+            should_scale_up: self.should_scale_up().await,
+            should_scale_down: self.should_scale_down().await,
+            recommendations: generate_recommendations(self).await,
         }
     }
 
     #[instrument(skip(self, request))]
-    pub async fn allocate_resources(&self, request: &ResourceRequest) -> Option<Allocation> {
+    pub async fn allocate_resources(&mut self, request: &ResourceRequest) -> Option<Allocation> {
         // Check if resources are available
         if self.cpu_monitor.current_usage + request.cpu_required > self.cpu_monitor.threshold {
             warn!("CPU threshold exceeded for request");
@@ -582,7 +1015,7 @@ impl DynamicResourceManager {
             AllocationStrategy::CostOptimized => self.allocate_cost_optimized(request),
             AllocationStrategy::PerformanceOptimized => self.allocate_performance_optimized(request),
             AllocationStrategy::Balanced => self.allocate_balanced(request),
-            AllocationStrategy::Custom(func) => func(request),
+            AllocationStrategy::Custom(func) => Some(func(request)),
         };
         
         if let Some(ref allocation) = allocation {
@@ -593,18 +1026,18 @@ impl DynamicResourceManager {
             
             counter!("resource_allocations_total").increment(1);
             gauge!("cpu_usage_percent").set(self.cpu_monitor.current_usage);
-            gauge!("memory_usage_mb").set(self.cpu_monitor.current_usage as f64);
+            gauge!("memory_usage_mb").set(self.memory_monitor.current_usage_mb as f64);
         }
         
         allocation
     }
 
     #[instrument(skip(self, allocation))]
-    pub async fn release_resources(&self, allocation: &Allocation) {
+    pub async fn release_resources(&mut self, allocation: &Allocation) {
         // Update monitors
-        self.cpu_monitor.current_usage -= allocation.allocated_cpu;
-        self.memory_monitor.current_usage_mb -= allocation.allocated_memory_mb;
-        self.network_monitor.current_io_mb -= allocation.allocated_network_mb;
+        self.cpu_monitor.current_usage = (self.cpu_monitor.current_usage - allocation.allocated_cpu).max(0.0);
+        self.memory_monitor.current_usage_mb = self.memory_monitor.current_usage_mb.saturating_sub(allocation.allocated_memory_mb);
+        self.network_monitor.current_io_mb = self.network_monitor.current_io_mb.saturating_sub(allocation.allocated_network_mb);
         
         counter!("resource_releases_total").increment(1);
         gauge!("cpu_usage_percent").set(self.cpu_monitor.current_usage);
@@ -635,8 +1068,8 @@ impl DynamicResourceManager {
         Some(Allocation {
             agent_id: request.agent_id.clone(),
             allocated_cpu: request.cpu_required * 0.8,
-            allocated_memory_mb: request.memory_required_mb * 0.8,
-            allocated_network_mb: request.network_required_mb * 0.8,
+            allocated_memory_mb: (request.memory_required_mb as f64 * 0.8) as u64,
+            allocated_network_mb: (request.network_required_mb as f64 * 0.8) as u64,
             cost_estimate: self.calculate_cost_estimate(request.cpu_required, request.memory_required_mb, 1.0),
         })
     }
@@ -647,12 +1080,12 @@ impl DynamicResourceManager {
     }
 
     pub async fn should_scale_up(&self) -> bool {
-        (self.cpu_monitor.current_usage as f64) > (self.cpu_monitor.threshold as f64 * self.scaling_policy.scale_up_threshold) ||
+        self.cpu_monitor.current_usage > (self.cpu_monitor.threshold * self.scaling_policy.scale_up_threshold) ||
         (self.memory_monitor.current_usage_mb as f64) > (self.memory_monitor.threshold_mb as f64 * self.scaling_policy.scale_up_threshold)
     }
 
     pub async fn should_scale_down(&self) -> bool {
-        (self.cpu_monitor.current_usage as f64) < (self.cpu_monitor.threshold as f64 * self.scaling_policy.scale_down_threshold) &&
+        self.cpu_monitor.current_usage < (self.cpu_monitor.threshold * self.scaling_policy.scale_down_threshold) &&
         (self.memory_monitor.current_usage_mb as f64) < (self.memory_monitor.threshold_mb as f64 * self.scaling_policy.scale_down_threshold)
     }
 }
@@ -661,10 +1094,26 @@ impl DynamicResourceManager {
 
 #[derive(Deserialize)]
 pub struct CacheWarmRequest {
+    /// Empty warms every agent in openclaw.json
+    #[serde(default)]
     pub agent_ids: Vec<String>,
     pub ttl_seconds: Option<u64>,
 }
 
+#[derive(Serialize)]
+pub struct ResourceStatus {
+    pub sample: Option<HostSample>,
+    pub cpu: CpuMonitor,
+    pub memory: MemoryMonitor,
+    pub network: NetworkMonitor,
+    pub disk: DiskMonitor,
+    /// Metrics above their warning threshold
+    pub alerts: HashMap<&'static str, ThresholdLevel>,
+    pub should_scale_up: bool,
+    pub should_scale_down: bool,
+    pub recommendations: Vec<String>,
+}
+
 #[derive(Serialize)]
 pub struct OptimizationStatus {
     pub cache_metrics: CacheMetrics,
@@ -675,31 +1124,33 @@ pub struct OptimizationStatus {
 
 pub async fn warm_cache(
     State(app_state): State<crate::AppState>,
+    headers: axum::http::HeaderMap,
     Json(request): Json<CacheWarmRequest>,
-) -> impl IntoResponse {
-    let cache = HierarchicalCache::new(1000, 5000);
-    let ttl = Duration::from_secs(request.ttl_seconds.unwrap_or(3600));
-    
-    cache.warm_cache_for_agents(request.agent_ids).await;
-    
-    Json(serde_json::json!({
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&app_state.pool, &headers, "agents", "write").await?;
+    let ttl = Duration::from_secs(request.ttl_seconds.unwrap_or(crate::config::current().agent_config_ttl_seconds));
+
+    let report = app_state.cache.warm_cache_for_agents(&request.agent_ids, ttl).await
+        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, e))?;
+
+    Ok(Json(serde_json::json!({
         "status": "success",
-        "message": "Cache warming initiated",
-        "cache_metrics": cache.get_metrics().await
-    }))
+        "warmed": report.warmed,
+        "missing": report.missing,
+        "cache_metrics": app_state.cache.get_metrics().await
+    })))
 }
 
 pub async fn get_optimization_status(
     State(app_state): State<crate::AppState>,
-) -> impl IntoResponse {
-    // This would typically use actual instances from app state
-    let cache = HierarchicalCache::new(1000, 5000);
-    let agent_pool = AgentPool::new(vec![]);
-    let resource_manager = DynamicResourceManager::new();
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&app_state.pool, &headers, "agents", "read").await?;
+    let resource_manager = app_state.resource_manager.read().await;
     
     let status = OptimizationStatus {
-        cache_metrics: cache.get_metrics().await,
-        pool_metrics: agent_pool.get_pool_metrics().await,
+        cache_metrics: app_state.cache.get_metrics().await,
+        pool_metrics: app_state.agent_pool.get_pool_metrics().await,
         resource_usage: ResourceUsage {
             cpu_percent: resource_manager.cpu_monitor.current_usage,
             memory_mb: resource_manager.memory_monitor.current_usage_mb,
@@ -710,8 +1161,8 @@ pub async fn get_optimization_status(
         },
         recommendations: generate_recommendations(&resource_manager).await,
     };
-    
-    Json(status)
+
+    Ok(Json(status))
 }
 
 async fn generate_recommendations(resource_manager: &DynamicResourceManager) -> Vec<String> {
@@ -729,16 +1180,110 @@ async fn generate_recommendations(resource_manager: &DynamicResourceManager) ->
         recommendations.push("CPU usage is high - consider optimizing agent configurations".to_string());
     }
     
-    if resource_manager.memory_monitor.current_usage_mb > 6000 {
+    if resource_manager.memory_monitor.threshold_mb > 0 && resource_manager.memory_monitor.current_usage_mb > resource_manager.memory_monitor.threshold_mb {
         recommendations.push("Memory usage is high - consider implementing memory optimization".to_string());
     }
     
     recommendations
 }
-pub async fn get_pool_status() -> impl IntoResponse {
-    (StatusCode::NOT_IMPLEMENTED, "Not implemented")
+
+pub async fn get_pool_status(
+    State(state): State<crate::AppState>,
+    crate::classification::Clearance(clearance): crate::classification::Clearance,
+    headers: axum::http::HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
+    state.agent_pool.status(&state.pool, clearance).await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn get_resource_status(
+    State(state): State<crate::AppState>,
+    headers: axum::http::HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "monitoring", "read").await?;
+    Ok(Json(state.resource_manager.read().await.status().await))
 }
 
-pub async fn get_resource_status() -> impl IntoResponse {
-    (StatusCode::NOT_IMPLEMENTED, "Not implemented")
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::common::{create_test_pool, insert_test_agent};
+
+    #[tokio::test]
+    async fn allocations_stay_within_max_concurrent() {
+        let pool = create_test_pool().await;
+        let agent_id = insert_test_agent(&pool, "Pooled Agent").await;
+        sqlx::query("UPDATE agents SET max_concurrent = 2 WHERE id = ?")
+            .bind(&agent_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let agent_pool = AgentPool::new(Vec::new());
+        agent_pool.refresh(&pool).await.unwrap();
+        assert_eq!(agent_pool.free_slots(&agent_id).await, 2);
+
+        agent_pool.allocate_agent(&agent_id, "task-1".to_string()).await.unwrap();
+        agent_pool.allocate_agent(&agent_id, "task-2".to_string()).await.unwrap();
+        assert_eq!(agent_pool.free_slots(&agent_id).await, 0);
+        assert!(!agent_pool.has_capacity(&agent_id, "task-3").await);
+        assert!(agent_pool.allocate_agent(&agent_id, "task-3".to_string()).await.is_err());
+
+        // A task the agent already holds still fits
+        assert!(agent_pool.has_capacity(&agent_id, "task-1").await);
+        agent_pool.allocate_agent(&agent_id, "task-1".to_string()).await.unwrap();
+
+        agent_pool.release_agent(&agent_id, "task-1").await.unwrap();
+        agent_pool.allocate_agent(&agent_id, "task-3".to_string()).await.unwrap();
+        assert_eq!(agent_pool.free_slots(&agent_id).await, 0);
+        assert!(agent_pool.allocate_agent("not-in-the-pool", "task-4".to_string()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn cache_counts_hits_misses_demotions_and_evictions() {
+        let cache = HierarchicalCache::new(1, 1);
+        let ttl = Duration::from_secs(60);
+        let a = CacheKey::AgentConfig("a".to_string());
+        let b = CacheKey::AgentConfig("b".to_string());
+
+        assert_eq!(cache.get(&a).await, None);
+        cache.put(a.clone(), serde_json::json!("a"), ttl).await;
+        assert_eq!(cache.get(&a).await, Some(serde_json::json!("a")));
+        let metrics = cache.get_metrics().await;
+        assert_eq!((metrics.l1_hits, metrics.l1_misses, metrics.l2_misses), (1, 1, 1));
+
+        // b pushes a down to L2, and reading a promotes it back and demotes b
+        cache.put(b.clone(), serde_json::json!("b"), ttl).await;
+        assert_eq!(cache.get(&a).await, Some(serde_json::json!("a")));
+        let metrics = cache.get_metrics().await;
+        assert_eq!((metrics.l2_hits, metrics.demotions, metrics.evictions), (1, 2, 0));
+        assert_eq!((metrics.l1_entries, metrics.l2_entries), (1, 1));
+
+        // A third key demotes a, which leaves no room for b in L2
+        cache.put(CacheKey::HealthProbe, serde_json::json!("probe"), ttl).await;
+        assert_eq!(cache.get(&b).await, None);
+        let metrics = cache.get_metrics().await;
+        assert_eq!((metrics.demotions, metrics.evictions), (3, 1));
+        assert_eq!(metrics.total_requests, 4);
+        assert_eq!(metrics.hit_rate, 0.5);
+    }
+
+    #[tokio::test]
+    async fn cache_counts_expirations_and_invalidations() {
+        let cache = HierarchicalCache::new(2, 2);
+        cache.put(CacheKey::HealthProbe, serde_json::json!(1), Duration::ZERO).await;
+        assert_eq!(cache.get(&CacheKey::HealthProbe).await, None);
+        let metrics = cache.get_metrics().await;
+        assert_eq!((metrics.expirations, metrics.l1_misses, metrics.l1_entries), (1, 1, 0));
+
+        let ttl = Duration::from_secs(60);
+        cache.put(CacheKey::OpenClawConfig, serde_json::json!([]), ttl).await;
+        cache.put(CacheKey::AgentConfig("a".to_string()), serde_json::json!({}), ttl).await;
+        cache.put(CacheKey::AgentConfig("b".to_string()), serde_json::json!({}), ttl).await;
+        // The full list and a's entry go; b's entry stays
+        assert_eq!(cache.invalidate_agent("a").await, 2);
+        assert_eq!(cache.get(&CacheKey::AgentConfig("b".to_string())).await, Some(serde_json::json!({})));
+        assert_eq!(cache.get_metrics().await.invalidations, 2);
+    }
 }
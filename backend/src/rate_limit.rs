@@ -0,0 +1,515 @@
+use crate::db::SqlitePool;
+use crate::security::{jwt_secret, SecurityService};
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::{Layer, Service};
+use tracing::warn;
+
+/// Client address resolved by the rate limit layer, available to handlers as an extension
+#[derive(Debug, Clone)]
+pub struct ClientIp(pub String);
+
+/// Header the rate limit layer stamps with the resolved [`ClientIp`], replacing any
+/// value the client sent, so code that only sees the `HeaderMap` reads the same address
+pub const CLIENT_IP_HEADER: &str = "x-client-ip";
+
+/// A budget of `requests` per `period`, all of which may be spent in one burst
+#[derive(Debug, Clone, Copy)]
+pub struct Quota {
+    pub requests: u32,
+    pub period: Duration,
+}
+
+impl Quota {
+    pub const fn per_minute(requests: u32) -> Self {
+        Self { requests, period: Duration::from_secs(60) }
+    }
+
+    fn emission_interval(&self) -> Duration {
+        self.period / self.requests.max(1)
+    }
+}
+
+/// Budgets for requests whose path starts with `prefix`
+#[derive(Debug, Clone)]
+pub struct RoutePolicy {
+    pub name: &'static str,
+    pub prefix: &'static str,
+    pub per_ip: Quota,
+    pub per_identity: Quota,
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    /// Checked in order; the first matching prefix wins
+    pub routes: Vec<RoutePolicy>,
+    pub default_route: RoutePolicy,
+    pub login_failure_limit: u32,
+    pub login_failure_window: Duration,
+    /// Peers whose `X-Forwarded-For` / `X-Real-IP` headers are believed
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            routes: vec![
+                RoutePolicy {
+                    name: "login",
+                    prefix: "/api/security/login",
+                    per_ip: Quota::per_minute(10),
+                    per_identity: Quota::per_minute(10),
+                },
+                RoutePolicy {
+                    name: "password",
+                    prefix: "/api/security/password",
+                    per_ip: Quota::per_minute(5),
+                    per_identity: Quota::per_minute(5),
+                },
+                RoutePolicy {
+                    name: "openclaw",
+                    prefix: "/api/openclaw/",
+                    per_ip: Quota::per_minute(60),
+                    per_identity: Quota::per_minute(30),
+                },
+            ],
+            default_route: RoutePolicy {
+                name: "default",
+                prefix: "/",
+                per_ip: Quota::per_minute(300),
+                per_identity: Quota::per_minute(600),
+            },
+            login_failure_limit: 10,
+            login_failure_window: Duration::from_secs(15 * 60),
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Ok(value) = std::env::var("RATE_LIMIT_ENABLED") {
+            config.enabled = value != "false" && value != "0";
+        }
+        if let Some(limit) = std::env::var("RATE_LIMIT_DEFAULT_PER_MINUTE").ok().and_then(|v| v.parse().ok()) {
+            config.default_route.per_ip = Quota::per_minute(limit);
+        }
+        if let Some(limit) = std::env::var("LOGIN_FAILURE_LIMIT").ok().and_then(|v| v.parse().ok()) {
+            config.login_failure_limit = limit;
+        }
+        if let Ok(value) = std::env::var("TRUSTED_PROXIES") {
+            config.trusted_proxies = value
+                .split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .filter_map(|v| match v.parse() {
+                    Ok(ip) => Some(ip),
+                    Err(_) => {
+                        warn!("Ignoring invalid TRUSTED_PROXIES entry: {}", v);
+                        None
+                    }
+                })
+                .collect();
+        }
+        config
+    }
+
+    fn policy_for(&self, path: &str) -> &RoutePolicy {
+        self.routes
+            .iter()
+            .find(|route| path.starts_with(route.prefix))
+            .unwrap_or(&self.default_route)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset_after: Duration,
+    pub retry_after: Duration,
+}
+
+struct LoginFailures {
+    window_start: Instant,
+    count: u32,
+    usernames: HashSet<String>,
+    blocked_until: Option<Instant>,
+    block_reported: bool,
+}
+
+/// GCRA limiter shared between the tower layer and handlers that need to
+/// report login failures. Every method takes `&self` so it can sit in `AppState`.
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    // Theoretical arrival time per bucket key
+    buckets: DashMap<String, Instant>,
+    login_failures: DashMap<String, LoginFailures>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: DashMap::new(),
+            login_failures: DashMap::new(),
+        }
+    }
+
+    pub fn check(&self, key: &str, quota: Quota) -> RateLimitDecision {
+        let now = Instant::now();
+        let interval = quota.emission_interval();
+        let burst_window = interval * quota.requests;
+
+        let mut tat = self.buckets.entry(key.to_string()).or_insert(now);
+        let base = (*tat).max(now);
+        let new_tat = base + interval;
+
+        // Admitting this request would push the bucket past its burst allowance
+        if new_tat > now + burst_window {
+            let retry_after = new_tat - burst_window - now;
+            return RateLimitDecision {
+                allowed: false,
+                limit: quota.requests,
+                remaining: 0,
+                reset_after: base - now,
+                retry_after,
+            };
+        }
+
+        *tat = new_tat;
+        let used = new_tat - now;
+        let remaining = ((burst_window - used).as_nanos() / interval.as_nanos().max(1)) as u32;
+
+        RateLimitDecision {
+            allowed: true,
+            limit: quota.requests,
+            remaining,
+            reset_after: used,
+            retry_after: Duration::ZERO,
+        }
+    }
+
+    /// Returns how long logins from `ip` stay blocked, if they are
+    pub fn login_blocked(&self, ip: &str) -> Option<Duration> {
+        let entry = self.login_failures.get(ip)?;
+        let blocked_until = entry.blocked_until?;
+        blocked_until.checked_duration_since(Instant::now())
+    }
+
+    /// True the first time it is called for the current block on `ip`, so a
+    /// blocked address is recorded once per window rather than once per request
+    pub fn report_login_block(&self, ip: &str) -> bool {
+        let Some(mut entry) = self.login_failures.get_mut(ip) else {
+            return false;
+        };
+        if entry.blocked_until.is_none() || entry.block_reported {
+            return false;
+        }
+        entry.block_reported = true;
+        true
+    }
+
+    /// Counts a failed login from `ip`. Returns the number of distinct usernames
+    /// tried when this failure trips the block, so the caller can record it.
+    pub fn record_login_failure(&self, ip: &str, username: &str) -> Option<usize> {
+        let now = Instant::now();
+        let mut entry = self.login_failures.entry(ip.to_string()).or_insert_with(|| LoginFailures {
+            window_start: now,
+            count: 0,
+            usernames: HashSet::new(),
+            blocked_until: None,
+            block_reported: false,
+        });
+
+        if now.duration_since(entry.window_start) > self.config.login_failure_window {
+            entry.window_start = now;
+            entry.count = 0;
+            entry.usernames.clear();
+            entry.blocked_until = None;
+            entry.block_reported = false;
+        }
+
+        entry.count += 1;
+        entry.usernames.insert(username.to_lowercase());
+
+        if entry.count >= self.config.login_failure_limit && entry.blocked_until.is_none() {
+            entry.blocked_until = Some(now + self.config.login_failure_window);
+            return Some(entry.usernames.len());
+        }
+
+        None
+    }
+
+    /// Drops buckets that have fully refilled and expired failure windows
+    pub fn prune(&self) {
+        let now = Instant::now();
+        self.buckets.retain(|_, tat| *tat > now);
+        let window = self.config.login_failure_window;
+        self.login_failures.retain(|_, failures| {
+            failures.blocked_until.is_some_and(|until| until > now)
+                || now.duration_since(failures.window_start) <= window
+        });
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    limiter: Arc<RateLimiter>,
+    pool: SqlitePool,
+}
+
+impl RateLimitLayer {
+    pub fn new(limiter: Arc<RateLimiter>, pool: SqlitePool) -> Self {
+        Self { limiter, pool }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            inner,
+            limiter: self.limiter.clone(),
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimitService<S> {
+    inner: S,
+    limiter: Arc<RateLimiter>,
+    pool: SqlitePool,
+}
+
+impl<S> Service<Request> for RateLimitService<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        // Take the service that was polled ready and leave a fresh clone behind
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let limiter = self.limiter.clone();
+        let pool = self.pool.clone();
+
+        Box::pin(async move {
+            let mut request = request;
+            let ip = client_ip(&request, &limiter.config.trusted_proxies);
+            match HeaderValue::from_str(&ip) {
+                Ok(value) => { request.headers_mut().insert(CLIENT_IP_HEADER, value); }
+                Err(_) => { request.headers_mut().remove(CLIENT_IP_HEADER); }
+            }
+            request.extensions_mut().insert(ClientIp(ip.clone()));
+
+            if !limiter.config.enabled {
+                return inner.call(request).await;
+            }
+
+            let path = request.uri().path().to_string();
+            let policy = limiter.config.policy_for(&path);
+
+            if policy.name == "login"
+                && let Some(retry_after) = limiter.login_blocked(&ip) {
+                    if limiter.report_login_block(&ip) {
+                        let service = SecurityService::new(jwt_secret());
+                        if let Err(e) = service.log_security_event(
+                            &pool,
+                            "login_failure",
+                            "Login blocked after repeated failures from this address",
+                            Some(ip.clone()),
+                            Some(path.clone()),
+                            None,
+                            serde_json::json!({ "retry_after_seconds": retry_after.as_secs() }).to_string(),
+                        ).await {
+                            warn!("Failed to record blocked login: {}", e);
+                        }
+                    }
+
+                    return Ok(too_many_requests(RateLimitDecision {
+                        allowed: false,
+                        limit: limiter.config.login_failure_limit,
+                        remaining: 0,
+                        reset_after: retry_after,
+                        retry_after,
+                    }));
+                }
+
+            let mut decision = limiter.check(&format!("{}:ip:{}", policy.name, ip), policy.per_ip);
+            if let Some(identity) = request_identity(request.headers()) {
+                let identity_decision = limiter.check(&format!("{}:{}", policy.name, identity), policy.per_identity);
+                if !identity_decision.allowed || identity_decision.remaining < decision.remaining {
+                    decision = identity_decision;
+                }
+            }
+
+            if !decision.allowed {
+                return Ok(too_many_requests(decision));
+            }
+
+            let mut response = inner.call(request).await?;
+            apply_rate_limit_headers(response.headers_mut(), &decision);
+            Ok(response)
+        })
+    }
+}
+
+/// Agent key if the caller sent one, otherwise the authenticated principal
+fn request_identity(headers: &HeaderMap) -> Option<String> {
+    if let Some(agent_key) = headers.get("x-agent-key").and_then(|v| v.to_str().ok()) {
+        return Some(format!("agent:{}", agent_key));
+    }
+
+    let token = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(crate::security::extract_bearer_token)?;
+    let claims = SecurityService::new(jwt_secret()).decode_token(&token).ok()?;
+    Some(format!("user:{}", claims.sub))
+}
+
+/// The connecting peer, unless it is a trusted proxy, in which case the nearest
+/// untrusted hop it reported. Forwarding headers from anyone else are ignored.
+fn client_ip(request: &Request, trusted_proxies: &[IpAddr]) -> String {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+
+    let Some(peer) = peer else {
+        return "0.0.0.0".to_string();
+    };
+    if !trusted_proxies.contains(&peer) {
+        return peer.to_string();
+    }
+
+    let headers = request.headers();
+    let forwarded = headers
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| {
+            v.rsplit(',')
+                .filter_map(|hop| hop.trim().parse::<IpAddr>().ok())
+                .find(|hop| !trusted_proxies.contains(hop))
+        });
+    let real_ip = || {
+        headers
+            .get("x-real-ip")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<IpAddr>().ok())
+    };
+
+    forwarded.or_else(real_ip).unwrap_or(peer).to_string()
+}
+
+fn apply_rate_limit_headers(headers: &mut HeaderMap, decision: &RateLimitDecision) {
+    headers.insert("ratelimit-limit", HeaderValue::from(decision.limit));
+    headers.insert("ratelimit-remaining", HeaderValue::from(decision.remaining));
+    headers.insert("ratelimit-reset", HeaderValue::from(ceil_secs(decision.reset_after)));
+}
+
+fn too_many_requests(decision: RateLimitDecision) -> Response {
+    let retry_after = ceil_secs(decision.retry_after).max(1);
+    let mut response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        format!("Rate limit exceeded, retry in {} seconds", retry_after),
+    ).into_response();
+
+    apply_rate_limit_headers(response.headers_mut(), &decision);
+    response.headers_mut().insert("retry-after", HeaderValue::from(retry_after));
+    response
+}
+
+fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+
+    fn request_from(peer: &str, forwarded_for: Option<&str>) -> Request {
+        let mut builder = Request::builder().uri("/api/security/login");
+        if let Some(value) = forwarded_for {
+            builder = builder.header("x-forwarded-for", value);
+        }
+        let mut request = builder.body(Body::empty()).unwrap();
+        request.extensions_mut().insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+        request
+    }
+
+    #[test]
+    fn forwarded_for_is_ignored_from_untrusted_peers() {
+        let request = request_from("198.51.100.4:5000", Some("203.0.113.7"));
+        assert_eq!(client_ip(&request, &[]), "198.51.100.4");
+    }
+
+    #[test]
+    fn trusted_proxy_reports_nearest_untrusted_hop() {
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let inner: IpAddr = "10.0.0.2".parse().unwrap();
+        let request = request_from("10.0.0.1:5000", Some("192.0.2.9, 203.0.113.7, 10.0.0.2"));
+        assert_eq!(client_ip(&request, &[proxy, inner]), "203.0.113.7");
+
+        let request = request_from("10.0.0.1:5000", None);
+        assert_eq!(client_ip(&request, &[proxy]), "10.0.0.1");
+    }
+
+    #[test]
+    fn login_block_is_reported_once_per_window() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            login_failure_limit: 2,
+            ..Default::default()
+        });
+        assert!(!limiter.report_login_block("203.0.113.7"));
+
+        limiter.record_login_failure("203.0.113.7", "alice");
+        assert!(limiter.record_login_failure("203.0.113.7", "bob").is_some());
+        assert!(limiter.login_blocked("203.0.113.7").is_some());
+
+        assert!(limiter.report_login_block("203.0.113.7"));
+        assert!(!limiter.report_login_block("203.0.113.7"));
+    }
+
+    #[tokio::test]
+    async fn identity_comes_from_tokens_signed_with_the_configured_secret() {
+        let pool = crate::tests::common::create_test_pool().await;
+        let (user, token) = crate::tests::common::create_test_admin_user(&pool).await;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", HeaderValue::from_str(&format!("Bearer {}", token)).unwrap());
+        assert_eq!(request_identity(&headers), Some(format!("user:{}", user.id)));
+
+        headers.insert("authorization", HeaderValue::from_static("Bearer not-a-token"));
+        assert_eq!(request_identity(&headers), None);
+
+        headers.insert("x-agent-key", HeaderValue::from_static("agent-key"));
+        assert_eq!(request_identity(&headers), Some("agent:agent-key".to_string()));
+    }
+}
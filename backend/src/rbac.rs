@@ -0,0 +1,758 @@
+use crate::models::*;
+use crate::db::SqlitePool;
+use crate::audit::AuditService;
+use sqlx::FromRow;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use axum::{
+    extract::{Path, State},
+    Json,
+    response::IntoResponse,
+    http::{HeaderMap, StatusCode},
+};
+use crate::AppState;
+
+/// How long resolved permissions are reused before the tables are read again
+fn permission_cache_ttl() -> Duration {
+    Duration::from_secs(crate::config::current().permission_cache_ttl_seconds)
+}
+
+impl Role {
+    pub fn permission_list(&self) -> Vec<String> {
+        self.permissions
+            .as_deref()
+            .and_then(|p| serde_json::from_str(p).ok())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateRoleRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<String>,
+    pub parent_role_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateRoleRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub permissions: Option<Vec<String>>,
+    /// An empty string detaches the role from its parent
+    pub parent_role_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AssignRoleRequest {
+    pub role_id: String,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct UserRoleAssignment {
+    pub id: String,
+    pub user_id: String,
+    pub role_id: String,
+    pub role_name: String,
+    pub assigned_at: DateTime<Utc>,
+    pub assigned_by: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EffectivePermissions {
+    pub user_id: String,
+    pub role: String,
+    /// Every role that contributed, including inherited ones
+    pub roles: Vec<String>,
+    /// Granted patterns as stored, e.g. `tasks:*`
+    pub grants: Vec<String>,
+    /// Catalogue permissions covered by the grants
+    pub permissions: Vec<String>,
+}
+
+#[derive(Clone)]
+struct CachedPermissions {
+    roles: Vec<String>,
+    grants: Vec<String>,
+    valid_until: Instant,
+}
+
+/// Resolved grants per user id, dropped whenever roles or assignments change
+static PERMISSION_CACHE: Lazy<DashMap<String, CachedPermissions>> = Lazy::new(DashMap::new);
+
+/// Clears the cached permissions of one user, or of everyone when `user_id` is `None`
+pub fn invalidate_permission_cache(user_id: Option<&str>) {
+    match user_id {
+        Some(user_id) => {
+            PERMISSION_CACHE.remove(user_id);
+        }
+        None => PERMISSION_CACHE.clear(),
+    }
+}
+
+/// Matches a granted pattern against a concrete `resource:action` permission.
+/// `*` grants everything, `tasks:*` every action on tasks and `*:read` read on everything.
+pub fn permission_matches(granted: &str, required: &str) -> bool {
+    if granted == "*" || granted == required {
+        return true;
+    }
+
+    match (granted.split_once(':'), required.split_once(':')) {
+        (Some((granted_resource, granted_action)), Some((resource, action))) => {
+            (granted_resource == "*" || granted_resource == resource)
+                && (granted_action == "*" || granted_action == action)
+        }
+        _ => false,
+    }
+}
+
+pub fn has_permission(grants: &[String], required: &str) -> bool {
+    grants.iter().any(|granted| permission_matches(granted, required))
+}
+
+fn valid_permission_pattern(pattern: &str) -> bool {
+    if pattern == "*" {
+        return true;
+    }
+    match pattern.split_once(':') {
+        Some((resource, action)) => {
+            let valid_part = |part: &str| {
+                part == "*" || (!part.is_empty() && part.chars().all(|c| c.is_ascii_lowercase() || c == '_'))
+            };
+            valid_part(resource) && valid_part(action)
+        }
+        None => false,
+    }
+}
+
+struct SeedRole {
+    id: &'static str,
+    name: &'static str,
+    description: &'static str,
+    parent: Option<&'static str>,
+    permissions: &'static [&'static str],
+}
+
+const SEED_ROLES: &[SeedRole] = &[
+    SeedRole {
+        id: "role_read_only",
+        name: "READ_ONLY",
+        description: "View agents and tasks",
+        parent: None,
+        permissions: &["agents:read", "tasks:read"],
+    },
+    SeedRole {
+        id: "role_user",
+        name: "USER",
+        description: "Standard operator",
+        parent: Some("role_read_only"),
+        permissions: &["monitoring:read"],
+    },
+    SeedRole {
+        id: "role_admin",
+        name: "ADMIN",
+        description: "Manages agents and tasks",
+        parent: Some("role_user"),
+        permissions: &["agents:*", "tasks:write", "tasks:delete", "users:read", "audit:read"],
+    },
+    SeedRole {
+        id: "role_super_admin",
+        name: "SUPER_ADMIN",
+        description: "Full system access",
+        parent: Some("role_admin"),
+        permissions: &["system:*", "users:*", "tasks:*", "audit:*", "security:read"],
+    },
+];
+
+const SEED_PERMISSIONS: &[(&str, &str)] = &[
+    ("system", "read"), ("system", "write"), ("system", "delete"), ("system", "admin"),
+    ("users", "read"), ("users", "write"), ("users", "delete"), ("users", "admin"),
+    ("agents", "read"), ("agents", "write"), ("agents", "delete"), ("agents", "admin"),
+    ("tasks", "read"), ("tasks", "write"), ("tasks", "delete"), ("tasks", "admin"),
+    ("audit", "read"), ("audit", "admin"), ("security", "read"), ("monitoring", "read"),
+];
+
+/// Inserts the built-in roles and permission catalogue. Existing rows are left
+/// alone so edits made through the API survive restarts.
+pub async fn seed_default_roles(pool: &SqlitePool) -> Result<(), anyhow::Error> {
+    for (resource, action) in SEED_PERMISSIONS {
+        let name = format!("{}:{}", resource, action);
+        sqlx::query(
+            "INSERT OR IGNORE INTO permissions (id, name, description, resource, action, is_system, created_at)
+             VALUES (?, ?, ?, ?, ?, 1, CURRENT_TIMESTAMP)"
+        )
+        .bind(format!("perm_{}_{}", resource, action))
+        .bind(&name)
+        .bind(format!("{} {}", action, resource))
+        .bind(resource)
+        .bind(action)
+        .execute(pool)
+        .await?;
+    }
+
+    // Parents come first in SEED_ROLES, so the foreign key always resolves
+    for role in SEED_ROLES {
+        sqlx::query(
+            "INSERT OR IGNORE INTO roles (id, name, description, permissions, parent_role_id, is_system, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, 1, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)"
+        )
+        .bind(role.id)
+        .bind(role.name)
+        .bind(role.description)
+        .bind(serde_json::to_string(role.permissions)?)
+        .bind(role.parent)
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(())
+}
+
+pub struct RbacService;
+
+impl RbacService {
+    /// Grants from the user's primary role, active role assignments and their
+    /// ancestors, plus the permissions stored directly on the user
+    pub async fn resolve(&self, pool: &SqlitePool, user: &User) -> Result<(Vec<String>, Vec<String>), anyhow::Error> {
+        if let Some(cached) = PERMISSION_CACHE.get(&user.id)
+            && cached.valid_until > Instant::now() {
+                return Ok((cached.roles.clone(), cached.grants.clone()));
+            }
+
+        let roles: HashMap<String, Role> = sqlx::query_as::<sqlx::Sqlite, Role>("SELECT * FROM roles")
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .map(|role| (role.id.clone(), role))
+            .collect();
+
+        let assignments: Vec<(String, Option<DateTime<Utc>>)> = sqlx::query_as(
+            "SELECT role_id, expires_at FROM user_roles
+             WHERE user_id = ? AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)"
+        )
+        .bind(&user.id)
+        .fetch_all(pool)
+        .await?;
+
+        let mut queue: VecDeque<String> = assignments.iter().map(|(role_id, _)| role_id.clone()).collect();
+        if let Some(primary) = roles.values().find(|role| role.name == user.role) {
+            queue.push_front(primary.id.clone());
+        }
+
+        let mut visited = HashSet::new();
+        let mut role_names = Vec::new();
+        let mut grants: Vec<String> = user
+            .permissions
+            .as_deref()
+            .and_then(|p| serde_json::from_str(p).ok())
+            .unwrap_or_default();
+
+        // The visited set stops the walk on inheritance cycles
+        while let Some(role_id) = queue.pop_front() {
+            if !visited.insert(role_id.clone()) {
+                continue;
+            }
+            let Some(role) = roles.get(&role_id) else {
+                continue;
+            };
+            role_names.push(role.name.clone());
+            grants.extend(role.permission_list());
+            if let Some(parent) = &role.parent_role_id {
+                queue.push_back(parent.clone());
+            }
+        }
+
+        grants.sort();
+        grants.dedup();
+
+        // Cache no longer than the first assignment still due to expire
+        let now = Utc::now();
+        let valid_for = assignments
+            .iter()
+            .filter_map(|(_, expires_at)| *expires_at)
+            .filter_map(|expires_at| (expires_at - now).to_std().ok())
+            .fold(permission_cache_ttl(), Duration::min);
+
+        PERMISSION_CACHE.insert(user.id.clone(), CachedPermissions {
+            roles: role_names.clone(),
+            grants: grants.clone(),
+            valid_until: Instant::now() + valid_for,
+        });
+
+        Ok((role_names, grants))
+    }
+
+    pub async fn effective_permissions(&self, pool: &SqlitePool, user: &User) -> Result<EffectivePermissions, anyhow::Error> {
+        let (roles, grants) = self.resolve(pool, user).await?;
+
+        let catalogue = self.list_permissions(pool).await?;
+        let permissions = catalogue
+            .into_iter()
+            .map(|permission| permission.name)
+            .filter(|name| has_permission(&grants, name))
+            .collect();
+
+        Ok(EffectivePermissions {
+            user_id: user.id.clone(),
+            role: user.role.clone(),
+            roles,
+            grants,
+            permissions,
+        })
+    }
+
+    pub async fn check(&self, pool: &SqlitePool, user: &User, resource: &str, action: &str) -> Result<bool, anyhow::Error> {
+        let (_, grants) = self.resolve(pool, user).await?;
+        Ok(has_permission(&grants, &format!("{}:{}", resource, action)))
+    }
+
+    pub async fn list_roles(&self, pool: &SqlitePool) -> Result<Vec<Role>, anyhow::Error> {
+        let roles = sqlx::query_as::<sqlx::Sqlite, Role>("SELECT * FROM roles ORDER BY is_system DESC, name")
+            .fetch_all(pool)
+            .await?;
+        Ok(roles)
+    }
+
+    pub async fn get_role(&self, pool: &SqlitePool, role_id: &str) -> Result<Option<Role>, anyhow::Error> {
+        let role = sqlx::query_as::<sqlx::Sqlite, Role>("SELECT * FROM roles WHERE id = ?")
+            .bind(role_id)
+            .fetch_optional(pool)
+            .await?;
+        Ok(role)
+    }
+
+    pub async fn list_permissions(&self, pool: &SqlitePool) -> Result<Vec<Permission>, anyhow::Error> {
+        let permissions = sqlx::query_as::<sqlx::Sqlite, Permission>("SELECT * FROM permissions ORDER BY resource, action")
+            .fetch_all(pool)
+            .await?;
+        Ok(permissions)
+    }
+
+    /// Rejects a parent that does not exist or that would make `role_id` its own ancestor
+    async fn validate_parent(&self, pool: &SqlitePool, role_id: &str, parent_id: &str) -> Result<(), anyhow::Error> {
+        let mut current = Some(parent_id.to_string());
+        let mut seen = HashSet::new();
+        while let Some(id) = current {
+            if id == role_id {
+                return Err(anyhow::anyhow!("Role inheritance would create a cycle"));
+            }
+            if !seen.insert(id.clone()) {
+                break;
+            }
+            let role = self.get_role(pool, &id).await?
+                .ok_or_else(|| anyhow::anyhow!("Parent role not found: {}", id))?;
+            current = role.parent_role_id;
+        }
+        Ok(())
+    }
+
+    fn validate_patterns(permissions: &[String]) -> Result<(), anyhow::Error> {
+        match permissions.iter().find(|p| !valid_permission_pattern(p)) {
+            Some(invalid) => Err(anyhow::anyhow!("Invalid permission pattern: {}", invalid)),
+            None => Ok(()),
+        }
+    }
+
+    pub async fn create_role(&self, pool: &SqlitePool, request: &CreateRoleRequest, created_by: &str) -> Result<Role, anyhow::Error> {
+        let name = request.name.trim();
+        if name.is_empty() {
+            return Err(anyhow::anyhow!("Role name is required"));
+        }
+        Self::validate_patterns(&request.permissions)?;
+
+        let id = uuid::Uuid::new_v4().to_string();
+        if let Some(parent) = &request.parent_role_id {
+            self.validate_parent(pool, &id, parent).await?;
+        }
+
+        sqlx::query(
+            "INSERT INTO roles (id, name, description, permissions, parent_role_id, is_system, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, 0, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)"
+        )
+        .bind(&id)
+        .bind(name)
+        .bind(&request.description)
+        .bind(serde_json::to_string(&request.permissions)?)
+        .bind(&request.parent_role_id)
+        .execute(pool)
+        .await?;
+
+        AuditService::log_entity_event(
+            pool,
+            "role",
+            &id,
+            "create",
+            None,
+            Some(&serde_json::to_string(request)?),
+            Some(created_by),
+            None,
+            None,
+            None,
+            None,
+            None,
+        ).await?;
+
+        info!("Created role {} ({})", name, id);
+        self.get_role(pool, &id).await?.ok_or_else(|| anyhow::anyhow!("Role disappeared after insert"))
+    }
+
+    pub async fn update_role(
+        &self,
+        pool: &SqlitePool,
+        role_id: &str,
+        request: &UpdateRoleRequest,
+        updated_by: &str,
+    ) -> Result<Option<Role>, anyhow::Error> {
+        let Some(existing) = self.get_role(pool, role_id).await? else {
+            return Ok(None);
+        };
+
+        if let Some(name) = &request.name {
+            if existing.is_system && name != &existing.name {
+                return Err(anyhow::anyhow!("System roles cannot be renamed"));
+            }
+            if name.trim().is_empty() {
+                return Err(anyhow::anyhow!("Role name is required"));
+            }
+        }
+        if let Some(permissions) = &request.permissions {
+            Self::validate_patterns(permissions)?;
+        }
+        let parent_role_id = match request.parent_role_id.as_deref() {
+            Some("") => None,
+            Some(parent) => {
+                self.validate_parent(pool, role_id, parent).await?;
+                Some(parent.to_string())
+            }
+            None => existing.parent_role_id.clone(),
+        };
+
+        let permissions = match &request.permissions {
+            Some(permissions) => Some(serde_json::to_string(permissions)?),
+            None => existing.permissions.clone(),
+        };
+
+        sqlx::query(
+            "UPDATE roles SET name = ?, description = ?, permissions = ?, parent_role_id = ?, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?"
+        )
+        .bind(request.name.as_deref().map(str::trim).unwrap_or(&existing.name))
+        .bind(request.description.as_ref().or(existing.description.as_ref()))
+        .bind(&permissions)
+        .bind(&parent_role_id)
+        .bind(role_id)
+        .execute(pool)
+        .await?;
+
+        // Any user may inherit from this role, so drop every cached entry
+        invalidate_permission_cache(None);
+
+        AuditService::log_entity_event(
+            pool,
+            "role",
+            role_id,
+            "update",
+            Some(&serde_json::to_string(&existing)?),
+            Some(&serde_json::to_string(request)?),
+            Some(updated_by),
+            None,
+            None,
+            None,
+            None,
+            None,
+        ).await?;
+
+        self.get_role(pool, role_id).await
+    }
+
+    pub async fn delete_role(&self, pool: &SqlitePool, role_id: &str, deleted_by: &str) -> Result<bool, anyhow::Error> {
+        let Some(existing) = self.get_role(pool, role_id).await? else {
+            return Ok(false);
+        };
+        if existing.is_system {
+            return Err(anyhow::anyhow!("System roles cannot be deleted"));
+        }
+
+        let users_with_primary_role: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE role = ?")
+            .bind(&existing.name)
+            .fetch_one(pool)
+            .await?;
+        if users_with_primary_role > 0 {
+            return Err(anyhow::anyhow!("Role is the primary role of {} users", users_with_primary_role));
+        }
+
+        // Assignments go with the role through ON DELETE CASCADE, children lose their parent
+        sqlx::query("DELETE FROM roles WHERE id = ?")
+            .bind(role_id)
+            .execute(pool)
+            .await?;
+
+        invalidate_permission_cache(None);
+
+        AuditService::log_entity_event(
+            pool,
+            "role",
+            role_id,
+            "delete",
+            Some(&serde_json::to_string(&existing)?),
+            None,
+            Some(deleted_by),
+            None,
+            None,
+            None,
+            None,
+            None,
+        ).await?;
+
+        Ok(true)
+    }
+
+    pub async fn list_user_roles(&self, pool: &SqlitePool, user_id: &str) -> Result<Vec<UserRoleAssignment>, anyhow::Error> {
+        let assignments = sqlx::query_as::<sqlx::Sqlite, UserRoleAssignment>(
+            "SELECT ur.id, ur.user_id, ur.role_id, r.name AS role_name, ur.assigned_at, ur.assigned_by, ur.expires_at
+             FROM user_roles ur JOIN roles r ON r.id = ur.role_id
+             WHERE ur.user_id = ?
+             ORDER BY ur.assigned_at"
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+        Ok(assignments)
+    }
+
+    pub async fn assign_role(
+        &self,
+        pool: &SqlitePool,
+        user_id: &str,
+        request: &AssignRoleRequest,
+        assigned_by: &str,
+    ) -> Result<(), anyhow::Error> {
+        if self.get_role(pool, &request.role_id).await?.is_none() {
+            return Err(anyhow::anyhow!("Role not found: {}", request.role_id));
+        }
+        if request.expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+            return Err(anyhow::anyhow!("expires_at must be in the future"));
+        }
+
+        // Re-assigning refreshes the expiry instead of failing on the unique pair
+        sqlx::query(
+            "INSERT INTO user_roles (id, user_id, role_id, assigned_at, assigned_by, expires_at)
+             VALUES (?, ?, ?, CURRENT_TIMESTAMP, ?, ?)
+             ON CONFLICT(user_id, role_id) DO UPDATE SET
+                assigned_at = CURRENT_TIMESTAMP,
+                assigned_by = excluded.assigned_by,
+                expires_at = excluded.expires_at"
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(user_id)
+        .bind(&request.role_id)
+        .bind(assigned_by)
+        .bind(request.expires_at)
+        .execute(pool)
+        .await?;
+
+        invalidate_permission_cache(Some(user_id));
+
+        AuditService::log_entity_event(
+            pool,
+            "user",
+            user_id,
+            "update",
+            None,
+            Some(&serde_json::json!({ "role_assigned": request }).to_string()),
+            Some(assigned_by),
+            None,
+            None,
+            None,
+            None,
+            None,
+        ).await?;
+
+        Ok(())
+    }
+
+    pub async fn remove_role(&self, pool: &SqlitePool, user_id: &str, role_id: &str, removed_by: &str) -> Result<bool, anyhow::Error> {
+        let result = sqlx::query("DELETE FROM user_roles WHERE user_id = ? AND role_id = ?")
+            .bind(user_id)
+            .bind(role_id)
+            .execute(pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        invalidate_permission_cache(Some(user_id));
+
+        AuditService::log_entity_event(
+            pool,
+            "user",
+            user_id,
+            "update",
+            Some(&serde_json::json!({ "role_removed": role_id }).to_string()),
+            None,
+            Some(removed_by),
+            None,
+            None,
+            None,
+            None,
+            None,
+        ).await?;
+
+        Ok(true)
+    }
+}
+
+/// Resolves the caller and checks they hold `resource:action`
+pub async fn authorized_user(
+    pool: &SqlitePool,
+    headers: &HeaderMap,
+    resource: &str,
+    action: &str,
+) -> Result<User, (StatusCode, String)> {
+    let user = crate::security::authenticated_user(pool, headers).await?;
+    match RbacService.check(pool, &user, resource, action).await {
+        Ok(true) => Ok(user),
+        Ok(false) => Err((StatusCode::FORBIDDEN, format!("Missing permission {}:{}", resource, action))),
+        Err(e) => {
+            warn!("Permission check failed for {}: {}", user.id, e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+// Axum Handlers
+pub async fn get_my_permissions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = crate::security::authenticated_user(&state.pool, &headers).await?;
+    RbacService.effective_permissions(&state.pool, &user).await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn list_roles(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    authorized_user(&state.pool, &headers, "users", "read").await?;
+    RbacService.list_roles(&state.pool).await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn create_role(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateRoleRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let admin = authorized_user(&state.pool, &headers, "users", "admin").await?;
+    RbacService.create_role(&state.pool, &payload, &admin.id).await
+        .map(|role| (StatusCode::CREATED, Json(role)))
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+pub async fn update_role(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(role_id): Path<String>,
+    Json(payload): Json<UpdateRoleRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let admin = authorized_user(&state.pool, &headers, "users", "admin").await?;
+    match RbacService.update_role(&state.pool, &role_id, &payload, &admin.id).await {
+        Ok(Some(role)) => Ok(Json(role)),
+        Ok(None) => Err((StatusCode::NOT_FOUND, "Role not found".to_string())),
+        Err(e) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+    }
+}
+
+pub async fn delete_role(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(role_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let admin = authorized_user(&state.pool, &headers, "users", "admin").await?;
+    match RbacService.delete_role(&state.pool, &role_id, &admin.id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "Role not found".to_string())),
+        Err(e) => Err((StatusCode::CONFLICT, e.to_string())),
+    }
+}
+
+pub async fn list_permissions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    authorized_user(&state.pool, &headers, "users", "read").await?;
+    RbacService.list_permissions(&state.pool).await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn list_user_roles(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(user_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    authorized_user(&state.pool, &headers, "users", "read").await?;
+    RbacService.list_user_roles(&state.pool, &user_id).await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn assign_user_role(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(user_id): Path<String>,
+    Json(payload): Json<AssignRoleRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let admin = authorized_user(&state.pool, &headers, "users", "admin").await?;
+    RbacService.assign_role(&state.pool, &user_id, &payload, &admin.id).await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+pub async fn remove_user_role(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((user_id, role_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let admin = authorized_user(&state.pool, &headers, "users", "admin").await?;
+    match RbacService.remove_role(&state.pool, &user_id, &role_id, &admin.id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "Role assignment not found".to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards_match_resource_and_action() {
+        assert!(permission_matches("*", "system:admin"));
+        assert!(permission_matches("tasks:*", "tasks:delete"));
+        assert!(permission_matches("*:read", "audit:read"));
+        assert!(!permission_matches("*:read", "audit:admin"));
+        assert!(!permission_matches("tasks:*", "agents:read"));
+        assert!(!permission_matches("tasks", "tasks:read"));
+    }
+
+    #[test]
+    fn permission_patterns_are_validated() {
+        assert!(valid_permission_pattern("*"));
+        assert!(valid_permission_pattern("audit_log:*"));
+        assert!(!valid_permission_pattern("Tasks:read"));
+        assert!(!valid_permission_pattern("tasks:"));
+        assert!(!valid_permission_pattern("tasks"));
+    }
+}
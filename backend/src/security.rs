@@ -1,19 +1,25 @@
 use crate::models::*;
 use crate::db::SqlitePool;
-use sqlx::{query, query_as};
+use sqlx::FromRow;
 use chrono::{Utc, Duration};
 use anyhow::Result;
 use tracing::{info, warn, error};
 use bcrypt::{hash, verify, DEFAULT_COST};
 use jsonwebtoken::{encode, decode, Header, Validation, EncodingKey, DecodingKey, Algorithm};
 use serde::{Serialize, Deserialize};
-use std::collections::HashMap;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Sha256, Digest};
+use data_encoding::BASE32_NOPAD;
+use once_cell::sync::Lazy;
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     Json,
     response::IntoResponse,
-    http::StatusCode,
+    http::{HeaderMap, HeaderValue, StatusCode},
 };
+use crate::mailer::{MailSender, OutgoingMail};
+use crate::rate_limit::CLIENT_IP_HEADER;
 use crate::AppState;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -23,6 +29,8 @@ pub struct Claims {
     pub iat: usize, // Issued at
     pub iss: String, // Issuer
     pub aud: String, // Audience
+    #[serde(default)]
+    pub jti: String, // Unique token ID, keeps same-second logins distinct
     pub role: String, // User role
     pub permissions: Vec<String>, // User permissions
     pub security_level: SecurityLevel,
@@ -44,6 +52,37 @@ pub struct LoginResponse {
     pub expires_at: String,
     pub permissions: Vec<String>,
     pub two_factor_required: bool,
+    pub challenge_token: Option<String>,
+}
+
+pub enum LoginOutcome {
+    Authenticated { user: User, token: String },
+    TwoFactorRequired { user: User, challenge_token: String },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TwoFactorLoginRequest {
+    pub challenge_token: String,
+    pub code: String,
+    pub remember_me: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TwoFactorEnrollmentResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TwoFactorCodeRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DisableTwoFactorRequest {
+    pub password: String,
+    pub code: String,
 }
 
 
@@ -79,6 +118,11 @@ pub struct ChangePasswordRequest {
     pub confirm_password: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ResetPasswordRequest {
     pub email: String,
@@ -87,6 +131,98 @@ pub struct ResetPasswordRequest {
     pub confirm_password: String,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyAction {
+    Allow,
+    StepUp,
+    Terminate,
+}
+
+impl AnomalyAction {
+    fn from_env(var: &str, default: AnomalyAction) -> Self {
+        match std::env::var(var).ok().as_deref().map(str::trim) {
+            Some("allow") => AnomalyAction::Allow,
+            Some("step_up") => AnomalyAction::StepUp,
+            Some("terminate") => AnomalyAction::Terminate,
+            Some(other) => {
+                warn!("Unknown {} value '{}', using {:?}", var, other, default);
+                default
+            }
+            None => default,
+        }
+    }
+}
+
+/// What to do when a session is used from a different IP or device than it was issued to
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SessionAnomalyPolicy {
+    pub on_ip_change: AnomalyAction,
+    pub on_device_change: AnomalyAction,
+}
+
+impl SessionAnomalyPolicy {
+    pub fn from_env() -> Self {
+        Self {
+            on_ip_change: AnomalyAction::from_env("SESSION_IP_CHANGE_POLICY", AnomalyAction::Allow),
+            on_device_change: AnomalyAction::from_env("SESSION_DEVICE_CHANGE_POLICY", AnomalyAction::StepUp),
+        }
+    }
+}
+
+pub enum SessionValidation {
+    Valid(User, Session),
+    StepUpRequired(User, Session),
+    Terminated,
+    Invalid,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct SessionSummary {
+    pub id: String,
+    pub device_fingerprint: Option<String>,
+    pub ip_address: String,
+    pub user_agent: Option<String>,
+    pub created_at: chrono::DateTime<Utc>,
+    pub last_accessed: chrono::DateTime<Utc>,
+    pub expires_at: chrono::DateTime<Utc>,
+    pub requires_step_up: bool,
+    pub login_method: Option<String>,
+    #[sqlx(default)]
+    pub current: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateSessionRequest {
+    pub remember_me: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StepUpRequest {
+    pub password: String,
+    pub two_factor_code: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RevokeSessionsQuery {
+    #[serde(default)]
+    pub keep_current: bool,
+}
+
+/// Signing key for session JWTs, from `JWT_SECRET`. Without it a random key is
+/// generated, so tokens issued before a restart stop verifying.
+static JWT_SECRET: Lazy<String> = Lazy::new(|| match std::env::var("JWT_SECRET") {
+    Ok(secret) if !secret.trim().is_empty() => secret,
+    _ => {
+        warn!("JWT_SECRET is not set; signing tokens with a key generated for this run");
+        generate_secure_token()
+    }
+});
+
+pub fn jwt_secret() -> String {
+    JWT_SECRET.clone()
+}
+
 pub struct SecurityService {
     jwt_secret: String,
     token_expiry: Duration,
@@ -95,18 +231,29 @@ pub struct SecurityService {
     lockout_duration: Duration,
     lockdown_duration: Duration,
     two_factor_issuer: String,
+    two_factor_challenge_duration: Duration,
+    two_factor_max_attempts: i32,
+    session_anomaly_policy: SessionAnomalyPolicy,
+    password_history_depth: usize,
+    reset_token_duration: Duration,
 }
 
 impl SecurityService {
     pub fn new(jwt_secret: String) -> Self {
+        let runtime = crate::config::current();
         Self {
             jwt_secret,
             token_expiry: Duration::hours(24),
             password_min_length: 8,
-            max_failed_attempts: 5,
-            lockout_duration: Duration::minutes(15),
+            max_failed_attempts: runtime.max_failed_attempts,
+            lockout_duration: Duration::minutes(runtime.lockout_minutes),
             lockdown_duration: Duration::hours(1),
             two_factor_issuer: "ClawController".to_string(),
+            two_factor_challenge_duration: Duration::minutes(5),
+            two_factor_max_attempts: 5,
+            session_anomaly_policy: SessionAnomalyPolicy::from_env(),
+            password_history_depth: 5,
+            reset_token_duration: Duration::minutes(30),
         }
     }
 
@@ -115,9 +262,10 @@ impl SecurityService {
         pool: &SqlitePool,
         username: &str,
         password: &str,
+        two_factor_code: Option<&str>,
         ip_address: &str,
         user_agent: &str,
-    ) -> Result<Option<(User, String)>, anyhow::Error> {
+    ) -> Result<Option<LoginOutcome>, anyhow::Error> {
         // Find user by username
         let user = sqlx::query_as::<sqlx::Sqlite, User>(
             "SELECT * FROM users WHERE username = ? AND is_active = 1"
@@ -128,102 +276,124 @@ impl SecurityService {
 
         if let Some(user) = user {
             // Check if account is locked
-            if let Some(locked_until) = user.locked_until {
-                if locked_until > Utc::now() {
+            if let Some(locked_until) = user.locked_until
+                && locked_until > Utc::now() {
                     warn!("User {} is locked until {}", username, locked_until);
                     return Ok(None);
                 }
-            }
 
             // Check failed attempts
             if user.failed_login_attempts >= self.max_failed_attempts as i32 {
                 warn!("User {} has exceeded max failed attempts", username);
                 // Lock the account
                 let locked_until = Utc::now() + self.lockout_duration;
-                sqlx::query!(
-                    "UPDATE users SET locked_until = ?, failed_login_attempts = 0 WHERE id = ?",
-                    locked_until,
-                    user.id
-                )
-                .execute(pool)
+                sqlx::query("UPDATE users SET locked_until = ?, failed_login_attempts = 0 WHERE id = ?")
+                    .bind(locked_until)
+                    .bind(&user.id)
+                    .execute(pool)
                 .await?;
                 return Ok(None);
             }
 
             // Verify password
-            if let Some(hash) = &user.password_hash {
-                if verify(password, hash).is_ok() {
-                    // Reset failed attempts on successful login
-                    if user.failed_login_attempts > 0 {
-                        sqlx::query!(
-                            "UPDATE users SET failed_login_attempts = 0, last_login = CURRENT_TIMESTAMP WHERE id = ?",
-                            user.id
-                        )
-                        .execute(pool)
-                        .await?;
+            if verify(password, &user.password_hash).unwrap_or(false) {
+                // Second factor: accept an inline code, otherwise hand out a challenge.
+                // Failed attempts are only reset once both factors have passed.
+                if user.two_factor_enabled {
+                    match two_factor_code {
+                        Some(code) => {
+                            if !self.verify_second_factor(pool, &user, code).await? {
+                                let failed_attempts = self.record_failed_attempt(pool, &user.id).await?;
+                                self.log_security_event(
+                                    pool,
+                                    "two_factor_failure",
+                                    "Invalid two-factor code at login",
+                                    Some(ip_address.to_string()),
+                                    None,
+                                    Some(&user.id),
+                                    serde_json::json!({
+                                        "username": username,
+                                        "failed_attempts": failed_attempts,
+                                        "user_agent": user_agent
+                                    }).to_string(),
+                                ).await?;
+                                return Ok(None);
+                            }
+                        }
+                        None => {
+                            let challenge_token = self.create_two_factor_challenge(pool, &user, ip_address, user_agent).await?;
+                            return Ok(Some(LoginOutcome::TwoFactorRequired { user, challenge_token }));
+                        }
                     }
+                }
 
-                    // Log successful login
-                    self.log_security_event(
-                        pool,
-                        "login_success",
-                        "User logged in successfully",
-                        Some(ip_address.to_string()),
-                        None,
-                        Some(&user.id),
-                        serde_json::json!({
-                            "username": username,
-                            "user_agent": user_agent
-                        }).to_string(),
-                    ).await?;
-
-                    return Ok((user, self.generate_token(&user)?));
-                } else {
-                    // Increment failed attempts
-                    let new_attempts = user.failed_login_attempts + 1;
-                    sqlx::query!(
-                        "UPDATE users SET failed_login_attempts = ? WHERE id = ?",
-                        new_attempts,
-                        user.id
-                    )
+                sqlx::query("UPDATE users SET failed_login_attempts = 0, last_login = CURRENT_TIMESTAMP WHERE id = ?")
+                    .bind(&user.id)
                     .execute(pool)
                     .await?;
 
-                    // Log failed login attempt
-                    self.log_security_event(
-                        pool,
-                        "login_failure",
-                        "Invalid password attempt",
-                        Some(ip_address.to_string()),
-                        None,
-                        Some(&user.id),
-                        serde_json::json!({
-                            "username": username,
-                            "failed_attempts": new_attempts,
-                            "user_agent": user_agent
-                        }).to_string(),
-                    ).await?;
-
-                    if new_attempts >= self.max_failed_attempts as i32 {
-                        // Lock the account
-                        let locked_until = Utc::now() + self.lockout_duration;
-                        sqlx::query!(
-                            "UPDATE users SET locked_until = ? WHERE id = ?",
-                            locked_until,
-                            user.id
-                        )
-                        .execute(pool)
-                        .await?;
-                    }
+                // Log successful login
+                self.log_security_event(
+                    pool,
+                    "login_success",
+                    "User logged in successfully",
+                    Some(ip_address.to_string()),
+                    None,
+                    Some(&user.id),
+                    serde_json::json!({
+                        "username": username,
+                        "user_agent": user_agent,
+                        "two_factor": user.two_factor_enabled
+                    }).to_string(),
+                ).await?;
 
-                    return Ok(None);
-                }
+                let token = self.generate_token(&user)?;
+                return Ok(Some(LoginOutcome::Authenticated { user, token }));
+            } else {
+                let new_attempts = self.record_failed_attempt(pool, &user.id).await?;
+
+                // Log failed login attempt
+                self.log_security_event(
+                    pool,
+                    "login_failure",
+                    "Invalid password attempt",
+                    Some(ip_address.to_string()),
+                    None,
+                    Some(&user.id),
+                    serde_json::json!({
+                        "username": username,
+                        "failed_attempts": new_attempts,
+                        "user_agent": user_agent
+                    }).to_string(),
+                ).await?;
+
+                return Ok(None);
             }
         }
 
         Ok(None)
     }
 
+    /// Counts a failed password or second-factor attempt and locks the account once
+    /// `max_failed_attempts` is reached. Returns the new count.
+    async fn record_failed_attempt(&self, pool: &SqlitePool, user_id: &str) -> Result<i32, anyhow::Error> {
+        let attempts: i32 = sqlx::query_scalar(
+            "UPDATE users SET failed_login_attempts = failed_login_attempts + 1 WHERE id = ? RETURNING failed_login_attempts"
+        )
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+
+        if attempts >= self.max_failed_attempts as i32 {
+            sqlx::query("UPDATE users SET locked_until = ? WHERE id = ?")
+                .bind(Utc::now() + self.lockout_duration)
+                .bind(user_id)
+                .execute(pool)
+                .await?;
+        }
+        Ok(attempts)
+    }
+
     pub async fn create_user(
         &self,
         pool: &SqlitePool,
@@ -267,8 +437,8 @@ impl SecurityService {
             failed_login_attempts: 0,
             locked_until: None,
             security_level: request.security_level.unwrap_or(SecurityLevel::Internal),
-            access_level: request.access_level_or(AccessLevel::ReadOnly),
-            permissions: request.permissions.unwrap_or_default(),
+            access_level: request.access_level.unwrap_or(AccessLevel::ReadOnly),
+            permissions: request.permissions.as_ref().map(serde_json::to_string).transpose()?,
             created_at: now,
             updated_at: now,
             timezone: Some("UTC".to_string()),
@@ -277,7 +447,6 @@ impl SecurityService {
             created_ip: None,
             modified_at: None,
             modified_by: None,
-            last_login_ip: None,
             last_password_change: Utc::now(),
             two_factor_enabled: false,
             two_factor_secret: None,
@@ -287,7 +456,7 @@ impl SecurityService {
         };
 
         // Insert user
-        sqlx::query!(
+        sqlx::query(
             r#"
             INSERT INTO users (
                 id, username, email, password_hash, role, is_active, 
@@ -296,24 +465,24 @@ impl SecurityService {
                 email_verified, phone_verified, timezone, language
             ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
-            user.id,
-            user.username,
-            user.email,
-            user.password_hash,
-            user.role,
-            user.is_active,
-            user.security_level,
-            user.access_level,
-            serde_json::to_string(&user.permissions)?,
-            user.created_at,
-            user.updated_at,
-            user.last_password_change,
-            user.two_factor_enabled,
-            user.email_verified,
-            user.phone_verified,
-            user.timezone,
-            user.language
         )
+        .bind(&user.id)
+        .bind(&user.username)
+        .bind(&user.email)
+        .bind(&user.password_hash)
+        .bind(&user.role)
+        .bind(user.is_active)
+        .bind(user.security_level)
+        .bind(user.access_level)
+        .bind(&user.permissions)
+        .bind(user.created_at)
+        .bind(user.updated_at)
+        .bind(user.last_password_change)
+        .bind(user.two_factor_enabled)
+        .bind(user.email_verified)
+        .bind(user.phone_verified)
+        .bind(&user.timezone)
+        .bind(&user.language)
         .execute(pool)
         .await?;
 
@@ -332,6 +501,20 @@ impl SecurityService {
                 "created_by": created_by
             }).to_string(),
         ).await?;
+        crate::audit::AuditService::log_entity_event(
+            pool,
+            "user",
+            &user.id,
+            "create",
+            None,
+            Some(&serde_json::json!({ "username": user.username, "role": user.role }).to_string()),
+            Some(created_by),
+            None,
+            Some(ip_address),
+            None,
+            None,
+            None,
+        ).await?;
 
         info!("Created user: {}", user.username);
 
@@ -352,64 +535,62 @@ impl SecurityService {
         .fetch_one(pool)
         .await?;
 
+        // Build dynamic UPDATE query
+        let mut update = sqlx::QueryBuilder::<sqlx::Sqlite>::new("UPDATE users SET updated_at = CURRENT_TIMESTAMP");
         let mut updated_fields = Vec::new();
-        
+
         if let Some(email) = &request.email {
-            updated_fields.push(("email", email));
+            update.push(", email = ").push_bind(email.clone());
+            updated_fields.push("email");
         }
         if let Some(role) = &request.role {
-            updated_fields.push(("role", role));
+            update.push(", role = ").push_bind(role.clone());
+            updated_fields.push("role");
         }
-        if let Some(security_level) = &request.security_level {
-            updated_fields.push(("security_level", format!("{:?}", security_level)));
+        if let Some(security_level) = request.security_level {
+            update.push(", security_level = ").push_bind(security_level);
+            updated_fields.push("security_level");
         }
-        if let Some(access_level) = &request.access_level {
-            updated_fields.push(("access_level", format!("{:?}", access_level)));
+        if let Some(access_level) = request.access_level {
+            update.push(", access_level = ").push_bind(access_level);
+            updated_fields.push("access_level");
         }
         if let Some(permissions) = &request.permissions {
-            updated_fields.push(("permissions", serde_json::to_string(&permissions)?));
+            update.push(", permissions = ").push_bind(serde_json::to_string(permissions)?);
+            updated_fields.push("permissions");
         }
         if let Some(is_active) = request.is_active {
-            updated_fields.push(("is_active", format!("{}", is_active)));
+            update.push(", is_active = ").push_bind(is_active);
+            updated_fields.push("is_active");
         }
         if let Some(profile_picture) = &request.profile_picture {
-            updated_fields.push(("profile_picture", profile_picture.clone()));
+            update.push(", profile_picture = ").push_bind(profile_picture.clone());
+            updated_fields.push("profile_picture");
         }
         if let Some(timezone) = &request.timezone {
-            updated_fields(("timezone", timezone.clone()));
+            update.push(", timezone = ").push_bind(timezone.clone());
+            updated_fields.push("timezone");
         }
         if let Some(language) = &request.language {
-            updated_fields.push(("language", language.clone()));
+            update.push(", language = ").push_bind(language.clone());
+            updated_fields.push("language");
         }
 
         if updated_fields.is_empty() {
             return Ok(user);
         }
 
-        // Build dynamic UPDATE query
-        let mut query = "UPDATE users SET updated_at = CURRENT_TIMESTAMP".to_string();
-        let mut params = Vec::new();
-        
-        for (field, value) in updated_fields {
-            query.push_str(&format!(", {} = ?", field));
-            params.push(value);
-        }
-        query.push_str(" WHERE id = ?");
-        params.push(user_id.to_string());
+        update.push(" WHERE id = ").push_bind(user_id);
+        update.build().execute(pool).await?;
 
-        sqlx::query(&query)
-            .bind_all(&params)
-            .execute(pool)
-            .await?;
+        // Role and direct permissions feed the resolved permission set
+        crate::rbac::invalidate_permission_cache(Some(user_id));
 
         // Fetch updated user
-        let updated_user = sqlx::query_as::<sqlx::Sqlite, 
-            User,
-            "SELECT * FROM users WHERE id = ?",
-            user_id
-        )
-        .fetch_one(pool)
-        .await?;
+        let updated_user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_one(pool)
+            .await?;
 
         // Log update
         self.log_security_event(
@@ -421,7 +602,7 @@ impl SecurityService {
             Some(user_id),
             serde_json::json!({
                 "modified_by": modified_by,
-                "updated_fields": updated_fields.iter().map(|(field, _)| field.to_string()).collect::<Vec<_>>()
+                "updated_fields": updated_fields
             }).to_string(),
         ).await?;
 
@@ -443,23 +624,19 @@ impl SecurityService {
         .await?;
 
         // Verify current password
-        if let Some(hash) = &user.password_hash {
-            if !verify(&request.current_password, hash).is_ok() {
-                self.log_security_event(
-                    pool,
-                    "password_change_failed",
-                    "Invalid current password",
-                    Some(ip_address.to_string()),
-                    None,
-                    Some(user_id),
-                    serde_json::json!({
-                        "reason": "invalid_current_password"
-                    }).to_string(),
-                ).await?;
-                return Err(anyhow::anyhow!("Invalid current password"));
-            }
-        } else {
-            return Err(anyhow::anyhow!("No password hash found"));
+        if !verify(&request.current_password, &user.password_hash).unwrap_or(false) {
+            self.log_security_event(
+                pool,
+                "password_change_failed",
+                "Invalid current password",
+                Some(ip_address.to_string()),
+                None,
+                Some(user_id),
+                serde_json::json!({
+                    "reason": "invalid_current_password"
+                }).to_string(),
+            ).await?;
+            return Err(anyhow::anyhow!("Invalid current password"));
         }
 
         // Validate new password
@@ -467,21 +644,8 @@ impl SecurityService {
             return Err(anyhow::anyhow!("Passwords do not match"));
         }
 
-        if request.new_password.len() < self.password_min_length {
-            return Err(anyhow::anyhow!("Password too short"));
-        }
-
-        // Hash new password
-        let new_hash = hash(&request.new_password, DEFAULT_COST)?;
-
-        // Update password and reset failed attempts
-        sqlx::query!(
-            "UPDATE users SET password_hash = ?, last_password_change = CURRENT_TIMESTAMP, failed_login_attempts = 0 WHERE id = ?",
-            new_hash,
-            user_id
-        )
-        .execute(pool)
-        .await?;
+        self.check_new_password(pool, &user, &request.new_password).await?;
+        self.store_new_password(pool, &user, &request.new_password).await?;
 
         // Log password change
         self.log_security_event(
@@ -501,6 +665,84 @@ impl SecurityService {
         Ok(())
     }
 
+    /// Issues a single-use reset token and mails it to the account owner.
+    /// Unknown addresses succeed silently so the endpoint cannot be used to probe accounts.
+    pub async fn request_password_reset(
+        &self,
+        pool: &SqlitePool,
+        mailer: &dyn MailSender,
+        email: &str,
+        ip_address: &str,
+    ) -> Result<(), anyhow::Error> {
+        let user = sqlx::query_as::<sqlx::Sqlite, User>(
+            "SELECT * FROM users WHERE email = ? AND is_active = 1"
+        )
+        .bind(email)
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(user) = user else {
+            info!("Password reset requested for unknown email from {}", ip_address);
+            return Ok(());
+        };
+
+        let reset_token = generate_secure_token();
+        let expires_at = Utc::now() + self.reset_token_duration;
+
+        // Only the newest token is valid
+        sqlx::query("UPDATE password_reset_tokens SET used_at = CURRENT_TIMESTAMP WHERE user_id = ? AND used_at IS NULL")
+            .bind(&user.id)
+            .execute(pool)
+            .await?;
+
+        sqlx::query(
+            "INSERT INTO password_reset_tokens (id, user_id, token_hash, expires_at, requested_ip, created_at)
+             VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP)"
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(&user.id)
+        .bind(hash_token(&reset_token))
+        .bind(expires_at)
+        .bind(ip_address)
+        .execute(pool)
+        .await?;
+
+        let base_url = std::env::var("PUBLIC_BASE_URL").unwrap_or_else(|_| "http://localhost:5173".to_string());
+        let body = format!(
+            "Hello {},\n\nA password reset was requested for your ClawController account.\n\n\
+             Reset link: {}/reset-password?email={}&token={}\n\n\
+             The link expires at {} and can only be used once. If you did not request this, \
+             you can ignore this message.\n",
+            user.username,
+            base_url.trim_end_matches('/'),
+            percent_encode(&user.email),
+            reset_token,
+            expires_at.to_rfc3339()
+        );
+
+        mailer.send(OutgoingMail {
+            to: user.email.clone(),
+            subject: "ClawController password reset".to_string(),
+            body,
+        }).await?;
+
+        self.log_security_event(
+            pool,
+            "password_reset_requested",
+            "Password reset token issued",
+            Some(ip_address.to_string()),
+            None,
+            Some(&user.id),
+            serde_json::json!({
+                "email": user.email,
+                "expires_at": expires_at.to_rfc3339(),
+                "mailer": mailer.name()
+            }).to_string(),
+        ).await?;
+
+        Ok(())
+    }
+
     pub async fn reset_password(
         &self,
         pool: &SqlitePool,
@@ -515,53 +757,146 @@ impl SecurityService {
         .fetch_optional(pool)
         .await?;
 
-        if let Some(user) = user {
-            // Verify reset token (in production, this would be sent via email)
-            // For now, we'll accept the token directly
-            if request.reset_token.len() < 32 {
-                return Err(anyhow::anyhow!("Invalid reset token"));
-            }
-
-            // Validate new password
-            if request.new_password != request.confirm_password {
-                return Err(anyhow::anyhow!("Passwords do not match"));
-            }
-
-            if request.new_password.len() < self.password_min_length {
-                return Err(anyhow::anyhow!("Password too short"));
-            }
-
-            // Hash new password
-            let new_hash = hash(&request.new_password, DEFAULT_COST)?;
+        let Some(user) = user else {
+            return Err(anyhow::anyhow!("Invalid or expired reset token"));
+        };
 
-            // Update password and reset failed attempts
-            sqlx::query!(
-                "UPDATE users SET password_hash = ?, last_password_change = CURRENT_TIMESTAMP, failed_login_attempts = 0, locked_until = NULL WHERE id = ?",
-                new_hash,
-                user.id
-            )
-            .execute(pool)
-            .await?;
+        // Claim the token before looking at the new password, so nothing about the
+        // account is revealed without a valid token. Held open until the password is
+        // accepted so a rejected password does not burn the token.
+        let mut tx = pool.begin().await?;
+        let consumed = sqlx::query(
+            "UPDATE password_reset_tokens SET used_at = CURRENT_TIMESTAMP
+             WHERE user_id = ? AND token_hash = ? AND used_at IS NULL AND datetime(expires_at) > datetime(?)"
+        )
+        .bind(&user.id)
+        .bind(hash_token(&request.reset_token))
+        .bind(Utc::now())
+        .execute(&mut *tx)
+        .await?;
 
-            // Log password reset
+        if consumed.rows_affected() == 0 {
+            tx.rollback().await?;
             self.log_security_event(
                 pool,
-                "password_reset",
-                "Password reset completed",
+                "password_change_failed",
+                "Invalid or expired password reset token",
                 Some(ip_address.to_string()),
                 None,
                 Some(&user.id),
                 serde_json::json!({
-                    "email": user.email,
-                    "reset_by": "user"
+                    "reason": "invalid_reset_token"
                 }).to_string(),
             ).await?;
+            return Err(anyhow::anyhow!("Invalid or expired reset token"));
+        }
 
-            info!("Password reset completed for: {}", user.email);
-        } else {
-            return Err(anyhow::anyhow!("Email not found"));
+        if request.new_password != request.confirm_password {
+            tx.rollback().await?;
+            return Err(anyhow::anyhow!("Passwords do not match"));
+        }
+
+        if let Err(e) = self.check_new_password(pool, &user, &request.new_password).await {
+            tx.rollback().await?;
+            return Err(e);
         }
 
+        tx.commit().await?;
+
+        self.store_new_password(pool, &user, &request.new_password).await?;
+
+        sqlx::query("UPDATE users SET locked_until = NULL WHERE id = ?")
+            .bind(&user.id)
+            .execute(pool)
+            .await?;
+
+        // Whoever held the old password may still hold a session
+        let revoked = self.revoke_all_user_sessions(pool, &user.id, None, "password_reset").await?;
+
+        // Log password reset
+        self.log_security_event(
+            pool,
+            "password_reset",
+            "Password reset completed",
+            Some(ip_address.to_string()),
+            None,
+            Some(&user.id),
+            serde_json::json!({
+                "email": user.email,
+                "reset_by": "user",
+                "sessions_revoked": revoked
+            }).to_string(),
+        ).await?;
+
+        info!("Password reset completed for: {}", user.email);
+
+        Ok(())
+    }
+
+    /// Applies the strength rules and rejects reuse of the current or last N passwords
+    async fn check_new_password(&self, pool: &SqlitePool, user: &User, new_password: &str) -> Result<(), anyhow::Error> {
+        let (is_strong, issues) = validate_password_strength(new_password);
+        if !is_strong || new_password.len() < self.password_min_length {
+            return Err(anyhow::anyhow!("Password does not meet requirements: {}", issues.join("; ")));
+        }
+
+        let previous_hashes = sqlx::query_scalar::<sqlx::Sqlite, String>(
+            "SELECT password_hash FROM password_history WHERE user_id = ? ORDER BY created_at DESC LIMIT ?"
+        )
+        .bind(&user.id)
+        .bind(self.password_history_depth as i64)
+        .fetch_all(pool)
+        .await?;
+
+        let reused = std::iter::once(&user.password_hash)
+            .chain(previous_hashes.iter())
+            .any(|old_hash| verify(new_password, old_hash).unwrap_or(false));
+
+        if reused {
+            return Err(anyhow::anyhow!(
+                "Password was used recently; choose one not among your last {} passwords",
+                self.password_history_depth
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn store_new_password(&self, pool: &SqlitePool, user: &User, new_password: &str) -> Result<(), anyhow::Error> {
+        let new_hash = hash(new_password, DEFAULT_COST)?;
+
+        let mut tx = pool.begin().await?;
+
+        sqlx::query("INSERT INTO password_history (id, user_id, password_hash, created_at) VALUES (?, ?, ?, CURRENT_TIMESTAMP)")
+            .bind(uuid::Uuid::new_v4().to_string())
+            .bind(&user.id)
+            .bind(&user.password_hash)
+            .execute(&mut *tx)
+            .await?;
+
+        // Update password and reset failed attempts
+        sqlx::query(
+            "UPDATE users SET password_hash = ?, last_password_change = CURRENT_TIMESTAMP, failed_login_attempts = 0 WHERE id = ?"
+        )
+        .bind(&new_hash)
+        .bind(&user.id)
+        .execute(&mut *tx)
+        .await?;
+
+        // Keep only as much history as the reuse check looks at
+        sqlx::query(
+            "DELETE FROM password_history WHERE user_id = ? AND id NOT IN (
+                SELECT id FROM password_history WHERE user_id = ? ORDER BY created_at DESC LIMIT ?
+             )"
+        )
+        .bind(&user.id)
+        .bind(&user.id)
+        .bind(self.password_history_depth as i64)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
         Ok(())
     }
 
@@ -603,29 +938,29 @@ impl SecurityService {
         };
 
         // Clean up expired sessions
-        sqlx::query!("DELETE FROM sessions WHERE expires_at < CURRENT_TIMESTAMP")
+        sqlx::query("DELETE FROM sessions WHERE expires_at < CURRENT_TIMESTAMP")
             .execute(pool)
             .await?;
 
         // Insert new session
-        sqlx::query!(
+        sqlx::query(
             r#"
             INSERT INTO sessions (
             id, user_id, token, expires_at, created_at, last_accessed, ip_address, 
             user_agent, is_active, device_fingerprint
         ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#,
-            session.id,
-            session.user_id,
-            session.token,
-            session.expires_at,
-            session.created_at,
-            session.last_accessed,
-            session.ip_address,
-            session.user_agent,
-            session.is_active,
-            session.device_fingerprint
         )
+        .bind(&session.id)
+        .bind(&session.user_id)
+        .bind(&session.token)
+        .bind(session.expires_at)
+        .bind(session.created_at)
+        .bind(session.last_accessed)
+        .bind(&session.ip_address)
+        .bind(&session.user_agent)
+        .bind(session.is_active)
+        .bind(&session.device_fingerprint)
         .execute(pool)
         .await?;
 
@@ -640,92 +975,285 @@ impl SecurityService {
             serde_json::json!({
                 "session_id": session_id,
                 "remember_me": remember_me,
-                "device_fingerprint": device_fingerprint
+                "device_fingerprint": session.device_fingerprint
             }).to_string(),
         ).await?;
 
         Ok(session)
     }
 
+    /// Resolves a session token and applies the anomaly policy when the caller's
+    /// device fingerprint no longer matches the one recorded at sign-in.
     pub async fn validate_session(
         &self,
         pool: &SqlitePool,
         token: &str,
         ip_address: &str,
-    ) -> Result<Option<User>, anyhow::Error> {
+        user_agent: &str,
+    ) -> Result<SessionValidation, anyhow::Error> {
         // Find session by token
         let session = sqlx::query_as::<sqlx::Sqlite, Session>(
             r#"
             SELECT s.id, s.user_id, s.token, s.expires_at, s.created_at, s.last_accessed, s.ip_address, s.user_agent, s.is_active, s.device_fingerprint FROM sessions s
             JOIN users u ON s.user_id = u.id
-            WHERE s.token = ? AND s.is_active = 1 AND (s.expires_at > CURRENT_TIMESTAMP)
+            WHERE s.token = ? AND s.is_active = 1 AND datetime(s.expires_at) > datetime(?)
             "#
         )
         .bind(token)
+        .bind(Utc::now())
         .fetch_optional(pool)
         .await?;
 
-        if let Some(session) = session {
-            // Update last_accessed
-            sqlx::query(
-                "UPDATE sessions SET last_accessed = CURRENT_TIMESTAMP WHERE id = ?"
-            )
-            .bind(&session.id)
-            .execute(pool)
-            .await?;
+        let Some(session) = session else {
+            return Ok(SessionValidation::Invalid);
+        };
 
-            // Check if IP address has changed significantly
-            if session.ip_address != ip_address {
-                // Log potential session hijacking
-                self.log_security_event(
-                    pool,
-                    "suspicious_activity",
-                    "Session IP address changed",
-                    Some(ip_address.to_string()),
-                    None,
-                    Some(&session.user_id),
-                    serde_json::json!({
-                        "old_ip": session.ip_address,
-                        "new_ip": ip_address,
-                        "user_agent": session.user_agent
-                    }).to_string(),
-                ).await?;
-            }
+        // Return the associated user
+        let user = sqlx::query_as::<sqlx::Sqlite, User>(
+            "SELECT * FROM users WHERE id = ? AND is_active = 1"
+        )
+        .bind(&session.user_id)
+        .fetch_optional(pool)
+        .await?;
 
-            // Return the associated user
-            let user = sqlx::query_as::<sqlx::Sqlite, User>(
-                "SELECT * FROM users WHERE id = ? AND is_active = 1"
-            )
-            .bind(&session.user_id)
-            .fetch_one(pool)
-            .await?;
+        let Some(user) = user else {
+            return Ok(SessionValidation::Invalid);
+        };
 
-            Ok(Some(user))
-        } else {
-            Ok(None)
+        // Update last_accessed
+        sqlx::query(
+            "UPDATE sessions SET last_accessed = CURRENT_TIMESTAMP WHERE id = ?"
+        )
+        .bind(&session.id)
+        .execute(pool)
+        .await?;
+
+        let mut step_up_pending = sqlx::query_scalar::<sqlx::Sqlite, bool>(
+            "SELECT COALESCE(requires_step_up, 0) FROM sessions WHERE id = ?"
+        )
+        .bind(&session.id)
+        .fetch_one(pool)
+        .await?;
+
+        let fingerprint = self.generate_device_fingerprint(user_agent, ip_address);
+        if session.device_fingerprint.as_deref() != Some(fingerprint.as_str()) && !step_up_pending {
+            // The fingerprint covers user agent and IP; an unchanged user agent means only the IP moved
+            let (anomaly, action) = if session.user_agent == user_agent {
+                ("ip_change", self.session_anomaly_policy.on_ip_change)
+            } else {
+                ("device_change", self.session_anomaly_policy.on_device_change)
+            };
+
+            self.log_security_event(
+                pool,
+                "suspicious_activity",
+                "Session fingerprint mismatch",
+                Some(ip_address.to_string()),
+                Some(format!("session:{}", session.id)),
+                Some(&session.user_id),
+                serde_json::json!({
+                    "anomaly": anomaly,
+                    "action": action,
+                    "old_ip": session.ip_address,
+                    "new_ip": ip_address,
+                    "old_user_agent": session.user_agent,
+                    "new_user_agent": user_agent,
+                    "old_fingerprint": session.device_fingerprint,
+                    "new_fingerprint": fingerprint
+                }).to_string(),
+            ).await?;
+
+            match action {
+                AnomalyAction::Allow => {
+                    // Adopt the new fingerprint so the same change is not reported on every request
+                    sqlx::query("UPDATE sessions SET ip_address = ?, user_agent = ?, device_fingerprint = ? WHERE id = ?")
+                        .bind(ip_address)
+                        .bind(user_agent)
+                        .bind(&fingerprint)
+                        .bind(&session.id)
+                        .execute(pool)
+                        .await?;
+                }
+                AnomalyAction::StepUp => {
+                    sqlx::query("UPDATE sessions SET requires_step_up = 1 WHERE id = ?")
+                        .bind(&session.id)
+                        .execute(pool)
+                        .await?;
+                    step_up_pending = true;
+                }
+                AnomalyAction::Terminate => {
+                    sqlx::query(
+                        "UPDATE sessions SET is_active = 0, revoked_at = CURRENT_TIMESTAMP, revoked_by = 'policy' WHERE id = ?"
+                    )
+                    .bind(&session.id)
+                    .execute(pool)
+                    .await?;
+
+                    warn!("Terminated session {} for {} after {}", session.id, user.username, anomaly);
+                    return Ok(SessionValidation::Terminated);
+                }
+            }
+        }
+
+        if step_up_pending {
+            return Ok(SessionValidation::StepUpRequired(user, session));
+        }
+
+        Ok(SessionValidation::Valid(user, session))
+    }
+
+    /// Clears a pending step-up once the user re-proves their password (and
+    /// second factor when enabled) from the new device.
+    pub async fn step_up_session(
+        &self,
+        pool: &SqlitePool,
+        user: &User,
+        session_id: &str,
+        password: &str,
+        two_factor_code: Option<&str>,
+        ip_address: &str,
+        user_agent: &str,
+    ) -> Result<bool, anyhow::Error> {
+        let mut verified = verify(password, &user.password_hash).unwrap_or(false);
+        if verified && user.two_factor_enabled {
+            verified = match two_factor_code {
+                Some(code) => self.verify_second_factor(pool, user, code).await?,
+                None => false,
+            };
+        }
+
+        if !verified {
+            self.log_security_event(
+                pool,
+                "login_failure",
+                "Session step-up verification failed",
+                Some(ip_address.to_string()),
+                Some(format!("session:{}", session_id)),
+                Some(&user.id),
+                serde_json::json!({
+                    "username": user.username,
+                    "user_agent": user_agent
+                }).to_string(),
+            ).await?;
+            return Ok(false);
         }
+
+        sqlx::query(
+            "UPDATE sessions SET requires_step_up = 0, ip_address = ?, user_agent = ?, device_fingerprint = ?,
+             two_factor_verified = ? WHERE id = ? AND user_id = ?"
+        )
+        .bind(ip_address)
+        .bind(user_agent)
+        .bind(self.generate_device_fingerprint(user_agent, ip_address))
+        .bind(user.two_factor_enabled)
+        .bind(session_id)
+        .bind(&user.id)
+        .execute(pool)
+        .await?;
+
+        info!("Session {} stepped up for {}", session_id, user.username);
+
+        Ok(true)
+    }
+
+    pub async fn list_user_sessions(&self, pool: &SqlitePool, user_id: &str) -> Result<Vec<SessionSummary>, anyhow::Error> {
+        let sessions = sqlx::query_as::<sqlx::Sqlite, SessionSummary>(
+            "SELECT id, device_fingerprint, ip_address, user_agent, created_at, last_accessed, expires_at,
+                COALESCE(requires_step_up, 0) AS requires_step_up, login_method
+             FROM sessions
+             WHERE user_id = ? AND is_active = 1 AND datetime(expires_at) > datetime(?)
+             ORDER BY last_accessed DESC"
+        )
+        .bind(user_id)
+        .bind(Utc::now())
+        .fetch_all(pool)
+        .await?;
+
+        Ok(sessions)
     }
 
     pub async fn revoke_session(&self, pool: &SqlitePool, token: &str) -> Result<(), anyhow::Error> {
-        sqlx::query!(
-            "UPDATE sessions SET is_active = 0 WHERE token = ?",
-            token
+        sqlx::query(
+            "UPDATE sessions SET is_active = 0, revoked_at = CURRENT_TIMESTAMP WHERE token = ?"
         )
+        .bind(token)
         .execute(pool)
         .await?;
 
         Ok(())
     }
 
-    pub async fn revoke_all_user_sessions(&self, pool: &SqlitePool, user_id: &str) -> Result<(), anyhow::Error> {
-        sqlx::query!(
-            "UPDATE sessions SET is_active = 0 WHERE user_id = ?",
-            user_id
+    pub async fn revoke_session_by_id(
+        &self,
+        pool: &SqlitePool,
+        user_id: &str,
+        session_id: &str,
+        revoked_by: &str,
+    ) -> Result<bool, anyhow::Error> {
+        let result = sqlx::query(
+            "UPDATE sessions SET is_active = 0, revoked_at = CURRENT_TIMESTAMP, revoked_by = ?
+             WHERE id = ? AND user_id = ? AND is_active = 1"
         )
+        .bind(revoked_by)
+        .bind(session_id)
+        .bind(user_id)
         .execute(pool)
         .await?;
 
-        Ok(())
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        self.log_security_event(
+            pool,
+            "session_revoked",
+            "Session revoked",
+            None,
+            Some(format!("session:{}", session_id)),
+            Some(user_id),
+            serde_json::json!({ "revoked_by": revoked_by }).to_string(),
+        ).await?;
+
+        Ok(true)
+    }
+
+    /// Revokes every active session of `user_id`, optionally keeping the caller's own.
+    pub async fn revoke_all_user_sessions(
+        &self,
+        pool: &SqlitePool,
+        user_id: &str,
+        except_session_id: Option<&str>,
+        revoked_by: &str,
+    ) -> Result<u64, anyhow::Error> {
+        let result = sqlx::query(
+            "UPDATE sessions SET is_active = 0, revoked_at = CURRENT_TIMESTAMP, revoked_by = ?
+             WHERE user_id = ? AND is_active = 1 AND (? IS NULL OR id != ?)"
+        )
+        .bind(revoked_by)
+        .bind(user_id)
+        .bind(except_session_id)
+        .bind(except_session_id)
+        .execute(pool)
+        .await?;
+
+        let revoked = result.rows_affected();
+        if revoked > 0 {
+            self.log_security_event(
+                pool,
+                "session_revoked",
+                "All sessions revoked",
+                None,
+                None,
+                Some(user_id),
+                serde_json::json!({
+                    "revoked_by": revoked_by,
+                    "sessions": revoked,
+                    "kept_session": except_session_id
+                }).to_string(),
+            ).await?;
+        }
+
+        Ok(revoked)
     }
 
     pub fn generate_token(&self, user: &User) -> Result<String, anyhow::Error> {
@@ -737,8 +1265,13 @@ impl SecurityService {
             iat: Utc::now().timestamp() as usize,
             iss: "ClawController".to_string(),
             aud: "clawcontroller-api".to_string(),
+            jti: uuid::Uuid::new_v4().to_string(),
             role: user.role.clone(),
-            permissions: user.permissions.clone().unwrap_or_default(),
+            permissions: user
+                .permissions
+                .as_deref()
+                .and_then(|p| serde_json::from_str(p).ok())
+                .unwrap_or_default(),
             security_level: user.security_level,
             access_level: user.access_level,
         };
@@ -781,6 +1314,10 @@ impl SecurityService {
             "data_breach" => 95,
             "suspicious_activity" => 70,
             "malware_detected" => 100,
+            "two_factor_failure" => 75,
+            "two_factor_disabled" => 60,
+            "session_revoked" => 30,
+            "password_change_failed" => 60,
             _ => 50,
         };
 
@@ -791,21 +1328,21 @@ impl SecurityService {
             _ => "critical",
         };
 
-        sqlx::query!(
+        sqlx::query(
             r#"
             INSERT INTO security_events (
                 id, event_type, severity, description, source_ip, target_resource, user_id, details, created_at
             ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
             "#,
-            uuid::Uuid::new_v4().to_string(),
-            event_type,
-            severity,
-            description,
-            ip_address,
-            target_resource,
-            user_id,
-            details
         )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(event_type)
+        .bind(severity)
+        .bind(description)
+        .bind(ip_address)
+        .bind(target_resource)
+        .bind(user_id)
+        .bind(details)
         .execute(pool)
         .await?;
 
@@ -814,6 +1351,7 @@ impl SecurityService {
 
     pub async fn check_permissions(
         &self,
+        pool: &SqlitePool,
         user: &User,
         resource: &str,
         action: &str,
@@ -823,52 +1361,19 @@ impl SecurityService {
             return true;
         }
 
-        // Check specific permissions
-        if let Some(permissions) = &user.permissions {
-            let required_permission = format!("{}:{}", resource, action);
-            permissions.contains(&required_permission)
-        } else {
-            false
+        match crate::rbac::RbacService.check(pool, user, resource, action).await {
+            Ok(allowed) => allowed,
+            Err(e) => {
+                warn!("Failed to resolve permissions for {}: {}", user.id, e);
+                false
+            }
         }
     }
 
-    pub async fn get_user_permissions(&self, user: &User) -> Vec<String> {
-        // Get user's direct permissions
-        let mut permissions = user.permissions.clone().unwrap_or_default();
-        
-        // Add role-based permissions
-        match user.role.as_str() {
-            "SUPER_ADMIN" => {
-                permissions.extend(vec![
-                    "system:read", "system:write", "system:delete", "system:admin",
-                    "users:read", "users:write", "users:delete", "users:admin",
-                    "agents:read", "agents:write", "agents:delete", "agents:admin",
-                    "tasks:read", "tasks:write", "tasks:delete", "tasks:admin",
-                    "audit:read", "security:read", "monitoring:read",
-                ]);
-            },
-            "ADMIN" => {
-                permissions.extend(vec![
-                    "agents:read", "agents:write", "agents:delete", "agents:admin",
-                    "tasks:read", "tasks:write", "tasks:delete",
-                    "users:read", "audit:read",
-                    "monitoring:read",
-                ]);
-            },
-            "USER" => {
-                permissions.extend(vec![
-                    "agents:read", "tasks:read", "monitoring:read",
-                ]);
-            },
-            "READ_ONLY" => {
-                permissions.extend(vec![
-                    "agents:read", "tasks:read",
-                ]);
-            },
-            _ => {}
-        }
-
-        permissions
+    /// Effective permission patterns from the user's roles and direct grants
+    pub async fn get_user_permissions(&self, pool: &SqlitePool, user: &User) -> Result<Vec<String>, anyhow::Error> {
+        let (_, grants) = crate::rbac::RbacService.resolve(pool, user).await?;
+        Ok(grants)
     }
 
     pub async fn validate_access_level(
@@ -886,6 +1391,389 @@ impl SecurityService {
     ) -> bool {
         user.security_level >= required_level
     }
+
+    /// Checks a TOTP code (rejecting replays of an already used time step) or,
+    /// failing that, consumes a matching recovery code.
+    pub async fn verify_second_factor(
+        &self,
+        pool: &SqlitePool,
+        user: &User,
+        code: &str,
+    ) -> Result<bool, anyhow::Error> {
+        let Some(secret) = &user.two_factor_secret else {
+            return Ok(false);
+        };
+
+        if let Some(step) = verify_totp(secret, code, Utc::now()) {
+            let result = sqlx::query(
+                "UPDATE users SET two_factor_last_step = ?
+                 WHERE id = ? AND (two_factor_last_step IS NULL OR two_factor_last_step < ?)"
+            )
+            .bind(step)
+            .bind(&user.id)
+            .bind(step)
+            .execute(pool)
+            .await?;
+
+            return Ok(result.rows_affected() > 0);
+        }
+
+        let result = sqlx::query(
+            "UPDATE two_factor_recovery_codes SET used_at = CURRENT_TIMESTAMP
+             WHERE user_id = ? AND code_hash = ? AND used_at IS NULL"
+        )
+        .bind(&user.id)
+        .bind(hash_token(&normalize_recovery_code(code)))
+        .execute(pool)
+        .await?;
+
+        if result.rows_affected() > 0 {
+            let remaining = sqlx::query_scalar::<sqlx::Sqlite, i64>(
+                "SELECT COUNT(*) FROM two_factor_recovery_codes WHERE user_id = ? AND used_at IS NULL"
+            )
+            .bind(&user.id)
+            .fetch_one(pool)
+            .await?;
+
+            warn!("User {} signed in with a recovery code ({} remaining)", user.username, remaining);
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    pub async fn create_two_factor_challenge(
+        &self,
+        pool: &SqlitePool,
+        user: &User,
+        ip_address: &str,
+        user_agent: &str,
+    ) -> Result<String, anyhow::Error> {
+        let challenge_token = generate_secure_token();
+        let expires_at = Utc::now() + self.two_factor_challenge_duration;
+
+        sqlx::query(
+            "INSERT INTO two_factor_challenges (id, user_id, token_hash, ip_address, user_agent, attempts, expires_at, created_at)
+             VALUES (?, ?, ?, ?, ?, 0, ?, ?)"
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(&user.id)
+        .bind(hash_token(&challenge_token))
+        .bind(ip_address)
+        .bind(user_agent)
+        .bind(expires_at)
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+
+        self.log_security_event(
+            pool,
+            "two_factor_challenge",
+            "Password accepted, two-factor challenge issued",
+            Some(ip_address.to_string()),
+            None,
+            Some(&user.id),
+            serde_json::json!({
+                "username": user.username,
+                "expires_at": expires_at.to_rfc3339(),
+                "user_agent": user_agent
+            }).to_string(),
+        ).await?;
+
+        Ok(challenge_token)
+    }
+
+    pub async fn complete_two_factor_login(
+        &self,
+        pool: &SqlitePool,
+        challenge_token: &str,
+        code: &str,
+        ip_address: &str,
+        user_agent: &str,
+    ) -> Result<Option<(User, String)>, anyhow::Error> {
+        let challenge = sqlx::query_as::<sqlx::Sqlite, (String, String, i32)>(
+            "SELECT id, user_id, attempts FROM two_factor_challenges
+             WHERE token_hash = ? AND consumed_at IS NULL AND datetime(expires_at) > datetime(?)"
+        )
+        .bind(hash_token(challenge_token))
+        .bind(Utc::now())
+        .fetch_optional(pool)
+        .await?;
+
+        let Some((challenge_id, user_id, attempts)) = challenge else {
+            return Ok(None);
+        };
+
+        let user = sqlx::query_as::<sqlx::Sqlite, User>(
+            "SELECT * FROM users WHERE id = ? AND is_active = 1"
+        )
+        .bind(&user_id)
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(user) = user else {
+            return Ok(None);
+        };
+        if user.locked_until.is_some_and(|locked_until| locked_until > Utc::now()) {
+            warn!("User {} is locked until {:?}", user.username, user.locked_until);
+            return Ok(None);
+        }
+
+        if !self.verify_second_factor(pool, &user, code).await? {
+            let attempts = attempts + 1;
+            // Fresh challenges are one password away, so guesses also count against the account
+            let failed_attempts = self.record_failed_attempt(pool, &user.id).await?;
+
+            // Burn the challenge once it has absorbed too many guesses
+            sqlx::query(
+                "UPDATE two_factor_challenges
+                 SET attempts = ?, consumed_at = CASE WHEN ? >= ? THEN CURRENT_TIMESTAMP ELSE consumed_at END
+                 WHERE id = ?"
+            )
+            .bind(attempts)
+            .bind(attempts)
+            .bind(self.two_factor_max_attempts)
+            .bind(&challenge_id)
+            .execute(pool)
+            .await?;
+
+            self.log_security_event(
+                pool,
+                "two_factor_failure",
+                "Invalid two-factor code for login challenge",
+                Some(ip_address.to_string()),
+                None,
+                Some(&user.id),
+                serde_json::json!({
+                    "username": user.username,
+                    "attempts": attempts,
+                    "failed_attempts": failed_attempts,
+                    "user_agent": user_agent
+                }).to_string(),
+            ).await?;
+
+            return Ok(None);
+        }
+
+        sqlx::query("UPDATE two_factor_challenges SET consumed_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(&challenge_id)
+            .execute(pool)
+            .await?;
+
+        sqlx::query("UPDATE users SET failed_login_attempts = 0, last_login = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(&user.id)
+            .execute(pool)
+            .await?;
+
+        self.log_security_event(
+            pool,
+            "login_success",
+            "User logged in with two-factor authentication",
+            Some(ip_address.to_string()),
+            None,
+            Some(&user.id),
+            serde_json::json!({
+                "username": user.username,
+                "user_agent": user_agent,
+                "two_factor": true
+            }).to_string(),
+        ).await?;
+
+        let token = self.generate_token(&user)?;
+        Ok(Some((user, token)))
+    }
+
+    /// Generates a new TOTP secret and recovery codes. The secret stays pending
+    /// until `confirm_two_factor_enrollment` sees a valid code from it.
+    pub async fn begin_two_factor_enrollment(
+        &self,
+        pool: &SqlitePool,
+        user: &User,
+        ip_address: &str,
+    ) -> Result<TwoFactorEnrollmentResponse, anyhow::Error> {
+        if user.two_factor_enabled {
+            return Err(anyhow::anyhow!("Two-factor authentication is already enabled"));
+        }
+
+        let secret = generate_totp_secret();
+        let recovery_codes = generate_recovery_codes(RECOVERY_CODE_COUNT);
+
+        let mut tx = pool.begin().await?;
+
+        sqlx::query("UPDATE users SET two_factor_secret = ?, two_factor_last_step = NULL, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(&secret)
+            .bind(&user.id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM two_factor_recovery_codes WHERE user_id = ?")
+            .bind(&user.id)
+            .execute(&mut *tx)
+            .await?;
+
+        for code in &recovery_codes {
+            sqlx::query("INSERT INTO two_factor_recovery_codes (id, user_id, code_hash, created_at) VALUES (?, ?, ?, CURRENT_TIMESTAMP)")
+                .bind(uuid::Uuid::new_v4().to_string())
+                .bind(&user.id)
+                .bind(hash_token(code))
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        self.log_security_event(
+            pool,
+            "two_factor_enrolled",
+            "Two-factor enrollment started",
+            Some(ip_address.to_string()),
+            None,
+            Some(&user.id),
+            serde_json::json!({
+                "username": user.username,
+                "stage": "pending"
+            }).to_string(),
+        ).await?;
+
+        Ok(TwoFactorEnrollmentResponse {
+            otpauth_uri: build_otpauth_uri(&self.two_factor_issuer, &user.username, &secret),
+            secret,
+            recovery_codes,
+        })
+    }
+
+    pub async fn confirm_two_factor_enrollment(
+        &self,
+        pool: &SqlitePool,
+        user: &User,
+        code: &str,
+        ip_address: &str,
+    ) -> Result<bool, anyhow::Error> {
+        if user.two_factor_enabled {
+            return Err(anyhow::anyhow!("Two-factor authentication is already enabled"));
+        }
+        let Some(secret) = &user.two_factor_secret else {
+            return Err(anyhow::anyhow!("No pending two-factor enrollment"));
+        };
+
+        // Only a TOTP code proves the authenticator app was set up
+        let Some(step) = verify_totp(secret, code, Utc::now()) else {
+            self.log_security_event(
+                pool,
+                "two_factor_failure",
+                "Invalid code while confirming two-factor enrollment",
+                Some(ip_address.to_string()),
+                None,
+                Some(&user.id),
+                serde_json::json!({ "username": user.username }).to_string(),
+            ).await?;
+            return Ok(false);
+        };
+
+        sqlx::query("UPDATE users SET two_factor_enabled = 1, two_factor_last_step = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(step)
+            .bind(&user.id)
+            .execute(pool)
+            .await?;
+
+        self.log_security_event(
+            pool,
+            "two_factor_enrolled",
+            "Two-factor authentication enabled",
+            Some(ip_address.to_string()),
+            None,
+            Some(&user.id),
+            serde_json::json!({
+                "username": user.username,
+                "stage": "enabled"
+            }).to_string(),
+        ).await?;
+
+        info!("Two-factor authentication enabled for {}", user.username);
+
+        Ok(true)
+    }
+
+    /// Turns 2FA off after re-checking the password and a current second factor.
+    pub async fn disable_two_factor(
+        &self,
+        pool: &SqlitePool,
+        user: &User,
+        password: &str,
+        code: &str,
+        ip_address: &str,
+    ) -> Result<bool, anyhow::Error> {
+        if !user.two_factor_enabled {
+            return Err(anyhow::anyhow!("Two-factor authentication is not enabled"));
+        }
+
+        let password_ok = verify(password, &user.password_hash).unwrap_or(false);
+        if !password_ok || !self.verify_second_factor(pool, user, code).await? {
+            self.log_security_event(
+                pool,
+                "two_factor_failure",
+                "Re-authentication failed while disabling two-factor authentication",
+                Some(ip_address.to_string()),
+                None,
+                Some(&user.id),
+                serde_json::json!({
+                    "username": user.username,
+                    "password_ok": password_ok
+                }).to_string(),
+            ).await?;
+            return Ok(false);
+        }
+
+        let mut tx = pool.begin().await?;
+
+        sqlx::query(
+            "UPDATE users SET two_factor_enabled = 0, two_factor_secret = NULL, two_factor_last_step = NULL,
+             updated_at = CURRENT_TIMESTAMP WHERE id = ?"
+        )
+        .bind(&user.id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM two_factor_recovery_codes WHERE user_id = ?")
+            .bind(&user.id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("UPDATE two_factor_challenges SET consumed_at = CURRENT_TIMESTAMP WHERE user_id = ? AND consumed_at IS NULL")
+            .bind(&user.id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        self.log_security_event(
+            pool,
+            "two_factor_disabled",
+            "Two-factor authentication disabled",
+            Some(ip_address.to_string()),
+            None,
+            Some(&user.id),
+            serde_json::json!({ "username": user.username }).to_string(),
+        ).await?;
+
+        warn!("Two-factor authentication disabled for {}", user.username);
+
+        Ok(true)
+    }
+
+    pub fn decode_token(&self, token: &str) -> Result<Claims, anyhow::Error> {
+        let mut validation = Validation::new(Algorithm::HS512);
+        validation.set_audience(&["clawcontroller-api"]);
+        validation.set_issuer(&["ClawController"]);
+
+        let data = decode::<Claims>(
+            token,
+            &DecodingKey::from_secret(self.jwt_secret.as_ref()),
+            &validation,
+        )?;
+
+        Ok(data.claims)
+    }
 }
 
 // Utility functions for security
@@ -908,6 +1796,111 @@ pub fn generate_secure_token() -> String {
         .collect()
 }
 
+// TOTP (RFC 6238) helpers: HMAC-SHA1, 30 second steps, 6 digits
+type HmacSha1 = Hmac<Sha1>;
+
+const TOTP_STEP_SECONDS: i64 = 30;
+const TOTP_DIGITS: u32 = 6;
+const TOTP_ALLOWED_SKEW_STEPS: i64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+
+pub fn generate_totp_secret() -> String {
+    use rand::RngCore;
+
+    let mut bytes = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    BASE32_NOPAD.encode(&bytes)
+}
+
+pub fn totp_code(key: &[u8], step: i64) -> u32 {
+    let mut mac = HmacSha1::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(&(step as u64).to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // Dynamic truncation (RFC 4226 section 5.3)
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = ((digest[offset] as u32 & 0x7f) << 24)
+        | ((digest[offset + 1] as u32) << 16)
+        | ((digest[offset + 2] as u32) << 8)
+        | (digest[offset + 3] as u32);
+
+    binary % 10u32.pow(TOTP_DIGITS)
+}
+
+/// Returns the matched time step so callers can refuse to accept it twice.
+pub fn verify_totp(secret: &str, code: &str, at: chrono::DateTime<Utc>) -> Option<i64> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    if code.len() != TOTP_DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let expected: u32 = code.parse().ok()?;
+
+    let normalized = secret.trim_end_matches('=').to_ascii_uppercase();
+    let key = BASE32_NOPAD.decode(normalized.as_bytes()).ok()?;
+
+    let current = at.timestamp().div_euclid(TOTP_STEP_SECONDS);
+    (current - TOTP_ALLOWED_SKEW_STEPS..=current + TOTP_ALLOWED_SKEW_STEPS)
+        .find(|step| totp_code(&key, *step) == expected)
+}
+
+pub fn build_otpauth_uri(issuer: &str, account: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        percent_encode(issuer),
+        percent_encode(account),
+        secret,
+        percent_encode(issuer),
+        TOTP_DIGITS,
+        TOTP_STEP_SECONDS
+    )
+}
+
+pub fn generate_recovery_codes(count: usize) -> Vec<String> {
+    use rand::Rng;
+
+    // No 0/o or 1/l so codes survive being read aloud or written down
+    const CHARSET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+    let mut rng = rand::thread_rng();
+
+    (0..count)
+        .map(|_| {
+            let raw: String = (0..10)
+                .map(|_| CHARSET[rng.gen_range(0..CHARSET.len())] as char)
+                .collect();
+            format!("{}-{}", &raw[..5], &raw[5..])
+        })
+        .collect()
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    let raw: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+
+    if raw.len() == 10 {
+        format!("{}-{}", &raw[..5], &raw[5..])
+    } else {
+        raw
+    }
+}
+
+/// SHA-256 hex digest for high-entropy secrets (challenge tokens, recovery codes)
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
 pub fn validate_email(email: &str) -> bool {
     email.contains('@') && email.contains('.') && email.len() > 5
 }
@@ -931,7 +1924,7 @@ pub fn validate_password_strength(password: &str) -> (bool, Vec<String>) {
         issues.push("Password must contain at least one digit".to_string());
     }
     
-    if password.chars().any(|c| !c.is_ascii()) {
+    if !password.is_ascii() {
         issues.push("Password must contain only ASCII characters".to_string());
     }
     
@@ -947,36 +1940,6 @@ pub fn validate_password_strength(password: &str) -> (bool, Vec<String>) {
     (issues.is_empty(), issues)
 }
 
-// Rate limiting
-pub struct RateLimiter {
-    max_requests: u32,
-    window_seconds: u64,
-    requests: std::collections::HashMap<String, (u32, std::time::Instant)>,
-}
-
-impl RateLimiter {
-    pub fn new(max_requests: u32, window_seconds: u64) -> Self {
-        Self {
-            max_requests,
-            window_seconds,
-            requests: std::collections::HashMap::new(),
-        }
-    }
-
-    pub fn is_allowed(&mut self, key: &str) -> bool {
-        let now = std::time::Instant::now();
-        let (count, last_reset) = self.requests.entry(key.to_string()).or_insert((0, now));
-        
-        if now.duration_since(*last_reset) > std::time::Duration::from_secs(self.window_seconds) {
-            *count = 0;
-            *last_reset = now;
-        }
-        
-        *count += 1;
-        *count <= self.max_requests
-    }
-}
-
 // Input validation
 pub fn validate_agent_name(name: &str) -> Result<(), String> {
     if name.is_empty() {
@@ -1006,11 +1969,7 @@ pub fn validate_json_field(json_str: &str) -> Result<serde_json::Value, String>
 
 // Security middleware utilities
 pub fn extract_bearer_token(auth_header: &str) -> Option<String> {
-    if auth_header.starts_with("Bearer ") {
-        Some(auth_header[7..].to_string())
-    } else {
-        None
-    }
+    auth_header.strip_prefix("Bearer ").map(str::to_string)
 }
 
 pub fn is_safe_path(path: &str) -> bool {
@@ -1090,48 +2049,251 @@ impl From<serde_json::Error> for SecurityError {
 }
 
 // Axum Handlers
+fn login_response(user: User, token: String, permissions: Vec<String>, challenge_token: Option<String>) -> LoginResponse {
+    let two_factor_required = challenge_token.is_some();
+    LoginResponse {
+        token,
+        user: UserResponse {
+            id: user.id.clone(),
+            username: user.username.clone(),
+            email: user.email.clone(),
+            role: user.role.clone(),
+            security_level: user.security_level,
+            access_level: user.access_level,
+            is_active: user.is_active,
+            created_at: user.created_at,
+            email_verified: user.email_verified,
+            language: user.language.clone(),
+            profile_picture: user.profile_picture.clone(),
+            timezone: user.timezone.clone(),
+            last_login: user.last_login,
+            two_factor_enabled: user.two_factor_enabled,
+        },
+        expires_at: if two_factor_required {
+            (Utc::now() + Duration::minutes(5)).to_rfc3339()
+        } else {
+            (Utc::now() + Duration::hours(24)).to_rfc3339()
+        },
+        permissions: if two_factor_required {
+            Vec::new()
+        } else {
+            permissions
+        },
+        two_factor_required,
+        challenge_token,
+    }
+}
+
+/// Client IP (as resolved by the rate limit layer) and user agent for security logging
+pub fn client_metadata(headers: &HeaderMap) -> (String, String) {
+    let ip_address = headers
+        .get(CLIENT_IP_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("0.0.0.0")
+        .to_string();
+    let user_agent = headers
+        .get("user-agent")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("unknown")
+        .to_string();
+
+    (ip_address, user_agent)
+}
+
+fn bearer_token(headers: &HeaderMap) -> Result<String, (StatusCode, String)> {
+    headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(extract_bearer_token)
+        .ok_or((StatusCode::UNAUTHORIZED, "Missing bearer token".to_string()))
+}
+
+/// Resolves the bearer token to its active session and user, applying the
+/// session anomaly policy for the calling device
+pub async fn authenticated_session(
+    pool: &SqlitePool,
+    headers: &HeaderMap,
+) -> Result<(User, Session), (StatusCode, String)> {
+    let token = bearer_token(headers)?;
+    let service = SecurityService::new(jwt_secret());
+    service.decode_token(&token)
+        .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid token".to_string()))?;
+
+    let (ip_address, user_agent) = client_metadata(headers);
+    match service.validate_session(pool, &token, &ip_address, &user_agent).await {
+        Ok(SessionValidation::Valid(user, session)) => Ok((user, session)),
+        Ok(SessionValidation::StepUpRequired(_, _)) => {
+            Err((StatusCode::UNAUTHORIZED, "Step-up authentication required".to_string()))
+        }
+        Ok(SessionValidation::Terminated) => {
+            Err((StatusCode::UNAUTHORIZED, "Session terminated by security policy".to_string()))
+        }
+        Ok(SessionValidation::Invalid) => Err((StatusCode::UNAUTHORIZED, "Session expired or revoked".to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+/// Resolves the bearer token on the request to an active user
+pub async fn authenticated_user(
+    pool: &SqlitePool,
+    headers: &HeaderMap,
+) -> Result<User, (StatusCode, String)> {
+    authenticated_session(pool, headers).await.map(|(user, _)| user)
+}
+
+/// Adds the browser hardening headers to every response
+pub async fn security_headers_middleware(
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.insert("x-content-type-options", HeaderValue::from_static("nosniff"));
+    headers.insert("x-frame-options", HeaderValue::from_static("DENY"));
+    headers.insert("x-xss-protection", HeaderValue::from_static("1; mode=block"));
+    headers.insert("referrer-policy", HeaderValue::from_static("no-referrer"));
+    response
+}
+
+fn require_admin(user: &User) -> Result<(), (StatusCode, String)> {
+    if user.access_level >= AccessLevel::Admin {
+        Ok(())
+    } else {
+        Err((StatusCode::FORBIDDEN, "Administrator access required".to_string()))
+    }
+}
+
+/// Feeds a failed login into the per-IP throttle and records when it trips
+async fn record_login_failure(state: &AppState, service: &SecurityService, ip_address: &str, username: &str) {
+    if let Some(distinct_usernames) = state.rate_limiter.record_login_failure(ip_address, username) {
+        warn!("Throttling logins from {} after failures across {} usernames", ip_address, distinct_usernames);
+        if let Err(e) = service.log_security_event(
+            &state.pool,
+            "suspicious_activity",
+            "Repeated login failures from one address, logins throttled",
+            Some(ip_address.to_string()),
+            Some("/api/security/login".to_string()),
+            None,
+            serde_json::json!({
+                "distinct_usernames": distinct_usernames,
+                "last_username": username
+            }).to_string(),
+        ).await {
+            error!("Failed to record login throttling: {}", e);
+        }
+    }
+}
+
 pub async fn authenticate_user(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<LoginRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let service = SecurityService::new("temp-secret".to_string());
-    match service.authenticate_user(&state.pool, &payload.username, &payload.password, "0.0.0.0", "unknown").await {
-        Ok(Some((user, token))) => {
-            Ok(Json(LoginResponse {
-                token,
-                user: UserResponse {
-                    id: user.id.clone(),
-                    username: user.username.clone(),
-                    email: user.email.clone(),
-                    role: user.role.clone(),
-                    security_level: user.security_level,
-                    access_level: user.access_level,
-                    is_active: user.is_active,
-                    created_at: user.created_at,
-                    email_verified: user.email_verified,
-                    language: user.language.clone(),
-                    profile_picture: user.profile_picture.clone(),
-                    timezone: user.timezone.clone(),
-                    last_login: user.last_login,
-                    two_factor_enabled: user.two_factor_enabled,
-                },
-                expires_at: (Utc::now() + Duration::hours(24)).to_rfc3339(),
-                permissions: user.permissions.unwrap_or_default(),
-                two_factor_required: false,
-            }))
+    let service = SecurityService::new(jwt_secret());
+    let (ip_address, user_agent) = client_metadata(&headers);
+    match service.authenticate_user(
+        &state.pool,
+        &payload.username,
+        &payload.password,
+        payload.two_factor_code.as_deref(),
+        &ip_address,
+        &user_agent,
+    ).await {
+        Ok(Some(LoginOutcome::Authenticated { user, .. })) => {
+            let session = service.create_session(&state.pool, &user.id, &ip_address, &user_agent, payload.remember_me.unwrap_or(false)).await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            let permissions = service.get_user_permissions(&state.pool, &user).await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            Ok(Json(login_response(user, session.token, permissions, None)))
+        }
+        Ok(Some(LoginOutcome::TwoFactorRequired { user, challenge_token })) => {
+            Ok(Json(login_response(user, String::new(), Vec::new(), Some(challenge_token))))
+        }
+        Ok(None) => {
+            record_login_failure(&state, &service, &ip_address, &payload.username).await;
+            Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()))
         }
-        Ok(None) => Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string())),
         Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
     }
 }
 
+pub async fn complete_two_factor_login(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<TwoFactorLoginRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let service = SecurityService::new(jwt_secret());
+    let (ip_address, user_agent) = client_metadata(&headers);
+    match service.complete_two_factor_login(&state.pool, &payload.challenge_token, &payload.code, &ip_address, &user_agent).await {
+        Ok(Some((user, _))) => {
+            let session = service.create_session(&state.pool, &user.id, &ip_address, &user_agent, payload.remember_me.unwrap_or(false)).await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            sqlx::query("UPDATE sessions SET login_method = 'two_factor', two_factor_verified = 1 WHERE id = ?")
+                .bind(&session.id)
+                .execute(&state.pool)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            let permissions = service.get_user_permissions(&state.pool, &user).await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            Ok(Json(login_response(user, session.token, permissions, None)))
+        }
+        Ok(None) => {
+            record_login_failure(&state, &service, &ip_address, "two-factor challenge").await;
+            Err((StatusCode::UNAUTHORIZED, "Invalid or expired challenge".to_string()))
+        }
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+pub async fn enroll_two_factor(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = authenticated_user(&state.pool, &headers).await?;
+    let (ip_address, _) = client_metadata(&headers);
+    let service = SecurityService::new(jwt_secret());
+    service.begin_two_factor_enrollment(&state.pool, &user, &ip_address).await
+        .map(Json)
+        .map_err(|e| (StatusCode::CONFLICT, e.to_string()))
+}
+
+pub async fn verify_two_factor_enrollment(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = authenticated_user(&state.pool, &headers).await?;
+    let (ip_address, _) = client_metadata(&headers);
+    let service = SecurityService::new(jwt_secret());
+    match service.confirm_two_factor_enrollment(&state.pool, &user, &payload.code, &ip_address).await {
+        Ok(true) => Ok(Json(serde_json::json!({ "two_factor_enabled": true }))),
+        Ok(false) => Err((StatusCode::UNAUTHORIZED, "Invalid two-factor code".to_string())),
+        Err(e) => Err((StatusCode::CONFLICT, e.to_string())),
+    }
+}
+
+pub async fn disable_two_factor(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<DisableTwoFactorRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = authenticated_user(&state.pool, &headers).await?;
+    let (ip_address, _) = client_metadata(&headers);
+    let service = SecurityService::new(jwt_secret());
+    match service.disable_two_factor(&state.pool, &user, &payload.password, &payload.code, &ip_address).await {
+        Ok(true) => Ok(Json(serde_json::json!({ "two_factor_enabled": false }))),
+        Ok(false) => Err((StatusCode::UNAUTHORIZED, "Re-authentication failed".to_string())),
+        Err(e) => Err((StatusCode::CONFLICT, e.to_string())),
+    }
+}
+
 pub async fn create_user(
     State(state): State<AppState>,
     Json(payload): Json<CreateUserRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let service = SecurityService::new("temp-secret".to_string());
+    let service = SecurityService::new(jwt_secret());
     service.create_user(&state.pool, payload, "system", "0.0.0.0").await
-        .map(|user| Json(user))
+        .map(|user| (StatusCode::CREATED, Json(user)))
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
 }
 
@@ -1140,16 +2302,246 @@ pub async fn update_user(
     State(state): State<AppState>,
     Json(payload): Json<UpdateUserRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let service = SecurityService::new("temp-secret".to_string());
+    let service = SecurityService::new(jwt_secret());
     service.update_user(&state.pool, &id, payload, "system").await
-        .map(|user| Json(user))
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn change_password(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = authenticated_user(&state.pool, &headers).await?;
+    let (ip_address, _) = client_metadata(&headers);
+    let service = SecurityService::new(jwt_secret());
+    service.change_password(&state.pool, &user.id, payload, &ip_address).await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ForgotPasswordRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let (ip_address, _) = client_metadata(&headers);
+    let service = SecurityService::new(jwt_secret());
+    service.request_password_reset(&state.pool, state.mailer.as_ref(), &payload.email, &ip_address).await
+        .map(|_| StatusCode::ACCEPTED)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn reset_password(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let (ip_address, _) = client_metadata(&headers);
+    let service = SecurityService::new(jwt_secret());
+    service.reset_password(&state.pool, payload, &ip_address).await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+pub async fn create_session(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateSessionRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = authenticated_user(&state.pool, &headers).await?;
+    let (ip_address, user_agent) = client_metadata(&headers);
+    let service = SecurityService::new(jwt_secret());
+    service.create_session(&state.pool, &user.id, &ip_address, &user_agent, payload.remember_me.unwrap_or(false)).await
+        .map(|session| (StatusCode::CREATED, Json(serde_json::json!({
+            "id": session.id,
+            "token": session.token,
+            "expires_at": session.expires_at.to_rfc3339(),
+            "device_fingerprint": session.device_fingerprint,
+        }))))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn list_my_sessions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let (user, current) = authenticated_session(&state.pool, &headers).await?;
+    let service = SecurityService::new(jwt_secret());
+    let mut sessions = service.list_user_sessions(&state.pool, &user.id).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    for session in sessions.iter_mut() {
+        session.current = session.id == current.id;
+    }
+    Ok(Json(sessions))
+}
+
+pub async fn revoke_my_sessions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<RevokeSessionsQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let (user, current) = authenticated_session(&state.pool, &headers).await?;
+    let keep = query.keep_current.then_some(current.id.as_str());
+    let service = SecurityService::new(jwt_secret());
+    service.revoke_all_user_sessions(&state.pool, &user.id, keep, &user.id).await
+        .map(|revoked| Json(serde_json::json!({ "revoked": revoked })))
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
 }
 
-pub async fn change_password() -> impl IntoResponse {
-    (StatusCode::NOT_IMPLEMENTED, "Not implemented")
+pub async fn revoke_session_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(session_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = authenticated_user(&state.pool, &headers).await?;
+    let service = SecurityService::new(jwt_secret());
+
+    // Admins may revoke any session; everyone else only their own
+    let owner_id = if user.access_level >= AccessLevel::Admin {
+        sqlx::query_scalar::<sqlx::Sqlite, String>("SELECT user_id FROM sessions WHERE id = ?")
+            .bind(&session_id)
+            .fetch_optional(&state.pool)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .unwrap_or_else(|| user.id.clone())
+    } else {
+        user.id.clone()
+    };
+
+    match service.revoke_session_by_id(&state.pool, &owner_id, &session_id, &user.id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "Active session not found".to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
 }
 
-pub async fn create_session() -> impl IntoResponse {
-    (StatusCode::NOT_IMPLEMENTED, "Not implemented")
+pub async fn step_up_session(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<StepUpRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let token = bearer_token(&headers)?;
+    let (ip_address, user_agent) = client_metadata(&headers);
+    let service = SecurityService::new(jwt_secret());
+
+    // Unlike other endpoints, a session awaiting step-up is accepted here
+    let (user, session) = match service.validate_session(&state.pool, &token, &ip_address, &user_agent).await {
+        Ok(SessionValidation::Valid(user, session)) | Ok(SessionValidation::StepUpRequired(user, session)) => (user, session),
+        Ok(_) => return Err((StatusCode::UNAUTHORIZED, "Session expired or revoked".to_string())),
+        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    };
+
+    match service.step_up_session(
+        &state.pool,
+        &user,
+        &session.id,
+        &payload.password,
+        payload.two_factor_code.as_deref(),
+        &ip_address,
+        &user_agent,
+    ).await {
+        Ok(true) => Ok(Json(serde_json::json!({ "session_id": session.id, "step_up": "verified" }))),
+        Ok(false) => Err((StatusCode::UNAUTHORIZED, "Step-up verification failed".to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+pub async fn list_user_sessions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(user_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let admin = authenticated_user(&state.pool, &headers).await?;
+    require_admin(&admin)?;
+    let service = SecurityService::new(jwt_secret());
+    service.list_user_sessions(&state.pool, &user_id).await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn revoke_user_sessions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(user_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let admin = authenticated_user(&state.pool, &headers).await?;
+    require_admin(&admin)?;
+    let service = SecurityService::new(jwt_secret());
+    service.revoke_all_user_sessions(&state.pool, &user_id, None, &admin.id).await
+        .map(|revoked| Json(serde_json::json!({ "user_id": user_id, "revoked": revoked })))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::common::{create_test_admin_user, create_test_pool};
+    use chrono::TimeZone;
+
+    /// The RFC 6238 Appendix B SHA-1 seed, "12345678901234567890"
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    fn at(timestamp: i64) -> chrono::DateTime<Utc> {
+        Utc.timestamp_opt(timestamp, 0).unwrap()
+    }
+
+    fn code_at(timestamp: i64) -> String {
+        let key = BASE32_NOPAD.decode(RFC_SECRET.as_bytes()).unwrap();
+        format!("{:06}", totp_code(&key, timestamp.div_euclid(TOTP_STEP_SECONDS)))
+    }
+
+    #[test]
+    fn totp_codes_match_the_rfc_6238_vectors() {
+        // Appendix B lists eight digits; six-digit codes are their last six
+        for (timestamp, code) in [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+            (20000000000, "353130"),
+        ] {
+            assert_eq!(code_at(timestamp), code, "T = {}", timestamp);
+            assert_eq!(verify_totp(RFC_SECRET, code, at(timestamp)), Some(timestamp / TOTP_STEP_SECONDS));
+        }
+    }
+
+    #[test]
+    fn totp_accepts_one_step_of_clock_skew_either_way() {
+        let now = 1234567890;
+        let step = now / TOTP_STEP_SECONDS;
+
+        assert_eq!(verify_totp(RFC_SECRET, &code_at(now - 30), at(now)), Some(step - 1));
+        assert_eq!(verify_totp(RFC_SECRET, &code_at(now + 30), at(now)), Some(step + 1));
+        assert_eq!(verify_totp(RFC_SECRET, &code_at(now - 60), at(now)), None);
+        assert_eq!(verify_totp(RFC_SECRET, &code_at(now + 60), at(now)), None);
+    }
+
+    #[test]
+    fn totp_tolerates_formatting_but_not_bad_input() {
+        let now = 1234567890;
+
+        assert!(verify_totp(RFC_SECRET, "005 924", at(now)).is_some());
+        assert!(verify_totp(&RFC_SECRET.to_ascii_lowercase(), "005924", at(now)).is_some());
+        assert!(verify_totp(RFC_SECRET, "5924", at(now)).is_none());
+        assert!(verify_totp(RFC_SECRET, "00592a", at(now)).is_none());
+        assert!(verify_totp("not base32!", "005924", at(now)).is_none());
+    }
+
+    #[tokio::test]
+    async fn totp_codes_are_accepted_once_per_step() {
+        let pool = create_test_pool().await;
+        let (mut user, _) = create_test_admin_user(&pool).await;
+        user.two_factor_secret = Some(RFC_SECRET.to_string());
+        let service = SecurityService::new("test-secret".to_string());
+        let now = Utc::now().timestamp();
+
+        assert!(service.verify_second_factor(&pool, &user, &code_at(now)).await.unwrap());
+        assert!(!service.verify_second_factor(&pool, &user, &code_at(now)).await.unwrap());
+        // An earlier step still inside the skew window is no longer usable either
+        assert!(!service.verify_second_factor(&pool, &user, &code_at(now - 30)).await.unwrap());
+        assert!(service.verify_second_factor(&pool, &user, &code_at(now + 30)).await.unwrap());
+    }
 }
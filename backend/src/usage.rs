@@ -0,0 +1,585 @@
+use crate::db::SqlitePool;
+use crate::agent_metrics::MetricsPeriod;
+use crate::config::ModelPrice;
+use crate::openclaw_integration::openclaw_state_dir;
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path as FsPath, PathBuf};
+use tracing::{debug, info, warn};
+use axum::{
+    extract::{Query, State},
+    Json,
+    response::IntoResponse,
+    http::{HeaderMap, StatusCode},
+};
+use crate::AppState;
+
+/// Label `route_task` gives the sessions it spawns
+const TASK_LABEL_PREFIX: &str = "task:";
+
+/// A session transcript at `<state>/agents/<agent>/sessions/<session>.jsonl`
+#[derive(Debug, Clone)]
+struct SessionTranscript {
+    agent_id: String,
+    session_id: String,
+    task_id: Option<String>,
+    path: PathBuf,
+}
+
+/// Token usage of one assistant turn
+#[derive(Debug, Clone, Default)]
+struct UsageRecord {
+    /// Hash of the transcript line, stable across rewrites that move it, with the
+    /// number of identical lines before it so repeated turns are counted apart
+    line_key: String,
+    timestamp: Option<DateTime<Utc>>,
+    model: Option<String>,
+    provider: Option<String>,
+    input_tokens: i64,
+    output_tokens: i64,
+    cache_read_tokens: i64,
+    cache_write_tokens: i64,
+    reported_cost: Option<f64>,
+}
+
+impl UsageRecord {
+    fn total_tokens(&self) -> i64 {
+        self.input_tokens + self.output_tokens + self.cache_read_tokens + self.cache_write_tokens
+    }
+
+    /// Priced from the configured table; the cost OpenClaw reported is only used
+    /// for models the table does not list
+    fn cost(&self, prices: &HashMap<String, ModelPrice>) -> f64 {
+        let price = self.model.as_deref().and_then(|model| {
+            prices
+                .get(model)
+                .or_else(|| model.rsplit_once('/').and_then(|(_, name)| prices.get(name)))
+        });
+
+        match price {
+            Some(price) => {
+                (self.input_tokens as f64 * price.input
+                    + self.output_tokens as f64 * price.output
+                    + self.cache_read_tokens as f64 * price.cache_read
+                    + self.cache_write_tokens as f64 * price.cache_write)
+                    / 1_000_000.0
+            }
+            None => self.reported_cost.unwrap_or(0.0),
+        }
+    }
+}
+
+fn first_i64(value: &serde_json::Value, keys: &[&str]) -> i64 {
+    keys.iter().find_map(|key| value.get(*key).and_then(|v| v.as_i64())).unwrap_or(0)
+}
+
+fn first_str(values: &[&serde_json::Value], key: &str) -> Option<String> {
+    values.iter().find_map(|value| value.get(key).and_then(|v| v.as_str()).map(str::to_string))
+}
+
+/// Reads usage from a transcript line. Both the nested `{"message": {...}}` layout and
+/// flat usage lines are accepted, with OpenClaw or provider-style token field names.
+fn parse_usage_line(line: &serde_json::Value, line_key: String) -> Option<UsageRecord> {
+    let message = line.get("message").unwrap_or(line);
+    if message.get("role").and_then(|r| r.as_str()).is_some_and(|role| role != "assistant") {
+        return None;
+    }
+    let usage = message.get("usage").or_else(|| line.get("usage"))?;
+
+    let record = UsageRecord {
+        line_key,
+        timestamp: [line, message].iter().find_map(|value| match value.get("timestamp")? {
+            serde_json::Value::String(s) => DateTime::parse_from_rfc3339(s).ok().map(|t| t.with_timezone(&Utc)),
+            serde_json::Value::Number(n) => n.as_i64().and_then(|ms| Utc.timestamp_millis_opt(ms).single()),
+            _ => None,
+        }),
+        model: first_str(&[message, line], "model"),
+        provider: first_str(&[message, line], "provider"),
+        input_tokens: first_i64(usage, &["input", "input_tokens", "inputTokens"]),
+        output_tokens: first_i64(usage, &["output", "output_tokens", "outputTokens"]),
+        cache_read_tokens: first_i64(usage, &["cacheRead", "cache_read_input_tokens", "cache_read"]),
+        cache_write_tokens: first_i64(usage, &["cacheWrite", "cache_creation_input_tokens", "cache_write"]),
+        reported_cost: match usage.get("cost") {
+            Some(serde_json::Value::Object(cost)) => cost.get("total").and_then(|v| v.as_f64()),
+            Some(cost) => cost.as_f64(),
+            None => None,
+        },
+    };
+
+    (record.total_tokens() > 0).then_some(record)
+}
+
+fn task_from_label(label: &str) -> Option<String> {
+    label.strip_prefix(TASK_LABEL_PREFIX).map(str::to_string).filter(|id| !id.is_empty())
+}
+
+/// Lists transcripts with the task each one works on, taken from the session
+/// labels in each agent's `sessions.json`
+fn discover_transcripts(state_dir: &FsPath) -> std::io::Result<Vec<SessionTranscript>> {
+    let agents_dir = state_dir.join("agents");
+    if !agents_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut transcripts = Vec::new();
+    for agent_entry in std::fs::read_dir(&agents_dir)? {
+        let agent_entry = agent_entry?;
+        let sessions_dir = agent_entry.path().join("sessions");
+        if !sessions_dir.is_dir() {
+            continue;
+        }
+        let agent_id = agent_entry.file_name().to_string_lossy().to_string();
+
+        let mut labels: HashMap<String, String> = HashMap::new();
+        if let Ok(index) = std::fs::read_to_string(sessions_dir.join("sessions.json")) {
+            match serde_json::from_str::<serde_json::Value>(&index) {
+                Ok(serde_json::Value::Object(sessions)) => {
+                    for entry in sessions.values() {
+                        if let (Some(session_id), Some(label)) = (
+                            entry.get("sessionId").and_then(|v| v.as_str()),
+                            entry.get("label").and_then(|v| v.as_str()),
+                        ) {
+                            labels.insert(session_id.to_string(), label.to_string());
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Unreadable session index for agent {}: {}", agent_id, e),
+            }
+        }
+
+        for file in std::fs::read_dir(&sessions_dir)? {
+            let path = file?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let Some(session_id) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+                continue;
+            };
+            transcripts.push(SessionTranscript {
+                agent_id: agent_id.clone(),
+                task_id: labels.get(&session_id).and_then(|label| task_from_label(label)),
+                session_id,
+                path,
+            });
+        }
+    }
+
+    Ok(transcripts)
+}
+
+/// Reads complete lines appended since `offset`. A transcript shorter than the
+/// offset was rewritten and is read again from the start. Lines before the offset
+/// are only hashed, to number repeats of a line across runs.
+fn read_new_usage(path: &FsPath, offset: u64) -> std::io::Result<(Vec<UsageRecord>, u64)> {
+    let mut file = std::fs::File::open(path)?;
+    let length = file.metadata()?.len();
+    let offset = if length < offset { 0 } else { offset as usize };
+
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer)?;
+    // A line still being written is picked up on the next run
+    let complete = buffer.iter().rposition(|b| *b == b'\n').map(|i| i + 1).unwrap_or(0).max(offset);
+
+    let mut records = Vec::new();
+    let mut repeats: HashMap<String, usize> = HashMap::new();
+    let mut start = 0;
+    for line in buffer[..complete].split_inclusive(|b| *b == b'\n') {
+        let line_start = start;
+        start += line.len();
+        let line = line.trim_ascii_end();
+        let hash = format!("{:x}", Sha256::digest(line));
+        let seen = repeats.entry(hash.clone()).or_default();
+        let line_key = match *seen {
+            0 => hash[..32].to_string(),
+            n => format!("{}-{}", &hash[..32], n),
+        };
+        *seen += 1;
+
+        if line_start < offset {
+            continue;
+        }
+        if let Ok(value) = serde_json::from_slice::<serde_json::Value>(line)
+            && let Some(record) = parse_usage_line(&value, line_key) {
+                records.push(record);
+            }
+    }
+
+    Ok((records, complete as u64))
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct IngestionSummary {
+    pub transcripts_scanned: u64,
+    pub records_ingested: u64,
+    pub tokens_ingested: i64,
+    pub cost_ingested: f64,
+    /// Transcripts of agents ClawController does not know about
+    pub skipped_transcripts: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CostGrouping {
+    Agent,
+    Task,
+    Tag,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct CostReportRow {
+    pub key: String,
+    pub calls: i64,
+    pub tokens_used: i64,
+    pub cost: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CostReport {
+    pub group_by: CostGrouping,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub total_tokens_used: i64,
+    pub total_cost: f64,
+    pub rows: Vec<CostReportRow>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CostReportQuery {
+    pub group_by: Option<CostGrouping>,
+    pub period: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+pub struct UsageService;
+
+impl UsageService {
+    /// Imports usage appended to OpenClaw transcripts since the last run into
+    /// `agent_activity_detailed`. Rows are keyed by the content of the transcript
+    /// line and how often it repeats before, so neither a run interrupted before its
+    /// offset was saved nor a rewritten transcript read again from the start double
+    /// counts, while identical turns are each counted.
+    pub async fn ingest(&self, pool: &SqlitePool) -> Result<IngestionSummary, anyhow::Error> {
+        let state_dir = openclaw_state_dir();
+        let transcripts = tokio::task::spawn_blocking(move || discover_transcripts(&state_dir)).await??;
+        let prices = crate::config::current().model_prices.clone();
+
+        let offsets: HashMap<String, i64> = sqlx::query_as::<sqlx::Sqlite, (String, i64)>(
+            "SELECT file_path, byte_offset FROM usage_ingestion_offsets"
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect();
+        let known_agents: Vec<String> = sqlx::query_scalar("SELECT id FROM agents")
+            .fetch_all(pool)
+            .await?;
+
+        let mut summary = IngestionSummary::default();
+        for transcript in transcripts {
+            summary.transcripts_scanned += 1;
+            if !known_agents.contains(&transcript.agent_id) {
+                summary.skipped_transcripts += 1;
+                continue;
+            }
+
+            let file_path = transcript.path.to_string_lossy().to_string();
+            let offset = offsets.get(&file_path).copied().unwrap_or(0) as u64;
+            let path = transcript.path.clone();
+            let (records, next_offset) = match tokio::task::spawn_blocking(move || read_new_usage(&path, offset)).await? {
+                Ok(read) => read,
+                Err(e) => {
+                    warn!("Could not read transcript {}: {}", file_path, e);
+                    continue;
+                }
+            };
+            if next_offset == offset {
+                continue;
+            }
+
+            let mut tx = pool.begin().await?;
+            for record in &records {
+                let cost = record.cost(&prices);
+                let inserted = sqlx::query(
+                    "INSERT OR IGNORE INTO agent_activity_detailed
+                     (id, agent_id, activity_type, description, task_id, tokens_used, cost, metadata, timestamp)
+                     VALUES (?, ?, 'model_usage', ?, ?, ?, ?, ?, ?)"
+                )
+                .bind(format!("usage-{}-{}", transcript.session_id, record.line_key))
+                .bind(&transcript.agent_id)
+                .bind(record.model.as_deref().unwrap_or("unknown model"))
+                .bind(&transcript.task_id)
+                .bind(record.total_tokens())
+                .bind(cost)
+                .bind(serde_json::json!({
+                    "source": "openclaw",
+                    "session_id": transcript.session_id,
+                    "model": record.model,
+                    "provider": record.provider,
+                    "input_tokens": record.input_tokens,
+                    "output_tokens": record.output_tokens,
+                    "cache_read_tokens": record.cache_read_tokens,
+                    "cache_write_tokens": record.cache_write_tokens,
+                    "reported_cost": record.reported_cost,
+                }).to_string())
+                .bind(record.timestamp.unwrap_or_else(Utc::now))
+                .execute(&mut *tx)
+                .await?;
+
+                if inserted.rows_affected() > 0 {
+                    summary.records_ingested += 1;
+                    summary.tokens_ingested += record.total_tokens();
+                    summary.cost_ingested += cost;
+                }
+            }
+
+            sqlx::query(
+                "INSERT INTO usage_ingestion_offsets (file_path, agent_id, session_id, task_id, byte_offset, updated_at)
+                 VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+                 ON CONFLICT(file_path) DO UPDATE SET
+                     task_id = excluded.task_id, byte_offset = excluded.byte_offset, updated_at = excluded.updated_at"
+            )
+            .bind(&file_path)
+            .bind(&transcript.agent_id)
+            .bind(&transcript.session_id)
+            .bind(&transcript.task_id)
+            .bind(next_offset as i64)
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            debug!("Ingested {} usage records from {}", records.len(), file_path);
+        }
+
+        if summary.records_ingested > 0 {
+            info!(
+                "Ingested {} usage records ({} tokens, {:.4} USD) from {} transcripts",
+                summary.records_ingested, summary.tokens_ingested, summary.cost_ingested, summary.transcripts_scanned
+            );
+        }
+        Ok(summary)
+    }
+
+    pub async fn cost_report(
+        &self,
+        pool: &SqlitePool,
+        group_by: CostGrouping,
+        period: &MetricsPeriod,
+    ) -> Result<CostReport, anyhow::Error> {
+        let sql = match group_by {
+            CostGrouping::Agent => {
+                "SELECT d.agent_id AS key, COUNT(*) AS calls,
+                        COALESCE(SUM(d.tokens_used), 0) AS tokens_used, COALESCE(SUM(d.cost), 0.0) AS cost
+                 FROM agent_activity_detailed d
+                 WHERE date(d.timestamp) BETWEEN ? AND ?
+                 GROUP BY d.agent_id ORDER BY cost DESC"
+            }
+            CostGrouping::Task => {
+                "SELECT d.task_id AS key, COUNT(*) AS calls,
+                        COALESCE(SUM(d.tokens_used), 0) AS tokens_used, COALESCE(SUM(d.cost), 0.0) AS cost
+                 FROM agent_activity_detailed d
+                 WHERE d.task_id IS NOT NULL AND date(d.timestamp) BETWEEN ? AND ?
+                 GROUP BY d.task_id ORDER BY cost DESC"
+            }
+            // A task with several tags counts towards each of them
+            CostGrouping::Tag => {
+                "SELECT tag.value AS key, COUNT(*) AS calls,
+                        COALESCE(SUM(d.tokens_used), 0) AS tokens_used, COALESCE(SUM(d.cost), 0.0) AS cost
+                 FROM agent_activity_detailed d
+                 JOIN tasks t ON t.id = d.task_id
+                 JOIN json_each(CASE WHEN json_valid(t.tags) THEN t.tags ELSE '[]' END) tag
+                 WHERE date(d.timestamp) BETWEEN ? AND ?
+                 GROUP BY tag.value ORDER BY cost DESC"
+            }
+        };
+
+        let rows = sqlx::query_as::<sqlx::Sqlite, CostReportRow>(sql)
+            .bind(period.from.to_string())
+            .bind(period.to.to_string())
+            .fetch_all(pool)
+            .await?;
+
+        // Totals come from the activity itself, since tag rows overlap
+        let (total_tokens_used, total_cost): (i64, f64) = sqlx::query_as(
+            "SELECT COALESCE(SUM(tokens_used), 0), COALESCE(SUM(cost), 0.0)
+             FROM agent_activity_detailed WHERE date(timestamp) BETWEEN ? AND ?"
+        )
+        .bind(period.from.to_string())
+        .bind(period.to.to_string())
+        .fetch_one(pool)
+        .await?;
+
+        Ok(CostReport { group_by, from: period.from, to: period.to, total_tokens_used, total_cost, rows })
+    }
+}
+
+// Axum Handlers
+pub async fn ingest_usage(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "admin").await?;
+    UsageService.ingest(&state.pool).await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn get_cost_report(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<CostReportQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::rbac::authorized_user(&state.pool, &headers, "agents", "read").await?;
+    let period = MetricsPeriod::parse(
+        query.period.as_deref().unwrap_or("30d"),
+        query.from.as_deref(),
+        query.to.as_deref(),
+    )
+    .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    UsageService.cost_report(&state.pool, query.group_by.unwrap_or(CostGrouping::Agent), &period).await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage_line(output_tokens: i64) -> String {
+        serde_json::json!({
+            "message": { "role": "assistant", "model": "claude-3-sonnet", "usage": { "input": 10, "output": output_tokens } }
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn rewritten_transcripts_yield_new_keys() {
+        let path = std::env::temp_dir().join(format!("usage-test-{}.jsonl", uuid::Uuid::new_v4()));
+        std::fs::write(&path, format!("{}\n{}\n", usage_line(100), usage_line(200))).unwrap();
+        let (first, offset) = read_new_usage(&path, 0).unwrap();
+        assert_eq!(first.len(), 2);
+
+        // Compaction rewrites the file shorter, so it is read again from the start
+        std::fs::write(&path, format!("{}\n", usage_line(300))).unwrap();
+        let (rewritten, _) = read_new_usage(&path, offset).unwrap();
+        assert_eq!(rewritten.len(), 1);
+        assert_eq!(rewritten[0].output_tokens, 300);
+        assert!(first.iter().all(|record| record.line_key != rewritten[0].line_key));
+
+        // The same line hashes the same wherever it sits
+        std::fs::write(&path, format!("{}\n{}\n", usage_line(300), usage_line(100))).unwrap();
+        let (reread, _) = read_new_usage(&path, 0).unwrap();
+        assert_eq!(reread[0].line_key, rewritten[0].line_key);
+        assert_eq!(reread[1].line_key, first[0].line_key);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn identical_turns_get_their_own_keys_across_runs() {
+        let path = std::env::temp_dir().join(format!("usage-test-{}.jsonl", uuid::Uuid::new_v4()));
+        std::fs::write(&path, format!("{}\n{}\n", usage_line(100), usage_line(100))).unwrap();
+        let (first, offset) = read_new_usage(&path, 0).unwrap();
+        assert_eq!(first.len(), 2);
+        assert_ne!(first[0].line_key, first[1].line_key);
+
+        // The same turn appended later is numbered after the ones already read
+        std::fs::write(&path, format!("{}\n{}\n{}\n", usage_line(100), usage_line(100), usage_line(100))).unwrap();
+        let (appended, next_offset) = read_new_usage(&path, offset).unwrap();
+        assert_eq!(appended.len(), 1);
+        assert!(first.iter().all(|record| record.line_key != appended[0].line_key));
+        assert_eq!(next_offset, std::fs::metadata(&path).unwrap().len());
+
+        // Read again from the start, every turn keeps the key it was ingested under
+        let (reread, _) = read_new_usage(&path, 0).unwrap();
+        let keys: Vec<&str> = reread.iter().map(|record| record.line_key.as_str()).collect();
+        assert_eq!(keys, [first[0].line_key.as_str(), first[1].line_key.as_str(), appended[0].line_key.as_str()]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    fn parse(line: serde_json::Value) -> Option<UsageRecord> {
+        parse_usage_line(&line, String::new())
+    }
+
+    #[test]
+    fn usage_lines_accept_openclaw_and_provider_field_names() {
+        let openclaw = parse(serde_json::json!({
+            "timestamp": "2025-03-01T10:00:00Z",
+            "message": {
+                "role": "assistant",
+                "model": "claude-3-sonnet",
+                "provider": "anthropic",
+                "usage": { "input": 1, "output": 2, "cacheRead": 3, "cacheWrite": 4, "cost": { "total": 0.5 } }
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            (openclaw.input_tokens, openclaw.output_tokens, openclaw.cache_read_tokens, openclaw.cache_write_tokens),
+            (1, 2, 3, 4)
+        );
+        assert_eq!(openclaw.model.as_deref(), Some("claude-3-sonnet"));
+        assert_eq!(openclaw.provider.as_deref(), Some("anthropic"));
+        assert_eq!(openclaw.reported_cost, Some(0.5));
+        assert_eq!(openclaw.timestamp, DateTime::parse_from_rfc3339("2025-03-01T10:00:00Z").ok().map(|t| t.with_timezone(&Utc)));
+
+        let provider = parse(serde_json::json!({
+            "model": "gpt-4o",
+            "timestamp": 1740823200000i64,
+            "usage": {
+                "input_tokens": 5, "output_tokens": 6,
+                "cache_read_input_tokens": 7, "cache_creation_input_tokens": 8, "cost": 0.25
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            (provider.input_tokens, provider.output_tokens, provider.cache_read_tokens, provider.cache_write_tokens),
+            (5, 6, 7, 8)
+        );
+        assert_eq!(provider.total_tokens(), 26);
+        assert_eq!(provider.reported_cost, Some(0.25));
+        assert_eq!(provider.timestamp, Utc.timestamp_millis_opt(1740823200000).single());
+
+        let camel = parse(serde_json::json!({ "usage": { "inputTokens": 9, "outputTokens": 10, "cache_read": 1, "cache_write": 2 } })).unwrap();
+        assert_eq!(
+            (camel.input_tokens, camel.output_tokens, camel.cache_read_tokens, camel.cache_write_tokens),
+            (9, 10, 1, 2)
+        );
+        assert_eq!(camel.reported_cost, None);
+    }
+
+    #[test]
+    fn usage_lines_without_assistant_tokens_are_skipped() {
+        assert!(parse(serde_json::json!({ "message": { "role": "user", "usage": { "input": 5 } } })).is_none());
+        assert!(parse(serde_json::json!({ "message": { "role": "assistant", "usage": { "input": 0, "output": 0 } } })).is_none());
+        assert!(parse(serde_json::json!({ "message": { "role": "assistant", "content": "hi" } })).is_none());
+    }
+
+    #[test]
+    fn cost_prefers_the_price_table_over_the_reported_cost() {
+        let prices = HashMap::from([(
+            "claude-3-sonnet".to_string(),
+            ModelPrice { input: 3.0, output: 15.0, cache_read: 0.3, cache_write: 3.75 },
+        )]);
+        let record = |model: Option<&str>, reported_cost| UsageRecord {
+            model: model.map(str::to_string),
+            input_tokens: 1_000_000,
+            output_tokens: 100_000,
+            cache_read_tokens: 1_000_000,
+            cache_write_tokens: 0,
+            reported_cost,
+            ..Default::default()
+        };
+
+        let priced = 3.0 + 1.5 + 0.3;
+        assert!((record(Some("claude-3-sonnet"), Some(99.0)).cost(&prices) - priced).abs() < 1e-9);
+        // Provider-qualified names fall back to the bare model name
+        assert!((record(Some("anthropic/claude-3-sonnet"), None).cost(&prices) - priced).abs() < 1e-9);
+        assert_eq!(record(Some("gpt-4o"), Some(0.42)).cost(&prices), 0.42);
+        assert_eq!(record(None, Some(0.42)).cost(&prices), 0.42);
+        assert_eq!(record(Some("gpt-4o"), None).cost(&prices), 0.0);
+    }
+}
@@ -1,12 +1,6 @@
 use crate::models::*;
 use crate::db::SqlitePool;
-use sqlx::{query, query_as};
 use serde_json::Value;
-use validator::Validate;
-use regex::Regex;
-use tracing::{info, warn, error};
-use std::collections::HashMap;
-use chrono::Utc;
 use axum::{
     extract::{State, Json},
     response::IntoResponse,
@@ -40,25 +34,22 @@ pub fn validate_agent_creation(agent: &CreateAgentRequest) -> Result<(), Vec<Loc
     }
     
     // Validate thinking level
-    if let Some(thinking) = agent.thinking_default {
-        if thinking < 1 || thinking > 6 {
+    if let Some(thinking) = agent.thinking_default
+        && (!(1..=6).contains(&thinking)) {
             errors.push(LocalClawValidationError::new("Thinking level must be between 1 and 6"));
         }
-    }
     
     // Validate temperature
-    if let Some(temp) = agent.temperature {
-        if temp < 0.0 || temp > 2.0 {
+    if let Some(temp) = agent.temperature
+        && (!(0.0..=2.0).contains(&temp)) {
             errors.push(LocalClawValidationError::new("Temperature must be between 0.0 and 2.0"));
         }
-    }
     
     // Validate max tokens
-    if let Some(max_tokens) = agent.max_tokens {
-        if max_tokens < 1 || max_tokens > 128000 {
+    if let Some(max_tokens) = agent.max_tokens
+        && (!(1..=128000).contains(&max_tokens)) {
             errors.push(LocalClawValidationError::new("Max tokens must be between 1 and 128000"));
         }
-    }
     
     if errors.is_empty() {
         Ok(())
@@ -98,32 +89,17 @@ pub fn validate_task_creation(task: &CreateTaskRequest) -> Result<(), Vec<LocalC
     }
     
     // Validate actual hours
-    if let Some(actual) = task.actual_hours {
-        if actual < 0.0 {
+    if let Some(actual) = task.actual_hours
+        && actual < 0.0 {
             errors.push(LocalClawValidationError::new("Actual hours cannot be negative"));
         }
-    }
     
     // Validate complexity score
-    if let Some(complexity) = task.complexity_score {
-        if complexity < 1 || complexity > 10 {
+    if let Some(complexity) = task.complexity_score
+        && (!(1..=10).contains(&complexity)) {
             errors.push(LocalClawValidationError::new("Complexity score must be between 1 and 10"));
         }
-    }
-    
-    // Validate dependencies
-    if let Some(dependencies) = &task.dependencies {
-        if let Err(_) = validate_json_field(dependencies) {
-            errors.push(LocalClawValidationError::new("Dependencies must be valid JSON array"));
-        }
-    }
-    
-    // Validate deliverables
-    if let Some(deliverables) = &task.deliverables {
-        if let Err(_) = validate_json_field(deliverables) {
-            errors.push(LocalClawValidationError::new("Deliverables must be valid JSON array"));
-        }
-    }
+
     
     if errors.is_empty() {
         Ok(())
@@ -144,11 +120,7 @@ pub fn validate_comment_creation(comment: &CreateCommentRequest) -> Result<(), V
     if comment.content.len() > 10000 {
         errors.push(LocalClawValidationError::new("Comment too long (max 10000 characters)"));
     }
-    
-    // Validate agent ID
-    if comment.agent_id.is_empty() {
-        errors.push(LocalClawValidationError::new("Agent ID cannot be empty"));
-    }
+
     
     if errors.is_empty() {
         Ok(())
@@ -188,43 +160,31 @@ pub fn validate_user_creation(user: &CreateUserRequest) -> Result<(), Vec<LocalC
     }
     
     // Validate role
-    match user.role.as_str() {
-        "SUPER_ADMIN" | "ADMIN" | "USER" | "READ_ONLY" => {},
+    match user.role.as_deref() {
+        None | Some("SUPER_ADMIN" | "ADMIN" | "USER" | "READ_ONLY") => {},
         _ => errors.push(LocalClawValidationError::new("Invalid role")),
     }
     
-    // Validate access level
-    match user.access_level {
-        AccessLevel::SuperAdmin | AccessLevel::Admin | AccessLevel::ReadWrite | AccessLevel::ReadOnly => {},
-        _ => errors.push(LocalClawValidationError::new("Invalid access level")),
-    }
-    
-    // Validate security level
-    match user.security_level {
-        SecurityLevel::Public | SecurityLevel::Internal | SecurityLevel::Confidential | 
-        SecurityLevel::Restricted | SecurityLevel::Secret => {},
-        _ => errors.push(LocalClawValidationError::new("Invalid security level")),
-    }
-    
-    // Validate access level vs security level
-    match user.access_level {
-        AccessLevel::SuperAdmin => {}
-        AccessLevel::Admin => {
-            if user.security_level < SecurityLevel::Internal {
-                errors.push(LocalClawValidationError::new("Admin access level requires at least Internal security level"));
+    // Validate access level vs security level when both are given explicitly
+    if let (Some(access_level), Some(security_level)) = (user.access_level, user.security_level) {
+        match access_level {
+            AccessLevel::SuperAdmin => {}
+            AccessLevel::Admin => {
+                if security_level < SecurityLevel::Internal {
+                    errors.push(LocalClawValidationError::new("Admin access level requires at least Internal security level"));
+                }
             }
-        }
-        AccessLevel::ReadWrite => {
-            if user.security_level < SecurityLevel::Internal {
-                errors.push(LocalClawValidationError::new("ReadWrite access level requires at least Internal security level"));
+            AccessLevel::ReadWrite => {
+                if security_level < SecurityLevel::Internal {
+                    errors.push(LocalClawValidationError::new("ReadWrite access level requires at least Internal security level"));
+                }
             }
-        }
-        AccessLevel::ReadOnly => {
-            if user.security_level > SecurityLevel::Public {
-                errors.push(LocalClawValidationError::new("ReadOnly access level cannot exceed Public security level"));
+            AccessLevel::ReadOnly => {
+                if security_level > SecurityLevel::Public {
+                    errors.push(LocalClawValidationError::new("ReadOnly access level cannot exceed Public security level"));
+                }
             }
         }
-        _ => {}
     }
     
     if errors.is_empty() {
@@ -286,50 +246,48 @@ pub async fn validate_resource_access(
     action: &str,
     pool: &SqlitePool,
 ) -> Result<(), String> {
-    // Check basic permissions
-    if !crate::security::SecurityService::check_permissions(user, resource_type, action, pool).await {
+    // Each resource type is checked against the permission resource that covers it
+    let permission_resource = match resource_type {
+        "agent" => "agents",
+        "task" => "tasks",
+        other => return Err(format!("Unknown resource type '{}'", other)),
+    };
+    let allowed = crate::rbac::RbacService
+        .check(pool, user, permission_resource, action)
+        .await
+        .map_err(|e| format!("Permission check failed: {}", e))?;
+    if !allowed {
         return Err("Insufficient permissions".to_string());
     }
-    
-    // Check entity ownership if applicable
+
+    // Check entity ownership
+    let is_super_admin = user.access_level == AccessLevel::SuperAdmin;
     match resource_type {
         "agent" => {
-            let agent = sqlx::query_as::<sqlx::Sqlite, 
-                Agent,
-                "SELECT * FROM agents WHERE id = ? AND is_deleted = 0",
-                resource_id
-            )
-            .fetch_optional(pool)
-            .await?;
-            
-            match (agent, user) {
-                (Some(agent), _) => {
-                    if user.id != agent.created_by && user.access_level != AccessLevel::SuperAdmin {
-                        return Err("Can only modify agents you created or have SuperAdmin access".to_string());
-                    }
-                }
-                (None, _) => return Err("Agent not found".to_string()),
+            let agent = sqlx::query_as::<_, Agent>("SELECT * FROM agents WHERE id = ? AND is_deleted = 0")
+                .bind(resource_id)
+                .fetch_optional(pool)
+                .await
+                .map_err(|e| format!("Database error: {}", e))?
+                .ok_or_else(|| "Agent not found".to_string())?;
+            if agent.created_by.as_deref() != Some(user.id.as_str()) && !is_super_admin {
+                return Err("Can only modify agents you created or have SuperAdmin access".to_string());
             }
         }
-        "task" => {
-            let task = sqlx::query_as::<sqlx::Sqlite, 
-                Task,
-                "SELECT * FROM tasks WHERE id = ? AND is_deleted = 0",
-                resource_id
-            )
-            .fetch_optional(pool)
-            .await?;
-            
-            match (task, user) {
-                (Some(task), _) => {
-                    if user.id != task.created_by && user.access_level != AccessLevel::SuperAdmin {
-                        return Err("Can only modify tasks you created or have SuperAdmin access".to_string());
-                    }
-                }
-                (None, _) => return Err("Task not found".to_string()),
+        _ => {
+            let task = sqlx::query_as::<_, Task>("SELECT * FROM tasks WHERE id = ? AND is_deleted = 0")
+                .bind(resource_id)
+                .fetch_optional(pool)
+                .await
+                .map_err(|e| format!("Database error: {}", e))?
+                .ok_or_else(|| "Task not found".to_string())?;
+            if user.security_level < task.classification {
+                return Err("Task classification exceeds your clearance".to_string());
+            }
+            if task.created_by != user.id && !is_super_admin {
+                return Err("Can only modify tasks you created or have SuperAdmin access".to_string());
             }
         }
-        _ => Ok(()),
     }
 
     Ok(())
@@ -356,23 +314,22 @@ pub fn sanitize_user_input(input: &str) -> String {
 }
 
 pub async fn validate_agent_performance(agent_id: &str, pool: &SqlitePool) -> Result<(), String> {
-    let metrics = sqlx::query_as::<sqlx::Sqlite, 
-        PerformanceMetric,
-        "SELECT * FROM performance_metrics 
-        WHERE entity_type = 'agent' AND entity_id = ? AND timestamp > datetime('now', '-1 hour')
-    ",
-        agent_id
+    let metrics = sqlx::query_as::<_, PerformanceMetric>(
+        "SELECT * FROM performance_metrics
+         WHERE entity_type = 'agent' AND entity_id = ? AND timestamp > datetime('now', '-1 hour')"
     )
+    .bind(agent_id)
     .fetch_optional(pool)
-    .await?;
-    
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
     if let Some(metric) = metrics {
-        if metric.avg_response_time > 5000.0 {
-            return Err(format!("Agent response time too high: {:.2}ms", metric.avg_response_time));
+        if let Some(avg_response_time) = metric.avg_response_time.filter(|ms| *ms > 5000.0) {
+            return Err(format!("Agent response time too high: {:.2}ms", avg_response_time));
         }
-        
-        if metric.request_count > 1000 {
-            return Err(format!("Too many requests: {}", metric.request_count));
+
+        if let Some(request_count) = metric.request_count.filter(|count| *count > 1000) {
+            return Err(format!("Too many requests: {}", request_count));
         }
     }
     
@@ -388,23 +345,23 @@ fn validate_password_strength(password: &str) -> (bool, Vec<String>) {
     let mut issues = Vec::new();
     
     if password.len() < 8 {
-        issues.push("Password must be at least 8 characters long");
+        issues.push("Password must be at least 8 characters long".to_string());
     }
     
     if !password.chars().any(|c| c.is_uppercase()) {
-        issues.push("Password must contain at least one uppercase letter");
+        issues.push("Password must contain at least one uppercase letter".to_string());
     }
     
     if !password.chars().any(|c| c.is_lowercase()) {
-        issues.push("Password must contain at least one lowercase letter");
+        issues.push("Password must contain at least one lowercase letter".to_string());
     }
     
     if !password.chars().any(|c| c.is_numeric()) {
-        issues.push("Password must contain at least one number");
+        issues.push("Password must contain at least one number".to_string());
     }
     
     if !password.chars().any(|c| "!@#$%^&*()_+-=[]{}|;:,.<>?".contains(c)) {
-        issues.push("Password must contain at least one special character");
+        issues.push("Password must contain at least one special character".to_string());
     }
     
     let common_passwords = vec![
@@ -412,8 +369,8 @@ fn validate_password_strength(password: &str) -> (bool, Vec<String>) {
         "welcome", "monkey", "dragon", "password1", "123456789",
     ];
     
-    if common_passwords.contains(&password.to_lowercase()) {
-        issues.push("Password is too common");
+    if common_passwords.contains(&password.to_lowercase().as_str()) {
+        issues.push("Password is too common".to_string());
     }
     
     (issues.is_empty(), issues)
@@ -446,8 +403,8 @@ pub async fn validate_agent_creation_handler(
     Json(payload): Json<CreateAgentRequest>,
 ) -> impl IntoResponse {
     match validate_agent_creation(&payload) {
-        Ok(_) => (StatusCode::OK, Json("Valid")),
-        Err(e) => (StatusCode::BAD_REQUEST, Json(e)),
+        Ok(_) => (StatusCode::OK, Json("Valid")).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(e)).into_response(),
     }
 }
 
@@ -456,8 +413,8 @@ pub async fn validate_task_creation_handler(
     Json(payload): Json<CreateTaskRequest>,
 ) -> impl IntoResponse {
     match validate_task_creation(&payload) {
-        Ok(_) => (StatusCode::OK, Json("Valid")),
-        Err(e) => (StatusCode::BAD_REQUEST, Json(e)),
+        Ok(_) => (StatusCode::OK, Json("Valid")).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(e)).into_response(),
     }
 }
 
@@ -466,14 +423,14 @@ pub async fn validate_comment_creation_handler(
     Json(payload): Json<CreateCommentRequest>,
 ) -> impl IntoResponse {
     match validate_comment_creation(&payload) {
-        Ok(_) => (StatusCode::OK, Json("Valid")),
-        Err(e) => (StatusCode::BAD_REQUEST, Json(e)),
+        Ok(_) => (StatusCode::OK, Json("Valid")).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(e)).into_response(),
     }
 }
 
 pub async fn validate_configuration_friendly_handler(
     State(_state): State<AppState>,
-    Json(payload): Json<Value>,
+    Json(_payload): Json<Value>,
 ) -> impl IntoResponse {
     (StatusCode::NOT_IMPLEMENTED, "Not implemented")
 }
@@ -1,85 +1,147 @@
-use axum::{Router, extract::State, response::IntoResponse};
+use axum::Router;
 use std::sync::Arc;
 use crate::AppState;
-use crate::db::SqlitePool;
+use crate::db::{SqlitePool, SqlitePoolOptions, SqliteConnectOptions};
 use crate::models::*;
+use crate::rate_limit::{RateLimitConfig, RateLimiter};
+use crate::security::{jwt_secret, CreateUserRequest, SecurityService};
 use chrono::Utc;
-use serde_json::Value;
+use serde_json::{json, Value};
+use std::str::FromStr;
 use uuid::Uuid;
 
 pub struct TestApp {
-    pub app: Router<Arc<AppState>>,
-    pub pool: Arc<SqlitePool>,
+    pub app: Router,
+    pub pool: SqlitePool,
+    pub state: AppState,
 }
 
 impl TestApp {
     pub async fn new() -> Self {
+        Self::with_rate_limits(Default::default()).await
+    }
+
+    /// An app whose rate limit layer uses `config` instead of the default budgets
+    pub async fn with_rate_limits(config: RateLimitConfig) -> Self {
         let pool = create_test_pool().await;
-        let state = AppState {
-            pool: pool.clone(),
-            manager: Arc::new(tokio::sync::broadcast::channel("global")),
-            gateway_status: Arc::new(tokio::sync::RwLock::new(crate::GatewayStatus::default())),
-            stuck_task_status: Arc::new(tokio::sync::RwLock::new(crate::StuckTaskStatus::default())),
-        };
-        
-        let app = create_app_with_state(state).await;
-        
-        Self { app, pool }
+        let mut state = create_test_state(pool.clone());
+        state.rate_limiter = Arc::new(RateLimiter::new(config));
+        let app = create_app_with_state(state.clone());
+
+        Self { app, pool, state }
     }
+
+    /// An app without request throttling, for tests that measure throughput
+    pub async fn unthrottled() -> Self {
+        Self::with_rate_limits(RateLimitConfig { enabled: false, ..Default::default() }).await
+    }
+}
+
+pub async fn create_test_app() -> Router {
+    TestApp::new().await.app
 }
 
-pub async fn create_test_pool() -> Arc<SqlitePool> {
-    let database_url = std::env::var("DATABASE_URL")
-        .unwrap_or_else(|| "sqlite:::memory:".to_string());
-    
-    let pool = sqlx::SqlitePool::connect(&database_url)
+/// A migrated database in its own file, so pooled connections and
+/// transactions behave as they do against the real database
+pub async fn create_test_pool() -> SqlitePool {
+    let path = std::env::temp_dir().join(format!("clawcontroller-test-{}.db", Uuid::new_v4()));
+    let options = SqliteConnectOptions::from_str(&format!("sqlite://{}", path.display()))
+        .expect("Invalid test database path")
+        .create_if_missing(true)
+        .foreign_keys(true)
+        .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal);
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect_with(options)
         .await
         .expect("Failed to create database pool");
-    
-    // Run migrations
-    sqlx::migrate!("./migrations")
+
+    crate::migrations::migrate_up(&pool, None)
         .await
         .expect("Failed to run migrations");
-    
-    Arc::new(pool)
-}
+    crate::rbac::seed_default_roles(&pool)
+        .await
+        .expect("Failed to seed roles");
 
-pub async fn create_app_with_state(state: Arc<AppState>) -> Router<Arc<AppState>> {
-    crate::create_app().with_state(state)
+    pool
 }
 
-pub fn create_test_agent() -> Agent {
-    Agent {
-        id: format!("test-agent-{}", Uuid::new_v4()),
-        name: "Test Agent".to_string(),
-        role: "SPC".to_string(),
-        status: AgentStatus::Working,
-        created_at: Utc::now(),
-        updated_at: Utc::now(),
-        is_active: true,
-        is_deleted: false,
-        created_by: None,
-        description: Some("Test agent description".to_string()),
-        avatar: None,
-        workspace: Some("test-workspace".to_string()),
-        token: None,
-        primary_model: Some("claude-3-sonnet".to_string()),
-        fallback_model: Some("gpt-4".to_string()),
-        current_model: None,
-        model_failure_count: 0,
-        skills: Some(serde_json::to_string(&vec!["research", "analysis", "writing"])),
-        max_concurrent: Some(5),
-        max_memory_mb: Some(4096),
-        max_execution_time_minutes: Some(60),
-        thinking_default: Some(3),
-        temperature: Some(0.7),
-        max_tokens: Some(4000),
-        security_level: SecurityLevel::Internal,
-        access_level: AccessLevel::ReadWrite,
-        sandbox_mode: SandboxMode::Enabled,
+pub fn create_test_state(pool: SqlitePool) -> AppState {
+    let runtime = crate::config::RuntimeConfig::default();
+    AppState {
+        pool,
+        manager: Arc::new(crate::ConnectionManager::new()),
+        gateway_status: Arc::new(tokio::sync::RwLock::new(GatewayStatus {
+            health_status: "unknown".to_string(),
+            uptime_seconds: 0,
+            last_check_time: Utc::now(),
+            restart_count: 0,
+            config: runtime.gateway.clone(),
+        })),
+        stuck_task_status: Arc::new(tokio::sync::RwLock::new(StuckTaskStatus {
+            total_notifications_sent: 0,
+            currently_tracked_tasks: 0,
+            last_run: Utc::now(),
+            config: runtime.monitoring.clone(),
+        })),
+        mailer: Arc::new(crate::mailer::LogMailer),
+        rate_limiter: Arc::new(RateLimiter::new(Default::default())),
+        collaboration: Arc::new(crate::openclaw_advanced_features::AgentCollaboration::new()),
+        agent_pool: Arc::new(crate::openclaw_optimization::AgentPool::new(vec![])),
+        resource_manager: Arc::new(tokio::sync::RwLock::new(crate::openclaw_optimization::DynamicResourceManager::new())),
+        cache: Arc::new(crate::openclaw_optimization::HierarchicalCache::new(1000, 5000)),
+        bulk_jobs: Arc::new(crate::agent_jobs::BulkJobRegistry::new()),
     }
 }
 
+pub fn create_app_with_state(state: AppState) -> Router {
+    crate::create_app(state)
+}
+
+/// Inserts a bare agent row and returns its id
+pub async fn insert_test_agent(pool: &SqlitePool, name: &str) -> String {
+    let id = format!("test-agent-{}", Uuid::new_v4());
+    sqlx::query("INSERT INTO agents (id, name, role, status, primary_model) VALUES (?, ?, 'SPC', 'IDLE', 'claude-3-sonnet')")
+        .bind(&id)
+        .bind(name)
+        .execute(pool)
+        .await
+        .expect("Failed to insert test agent");
+    id
+}
+
+/// Creates a user holding `role` and a session for it; returns the user and bearer token
+pub async fn create_test_user_with_role(pool: &SqlitePool, role: &str) -> (User, String) {
+    let service = SecurityService::new(jwt_secret());
+    let username = format!("user-{}", Uuid::new_v4().simple());
+    let user = service.create_user(
+        pool,
+        CreateUserRequest {
+            email: format!("{}@example.com", username),
+            username,
+            password: "Str0ng-Test-Pass!".to_string(),
+            role: Some(role.to_string()),
+            security_level: Some(SecurityLevel::Confidential),
+            access_level: None,
+            permissions: None,
+        },
+        "test",
+        "127.0.0.1",
+    )
+    .await
+    .expect("Failed to create test user");
+
+    let session = service.create_session(pool, &user.id, "0.0.0.0", "unknown", false)
+        .await
+        .expect("Failed to create test session");
+    (user, session.token)
+}
+
+pub async fn create_test_admin_user(pool: &SqlitePool) -> (User, String) {
+    create_test_user_with_role(pool, "SUPER_ADMIN").await
+}
+
 pub fn create_test_agent_data() -> Value {
     json!({
         "id": format!("test-agent-{}", Uuid::new_v4()),
@@ -101,30 +163,6 @@ pub fn create_test_agent_data() -> Value {
     })
 }
 
-pub fn create_test_task() -> Task {
-    Task {
-        id: format!("test-task-{}", Uuid::new_v4()),
-        title: "Test Task".to_string(),
-        description: Some("Test task description".to_string()),
-        status: TaskStatus::Inbox,
-        priority: Priority::Medium,
-        tags: Some(serde_json::to_string(&vec!["test", "sample"])),
-        assignee_id: None,
-        reviewer: None,
-        reviewer_id: None,
-        created_at: Utc::now(),
-        updated_at: Utc::now(),
-        due_at: None,
-        estimated_hours: Some(2.0),
-        actual_hours: None,
-        complexity_score: Some(5),
-        dependencies: Some(serde_json::to_string(&vec![])),
-        deliverables: Some(serde_json::to_string(&vec![])),
-        is_deleted: false,
-        created_by: Some("test-user".to_string()),
-    }
-}
-
 pub fn create_test_task_data() -> Value {
     json!({
         "title": "Test Task",
@@ -136,23 +174,9 @@ pub fn create_test_task_data() -> Value {
     })
 }
 
-pub fn create_test_user() -> User {
-    User {
-        id: format!("user-{}", Uuid::new_v4()),
-        username: "testuser".to_string(),
-        email: "test@example.com".to_string(),
-        password_hash: "hashed_password".to_string(),
-        role: "USER".to_string(),
-        access_level: AccessLevel::ReadWrite,
-        security_level: SecurityLevel::Internal,
-        is_active: true,
-        created_at: Utc::now(),
-        updated_at: Utc::now(),
-        profile_picture: None,
-        timezone: "UTC".to_string(),
-        preferences: None,
-        last_login: None,
-        failed_login_attempts: 0,
-        locked_until: None,
-    }
+pub async fn response_json<T: serde::de::DeserializeOwned>(response: axum::response::Response) -> T {
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("Failed to read response body");
+    serde_json::from_slice(&body).expect("Response body is not the expected JSON")
 }
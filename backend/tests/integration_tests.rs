@@ -1,13 +1,11 @@
 use axum::{
     body::Body,
     http::{Request, StatusCode, Method},
-    response::IntoResponse,
 };
 use tower::ServiceExt;
 use serde_json::json;
 
-mod common;
-use common::*;
+use super::common::*;
 
 #[tokio::test]
 async fn test_basic_endpoints() {
@@ -15,6 +13,7 @@ async fn test_basic_endpoints() {
     
     // Test root endpoint
     let response = app
+        .clone()
         .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
         .await
         .unwrap();
@@ -22,6 +21,7 @@ async fn test_basic_endpoints() {
     
     // Test agents endpoint
     let response = app
+        .clone()
         .oneshot(Request::builder().uri("/api/agents").body(Body::empty()).unwrap())
         .await
         .unwrap();
@@ -29,6 +29,7 @@ async fn test_basic_endpoints() {
     
     // Test tasks endpoint
     let response = app
+        .clone()
         .oneshot(Request::builder().uri("/api/tasks").body(Body::empty()).unwrap())
         .await
         .unwrap();
@@ -37,15 +38,18 @@ async fn test_basic_endpoints() {
 
 #[tokio::test]
 async fn test_agent_crud() {
-    let app = create_test_app().await;
+    let TestApp { app, pool, .. } = TestApp::new().await;
+    let (_, token) = create_test_admin_user(&pool).await;
     
     // Create agent
     let agent_data = create_test_agent_data();
     let response = app
+        .clone()
         .oneshot(
             Request::builder()
                 .method(Method::POST)
                 .uri("/api/agents")
+                .header("authorization", format!("Bearer {}", token))
                 .header("content-type", "application/json")
                 .body(Body::from(agent_data.to_string()))
                 .unwrap()
@@ -56,6 +60,7 @@ async fn test_agent_crud() {
     
     // Get agent
     let response = app
+        .clone()
         .oneshot(Request::builder().uri("/api/agents").body(Body::empty()).unwrap())
         .await
         .unwrap();
@@ -68,17 +73,21 @@ async fn test_openclaw_integration() {
     
     // Test OpenClaw status
     let response = app
+        .clone()
         .oneshot(Request::builder().uri("/api/openclaw/status").body(Body::empty()).unwrap())
         .await
         .unwrap();
     assert_eq!(response.status(), StatusCode::OK);
+    let status: serde_json::Value = response_json(response).await;
     
-    // Test OpenClaw agents
+    // Test OpenClaw agents; without an openclaw.json on this host the list is unavailable
     let response = app
+        .clone()
         .oneshot(Request::builder().uri("/api/openclaw/agents").body(Body::empty()).unwrap())
         .await
         .unwrap();
-    assert_eq!(response.status(), StatusCode::OK);
+    let expected = if status["available"] == true { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    assert_eq!(response.status(), expected);
 }
 
 #[tokio::test]
@@ -96,6 +105,7 @@ async fn test_security_endpoints() {
     });
     
     let response = app
+        .clone()
         .oneshot(
             Request::builder()
                 .method(Method::POST)
@@ -111,19 +121,475 @@ async fn test_security_endpoints() {
 
 #[tokio::test]
 async fn test_optimization_endpoints() {
-    let app = create_test_app().await;
+    let test_app = TestApp::new().await;
+    let (_, token) = create_test_admin_user(&test_app.pool).await;
     
     // Test optimization status
-    let response = app
+    let response = test_app.app
+        .clone()
         .oneshot(Request::builder().uri("/api/optimization/status").body(Body::empty()).unwrap())
         .await
         .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    
+    let response = test_app.app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/optimization/status")
+                .header("authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap()
+        )
+        .await
+        .unwrap();
     assert_eq!(response.status(), StatusCode::OK);
     
     // Test pool status
+    let response = test_app.app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/optimization/pool/status")
+                .header("authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap()
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_task_creation_records_its_author() {
+    let TestApp { app, pool, .. } = TestApp::new().await;
+    let (user, token) = create_test_admin_user(&pool).await;
+    let post = |auth: Option<String>| {
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/tasks")
+            .header("content-type", "application/json");
+        if let Some(auth) = auth {
+            request = request.header("authorization", auth);
+        }
+        request.body(Body::from(json!({ "title": "Write the report" }).to_string())).unwrap()
+    };
+    
+    // Someone has to own the task
+    let response = app.clone().oneshot(post(None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    
+    let response = app.clone().oneshot(post(Some(format!("Bearer {}", token)))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let task: serde_json::Value = response_json(response).await;
+    assert_eq!(task["created_by"], json!(user.id));
+}
+
+#[tokio::test]
+async fn test_task_classification_enforcement() {
+    let app = create_test_app().await;
+    
+    // Anonymous callers cannot create data above their clearance
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/tasks")
+                .header("content-type", "application/json")
+                .body(Body::from(json!({
+                    "title": "Incident review",
+                    "classification": "SECRET"
+                }).to_string()))
+                .unwrap()
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    
+    // Unknown classifications are rejected
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/tasks")
+                .header("content-type", "application/json")
+                .body(Body::from(json!({
+                    "title": "Incident review",
+                    "classification": "TOP_SECRET"
+                }).to_string()))
+                .unwrap()
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_agent_metrics_period_and_review_validation() {
+    let TestApp { app, pool, .. } = TestApp::new().await;
+    let (_, token) = create_test_admin_user(&pool).await;
+    
+    // Periods are 7d/30d-style or custom ranges with both ends
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/agents/test-agent/analytics?period=fortnight")
+                .header("authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap()
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/agents/test-agent/analytics?period=custom&from=2026-01-01")
+                .header("authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap()
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    
+    // Reviews only apply to tasks that exist
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/tasks/missing-task/review")
+                .header("authorization", format!("Bearer {}", token))
+                .header("content-type", "application/json")
+                .body(Body::from(json!({ "outcome": "approved" }).to_string()))
+                .unwrap()
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_usage_cost_report_validation() {
+    let app = create_test_app().await;
+    
+    // Reports group by agent, task or tag only
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/usage/costs?group_by=model")
+                .body(Body::empty())
+                .unwrap()
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    
+    // Ingestion is restricted to administrators
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/usage/ingest")
+                .body(Body::empty())
+                .unwrap()
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_review_requires_task_write() {
+    let TestApp { app, pool, .. } = TestApp::new().await;
+    sqlx::query("INSERT INTO tasks (id, title, status, created_by) VALUES ('review-task', 'Review me', 'REVIEW', 'test')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let review = json!({ "outcome": "approved", "reviewer_id": "someone-else" });
+    
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/tasks/review-task/review")
+                .header("content-type", "application/json")
+                .body(Body::from(review.to_string()))
+                .unwrap()
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    
+    // The recorded reviewer is the caller, whatever the payload claims
+    let (admin, token) = create_test_admin_user(&pool).await;
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/tasks/review-task/review")
+                .header("content-type", "application/json")
+                .header("authorization", format!("Bearer {}", token))
+                .body(Body::from(review.to_string()))
+                .unwrap()
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    let (status, reviewer_id): (String, Option<String>) = sqlx::query_as(
+        "SELECT t.status, r.reviewer_id FROM tasks t JOIN task_reviews r ON r.task_id = t.id WHERE t.id = 'review-task'"
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(status, "DONE");
+    assert_eq!(reviewer_id.as_deref(), Some(admin.id.as_str()));
+}
+
+#[tokio::test]
+async fn test_agent_analytics_require_agent_read() {
+    let app = create_test_app().await;
+    
+    let response = app
+        .clone()
+        .oneshot(Request::builder().uri("/api/agents/some-agent/analytics").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    
     let response = app
-        .oneshot(Request::builder().uri("/api/optimization/pool/status").body(Body::empty()).unwrap())
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/agents/compare")
+                .header("content-type", "application/json")
+                .body(Body::from(json!({ "agent_ids": ["a", "b"], "comparison_type": "Performance", "metrics": [], "period": "7d" }).to_string()))
+                .unwrap()
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_apply_keeps_primary_model_while_failed_over() {
+    let pool = create_test_pool().await;
+    let agent_id = insert_test_agent(&pool, "Failover Agent").await;
+    sqlx::query("UPDATE agents SET current_model = 'gpt-4', fallback_model = 'gpt-4' WHERE id = ?")
+        .bind(&agent_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // openclaw.json as the failover left it: the fallback named as primary
+    let config: crate::models::OpenClawAgentConfig = serde_json::from_value(json!({
+        "id": agent_id,
+        "model": { "primary": "gpt-4", "fallbacks": ["claude-3-sonnet"] }
+    }))
+    .unwrap();
+    crate::openclaw_integration_helpers::apply_agent_config_to_db(&mut pool.acquire().await.unwrap(), &agent_id, &config)
+        .await
+        .unwrap();
+
+    let (primary, fallback): (Option<String>, Option<String>) =
+        sqlx::query_as("SELECT primary_model, fallback_model FROM agents WHERE id = ?")
+            .bind(&agent_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(primary.as_deref(), Some("claude-3-sonnet"));
+    assert_eq!(fallback.as_deref(), Some("gpt-4"));
+
+    // Once back on the primary, a new primary is applied as usual
+    sqlx::query("UPDATE agents SET current_model = primary_model WHERE id = ?")
+        .bind(&agent_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    crate::openclaw_integration_helpers::apply_agent_config_to_db(&mut pool.acquire().await.unwrap(), &agent_id, &config)
+        .await
+        .unwrap();
+    let primary: Option<String> = sqlx::query_scalar("SELECT primary_model FROM agents WHERE id = ?")
+        .bind(&agent_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(primary.as_deref(), Some("gpt-4"));
+}
+
+#[tokio::test]
+async fn test_team_context_versions_survive_delete_and_member_changes_are_audited() {
+    let test_app = TestApp::new().await;
+    let (_, token) = create_test_admin_user(&test_app.pool).await;
+    let agent_id = insert_test_agent(&test_app.pool, "Team Member").await;
+
+    let response = test_app.app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/collaboration/teams")
+                .header("authorization", format!("Bearer {}", token))
+                .header("content-type", "application/json")
+                .body(Body::from(json!({ "name": "Versioned Team" }).to_string()))
+                .unwrap()
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let team: serde_json::Value = response_json(response).await;
+    let team_id = team["id"].as_str().unwrap().to_string();
+
+    let response = test_app.app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri(format!("/api/collaboration/teams/{}/members", team_id))
+                .header("authorization", format!("Bearer {}", token))
+                .header("content-type", "application/json")
+                .body(Body::from(json!({ "agent_id": agent_id, "role_type": "executor" }).to_string()))
+                .unwrap()
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let audited: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM audit_log WHERE entity_type = 'system' AND entity_id = ?")
+        .bind(format!("team:{}", team_id))
+        .fetch_one(&test_app.pool)
+        .await
+        .unwrap();
+    assert_eq!(audited, 2);
+
+    let collaboration = &test_app.state.collaboration;
+    let value = json!({ "step": 1 });
+    collaboration.write_context(&test_app.pool, &team_id, "plan", Some(&value), None, "tester").await.unwrap();
+    collaboration.write_context(&test_app.pool, &team_id, "plan", Some(&value), Some(1), "tester").await.unwrap();
+    let deleted = collaboration.write_context(&test_app.pool, &team_id, "plan", None, Some(2), "tester").await.unwrap();
+    assert_eq!(deleted.version, 3);
+
+    // A deleted key starts again from version 0 for conflict checks, but is numbered after its history
+    let rewritten = collaboration.write_context(&test_app.pool, &team_id, "plan", Some(&value), Some(0), "tester").await.unwrap();
+    assert_eq!(rewritten.version, 4);
+    let versions: Vec<u32> = collaboration
+        .context_history(&test_app.pool, &team_id, "plan")
+        .await
+        .unwrap()
+        .iter()
+        .map(|entry| entry.version)
+        .collect();
+    assert_eq!(versions, vec![4, 3, 2, 1]);
+}
+
+#[tokio::test]
+async fn test_fanout_delegation_stays_within_member_capacity() {
+    let test_app = TestApp::new().await;
+    let (_, token) = create_test_admin_user(&test_app.pool).await;
+    let agent_id = insert_test_agent(&test_app.pool, "Fanout Member").await;
+    sqlx::query("UPDATE agents SET max_concurrent = 2 WHERE id = ?")
+        .bind(&agent_id)
+        .execute(&test_app.pool)
+        .await
+        .unwrap();
+    let task_id = format!("task-{}", uuid::Uuid::new_v4());
+    sqlx::query("INSERT INTO tasks (id, title, status, created_by) VALUES (?, 'Split me', 'INBOX', ?)")
+        .bind(&task_id)
+        .bind(&agent_id)
+        .execute(&test_app.pool)
+        .await
+        .unwrap();
+
+    let post = |uri: String, body: serde_json::Value| {
+        Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header("authorization", format!("Bearer {}", token))
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    let response = test_app.app
+        .clone()
+        .oneshot(post("/api/collaboration/teams".to_string(), json!({ "name": "Fanout Team", "member_ids": [agent_id] })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let team: serde_json::Value = response_json(response).await;
+    let delegate_uri = format!("/api/collaboration/teams/{}/delegate", team["id"].as_str().unwrap());
+
+    let response = test_app.app
+        .clone()
+        .oneshot(post(delegate_uri.clone(), json!({ "task_id": task_id, "mode": "fanout", "subtasks": ["a", "b", "c"] })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = test_app.app
+        .clone()
+        .oneshot(post(delegate_uri.clone(), json!({ "task_id": task_id, "mode": "fanout", "subtasks": ["a", "b"] })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let delegation: serde_json::Value = response_json(response).await;
+    let assignments = delegation["assignments"].as_array().unwrap();
+    assert_eq!(assignments.len(), 2);
+    assert!(assignments.iter().all(|a| a["agent_id"] == json!(agent_id)));
+
+    // Both slots are now held by the sub-tasks
+    let response = test_app.app
+        .clone()
+        .oneshot(post(delegate_uri, json!({ "task_id": task_id, "mode": "fanout" })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_advanced_features_status_reports_learning_activity() {
+    let test_app = TestApp::new().await;
+    let (_, token) = create_test_admin_user(&test_app.pool).await;
+    let agent_id = insert_test_agent(&test_app.pool, "Learning Agent").await;
+    sqlx::query("INSERT INTO agent_feedback (id, agent_id, feedback_type, rating, source) VALUES (?, ?, 'user_rating', 4, 'user')")
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(&agent_id)
+        .execute(&test_app.pool)
+        .await
+        .unwrap();
+
+    let response = test_app.app
+        .clone()
+        .oneshot(Request::builder().uri("/api/collaboration/status").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = test_app.app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/collaboration/status")
+                .header("authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap()
+        )
         .await
         .unwrap();
     assert_eq!(response.status(), StatusCode::OK);
+    let status: serde_json::Value = response_json(response).await;
+    assert_eq!(status["learning_metrics"]["total_feedback_processed"], json!(1));
+    assert_eq!(status["learning_metrics"]["adaptations_performed"], json!(0));
 }
@@ -1,30 +1,31 @@
 use axum::{
     body::Body,
     http::{Request, StatusCode, Method},
-    response::IntoResponse,
 };
 use tower::ServiceExt;
 use serde_json::json;
-use std::time::Instant;
-use tokio::time::{sleep, Duration};
+use std::time::{Duration, Instant};
 
-mod common;
-use common::*;
+use super::common::*;
 
 #[tokio::test]
 async fn test_concurrent_requests() {
-    let app = create_test_app().await;
+    let test_app = TestApp::unthrottled().await;
+    let (_, token) = create_test_admin_user(&test_app.pool).await;
+    let app = test_app.app;
     let start = Instant::now();
     
     // Test concurrent agent creation
-    let handles: Vec<_> = (0..100).map(|i| {
+    let handles: Vec<_> = (0..100).map(|_| {
         let app = app.clone();
+        let token = token.clone();
         tokio::spawn(async move {
             let agent_data = create_test_agent_data();
-            app.oneshot(
+            app.clone().oneshot(
                 Request::builder()
                     .method(Method::POST)
                     .uri("/api/agents")
+                    .header("authorization", format!("Bearer {}", token))
                     .header("content-type", "application/json")
                     .body(Body::from(agent_data.to_string()))
                     .unwrap()
@@ -33,7 +34,7 @@ async fn test_concurrent_requests() {
     }).collect();
     
     for handle in handles {
-        let response = handle.await.unwrap();
+        let response = handle.await.unwrap().unwrap();
         assert_eq!(response.status(), StatusCode::CREATED);
     }
     
@@ -44,16 +45,20 @@ async fn test_concurrent_requests() {
 
 #[tokio::test]
 async fn test_memory_usage() {
-    let app = create_test_app().await;
+    let test_app = TestApp::unthrottled().await;
+    let (_, token) = create_test_admin_user(&test_app.pool).await;
+    let app = test_app.app;
     
     // Create large number of agents
-    for i in 0..1000 {
+    for _ in 0..1000 {
         let agent_data = create_test_agent_data();
         let response = app
+            .clone()
             .oneshot(
                 Request::builder()
                     .method(Method::POST)
                     .uri("/api/agents")
+                    .header("authorization", format!("Bearer {}", token))
                     .header("content-type", "application/json")
                     .body(Body::from(agent_data.to_string()))
                     .unwrap()
@@ -66,6 +71,7 @@ async fn test_memory_usage() {
     
     // Test memory usage by fetching all agents
     let response = app
+        .clone()
         .oneshot(Request::builder().uri("/api/agents").body(Body::empty()).unwrap())
         .await
         .unwrap();
@@ -73,13 +79,15 @@ async fn test_memory_usage() {
     assert_eq!(response.status(), StatusCode::OK);
     
     // Verify we can handle the load
-    let agents: Vec<Agent> = response.json().await;
+    let agents: Vec<serde_json::Value> = response_json(response).await;
     assert_eq!(agents.len(), 1000);
 }
 
 #[tokio::test]
 async fn test_response_time() {
-    let app = create_test_app().await;
+    let test_app = TestApp::new().await;
+    let (_, token) = create_test_admin_user(&test_app.pool).await;
+    let app = test_app.app;
     
     // Test response time for various endpoints
     let endpoints = vec![
@@ -93,7 +101,14 @@ async fn test_response_time() {
     for endpoint in endpoints {
         let start = Instant::now();
         let response = app
-            .oneshot(Request::builder().uri(endpoint).body(Body::empty()).unwrap())
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(endpoint)
+                    .header("authorization", format!("Bearer {}", token))
+                    .body(Body::empty())
+                    .unwrap()
+            )
             .await
             .unwrap();
         
@@ -105,44 +120,43 @@ async fn test_response_time() {
 
 #[tokio::test]
 async fn test_caching_performance() {
-    let app = create_test_app().await;
+    let cache = TestApp::new().await.state.cache;
+    let key = crate::openclaw_optimization::CacheKey::AgentConfig("cached-agent".to_string());
     
-    // First request (cache miss)
-    let start = Instant::now();
-    let response = app
-        .oneshot(Request::builder().uri("/api/agents").body(Body::empty()).unwrap())
-        .await
-        .unwrap();
-    let first_request_time = start.elapsed();
+    // First lookup misses both tiers
+    assert!(cache.get(&key).await.is_none());
+    cache.put(key.clone(), json!({ "id": "cached-agent" }), Duration::from_secs(60)).await;
     
-    // Second request (cache hit)
+    // Repeated lookups are served from L1
     let start = Instant::now();
-    let response = app
-        .oneshot(Request::builder().uri("/api/agents").body(Body::empty()).unwrap())
-        .await
-        .unwrap();
-    let second_request_time = start.elapsed();
-    
-    assert_eq!(response.status(), StatusCode::OK);
+    for _ in 0..1000 {
+        assert_eq!(cache.get(&key).await, Some(json!({ "id": "cached-agent" })));
+    }
+    let hit_time = start.elapsed();
     
-    // Cache hit should be faster
-    assert!(second_request_time < first_request_time, "Caching not working properly");
-    println!("First request: {:?}, Second request: {:?}", first_request_time, second_request_time);
+    let metrics = cache.get_metrics().await;
+    assert_eq!((metrics.l1_hits, metrics.l1_misses, metrics.l2_misses), (1000, 1, 1));
+    println!("1000 cache hits: {:?}", hit_time);
+    assert!(hit_time.as_millis() < 500, "Cache lookups too slow");
 }
 
 #[tokio::test]
 async fn test_database_performance() {
-    let app = create_test_app().await;
+    let test_app = TestApp::unthrottled().await;
+    let (_, token) = create_test_admin_user(&test_app.pool).await;
+    let app = test_app.app;
     
     // Test database write performance
     let start = Instant::now();
-    for i in 0..100 {
+    for _ in 0..100 {
         let agent_data = create_test_agent_data();
         let response = app
+            .clone()
             .oneshot(
                 Request::builder()
                     .method(Method::POST)
                     .uri("/api/agents")
+                    .header("authorization", format!("Bearer {}", token))
                     .header("content-type", "application/json")
                     .body(Body::from(agent_data.to_string()))
                     .unwrap()
@@ -157,6 +171,7 @@ async fn test_database_performance() {
     // Test database read performance
     let start = Instant::now();
     let response = app
+        .clone()
         .oneshot(Request::builder().uri("/api/agents").body(Body::empty()).unwrap())
         .await
         .unwrap();
@@ -171,22 +186,26 @@ async fn test_database_performance() {
 
 #[tokio::test]
 async fn test_stress_test() {
-    let app = create_test_app().await;
+    let test_app = TestApp::unthrottled().await;
+    let (_, token) = create_test_admin_user(&test_app.pool).await;
+    let app = test_app.app;
     let start = Instant::now();
     
     // High concurrency stress test
     let handles: Vec<_> = (0..500).map(|i| {
         let app = app.clone();
+        let token = token.clone();
         tokio::spawn(async move {
             // Mix of different operations
             match i % 4 {
                 0 => {
                     // Create agent
                     let agent_data = create_test_agent_data();
-                    app.oneshot(
+                    app.clone().oneshot(
                         Request::builder()
                             .method(Method::POST)
                             .uri("/api/agents")
+                            .header("authorization", format!("Bearer {}", token))
                             .header("content-type", "application/json")
                             .body(Body::from(agent_data.to_string()))
                             .unwrap()
@@ -194,15 +213,16 @@ async fn test_stress_test() {
                 }
                 1 => {
                     // Get agents
-                    app.oneshot(Request::builder().uri("/api/agents").body(Body::empty()).unwrap()).await
+                    app.clone().oneshot(Request::builder().uri("/api/agents").body(Body::empty()).unwrap()).await
                 }
                 2 => {
                     // Create task
                     let task_data = create_test_task_data();
-                    app.oneshot(
+                    app.clone().oneshot(
                         Request::builder()
                             .method(Method::POST)
                             .uri("/api/tasks")
+                            .header("authorization", format!("Bearer {}", token))
                             .header("content-type", "application/json")
                             .body(Body::from(task_data.to_string()))
                             .unwrap()
@@ -210,7 +230,7 @@ async fn test_stress_test() {
                 }
                 3 => {
                     // Get tasks
-                    app.oneshot(Request::builder().uri("/api/tasks").body(Body::empty()).unwrap()).await
+                    app.clone().oneshot(Request::builder().uri("/api/tasks").body(Body::empty()).unwrap()).await
                 }
                 _ => unreachable!()
             }
@@ -219,7 +239,7 @@ async fn test_stress_test() {
     
     let mut success_count = 0;
     for handle in handles {
-        let response = handle.await.unwrap();
+        let response = handle.await.unwrap().unwrap();
         if response.status().is_success() {
             success_count += 1;
         }
@@ -16,6 +16,7 @@ export default function StatusPage() {
   const [gatewayStatus, setGatewayStatus] = useState(null)
   const [stuckTaskStatus, setStuckTaskStatus] = useState(null)
   const [stuckTasks, setStuckTasks] = useState([])
+  const [agentHealth, setAgentHealth] = useState({})
   const [loading, setLoading] = useState(true)
   const [lastRefresh, setLastRefresh] = useState(new Date())
 
@@ -35,6 +36,14 @@ export default function StatusPage() {
       const stuckTaskCheck = await api.get('/api/monitoring/stuck-tasks/check')
       setStuckTasks(stuckTaskCheck.stuck_tasks || [])
 
+      // Agent health scores need monitoring:read; the page works without them
+      try {
+        const healthData = await api.get('/api/monitoring/agents/health')
+        setAgentHealth(Object.fromEntries((healthData || []).map(h => [h.agent_id, h])))
+      } catch (error) {
+        console.error('Failed to fetch agent health:', error)
+      }
+
       setLastRefresh(new Date())
     } catch (error) {
       console.error('Failed to fetch status data:', error)
@@ -88,6 +97,7 @@ export default function StatusPage() {
   const standbyAgents = agents.filter(a => a.status === 'STANDBY').length
   const offlineAgents = agents.filter(a => a.status === 'OFFLINE').length
   const totalAgents = agents.length
+  const degradedAgents = Object.values(agentHealth).filter(h => h.degraded).length
 
   const activeTasks = tasks.filter(t => t.status !== 'DONE').length
   const inProgressTasks = tasks.filter(t => t.status === 'IN_PROGRESS').length
@@ -98,9 +108,10 @@ export default function StatusPage() {
     const gatewayHealthy = gatewayStatus?.health_status === 'healthy'
     const hasStuckTasks = stuckTasks.length > 0
     const hasOfflineAgents = offlineAgents > 0
+    const hasDegradedAgents = degradedAgents > 0
 
     if (!gatewayHealthy) return 'critical'
-    if (hasStuckTasks || hasOfflineAgents) return 'warning'
+    if (hasStuckTasks || hasOfflineAgents || hasDegradedAgents) return 'warning'
     return 'healthy'
   }
 
@@ -196,6 +207,10 @@ export default function StatusPage() {
             <span className="health-stat-value">{stuckTasks.length}</span>
             <span className="health-stat-label">{t('status.stuck_tasks')}</span>
           </div>
+          <div className="health-stat">
+            <span className="health-stat-value">{degradedAgents}</span>
+            <span className="health-stat-label">{t('status.degraded_agents')}</span>
+          </div>
         </div>
       </div>
 
@@ -297,6 +312,15 @@ export default function StatusPage() {
                       <div className={`agent-status-text agent-status-text--${agent.status.toLowerCase()}`}>
                         {agent.status}
                       </div>
+                      {agentHealth[agent.id] && (
+                        <div
+                          className={`agent-health-text ${agentHealth[agent.id].degraded ? 'agent-health-text--degraded' : ''}`}
+                          title={agentHealth[agent.id].issues.map(issue => issue.title).join('\n')}
+                        >
+                          {t('status.health_score', { score: Math.round(agentHealth[agent.id].overall_health) })}
+                          {agentHealth[agent.id].degraded && ` · ${t('status.degraded')}`}
+                        </div>
+                      )}
                     </div>
                   </div>
                 ))}